@@ -45,7 +45,8 @@ typedef struct
         GBFFIELDVALUE_TAG_LONG = 3,
         GBFFIELDVALUE_TAG_STRING = 4,
         GBFFIELDVALUE_TAG_BYTES = 5,
-        GBFFIELDVALUE_TAG_BOOLEAN = 6
+        GBFFIELDVALUE_TAG_BOOLEAN = 6,
+        GBFFIELDVALUE_TAG_NULL = 7 // no value
     } tag;
     union
     {
@@ -78,7 +79,8 @@ PhObj(GbfDatabase *) database_new(MemView *mv, uint64_t *at, PhErr(MemViewError)
 PhObj(GbfDbParms *) database_get_db_parms(GbfDatabase *self, PhErr(MemViewError) * err);
 PhMaybe(GbfTableDef *) database_get_table_def_by_name(GbfDatabase *self, char *table_name, PhErr(MemViewError) * err);
 PhObj(PhVec(GbfTableDef *)) database_get_table_defs(GbfDatabase *self, PhErr(MemViewError) * err);
-// PhObjMaybe(GbfTableView *) database_get_table_view_by_name(GbfDatabase *self, GbfTableSchema *schema, char *table_name, PhErr(MemViewError) * err);
+// the returned view borrows the database and must be freed before it
+PhObj(GbfTableView *) database_get_table_view_by_name(GbfDatabase *self, char *table_name, PhErr(MemViewError) * err);
 
 // #-class GbfTableDef
 GbfTableSchema *database_table_def_get_schema(GbfTableDef *self, PhErr(MemViewError) * err);
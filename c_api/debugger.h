@@ -16,6 +16,13 @@ typedef enum
     DEBUGGER_ERROR_INVALID_THREAD = 8,
     DEBUGGER_ERROR_INVALID_BREAKPOINT = 9,
     DEBUGGER_ERROR_NO_THREADS = 10,
+    DEBUGGER_ERROR_BUSY = 11,
+    DEBUGGER_ERROR_MEMORY_NOT_WRITABLE = 12,
+    DEBUGGER_ERROR_NO_FREE_WATCHPOINTS = 13,
+    DEBUGGER_ERROR_NO_FRAME = 14,
+    DEBUGGER_ERROR_INVALID_SPEC = 15,
+    DEBUGGER_ERROR_ATTACH_FAILED = 16,
+    DEBUGGER_ERROR_UNSUPPORTED_INSTRUCTION = 17,
 } DebuggerError;
 
 typedef enum
@@ -30,13 +37,20 @@ typedef enum
     DEBUGGER_EVENT_KIND_THREAD_SPAWNED = 7,
     DEBUGGER_EVENT_KIND_THREAD_KILLED = 8,
     DEBUGGER_EVENT_KIND_USER_EVENT = 9,
+    DEBUGGER_EVENT_KIND_INTERRUPTED = 10,
+    DEBUGGER_EVENT_KIND_EXEC = 11,
+    DEBUGGER_EVENT_KIND_WATCHPOINT_HIT = 12,
+    DEBUGGER_EVENT_KIND_PROCESS_EXITED = 13,
 } DebuggerEventKind;
 
 typedef struct
 {
     DebuggerEventKind kind;
-    uint32_t code;
+    uint32_t code; // PROCESS_EXITED: exit status, or 0x100 | signal if killed by one
     uint32_t pid;
+    uint32_t bp_idx; // breakpoint or watchpoint slot, UINT32_MAX if neither
+    uint64_t bp_addr;
+    char *image_path; // only set for exec events
 } DebuggerEvent;
 
 // /////
@@ -61,21 +75,69 @@ typedef struct
     uint64_t len;
     char *text;
     PhVec(DisasmDispInstructionRun *) runs;
+    PhVec(uint8_t) raw_bytes; // empty unless requested
 } DisasmDispInstruction;
 
 // /////
 
+typedef enum
+{
+    REGISTER_KIND_GENERAL_PURPOSE = 0,
+    REGISTER_KIND_FLOATING_POINT = 1,
+    REGISTER_KIND_CONTROL = 2,
+    REGISTER_KIND_FLAG = 3,
+} RegisterKind;
+
+typedef enum
+{
+    REGISTER_ROLE_NONE = 0,
+    REGISTER_ROLE_FLAG = 1,
+    REGISTER_ROLE_PROGRAM_COUNTER = 2,
+    REGISTER_ROLE_STACK_POINTER = 3,
+    REGISTER_ROLE_BASE_POINTER = 4,
+} RegisterRole;
+
+typedef struct
+{
+    char *name;
+    RegisterKind kind;
+    RegisterRole role;
+    uint32_t addr;    // offset in the sleigh register space
+    int32_t mizl_idx; // pass to debugger_read_register_by_idx_buf
+    int64_t dbg_idx;  // remote debugger's index, -1 if not remote
+    int32_t bit_len;
+} RegisterInfo;
+
+// /////
+
 typedef struct PhOpaque(Debugger) Debugger;
 
 Debugger *debugger_linux_new();
 
 int debugger_get_big_endian(Debugger *self);
+uint32_t debugger_get_pointer_size(Debugger *self);
 int debugger_run(Debugger *self, char *path, char **args, PhErr(DebuggerError) * err);
 DebuggerEvent *debugger_wait_next_event(Debugger *self, bool no_block, PhErr(DebuggerError) * err);
+void debugger_interrupt(Debugger *self, PhErr(DebuggerError) * err);
 DisasmDispInstruction *debugger_disassemble_one(Debugger *self, uint64_t addr, PhErr(DebuggerError) * err);
+PhObj(PhVec(DisasmDispInstruction *)) debugger_disassemble_range(Debugger *self, uint64_t start, int32_t count, PhErr(DebuggerError) * err);
+PhObj(PhVec(RegisterInfo *)) debugger_get_register_infos(Debugger *self, int32_t thread_idx);
+void debugger_read_register_by_idx_buf(Debugger *self, int32_t thread_idx, int32_t reg_idx, char *out_data, size_t out_data_len, PhErr(DebuggerError) * err);
 void debugger_read_register_by_name_buf(Debugger *self, int32_t thread_idx, char *name, char *out_data, size_t out_data_len, PhErr(DebuggerError) * err);
+void debugger_write_register_by_name_buf(Debugger *self, int32_t thread_idx, char *name, char *data, size_t data_len, PhErr(DebuggerError) * err);
+// both return the address just past the last byte
+uint64_t debugger_read_bytes(Debugger *self, int32_t thread_idx, uint64_t addr, char *out_data, size_t out_data_len, PhErr(DebuggerError) * err);
+uint64_t debugger_write_bytes(Debugger *self, int32_t thread_idx, uint64_t addr, char *data, size_t data_len, PhErr(DebuggerError) * err);
+// first match in [start, end), or DEBUGGER_SCAN_NOT_FOUND. mask can be null,
+// otherwise it's needle_len bytes and only bits set in it are compared.
+#define DEBUGGER_SCAN_NOT_FOUND UINT64_MAX
+uint64_t debugger_scan_memory(Debugger *self, int32_t thread_idx, uint64_t start, uint64_t end, char *needle, char *mask, size_t needle_len, PhErr(DebuggerError) * err);
+void debugger_invalidate_register_cache(Debugger *self, int32_t thread_idx, PhErr(DebuggerError) * err);
+void debugger_flush_register_cache(Debugger *self, int32_t thread_idx, PhErr(DebuggerError) * err);
 uint32_t debugger_add_breakpoint(Debugger *self, int32_t thread_idx, uint64_t addr, PhErr(DebuggerError) * err);
 void debugger_step(Debugger *self, int32_t thread_idx, PhErr(DebuggerError) * err);
-void debugger_cont_all(Debugger *self, PhErr(DebuggerError) * err);
+void debugger_step_out(Debugger *self, int32_t thread_idx, PhErr(DebuggerError) * err);
+void debugger_cont_all(Debugger *self, PhErr(DebuggerError) * err); // resumes every stopped thread
+void debugger_cont_one(Debugger *self, int32_t thread_idx, PhErr(DebuggerError) * err); // the others stay stopped
 
 #endif // MIZL_DEBUGGER_H
\ No newline at end of file
@@ -0,0 +1,18 @@
+#ifndef MIZL_DISASM_H
+#define MIZL_DISASM_H
+
+#include "common.h"
+#include "debugger.h"
+#include "memview.h"
+
+typedef struct PhOpaque(Disasm) Disasm;
+
+// ///////
+
+// #-class Disasm
+PhObj(Disasm *) disasm_new(unsigned char *sla_data, uint64_t sla_len, unsigned char *pspec_data, uint64_t pspec_len, PhErr(MemViewError) * err); // #ctor
+void disasm_drop(Disasm *self, PhErr(MemViewError) * err);
+PhObj(DisasmDispInstruction *) disasm_one(Disasm *self, MemView *mv, uint64_t addr, PhErr(MemViewError) * err);
+PhObj(PhVec(DisasmDispInstruction *)) disasm_bytes(Disasm *self, unsigned char *data, uint64_t len, uint64_t base_addr, PhErr(MemViewError) * err);
+
+#endif // MIZL_DISASM_H
\ No newline at end of file
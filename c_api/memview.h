@@ -10,14 +10,29 @@ typedef enum
     MEM_VIEW_ERROR_READ_ACCESS_DENIED = 1,
     MEM_VIEW_ERROR_WRITE_ACCESS_DENIED = 2,
     MEM_VIEW_ERROR_NOT_LOADED = 3,
-    MEM_VIEW_ERROR_GENERIC = 4
+    MEM_VIEW_ERROR_INVALID_PARAMETER = 4,
+    MEM_VIEW_ERROR_GENERIC = 5,
+    MEM_VIEW_ERROR_READ_ONLY = 6,
+    MEM_VIEW_ERROR_UNMAPPED = 7
 } MemViewError;
 
 typedef struct PhOpaque(MemView) MemView;
 
+// read_bytes and write_bytes return -1 on success or a MemViewError on failure.
+// write_bytes and drop can be null.
+typedef struct
+{
+    void *user_data;
+    int32_t (*read_bytes)(void *user_data, uint64_t addr, unsigned char *out_data, int32_t count);
+    int32_t (*write_bytes)(void *user_data, uint64_t addr, const unsigned char *data, int32_t count);
+    uint64_t (*max_address)(void *user_data);
+    void (*drop)(void *user_data);
+} MemViewCallbacks;
+
 // ///////
 
 PhObj(MemView *) static_mem_view_from_file(char *path, PhErr(MemViewError) * err);                             // #ctor
 PhObj(MemView *) static_mem_view_from_data(unsigned char *data, uint64_t size, PhErr(MemViewError) * err_str); // #ctor
+PhObj(MemView *) callback_mem_view_new(MemViewCallbacks *callbacks, PhErr(MemViewError) * err);                // #ctor
 
 #endif // MIZL_MEMVIEW_H
\ No newline at end of file
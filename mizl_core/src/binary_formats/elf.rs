@@ -1,2 +1,3 @@
 pub mod consts;
 pub mod file;
+pub mod symbols;
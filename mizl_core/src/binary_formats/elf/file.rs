@@ -1,6 +1,12 @@
 use crate::{
+    binary_formats::elf::consts::{
+        ELF_MAGIC, ELFCLASS32, ELFCLASS64, ELFDATA2LSB, ELFDATA2MSB, EM_386, EM_X86_64, ET_DYN, ET_EXEC, PT_LOAD,
+    },
     consts::arch::{Bitness, Endianness},
-    memory::memview::{MemView, MemViewError},
+    memory::{
+        composite_memview::CompositeMemView,
+        memview::{MemView, MemViewError, StaticMemView, ZeroMemView},
+    },
 };
 
 pub struct ElfHeaderIdent {
@@ -30,12 +36,36 @@ pub struct ElfHeader {
     pub shstrndx: u16,
 }
 
+pub struct ElfSectionHeader {
+    pub name: u32,
+    pub section_type: u32,
+    pub flags: u64,  // u32 on 32-bit
+    pub addr: u64,   // u32 on 32-bit
+    pub offset: u64, // u32 on 32-bit
+    pub size: u64,   // u32 on 32-bit
+    pub link: u32,
+    pub info: u32,
+    pub addralign: u64, // u32 on 32-bit
+    pub entsize: u64,   // u32 on 32-bit
+}
+
+pub struct ElfProgramHeader {
+    pub segment_type: u32,
+    pub flags: u32,
+    pub offset: u64, // u32 on 32-bit
+    pub vaddr: u64,  // u32 on 32-bit
+    pub paddr: u64,  // u32 on 32-bit
+    pub filesz: u64, // u32 on 32-bit
+    pub memsz: u64,  // u32 on 32-bit
+    pub align: u64,  // u32 on 32-bit
+}
+
 pub enum ElfReadError {
     IOError(MemViewError),
 }
 
 impl ElfHeaderIdent {
-    pub fn new(mv: &Box<dyn MemView>, addr: &mut u64) -> Result<ElfHeaderIdent, MemViewError> {
+    pub fn new(mv: &dyn MemView, addr: &mut u64) -> Result<ElfHeaderIdent, MemViewError> {
         let mut magic = [0u8; 4];
         mv.read_bytes(addr, &mut magic, 4)?;
         let class = mv.read_u8(addr)?;
@@ -58,7 +88,7 @@ impl ElfHeaderIdent {
 }
 
 impl ElfHeader {
-    pub fn new(mv: &Box<dyn MemView>, addr: &mut u64) -> Result<ElfHeader, MemViewError> {
+    pub fn new(mv: &dyn MemView, addr: &mut u64) -> Result<ElfHeader, MemViewError> {
         let ident = ElfHeaderIdent::new(mv, addr)?;
 
         let mut file_type: u16;
@@ -129,11 +159,15 @@ impl ElfHeader {
         })
     }
 
+    pub fn bitness_and_endianness(&self) -> (Bitness, Endianness) {
+        Self::get_endianness_and_bitness(self.ident.class, self.ident.data, self.machine)
+    }
+
     // todo: need full format
     pub fn get_endianness_and_bitness(class: u8, data: u8, machine: u16) -> (Bitness, Endianness) {
         match machine {
-            0x0003 => (Bitness::Bit32, Endianness::LittleEndian),
-            0x003f => (Bitness::Bit64, Endianness::LittleEndian),
+            EM_386 => (Bitness::Bit32, Endianness::LittleEndian),
+            EM_X86_64 => (Bitness::Bit64, Endianness::LittleEndian),
             _ => {
                 // fallback
                 let bitness = if class == 2 { Bitness::Bit64 } else { Bitness::Bit32 };
@@ -147,3 +181,414 @@ impl ElfHeader {
         }
     }
 }
+
+impl ElfSectionHeader {
+    pub fn new(
+        mv: &dyn MemView,
+        addr: &mut u64,
+        bitness: Bitness,
+        endianness: Endianness,
+    ) -> Result<ElfSectionHeader, MemViewError> {
+        let name = mv.read_u32(addr, endianness)?;
+        let section_type = mv.read_u32(addr, endianness)?;
+        if bitness == Bitness::Bit64 {
+            Ok(ElfSectionHeader {
+                name,
+                section_type,
+                flags: mv.read_u64(addr, endianness)?,
+                addr: mv.read_u64(addr, endianness)?,
+                offset: mv.read_u64(addr, endianness)?,
+                size: mv.read_u64(addr, endianness)?,
+                link: mv.read_u32(addr, endianness)?,
+                info: mv.read_u32(addr, endianness)?,
+                addralign: mv.read_u64(addr, endianness)?,
+                entsize: mv.read_u64(addr, endianness)?,
+            })
+        } else {
+            Ok(ElfSectionHeader {
+                name,
+                section_type,
+                flags: mv.read_u32(addr, endianness)? as u64,
+                addr: mv.read_u32(addr, endianness)? as u64,
+                offset: mv.read_u32(addr, endianness)? as u64,
+                size: mv.read_u32(addr, endianness)? as u64,
+                link: mv.read_u32(addr, endianness)?,
+                info: mv.read_u32(addr, endianness)?,
+                addralign: mv.read_u32(addr, endianness)? as u64,
+                entsize: mv.read_u32(addr, endianness)? as u64,
+            })
+        }
+    }
+}
+
+impl ElfProgramHeader {
+    pub fn new(
+        mv: &dyn MemView,
+        addr: &mut u64,
+        bitness: Bitness,
+        endianness: Endianness,
+    ) -> Result<ElfProgramHeader, MemViewError> {
+        let segment_type = mv.read_u32(addr, endianness)?;
+        if bitness == Bitness::Bit64 {
+            // flags moved up here on 64-bit for alignment
+            Ok(ElfProgramHeader {
+                segment_type,
+                flags: mv.read_u32(addr, endianness)?,
+                offset: mv.read_u64(addr, endianness)?,
+                vaddr: mv.read_u64(addr, endianness)?,
+                paddr: mv.read_u64(addr, endianness)?,
+                filesz: mv.read_u64(addr, endianness)?,
+                memsz: mv.read_u64(addr, endianness)?,
+                align: mv.read_u64(addr, endianness)?,
+            })
+        } else {
+            let offset = mv.read_u32(addr, endianness)? as u64;
+            let vaddr = mv.read_u32(addr, endianness)? as u64;
+            let paddr = mv.read_u32(addr, endianness)? as u64;
+            let filesz = mv.read_u32(addr, endianness)? as u64;
+            let memsz = mv.read_u32(addr, endianness)? as u64;
+            let flags = mv.read_u32(addr, endianness)?;
+            let align = mv.read_u32(addr, endianness)? as u64;
+            Ok(ElfProgramHeader {
+                segment_type,
+                flags,
+                offset,
+                vaddr,
+                paddr,
+                filesz,
+                memsz,
+                align,
+            })
+        }
+    }
+}
+
+// ////////////////////////////////////
+
+// an executable or shared object loaded from disk. as_memview lays out the
+// loadable segments the way the loader would, so code can be disassembled
+// without a live process.
+// the fields stay private so the checks new makes still hold in as_memview.
+pub struct ElfFile {
+    mv: Box<dyn MemView>, // the raw file
+    header: ElfHeader,
+    program_headers: Vec<ElfProgramHeader>,
+}
+
+impl ElfFile {
+    // a segment's file bytes are copied into memory with a single read when
+    // mapped, so refuse anything bigger than one read can fill
+    const MAX_SEGMENT_SIZE: u64 = i32::MAX as u64;
+
+    pub fn new(data: Vec<u8>) -> Result<ElfFile, MemViewError> {
+        let data_len = data.len() as u64;
+        let mv: Box<dyn MemView> = Box::new(StaticMemView::new(data));
+
+        // check the ident before trusting anything the header says
+        let ident = ElfHeaderIdent::new(mv.as_ref(), &mut 0)?;
+        if ident.magic != ELF_MAGIC {
+            return Err(MemViewError::generic_static("not an elf file"));
+        }
+        if ident.class != ELFCLASS32 && ident.class != ELFCLASS64 {
+            let err_str = format!("unknown elf class {}", ident.class);
+            return Err(MemViewError::generic_dynamic(err_str));
+        }
+        if ident.data != ELFDATA2LSB && ident.data != ELFDATA2MSB {
+            let err_str = format!("unknown elf data encoding {}", ident.data);
+            return Err(MemViewError::generic_dynamic(err_str));
+        }
+
+        let header = ElfHeader::new(mv.as_ref(), &mut 0)?;
+        if header.file_type != ET_EXEC && header.file_type != ET_DYN {
+            let err_str = format!(
+                "unsupported elf type {}, only executables and shared objects can be loaded",
+                header.file_type
+            );
+            return Err(MemViewError::generic_dynamic(err_str));
+        }
+
+        let (bitness, endianness) = header.bitness_and_endianness();
+        let min_phentsize = if bitness == Bitness::Bit64 { 0x38 } else { 0x20 };
+        if header.phnum > 0 && (header.phentsize as u64) < min_phentsize {
+            let err_str = format!("program header size {} is too small", header.phentsize);
+            return Err(MemViewError::generic_dynamic(err_str));
+        }
+
+        let ph_table_end = (header.phnum as u64)
+            .checked_mul(header.phentsize as u64)
+            .and_then(|v| v.checked_add(header.phoff));
+        if ph_table_end.is_none_or(|v| v > data_len) {
+            return Err(MemViewError::generic_static(
+                "program header table is past the end of the file",
+            ));
+        }
+
+        let mut program_headers = Vec::with_capacity(header.phnum as usize);
+        for i in 0..header.phnum as u64 {
+            let mut addr = header.phoff + i * header.phentsize as u64;
+            program_headers.push(ElfProgramHeader::new(mv.as_ref(), &mut addr, bitness, endianness)?);
+        }
+
+        Self::check_load_segments(&program_headers, data_len)?;
+
+        Ok(ElfFile {
+            mv,
+            header,
+            program_headers,
+        })
+    }
+
+    fn check_load_segments(program_headers: &[ElfProgramHeader], data_len: u64) -> Result<(), MemViewError> {
+        let mut ranges: Vec<(u64, u64)> = Vec::new();
+        for ph in program_headers.iter().filter(|ph| ph.segment_type == PT_LOAD) {
+            if ph.filesz > ph.memsz {
+                let err_str = format!("segment at {:#x} has more file bytes than memory bytes", ph.vaddr);
+                return Err(MemViewError::generic_dynamic(err_str));
+            }
+            if ph.filesz > Self::MAX_SEGMENT_SIZE {
+                let err_str = format!("segment at {:#x} is too large to map", ph.vaddr);
+                return Err(MemViewError::generic_dynamic(err_str));
+            }
+            if ph.offset.checked_add(ph.filesz).is_none_or(|v| v > data_len) {
+                let err_str = format!("segment at {:#x} is past the end of the file", ph.vaddr);
+                return Err(MemViewError::generic_dynamic(err_str));
+            }
+
+            match ph.vaddr.checked_add(ph.memsz) {
+                Some(end) if ph.memsz > 0 => ranges.push((ph.vaddr, end)),
+                Some(_) => {}
+                None => return Err(MemViewError::generic_static("segment wraps around the address space")),
+            }
+        }
+
+        ranges.sort();
+        for pair in ranges.windows(2) {
+            if pair[0].1 > pair[1].0 {
+                let err_str = format!("segments at {:#x} and {:#x} overlap", pair[0].0, pair[1].0);
+                return Err(MemViewError::generic_dynamic(err_str));
+            }
+        }
+
+        Ok(())
+    }
+
+    // the address execution starts at. for ET_DYN this is relative to
+    // wherever the file ends up being loaded.
+    pub fn entry(&self) -> u64 {
+        self.header.entry
+    }
+
+    // e_machine, one of the EM_ constants
+    pub fn machine(&self) -> u16 {
+        self.header.machine
+    }
+
+    pub fn bitness(&self) -> Bitness {
+        self.header.bitness_and_endianness().0
+    }
+
+    pub fn endianness(&self) -> Endianness {
+        self.header.bitness_and_endianness().1
+    }
+
+    // ET_DYN files are position independent and are mapped at base 0 here
+    pub fn is_position_independent(&self) -> bool {
+        self.header.file_type == ET_DYN
+    }
+
+    pub fn header(&self) -> &ElfHeader {
+        &self.header
+    }
+
+    pub fn program_headers(&self) -> &[ElfProgramHeader] {
+        &self.program_headers
+    }
+
+    pub fn load_segments(&self) -> impl Iterator<Item = &ElfProgramHeader> {
+        self.program_headers.iter().filter(|ph| ph.segment_type == PT_LOAD)
+    }
+
+    // the lowest virtual address of any loadable segment. this is what the first
+    // mapping of the file lines up with at runtime, so addresses in the file can
+    // be turned into runtime addresses with `addr - load_base + module_base`.
+    pub fn load_base(&self) -> u64 {
+        // mappings start on a page boundary, so round down the same way the loader does.
+        // assumes 4k pages, which is true for everything we debug right now.
+        self.load_segments().map(|ph| ph.vaddr & !0xfff).min().unwrap_or(0)
+    }
+
+    // read from the file each time since only symbol lookups need them
+    pub fn section_headers(&self) -> Result<Vec<ElfSectionHeader>, MemViewError> {
+        let (bitness, endianness) = self.header.bitness_and_endianness();
+        let mut sections = Vec::with_capacity(self.header.shnum as usize);
+        for i in 0..self.header.shnum as u64 {
+            let mut addr = self.header.shoff + i * self.header.shentsize as u64;
+            sections.push(ElfSectionHeader::new(self.mv.as_ref(), &mut addr, bitness, endianness)?);
+        }
+        Ok(sections)
+    }
+
+    // a section's bytes as they are in the file
+    pub fn section_data(&self, section: &ElfSectionHeader) -> Result<Vec<u8>, MemViewError> {
+        if section.size > i32::MAX as u64 {
+            return Err(MemViewError::InvalidParameter);
+        }
+
+        let mut data = vec![0u8; section.size as usize];
+        let mut addr = section.offset;
+        self.mv.read_bytes(&mut addr, &mut data, section.size as i32)?;
+        Ok(data)
+    }
+
+    // the raw file, for reading tables at file offsets
+    pub fn file_view(&self) -> &dyn MemView {
+        self.mv.as_ref()
+    }
+
+    // every PT_LOAD segment at its virtual address. the part past p_filesz
+    // (usually .bss) reads as zeros and can't be written. addresses outside
+    // the segments read as Unmapped. file bytes are copied, so writes don't
+    // touch the file.
+    pub fn as_memview(&self) -> impl MemView + use<> {
+        let mut mv = CompositeMemView::new();
+        for ph in self.load_segments() {
+            // sizes and overlaps were checked in new
+            if ph.filesz > 0 {
+                let mut segment_data = vec![0u8; ph.filesz as usize];
+                let mut offset = ph.offset;
+                self.mv
+                    .read_bytes(&mut offset, &mut segment_data, ph.filesz as i32)
+                    .expect("segment bounds were checked in new");
+                mv.add_view(ph.vaddr, ph.filesz, Box::new(StaticMemView::new(segment_data)))
+                    .expect("segment overlaps were checked in new");
+            }
+
+            let zero_len = ph.memsz - ph.filesz;
+            if zero_len > 0 {
+                mv.add_view(ph.vaddr + ph.filesz, zero_len, Box::new(ZeroMemView::new(zero_len)))
+                    .expect("segment overlaps were checked in new");
+            }
+        }
+        mv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        binary_formats::elf::consts::{EM_X86_64, PF_R, PF_X},
+        sleigh::disasm::x86_64_disasm,
+    };
+
+    // a 64-bit little endian elf with one PT_LOAD segment holding `code` at
+    // `vaddr`, followed by `bss_len` zero bytes
+    fn make_elf64(file_type: u16, vaddr: u64, code: &[u8], bss_len: u64) -> Vec<u8> {
+        const EHDR_LEN: u64 = 0x40;
+        const PHDR_LEN: u64 = 0x38;
+        let code_offset = EHDR_LEN + PHDR_LEN;
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&ELF_MAGIC);
+        data.extend_from_slice(&[ELFCLASS64, ELFDATA2LSB, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        data.extend_from_slice(&file_type.to_le_bytes());
+        data.extend_from_slice(&EM_X86_64.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&vaddr.to_le_bytes()); // entry
+        data.extend_from_slice(&EHDR_LEN.to_le_bytes()); // phoff
+        data.extend_from_slice(&0u64.to_le_bytes()); // shoff
+        data.extend_from_slice(&0u32.to_le_bytes()); // flags
+        for value in [EHDR_LEN as u16, PHDR_LEN as u16, 1, 0x40, 0, 0] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+
+        data.extend_from_slice(&PT_LOAD.to_le_bytes());
+        data.extend_from_slice(&(PF_R | PF_X).to_le_bytes());
+        data.extend_from_slice(&code_offset.to_le_bytes());
+        data.extend_from_slice(&vaddr.to_le_bytes());
+        data.extend_from_slice(&vaddr.to_le_bytes());
+        data.extend_from_slice(&(code.len() as u64).to_le_bytes());
+        data.extend_from_slice(&(code.len() as u64 + bss_len).to_le_bytes());
+        data.extend_from_slice(&0x1000u64.to_le_bytes());
+
+        data.extend_from_slice(code);
+        data
+    }
+
+    #[test]
+    fn exec_segments_are_mapped_at_vaddr() {
+        // push rbp; ret
+        let elf = ElfFile::new(make_elf64(ET_EXEC, 0x401000, &[0x55, 0xc3], 0x10)).unwrap();
+        assert_eq!(elf.entry(), 0x401000);
+        assert_eq!(elf.machine(), EM_X86_64);
+        assert!(elf.bitness() == Bitness::Bit64);
+        assert!(elf.endianness() == Endianness::LittleEndian);
+        assert!(!elf.is_position_independent());
+        assert_eq!(elf.load_base(), 0x401000);
+
+        let mv = elf.as_memview();
+        let disasm = x86_64_disasm();
+        assert_eq!(disasm.disasm_display(&mv, elf.entry()).unwrap().text, "PUSH RBP");
+        assert_eq!(disasm.disasm_display(&mv, elf.entry() + 1).unwrap().text, "RET");
+
+        // bss is zero filled up to memsz and nothing is mapped after it
+        let mut bss = [0xffu8; 0x10];
+        mv.read_bytes(&mut 0x401002, &mut bss, 0x10).unwrap();
+        assert_eq!(bss, [0u8; 0x10]);
+        assert_eq!(mv.read_u8(&mut 0x401012), Err(MemViewError::Unmapped));
+        assert_eq!(mv.read_u8(&mut 0x400fff), Err(MemViewError::Unmapped));
+    }
+
+    #[test]
+    fn dyn_segments_are_mapped_at_base_zero() {
+        let elf = ElfFile::new(make_elf64(ET_DYN, 0x1000, &[0xc3], 0)).unwrap();
+        assert!(elf.is_position_independent());
+
+        let mv = elf.as_memview();
+        assert_eq!(mv.read_u8(&mut 0x1000), Ok(0xc3));
+        assert_eq!(x86_64_disasm().disasm_display(&mv, 0x1000).unwrap().text, "RET");
+    }
+
+    #[test]
+    fn large_bss_is_not_allocated() {
+        let elf = ElfFile::new(make_elf64(ET_EXEC, 0x401000, &[0xc3], 1 << 40)).unwrap();
+        let mut mv = elf.as_memview();
+        assert_eq!(mv.read_u8(&mut 0x401000), Ok(0xc3));
+        assert_eq!(mv.read_u8(&mut (0x401000 + (1 << 40))), Ok(0));
+        assert_eq!(mv.max_address(), Ok(0x401001 + (1 << 40)));
+
+        // code is a copy and can be patched, bss has nothing to write to
+        mv.write_bytes(&mut 0x401000, &[0x90]).unwrap();
+        assert_eq!(mv.read_u8(&mut 0x401000), Ok(0x90));
+        assert_eq!(mv.write_bytes(&mut 0x401001, &[1]), Err(MemViewError::ReadOnly));
+    }
+
+    #[test]
+    fn bad_headers_are_rejected() {
+        let good = make_elf64(ET_EXEC, 0x401000, &[0xc3], 0);
+
+        // cut off in the middle of the elf header and the program header
+        assert!(ElfFile::new(good[..0x20].to_vec()).is_err());
+        assert!(ElfFile::new(good[..0x50].to_vec()).is_err());
+
+        let mut bad_magic = good.clone();
+        bad_magic[1] = b'X';
+        assert!(ElfFile::new(bad_magic).is_err());
+
+        let mut bad_class = good.clone();
+        bad_class[4] = 3;
+        assert!(ElfFile::new(bad_class).is_err());
+
+        // segment bytes past the end of the file
+        let mut bad_segment = good.clone();
+        bad_segment[0x40 + 0x20] = 0x40; // p_filesz
+        assert!(ElfFile::new(bad_segment).is_err());
+
+        // relocatable objects have nothing to map
+        let mut relocatable = good.clone();
+        relocatable[0x10] = 1;
+        assert!(ElfFile::new(relocatable).is_err());
+
+        assert!(ElfFile::new(good).is_ok());
+    }
+}
@@ -0,0 +1,126 @@
+use crate::{binary_formats::elf::file::ElfFile, consts::arch::Bitness, memory::memview::MemViewError};
+
+const SHT_SYMTAB: u32 = 2;
+const SHT_DYNSYM: u32 = 11;
+const SHN_UNDEF: u16 = 0;
+
+pub const STT_OBJECT: u8 = 1;
+pub const STT_FUNC: u8 = 2;
+
+pub struct ElfSymbol {
+    pub name: String,
+    pub value: u64,
+    pub size: u64,
+    pub info: u8,
+    pub other: u8,
+    pub shndx: u16,
+}
+
+impl ElfSymbol {
+    pub fn symbol_type(&self) -> u8 {
+        self.info & 0xf
+    }
+}
+
+fn get_strtab_str(strtab: &[u8], offset: u32) -> String {
+    let start = offset as usize;
+    if start >= strtab.len() {
+        return String::new();
+    }
+
+    let len = strtab[start..]
+        .iter()
+        .position(|b| *b == 0)
+        .unwrap_or(strtab.len() - start);
+    String::from_utf8_lossy(&strtab[start..start + len]).into_owned()
+}
+
+// reads every defined symbol from .symtab and .dynsym. stripped files usually
+// only have .dynsym, so a symbol can show up twice when both exist.
+pub fn read_symbols(elf: &ElfFile) -> Result<Vec<ElfSymbol>, MemViewError> {
+    let mv = elf.file_view();
+    let (bitness, endianness) = (elf.bitness(), elf.endianness());
+    let sections = elf.section_headers()?;
+
+    let mut symbols = Vec::new();
+    for section in &sections {
+        if section.section_type != SHT_SYMTAB && section.section_type != SHT_DYNSYM {
+            continue;
+        }
+
+        let strtab = match sections.get(section.link as usize) {
+            Some(v) => elf.section_data(v)?,
+            None => return Err(MemViewError::generic_static("symbol table has bad string table link")),
+        };
+
+        let entsize = if section.entsize != 0 {
+            section.entsize
+        } else if bitness == Bitness::Bit64 {
+            24
+        } else {
+            16
+        };
+
+        for i in 0..section.size / entsize {
+            let mut addr = section.offset + i * entsize;
+            let name: u32;
+            let value: u64;
+            let size: u64;
+            let info: u8;
+            let other: u8;
+            let shndx: u16;
+
+            // field order differs between 32-bit and 64-bit
+            if bitness == Bitness::Bit64 {
+                name = mv.read_u32(&mut addr, endianness)?;
+                info = mv.read_u8(&mut addr)?;
+                other = mv.read_u8(&mut addr)?;
+                shndx = mv.read_u16(&mut addr, endianness)?;
+                value = mv.read_u64(&mut addr, endianness)?;
+                size = mv.read_u64(&mut addr, endianness)?;
+            } else {
+                name = mv.read_u32(&mut addr, endianness)?;
+                value = mv.read_u32(&mut addr, endianness)? as u64;
+                size = mv.read_u32(&mut addr, endianness)? as u64;
+                info = mv.read_u8(&mut addr)?;
+                other = mv.read_u8(&mut addr)?;
+                shndx = mv.read_u16(&mut addr, endianness)?;
+            }
+
+            // imports have no address in this file
+            if shndx == SHN_UNDEF {
+                continue;
+            }
+
+            symbols.push(ElfSymbol {
+                name: get_strtab_str(&strtab, name),
+                value,
+                size,
+                info,
+                other,
+                shndx,
+            });
+        }
+    }
+
+    Ok(symbols)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_defined_symbols_through_elf_file() {
+        let elf = ElfFile::new(std::fs::read("/bin/ls").expect("can't read /bin/ls")).unwrap();
+        let symbols = read_symbols(&elf).unwrap();
+
+        // ls imports most of what's in its .dynsym, but exports a few things too
+        assert!(symbols.iter().all(|s| s.shndx != SHN_UNDEF));
+        assert!(
+            symbols
+                .iter()
+                .any(|s| s.symbol_type() == STT_FUNC && !s.name.is_empty())
+        );
+    }
+}
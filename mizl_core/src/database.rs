@@ -6,6 +6,8 @@ pub mod gbf_helpers;
 pub mod gbf_long_fixed_node;
 pub mod gbf_long_interior_node;
 pub mod gbf_long_var_node;
+pub mod gbf_merged_table_view;
+pub mod gbf_node_cache;
 pub mod gbf_node_kind;
 pub mod gbf_record;
 pub mod gbf_table_schema;
@@ -4,6 +4,14 @@ use crate::{
     memory::memview::{MemView, MemViewError},
 };
 
+// a run of addresses that are either all backed by a buffer or all holes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GbfChainedBufRegion {
+    pub start: u64,
+    pub len: u64,
+    pub mapped: bool,
+}
+
 // todo: currently unused/unchecked. NEEDS TESTING!
 // a memview that reads a specific ChainedBuffer
 pub struct GbfChainedBufMemView<'a> {
@@ -94,6 +102,42 @@ impl<'a> GbfChainedBufMemView<'a> {
         }
     }
 
+    // the region addr is in, or None past the end. buffers that were never
+    // allocated are holes, and reading them gives Unmapped.
+    pub fn region_at(&self, addr: u64) -> Option<GbfChainedBufRegion> {
+        Self::find_region(
+            &self.buffer_map,
+            self.get_chain_data_len(),
+            self.buffer_size as u64,
+            addr,
+        )
+    }
+
+    fn find_region(
+        buffer_map: &[i32],
+        chain_data_len: u64,
+        buffer_size: u64,
+        addr: u64,
+    ) -> Option<GbfChainedBufRegion> {
+        if addr >= buffer_size || chain_data_len == 0 {
+            return None;
+        }
+
+        let index = (addr / chain_data_len) as usize;
+        let mapped = *buffer_map.get(index)? >= 0;
+        let is_same = |v: &i32| (*v >= 0) == mapped;
+        let first_index = index - buffer_map[..index].iter().rev().take_while(|v| is_same(v)).count();
+        let last_index = index + buffer_map[index + 1..].iter().take_while(|v| is_same(v)).count();
+
+        let start = first_index as u64 * chain_data_len;
+        let end = std::cmp::min((last_index as u64 + 1) * chain_data_len, buffer_size);
+        Some(GbfChainedBufRegion {
+            start,
+            len: end - start,
+            mapped,
+        })
+    }
+
     fn read_bytes_from_buffer(
         &self,
         buffer_index: i32,
@@ -110,8 +154,8 @@ impl<'a> GbfChainedBufMemView<'a> {
         // todo: assuming buffer_index is in bounds of buffer_map
         let buffer_id = self.buffer_map[buffer_index as usize];
         if buffer_id < 0 {
-            // buffer is not initialized yet, fill with zeros
-            out_data[out_offset..(out_offset + read_len)].fill(0);
+            // buffer was never allocated, so there's nothing to read
+            return Err(MemViewError::Unmapped);
         } else {
             let mut read_addr = self.gbf.get_buffer_address(buffer_id);
             read_addr += Self::get_chain_data_prefix_len(self.is_indexed());
@@ -168,6 +212,15 @@ impl<'a> MemView for GbfChainedBufMemView<'a> {
 
         let chain_data_len = self.get_chain_data_len();
 
+        // check for holes first so a failed read doesn't move addr
+        let last_index = ((*addr + count as u64 - 1) / chain_data_len) as usize;
+        let first_index = (*addr / chain_data_len) as usize;
+        match self.buffer_map.get(first_index..=last_index) {
+            Some(v) if v.iter().all(|buffer_id| *buffer_id >= 0) => {}
+            Some(_) => return Err(MemViewError::Unmapped),
+            None => return Err(MemViewError::EndOfStream),
+        }
+
         let mut out_data_offset = 0usize;
         let mut index = (*addr / chain_data_len) as i32;
         let mut buffer_data_offset = (*addr % chain_data_len) as usize;
@@ -184,10 +237,6 @@ impl<'a> MemView for GbfChainedBufMemView<'a> {
         Ok(())
     }
 
-    fn write_bytes(&mut self, _addr: &mut u64, _value: &[u8]) -> Result<(), MemViewError> {
-        panic!("writing to chained buffer not supported yet");
-    }
-
     fn max_address(&self) -> Result<u64, MemViewError> {
         Ok(self.buffer_size as u64)
     }
@@ -200,3 +249,99 @@ impl<'a> MemView for GbfChainedBufMemView<'a> {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_mapped_and_unmapped_regions() {
+        // buffers of 16 bytes, the last one only partly used
+        let buffer_map = [3, 4, -1, -1, 7, 8];
+        let find = |addr| GbfChainedBufMemView::find_region(&buffer_map, 16, 90, addr);
+
+        let region = GbfChainedBufRegion {
+            start: 0,
+            len: 32,
+            mapped: true,
+        };
+        assert_eq!(find(0), Some(region));
+        assert_eq!(find(31), Some(region));
+
+        let region = GbfChainedBufRegion {
+            start: 32,
+            len: 32,
+            mapped: false,
+        };
+        assert_eq!(find(32), Some(region));
+        assert_eq!(find(50), Some(region));
+
+        let region = GbfChainedBufRegion {
+            start: 64,
+            len: 26,
+            mapped: true,
+        };
+        assert_eq!(find(89), Some(region));
+        assert_eq!(find(90), None);
+    }
+
+    #[test]
+    fn reads_fail_on_holes_without_moving_addr() {
+        use crate::{
+            database::{gbf_db_parms::GbfDbParms, gbf_tables::GbfTables},
+            memory::memview::StaticMemView,
+        };
+        use std::collections::HashMap;
+
+        // blocks of 32 bytes, so each indexed buffer holds 26 bytes of chain
+        // data starting 6 bytes into the block. block n + 1 is buffer n.
+        let gbf = GbfFile {
+            magic: 0,
+            file_id: 0,
+            format_version: 0,
+            block_size: 32,
+            block_count: 3,
+            first_free_buffer_idx: -1,
+            db_parms: GbfDbParms {
+                node_code: 0,
+                data_len: 0,
+                version: 0,
+                values: Vec::new(),
+            },
+            tables: GbfTables {
+                table_defs: HashMap::new(),
+            },
+            mv: Box::new(StaticMemView::new((0..96).collect())),
+        };
+        let view = GbfChainedBufMemView {
+            gbf: &gbf,
+            buffer_size: 78,
+            obfuscated: false,
+            index_map: vec![2],
+            buffer_map: vec![0, -1, 1],
+        };
+
+        let mut addr = 0;
+        let mut out = [0u8; 4];
+        view.read_bytes(&mut addr, &mut out, 4).unwrap();
+        assert_eq!(out, [38, 39, 40, 41]);
+        assert_eq!(addr, 4);
+
+        // the third buffer comes after the hole
+        let mut addr = 52;
+        view.read_bytes(&mut addr, &mut out, 4).unwrap();
+        assert_eq!(out, [70, 71, 72, 73]);
+        assert_eq!(addr, 56);
+
+        for (start, count) in [(20, 10), (30, 4), (50, 4)] {
+            let mut addr = start;
+            let mut out = [0xffu8; 10];
+            assert!(matches!(
+                view.read_bytes(&mut addr, &mut out, count),
+                Err(MemViewError::Unmapped)
+            ));
+            assert_eq!(addr, start);
+            assert!(out.iter().all(|b| *b == 0xff));
+        }
+    }
+}
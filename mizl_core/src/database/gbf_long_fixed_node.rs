@@ -72,13 +72,9 @@ impl<'g> GbfLongFixedNode<'g> {
     }
 
     pub fn find_entry_index_by_key(&self, key: i64) -> Result<BinarySearchMatch, MemViewError> {
-        if self.entry_count == 0 {
-            return Ok(BinarySearchMatch::Missing(0));
-        } else if self.entry_count == 1 {
-            return Ok(BinarySearchMatch::Found(0));
-        }
-
-        let mut min = 1;
+        // unlike interior nodes, every key in a leaf is a real record key,
+        // so the search has to include the first one
+        let mut min = 0;
         let mut max = self.entry_count - 1;
         while min <= max {
             let i = (min + max) / 2;
@@ -96,7 +92,7 @@ impl<'g> GbfLongFixedNode<'g> {
         }
 
         // no match. if this element were to be added,
-        // we would insert it to the right of `min`
+        // it would go at `min`
         Ok(BinarySearchMatch::Missing(min))
     }
 
@@ -105,9 +101,19 @@ impl<'g> GbfLongFixedNode<'g> {
         key: i64,
         index: i32,
         schema: &GbfTableSchema,
+    ) -> Result<Option<GbfRecord>, MemViewError> {
+        self.get_entry_by_index_projected(key, index, schema, None)
+    }
+
+    pub fn get_entry_by_index_projected(
+        &self,
+        key: i64,
+        index: i32,
+        schema: &GbfTableSchema,
+        projection: Option<&[bool]>,
     ) -> Result<Option<GbfRecord>, MemViewError> {
         let at = &mut self.get_value_addr_at(index)?;
-        let record = schema.read_record(GbfFieldValue::Long(key), &self.gbf.mv, at)?;
+        let record = schema.read_record_projected(GbfFieldValue::Long(key), &self.gbf.mv, at, projection)?;
         Ok(Some(record))
     }
 
@@ -120,7 +126,13 @@ impl<'g> GbfLongFixedNode<'g> {
         self.get_entry_by_index(key, entry_idx, schema)
     }
 
-    fn get_prev_node_last_entry(&self, key: i64, schema: &GbfTableSchema) -> Result<Option<GbfRecord>, MemViewError> {
+    // reads the entry's own key, for results that aren't an exact match
+    fn get_indexed_entry(&self, index: i32, schema: &GbfTableSchema) -> Result<Option<GbfRecord>, MemViewError> {
+        let key = self.get_key_at(index)?;
+        self.get_entry_by_index(key, index, schema)
+    }
+
+    fn get_prev_node_last_entry(&self, schema: &GbfTableSchema) -> Result<Option<GbfRecord>, MemViewError> {
         // largest entry from previous node
         if self.prev_leaf_nid == -1 {
             return Ok(None); // nothing left of us, so stop the search
@@ -131,10 +143,10 @@ impl<'g> GbfLongFixedNode<'g> {
             return Ok(None); // just in case, there are no entries in this node
         }
 
-        prev_node.get_entry_by_index(key, prev_node.entry_count - 1, schema)
+        prev_node.get_indexed_entry(prev_node.entry_count - 1, schema)
     }
 
-    fn get_next_node_first_entry(&self, key: i64, schema: &GbfTableSchema) -> Result<Option<GbfRecord>, MemViewError> {
+    fn get_next_node_first_entry(&self, schema: &GbfTableSchema) -> Result<Option<GbfRecord>, MemViewError> {
         // smallest entry from next node
         if self.next_leaf_nid == -1 {
             return Ok(None); // nothing right of us, so stop the search
@@ -145,7 +157,7 @@ impl<'g> GbfLongFixedNode<'g> {
             return Ok(None); // just in case, there are no entries in this node
         }
 
-        next_node.get_entry_by_index(key, 0, schema)
+        next_node.get_indexed_entry(0, schema)
     }
 
     pub fn get_entry_at_before(&self, key: i64, schema: &GbfTableSchema) -> Result<Option<GbfRecord>, MemViewError> {
@@ -156,11 +168,10 @@ impl<'g> GbfLongFixedNode<'g> {
             }
             BinarySearchMatch::Missing(node_entry_idx) => {
                 let result_index = node_entry_idx - 1;
-                let real_key = self.get_key_at(result_index)?;
                 if result_index < 0 {
-                    self.get_prev_node_last_entry(real_key, schema)
+                    self.get_prev_node_last_entry(schema)
                 } else {
-                    self.get_entry_by_index(real_key, result_index, schema)
+                    self.get_indexed_entry(result_index, schema)
                 }
             }
         }
@@ -172,18 +183,17 @@ impl<'g> GbfLongFixedNode<'g> {
                 // found exact match, take previous one
                 let result_index = node_entry_idx - 1;
                 if result_index < 0 {
-                    self.get_prev_node_last_entry(key, schema)
+                    self.get_prev_node_last_entry(schema)
                 } else {
-                    self.get_entry_by_index(key, node_entry_idx, schema)
+                    self.get_indexed_entry(result_index, schema)
                 }
             }
             BinarySearchMatch::Missing(node_entry_idx) => {
                 let result_index = node_entry_idx - 1;
-                let real_key = self.get_key_at(result_index)?;
                 if result_index < 0 {
-                    self.get_prev_node_last_entry(real_key, schema)
+                    self.get_prev_node_last_entry(schema)
                 } else {
-                    self.get_entry_by_index(real_key, result_index, schema)
+                    self.get_indexed_entry(result_index, schema)
                 }
             }
         }
@@ -197,11 +207,10 @@ impl<'g> GbfLongFixedNode<'g> {
             }
             BinarySearchMatch::Missing(node_entry_idx) => {
                 let result_index = node_entry_idx;
-                let real_key = self.get_key_at(result_index)?;
                 if result_index >= self.entry_count {
-                    self.get_next_node_first_entry(real_key, schema)
+                    self.get_next_node_first_entry(schema)
                 } else {
-                    self.get_entry_by_index(real_key, result_index, schema)
+                    self.get_indexed_entry(result_index, schema)
                 }
             }
         }
@@ -213,18 +222,17 @@ impl<'g> GbfLongFixedNode<'g> {
                 // found exact match, take next one
                 let result_index = node_entry_idx + 1;
                 if result_index >= self.entry_count {
-                    self.get_next_node_first_entry(key, schema)
+                    self.get_next_node_first_entry(schema)
                 } else {
-                    self.get_entry_by_index(key, result_index, schema)
+                    self.get_indexed_entry(result_index, schema)
                 }
             }
             BinarySearchMatch::Missing(node_entry_idx) => {
                 let result_index = node_entry_idx;
-                let real_key = self.get_key_at(result_index)?;
                 if result_index >= self.entry_count {
-                    self.get_next_node_first_entry(real_key, schema)
+                    self.get_next_node_first_entry(schema)
                 } else {
-                    self.get_entry_by_index(real_key, result_index, schema)
+                    self.get_indexed_entry(result_index, schema)
                 }
             }
         }
@@ -237,6 +245,8 @@ pub struct GbfLongFixedIterator<'g, 's> {
     cur_node: GbfLongFixedNode<'g>,
     cur_node_idx: i32,
     schema: &'s GbfTableSchema,
+    projection: Option<Vec<bool>>,
+    reverse: bool, // walk towards smaller keys through prev_leaf_nid
 }
 
 impl<'g, 's> GbfLongFixedIterator<'g, 's> {
@@ -244,25 +254,84 @@ impl<'g, 's> GbfLongFixedIterator<'g, 's> {
         cur_node: GbfLongFixedNode<'g>,
         cur_node_idx: i32,
         schema: &'s GbfTableSchema,
+        projection: Option<Vec<bool>>,
+    ) -> GbfLongFixedIterator<'g, 's> {
+        GbfLongFixedIterator {
+            cur_node,
+            cur_node_idx,
+            schema,
+            projection,
+            reverse: false,
+        }
+    }
+
+    // yields cur_node_idx first, then every smaller key. the index can be -1
+    // to start at the last entry of the previous leaf.
+    pub fn new_rev(
+        cur_node: GbfLongFixedNode<'g>,
+        cur_node_idx: i32,
+        schema: &'s GbfTableSchema,
+        projection: Option<Vec<bool>>,
     ) -> GbfLongFixedIterator<'g, 's> {
         GbfLongFixedIterator {
             cur_node,
             cur_node_idx,
             schema,
+            projection,
+            reverse: true,
         }
     }
+
+    fn seek_to_valid_index(&mut self) -> Result<bool, MemViewError> {
+        if self.reverse {
+            while self.cur_node_idx < 0 {
+                if self.cur_node.prev_leaf_nid == -1 {
+                    return Ok(false); // we've hit the start
+                }
+
+                self.cur_node =
+                    GbfLongFixedNode::new(&self.cur_node.gbf, self.cur_node.prev_leaf_nid, self.cur_node.value_len)?;
+                self.cur_node_idx = self.cur_node.entry_count - 1;
+            }
+        } else {
+            // the starting index can already be past the end if the first
+            // key we wanted is bigger than everything in the leaf
+            while self.cur_node_idx >= self.cur_node.entry_count {
+                if self.cur_node.next_leaf_nid == -1 {
+                    return Ok(false); // we've hit the end
+                }
+
+                self.cur_node =
+                    GbfLongFixedNode::new(&self.cur_node.gbf, self.cur_node.next_leaf_nid, self.cur_node.value_len)?;
+                self.cur_node_idx = 0;
+            }
+        }
+        Ok(true)
+    }
 }
 
 impl<'g, 's> Iterator for GbfLongFixedIterator<'g, 's> {
     type Item = Result<GbfRecord, MemViewError>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        // move on to the next leaf once this one is used up
+        match self.seek_to_valid_index() {
+            Ok(true) => {}
+            Ok(false) => return None,
+            Err(e) => return Some(Err(e)),
+        }
+
         // get value at cur index
         let key = match self.cur_node.get_key_at(self.cur_node_idx) {
             Ok(v) => v,
             Err(e) => return Some(Err(e)),
         };
-        let entry_maybe = match self.cur_node.get_entry_by_index(key, self.cur_node_idx, self.schema) {
+        let entry_maybe = match self.cur_node.get_entry_by_index_projected(
+            key,
+            self.cur_node_idx,
+            self.schema,
+            self.projection.as_deref(),
+        ) {
             Ok(v) => v,
             Err(e) => return Some(Err(e)),
         };
@@ -271,28 +340,7 @@ impl<'g, 's> Iterator for GbfLongFixedIterator<'g, 's> {
             None => return None, // shouldn't happen
         };
 
-        // move cur_node/cur_node_idx ahead one
-        if (self.cur_node_idx + 1) < self.cur_node.entry_count {
-            // next index is still within this node
-            self.cur_node_idx += 1;
-        } else {
-            if self.cur_node.next_leaf_nid == -1 {
-                return None; // we've hit the end
-            }
-
-            self.cur_node =
-                match GbfLongFixedNode::new(&self.cur_node.gbf, self.cur_node.next_leaf_nid, self.cur_node.value_len) {
-                    Ok(v) => v,
-                    Err(e) => return Some(Err(e)),
-                };
-
-            if self.cur_node.entry_count < 1 {
-                return None; // shouldn't happen
-            }
-
-            self.cur_node_idx = 0;
-        }
-
+        self.cur_node_idx += if self.reverse { -1 } else { 1 };
         Some(Ok(entry))
     }
 }
@@ -75,13 +75,9 @@ impl<'g> GbfLongVarNode<'g> {
     }
 
     pub fn find_entry_index_by_key(&self, key: i64) -> Result<BinarySearchMatch, MemViewError> {
-        if self.entry_count == 0 {
-            return Ok(BinarySearchMatch::Missing(0));
-        } else if self.entry_count == 1 {
-            return Ok(BinarySearchMatch::Found(0));
-        }
-
-        let mut min = 1;
+        // unlike interior nodes, every key in a leaf is a real record key,
+        // so the search has to include the first one
+        let mut min = 0;
         let mut max = self.entry_count - 1;
         while min <= max {
             let i = (min + max) / 2;
@@ -99,8 +95,8 @@ impl<'g> GbfLongVarNode<'g> {
         }
 
         // no match. if this element were to be added,
-        // we would insert it to the right of `min`
-        Ok(BinarySearchMatch::Missing(min - 1))
+        // it would go at `min`
+        Ok(BinarySearchMatch::Missing(min))
     }
 
     pub fn get_entry_by_index(
@@ -108,9 +104,19 @@ impl<'g> GbfLongVarNode<'g> {
         key: i64,
         index: i32,
         schema: &GbfTableSchema,
+    ) -> Result<Option<GbfRecord>, MemViewError> {
+        self.get_entry_by_index_projected(key, index, schema, None)
+    }
+
+    pub fn get_entry_by_index_projected(
+        &self,
+        key: i64,
+        index: i32,
+        schema: &GbfTableSchema,
+        projection: Option<&[bool]>,
     ) -> Result<Option<GbfRecord>, MemViewError> {
         let at = &mut self.get_value_addr_at(index)?;
-        let record = schema.read_record(GbfFieldValue::Long(key), &self.gbf.mv, at)?;
+        let record = schema.read_record_projected(GbfFieldValue::Long(key), &self.gbf.mv, at, projection)?;
         Ok(Some(record))
     }
 
@@ -123,7 +129,13 @@ impl<'g> GbfLongVarNode<'g> {
         self.get_entry_by_index(key, entry_idx, schema)
     }
 
-    fn get_prev_node_last_entry(&self, key: i64, schema: &GbfTableSchema) -> Result<Option<GbfRecord>, MemViewError> {
+    // reads the entry's own key, for results that aren't an exact match
+    fn get_indexed_entry(&self, index: i32, schema: &GbfTableSchema) -> Result<Option<GbfRecord>, MemViewError> {
+        let key = self.get_key_at(index)?;
+        self.get_entry_by_index(key, index, schema)
+    }
+
+    fn get_prev_node_last_entry(&self, schema: &GbfTableSchema) -> Result<Option<GbfRecord>, MemViewError> {
         // largest entry from previous node
         if self.prev_leaf_nid == -1 {
             return Ok(None); // nothing left of us, so stop the search
@@ -134,10 +146,10 @@ impl<'g> GbfLongVarNode<'g> {
             return Ok(None); // just in case, there are no entries in this node
         }
 
-        prev_node.get_entry_by_index(key, prev_node.entry_count - 1, schema)
+        prev_node.get_indexed_entry(prev_node.entry_count - 1, schema)
     }
 
-    fn get_next_node_first_entry(&self, key: i64, schema: &GbfTableSchema) -> Result<Option<GbfRecord>, MemViewError> {
+    fn get_next_node_first_entry(&self, schema: &GbfTableSchema) -> Result<Option<GbfRecord>, MemViewError> {
         // smallest entry from next node
         if self.next_leaf_nid == -1 {
             return Ok(None); // nothing right of us, so stop the search
@@ -148,7 +160,7 @@ impl<'g> GbfLongVarNode<'g> {
             return Ok(None); // just in case, there are no entries in this node
         }
 
-        next_node.get_entry_by_index(key, 0, schema)
+        next_node.get_indexed_entry(0, schema)
     }
 
     pub fn get_entry_at_before(&self, key: i64, schema: &GbfTableSchema) -> Result<Option<GbfRecord>, MemViewError> {
@@ -159,11 +171,10 @@ impl<'g> GbfLongVarNode<'g> {
             }
             BinarySearchMatch::Missing(node_entry_idx) => {
                 let result_index = node_entry_idx - 1;
-                let real_key = self.get_key_at(result_index)?;
                 if result_index < 0 {
-                    self.get_prev_node_last_entry(real_key, schema)
+                    self.get_prev_node_last_entry(schema)
                 } else {
-                    self.get_entry_by_index(real_key, result_index, schema)
+                    self.get_indexed_entry(result_index, schema)
                 }
             }
         }
@@ -175,18 +186,17 @@ impl<'g> GbfLongVarNode<'g> {
                 // found exact match, take previous one
                 let result_index = node_entry_idx - 1;
                 if result_index < 0 {
-                    self.get_prev_node_last_entry(key, schema)
+                    self.get_prev_node_last_entry(schema)
                 } else {
-                    self.get_entry_by_index(key, node_entry_idx, schema)
+                    self.get_indexed_entry(result_index, schema)
                 }
             }
             BinarySearchMatch::Missing(node_entry_idx) => {
                 let result_index = node_entry_idx - 1;
-                let real_key = self.get_key_at(result_index)?;
                 if result_index < 0 {
-                    self.get_prev_node_last_entry(real_key, schema)
+                    self.get_prev_node_last_entry(schema)
                 } else {
-                    self.get_entry_by_index(real_key, result_index, schema)
+                    self.get_indexed_entry(result_index, schema)
                 }
             }
         }
@@ -200,11 +210,10 @@ impl<'g> GbfLongVarNode<'g> {
             }
             BinarySearchMatch::Missing(node_entry_idx) => {
                 let result_index = node_entry_idx;
-                let real_key = self.get_key_at(result_index)?;
                 if result_index >= self.entry_count {
-                    self.get_next_node_first_entry(real_key, schema)
+                    self.get_next_node_first_entry(schema)
                 } else {
-                    self.get_entry_by_index(real_key, result_index, schema)
+                    self.get_indexed_entry(result_index, schema)
                 }
             }
         }
@@ -216,18 +225,17 @@ impl<'g> GbfLongVarNode<'g> {
                 // found exact match, take next one
                 let result_index = node_entry_idx + 1;
                 if result_index >= self.entry_count {
-                    self.get_next_node_first_entry(key, schema)
+                    self.get_next_node_first_entry(schema)
                 } else {
-                    self.get_entry_by_index(key, result_index, schema)
+                    self.get_indexed_entry(result_index, schema)
                 }
             }
             BinarySearchMatch::Missing(node_entry_idx) => {
                 let result_index = node_entry_idx;
-                let real_key = self.get_key_at(result_index)?;
                 if result_index >= self.entry_count {
-                    self.get_next_node_first_entry(real_key, schema)
+                    self.get_next_node_first_entry(schema)
                 } else {
-                    self.get_entry_by_index(real_key, result_index, schema)
+                    self.get_indexed_entry(result_index, schema)
                 }
             }
         }
@@ -240,6 +248,8 @@ pub struct GbfLongVarIterator<'g, 's> {
     cur_node: GbfLongVarNode<'g>,
     cur_node_idx: i32,
     schema: &'s GbfTableSchema,
+    projection: Option<Vec<bool>>,
+    reverse: bool, // walk towards smaller keys through prev_leaf_nid
 }
 
 impl<'g, 's> GbfLongVarIterator<'g, 's> {
@@ -247,25 +257,82 @@ impl<'g, 's> GbfLongVarIterator<'g, 's> {
         cur_node: GbfLongVarNode<'g>,
         cur_node_idx: i32,
         schema: &'s GbfTableSchema,
+        projection: Option<Vec<bool>>,
+    ) -> GbfLongVarIterator<'g, 's> {
+        GbfLongVarIterator {
+            cur_node,
+            cur_node_idx,
+            schema,
+            projection,
+            reverse: false,
+        }
+    }
+
+    // yields cur_node_idx first, then every smaller key. the index can be -1
+    // to start at the last entry of the previous leaf.
+    pub fn new_rev(
+        cur_node: GbfLongVarNode<'g>,
+        cur_node_idx: i32,
+        schema: &'s GbfTableSchema,
+        projection: Option<Vec<bool>>,
     ) -> GbfLongVarIterator<'g, 's> {
         GbfLongVarIterator {
             cur_node,
             cur_node_idx,
             schema,
+            projection,
+            reverse: true,
         }
     }
+
+    fn seek_to_valid_index(&mut self) -> Result<bool, MemViewError> {
+        if self.reverse {
+            while self.cur_node_idx < 0 {
+                if self.cur_node.prev_leaf_nid == -1 {
+                    return Ok(false); // we've hit the start
+                }
+
+                self.cur_node = GbfLongVarNode::new(&self.cur_node.gbf, self.cur_node.prev_leaf_nid)?;
+                self.cur_node_idx = self.cur_node.entry_count - 1;
+            }
+        } else {
+            // the starting index can already be past the end if the first
+            // key we wanted is bigger than everything in the leaf
+            while self.cur_node_idx >= self.cur_node.entry_count {
+                if self.cur_node.next_leaf_nid == -1 {
+                    return Ok(false); // we've hit the end
+                }
+
+                self.cur_node = GbfLongVarNode::new(&self.cur_node.gbf, self.cur_node.next_leaf_nid)?;
+                self.cur_node_idx = 0;
+            }
+        }
+        Ok(true)
+    }
 }
 
 impl<'g, 's> Iterator for GbfLongVarIterator<'g, 's> {
     type Item = Result<GbfRecord, MemViewError>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        // move on to the next leaf once this one is used up
+        match self.seek_to_valid_index() {
+            Ok(true) => {}
+            Ok(false) => return None,
+            Err(e) => return Some(Err(e)),
+        }
+
         // get value at cur index
         let key = match self.cur_node.get_key_at(self.cur_node_idx) {
             Ok(v) => v,
             Err(e) => return Some(Err(e)),
         };
-        let entry_maybe = match self.cur_node.get_entry_by_index(key, self.cur_node_idx, self.schema) {
+        let entry_maybe = match self.cur_node.get_entry_by_index_projected(
+            key,
+            self.cur_node_idx,
+            self.schema,
+            self.projection.as_deref(),
+        ) {
             Ok(v) => v,
             Err(e) => return Some(Err(e)),
         };
@@ -274,27 +341,7 @@ impl<'g, 's> Iterator for GbfLongVarIterator<'g, 's> {
             None => return None, // shouldn't happen
         };
 
-        // move cur_node/cur_node_idx ahead one
-        if (self.cur_node_idx + 1) < self.cur_node.entry_count {
-            // next index is still within this node
-            self.cur_node_idx += 1;
-        } else {
-            if self.cur_node.next_leaf_nid == -1 {
-                return None; // we've hit the end
-            }
-
-            self.cur_node = match GbfLongVarNode::new(&self.cur_node.gbf, self.cur_node.next_leaf_nid) {
-                Ok(v) => v,
-                Err(e) => return Some(Err(e)),
-            };
-
-            if self.cur_node.entry_count < 1 {
-                return None; // shouldn't happen
-            }
-
-            self.cur_node_idx = 0;
-        }
-
+        self.cur_node_idx += if self.reverse { -1 } else { 1 };
         Some(Ok(entry))
     }
 }
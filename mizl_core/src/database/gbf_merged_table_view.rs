@@ -0,0 +1,183 @@
+use crate::{
+    database::{
+        gbf::GbfFile,
+        gbf_record::{GbfFieldValue, GbfRecord},
+        gbf_table_view::{GbfTableView, GbfTableViewIterator},
+    },
+    memory::memview::MemViewError,
+};
+use std::iter::Peekable;
+
+// the union of the same table across several files, read in key order.
+//
+// keys are only unique within one file, so the same key can show up in more
+// than one view. nothing is dropped in that case: iteration returns every
+// record with that key, in the order the views were added, and get returns
+// the one from the first view that has it.
+pub struct GbfMergedTableView<'g, 's> {
+    views: Vec<GbfTableView<'g, 's>>,
+}
+
+impl<'g, 's> GbfMergedTableView<'g, 's> {
+    pub fn new(views: Vec<GbfTableView<'g, 's>>) -> GbfMergedTableView<'g, 's> {
+        GbfMergedTableView { views }
+    }
+
+    // one view per file that has a table with this name. files without it are skipped.
+    pub fn from_files(gbfs: &[&'g GbfFile], table_name: &str) -> Result<GbfMergedTableView<'g, 'g>, MemViewError> {
+        let mut views = Vec::new();
+        for gbf in gbfs {
+            let table_def = match gbf.tables.table_defs.get(table_name) {
+                Some(v) => v,
+                None => continue,
+            };
+            views.push(GbfTableView::new(gbf, &table_def.schema, table_def.root_nid)?);
+        }
+        Ok(GbfMergedTableView::new(views))
+    }
+
+    pub fn get_views(&self) -> &[GbfTableView<'g, 's>] {
+        &self.views
+    }
+
+    pub fn get(&self, key: i64) -> Result<Option<GbfRecord>, MemViewError> {
+        for view in &self.views {
+            if let Some(record) = view.get(key)? {
+                return Ok(Some(record));
+            }
+        }
+        Ok(None)
+    }
+}
+
+fn get_record_key(record: &GbfRecord) -> Result<i64, MemViewError> {
+    match record.key {
+        GbfFieldValue::Long(v) => Ok(v),
+        _ => Err(MemViewError::generic_static("merged views only support long keys")),
+    }
+}
+
+// k-way merge of each view's iterator. there are only ever a handful of files,
+// so finding the smallest head is a linear scan instead of a heap.
+pub struct GbfMergedTableViewIterator<I: Iterator<Item = Result<GbfRecord, MemViewError>>> {
+    iterators: Vec<Peekable<I>>,
+}
+
+impl<'g, 's> GbfMergedTableViewIterator<GbfTableViewIterator<'g, 's>> {
+    pub fn new(
+        mtv: &'s GbfMergedTableView<'g, 's>,
+        key: i64,
+    ) -> Result<GbfMergedTableViewIterator<GbfTableViewIterator<'g, 's>>, MemViewError> {
+        let mut iterators = Vec::with_capacity(mtv.views.len());
+        for view in &mtv.views {
+            iterators.push(GbfTableViewIterator::new(view, key)?);
+        }
+        Ok(GbfMergedTableViewIterator::from_iterators(iterators))
+    }
+}
+
+impl<I: Iterator<Item = Result<GbfRecord, MemViewError>>> GbfMergedTableViewIterator<I> {
+    // each iterator has to already be in key order
+    pub fn from_iterators(iterators: Vec<I>) -> GbfMergedTableViewIterator<I> {
+        GbfMergedTableViewIterator {
+            iterators: iterators.into_iter().map(|v| v.peekable()).collect(),
+        }
+    }
+}
+
+impl<I: Iterator<Item = Result<GbfRecord, MemViewError>>> Iterator for GbfMergedTableViewIterator<I> {
+    type Item = Result<GbfRecord, MemViewError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut min_idx: Option<usize> = None;
+        let mut min_key = i64::MAX;
+        for (i, iterator) in self.iterators.iter_mut().enumerate() {
+            let key = match iterator.peek() {
+                Some(Ok(record)) => match get_record_key(record) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        // skip past it, or the next call would trip over the same record
+                        iterator.next();
+                        return Some(Err(e));
+                    }
+                },
+                // hand errors back right away, the iterator decides what comes after
+                Some(Err(_)) => return iterator.next(),
+                None => continue,
+            };
+
+            // strictly less so earlier views win ties
+            if min_idx.is_none() || key < min_key {
+                min_idx = Some(i);
+                min_key = key;
+            }
+        }
+
+        match min_idx {
+            Some(i) => self.iterators[i].next(),
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(key: i64, value: i32) -> Result<GbfRecord, MemViewError> {
+        Ok(GbfRecord::new(
+            GbfFieldValue::Long(key),
+            vec![GbfFieldValue::Int(value)],
+        ))
+    }
+
+    fn merged_keys(iterators: Vec<Vec<Result<GbfRecord, MemViewError>>>) -> Vec<Result<(i64, i32), MemViewError>> {
+        let iterators = iterators.into_iter().map(|v| v.into_iter()).collect();
+        GbfMergedTableViewIterator::from_iterators(iterators)
+            .map(|r| r.map(|r| (get_record_key(&r).unwrap(), r.get_int(0).unwrap())))
+            .collect()
+    }
+
+    #[test]
+    fn merge_is_in_key_order() {
+        let keys = merged_keys(vec![
+            vec![record(10, 0), record(40, 0)],
+            vec![],
+            vec![record(5, 2), record(20, 2), record(50, 2)],
+            vec![record(30, 3)],
+        ]);
+        let keys: Vec<i64> = keys.into_iter().map(|r| r.unwrap().0).collect();
+        assert_eq!(keys, vec![5, 10, 20, 30, 40, 50]);
+    }
+
+    #[test]
+    fn ties_go_in_view_order() {
+        let keys = merged_keys(vec![
+            vec![record(10, 0), record(20, 0)],
+            vec![record(10, 1)],
+            vec![record(10, 2), record(20, 2)],
+        ]);
+        let keys: Vec<(i64, i32)> = keys.into_iter().map(|r| r.unwrap()).collect();
+        assert_eq!(keys, vec![(10, 0), (10, 1), (10, 2), (20, 0), (20, 2)]);
+    }
+
+    #[test]
+    fn bad_keys_are_reported_once() {
+        let string_key = Ok(GbfRecord::new(
+            GbfFieldValue::String("a".into()),
+            vec![GbfFieldValue::Int(1)],
+        ));
+        let keys = merged_keys(vec![
+            vec![record(10, 0), record(30, 0)],
+            vec![string_key, record(20, 1)],
+            vec![Err(MemViewError::EndOfStream), record(40, 2)],
+        ]);
+
+        // both errors come out first since they're found while looking for the smallest key
+        assert_eq!(keys.len(), 6);
+        assert!(keys[0].is_err());
+        assert_eq!(keys[1], Err(MemViewError::EndOfStream));
+        let rest: Vec<(i64, i32)> = keys[2..].iter().map(|r| r.clone().unwrap()).collect();
+        assert_eq!(rest, vec![(10, 0), (20, 1), (30, 0), (40, 2)]);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::{
+    database::{gbf_binary_search::BinarySearchMatch, gbf_long_interior_node::GbfLongInteriorNode},
+    memory::memview::MemViewError,
+};
+use std::collections::HashMap;
+
+// the keys and child nids of an interior node, copied out so lookups
+// don't have to go back to the memview
+pub struct GbfCachedInteriorNode {
+    keys: Vec<i64>,
+    child_nids: Vec<i32>,
+}
+
+impl GbfCachedInteriorNode {
+    pub fn read(interior: &GbfLongInteriorNode) -> Result<GbfCachedInteriorNode, MemViewError> {
+        let entry_count = std::cmp::max(interior.entry_count, 0) as usize;
+        let mut keys = Vec::with_capacity(entry_count);
+        let mut child_nids = Vec::with_capacity(entry_count);
+        for i in 0..entry_count as i32 {
+            keys.push(interior.get_key_at(i)?);
+            child_nids.push(interior.get_value_at(i)?);
+        }
+        Ok(GbfCachedInteriorNode { keys, child_nids })
+    }
+
+    // same search as GbfLongInteriorNode::find_entry_index_by_key. the first key
+    // is never compared since everything left of the second key goes there.
+    fn find_entry_index_by_key(&self, key: i64) -> BinarySearchMatch {
+        if self.keys.is_empty() {
+            return BinarySearchMatch::Missing(0);
+        } else if self.keys.len() == 1 {
+            return BinarySearchMatch::Found(0);
+        }
+
+        let idx = 1 + self.keys[1..].partition_point(|k| *k < key);
+        if idx < self.keys.len() && self.keys[idx] == key {
+            BinarySearchMatch::Found(idx as i32)
+        } else {
+            BinarySearchMatch::Missing(idx as i32)
+        }
+    }
+
+    pub fn get_entry(&self, key: i64) -> Result<i32, MemViewError> {
+        let entry_idx = match self.find_entry_index_by_key(key) {
+            BinarySearchMatch::Found(v) => v,
+            BinarySearchMatch::Missing(v) => v - 1,
+        };
+        match self.child_nids.get(entry_idx as usize) {
+            Some(v) => Ok(*v),
+            None => Err(MemViewError::generic_static("interior node has no entries")),
+        }
+    }
+}
+
+// bounded lru of interior nodes by nid. leaf nodes aren't cached since point
+// lookups only read a few entries out of them anyway.
+pub struct GbfNodeCache {
+    capacity: usize,
+    tick: u64,
+    nodes: HashMap<i32, (u64, GbfCachedInteriorNode)>, // nid -> (last used tick, node)
+}
+
+impl GbfNodeCache {
+    pub const DEFAULT_CAPACITY: usize = 64;
+
+    // a capacity of 0 turns the cache off
+    pub fn new(capacity: usize) -> GbfNodeCache {
+        GbfNodeCache {
+            capacity,
+            tick: 0,
+            nodes: HashMap::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.nodes.len() > self.capacity {
+            self.evict_oldest();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+    }
+
+    pub fn get(&mut self, nid: i32) -> Option<&GbfCachedInteriorNode> {
+        self.tick += 1;
+        match self.nodes.get_mut(&nid) {
+            Some((last_used, node)) => {
+                *last_used = self.tick;
+                Some(node)
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&mut self, nid: i32, node: GbfCachedInteriorNode) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if !self.nodes.contains_key(&nid) && self.nodes.len() >= self.capacity {
+            self.evict_oldest();
+        }
+
+        self.tick += 1;
+        self.nodes.insert(nid, (self.tick, node));
+    }
+
+    fn evict_oldest(&mut self) {
+        let oldest_nid = match self.nodes.iter().min_by_key(|(_, (last_used, _))| *last_used) {
+            Some((nid, _)) => *nid,
+            None => return,
+        };
+        self.nodes.remove(&oldest_nid);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_node(keys: &[i64], child_nids: &[i32]) -> GbfCachedInteriorNode {
+        GbfCachedInteriorNode {
+            keys: keys.to_vec(),
+            child_nids: child_nids.to_vec(),
+        }
+    }
+
+    #[test]
+    fn interior_lookup_matches_node_search() {
+        let node = make_node(&[0, 100, 200], &[10, 11, 12]);
+        assert_eq!(node.get_entry(-5).unwrap(), 10);
+        assert_eq!(node.get_entry(99).unwrap(), 10);
+        assert_eq!(node.get_entry(100).unwrap(), 11);
+        assert_eq!(node.get_entry(150).unwrap(), 11);
+        assert_eq!(node.get_entry(200).unwrap(), 12);
+        assert_eq!(node.get_entry(i64::MAX).unwrap(), 12);
+        assert!(make_node(&[], &[]).get_entry(0).is_err());
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut cache = GbfNodeCache::new(2);
+        cache.insert(1, make_node(&[0], &[1]));
+        cache.insert(2, make_node(&[0], &[2]));
+        assert!(cache.get(1).is_some());
+        cache.insert(3, make_node(&[0], &[3]));
+        assert!(cache.get(2).is_none());
+        assert!(cache.get(1).is_some());
+        assert!(cache.get(3).is_some());
+
+        cache.set_capacity(0);
+        assert!(cache.is_empty());
+        cache.insert(4, make_node(&[0], &[4]));
+        assert!(cache.get(4).is_none());
+    }
+}
@@ -1,9 +1,10 @@
+use crate::consts::arch::Endianness;
 use crate::ffi::core_framework::prelude::*;
-use crate::ffi::definitions::database::GbfFieldValueFfi;
-use crate::memory::memview::MemViewError;
+use crate::memory::memview::{MemView, MemViewError, StaticMemView};
 use mizl_pm::FfiSerialize;
 
 #[derive(FfiSerialize)]
+#[ffi_serialize(deserialize)]
 pub struct GbfRecord {
     pub key: GbfFieldValue,
     pub values: Vec<GbfFieldValue>,
@@ -21,6 +22,10 @@ impl GbfRecord {
         }
     }
 
+    pub fn is_null(&self, index: usize) -> Result<bool, MemViewError> {
+        Ok(matches!(self.get_value_or_err(index)?, GbfFieldValue::Null))
+    }
+
     // todo: move get_xxx logic to GbfFieldValue
     pub fn get_boolean(&self, index: usize) -> Result<bool, MemViewError> {
         match self.get_value_or_err(index)? {
@@ -29,6 +34,7 @@ impl GbfRecord {
             GbfFieldValue::Short(v) => Ok(*v != 0),
             GbfFieldValue::Int(v) => Ok(*v != 0),
             GbfFieldValue::Long(v) => Ok(*v != 0),
+            GbfFieldValue::Null => Ok(false),
             _ => return Err(MemViewError::generic_static("unexpected field type")),
         }
     }
@@ -39,6 +45,7 @@ impl GbfRecord {
             GbfFieldValue::Short(v) => Ok(*v as i8),
             GbfFieldValue::Int(v) => Ok(*v as i8),
             GbfFieldValue::Long(v) => Ok(*v as i8),
+            GbfFieldValue::Null => Ok(0),
             _ => return Err(MemViewError::generic_static("unexpected field type")),
         }
     }
@@ -49,6 +56,7 @@ impl GbfRecord {
             GbfFieldValue::Byte(v) => Ok(*v as i16),
             GbfFieldValue::Int(v) => Ok(*v as i16),
             GbfFieldValue::Long(v) => Ok(*v as i16),
+            GbfFieldValue::Null => Ok(0),
             _ => return Err(MemViewError::generic_static("unexpected field type")),
         }
     }
@@ -59,6 +67,7 @@ impl GbfRecord {
             GbfFieldValue::Byte(v) => Ok(*v as i32),
             GbfFieldValue::Short(v) => Ok(*v as i32),
             GbfFieldValue::Long(v) => Ok(*v as i32),
+            GbfFieldValue::Null => Ok(0),
             _ => return Err(MemViewError::generic_static("unexpected field type")),
         }
     }
@@ -69,6 +78,7 @@ impl GbfRecord {
             GbfFieldValue::Byte(v) => Ok(*v as i64),
             GbfFieldValue::Short(v) => Ok(*v as i64),
             GbfFieldValue::Int(v) => Ok(*v as i64),
+            GbfFieldValue::Null => Ok(0),
             _ => return Err(MemViewError::generic_static("unexpected field type")),
         }
     }
@@ -76,13 +86,45 @@ impl GbfRecord {
     pub fn get_string(&self, index: usize) -> Result<String, MemViewError> {
         match self.get_value_or_err(index)? {
             GbfFieldValue::String(v) => Ok(v.clone()),
+            GbfFieldValue::Null => Ok(String::new()),
+            _ => return Err(MemViewError::generic_static("unexpected field type")),
+        }
+    }
+
+    // the schema doesn't record a column's text encoding, so utf-16 text has
+    // to be asked for. uses the database's endianness, which is always big.
+    pub fn get_string_utf16(&self, index: usize) -> Result<String, MemViewError> {
+        self.get_string_utf16_endian(index, Endianness::BigEndian)
+    }
+
+    pub fn get_string_utf16_endian(&self, index: usize, endian: Endianness) -> Result<String, MemViewError> {
+        let raw_bytes = match self.get_value_or_err(index)? {
+            GbfFieldValue::Bytes(v) => v.as_slice(),
+            GbfFieldValue::String(v) => v.as_bytes(),
+            GbfFieldValue::Null => return Ok(String::new()),
             _ => return Err(MemViewError::generic_static("unexpected field type")),
+        };
+
+        if raw_bytes.len() % 2 != 0 {
+            return Err(MemViewError::generic_static("odd length utf-16 string"));
+        }
+
+        let mv = StaticMemView::new(raw_bytes.to_vec());
+        let mut units = Vec::with_capacity(raw_bytes.len() / 2);
+        let mut at = 0;
+        while at < raw_bytes.len() as u64 {
+            units.push(mv.read_u16(&mut at, endian)?);
+        }
+        match char::decode_utf16(units).collect() {
+            Ok(v) => Ok(v),
+            Err(_) => Err(MemViewError::generic_static("invalid utf-16 string read")),
         }
     }
 
     pub fn get_bytes(&self, index: usize) -> Result<Vec<u8>, MemViewError> {
         match self.get_value_or_err(index)? {
             GbfFieldValue::Bytes(v) => Ok(v.clone()),
+            GbfFieldValue::Null => Ok(Vec::new()),
             _ => return Err(MemViewError::generic_static("unexpected field type")),
         }
     }
@@ -176,12 +218,79 @@ impl GbfExtensionKind {
     pub const SPARSE_FIELD_LIST: u8 = 1;
 }
 
+// variant order is the ffi tag, keep it in sync with GBFFIELDVALUE_TAG_*
+#[derive(FfiSerialize)]
+#[ffi_serialize(deserialize)]
 pub enum GbfFieldValue {
-    Boolean(bool),
     Byte(i8),
     Short(i16),
     Int(i32),
     Long(i64),
     String(String),
     Bytes(Vec<u8>),
+    Boolean(bool),
+    // a sparse column missing from the row, or a string/bytes field stored with
+    // a -1 length. the get_xxx functions treat it as the kind's zero value.
+    Null,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_utf16_strings() {
+        let values = vec![
+            GbfFieldValue::Bytes(vec![0x00, 0x68, 0x00, 0x69, 0xd8, 0x3d, 0xde, 0x00]),
+            GbfFieldValue::Bytes(vec![0x68, 0x00, 0x69, 0x00]),
+            GbfFieldValue::Null,
+            GbfFieldValue::Bytes(vec![0x00, 0x68, 0x00]),
+            GbfFieldValue::Bytes(vec![0xd8, 0x3d]),
+            GbfFieldValue::Int(1),
+        ];
+        let record = GbfRecord::new(GbfFieldValue::Long(0), values);
+
+        assert_eq!(record.get_string_utf16(0).unwrap(), "hi\u{1f600}");
+        assert_eq!(
+            record.get_string_utf16_endian(1, Endianness::LittleEndian).unwrap(),
+            "hi"
+        );
+        assert_eq!(record.get_string_utf16(2).unwrap(), "");
+        assert!(record.get_string_utf16(3).is_err());
+        assert!(record.get_string_utf16(4).is_err());
+        assert!(record.get_string_utf16(5).is_err());
+    }
+
+    #[test]
+    fn field_value_ffi_layout() {
+        // tag, then an 8 byte union
+        assert_eq!(GbfFieldValueFfi::calculate_base_size(), 16);
+
+        let mut buffer = vec![0u64; 8];
+        let start = buffer.as_mut_ptr() as *mut u8;
+        unsafe {
+            GbfFieldValueFfi::serialize(start, &GbfFieldValue::Long(-5));
+            assert_eq!(*(start as *const i32), 3);
+            assert_eq!(*(start.add(8) as *const i64), -5);
+
+            GbfFieldValueFfi::serialize(start, &GbfFieldValue::Boolean(true));
+            assert_eq!(*(start as *const i32), 6);
+            assert_eq!(*(start.add(8) as *const u64), 1);
+
+            GbfFieldValueFfi::serialize(start, &GbfFieldValue::Null);
+            assert_eq!(*(start as *const i32), 7);
+            assert_eq!(*(start.add(8) as *const u64), 0);
+
+            let bytes = GbfFieldValue::Bytes(vec![1, 2, 3]);
+            let end = GbfFieldValueFfi::serialize(start, &bytes);
+            assert_eq!(*(start as *const i32), 5);
+            let data_ptr = *(start.add(8) as *const *const u8);
+            assert_eq!(*(data_ptr.sub(4) as *const u32), 3);
+            assert_eq!(std::slice::from_raw_parts(data_ptr, 3), [1, 2, 3]);
+            assert_eq!(
+                end as usize - start as usize,
+                GbfFieldValueFfi::calculate_full_size(&bytes)
+            );
+        }
+    }
 }
@@ -3,7 +3,7 @@ use std::collections::HashSet;
 use crate::{
     consts::arch::Endianness,
     database::{
-        gbf_helpers::{read_bytestring, read_string},
+        gbf_helpers::read_bytestring,
         gbf_record::{GbfFieldKind, GbfFieldValue, GbfRecord},
     },
     memory::memview::{MemView, MemViewError},
@@ -16,6 +16,8 @@ pub struct GbfTableSchema {
     pub sparse_columns: Option<HashSet<i32>>,
     pub kinds: Vec<GbfFieldKind>,
     pub names: Vec<String>,
+    // groups of column names that mean the same thing, used by get_column_idx_ci
+    pub aliases: Vec<Vec<String>>,
 }
 
 impl GbfTableSchema {
@@ -32,6 +34,7 @@ impl GbfTableSchema {
             sparse_columns,
             kinds: Vec::new(),
             names: Vec::new(),
+            aliases: Vec::new(),
         }
     }
 
@@ -63,21 +66,101 @@ impl GbfTableSchema {
         self.names.iter().position(|e| e == name)
     }
 
+    // whether a row can come back with GbfFieldValue::Null for this column. that
+    // happens for sparse columns (missing from the row) and for strings and bytes
+    // (stored with a -1 length). the format doesn't store default values, so an
+    // absent value is always Null rather than some per-column default.
+    pub fn is_nullable(&self, idx: usize) -> bool {
+        let kind = match self.kinds.get(idx) {
+            Some(v) => v,
+            None => return false,
+        };
+
+        let is_sparse = match &self.sparse_columns {
+            Some(v) => v.contains(&(idx as i32)),
+            None => false,
+        };
+
+        is_sparse || matches!(kind, GbfFieldKind::String | GbfFieldKind::Bytes)
+    }
+
+    // different ghidra versions don't always agree on column names, so this lets
+    // a reader say "Addr" and "Address" are the same column. names in a group are
+    // compared case-insensitively, same as get_column_idx_ci.
+    pub fn add_alias_group(&mut self, names: &[&str]) {
+        self.aliases.push(names.iter().map(|n| n.to_string()).collect());
+    }
+
+    // slower than get_column_idx, so look up the index once and not in a loop.
+    // tries the name itself first, then every name aliased to it.
+    pub fn get_column_idx_ci(&self, name: &str) -> Option<usize> {
+        if let Some(idx) = self.names.iter().position(|e| e.eq_ignore_ascii_case(name)) {
+            return Some(idx);
+        }
+
+        for group in &self.aliases {
+            if !group.iter().any(|a| a.eq_ignore_ascii_case(name)) {
+                continue;
+            }
+
+            for alias in group {
+                if let Some(idx) = self.names.iter().position(|e| e.eq_ignore_ascii_case(alias)) {
+                    return Some(idx);
+                }
+            }
+        }
+
+        None
+    }
+
+    // turn a list of column indices into a per-column mask for read_record_projected
+    pub fn make_projection(&self, columns: &[usize]) -> Result<Vec<bool>, MemViewError> {
+        let mut projection = vec![false; self.kinds.len()];
+        for column in columns {
+            match projection.get_mut(*column) {
+                Some(v) => *v = true,
+                None => return Err(MemViewError::generic_static("projected column out of bounds")),
+            }
+        }
+        Ok(projection)
+    }
+
     pub fn read_record(
         &self,
         key: GbfFieldValue,
         mv: &Box<dyn MemView>,
         at: &mut u64,
     ) -> Result<GbfRecord, MemViewError> {
-        let mut values: Vec<GbfFieldValue> = Vec::new();
+        self.read_record_projected(key, mv, at, None)
+    }
+
+    // only decodes columns set in `projection` and leaves the rest as default values
+    // (not Null, since we never looked at them).
+    // records have no offset table so there's no random access to a column. we still
+    // have to walk every column up to the last requested one, but skipped strings and
+    // bytes are never allocated. sparse tables store optional columns after all of the
+    // required ones, so those always walk the whole record.
+    pub fn read_record_projected(
+        &self,
+        key: GbfFieldValue,
+        mv: &Box<dyn MemView>,
+        at: &mut u64,
+        projection: Option<&[bool]>,
+    ) -> Result<GbfRecord, MemViewError> {
+        let mut values: Vec<GbfFieldValue> = Vec::with_capacity(self.kinds.len());
+        let is_wanted = |i: usize| projection.map_or(true, |p| p[i]);
 
         if let Some(sparse_columns) = &self.sparse_columns {
             // read required fields
             for i in 0..self.kinds.len() {
                 let kind = &self.kinds[i];
-                if !sparse_columns.contains(&(i as i32)) {
+                if sparse_columns.contains(&(i as i32)) {
+                    // stays null unless it shows up in the optional fields below
+                    values.push(GbfFieldValue::Null);
+                } else if is_wanted(i) {
                     values.push(Self::read_value(kind, mv, at)?);
                 } else {
+                    Self::skip_value(kind, mv, at)?;
                     values.push(Self::default_value(kind));
                 }
             }
@@ -87,11 +170,28 @@ impl GbfTableSchema {
             for _ in 0..sparse_field_count {
                 let this_sparse_field_idx = mv.read_u8(at)? as usize;
                 let kind = &self.kinds[this_sparse_field_idx];
-                values[this_sparse_field_idx] = Self::read_value(kind, mv, at)?;
+                if is_wanted(this_sparse_field_idx) {
+                    values[this_sparse_field_idx] = Self::read_value(kind, mv, at)?;
+                } else {
+                    Self::skip_value(kind, mv, at)?;
+                }
             }
         } else {
-            for kind in &self.kinds {
-                values.push(Self::read_value(kind, mv, at)?);
+            let last_wanted = match projection {
+                Some(p) => p.iter().rposition(|v| *v),
+                None => self.kinds.len().checked_sub(1),
+            };
+
+            for (i, kind) in self.kinds.iter().enumerate() {
+                if last_wanted.map_or(true, |last| i > last) {
+                    // nothing else requested, no need to walk the rest
+                    values.push(Self::default_value(kind));
+                } else if is_wanted(i) {
+                    values.push(Self::read_value(kind, mv, at)?);
+                } else {
+                    Self::skip_value(kind, mv, at)?;
+                    values.push(Self::default_value(kind));
+                }
             }
         }
 
@@ -106,20 +206,39 @@ impl GbfTableSchema {
             GbfFieldKind::Short => GbfFieldValue::Short(mv.read_i16(at, endian)?),
             GbfFieldKind::Int => GbfFieldValue::Int(mv.read_i32(at, endian)?),
             GbfFieldKind::Long => GbfFieldValue::Long(mv.read_i64(at, endian)?),
-            GbfFieldKind::String => GbfFieldValue::String(match read_string(&mv, at)? {
-                Some(v) => v,          // regular string
-                None => String::new(), // null string (should we be handling this?)
-            }),
-            GbfFieldKind::Bytes => {
-                GbfFieldValue::Bytes(match read_bytestring(&mv, at)? {
-                    Some(v) => v,                  // regular bytestring
-                    None => Vec::with_capacity(0), // empty bytestring (shouldn't happen?)
-                })
-            }
+            // the schema doesn't say how a string column is encoded. anything
+            // that isn't utf-8 (like utf-16 text) stays as bytes so
+            // get_string_utf16 can still read it.
+            GbfFieldKind::String => match read_bytestring(&mv, at)? {
+                Some(v) => match String::from_utf8(v) {
+                    Ok(v) => GbfFieldValue::String(v),
+                    Err(e) => GbfFieldValue::Bytes(e.into_bytes()),
+                },
+                None => GbfFieldValue::Null,
+            },
+            GbfFieldKind::Bytes => match read_bytestring(&mv, at)? {
+                Some(v) => GbfFieldValue::Bytes(v),
+                None => GbfFieldValue::Null,
+            },
         };
         Ok(value)
     }
 
+    fn skip_value(kind: &GbfFieldKind, mv: &Box<dyn MemView>, at: &mut u64) -> Result<(), MemViewError> {
+        let endian = Endianness::BigEndian; // always big endian
+        let len = kind.get_len();
+        if len >= 0 {
+            *at += len as u64;
+        } else {
+            // string and bytes are both length prefixed, -1 being null
+            let var_len = mv.read_i32(at, endian)?;
+            if var_len > 0 {
+                *at += var_len as u64;
+            }
+        }
+        Ok(())
+    }
+
     fn default_value(kind: &GbfFieldKind) -> GbfFieldValue {
         match kind {
             GbfFieldKind::Boolean => GbfFieldValue::Boolean(false),
@@ -132,3 +251,78 @@ impl GbfTableSchema {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::memview::StaticMemView;
+
+    #[test]
+    fn column_lookup_ci_and_aliases() {
+        let mut schema = GbfTableSchema::new("Symbols".into(), "Key".into(), GbfFieldKind::Long, None);
+        schema.add_column(GbfFieldKind::String, "Name".into());
+        schema.add_column(GbfFieldKind::Long, "Address".into());
+        schema.add_alias_group(&["Addr", "Address"]);
+
+        assert_eq!(schema.get_column_idx("name"), None);
+        assert_eq!(schema.get_column_idx_ci("name"), Some(0));
+        assert_eq!(schema.get_column_idx_ci("ADDRESS"), Some(1));
+        assert_eq!(schema.get_column_idx_ci("addr"), Some(1));
+        assert_eq!(schema.get_column_idx_ci("Namespace"), None);
+    }
+
+    #[test]
+    fn absent_values_are_null() {
+        let mut sparse_columns = HashSet::new();
+        sparse_columns.insert(2);
+        let mut schema = GbfTableSchema::new("Test".into(), "Key".into(), GbfFieldKind::Long, Some(sparse_columns));
+        schema.add_column(GbfFieldKind::Long, "Address".into());
+        schema.add_column(GbfFieldKind::String, "Name".into());
+        schema.add_column(GbfFieldKind::Int, "Flags".into());
+
+        assert!(!schema.is_nullable(0));
+        assert!(schema.is_nullable(1));
+        assert!(schema.is_nullable(2));
+        assert!(!schema.is_nullable(3));
+
+        // address, null name, no sparse fields, then padding so the string
+        // length check has something past the end
+        let mut data = vec![0, 0, 0, 0, 0, 0, 0x10, 0x00];
+        data.extend_from_slice(&[0xff, 0xff, 0xff, 0xff]);
+        data.push(0);
+        data.extend_from_slice(&[0; 4]);
+        let mv: Box<dyn MemView> = Box::new(StaticMemView::new(data));
+
+        let mut at = 0;
+        let record = schema.read_record(GbfFieldValue::Long(1), &mv, &mut at).unwrap();
+        assert_eq!(record.get_long(0).unwrap(), 0x1000);
+        assert!(!record.is_null(0).unwrap());
+        assert!(record.is_null(1).unwrap());
+        assert!(record.is_null(2).unwrap());
+        assert_eq!(record.get_string(1).unwrap(), "");
+        assert_eq!(record.get_int(2).unwrap(), 0);
+    }
+
+    #[test]
+    fn utf16_strings_survive_reading() {
+        let mut schema = GbfTableSchema::new("Test".into(), "Key".into(), GbfFieldKind::Long, None);
+        schema.add_column(GbfFieldKind::String, "Ascii".into());
+        schema.add_column(GbfFieldKind::String, "Emoji".into());
+
+        // "hi" is valid utf-8 too (with nuls), the surrogate pair isn't
+        let mut data = Vec::new();
+        for text in [&[0x00, 0x68, 0x00, 0x69][..], &[0xd8, 0x3d, 0xde, 0x00]] {
+            data.extend_from_slice(&(text.len() as i32).to_be_bytes());
+            data.extend_from_slice(text);
+        }
+        data.push(0);
+        let mv: Box<dyn MemView> = Box::new(StaticMemView::new(data));
+
+        let mut at = 0;
+        let record = schema.read_record(GbfFieldValue::Long(1), &mv, &mut at).unwrap();
+        assert_eq!(record.get_string_utf16(0).unwrap(), "hi");
+        assert_eq!(record.get_string_utf16(1).unwrap(), "\u{1f600}");
+        assert!(record.get_string(1).is_err());
+        assert_eq!(record.get_bytes(1).unwrap(), vec![0xd8, 0x3d, 0xde, 0x00]);
+    }
+}
@@ -5,18 +5,34 @@ use crate::{
         gbf_long_fixed_node::{GbfLongFixedIterator, GbfLongFixedNode},
         gbf_long_interior_node::GbfLongInteriorNode,
         gbf_long_var_node::{GbfLongVarIterator, GbfLongVarNode},
+        gbf_node_cache::{GbfCachedInteriorNode, GbfNodeCache},
         gbf_node_kind::GbfNodeKind,
-        gbf_record::GbfRecord,
+        gbf_record::{GbfFieldValue, GbfRecord},
         gbf_table_schema::GbfTableSchema,
     },
     memory::memview::MemViewError,
 };
+use std::{cell::RefCell, collections::HashSet, ops::ControlFlow};
+
+// shape of a table's b-tree, from GbfTableView::stats
+#[derive(Debug, Clone, Copy)]
+pub struct GbfTableStats {
+    pub depth: u32, // levels including the leaves, 1 if the root is a leaf
+    pub interior_node_count: u32,
+    pub leaf_node_count: u32,
+    pub record_count: u64,
+    pub avg_fanout: f64, // children per interior node, 0 if there are none
+}
 
 // a table view that reads a specific table
 pub struct GbfTableView<'g, 's> {
     gbf: &'g GbfFile,
     schema: &'s GbfTableSchema,
     root_nid: i32,
+    // interior nodes on recently used root-to-leaf paths. the file is borrowed
+    // immutably for the view's lifetime so nothing can go stale, but anything
+    // that writes to the file later needs to clear this.
+    node_cache: RefCell<GbfNodeCache>,
 }
 
 impl<'g, 's> GbfTableView<'g, 's> {
@@ -26,7 +42,63 @@ impl<'g, 's> GbfTableView<'g, 's> {
         root_nid: i32,
     ) -> Result<GbfTableView<'g, 's>, MemViewError> {
         // should error if root_nid is invalid
-        Ok(GbfTableView { gbf, schema, root_nid })
+        Ok(GbfTableView {
+            gbf,
+            schema,
+            root_nid,
+            node_cache: RefCell::new(GbfNodeCache::new(GbfNodeCache::DEFAULT_CAPACITY)),
+        })
+    }
+
+    // max number of interior nodes to keep around, 0 turns caching off
+    pub fn set_node_cache_size(&self, size: usize) {
+        self.node_cache.borrow_mut().set_capacity(size);
+    }
+
+    pub fn get_node_cache_size(&self) -> usize {
+        self.node_cache.borrow().capacity()
+    }
+
+    pub fn clear_node_cache(&self) {
+        self.node_cache.borrow_mut().clear();
+    }
+
+    // point lookup for the record with exactly this key
+    pub fn get(&self, key: i64) -> Result<Option<GbfRecord>, MemViewError> {
+        self.get_record_at_long(key)
+    }
+
+    // records with keys in [lo, hi], in key order. only the leaves in that
+    // range are read.
+    pub fn range(&'s self, lo: i64, hi: i64) -> Result<GbfTableViewIterator<'g, 's>, MemViewError> {
+        GbfTableViewIterator::new_range(self, lo, hi)
+    }
+
+    // the record with the largest key <= key, e.g. the symbol containing an
+    // address in an address keyed table
+    pub fn floor(&'s self, key: i64) -> Result<Option<GbfRecord>, MemViewError> {
+        GbfTableViewIterator::new_rev(self, key)?.next().transpose()
+    }
+
+    // like get, but only searches the keys and never decodes the record
+    pub fn contains_key(&self, key: i64) -> Result<bool, MemViewError> {
+        let leaf_node_nid = self.get_leaf_node_long(key)?;
+        let node_kind = self.gbf.read_block_kind(leaf_node_nid)?;
+        let search_match = match node_kind {
+            GbfNodeKind::LONGKEY_VAR_REC => {
+                let var_node = GbfLongVarNode::new(self.gbf, leaf_node_nid)?;
+                var_node.find_entry_index_by_key(key)?
+            }
+            GbfNodeKind::LONGKEY_FIXED_REC => {
+                let fixed_node = GbfLongFixedNode::new(self.gbf, leaf_node_nid, self.schema.get_value_len())?;
+                fixed_node.find_entry_index_by_key(key)?
+            }
+            _ => {
+                let err_str = format!("unexpected block id {} while finding record", node_kind);
+                return Err(MemViewError::generic_dynamic(err_str));
+            }
+        };
+        Ok(matches!(search_match, BinarySearchMatch::Found(_)))
     }
 
     pub fn get_record_at_long(&self, key: i64) -> Result<Option<GbfRecord>, MemViewError> {
@@ -124,16 +196,166 @@ impl<'g, 's> GbfTableView<'g, 's> {
         }
     }
 
+    // streaming alternative to GbfTableViewIterator for bulk scans. walks the leaf
+    // nodes directly starting at `key` and stops early once `f` returns Break.
+    pub fn for_each<F>(&self, key: i64, mut f: F) -> Result<(), MemViewError>
+    where
+        F: FnMut(GbfRecord) -> ControlFlow<()>,
+    {
+        let leaf_node_nid = self.get_leaf_node_long(key)?;
+        let node_kind = self.gbf.read_block_kind(leaf_node_nid)?;
+        match node_kind {
+            GbfNodeKind::LONGKEY_VAR_REC => {
+                let mut var_node = GbfLongVarNode::new(self.gbf, leaf_node_nid)?;
+                let mut entry_idx = Self::get_start_index(var_node.find_entry_index_by_key(key)?);
+                loop {
+                    while entry_idx < var_node.entry_count {
+                        let entry_key = var_node.get_key_at(entry_idx)?;
+                        if let Some(record) = var_node.get_entry_by_index(entry_key, entry_idx, self.schema)? {
+                            if f(record).is_break() {
+                                return Ok(());
+                            }
+                        }
+                        entry_idx += 1;
+                    }
+
+                    if var_node.next_leaf_nid == -1 {
+                        return Ok(()); // we've hit the end
+                    }
+
+                    var_node = GbfLongVarNode::new(self.gbf, var_node.next_leaf_nid)?;
+                    entry_idx = 0;
+                }
+            }
+            GbfNodeKind::LONGKEY_FIXED_REC => {
+                let value_len = self.schema.get_value_len();
+                let mut fixed_node = GbfLongFixedNode::new(self.gbf, leaf_node_nid, value_len)?;
+                let mut entry_idx = Self::get_start_index(fixed_node.find_entry_index_by_key(key)?);
+                loop {
+                    while entry_idx < fixed_node.entry_count {
+                        let entry_key = fixed_node.get_key_at(entry_idx)?;
+                        if let Some(record) = fixed_node.get_entry_by_index(entry_key, entry_idx, self.schema)? {
+                            if f(record).is_break() {
+                                return Ok(());
+                            }
+                        }
+                        entry_idx += 1;
+                    }
+
+                    if fixed_node.next_leaf_nid == -1 {
+                        return Ok(()); // we've hit the end
+                    }
+
+                    fixed_node = GbfLongFixedNode::new(self.gbf, fixed_node.next_leaf_nid, value_len)?;
+                    entry_idx = 0;
+                }
+            }
+            _ => {
+                let err_str = format!("unsupported block id {} while iterating records", node_kind);
+                return Err(MemViewError::generic_dynamic(err_str));
+            }
+        }
+    }
+
+    // walks every node in the tree, so this reads the whole index. errors if the
+    // tree isn't one (a node reachable twice, or leaves at different depths).
+    pub fn stats(&self) -> Result<GbfTableStats, MemViewError> {
+        let mut leaf_depth: Option<u32> = None;
+        let mut interior_node_count = 0u32;
+        let mut leaf_node_count = 0u32;
+        let mut child_count = 0u64;
+        let mut record_count = 0u64;
+
+        let mut visited_nids = HashSet::new();
+        let mut pending_nids = vec![(self.root_nid, 1u32)];
+        while let Some((nid, depth)) = pending_nids.pop() {
+            if !visited_nids.insert(nid) {
+                let err_str = format!("node {} is reachable more than once", nid);
+                return Err(MemViewError::generic_dynamic(err_str));
+            }
+
+            let node_kind = self.gbf.read_block_kind(nid)?;
+            let entry_count = match node_kind {
+                GbfNodeKind::LONGKEY_INTERIOR => {
+                    let interior = GbfLongInteriorNode::new(self.gbf, nid)?;
+                    for i in 0..interior.entry_count {
+                        pending_nids.push((interior.get_value_at(i)?, depth + 1));
+                    }
+
+                    interior_node_count += 1;
+                    child_count += std::cmp::max(interior.entry_count, 0) as u64;
+                    continue;
+                }
+                GbfNodeKind::LONGKEY_VAR_REC => GbfLongVarNode::new(self.gbf, nid)?.entry_count,
+                GbfNodeKind::LONGKEY_FIXED_REC => {
+                    GbfLongFixedNode::new(self.gbf, nid, self.schema.get_value_len())?.entry_count
+                }
+                _ => {
+                    let err_str = format!("unexpected block id {} while reading table stats", node_kind);
+                    return Err(MemViewError::generic_dynamic(err_str));
+                }
+            };
+
+            if *leaf_depth.get_or_insert(depth) != depth {
+                let err_str = format!(
+                    "leaf node {} is at depth {} instead of {}",
+                    nid,
+                    depth,
+                    leaf_depth.unwrap()
+                );
+                return Err(MemViewError::generic_dynamic(err_str));
+            }
+
+            leaf_node_count += 1;
+            record_count += std::cmp::max(entry_count, 0) as u64;
+        }
+
+        let avg_fanout = match interior_node_count {
+            0 => 0.0,
+            _ => child_count as f64 / interior_node_count as f64,
+        };
+
+        Ok(GbfTableStats {
+            depth: leaf_depth.unwrap_or(0),
+            interior_node_count,
+            leaf_node_count,
+            record_count,
+            avg_fanout,
+        })
+    }
+
+    fn get_start_index(search_match: BinarySearchMatch) -> i32 {
+        let entry_idx = match search_match {
+            BinarySearchMatch::Found(v) => v,
+            BinarySearchMatch::Missing(v) => v, // start to right of missing key
+        };
+        std::cmp::max(entry_idx, 0)
+    }
+
     fn get_leaf_node_long(&self, key: i64) -> Result<i32, MemViewError> {
         // does not detect getting stuck in infinite loops
         let mut cur_nid = self.root_nid;
         loop {
+            if let Some(cached_node) = self.node_cache.borrow_mut().get(cur_nid) {
+                cur_nid = cached_node.get_entry(key)?;
+                continue;
+            }
+
             let node_kind = self.gbf.read_block_kind(cur_nid)?;
             match node_kind {
                 GbfNodeKind::LONGKEY_INTERIOR => {
                     // all values point to another block
                     let interior = GbfLongInteriorNode::new(self.gbf, cur_nid)?;
-                    cur_nid = interior.get_entry(key)?;
+                    if self.node_cache.borrow().capacity() == 0 {
+                        // copying the whole node only pays off if it's kept
+                        cur_nid = interior.get_entry(key)?;
+                        continue;
+                    }
+
+                    let cached_node = GbfCachedInteriorNode::read(&interior)?;
+                    let next_nid = cached_node.get_entry(key)?;
+                    self.node_cache.borrow_mut().insert(cur_nid, cached_node);
+                    cur_nid = next_nid;
                 }
                 GbfNodeKind::LONGKEY_FIXED_REC | GbfNodeKind::LONGKEY_VAR_REC => {
                     // this is a leaf node, return this index
@@ -158,10 +380,84 @@ enum GbfTableViewIteratorKind<'g, 's> {
 
 pub struct GbfTableViewIterator<'g, 's> {
     iterator: GbfTableViewIteratorKind<'g, 's>,
+    max_key: i64, // inclusive
 }
 
 impl<'g, 's> GbfTableViewIterator<'g, 's> {
     pub fn new(tv: &'s GbfTableView<'g, 's>, key: i64) -> Result<GbfTableViewIterator<'g, 's>, MemViewError> {
+        Self::new_projected(tv, key, None)
+    }
+
+    // stops after the last record with a key <= hi
+    pub fn new_range(
+        tv: &'s GbfTableView<'g, 's>,
+        lo: i64,
+        hi: i64,
+    ) -> Result<GbfTableViewIterator<'g, 's>, MemViewError> {
+        if hi < lo {
+            return Ok(GbfTableViewIterator {
+                iterator: GbfTableViewIteratorKind::EmptyIterator,
+                max_key: hi,
+            });
+        }
+
+        let mut iterator = Self::new_projected(tv, lo, None)?;
+        iterator.max_key = hi;
+        Ok(iterator)
+    }
+
+    // only decodes the given column indices, other columns are left as default values
+    pub fn new_with_columns(
+        tv: &'s GbfTableView<'g, 's>,
+        key: i64,
+        columns: &[usize],
+    ) -> Result<GbfTableViewIterator<'g, 's>, MemViewError> {
+        let projection = tv.schema.make_projection(columns)?;
+        Self::new_projected(tv, key, Some(projection))
+    }
+
+    // records with keys <= key, largest key first
+    pub fn new_rev(tv: &'s GbfTableView<'g, 's>, key: i64) -> Result<GbfTableViewIterator<'g, 's>, MemViewError> {
+        let leaf_node_nid = tv.get_leaf_node_long(key)?;
+        let node_kind = tv.gbf.read_block_kind(leaf_node_nid)?;
+        let iterator = match node_kind {
+            GbfNodeKind::LONGKEY_VAR_REC => {
+                let var_node = GbfLongVarNode::new(tv.gbf, leaf_node_nid)?;
+                let entry_idx = Self::get_rev_start_index(var_node.find_entry_index_by_key(key)?);
+                let var_iterator = GbfLongVarIterator::new_rev(var_node, entry_idx, tv.schema, None);
+                GbfTableViewIteratorKind::LongVarIterator(var_iterator)
+            }
+            GbfNodeKind::LONGKEY_FIXED_REC => {
+                let fixed_node = GbfLongFixedNode::new(tv.gbf, leaf_node_nid, tv.schema.get_value_len())?;
+                let entry_idx = Self::get_rev_start_index(fixed_node.find_entry_index_by_key(key)?);
+                let fixed_iterator = GbfLongFixedIterator::new_rev(fixed_node, entry_idx, tv.schema, None);
+                GbfTableViewIteratorKind::LongFixedIterator(fixed_iterator)
+            }
+            _ => {
+                let err_str = format!("unsupported block id {} while iterating records", node_kind);
+                return Err(MemViewError::generic_dynamic(err_str));
+            }
+        };
+
+        Ok(GbfTableViewIterator {
+            iterator,
+            max_key: i64::MAX,
+        })
+    }
+
+    fn get_rev_start_index(search_match: BinarySearchMatch) -> i32 {
+        match search_match {
+            BinarySearchMatch::Found(v) => v,
+            // start to left of missing key. -1 moves on to the previous leaf
+            BinarySearchMatch::Missing(v) => v - 1,
+        }
+    }
+
+    fn new_projected(
+        tv: &'s GbfTableView<'g, 's>,
+        key: i64,
+        projection: Option<Vec<bool>>,
+    ) -> Result<GbfTableViewIterator<'g, 's>, MemViewError> {
         let leaf_node_nid = tv.get_leaf_node_long(key)?;
         let node_kind = tv.gbf.read_block_kind(leaf_node_nid)?;
         let iterator: GbfTableViewIteratorKind;
@@ -177,7 +473,7 @@ impl<'g, 's> GbfTableViewIterator<'g, 's> {
                         entry_idx = 0;
                     }
 
-                    let var_iterator = GbfLongVarIterator::new(var_node, entry_idx, tv.schema);
+                    let var_iterator = GbfLongVarIterator::new(var_node, entry_idx, tv.schema, projection);
                     iterator = GbfTableViewIteratorKind::LongVarIterator(var_iterator);
                 } else {
                     iterator = GbfTableViewIteratorKind::EmptyIterator;
@@ -194,7 +490,7 @@ impl<'g, 's> GbfTableViewIterator<'g, 's> {
                         entry_idx = 0;
                     }
 
-                    let fixed_iterator = GbfLongFixedIterator::new(fixed_node, entry_idx, tv.schema);
+                    let fixed_iterator = GbfLongFixedIterator::new(fixed_node, entry_idx, tv.schema, projection);
                     iterator = GbfTableViewIteratorKind::LongFixedIterator(fixed_iterator);
                 } else {
                     iterator = GbfTableViewIteratorKind::EmptyIterator;
@@ -206,7 +502,10 @@ impl<'g, 's> GbfTableViewIterator<'g, 's> {
             }
         }
 
-        Ok(GbfTableViewIterator { iterator })
+        Ok(GbfTableViewIterator {
+            iterator,
+            max_key: i64::MAX,
+        })
     }
 }
 
@@ -214,10 +513,344 @@ impl<'g, 's> Iterator for GbfTableViewIterator<'g, 's> {
     type Item = Result<GbfRecord, MemViewError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.iterator {
+        let item = match self.iterator {
             GbfTableViewIteratorKind::EmptyIterator => None,
             GbfTableViewIteratorKind::LongVarIterator(ref mut i) => i.next(),
             GbfTableViewIteratorKind::LongFixedIterator(ref mut i) => i.next(),
+        };
+
+        if let Some(Ok(GbfRecord {
+            key: GbfFieldValue::Long(key),
+            ..
+        })) = item
+        {
+            if key > self.max_key {
+                self.iterator = GbfTableViewIteratorKind::EmptyIterator;
+                return None;
+            }
+        }
+        item
+    }
+}
+
+// the table view tests' database, for ffi tests
+#[cfg(test)]
+pub(crate) use tests::{make_gbf, make_schema};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::gbf_record::GbfFieldKind,
+        memory::memview::{MemView, StaticMemView},
+    };
+
+    const BLOCK_SIZE: usize = 0x100;
+
+    fn put(data: &mut [u8], at: &mut usize, bytes: &[u8]) {
+        data[*at..*at + bytes.len()].copy_from_slice(bytes);
+        *at += bytes.len();
+    }
+
+    fn buffer_start(block_size: usize, nid: usize) -> usize {
+        (nid + 1) * block_size + GbfFile::BLOCK_PREFIX_SIZE as usize
+    }
+
+    fn put_fixed_leaf(data: &mut [u8], at: &mut usize, prev: i32, next: i32, entries: &[(i64, i32)]) {
+        put(data, at, &[GbfNodeKind::LONGKEY_FIXED_REC]);
+        put(data, at, &(entries.len() as i32).to_be_bytes());
+        put(data, at, &prev.to_be_bytes());
+        put(data, at, &next.to_be_bytes());
+        for (key, value) in entries {
+            put(data, at, &key.to_be_bytes());
+            put(data, at, &value.to_be_bytes());
         }
     }
+
+    // a three record leaf and a one record leaf
+    pub(crate) fn make_gbf() -> GbfFile {
+        make_gbf_with_leaves(&[(10, 100), (20, 200), (30, 300)], &[(40, 400)])
+    }
+
+    fn make_gbf_with_leaves(left: &[(i64, i32)], right: &[(i64, i32)]) -> GbfFile {
+        make_gbf_with_split(left, right[0].0, right)
+    }
+
+    // the table under test has an interior root (4) over the left (2) and
+    // right (3) leaf. `split` is the interior key of the right leaf, which can
+    // be below its first key
+    fn make_gbf_with_split(left: &[(i64, i32)], split: i64, right: &[(i64, i32)]) -> GbfFile {
+        make_gbf_with_tree(BLOCK_SIZE, &[left, right], &[left[0].0, split])
+    }
+
+    // block 0 is the db parms, block 1 an empty master table, then one block
+    // per leaf and an interior root over all of them with `splits` as its keys
+    fn make_gbf_with_tree(block_size: usize, leaves: &[&[(i64, i32)]], splits: &[i64]) -> GbfFile {
+        let root_nid = 2 + leaves.len();
+        let mut data = vec![0u8; block_size * (root_nid + 2)];
+        let at = &mut 0;
+        put(&mut data, at, &0x1234u64.to_be_bytes());
+        put(&mut data, at, &0i64.to_be_bytes());
+        put(&mut data, at, &1i32.to_be_bytes());
+        put(&mut data, at, &(block_size as i32).to_be_bytes());
+        put(&mut data, at, &(-1i32).to_be_bytes());
+
+        let at = &mut buffer_start(block_size, 0);
+        put(&mut data, at, &[GbfNodeKind::CHAINED_BUFFER_DATA]);
+        put(&mut data, at, &13i32.to_be_bytes());
+        put(&mut data, at, &[1]);
+        for value in [1i32, 0, 0] {
+            put(&mut data, at, &value.to_be_bytes());
+        }
+
+        let at = &mut buffer_start(block_size, 1);
+        put(&mut data, at, &[GbfNodeKind::LONGKEY_VAR_REC]);
+        for value in [0i32, -1, -1] {
+            put(&mut data, at, &value.to_be_bytes());
+        }
+
+        for (i, entries) in leaves.iter().enumerate() {
+            let nid = 2 + i;
+            let prev = if i == 0 { -1 } else { nid as i32 - 1 };
+            let next = if i + 1 == leaves.len() { -1 } else { nid as i32 + 1 };
+            put_fixed_leaf(&mut data, &mut buffer_start(block_size, nid), prev, next, entries);
+        }
+
+        let at = &mut buffer_start(block_size, root_nid);
+        put(&mut data, at, &[GbfNodeKind::LONGKEY_INTERIOR]);
+        put(&mut data, at, &(leaves.len() as i32).to_be_bytes());
+        for (i, split) in splits.iter().enumerate() {
+            put(&mut data, at, &split.to_be_bytes());
+            put(&mut data, at, &(2 + i as i32).to_be_bytes());
+        }
+
+        let mv: Box<dyn MemView> = Box::new(StaticMemView::new(data));
+        GbfFile::new(mv, &mut 0).unwrap()
+    }
+
+    pub(crate) fn make_schema() -> GbfTableSchema {
+        let mut schema = GbfTableSchema::new("Test".into(), "Key".into(), GbfFieldKind::Long, None);
+        schema.add_column(GbfFieldKind::Int, "Value".into());
+        schema
+    }
+
+    fn record_key(record: &GbfRecord) -> i64 {
+        match record.key {
+            GbfFieldValue::Long(v) => v,
+            _ => panic!("not a long key"),
+        }
+    }
+
+    #[test]
+    fn get_matches_iteration() {
+        let gbf = make_gbf();
+        let schema = make_schema();
+        let tv = GbfTableView::new(&gbf, &schema, 4).unwrap();
+
+        let all: Vec<GbfRecord> = GbfTableViewIterator::new(&tv, i64::MIN)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let all_keys: Vec<i64> = all.iter().map(record_key).collect();
+        assert_eq!(all_keys, vec![10, 20, 30, 40]);
+
+        for key in 0..50 {
+            let expected = all.iter().find(|r| record_key(r) == key);
+            let got = tv.get(key).unwrap();
+            assert_eq!(got.is_some(), expected.is_some(), "key {}", key);
+            if let (Some(got), Some(expected)) = (got, expected) {
+                assert_eq!(got.get_int(0).unwrap(), expected.get_int(0).unwrap());
+            }
+            assert_eq!(tv.contains_key(key).unwrap(), expected.is_some(), "key {}", key);
+        }
+    }
+
+    #[test]
+    fn range_is_bounded_above() {
+        let gbf = make_gbf();
+        let schema = make_schema();
+        let tv = GbfTableView::new(&gbf, &schema, 4).unwrap();
+        let range_keys = |lo, hi| -> Vec<i64> { tv.range(lo, hi).unwrap().map(|r| record_key(&r.unwrap())).collect() };
+
+        assert_eq!(range_keys(15, 40), vec![20, 30, 40]);
+        assert_eq!(range_keys(10, 10), vec![10]);
+        assert_eq!(range_keys(31, 39), Vec::<i64>::new());
+        assert_eq!(range_keys(35, i64::MAX), vec![40]);
+        assert_eq!(range_keys(i64::MIN, 25), vec![10, 20]);
+        assert_eq!(range_keys(30, 20), Vec::<i64>::new());
+    }
+
+    #[test]
+    fn reverse_iteration_crosses_leaves() {
+        let gbf = make_gbf();
+        let schema = make_schema();
+        let tv = GbfTableView::new(&gbf, &schema, 4).unwrap();
+        let rev_keys = |key| -> Vec<i64> {
+            GbfTableViewIterator::new_rev(&tv, key)
+                .unwrap()
+                .map(|r| record_key(&r.unwrap()))
+                .collect()
+        };
+
+        assert_eq!(rev_keys(i64::MAX), vec![40, 30, 20, 10]);
+        assert_eq!(rev_keys(40), vec![40, 30, 20, 10]);
+        assert_eq!(rev_keys(39), vec![30, 20, 10]);
+        assert_eq!(rev_keys(10), vec![10]);
+        assert_eq!(rev_keys(9), Vec::<i64>::new());
+    }
+
+    #[test]
+    fn floor_finds_containing_symbol() {
+        // one symbol per leaf so the lookup has to cross back into leaf 2
+        let gbf = make_gbf_with_leaves(&[(0x1000, 1)], &[(0x2000, 2)]);
+        let schema = make_schema();
+        let tv = GbfTableView::new(&gbf, &schema, 4).unwrap();
+
+        let floor_key = |key| tv.floor(key).unwrap().map(|r| record_key(&r));
+        assert_eq!(floor_key(0x1500), Some(0x1000));
+        assert_eq!(tv.floor(0x1500).unwrap().unwrap().get_int(0).unwrap(), 1);
+        assert_eq!(floor_key(0x1000), Some(0x1000));
+        assert_eq!(floor_key(0x1fff), Some(0x1000));
+        assert_eq!(floor_key(0x2000), Some(0x2000));
+        assert_eq!(floor_key(u32::MAX as i64), Some(0x2000));
+        assert_eq!(floor_key(0xfff), None);
+    }
+
+    #[test]
+    fn floor_below_right_leaf_first_key() {
+        // keys in 0x1800..0x2000 go to leaf 3 but sort before its first record,
+        // so floor has to fall back to the end of leaf 2
+        let gbf = make_gbf_with_split(&[(0x1000, 1)], 0x1800, &[(0x2000, 2)]);
+        let schema = make_schema();
+        let tv = GbfTableView::new(&gbf, &schema, 4).unwrap();
+
+        let floor_key = |key| tv.floor(key).unwrap().map(|r| record_key(&r));
+        assert_eq!(floor_key(0x1800), Some(0x1000));
+        assert_eq!(floor_key(0x1fff), Some(0x1000));
+        assert_eq!(tv.floor(0x1900).unwrap().unwrap().get_int(0).unwrap(), 1);
+        assert_eq!(floor_key(0x2000), Some(0x2000));
+        assert_eq!(floor_key(0x17ff), Some(0x1000));
+    }
+
+    #[test]
+    fn get_and_contains_key_at_leaf_edges() {
+        // the right leaf's interior key is below its first key
+        let gbf = make_gbf_with_split(&[(0x1000, 1), (0x1100, 2)], 0x1800, &[(0x2000, 3)]);
+        let schema = make_schema();
+        let tv = GbfTableView::new(&gbf, &schema, 4).unwrap();
+
+        let value = |key| tv.get(key).unwrap().map(|r| r.get_int(0).unwrap());
+        for (key, expected) in [
+            (i64::MIN, None),
+            (0xfff, None),
+            (0x1000, Some(1)),
+            (0x1001, None),
+            (0x1100, Some(2)),
+            (0x1800, None),
+            (0x1fff, None),
+            (0x2000, Some(3)),
+            (i64::MAX, None),
+        ] {
+            assert_eq!(value(key), expected, "key {:#x}", key);
+            assert_eq!(tv.contains_key(key).unwrap(), expected.is_some(), "key {:#x}", key);
+        }
+    }
+
+    #[test]
+    fn stats_counts_nodes_and_records() {
+        let leaves: [&[(i64, i32)]; 3] = [&[(10, 1), (20, 2)], &[(30, 3)], &[(40, 4), (50, 5), (60, 6)]];
+        let gbf = make_gbf_with_tree(BLOCK_SIZE, &leaves, &[10, 30, 40]);
+        let schema = make_schema();
+
+        let stats = GbfTableView::new(&gbf, &schema, 5).unwrap().stats().unwrap();
+        assert_eq!(stats.depth, 2);
+        assert_eq!(stats.interior_node_count, 1);
+        assert_eq!(stats.leaf_node_count, 3);
+        assert_eq!(stats.record_count, 6);
+        assert_eq!(stats.avg_fanout, 3.0);
+
+        // a root that's a leaf on its own
+        let stats = GbfTableView::new(&gbf, &schema, 4).unwrap().stats().unwrap();
+        assert_eq!(stats.depth, 1);
+        assert_eq!(stats.interior_node_count, 0);
+        assert_eq!(stats.leaf_node_count, 1);
+        assert_eq!(stats.record_count, 3);
+        assert_eq!(stats.avg_fanout, 0.0);
+
+        // block 0 is the db parms, not a node
+        assert!(GbfTableView::new(&gbf, &schema, 0).unwrap().stats().is_err());
+    }
+
+    #[test]
+    fn cached_leaf_lookups_match_uncached() {
+        let leaves: [&[(i64, i32)]; 3] = [&[(10, 1), (20, 2)], &[(30, 3)], &[(40, 4), (50, 5), (60, 6)]];
+        let gbf = make_gbf_with_tree(BLOCK_SIZE, &leaves, &[10, 30, 40]);
+        let schema = make_schema();
+
+        // straight through the nodes in the file, no cache involved
+        let uncached_leaf = |key| {
+            let interior = GbfLongInteriorNode::new(&gbf, 5).unwrap();
+            interior.get_entry(key).unwrap()
+        };
+
+        for capacity in [0, 1, GbfNodeCache::DEFAULT_CAPACITY] {
+            let tv = GbfTableView::new(&gbf, &schema, 5).unwrap();
+            tv.set_node_cache_size(capacity);
+            // the first pass misses (or never caches), the second hits
+            for _ in 0..2 {
+                for key in [i64::MIN, 0, 10, 29, 30, 35, 40, 60, i64::MAX] {
+                    assert_eq!(tv.get_leaf_node_long(key).unwrap(), uncached_leaf(key), "key {}", key);
+                }
+            }
+            assert_eq!(tv.node_cache.borrow().len(), capacity.min(1));
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn for_each_benchmark() {
+        use std::time::Instant;
+
+        // big enough blocks that one root can sit over every leaf
+        const BIG_BLOCK_SIZE: usize = 0x4000;
+        const LEAF_COUNT: usize = 1000;
+        const PER_LEAF: usize = 1000;
+
+        let leaves: Vec<Vec<(i64, i32)>> = (0..LEAF_COUNT)
+            .map(|l| {
+                (0..PER_LEAF)
+                    .map(|e| ((l * PER_LEAF + e) as i64 * 4, e as i32))
+                    .collect()
+            })
+            .collect();
+        let leaf_refs: Vec<&[(i64, i32)]> = leaves.iter().map(|v| v.as_slice()).collect();
+        let splits: Vec<i64> = leaves.iter().map(|v| v[0].0).collect();
+        let gbf = make_gbf_with_tree(BIG_BLOCK_SIZE, &leaf_refs, &splits);
+        let schema = make_schema();
+        let tv = GbfTableView::new(&gbf, &schema, 2 + LEAF_COUNT as i32).unwrap();
+
+        let start = Instant::now();
+        let mut iter_sum = 0i64;
+        for record in GbfTableViewIterator::new(&tv, i64::MIN).unwrap() {
+            iter_sum += record.unwrap().get_int(0).unwrap() as i64;
+        }
+        let iter_elapsed = start.elapsed();
+
+        let start = Instant::now();
+        let mut for_each_sum = 0i64;
+        tv.for_each(i64::MIN, |record| {
+            for_each_sum += record.get_int(0).unwrap() as i64;
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+        let for_each_elapsed = start.elapsed();
+
+        assert_eq!(iter_sum, for_each_sum);
+        println!(
+            "{} records: iterator {:?}, for_each {:?}",
+            LEAF_COUNT * PER_LEAF,
+            iter_elapsed,
+            for_each_elapsed
+        );
+    }
 }
@@ -1,7 +1,12 @@
+pub mod address_expr;
 pub mod breakpoint;
 pub mod chunked_free_memview;
 pub mod debugger;
 pub mod fast_util;
 pub mod host_debugger_infos;
 pub mod host_debuggers;
+#[cfg(test)]
+pub mod mock_debugger;
+pub mod module_symbols;
 pub mod registers;
+pub mod trace_log;
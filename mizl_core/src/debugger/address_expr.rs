@@ -0,0 +1,254 @@
+use super::{
+    debugger::{Debugger, DebuggerError, DebuggerHelper, DebuggerThreadIndex},
+    fast_util::read_swap_bytes,
+    module_symbols::ModuleSymbolResolver,
+};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AddressExprError {
+    // a character or token that doesn't belong, or a missing operand or ]
+    Syntax,
+    // only one level of [] is supported
+    NestedDeref,
+    // not a number, register or symbol
+    UnknownName(String),
+    Debugger(DebuggerError),
+}
+
+impl fmt::Display for AddressExprError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AddressExprError::Syntax => write!(f, "couldn't parse the address"),
+            AddressExprError::NestedDeref => write!(f, "[] can't be nested"),
+            AddressExprError::UnknownName(name) => write!(f, "{} isn't a register or symbol", name),
+            AddressExprError::Debugger(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+// evaluates addresses typed in by the user, like `401000`, `rip+0x10`,
+// `main` or `[rsp]+8`. numbers are hex with or without 0x, registers are
+// matched ignoring case, symbols come from `symbols` if there are any and
+// [x] reads a pointer at x. a symbol that looks like hex (like `add`) wins
+// over the number, use 0x to get the number instead.
+pub fn evaluate_address<DBG>(
+    debugger: &DBG,
+    symbols: Option<&ModuleSymbolResolver>,
+    text: &str,
+) -> Result<u64, AddressExprError>
+where
+    DBG: Debugger + ?Sized,
+{
+    let text = text.trim();
+    let mut parser = AddressExprParser {
+        debugger,
+        symbols,
+        text: text.as_bytes(),
+        pos: 0,
+        in_deref: false,
+    };
+    let value = parser.parse_sum()?;
+    parser.skip_spaces();
+    if parser.pos != parser.text.len() {
+        return Err(AddressExprError::Syntax);
+    }
+    Ok(value)
+}
+
+struct AddressExprParser<'a, DBG: Debugger + ?Sized> {
+    debugger: &'a DBG,
+    symbols: Option<&'a ModuleSymbolResolver>,
+    text: &'a [u8],
+    pos: usize,
+    in_deref: bool,
+}
+
+impl<DBG: Debugger + ?Sized> AddressExprParser<'_, DBG> {
+    fn skip_spaces(&mut self) {
+        while self.pos < self.text.len() && self.text[self.pos] == b' ' {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_spaces();
+        self.text.get(self.pos).copied()
+    }
+
+    // term (+|- term)*, wrapping like the cpu would
+    fn parse_sum(&mut self) -> Result<u64, AddressExprError> {
+        let mut value = if self.peek() == Some(b'-') {
+            self.pos += 1;
+            self.parse_term()?.wrapping_neg()
+        } else {
+            self.parse_term()?
+        };
+
+        loop {
+            match self.peek() {
+                Some(b'+') => {
+                    self.pos += 1;
+                    value = value.wrapping_add(self.parse_term()?);
+                }
+                Some(b'-') => {
+                    self.pos += 1;
+                    value = value.wrapping_sub(self.parse_term()?);
+                }
+                _ => return Ok(value),
+            }
+        }
+    }
+
+    fn parse_term(&mut self) -> Result<u64, AddressExprError> {
+        if self.peek() != Some(b'[') {
+            return self.parse_atom();
+        }
+        if self.in_deref {
+            return Err(AddressExprError::NestedDeref);
+        }
+
+        self.pos += 1;
+        self.in_deref = true;
+        let addr = self.parse_sum()?;
+        self.in_deref = false;
+        if self.peek() != Some(b']') {
+            return Err(AddressExprError::Syntax);
+        }
+        self.pos += 1;
+        self.read_pointer(addr)
+    }
+
+    fn parse_atom(&mut self) -> Result<u64, AddressExprError> {
+        self.skip_spaces();
+        let start = self.pos;
+        // :: and . show up in demangled and versioned symbol names
+        while self.pos < self.text.len()
+            && (self.text[self.pos].is_ascii_alphanumeric() || b"_.:@$".contains(&self.text[self.pos]))
+        {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(AddressExprError::Syntax);
+        }
+
+        // only ascii was consumed, so this is still valid utf-8
+        let token = std::str::from_utf8(&self.text[start..self.pos]).or(Err(AddressExprError::Syntax))?;
+        if let Some(digits) = token.strip_prefix("0x") {
+            return u64::from_str_radix(digits, 16).or(Err(AddressExprError::Syntax));
+        }
+
+        // hex-looking names never go to the debugger, so plain numbers still
+        // work with nothing running
+        let symbol = self.symbols.and_then(|s| s.find_symbol(token));
+        if let Ok(v) = u64::from_str_radix(token, 16) {
+            return Ok(symbol.unwrap_or(v));
+        }
+        if let Some(v) = self.read_register(token)? {
+            return Ok(v);
+        }
+        symbol.ok_or_else(|| AddressExprError::UnknownName(token.to_string()))
+    }
+
+    // None if there's no register with that name
+    fn read_register(&self, name: &str) -> Result<Option<u64>, AddressExprError> {
+        let thread_idx = DebuggerThreadIndex::Current;
+        // register infos only list full registers, sub-registers like eax are
+        // tried with the name as typed
+        let reg_name = self
+            .debugger
+            .get_register_infos(thread_idx)
+            .iter()
+            .find(|r| r.name.eq_ignore_ascii_case(name))
+            .map(|r| r.name.clone())
+            .unwrap_or_else(|| name.to_string());
+
+        match self.debugger.read_register_by_name::<u64>(thread_idx, &reg_name) {
+            Ok(v) => Ok(Some(v)),
+            Err(DebuggerError::InvalidRegister) => Ok(None),
+            Err(e) => Err(AddressExprError::Debugger(e)),
+        }
+    }
+
+    fn read_pointer(&self, addr: u64) -> Result<u64, AddressExprError> {
+        let big_endian = self.debugger.is_big_endian();
+        let mut data = vec![0u8; self.debugger.get_pointer_size() as usize];
+        self.debugger
+            .read_bytes(DebuggerThreadIndex::Current, addr, &mut data)
+            .map_err(AddressExprError::Debugger)?;
+        match data.len() {
+            4 => Ok(read_swap_bytes::<u32>(&data, big_endian) as u64),
+            8 => Ok(read_swap_bytes::<u64>(&data, big_endian)),
+            _ => Err(AddressExprError::Debugger(DebuggerError::InternalError)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::debugger::{
+        host_debuggers::debugger_linux_modules::LoadedModule,
+        mock_debugger::{MockDebugger, MockStepMode, mock_x86_64},
+        module_symbols::SymbolMap,
+    };
+
+    fn make_debugger() -> MockDebugger {
+        let mut mem = vec![0u8; 0x100];
+        mem[0xf0..0xf8].copy_from_slice(&0x1234u64.to_le_bytes());
+        let dbg = mock_x86_64(mem, MockStepMode::Fixed(4));
+        dbg.set_register("RIP", 0x40).unwrap();
+        dbg.set_register("RSP", 0xf0).unwrap();
+        dbg
+    }
+
+    #[test]
+    fn evaluates_numbers_registers_and_derefs() {
+        let dbg = make_debugger();
+        let eval = |text| evaluate_address(&dbg, None, text);
+
+        assert_eq!(eval("401000"), Ok(0x401000));
+        assert_eq!(eval("rip+0x10"), Ok(0x50));
+        assert_eq!(eval(" RIP - 8 "), Ok(0x38));
+        assert_eq!(eval("[rsp]"), Ok(0x1234));
+        assert_eq!(eval("[rsp + 0] + 4"), Ok(0x1238));
+        assert_eq!(eval("-1"), Ok(u64::MAX));
+
+        assert_eq!(eval("[[rsp]]"), Err(AddressExprError::NestedDeref));
+        assert_eq!(eval("[rsp"), Err(AddressExprError::Syntax));
+        assert_eq!(eval("rip+"), Err(AddressExprError::Syntax));
+        assert_eq!(eval("main"), Err(AddressExprError::UnknownName("main".to_string())));
+    }
+
+    #[test]
+    fn evaluates_symbols() {
+        let dbg = make_debugger();
+        let mut symbol_map = SymbolMap::new();
+        symbol_map.add(0x1100, 0x40, "main".to_string());
+        let mut resolver = ModuleSymbolResolver::new();
+        let module = LoadedModule::new("/bin/ls".to_string(), 0x400000, 0x2000);
+        resolver.add_module(module, 0x1000, symbol_map);
+
+        assert_eq!(evaluate_address(&dbg, Some(&resolver), "main+4"), Ok(0x400104));
+    }
+
+    #[test]
+    fn symbols_that_look_like_hex_win() {
+        let dbg = make_debugger();
+        let mut symbol_map = SymbolMap::new();
+        symbol_map.add(0x1100, 0x40, "add".to_string());
+        symbol_map.add(0x1200, 0x40, "cafe".to_string());
+        let mut resolver = ModuleSymbolResolver::new();
+        let module = LoadedModule::new("/bin/ls".to_string(), 0x400000, 0x2000);
+        resolver.add_module(module, 0x1000, symbol_map);
+        let eval = |text| evaluate_address(&dbg, Some(&resolver), text);
+
+        assert_eq!(eval("add"), Ok(0x400100));
+        assert_eq!(eval("cafe+4"), Ok(0x400204));
+        assert_eq!(eval("[rsp] - add"), Ok(0x1234u64.wrapping_sub(0x400100)));
+        assert_eq!(eval("0xadd"), Ok(0xadd));
+        assert_eq!(eval("0xcafe"), Ok(0xcafe));
+        assert_eq!(eval("beef"), Ok(0xbeef));
+        assert_eq!(eval("0xnope"), Err(AddressExprError::Syntax));
+    }
+}
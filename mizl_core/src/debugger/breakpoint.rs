@@ -9,6 +9,7 @@ pub enum BreakpointKind {
 
 #[derive(Clone)]
 pub struct BreakpointEntry {
+    pub id: u32, // assigned by the container
     pub addr: u64,
     _enabled: bool,
     _bp_kind: BreakpointKind,
@@ -38,6 +39,7 @@ where
 impl BreakpointEntry {
     pub fn new(addr: u64, bp_bytes: Vec<u8>, orig_bytes: Vec<u8>) -> BreakpointEntry {
         BreakpointEntry {
+            id: 0,
             addr,
             _enabled: true,
             _bp_kind: BreakpointKind::Normal,
@@ -56,7 +58,8 @@ impl BreakpointContainer {
         }
     }
 
-    pub fn add_breakpoint(&mut self, entry: BreakpointEntry) -> u32 {
+    pub fn add_breakpoint(&mut self, mut entry: BreakpointEntry) -> u32 {
+        entry.id = self.bp_id;
         let addr = entry.addr;
         let insert_idx = match self.bps_sorted.binary_search_by(|e| e.addr.cmp(&addr)) {
             Ok(i) => i,
@@ -70,6 +73,11 @@ impl BreakpointContainer {
         self.bp_id - 1
     }
 
+    // in address order
+    pub fn iter(&self) -> impl Iterator<Item = &BreakpointEntry> {
+        self.bps_sorted.iter()
+    }
+
     pub fn get_breakpoint(&self, start: u64) -> Option<&BreakpointEntry> {
         match self.bps_sorted.binary_search_by(|e| e.addr.cmp(&start)) {
             Ok(i) => Some(&self.bps_sorted[i]),
@@ -80,22 +88,23 @@ impl BreakpointContainer {
     // todo: opto this somehow
     // we do a lot of short reads so this will be a little bad...
     pub fn fixup_bp_memory(&self, data: &mut [u8], data_addr: u64) {
-        // let mem_len = data.len();
-        // let mem_start = data_addr;
-        // let mem_end = mem_start + data.len() as u64;
-        // let (bp_start_idx, bp_end_idx) = Self::find_bps_in_range(&self, mem_start, mem_end);
-        // for bp in &self.bps_sorted[bp_start_idx..bp_end_idx] {
-        //     let bp_mem_len = bp.bp_bytes.len();
-        //     let bp_mem_start: isize = bp.addr.wrapping_sub(data_addr) as usize as isize;
-        //     let (src_start, dst_start) = if bp_mem_start < 0 {
-        //         ((-bp_mem_start) as usize, 0)
-        //     } else {
-        //         (0, bp_mem_start as usize)
-        //     };
-
-        //     let count = (bp_mem_len - src_start).min(mem_len - dst_start);
-        //     data[dst_start..dst_start + count].copy_from_slice(&bp.orig_bytes[src_start..src_start + count]);
-        // }
+        let data_end = data_addr.saturating_add(data.len() as u64);
+
+        // breakpoints never overlap, so their ends are sorted too
+        let start_idx = self
+            .bps_sorted
+            .partition_point(|e| e.addr + e.bp_bytes.len() as u64 <= data_addr);
+        for bp in &self.bps_sorted[start_idx..] {
+            if bp.addr >= data_end {
+                break;
+            }
+
+            let bp_end = bp.addr + bp.orig_bytes.len() as u64;
+            let copy_start = std::cmp::max(bp.addr, data_addr);
+            let copy_end = std::cmp::min(bp_end, data_end);
+            let src = &bp.orig_bytes[(copy_start - bp.addr) as usize..(copy_end - bp.addr) as usize];
+            data[(copy_start - data_addr) as usize..(copy_end - data_addr) as usize].copy_from_slice(src);
+        }
     }
 
     // todo: check this for correctness
@@ -168,3 +177,29 @@ where
         self.mem_view.can_write_while_running()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixup_restores_overlapping_bytes() {
+        let mut bp_cont = BreakpointContainer::new();
+        bp_cont.add_breakpoint(BreakpointEntry::new(0x1002, vec![0xcc], vec![0x11]));
+        bp_cont.add_breakpoint(BreakpointEntry::new(0x1005, vec![0xcc, 0xcc], vec![0x22, 0x33]));
+        bp_cont.add_breakpoint(BreakpointEntry::new(0x2000, vec![0xcc], vec![0x44]));
+
+        let mut data = [0xcc; 6];
+        bp_cont.fixup_bp_memory(&mut data, 0x1001);
+        assert_eq!(data, [0xcc, 0x11, 0xcc, 0xcc, 0x22, 0x33]);
+
+        // only the second half of the two byte breakpoint is in range
+        let mut data = [0xcc; 2];
+        bp_cont.fixup_bp_memory(&mut data, 0x1006);
+        assert_eq!(data, [0x33, 0xcc]);
+
+        let mut data = [0xcc; 2];
+        bp_cont.fixup_bp_memory(&mut data, 0x1010);
+        assert_eq!(data, [0xcc, 0xcc]);
+    }
+}
@@ -1,10 +1,10 @@
 use super::{fast_util::read_swap_bytes, registers::registers::RegisterInfo};
 use crate::ffi::core_framework::prelude::*;
-use crate::sleigh::disasm::DisasmDispInstruction;
+use crate::sleigh::disasm::{DisasmDispInstruction, DisasmError};
 use bitflags::bitflags;
 use std::fmt;
 
-#[derive(Debug, ToPrimitive, Clone, Copy)]
+#[derive(Debug, ToPrimitive, Clone, Copy, PartialEq)]
 pub enum DebuggerError {
     InvalidArguments = 0,
     ForkFailed = 1,
@@ -17,9 +17,16 @@ pub enum DebuggerError {
     InvalidThread = 8,
     InvalidBreakpoint = 9,
     NoThreads = 10,
+    Busy = 11,
+    MemoryNotWritable = 12,
+    NoFreeWatchpoints = 13,
+    NoFrame = 14,
+    InvalidSpec = 15,
+    AttachFailed = 16,
+    UnsupportedInstruction = 17,
 }
 
-#[derive(Debug, ToPrimitive, Clone, Copy, PartialEq)]
+#[derive(Debug, FromPrimitive, ToPrimitive, Clone, Copy, PartialEq)]
 pub enum DebuggerEventKind {
     Failed = 0,
     NoEvent = 1,
@@ -31,21 +38,51 @@ pub enum DebuggerEventKind {
     ThreadSpawned = 7,
     ThreadKilled = 8,
     UserEvent = 9,
+    Interrupted = 10,
+    Exec = 11,
+    WatchpointHit = 12,
+    ProcessExited = 13,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DebuggerWatchpointKind {
+    Execute = 0,
+    Write = 1,
+    ReadWrite = 2, // x86 can't watch only reads
 }
 
 bitflags! {
-    #[derive(Default)]
+    #[derive(Default, Clone, Copy)]
     pub struct DebuggerFlags: u32 {
         const NonStop = 1 << 0;
+
+        // the rest are ptrace options on linux, set on every thread when the
+        // debugger runs or attaches to a process.
+
+        // new threads are traced automatically and reported as ThreadSpawned
+        const TraceClone = 1 << 1;
+        // exec is reported as Exec instead of a plain SIGTRAP
+        const TraceExec = 1 << 2;
+        // the process is killed if the debugger exits without detaching
+        const ExitKill = 1 << 3;
+        // syscall stops have bit 0x80 set in their signal so they can't be
+        // mistaken for a breakpoint's SIGTRAP
+        const TraceSysGood = 1 << 4;
     }
 }
 
 #[derive(FfiSerialize)]
+#[ffi_serialize(deserialize)]
 pub struct DebuggerEvent {
     #[ffi_serialize_enum]
     pub kind: DebuggerEventKind,
-    pub code: u32, // native event code
-    pub pid: u32,  // native pid
+    // native event code. for ProcessExited it's the exit status, or the
+    // signal that killed the process with PROCESS_KILLED_BY_SIGNAL set.
+    pub code: u32,
+    pub pid: u32,    // native pid
+    pub bp_idx: u32, // breakpoint (or watchpoint slot) that was hit, u32::MAX if neither
+    pub bp_addr: u64,
+    pub image_path: String, // new executable for exec events, empty otherwise
 }
 
 #[derive(Clone, Copy)]
@@ -54,6 +91,27 @@ pub enum DebuggerThreadIndex {
     Specific(u32),
 }
 
+impl DebuggerThreadIndex {
+    // the ffi passes thread indices as an i32 where any negative value (-1 by
+    // convention) means the current thread
+    pub fn from_ffi(thread_idx: i32) -> DebuggerThreadIndex {
+        if thread_idx < 0 {
+            DebuggerThreadIndex::Current
+        } else {
+            DebuggerThreadIndex::Specific(thread_idx as u32)
+        }
+    }
+
+    // None if the index is too big for an i32, since it would come back
+    // negative and turn into the current thread
+    pub fn to_ffi(&self) -> Option<i32> {
+        match self {
+            DebuggerThreadIndex::Current => Some(-1),
+            DebuggerThreadIndex::Specific(v) => i32::try_from(*v).ok(),
+        }
+    }
+}
+
 impl fmt::Display for DebuggerError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -68,6 +126,33 @@ impl fmt::Display for DebuggerError {
             DebuggerError::InvalidThread => write!(f, "the requested thread doesn't exist"),
             DebuggerError::InvalidBreakpoint => write!(f, "the requested breakpoint doesn't exist"),
             DebuggerError::NoThreads => write!(f, "there are no running threads to process"),
+            DebuggerError::Busy => write!(f, "another thread is still stepping"),
+            DebuggerError::MemoryNotWritable => write!(f, "the requested memory is readable but can't be written"),
+            DebuggerError::NoFreeWatchpoints => write!(f, "every hardware watchpoint slot is in use"),
+            DebuggerError::NoFrame => write!(f, "couldn't find the return address of the current function"),
+            DebuggerError::InvalidSpec => write!(f, "the sleigh or processor spec couldn't be loaded"),
+            DebuggerError::AttachFailed => write!(f, "couldn't attach to the process (is ptrace allowed?)"),
+            DebuggerError::UnsupportedInstruction => write!(
+                f,
+                "the instruction uses a spec feature the disassembler doesn't support"
+            ),
+        }
+    }
+}
+
+// keeps "address not mapped" apart from "not an instruction", which is what
+// callers scanning unknown bytes care about
+impl From<DisasmError> for DebuggerError {
+    fn from(err: DisasmError) -> Self {
+        match err {
+            DisasmError::InvalidRead(_) => DebuggerError::MemoryAccessFailed,
+            DisasmError::Truncated | DisasmError::PatternNotFound => DebuggerError::DisassemblyFailed,
+            DisasmError::UnsupportedSymbol
+            | DisasmError::ContextOverflow
+            | DisasmError::ContextSizeMismatch
+            | DisasmError::UnsupportedPcodeOp(_)
+            | DisasmError::NoSemantics
+            | DisasmError::DecisionTooWide => DebuggerError::UnsupportedInstruction,
         }
     }
 }
@@ -85,12 +170,18 @@ impl fmt::Display for DebuggerEventKind {
             DebuggerEventKind::ThreadSpawned => write!(f, "thread spawned"),
             DebuggerEventKind::ThreadKilled => write!(f, "thread killed"),
             DebuggerEventKind::UserEvent => write!(f, "custom user event"),
+            DebuggerEventKind::Interrupted => write!(f, "interrupted"),
+            DebuggerEventKind::Exec => write!(f, "process called exec"),
+            DebuggerEventKind::WatchpointHit => write!(f, "watchpoint hit"),
+            DebuggerEventKind::ProcessExited => write!(f, "process exited"),
         }
     }
 }
 
 pub trait Debugger {
     fn is_big_endian(&self) -> bool;
+    // in bytes
+    fn get_pointer_size(&self) -> u32;
     fn get_flags(&self) -> DebuggerFlags;
     fn set_flags(&self, flags: DebuggerFlags) -> Result<(), DebuggerError>;
 
@@ -100,8 +191,14 @@ pub trait Debugger {
     fn wait_next_event(&self, no_block: bool) -> Result<DebuggerEvent, DebuggerError>;
     fn add_event_id(&self, id: u32) -> Result<(), DebuggerError>;
     fn remove_event_id(&self, id: u32) -> Result<(), DebuggerError>;
+    // wake up a wait_next_event blocked on another thread. it returns
+    // Interrupted. if nothing is waiting, the next wait returns it instead.
+    fn interrupt(&self) -> Result<(), DebuggerError>;
 
     fn disassemble_one(&self, addr: u64) -> Result<DisasmDispInstruction, DebuggerError>;
+    // up to count instructions from start, each following the last. stops
+    // early at one that doesn't decode and only errors if that's the first.
+    fn disassemble_range(&self, start: u64, count: i32) -> Result<Vec<DisasmDispInstruction>, DebuggerError>;
 
     fn get_register_infos(&self, thread_idx: DebuggerThreadIndex) -> Vec<&RegisterInfo>;
     fn read_register_by_idx_buf(
@@ -116,17 +213,56 @@ pub trait Debugger {
         name: &str,
         out_data: &mut [u8],
     ) -> Result<(), DebuggerError>;
+    // data must be at least as long as the register. sub-registers (like eax)
+    // only change their own bytes of the full register.
+    fn write_register_by_name_buf(
+        &self,
+        thread_idx: DebuggerThreadIndex,
+        name: &str,
+        data: &[u8],
+    ) -> Result<(), DebuggerError>;
+    // registers are cached per stop. invalidate makes the next read fetch them
+    // again, flush fetches them right away. only needed if something outside the
+    // debugger changed the registers.
+    fn invalidate_register_cache(&self, thread_idx: DebuggerThreadIndex) -> Result<(), DebuggerError>;
+    fn flush_register_cache(&self, thread_idx: DebuggerThreadIndex) -> Result<(), DebuggerError>;
 
     // todo: count is probably unnecessary
     fn read_bytes(&self, thread_idx: DebuggerThreadIndex, addr: u64, out_data: &mut [u8])
         -> Result<u64, DebuggerError>;
     fn write_bytes(&self, thread_idx: DebuggerThreadIndex, addr: u64, data: &[u8]) -> Result<u64, DebuggerError>;
+    // see MemView::find. memory reads as it would with no breakpoints set.
+    fn scan_memory(
+        &self,
+        thread_idx: DebuggerThreadIndex,
+        start: u64,
+        end: u64,
+        needle: &[u8],
+        mask: Option<&[u8]>,
+    ) -> Result<Option<u64>, DebuggerError>;
 
     fn add_breakpoint(&self, thread_idx: DebuggerThreadIndex, addr: u64) -> Result<u32, DebuggerError>;
     //fn add_breakpoint_of_type(&self, addr: u64, bp_type_idx: u32) -> u32;
     fn remove_breakpoint(&self, thread_idx: DebuggerThreadIndex, bp_idx: u32) -> Result<(), DebuggerError>;
 
+    // hardware watchpoints apply to every thread. len has to be 1, 2, 4 or 8 (only 1
+    // for Execute) and addr aligned to it. returns the slot, which WatchpointHit
+    // events report in bp_idx.
+    fn add_watchpoint(
+        &self,
+        thread_idx: DebuggerThreadIndex,
+        addr: u64,
+        len: u32,
+        kind: DebuggerWatchpointKind,
+    ) -> Result<u32, DebuggerError>;
+    fn remove_watchpoint(&self, thread_idx: DebuggerThreadIndex, slot: u32) -> Result<(), DebuggerError>;
+
     fn step(&self, thread_idx: DebuggerThreadIndex) -> Result<(), DebuggerError>;
+    // run this thread until the current function returns. the event that comes
+    // back is StepComplete in the caller (or whatever stopped the thread first).
+    fn step_out(&self, thread_idx: DebuggerThreadIndex) -> Result<(), DebuggerError>;
+    // cont_all resumes every stopped thread. cont_one only resumes thread_idx
+    // and leaves the others stopped.
     fn cont_all(&self) -> Result<(), DebuggerError>;
     fn cont_one(&self, thread_idx: DebuggerThreadIndex) -> Result<(), DebuggerError>;
 }
@@ -139,9 +275,28 @@ pub trait DebuggerHelper {
     fn read_register_by_name<T>(&self, thread_idx: DebuggerThreadIndex, name: &str) -> Result<T, DebuggerError>
     where
         T: Default + Copy;
+
+    // single step `count` times, returning early if anything other than a step completion happens
+    fn step_n(&self, thread_idx: DebuggerThreadIndex, count: u32) -> Result<DebuggerEvent, DebuggerError>;
+
+    // single step until `predicate` holds for the register's value or `max_steps` is hit.
+    // returns the event we stopped on (StepComplete if the predicate held, or whatever
+    // interrupted us) and None if we ran out of steps. this is slow since it steps and
+    // reads the register once per instruction, but it works for things hardware
+    // watchpoints can't watch.
+    fn step_until_register<T, F>(
+        &self,
+        thread_idx: DebuggerThreadIndex,
+        name: &str,
+        max_steps: u32,
+        predicate: F,
+    ) -> Result<Option<DebuggerEvent>, DebuggerError>
+    where
+        T: Default + Copy,
+        F: FnMut(T) -> bool;
 }
 
-impl<BT: Debugger> DebuggerHelper for BT {
+impl<BT: Debugger + ?Sized> DebuggerHelper for BT {
     fn read_register_by_idx<T>(&self, thread_idx: DebuggerThreadIndex, reg_idx: i32) -> Result<T, DebuggerError>
     where
         T: Default + Copy,
@@ -159,14 +314,62 @@ impl<BT: Debugger> DebuggerHelper for BT {
         self.read_register_by_name_buf(thread_idx, name, &mut buffer)?;
         Ok(read_swap_bytes(&buffer, self.is_big_endian()))
     }
+
+    fn step_n(&self, thread_idx: DebuggerThreadIndex, count: u32) -> Result<DebuggerEvent, DebuggerError> {
+        let mut event = DebuggerEvent::new(DebuggerEventKind::NoEvent, 0);
+        for _ in 0..count {
+            self.step(thread_idx)?;
+            event = self.wait_next_event(false)?;
+            match event.kind {
+                DebuggerEventKind::StepComplete | DebuggerEventKind::StepCompleteSyscall => {}
+                _ => return Ok(event),
+            }
+        }
+        Ok(event)
+    }
+
+    fn step_until_register<T, F>(
+        &self,
+        thread_idx: DebuggerThreadIndex,
+        name: &str,
+        max_steps: u32,
+        mut predicate: F,
+    ) -> Result<Option<DebuggerEvent>, DebuggerError>
+    where
+        T: Default + Copy,
+        F: FnMut(T) -> bool,
+    {
+        for _ in 0..max_steps {
+            let event = self.step_n(thread_idx, 1)?;
+            match event.kind {
+                DebuggerEventKind::StepComplete | DebuggerEventKind::StepCompleteSyscall => {}
+                _ => return Ok(Some(event)),
+            }
+
+            let value = self.read_register_by_name::<T>(thread_idx, name)?;
+            if predicate(value) {
+                return Ok(Some(event));
+            }
+        }
+        Ok(None)
+    }
 }
 
 impl DebuggerEvent {
+    pub const PROCESS_KILLED_BY_SIGNAL: u32 = 0x100;
+
     pub fn new(kind: DebuggerEventKind, code: u32) -> DebuggerEvent {
-        DebuggerEvent { kind, code, pid: 0 }
+        DebuggerEvent::new_with_pid(kind, code, 0)
     }
 
     pub fn new_with_pid(kind: DebuggerEventKind, code: u32, pid: u32) -> DebuggerEvent {
-        DebuggerEvent { kind, code, pid }
+        DebuggerEvent {
+            kind,
+            code,
+            pid,
+            bp_idx: u32::MAX,
+            bp_addr: 0,
+            image_path: String::new(),
+        }
     }
 }
@@ -1,3 +1,5 @@
+pub mod flags_arch;
+pub mod flags_arch_amd64;
 pub mod regmap_arch;
 pub mod regmap_arch_amd64;
 pub mod regmap_os_natreg;
@@ -0,0 +1,72 @@
+use super::flags_arch_amd64::{FLAG_INFOS_AMD64, FLAGS_REG_AMD64};
+
+// one bit of the flags register
+pub struct FlagInfo {
+    pub name: &'static str,
+    pub bit: u32,
+}
+
+// the flags register decoded into the bits this architecture knows about.
+// bits without a FlagInfo are kept in raw but can't be looked up by name.
+pub struct FlagsSnapshot {
+    pub raw: u64,
+    infos: &'static [FlagInfo],
+}
+
+impl FlagsSnapshot {
+    pub fn new(raw: u64) -> FlagsSnapshot {
+        FlagsSnapshot {
+            raw,
+            infos: ARCH_FLAG_INFOS,
+        }
+    }
+
+    pub fn get_infos(&self) -> &'static [FlagInfo] {
+        self.infos
+    }
+
+    // names are case insensitive. None if the flag doesn't exist on this arch.
+    pub fn get(&self, name: &str) -> Option<bool> {
+        let info = find_flag_info(name)?;
+        Some(self.raw & (1 << info.bit) != 0)
+    }
+
+    // (name, value) for every known flag, lowest bit first
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, bool)> + '_ {
+        self.infos
+            .iter()
+            .map(|info| (info.name, self.raw & (1 << info.bit) != 0))
+    }
+}
+
+pub fn find_flag_info(name: &str) -> Option<&'static FlagInfo> {
+    ARCH_FLAG_INFOS.iter().find(|info| info.name.eq_ignore_ascii_case(name))
+}
+
+#[cfg(target_arch = "x86_64")]
+pub const ARCH_FLAG_INFOS: &[FlagInfo] = FLAG_INFOS_AMD64;
+#[cfg(target_arch = "x86_64")]
+pub const ARCH_FLAGS_REG: i32 = FLAGS_REG_AMD64;
+
+#[cfg(not(target_arch = "x86_64"))]
+pub const ARCH_FLAG_INFOS: &[FlagInfo] = &[];
+#[cfg(not(target_arch = "x86_64"))]
+pub const ARCH_FLAGS_REG: i32 = -1;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn decodes_named_bits() {
+        // cf, zf, if and the always-set bit 1
+        let flags = FlagsSnapshot::new(0x243);
+        assert_eq!(flags.get("cf"), Some(true));
+        assert_eq!(flags.get("ZF"), Some(true));
+        assert_eq!(flags.get("sf"), Some(false));
+        assert_eq!(flags.get("if"), Some(true));
+        assert_eq!(flags.get("nope"), None);
+        assert_eq!(flags.iter().filter(|(_, v)| *v).count(), 3);
+    }
+}
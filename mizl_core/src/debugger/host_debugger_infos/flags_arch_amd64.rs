@@ -0,0 +1,17 @@
+use super::{flags_arch::FlagInfo, regmap_arch_amd64::RegCodeAmd64};
+
+// linux only hands us eflags, and the upper half of rflags is reserved anyway
+pub const FLAGS_REG_AMD64: i32 = RegCodeAmd64::Eflags as i32;
+
+#[rustfmt::skip]
+pub const FLAG_INFOS_AMD64: &[FlagInfo] = &[
+    FlagInfo { name: "CF", bit: 0 },
+    FlagInfo { name: "PF", bit: 2 },
+    FlagInfo { name: "AF", bit: 4 },
+    FlagInfo { name: "ZF", bit: 6 },
+    FlagInfo { name: "SF", bit: 7 },
+    FlagInfo { name: "TF", bit: 8 },
+    FlagInfo { name: "IF", bit: 9 },
+    FlagInfo { name: "DF", bit: 10 },
+    FlagInfo { name: "OF", bit: 11 },
+];
@@ -8,7 +8,7 @@ use crate::{
         registers::{NativeRegisterInfo, RegisterInfo, RegisterKind, RegisterRole},
         regmap::RegmapEntry,
     },
-    sleigh::sla_file::{Sleigh, SymbolInner},
+    sleigh::sla_file::Sleigh,
 };
 use num::FromPrimitive;
 use smallvec::SmallVec;
@@ -134,6 +134,12 @@ pub struct Amd64NativeRegisterInfo {
     // registers directly readable from the host, it
     // will not contain smaller overlapping registers.
     host_infos_lookup: Vec<Option<usize>>,
+
+    // registers that live inside a host register but don't start where it
+    // does, like AH or the lanes of an xmm register. these are only found by
+    // name so get_all_infos doesn't fill up with every lane.
+    sub_infos: Vec<RegisterInfo>,
+    sub_infos_lookup: HashMap<String, usize>,
 }
 
 impl Amd64NativeRegisterInfo {
@@ -174,18 +180,18 @@ impl Amd64NativeRegisterInfo {
             } else {
                 for varnode_idx in varnode_idxs {
                     let base_sym = &sla_symbols[varnode_idx as usize];
-                    if let SymbolInner::VarnodeSym(varnode_sym) = &base_sym.inner {
+                    if let Some(varnode_sym) = base_sym.as_varnode() {
                         tmp_infos.push(RegisterInfo {
-                            name: base_sym.name.to_owned(),
+                            name: varnode_sym.name().to_owned(),
                             kind: RegisterKind::GeneralPurpose,
                             role: RegisterRole::None,
-                            addr: varnode_sym.offset,
+                            addr: varnode_sym.offset(),
                             mizl_idx: entry.reg_idx,
                             dbg_idx: -1,
-                            bit_len: varnode_sym.size * 8,
+                            bit_len: varnode_sym.size() * 8,
                         });
 
-                        if entry.size == varnode_sym.size {
+                        if entry.size == varnode_sym.size() {
                             // registers are an exact match, store in host_infos
                             host_tmp_info = Some(infos_len + tmp_infos.len() - 1);
                         }
@@ -223,13 +229,58 @@ impl Amd64NativeRegisterInfo {
             }
         }
 
+        let (sub_infos, sub_infos_lookup) =
+            Self::find_sub_registers(sleigh, &infos, &reg_infos_lookup, &host_infos_lookup);
+
         Amd64NativeRegisterInfo {
             infos,
             reg_infos_lookup,
             host_infos_lookup,
+            sub_infos,
+            sub_infos_lookup,
         }
     }
 
+    fn find_sub_registers(
+        sleigh: &Sleigh,
+        infos: &Vec<RegisterInfo>,
+        reg_infos_lookup: &HashMap<String, usize>,
+        host_infos_lookup: &Vec<Option<usize>>,
+    ) -> (Vec<RegisterInfo>, HashMap<String, usize>) {
+        let mut sub_infos: Vec<RegisterInfo> = Vec::new();
+        let mut sub_infos_lookup: HashMap<String, usize> = HashMap::new();
+
+        let host_infos: Vec<&RegisterInfo> = host_infos_lookup.iter().flatten().map(|idx| &infos[*idx]).collect();
+        for varnode_sym in sleigh.get_varnodes_in_space("register") {
+            if reg_infos_lookup.contains_key(varnode_sym.name()) {
+                continue;
+            }
+
+            let start = varnode_sym.offset() as u64;
+            let end = start + varnode_sym.size() as u64;
+            let host_info = host_infos.iter().find(|h| {
+                let host_start = h.addr as u64;
+                let host_end = host_start + (h.bit_len as u64 + 7) / 8;
+                h.addr != u32::MAX && host_start <= start && end <= host_end
+            });
+
+            if let Some(host_info) = host_info {
+                sub_infos_lookup.insert(varnode_sym.name().to_owned(), sub_infos.len());
+                sub_infos.push(RegisterInfo {
+                    name: varnode_sym.name().to_owned(),
+                    kind: RegisterKind::GeneralPurpose,
+                    role: RegisterRole::None,
+                    addr: varnode_sym.offset(),
+                    mizl_idx: host_info.mizl_idx,
+                    dbg_idx: -1,
+                    bit_len: varnode_sym.size() * 8,
+                });
+            }
+        }
+
+        (sub_infos, sub_infos_lookup)
+    }
+
     fn conv_name_fallback(reg_index: i32) -> Option<String> {
         let reg_code = FromPrimitive::from_i32(reg_index)?;
         let reg_name = match reg_code {
@@ -538,11 +589,19 @@ impl NativeRegisterInfo for Amd64NativeRegisterInfo {
                     return self.infos.get(info_idx);
                 }
             }
+            for info in &self.sub_infos_lookup {
+                if info.0.to_lowercase() == search_lower {
+                    let info_idx = *info.1;
+                    return self.sub_infos.get(info_idx);
+                }
+            }
 
             None
+        } else if let Some(info_idx) = self.reg_infos_lookup.get(search) {
+            self.infos.get(*info_idx)
         } else {
-            let info_idx = *self.reg_infos_lookup.get(search)?;
-            self.infos.get(info_idx)
+            let info_idx = *self.sub_infos_lookup.get(search)?;
+            self.sub_infos.get(info_idx)
         }
     }
 
@@ -558,3 +617,31 @@ impl NativeRegisterInfo for Amd64NativeRegisterInfo {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn sub_registers_point_into_their_host() {
+        let root = concat!(env!("CARGO_MANIFEST_DIR"), "/..");
+        let sla_data = fs::read(format!("{}/x86-64.sla", root)).expect("can't read sla");
+        let sleigh = Sleigh::new(&sla_data);
+        let nat_reg_info = Amd64NativeRegisterInfo::new(&sleigh);
+
+        let rax = nat_reg_info.get_host_info(RegCodeAmd64::Rax as i32).unwrap();
+        assert_eq!(rax.name, "RAX");
+        for (name, addr, bit_len) in [("EAX", 0, 32), ("AX", 0, 16), ("AL", 0, 8), ("AH", 1, 8)] {
+            let info = nat_reg_info.get_reg_info(name, true).unwrap();
+            assert_eq!(info.addr, rax.addr + addr);
+            assert_eq!(info.bit_len, bit_len);
+            assert_eq!(info.mizl_idx, RegCodeAmd64::Rax as i32);
+        }
+
+        // only registers starting at the host's address are listed
+        assert!(nat_reg_info.get_all_infos().iter().any(|i| i.name == "EAX"));
+        assert!(!nat_reg_info.get_all_infos().iter().any(|i| i.name == "AH"));
+        assert_eq!(nat_reg_info.get_reg_info("ah", false).unwrap().name, "AH");
+    }
+}
@@ -1,6 +1,11 @@
 pub mod debugger_linux;
 pub mod debugger_linux_amd64;
+pub mod debugger_linux_builder;
+pub mod debugger_linux_event_source;
 pub mod debugger_linux_fb_arch;
 pub mod debugger_linux_memview;
+pub mod debugger_linux_modules;
+pub mod debugger_linux_procinfo;
 pub mod debugger_linux_sighandler;
+pub mod debugger_linux_signals;
 pub mod debugger_linux_superpt;
@@ -1,42 +1,62 @@
-use super::{debugger_linux_memview::DebuggerLinuxMemView, debugger_linux_superpt as superpt};
+use super::{
+    debugger_linux_memview::{DebuggerLinuxMemCache, DebuggerLinuxMemView},
+    debugger_linux_superpt as superpt,
+};
 use crate::{
     debugger::{
         breakpoint::{BreakpointContainer, BreakpointEntry, BreakpointWrapMemView},
         chunked_free_memview::ChunkedFreeMemView,
-        debugger::{Debugger, DebuggerError, DebuggerEvent, DebuggerEventKind, DebuggerFlags, DebuggerThreadIndex},
+        debugger::{
+            Debugger, DebuggerError, DebuggerEvent, DebuggerEventKind, DebuggerFlags, DebuggerThreadIndex,
+            DebuggerWatchpointKind,
+        },
         host_debugger_infos::{
+            flags_arch::{ARCH_FLAGS_REG, FlagsSnapshot, find_flag_info},
             regmap_arch::ArchNativeRegisterInfo,
             regmap_arch_amd64::{RegCodeAmd64, RegSrcAmd64},
-            regmap_os_natreg::get_regmap_entries,
+            regmap_os_natreg::{find_regmap_entry, get_regmap_entries},
         },
-        host_debuggers::debugger_linux_sighandler::sigchld_register,
+        host_debuggers::{
+            debugger_linux_builder::{DebuggerBuilder, DebuggerMemReadMode},
+            debugger_linux_event_source::{DebuggerLinuxEventSource, DebuggerLinuxRawEvent},
+            debugger_linux_modules::{
+                AddressInfo, LoadedModule, query_address, read_loaded_modules, read_mapped_regions,
+            },
+            debugger_linux_procinfo::{read_cmdline, read_environ, read_exe_path, read_task_ids},
+            debugger_linux_sighandler::{sigchld_register, sigchld_unregister},
+            debugger_linux_signals::{DebuggerLinuxSignalInfo, read_signal_info},
+        },
+        module_symbols::ModuleSymbolResolver,
         registers::registers::{NativeRegisterInfo, RegisterInfo},
     },
     memory::memview::MemView,
-    sleigh::{
-        disasm::{Disasm, DisasmDispInstruction},
-        pspec_file::Pspec,
-        sla_file::Sleigh,
-    },
+    sleigh::disasm::{Disasm, DisasmDispInstruction},
 };
 use crossbeam::channel::{Receiver, Sender, bounded};
 use libc;
+use log::{debug, trace};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet, VecDeque},
     ffi::CString,
-    fmt, fs,
+    fmt,
     ops::DerefMut,
     path::Path,
-    sync::{Arc, Mutex, MutexGuard, RwLock},
+    sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard},
     thread::{self, ThreadId},
 };
 
 // todo: use traits so we don't have to import everything manually
 cfg_if::cfg_if! {
     if #[cfg(target_arch = "x86_64")] {
-        use super::debugger_linux_amd64::convert_si_code;
+        use super::debugger_linux_amd64::{
+            RETURN_ADDR_CODE_LEN, SW_BREAKPOINT_BYTES, WATCHPOINT_SLOT_COUNT, convert_si_code, find_return_addr_slot,
+            take_hit_watchpoint_slot, write_watchpoints,
+        };
     } else {
-        use super::debugger_linux_fb_arch::convert_si_code;
+        use super::debugger_linux_fb_arch::{
+            RETURN_ADDR_CODE_LEN, SW_BREAKPOINT_BYTES, WATCHPOINT_SLOT_COUNT, convert_si_code, find_return_addr_slot,
+            take_hit_watchpoint_slot, write_watchpoints,
+        };
     }
 }
 
@@ -47,13 +67,15 @@ pub enum DebuggerLinuxPauseState {
     StoppedUnknownReason,
     StepCompleted,
     SwBreakpointHit,
-    _HwBreakpointHit,
-    _SyscallHit,
+    HwBreakpointHit,
+    SyscallHit,
     SyscallHitEnd,
     _ExceptionOccurred,
+    Stepping,
     SteppingBp,
     SteppingBpContOne,
     SteppingBpContAll,
+    ExecStopped,
     // ...
 }
 
@@ -65,13 +87,26 @@ impl DebuggerLinuxPauseState {
             DebuggerLinuxPauseState::StoppedUnknownReason => true,
             DebuggerLinuxPauseState::StepCompleted => true,
             DebuggerLinuxPauseState::SwBreakpointHit => true,
-            DebuggerLinuxPauseState::_HwBreakpointHit => true,
-            DebuggerLinuxPauseState::_SyscallHit => true,
+            DebuggerLinuxPauseState::HwBreakpointHit => true,
+            DebuggerLinuxPauseState::SyscallHit => true,
             DebuggerLinuxPauseState::SyscallHitEnd => true,
             DebuggerLinuxPauseState::_ExceptionOccurred => true,
+            DebuggerLinuxPauseState::Stepping => false,
             DebuggerLinuxPauseState::SteppingBp => false,
             DebuggerLinuxPauseState::SteppingBpContOne => false,
             DebuggerLinuxPauseState::SteppingBpContAll => false,
+            DebuggerLinuxPauseState::ExecStopped => true,
+        }
+    }
+
+    // we single stepped the thread and are waiting for the trap
+    pub fn is_stepping(&self) -> bool {
+        match self {
+            DebuggerLinuxPauseState::Stepping
+            | DebuggerLinuxPauseState::SteppingBp
+            | DebuggerLinuxPauseState::SteppingBpContOne
+            | DebuggerLinuxPauseState::SteppingBpContAll => true,
+            _ => false,
         }
     }
 }
@@ -84,31 +119,86 @@ impl fmt::Display for DebuggerLinuxPauseState {
             DebuggerLinuxPauseState::StoppedUnknownReason => write!(f, "StoppedUnknownReason"),
             DebuggerLinuxPauseState::StepCompleted => write!(f, "StepCompleted"),
             DebuggerLinuxPauseState::SwBreakpointHit => write!(f, "SwBreakpointHit"),
-            DebuggerLinuxPauseState::_HwBreakpointHit => write!(f, "HwBreakpointHit"),
-            DebuggerLinuxPauseState::_SyscallHit => write!(f, "SyscallHit"),
+            DebuggerLinuxPauseState::HwBreakpointHit => write!(f, "HwBreakpointHit"),
+            DebuggerLinuxPauseState::SyscallHit => write!(f, "SyscallHit"),
             DebuggerLinuxPauseState::SyscallHitEnd => write!(f, "SyscallHitEnd"),
             DebuggerLinuxPauseState::_ExceptionOccurred => write!(f, "ExceptionOccurred"),
+            DebuggerLinuxPauseState::Stepping => write!(f, "Stepping"),
             DebuggerLinuxPauseState::SteppingBp => write!(f, "SteppingBp"),
             DebuggerLinuxPauseState::SteppingBpContOne => write!(f, "SteppingBpContOne"),
             DebuggerLinuxPauseState::SteppingBpContAll => write!(f, "SteppingBpContAll"),
+            DebuggerLinuxPauseState::ExecStopped => write!(f, "ExecStopped"),
         }
     }
 }
 
+// with TRACESYSGOOD, syscall stops are SIGTRAP | 0x80 and never reach the
+// si_code match. si_code only means something trap related for SIGTRAP, a
+// SIGSEGV's SEGV_MAPERR has the same value as TRAP_BRKPT for example. prev is
+// the thread's pause state from before it stopped.
+fn convert_stop(
+    status: i32,
+    si_code: i32,
+    prev: &DebuggerLinuxPauseState,
+) -> (DebuggerLinuxPauseState, DebuggerEventKind) {
+    match libc::WSTOPSIG(status) {
+        sig if sig == libc::SIGTRAP | 0x80 => (
+            DebuggerLinuxPauseState::SyscallHit,
+            DebuggerEventKind::StepCompleteSyscall,
+        ),
+        libc::SIGTRAP => convert_si_code(si_code, prev),
+        _ => (
+            DebuggerLinuxPauseState::StoppedUnknownReason,
+            DebuggerEventKind::MiscSignalReceived,
+        ),
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct DebuggerLinuxWatchpoint {
+    pub addr: u64,
+    pub len: u32,
+    pub kind: DebuggerWatchpointKind,
+}
+
+// a thread's registers in the kernel's own layout, the way a core file stores them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DebuggerLinuxRegset {
+    Prstatus, // user_regs_struct, the pr_reg part of NT_PRSTATUS
+    Fpregset, // user_fpregs_struct, NT_FPREGSET
+}
+
 struct DebuggerLinuxThread {
     pid: i32,
     pause_state: DebuggerLinuxPauseState,
     proc_mem: DebuggerLinuxMemView,
     reg_mem: ChunkedFreeMemView,
+    queued_signal: i32,     // delivered on the next cont, 0 = none
+    frozen: bool,           // skipped by cont_all
+    reg_mem_dirty: bool,    // reg_mem has to be loaded again before it's read
+    reg_write_dirty: bool,  // reg_mem has writes the thread doesn't have yet
+    stop_all_pending: bool, // stop_all sent a SIGSTOP we haven't seen yet
 }
 
 enum DebuggerLinuxCmdReqOp {
     SingleStep(DebuggerThreadIndex),
+    StepOver(DebuggerThreadIndex),
+    StepOut(DebuggerThreadIndex),
+    Detach,
     ContinueOne(DebuggerThreadIndex),
     Continue,
+    StopAll,
     DisasmOne(u64),
+    DisasmRange(u64, usize),
     ReadBytes(i32, u64, Arc<Mutex<Vec<u8>>>, i32),
+    // thread pid, start, end, needle, mask
+    ScanMemory(i32, u64, u64, Vec<u8>, Option<Vec<u8>>),
     LoadRegCache(i32),
+    WriteRegister(DebuggerThreadIndex, String, Vec<u8>),
+    SetFlag(DebuggerThreadIndex, u32, bool),
+    AddWatchpoint(DebuggerThreadIndex, DebuggerLinuxWatchpoint),
+    RemoveWatchpoint(DebuggerThreadIndex, u32),
+    GetRawRegset(DebuggerThreadIndex, DebuggerLinuxRegset),
     // ...
 }
 
@@ -116,7 +206,11 @@ enum DebuggerLinuxCmdRspOp {
     Error(DebuggerError),
     Success,
     ResultDisasmOne(DisasmDispInstruction),
+    ResultDisasmRange(Vec<DisasmDispInstruction>),
     ResultReadBytes(u64),
+    ResultScanMemory(Option<u64>),
+    ResultAddWatchpoint(u32),
+    ResultRawRegset(Vec<u8>),
 }
 
 enum DebuggerLinuxStepKind {
@@ -125,20 +219,48 @@ enum DebuggerLinuxStepKind {
     StepBpContAll,
 }
 
+// what a thread stopping did to the state, and what's left for
+// handle_child_event to do with the real process
+struct DebuggerLinuxStopOutcome {
+    event: DebuggerEvent,
+    prev_pause_state: DebuggerLinuxPauseState,
+    // the thread finished stepping over a breakpoint, which has to be written
+    // back (and the thread possibly continued) before anything else happens
+    finished_bp_step: bool,
+}
+
 struct DebuggerLinuxState {
     // the "current" thread which is really just a convenience thing.
     // it's normally the last stopped thread unless the user switched.
     cur_thread_pid: Option<i32>,
     // only one thread can step at a time, so this is fine to be on state.
-    // todo: we should actually enforce this rule
+    // any step or cont that comes in while this is set gets Busy back.
     // todo: should we move this to the thread info itself? is there any
     // reason to restrict one thread from stepping at a time?
     stepping_thread_pid: Option<i32>,
     stepping_thread_bp: Option<BreakpointEntry>,
+    // (thread pid, breakpoint, lowest sp) that step_over put after a call or
+    // step_out put on the return address. it's never in bp_cont so the user
+    // doesn't see it, and it comes out on the next reported stop no matter why
+    // the thread stopped. a hit with sp below the lowest sp is a deeper call
+    // of a recursive function, which gets stepped past instead.
+    step_over_bp: Option<(i32, BreakpointEntry, u64)>,
     threads: HashMap<i32, DebuggerLinuxThread>,
     bp_cont: BreakpointContainer,
-    reg_mem_dirty: bool,
+    // debug registers are per thread, but we keep every thread's the same
+    watchpoints: [Option<DebuggerLinuxWatchpoint>; WATCHPOINT_SLOT_COUNT],
     pending_events: Vec<libc::epoll_event>,
+    // how threads we start tracking read and write memory
+    mem_read_mode: DebuggerMemReadMode,
+    // shared by every thread's proc_mem if the read cache is on
+    mem_cache: Option<DebuggerLinuxMemCache>,
+    // new threads whose first stop showed up before their parent's clone event
+    pending_clone_pids: HashSet<i32>,
+    // stop_all is waiting for its SIGSTOPs to show up
+    stopping_all: bool,
+    // child events that came in while stop_all was waiting. wait_next_event
+    // hands these out before looking for new ones.
+    queued_events: VecDeque<Result<DebuggerEvent, DebuggerError>>,
 }
 
 struct DebuggerLinuxChannelContainer {
@@ -148,10 +270,11 @@ struct DebuggerLinuxChannelContainer {
     // dbg thread -> cmd thread
     cmd_rsp_tx: Sender<DebuggerLinuxCmdRspOp>,
     cmd_rsp_rx: Receiver<DebuggerLinuxCmdRspOp>,
-    // epoll/action/sigchld -> dbg thread
+    // epoll/action/sigchld/interrupt -> dbg thread
     epoll_fd: i32,
     action_fd: i32,
     sigchld_fd: i32,
+    interrupt_fd: i32,
 }
 
 struct DebuggerLinuxSessionState {
@@ -163,26 +286,216 @@ pub struct DebuggerLinux {
     // set on startup
     disasm: Disasm,
     nat_reg_info: ArchNativeRegisterInfo,
+    symbols: Option<ModuleSymbolResolver>,
+    flags: RwLock<DebuggerFlags>,
     // configured when process is actually loaded
     state: Arc<Mutex<DebuggerLinuxState>>,
     session_state: RwLock<Option<DebuggerLinuxSessionState>>,
+    event_source: Box<dyn DebuggerLinuxEventSource>,
 }
 
 impl DebuggerLinuxThread {
-    pub fn new(pid: i32) -> DebuggerLinuxThread {
-        let proc_mem = DebuggerLinuxMemView::new(pid);
+    pub fn new(pid: i32, mem_read_mode: DebuggerMemReadMode) -> DebuggerLinuxThread {
+        let proc_mem = match mem_read_mode {
+            DebuggerMemReadMode::ProcMem => DebuggerLinuxMemView::new(pid),
+            DebuggerMemReadMode::Ptrace => DebuggerLinuxMemView::new_ptrace_only(pid),
+        };
         let reg_mem = ChunkedFreeMemView::new(64);
         DebuggerLinuxThread {
             pid,
             pause_state: DebuggerLinuxPauseState::FirstStop,
             proc_mem,
             reg_mem,
+            queued_signal: 0,
+            frozen: false,
+            reg_mem_dirty: true,
+            reg_write_dirty: false,
+            stop_all_pending: false,
+        }
+    }
+}
+
+impl DebuggerLinuxState {
+    pub fn new() -> DebuggerLinuxState {
+        DebuggerLinuxState {
+            cur_thread_pid: None,
+            stepping_thread_pid: None,
+            stepping_thread_bp: None,
+            step_over_bp: None,
+            threads: HashMap::new(),
+            bp_cont: BreakpointContainer::new(),
+            watchpoints: [None; WATCHPOINT_SLOT_COUNT],
+            pending_events: Vec::new(),
+            mem_read_mode: DebuggerMemReadMode::ProcMem,
+            mem_cache: None,
+            pending_clone_pids: HashSet::new(),
+            stopping_all: false,
+            queued_events: VecDeque::new(),
+        }
+    }
+
+    fn new_thread(&self, pid: i32) -> DebuggerLinuxThread {
+        let mut thread = DebuggerLinuxThread::new(pid, self.mem_read_mode);
+        thread.proc_mem.set_cache(self.mem_cache.clone());
+        thread
+    }
+
+    // registers and memory may not be what we last read anymore
+    fn mark_dirty(&mut self) {
+        for thread in self.threads.values_mut() {
+            thread.reg_mem_dirty = true;
+        }
+        if let Some(mem_cache) = &self.mem_cache {
+            mem_cache.invalidate();
+        }
+    }
+
+    // each thread's registers are loaded on their own, so one thread's load
+    // says nothing about another's. threads we don't know about have nothing
+    // loaded.
+    fn is_reg_cache_dirty(&self, thread_pid: i32) -> bool {
+        self.threads.get(&thread_pid).is_none_or(|t| t.reg_mem_dirty)
+    }
+
+    // anything read while a thread is running could be stale by the time it
+    // stops, so nothing goes through the cache until they all have
+    fn update_mem_cache_bypass(&mut self) {
+        let any_running = self.threads.values().any(|t| !t.pause_state.is_stopped());
+        for thread in self.threads.values_mut() {
+            thread.proc_mem.set_bypass_cache(any_running);
+        }
+    }
+
+    // a second step while one is in flight would overwrite stepping_thread_bp,
+    // and then the original breakpoint bytes would never get written back.
+    fn check_not_stepping(&self) -> Result<(), DebuggerError> {
+        if self.stepping_thread_pid.is_some() {
+            return Err(DebuggerError::Busy);
+        }
+        Ok(())
+    }
+
+    // the part of handling a stopped thread that only touches our own state.
+    // returns None for threads we aren't tracking.
+    fn apply_stop(&mut self, raw_event: &DebuggerLinuxRawEvent) -> Option<DebuggerLinuxStopOutcome> {
+        let pid = raw_event.pid;
+        let thread_state = self.threads.get_mut(&pid)?;
+
+        let prev_pause_state = thread_state.pause_state.clone();
+        let was_stepping_bp = prev_pause_state == DebuggerLinuxPauseState::SteppingBp
+            || prev_pause_state == DebuggerLinuxPauseState::SteppingBpContOne
+            || prev_pause_state == DebuggerLinuxPauseState::SteppingBpContAll;
+
+        let (pause_state, evt_kind) = convert_stop(raw_event.status, raw_event.si_code, &prev_pause_state);
+        let event = DebuggerEvent::new(evt_kind, raw_event.status as u32);
+        thread_state.pause_state = pause_state;
+        trace!(
+            "thread {} pause state -> {} (stopped)",
+            thread_state.pid, thread_state.pause_state
+        );
+
+        // a watchpoint can fire on the instruction we stepped over, which still finishes the step
+        let finished_bp_step = was_stepping_bp
+            && (evt_kind == DebuggerEventKind::StepComplete || evt_kind == DebuggerEventKind::WatchpointHit);
+        if !finished_bp_step {
+            // we stopped normally, so unset the stepping thread pid
+            if self.stepping_thread_pid == Some(pid) {
+                self.stepping_thread_pid = None;
+            }
+        }
+
+        Some(DebuggerLinuxStopOutcome {
+            event,
+            prev_pause_state,
+            finished_bp_step,
+        })
+    }
+
+    // the SIGSTOP stop_all sent to a thread. while stop_all is waiting, this
+    // is the stop it asked for. otherwise something else stopped the thread
+    // first and it has been resumed since, so nothing changes and it has to go
+    // right back to what it was doing. returns false if it's not one of ours.
+    fn apply_stop_all_sigstop(&mut self, raw_event: &DebuggerLinuxRawEvent) -> bool {
+        if libc::WSTOPSIG(raw_event.status) != libc::SIGSTOP {
+            return false;
+        }
+
+        let thread = match self.threads.get_mut(&raw_event.pid) {
+            Some(v) if v.stop_all_pending => v,
+            _ => return false,
+        };
+        thread.stop_all_pending = false;
+        if self.stopping_all {
+            thread.pause_state = DebuggerLinuxPauseState::StoppedUnknownReason;
+            trace!("thread {} pause state -> {} (stop all)", thread.pid, thread.pause_state);
+        }
+        true
+    }
+
+    // parent_pid created a thread. both are left stopped so the user gets a
+    // chance to look at the new thread before either one runs again.
+    // returns false for parents we aren't tracking.
+    fn apply_clone(&mut self, parent_pid: i32, new_pid: i32) -> bool {
+        let parent = match self.threads.get_mut(&parent_pid) {
+            Some(v) => v,
+            None => return false,
+        };
+        parent.pause_state = DebuggerLinuxPauseState::StoppedUnknownReason;
+        trace!("thread {} pause state -> {} (clone)", parent_pid, parent.pause_state);
+
+        let thread = self.new_thread(new_pid);
+        trace!("thread {} pause state -> {} (new thread)", new_pid, thread.pause_state);
+        self.threads.insert(new_pid, thread);
+        self.mark_dirty();
+        true
+    }
+
+    // pid is gone for good. if it was the current thread, the lowest living
+    // thread takes over. returns false for threads we aren't tracking.
+    fn apply_thread_exit(&mut self, pid: i32) -> bool {
+        if self.threads.remove(&pid).is_none() {
+            return false;
         }
+
+        if self.cur_thread_pid == Some(pid) {
+            self.cur_thread_pid = self.threads.keys().min().copied();
+        }
+        if self.stepping_thread_pid == Some(pid) {
+            self.stepping_thread_pid = None;
+        }
+        self.mark_dirty();
+        true
+    }
+
+    // the process replaced its image. every other thread is gone, the thread that
+    // called exec now has the leader's pid, and nothing from the old address space
+    // (breakpoints, memory, registers) means anything anymore.
+    // returns false for processes we aren't tracking.
+    fn apply_exec(&mut self, pid: i32) -> bool {
+        if !self.threads.contains_key(&pid) {
+            return false;
+        }
+
+        self.threads.clear();
+        let mut thread = self.new_thread(pid);
+        thread.pause_state = DebuggerLinuxPauseState::ExecStopped;
+        trace!("thread {} pause state -> {} (exec)", pid, thread.pause_state);
+        self.threads.insert(pid, thread);
+
+        self.cur_thread_pid = Some(pid);
+        self.stepping_thread_pid = None;
+        self.stepping_thread_bp = None;
+        self.step_over_bp = None;
+        self.bp_cont = BreakpointContainer::new();
+        // the kernel drops debug registers on exec too
+        self.watchpoints = [None; WATCHPOINT_SLOT_COUNT];
+        self.mark_dirty();
+        true
     }
 }
 
 impl DebuggerLinuxChannelContainer {
-    pub fn new(epoll_fd: i32, action_fd: i32, sigchld_fd: i32) -> DebuggerLinuxChannelContainer {
+    pub fn new(epoll_fd: i32, action_fd: i32, sigchld_fd: i32, interrupt_fd: i32) -> DebuggerLinuxChannelContainer {
         let (cmd_req_tx, cmd_req_rx) = bounded(1);
         let (cmd_rsp_tx, cmd_rsp_rx) = bounded(1);
         DebuggerLinuxChannelContainer {
@@ -193,6 +506,17 @@ impl DebuggerLinuxChannelContainer {
             epoll_fd,
             action_fd,
             sigchld_fd,
+            interrupt_fd,
+        }
+    }
+
+    fn close_fds(&self) {
+        sigchld_unregister(self.sigchld_fd);
+        unsafe {
+            libc::close(self.interrupt_fd);
+            libc::close(self.sigchld_fd);
+            libc::close(self.action_fd);
+            libc::close(self.epoll_fd);
         }
     }
 }
@@ -207,231 +531,1098 @@ impl DebuggerLinuxSessionState {
 }
 
 impl DebuggerLinux {
+    // panics if the spec files aren't in the working directory. use
+    // DebuggerBuilder to get an error back instead.
     pub fn new() -> DebuggerLinux {
-        let disasm: Disasm = Self::setup_disasm();
+        DebuggerBuilder::new().build().expect("couldn't set up debugger")
+    }
+
+    // events normally come from waitpid, see DebuggerLinuxEventSource
+    pub fn new_with_event_source(event_source: Box<dyn DebuggerLinuxEventSource>) -> DebuggerLinux {
+        DebuggerBuilder::new()
+            .event_source(event_source)
+            .build()
+            .expect("couldn't set up debugger")
+    }
+
+    // only DebuggerBuilder should call this, after it has checked everything
+    pub(super) fn new_from_parts(
+        disasm: Disasm,
+        event_source: Box<dyn DebuggerLinuxEventSource>,
+        flags: DebuggerFlags,
+        symbols: Option<ModuleSymbolResolver>,
+        mem_read_mode: DebuggerMemReadMode,
+        mem_read_cache: bool,
+    ) -> DebuggerLinux {
         let nat_reg_info = ArchNativeRegisterInfo::new(&disasm.sleigh);
-        let state = Arc::new(Mutex::new(DebuggerLinuxState {
-            cur_thread_pid: None,
-            stepping_thread_pid: None,
-            stepping_thread_bp: None,
-            threads: HashMap::new(),
-            bp_cont: BreakpointContainer::new(),
-            reg_mem_dirty: true,
-            pending_events: Vec::new(),
-        }));
+        let mut state = DebuggerLinuxState::new();
+        state.mem_read_mode = mem_read_mode;
+        if mem_read_cache {
+            state.mem_cache = Some(DebuggerLinuxMemCache::new());
+        }
         DebuggerLinux {
             disasm,
             nat_reg_info,
-            state,
+            symbols,
+            flags: RwLock::new(flags),
+            state: Arc::new(Mutex::new(state)),
             session_state: RwLock::new(None),
+            event_source,
         }
     }
 
-    fn setup_disasm() -> Disasm {
-        let sla_data: Vec<u8>;
-        let pspec_data: String;
-        if cfg!(target_arch = "x86_64") {
-            sla_data = fs::read("x86-64.sla").expect("can't read sla");
-            pspec_data = fs::read_to_string("x86-64.pspec").expect("can't read pspec");
-        } else {
-            unimplemented!()
+    // the PTRACE_O_* options for the flags that map to one. without TRACEEXEC,
+    // an exec looks like a plain SIGTRAP and we'd keep breakpoints around for
+    // an address space that no longer exists. TRACECLONE attaches us to new
+    // threads and tells us about them (threads made with it inherit options).
+    fn ptrace_options(&self) -> i32 {
+        let flags = self.get_flags();
+        let mut options = 0;
+        if flags.contains(DebuggerFlags::TraceClone) {
+            options |= libc::PTRACE_O_TRACECLONE;
         }
-
-        let sleigh = Sleigh::new(&sla_data);
-        let pspec = Pspec::new(pspec_data).expect("error in pspec");
-
-        let initial_ctx = pspec.get_initial_ctx(&sleigh).expect("error in pspec");
-        Disasm::new(sleigh, initial_ctx)
-    }
-
-    // runs in: cmd thread, dbg thread
-    fn get_thread_pid_or_current(
-        state: &DebuggerLinuxState,
-        thread_idx: DebuggerThreadIndex,
-    ) -> Result<i32, DebuggerError> {
-        match thread_idx {
-            DebuggerThreadIndex::Current => state.cur_thread_pid.ok_or(DebuggerError::NoThreads),
-            DebuggerThreadIndex::Specific(i) => Ok(i as i32),
+        if flags.contains(DebuggerFlags::TraceExec) {
+            options |= libc::PTRACE_O_TRACEEXEC;
+        }
+        if flags.contains(DebuggerFlags::ExitKill) {
+            options |= libc::PTRACE_O_EXITKILL;
+        }
+        if flags.contains(DebuggerFlags::TraceSysGood) {
+            options |= libc::PTRACE_O_TRACESYSGOOD;
         }
+        options
     }
 
-    // runs in: dbg thread
-    // our max address size is u64 so always return u64 for this
-    fn read_register_pc(
-        &self,
-        state: &mut DebuggerLinuxState,
-        thread_idx: DebuggerThreadIndex,
-    ) -> Result<u64, DebuggerError> {
-        if cfg!(target_arch = "x86_64") {
-            let mut out_data = [0u8; 8];
-            self.read_register_by_idx_buf_dbg(state, thread_idx, RegCodeAmd64::Rip as i32, &mut out_data)?;
-            return Ok(u64::from_le_bytes(out_data));
-        } else {
-            unimplemented!()
-        }
+    // the symbols passed to DebuggerBuilder, if any
+    pub fn get_symbols(&self) -> Option<&ModuleSymbolResolver> {
+        self.symbols.as_ref()
     }
 
-    // runs in: cmd thread, dbg thread
-    fn is_debugger_thread(&self) -> bool {
-        let sstate_opt_guard = self.session_state.read().unwrap();
-        let sstate_opt = sstate_opt_guard.as_ref();
-        match sstate_opt {
-            Some(sstate) => thread::current().id() == sstate.dbg_thread_id,
-            None => false,
-        }
+    // the decoder the debugger was built with, for callers that want to
+    // disassemble memory themselves (like Disasm::listing)
+    pub fn get_disasm(&self) -> &Disasm {
+        &self.disasm
     }
 
     // runs in: dbg thread
-    // "fixes up" registers even though their values
-    // may be different in reality
-    fn reg_val_arch_adjust(
-        &self,
-        thread_info: &DebuggerLinuxThread,
-        reg_info: &RegisterInfo,
-        src_bytes: &[u8],
-    ) -> Option<Vec<u8>> {
-        if cfg!(target_arch = "x86_64") {
-            // rip points one byte ahead on x86 after hitting a breakpoint
-            if reg_info.mizl_idx == RegCodeAmd64::Rip as i32 {
-                if thread_info.pause_state == DebuggerLinuxPauseState::SwBreakpointHit {
-                    let mut modified_rip = u64::from_le_bytes(src_bytes.try_into().unwrap());
-                    modified_rip -= 1; // move 1 back (TODO: the breakpoint may not be a single byte?!)
-                    return Some(Vec::from(u64::to_le_bytes(modified_rip)));
-                }
+    // starts tracking thread_pids (the first one becomes the current thread)
+    // and sets up the fds wait_next_event listens on. the calling thread
+    // becomes the dbg thread.
+    fn setup_session(&self, thread_pids: &[i32]) -> Result<(), DebuggerError> {
+        // anything cached came from some other process
+        self.disasm.invalidate(0, u64::MAX);
+
+        // set up events to notify wait_next_event
+        // todo: this is kinda nasty. we should have something to
+        // automatically close/unset whatever we drop the object.
+        // todo: check statuses
+        let epoll_fd: i32;
+        let action_fd: i32;
+        let sigchld_fd: i32;
+        let interrupt_fd: i32;
+        unsafe {
+            // setup epoll
+            epoll_fd = libc::epoll_create1(0);
+            if epoll_fd < 0 {
+                return Err(DebuggerError::InternalError);
             }
-        }
-        None
-    }
 
-    // runs in: dbg thread
-    fn load_reg_cache(&self, state: &mut DebuggerLinuxState, thread_pid: i32) -> Result<(), DebuggerError> {
-        let thread_mut = state.threads.get_mut(&thread_pid).ok_or(DebuggerError::InvalidThread)?;
+            // setup action eventfd
+            action_fd = libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK);
+            if action_fd < 0 {
+                libc::close(epoll_fd);
+                return Err(DebuggerError::InternalError);
+            }
 
-        let reg_data = superpt::getregs(thread_mut.pid);
-        let fpreg_data = superpt::getfpregs(thread_mut.pid);
+            // at first glance, signalfd should be perfect for epolling for either
+            // a user action or a SIGCHLD signal. we just have to epoll for it,
+            // plus we get a free signalfd_siginfo object that tells us about why
+            // we stopped, right? unfortunately, linux (or rather, unix systems).
+            // in order for signalfd to work correctly, ALL threads in the process
+            // must have SIGCHLD blocked. if even one thread has it unblocked,
+            // that thread will be assigned to take care of the signal (which
+            // probably means doing nothing but discarding signal.) even if we get
+            // the epoll event, signalfd most likely has been consumed and we'll
+            // either block on read or skip it if EFD_NONBLOCK is set. since we
+            // can't guarantee the consumer can even block the signal in every
+            // thread (i.e., any language with a runtime), this won't work.
+            // the alternative? global signal handlers, yay! now we will have to
+            // keep track of every signal handler that's been registered. and we
+            // better hope nobody else wants to handle SIGCHLD.
+            sigchld_fd = libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK);
+            if sigchld_fd < 0 {
+                libc::close(action_fd);
+                libc::close(epoll_fd);
+                return Err(DebuggerError::InternalError);
+            }
 
-        // println!("[checking adjusted arch reg vals]");
-        for item in get_regmap_entries() {
-            let src_bytes_start = item.native_off;
-            let src_bytes_end = src_bytes_start + item.size as usize;
-            let src_bytes: &[u8];
-            if cfg!(target_arch = "x86_64") {
-                src_bytes = match item.source {
-                    x if x == RegSrcAmd64::Standard as i32 => &reg_data[src_bytes_start..src_bytes_end],
-                    x if x == RegSrcAmd64::FloatingPoint as i32 => &fpreg_data[src_bytes_start..src_bytes_end],
-                    _ => unimplemented!(),
-                };
-            } else {
-                unimplemented!();
+            // setup interrupt eventfd, only used to wake up wait_next_event
+            interrupt_fd = libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK);
+            if interrupt_fd < 0 {
+                libc::close(sigchld_fd);
+                libc::close(action_fd);
+                libc::close(epoll_fd);
+                return Err(DebuggerError::InternalError);
             }
 
-            let reg_info = match self.nat_reg_info.get_host_info(item.reg_idx) {
-                Some(v) => v,
-                None => return Err(DebuggerError::InternalError),
+            // register handler now
+            sigchld_register(sigchld_fd);
+
+            // add all fds to epoll
+            let mut action_evt = libc::epoll_event {
+                events: libc::EPOLLIN as u32,
+                u64: action_fd as u64,
             };
+            let mut sigchld_evt = libc::epoll_event {
+                events: libc::EPOLLIN as u32,
+                u64: sigchld_fd as u64,
+            };
+            libc::epoll_ctl(epoll_fd, libc::EPOLL_CTL_ADD, action_fd, &mut action_evt);
+            let mut interrupt_evt = libc::epoll_event {
+                events: libc::EPOLLIN as u32,
+                u64: interrupt_fd as u64,
+            };
+            libc::epoll_ctl(epoll_fd, libc::EPOLL_CTL_ADD, sigchld_fd, &mut sigchld_evt);
+            libc::epoll_ctl(epoll_fd, libc::EPOLL_CTL_ADD, interrupt_fd, &mut interrupt_evt);
+        }
 
-            // max means we have no idea where this is in sleigh space.
-            // don't try to guess since we would've had a unique addr
-            // assigned even if it was not in the sleigh.
-            if reg_info.addr == u32::MAX {
-                continue;
+        let mut state = self.state.lock().unwrap();
+        for pid in thread_pids {
+            let thread = state.new_thread(*pid);
+            state.threads.insert(*pid, thread);
+        }
+        state.cur_thread_pid = thread_pids.first().copied();
+        {
+            let mut sstate_opt = self.session_state.write().unwrap();
+            // a detached session's fds are only closed once the next one replaces it
+            if let Some(old_sstate) = sstate_opt.take() {
+                old_sstate.chan_cont.close_fds();
             }
 
-            let mut dst_addr = reg_info.addr as u64;
-            match self.reg_val_arch_adjust(thread_mut, reg_info, &src_bytes) {
-                Some(new_val) => {
-                    // register value was modified
-                    thread_mut
-                        .reg_mem
-                        .write_bytes(&mut dst_addr, &new_val)
-                        .or(Err(DebuggerError::InvalidRegister))?;
-                }
-                None => {
-                    // register value is unchanged
-                    thread_mut
-                        .reg_mem
-                        .write_bytes(&mut dst_addr, &src_bytes)
-                        .or(Err(DebuggerError::InvalidRegister))?;
-                }
-            }
+            let chan_cont = DebuggerLinuxChannelContainer::new(epoll_fd, action_fd, sigchld_fd, interrupt_fd);
+            let sstate = DebuggerLinuxSessionState::new(thread::current().id(), chan_cont);
+            *sstate_opt = Some(sstate);
         }
-
-        state.reg_mem_dirty = false;
         Ok(())
     }
 
-    // runs in: dbg thread
-    fn step_impl(
-        &self,
-        mut state_guard: MutexGuard<'_, DebuggerLinuxState>,
-        thread_idx: DebuggerThreadIndex,
-        step_kind: DebuggerLinuxStepKind,
-    ) -> Result<(), DebuggerError> {
-        let state = state_guard.deref_mut();
-        let thread_pid = Self::get_thread_pid_or_current(&state, thread_idx)?;
-        {
-            let mut bp_opt: Option<&BreakpointEntry> = None;
+    // runs in: cmd thread, dbg thread
+    // resolves a thread index to a pid and confirms we are actually tracking it
+    pub fn validate_thread(&self, thread_idx: DebuggerThreadIndex) -> Result<i32, DebuggerError> {
+        let state = self.state.lock().unwrap();
+        Self::validate_thread_locked(&state, thread_idx)
+    }
 
-            let mut thread = state.threads.get_mut(&thread_pid).ok_or(DebuggerError::InvalidThread)?;
-            if thread.pause_state == DebuggerLinuxPauseState::SwBreakpointHit {
-                // pc is always adjusted to the address of the potential
-                // sw breakpoint that caused a stop, so this is fine.
-                let pc = self.read_register_pc(state, thread_idx.clone())?;
-                bp_opt = state.bp_cont.get_breakpoint(pc);
+    // runs in: cmd thread, dbg thread
+    // blocked and pending signal masks, useful for figuring out why a signal isn't arriving
+    pub fn get_signal_info(&self, thread_idx: DebuggerThreadIndex) -> Result<DebuggerLinuxSignalInfo, DebuggerError> {
+        let thread_pid = self.validate_thread(thread_idx)?;
+        read_signal_info(thread_pid)
+    }
 
-                // todo: double mut borrow
-                thread = state.threads.get_mut(&thread_pid).ok_or(DebuggerError::InvalidThread)?;
+    // runs in: cmd thread, dbg thread
+    // the thread's registers exactly as ptrace hands them back, skipping the
+    // sleigh register cache. meant for writing core files, so nothing is
+    // adjusted: rip is still one past a breakpoint the thread just hit.
+    pub fn get_raw_regset(
+        &self,
+        thread_idx: DebuggerThreadIndex,
+        which: DebuggerLinuxRegset,
+    ) -> Result<Vec<u8>, DebuggerError> {
+        if self.is_debugger_thread() {
+            let mut state = self.state.lock().unwrap();
+            return self.get_raw_regset_impl(&mut state, thread_idx, which);
+        } else {
+            self.validate_thread(thread_idx)?;
+            match self.send_cmd_req(DebuggerLinuxCmdReqOp::GetRawRegset(thread_idx, which)) {
+                DebuggerLinuxCmdRspOp::ResultRawRegset(v) => return Ok(v),
+                DebuggerLinuxCmdRspOp::Error(e) => return Err(e),
+                _ => return Err(DebuggerError::InternalError),
             }
-
-            match bp_opt {
-                Some(bp) => {
-                    let orig_bytes = &bp.orig_bytes;
-                    let mut mut_addr = bp.addr;
-                    thread
-                        .proc_mem
-                        .write_bytes(&mut mut_addr, &orig_bytes)
-                        .or(Err(DebuggerError::MemoryAccessFailed))?;
-
-                    thread.pause_state = match step_kind {
-                        DebuggerLinuxStepKind::Step => DebuggerLinuxPauseState::SteppingBp,
-                        DebuggerLinuxStepKind::StepBpContOne => DebuggerLinuxPauseState::SteppingBpContOne,
-                        DebuggerLinuxStepKind::StepBpContAll => DebuggerLinuxPauseState::SteppingBpContAll,
-                    };
-                    println!("[setting pause state to {} 2]", thread.pause_state);
-                    state.reg_mem_dirty = true;
-                    state.stepping_thread_pid = Some(thread_pid);
-                    state.stepping_thread_bp = Some(bp.clone());
-                }
-                None => {
-                    // we didn't hit a sw breakpoint or we couldn't
-                    // find a breakpoint (so it's not our's?)
-
-                    // assume registers will change after this
-                    state.reg_mem_dirty = true;
-
-                    // when the user thread continues before receiving a trap,
-                    // call singlestep again rather than continue. once we hit
-                    // the trap we were expecting, switch back to cont.
-                    state.stepping_thread_pid = Some(thread_pid);
-
-                    thread.pause_state = DebuggerLinuxPauseState::Running;
-                    println!("[setting pause state to {} 3]", thread.pause_state);
-                }
-            };
         }
-        std::mem::drop(state_guard); // unlock state
-
-        superpt::singlestep(thread_pid);
-        Ok(())
     }
 
     // runs in: dbg thread
-    fn step_replace_bp_impl(
+    fn get_raw_regset_impl(
+        &self,
+        state: &mut DebuggerLinuxState,
+        thread_idx: DebuggerThreadIndex,
+        which: DebuggerLinuxRegset,
+    ) -> Result<Vec<u8>, DebuggerError> {
+        self.verify_stopped_by_thread_idx(state, thread_idx)?;
+        let thread_pid = Self::validate_thread_locked(state, thread_idx)?;
+
+        // register writes still sitting in the cache go out first
+        self.flush_reg_cache(state, thread_pid)?;
+
+        let regset = match which {
+            DebuggerLinuxRegset::Prstatus => superpt::getregs(thread_pid).to_vec(),
+            DebuggerLinuxRegset::Fpregset => superpt::getfpregs(thread_pid).to_vec(),
+        };
+        Ok(regset)
+    }
+
+    // runs in: cmd thread, dbg thread
+    // argv of the process the thread belongs to. see debugger_linux_procinfo for staleness notes.
+    pub fn get_cmdline(&self, thread_idx: DebuggerThreadIndex) -> Result<Vec<String>, DebuggerError> {
+        let thread_pid = self.validate_thread(thread_idx)?;
+        read_cmdline(thread_pid)
+    }
+
+    // runs in: cmd thread, dbg thread
+    // environment as (key, value) pairs. see debugger_linux_procinfo for staleness notes.
+    pub fn get_environ(&self, thread_idx: DebuggerThreadIndex) -> Result<Vec<(String, String)>, DebuggerError> {
+        let thread_pid = self.validate_thread(thread_idx)?;
+        read_environ(thread_pid)
+    }
+
+    // runs in: cmd thread, dbg thread
+    // every file mapped into the thread's process, main executable included
+    pub fn get_loaded_modules(&self, thread_idx: DebuggerThreadIndex) -> Result<Vec<LoadedModule>, DebuggerError> {
+        let thread_pid = self.validate_thread(thread_idx)?;
+        read_loaded_modules(thread_pid)
+    }
+
+    // runs in: cmd thread, dbg thread
+    // the mapping addr is in and its r/w/x permissions, None if it isn't mapped
+    pub fn query_address(
+        &self,
+        thread_idx: DebuggerThreadIndex,
+        addr: u64,
+    ) -> Result<Option<AddressInfo>, DebuggerError> {
+        let thread_pid = self.validate_thread(thread_idx)?;
+        query_address(thread_pid, addr)
+    }
+
+    // runs in: cmd thread, dbg thread
+    // deliver `sig` to the thread the next time it is continued. the signal
+    // is cleared once delivered. use 0 to clear a queued signal.
+    pub fn queue_signal(&self, thread_idx: DebuggerThreadIndex, sig: i32) -> Result<(), DebuggerError> {
+        if sig < 0 || sig > 64 {
+            return Err(DebuggerError::InvalidArguments);
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let thread_pid = Self::validate_thread_locked(&state, thread_idx)?;
+        let thread = state.threads.get_mut(&thread_pid).ok_or(DebuggerError::InvalidThread)?;
+        if !thread.pause_state.is_stopped() {
+            return Err(DebuggerError::NotStopped);
+        }
+
+        thread.queued_signal = sig;
+        Ok(())
+    }
+
+    // runs in: cmd thread, dbg thread
+    // frozen threads stay stopped when everything else is continued with
+    // cont_all. cont_one and step still work on them.
+    pub fn freeze_thread(&self, thread_idx: DebuggerThreadIndex) -> Result<(), DebuggerError> {
+        self.set_thread_frozen(thread_idx, true)
+    }
+
+    // runs in: cmd thread, dbg thread
+    pub fn thaw_thread(&self, thread_idx: DebuggerThreadIndex) -> Result<(), DebuggerError> {
+        self.set_thread_frozen(thread_idx, false)
+    }
+
+    // runs in: cmd thread, dbg thread
+    fn set_thread_frozen(&self, thread_idx: DebuggerThreadIndex, frozen: bool) -> Result<(), DebuggerError> {
+        let mut state = self.state.lock().unwrap();
+        let thread_pid = Self::validate_thread_locked(&state, thread_idx)?;
+        let thread = state.threads.get_mut(&thread_pid).ok_or(DebuggerError::InvalidThread)?;
+        thread.frozen = frozen;
+        Ok(())
+    }
+
+    // runs in: cmd thread, dbg thread
+    // ctrl-c style break. sends SIGSTOP to every running thread and returns right
+    // away. each stop shows up later in wait_next_event as a MiscSignalReceived
+    // event for SIGSTOP, and the next cont drops the SIGSTOP instead of passing
+    // it on. note: PTRACE_INTERRUPT would be cleaner, but it only works on
+    // threads attached with PTRACE_SEIZE and we use PTRACE_TRACEME.
+    pub fn interrupt_inferior(&self) -> Result<(), DebuggerError> {
+        let state = self.state.lock().unwrap();
+        // a thread stepping over a breakpoint will stop on its own soon
+        state.check_not_stepping()?;
+
+        // signals aren't tied to the ptrace thread, so no need to go through the dbg thread
+        for (pid, thread) in &state.threads {
+            if !thread.frozen && !thread.pause_state.is_stopped() {
+                superpt::tkill(*pid, libc::SIGSTOP);
+            }
+        }
+        Ok(())
+    }
+
+    // runs in: cmd thread, dbg thread
+    // the flags register split into named bits, see flags_arch
+    pub fn read_flags(&self, thread_idx: DebuggerThreadIndex) -> Result<FlagsSnapshot, DebuggerError> {
+        let reg_info = self
+            .nat_reg_info
+            .get_host_info(ARCH_FLAGS_REG)
+            .ok_or(DebuggerError::InvalidRegister)?;
+        let reg_size = ((reg_info.bit_len + 7) / 8) as usize;
+        if reg_size > 8 {
+            return Err(DebuggerError::InternalError);
+        }
+
+        let mut data = [0u8; 8];
+        self.read_register_by_idx_buf(thread_idx, ARCH_FLAGS_REG, &mut data[..reg_size])?;
+        let raw = if self.is_big_endian() {
+            u64::from_be_bytes(data) >> ((8 - reg_size) * 8)
+        } else {
+            u64::from_le_bytes(data)
+        };
+        Ok(FlagsSnapshot::new(raw))
+    }
+
+    // runs in: cmd thread, dbg thread
+    // set or clear one flag by name (like "zf"). the thread has to be stopped.
+    pub fn set_flag(&self, thread_idx: DebuggerThreadIndex, name: &str, value: bool) -> Result<(), DebuggerError> {
+        let info = find_flag_info(name).ok_or(DebuggerError::InvalidRegister)?;
+        if self.is_debugger_thread() {
+            let state = self.state.lock().unwrap();
+            return self.set_flag_impl(state, thread_idx, info.bit, value);
+        } else {
+            match self.send_cmd_req(DebuggerLinuxCmdReqOp::SetFlag(thread_idx, info.bit, value)) {
+                DebuggerLinuxCmdRspOp::Success => return Ok(()),
+                DebuggerLinuxCmdRspOp::Error(e) => return Err(e),
+                _ => return Err(DebuggerError::InternalError),
+            }
+        }
+    }
+
+    // runs in: cmd thread, dbg thread
+    // like step, but a call runs until it returns. this continues only this
+    // thread, and the event that comes back is StepComplete once the call
+    // returns (or whatever stopped the thread first).
+    pub fn step_over(&self, thread_idx: DebuggerThreadIndex) -> Result<(), DebuggerError> {
+        if self.is_debugger_thread() {
+            let state = self.state.lock().unwrap();
+            return self.step_over_impl(state, thread_idx);
+        } else {
+            self.validate_thread(thread_idx)?;
+            match self.send_cmd_req(DebuggerLinuxCmdReqOp::StepOver(thread_idx)) {
+                DebuggerLinuxCmdRspOp::Success => return Ok(()),
+                DebuggerLinuxCmdRspOp::Error(e) => return Err(e),
+                _ => return Err(DebuggerError::InternalError),
+            }
+        }
+    }
+
+    // runs in: dbg thread
+    // closes the fds wait_next_event listens on. cmd threads that ask for
+    // anything afterwards get NoThreads.
+    fn end_session(&self) {
+        if let Some(old_sstate) = self.session_state.write().unwrap().take() {
+            old_sstate.chan_cont.close_fds();
+        }
+    }
+
+    // runs in: dbg thread
+    // start debugging a process that's already running. like run, the thread
+    // that calls this becomes the dbg thread. every thread is stopped and in
+    // FirstStop afterwards, with the main thread as the current one.
+    pub fn attach(&self, pid: i32) -> Result<i32, DebuggerError> {
+        if !self.state.lock().unwrap().threads.is_empty() {
+            return Err(DebuggerError::AlreadyRunning);
+        }
+
+        if superpt::attach(pid).is_err() {
+            return Err(DebuggerError::AttachFailed);
+        }
+        if let Err(e) = self.setup_session(&[pid]) {
+            Self::detach_pids(&[pid]);
+            self.state.lock().unwrap().threads.clear();
+            return Err(e);
+        }
+
+        if let Err(e) = self.attach_threads(pid) {
+            let attached_pids: Vec<i32> = self.state.lock().unwrap().threads.keys().copied().collect();
+            Self::detach_pids(&attached_pids);
+            self.state.lock().unwrap().threads.clear();
+            self.end_session();
+            return Err(e);
+        }
+        Ok(pid)
+    }
+
+    // runs in: dbg thread
+    // the attach SIGSTOPs come back through the event loop the same way
+    // stop_all's do, so other stops that show up in the meantime are kept for
+    // wait_next_event. threads can be created while we're attaching to the
+    // others, so keep looking until a pass with everything stopped doesn't
+    // turn up anything new.
+    fn attach_threads(&self, pid: i32) -> Result<(), DebuggerError> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(thread) = state.threads.get_mut(&pid) {
+            thread.pause_state = DebuggerLinuxPauseState::Running;
+            thread.stop_all_pending = true;
+        }
+
+        loop {
+            let mut found_new = false;
+            for tid in read_task_ids(pid).map_err(|_| DebuggerError::AttachFailed)? {
+                if state.threads.contains_key(&tid) {
+                    continue;
+                }
+
+                // the thread exited before we got to it
+                if superpt::attach(tid).is_err() {
+                    continue;
+                }
+
+                let mut thread = state.new_thread(tid);
+                thread.pause_state = DebuggerLinuxPauseState::Running;
+                thread.stop_all_pending = true;
+                state.threads.insert(tid, thread);
+                found_new = true;
+            }
+
+            let waited = state.threads.values().any(|t| !t.pause_state.is_stopped());
+            self.stop_all_impl(state)?;
+            state = self.state.lock().unwrap();
+            if state.threads.values().any(|t| !t.pause_state.is_stopped()) {
+                return Err(DebuggerError::AttachFailed);
+            }
+            if !found_new && !waited {
+                break;
+            }
+        }
+
+        // the main thread went away while we were attaching
+        if !state.threads.contains_key(&pid) {
+            return Err(DebuggerError::AttachFailed);
+        }
+
+        let ptrace_options = self.ptrace_options();
+        for thread in state.threads.values_mut() {
+            superpt::setoptions(thread.pid, ptrace_options);
+            if thread.pause_state == DebuggerLinuxPauseState::StoppedUnknownReason {
+                thread.pause_state = DebuggerLinuxPauseState::FirstStop;
+            }
+        }
+        state.cur_thread_pid = Some(pid);
+        Ok(())
+    }
+
+    // runs in: cmd thread, dbg thread
+    // stop debugging and let the process keep running as if we were never
+    // there. breakpoints and watchpoints are taken out first. a wait_next_event
+    // that's blocked on the dbg thread returns NoThreads.
+    pub fn detach(&self) -> Result<(), DebuggerError> {
+        if self.is_debugger_thread() {
+            let state = self.state.lock().unwrap();
+            return self.detach_impl(state);
+        } else {
+            match self.send_cmd_req(DebuggerLinuxCmdReqOp::Detach) {
+                DebuggerLinuxCmdRspOp::Success => return Ok(()),
+                DebuggerLinuxCmdRspOp::Error(e) => return Err(e),
+                _ => return Err(DebuggerError::InternalError),
+            }
+        }
+    }
+
+    // runs in: dbg thread
+    fn detach_pids(pids: &[i32]) {
+        for pid in pids {
+            superpt::detach(*pid, 0);
+        }
+    }
+
+    // runs in: cmd thread
+    // stop every running thread and wait until they have all stopped
+    pub fn stop_all(&self) -> Result<(), DebuggerError> {
+        if self.is_debugger_thread() {
+            let state = self.state.lock().unwrap();
+            return self.stop_all_impl(state);
+        } else {
+            match self.send_cmd_req(DebuggerLinuxCmdReqOp::StopAll) {
+                DebuggerLinuxCmdRspOp::Success => return Ok(()),
+                DebuggerLinuxCmdRspOp::Error(e) => return Err(e),
+                _ => return Err(DebuggerError::InternalError),
+            }
+        }
+    }
+
+    // runs in: cmd thread, dbg thread
+    fn validate_thread_locked(
+        state: &DebuggerLinuxState,
+        thread_idx: DebuggerThreadIndex,
+    ) -> Result<i32, DebuggerError> {
+        let thread_pid = match thread_idx {
+            DebuggerThreadIndex::Current => state.cur_thread_pid.ok_or(DebuggerError::NoThreads)?,
+            DebuggerThreadIndex::Specific(i) => i as i32,
+        };
+
+        if !state.threads.contains_key(&thread_pid) {
+            return Err(DebuggerError::InvalidThread);
+        }
+
+        Ok(thread_pid)
+    }
+
+    // runs in: dbg thread
+    // our max address size is u64 so always return u64 for this
+    fn read_register_pc(
+        &self,
+        state: &mut DebuggerLinuxState,
+        thread_idx: DebuggerThreadIndex,
+    ) -> Result<u64, DebuggerError> {
+        if cfg!(target_arch = "x86_64") {
+            let mut out_data = [0u8; 8];
+            self.read_register_by_idx_buf_dbg(state, thread_idx, RegCodeAmd64::Rip as i32, &mut out_data)?;
+            return Ok(u64::from_le_bytes(out_data));
+        } else {
+            unimplemented!()
+        }
+    }
+
+    // runs in: dbg thread
+    fn read_register_sp(
+        &self,
+        state: &mut DebuggerLinuxState,
+        thread_idx: DebuggerThreadIndex,
+    ) -> Result<u64, DebuggerError> {
+        if cfg!(target_arch = "x86_64") {
+            let mut out_data = [0u8; 8];
+            self.read_register_by_idx_buf_dbg(state, thread_idx, RegCodeAmd64::Rsp as i32, &mut out_data)?;
+            return Ok(u64::from_le_bytes(out_data));
+        } else {
+            // no register map for any other host yet
+            Err(DebuggerError::InternalError)
+        }
+    }
+
+    // runs in: cmd thread, dbg thread
+    fn is_debugger_thread(&self) -> bool {
+        let sstate_opt_guard = self.session_state.read().unwrap();
+        let sstate_opt = sstate_opt_guard.as_ref();
+        match sstate_opt {
+            Some(sstate) => thread::current().id() == sstate.dbg_thread_id,
+            None => false,
+        }
+    }
+
+    // runs in: dbg thread
+    // "fixes up" registers even though their values
+    // may be different in reality
+    fn reg_val_arch_adjust(
+        &self,
+        thread_info: &DebuggerLinuxThread,
+        reg_info: &RegisterInfo,
+        src_bytes: &[u8],
+    ) -> Option<Vec<u8>> {
+        if cfg!(target_arch = "x86_64") {
+            // rip points past the int3 on x86 after hitting a breakpoint
+            if reg_info.mizl_idx == RegCodeAmd64::Rip as i32 {
+                if thread_info.pause_state == DebuggerLinuxPauseState::SwBreakpointHit {
+                    let mut modified_rip = u64::from_le_bytes(src_bytes.try_into().unwrap());
+                    modified_rip -= SW_BREAKPOINT_BYTES.len() as u64;
+                    return Some(Vec::from(u64::to_le_bytes(modified_rip)));
+                }
+            }
+        }
+        None
+    }
+
+    // runs in: dbg thread
+    // the opposite of reg_val_arch_adjust, for writing the cache back
+    fn reg_val_arch_unadjust(
+        &self,
+        thread_info: &DebuggerLinuxThread,
+        reg_info: &RegisterInfo,
+        src_bytes: &[u8],
+    ) -> Option<Vec<u8>> {
+        if cfg!(target_arch = "x86_64") {
+            if reg_info.mizl_idx == RegCodeAmd64::Rip as i32 {
+                if thread_info.pause_state == DebuggerLinuxPauseState::SwBreakpointHit {
+                    let mut modified_rip = u64::from_le_bytes(src_bytes.try_into().unwrap());
+                    modified_rip += SW_BREAKPOINT_BYTES.len() as u64;
+                    return Some(Vec::from(u64::to_le_bytes(modified_rip)));
+                }
+            }
+        }
+        None
+    }
+
+    // runs in: dbg thread
+    fn load_reg_cache(&self, state: &mut DebuggerLinuxState, thread_pid: i32) -> Result<(), DebuggerError> {
+        let thread_mut = state.threads.get_mut(&thread_pid).ok_or(DebuggerError::InvalidThread)?;
+
+        let reg_data = superpt::getregs(thread_mut.pid);
+        let fpreg_data = superpt::getfpregs(thread_mut.pid);
+
+        // println!("[checking adjusted arch reg vals]");
+        for item in get_regmap_entries() {
+            let src_bytes_start = item.native_off;
+            let src_bytes_end = src_bytes_start + item.size as usize;
+            let src_bytes: &[u8];
+            if cfg!(target_arch = "x86_64") {
+                src_bytes = match item.source {
+                    x if x == RegSrcAmd64::Standard as i32 => &reg_data[src_bytes_start..src_bytes_end],
+                    x if x == RegSrcAmd64::FloatingPoint as i32 => &fpreg_data[src_bytes_start..src_bytes_end],
+                    _ => unimplemented!(),
+                };
+            } else {
+                unimplemented!();
+            }
+
+            let reg_info = match self.nat_reg_info.get_host_info(item.reg_idx) {
+                Some(v) => v,
+                None => return Err(DebuggerError::InternalError),
+            };
+
+            // max means we have no idea where this is in sleigh space.
+            // don't try to guess since we would've had a unique addr
+            // assigned even if it was not in the sleigh.
+            if reg_info.addr == u32::MAX {
+                continue;
+            }
+
+            let mut dst_addr = reg_info.addr as u64;
+            match self.reg_val_arch_adjust(thread_mut, reg_info, &src_bytes) {
+                Some(new_val) => {
+                    // register value was modified
+                    thread_mut
+                        .reg_mem
+                        .write_bytes(&mut dst_addr, &new_val)
+                        .or(Err(DebuggerError::InvalidRegister))?;
+                }
+                None => {
+                    // register value is unchanged
+                    thread_mut
+                        .reg_mem
+                        .write_bytes(&mut dst_addr, &src_bytes)
+                        .or(Err(DebuggerError::InvalidRegister))?;
+                }
+            }
+        }
+
+        thread_mut.reg_mem_dirty = false;
+        Ok(())
+    }
+
+    // runs in: dbg thread
+    // writes every register in the cache back to the thread, if any changed
+    fn flush_reg_cache(&self, state: &mut DebuggerLinuxState, thread_pid: i32) -> Result<(), DebuggerError> {
+        let thread_mut = state.threads.get_mut(&thread_pid).ok_or(DebuggerError::InvalidThread)?;
+        if !thread_mut.reg_write_dirty {
+            return Ok(());
+        }
+
+        let mut reg_data = superpt::getregs(thread_mut.pid);
+        let mut fpreg_data = superpt::getfpregs(thread_mut.pid);
+
+        for item in get_regmap_entries() {
+            let dst_bytes_start = item.native_off;
+            let dst_bytes_end = dst_bytes_start + item.size as usize;
+            let dst_bytes: &mut [u8];
+            if cfg!(target_arch = "x86_64") {
+                dst_bytes = match item.source {
+                    x if x == RegSrcAmd64::Standard as i32 => &mut reg_data[dst_bytes_start..dst_bytes_end],
+                    x if x == RegSrcAmd64::FloatingPoint as i32 => &mut fpreg_data[dst_bytes_start..dst_bytes_end],
+                    _ => return Err(DebuggerError::InternalError),
+                };
+            } else {
+                return Err(DebuggerError::InternalError);
+            }
+
+            let reg_info = match self.nat_reg_info.get_host_info(item.reg_idx) {
+                Some(v) => v,
+                None => return Err(DebuggerError::InternalError),
+            };
+
+            // never loaded into the cache either
+            if reg_info.addr == u32::MAX {
+                continue;
+            }
+
+            let mut src_addr = reg_info.addr as u64;
+            thread_mut
+                .reg_mem
+                .read_bytes(&mut src_addr, dst_bytes, item.size as i32)
+                .or(Err(DebuggerError::InvalidRegister))?;
+
+            if let Some(new_val) = self.reg_val_arch_unadjust(thread_mut, reg_info, dst_bytes) {
+                dst_bytes.copy_from_slice(&new_val);
+            }
+        }
+
+        superpt::setregs(thread_mut.pid, &reg_data);
+        superpt::setfpregs(thread_mut.pid, &fpreg_data);
+        thread_mut.reg_write_dirty = false;
+        Ok(())
+    }
+
+    // runs in: dbg thread
+    fn write_register_impl(
+        &self,
+        state: &mut DebuggerLinuxState,
+        thread_idx: DebuggerThreadIndex,
+        name: &str,
+        data: &[u8],
+    ) -> Result<(), DebuggerError> {
+        let thread_pid = Self::validate_thread_locked(&state, thread_idx)?;
+        self.verify_stopped_by_thread_idx(state, thread_idx)?;
+
+        let reg_info = self
+            .nat_reg_info
+            .get_reg_info(name, true)
+            .ok_or(DebuggerError::InvalidRegister)?;
+
+        let reg_start = reg_info.addr as u64;
+        let reg_size = ((reg_info.bit_len + 7) / 8) as u64;
+        if data.len() < reg_size as usize {
+            return Err(DebuggerError::InvalidRegister);
+        }
+
+        // only registers ptrace can set are written back, so anything
+        // outside of them (like sleigh's separate flag registers) is lost
+        let reg_end = reg_start + reg_size;
+        let is_native = get_regmap_entries()
+            .iter()
+            .any(|item| match self.nat_reg_info.get_host_info(item.reg_idx) {
+                Some(host_info) if host_info.addr != u32::MAX => {
+                    let host_start = host_info.addr as u64;
+                    reg_start >= host_start && reg_end <= host_start + item.size as u64
+                }
+                _ => false,
+            });
+        if !is_native {
+            return Err(DebuggerError::InvalidRegister);
+        }
+
+        if state.is_reg_cache_dirty(thread_pid) {
+            self.load_reg_cache(state, thread_pid)?;
+        }
+
+        let thread = state.threads.get_mut(&thread_pid).ok_or(DebuggerError::InvalidThread)?;
+        let mut reg_start_mut = reg_start;
+        thread
+            .reg_mem
+            .write_bytes(&mut reg_start_mut, &data[..reg_size as usize])
+            .or(Err(DebuggerError::InvalidRegister))?;
+        thread.reg_write_dirty = true;
+
+        // moving pc off of a breakpoint means we aren't on it anymore, and
+        // moving it onto one means cont has to step over it first
+        if thread.pause_state.is_stopped() && self.overlaps_pc(reg_start, reg_end)? {
+            let pc = self.read_register_pc(state, DebuggerThreadIndex::Specific(thread_pid as u32))?;
+            let on_bp = state.bp_cont.get_breakpoint(pc).is_some();
+            let thread = state.threads.get_mut(&thread_pid).ok_or(DebuggerError::InvalidThread)?;
+            thread.pause_state = match on_bp {
+                true => DebuggerLinuxPauseState::SwBreakpointHit,
+                false => DebuggerLinuxPauseState::StoppedUnknownReason,
+            };
+            trace!(
+                "thread {} pause state -> {} (pc written)",
+                thread_pid, thread.pause_state
+            );
+        }
+
+        self.flush_reg_cache(state, thread_pid)
+    }
+
+    fn overlaps_pc(&self, reg_start: u64, reg_end: u64) -> Result<bool, DebuggerError> {
+        if cfg!(target_arch = "x86_64") {
+            match self.nat_reg_info.get_host_info(RegCodeAmd64::Rip as i32) {
+                Some(pc_info) => {
+                    let pc_start = pc_info.addr as u64;
+                    Ok(reg_start < pc_start + 8 && pc_start < reg_end)
+                }
+                None => Ok(false),
+            }
+        } else {
+            Err(DebuggerError::InternalError)
+        }
+    }
+
+    // runs in: dbg thread
+    fn step_impl(
+        &self,
+        mut state_guard: MutexGuard<'_, DebuggerLinuxState>,
+        thread_idx: DebuggerThreadIndex,
+        step_kind: DebuggerLinuxStepKind,
+    ) -> Result<(), DebuggerError> {
+        let state = state_guard.deref_mut();
+        let thread_pid = Self::validate_thread_locked(&state, thread_idx)?;
+        state.check_not_stepping()?;
+        {
+            let mut bp_opt: Option<BreakpointEntry> = None;
+
+            let thread = state.threads.get(&thread_pid).ok_or(DebuggerError::InvalidThread)?;
+            if thread.pause_state == DebuggerLinuxPauseState::SwBreakpointHit {
+                // pc is always adjusted to the address of the potential
+                // sw breakpoint that caused a stop, so this is fine.
+                let pc = self.read_register_pc(state, thread_idx.clone())?;
+                bp_opt = state.bp_cont.get_breakpoint(pc).cloned();
+            }
+
+            match bp_opt {
+                Some(bp) => {
+                    // only the register cache has pc on the breakpoint. the thread
+                    // itself is still past it, which would skip the instruction.
+                    self.write_register_pc(state, thread_pid, bp.addr)?;
+
+                    let thread = state.threads.get_mut(&thread_pid).ok_or(DebuggerError::InvalidThread)?;
+                    let orig_bytes = &bp.orig_bytes;
+                    let mut mut_addr = bp.addr;
+                    thread
+                        .proc_mem
+                        .write_bytes(&mut mut_addr, &orig_bytes)
+                        .or(Err(DebuggerError::MemoryAccessFailed))?;
+
+                    thread.pause_state = match step_kind {
+                        DebuggerLinuxStepKind::Step => DebuggerLinuxPauseState::SteppingBp,
+                        DebuggerLinuxStepKind::StepBpContOne => DebuggerLinuxPauseState::SteppingBpContOne,
+                        DebuggerLinuxStepKind::StepBpContAll => DebuggerLinuxPauseState::SteppingBpContAll,
+                    };
+                    trace!(
+                        "thread {} pause state -> {} (step over bp)",
+                        thread_pid, thread.pause_state
+                    );
+                    state.mark_dirty();
+                    state.stepping_thread_pid = Some(thread_pid);
+                    state.stepping_thread_bp = Some(bp);
+                }
+                None => {
+                    // we didn't hit a sw breakpoint or we couldn't
+                    // find a breakpoint (so it's not our's?)
+
+                    // assume registers will change after this
+                    state.mark_dirty();
+
+                    // this is cleared once we get the trap we were expecting
+                    state.stepping_thread_pid = Some(thread_pid);
+
+                    let thread = state.threads.get_mut(&thread_pid).ok_or(DebuggerError::InvalidThread)?;
+                    thread.pause_state = DebuggerLinuxPauseState::Stepping;
+                    trace!("thread {} pause state -> {} (step)", thread_pid, thread.pause_state);
+                }
+            };
+        }
+        std::mem::drop(state_guard); // unlock state
+
+        superpt::singlestep(thread_pid);
+        Ok(())
+    }
+
+    // runs in: dbg thread
+    fn step_over_impl(
+        &self,
+        mut state_guard: MutexGuard<'_, DebuggerLinuxState>,
+        thread_idx: DebuggerThreadIndex,
+    ) -> Result<(), DebuggerError> {
+        let state = state_guard.deref_mut();
+        let thread_pid = Self::validate_thread_locked(&state, thread_idx)?;
+        state.check_not_stepping()?;
+        if state.step_over_bp.is_some() {
+            return Err(DebuggerError::Busy);
+        }
+        self.verify_stopped_by_thread_idx(state, thread_idx)?;
+
+        let pc = self.read_register_pc(state, thread_idx)?;
+        let thread = state.threads.get_mut(&thread_pid).ok_or(DebuggerError::InvalidThread)?;
+        let (mnemonic, ins_len) = {
+            let mem_bp_wrapped = BreakpointWrapMemView {
+                mem_view: &mut thread.proc_mem,
+                bp_cont: &state.bp_cont,
+            };
+            self.disasm.disasm_mnemonic(&mem_bp_wrapped, pc)?
+        };
+
+        if !mnemonic.eq_ignore_ascii_case("call") {
+            return self.step_impl(state_guard, thread_idx, DebuggerLinuxStepKind::Step);
+        }
+
+        let ret_addr = pc.checked_add(ins_len).ok_or(DebuggerError::InternalError)?;
+        // the call pushes the return address and ret pops it, so sp ends up here again
+        let min_sp = self.read_register_sp(state, thread_idx)?;
+        self.run_to_temp_bp_impl(state_guard, thread_idx, ret_addr, min_sp)
+    }
+
+    // runs in: dbg thread
+    fn step_out_impl(
+        &self,
+        mut state_guard: MutexGuard<'_, DebuggerLinuxState>,
+        thread_idx: DebuggerThreadIndex,
+    ) -> Result<(), DebuggerError> {
+        let state = state_guard.deref_mut();
+        let thread_pid = Self::validate_thread_locked(&state, thread_idx)?;
+        state.check_not_stepping()?;
+        if state.step_over_bp.is_some() {
+            return Err(DebuggerError::Busy);
+        }
+        self.verify_stopped_by_thread_idx(state, thread_idx)?;
+
+        let pc = self.read_register_pc(state, thread_idx)?;
+        let sp = self.read_register_sp(state, thread_idx)?;
+        let thread = state.threads.get_mut(&thread_pid).ok_or(DebuggerError::InvalidThread)?;
+
+        let mut code = [0u8; RETURN_ADDR_CODE_LEN];
+        {
+            // a user breakpoint on the prologue shouldn't hide it
+            let mem_bp_wrapped = BreakpointWrapMemView {
+                mem_view: &mut thread.proc_mem,
+                bp_cont: &state.bp_cont,
+            };
+            let mut mut_addr = pc;
+            mem_bp_wrapped
+                .read_bytes(&mut mut_addr, &mut code, RETURN_ADDR_CODE_LEN as i32)
+                .or(Err(DebuggerError::MemoryAccessFailed))?;
+        }
+
+        let ret_slot = find_return_addr_slot(&code, sp).ok_or(DebuggerError::NoFrame)?;
+        let mut ret_bytes = [0u8; 8];
+        let mut mut_addr = ret_slot;
+        thread
+            .proc_mem
+            .read_bytes(&mut mut_addr, &mut ret_bytes, 8)
+            .or(Err(DebuggerError::MemoryAccessFailed))?;
+
+        let ret_addr = u64::from_le_bytes(ret_bytes);
+        // ret pops the return address, so the caller's sp is just above it
+        let min_sp = ret_slot.checked_add(8).ok_or(DebuggerError::NoFrame)?;
+        self.run_to_temp_bp_impl(state_guard, thread_idx, ret_addr, min_sp)
+    }
+
+    // runs in: dbg thread
+    // continues only this thread until it comes back to addr with sp at or
+    // above min_sp, which finish_step_over turns into a step
+    fn run_to_temp_bp_impl(
+        &self,
+        mut state_guard: MutexGuard<'_, DebuggerLinuxState>,
+        thread_idx: DebuggerThreadIndex,
+        addr: u64,
+        min_sp: u64,
+    ) -> Result<(), DebuggerError> {
+        let state = state_guard.deref_mut();
+        let thread_pid = Self::validate_thread_locked(&state, thread_idx)?;
+        let thread = state.threads.get_mut(&thread_pid).ok_or(DebuggerError::InvalidThread)?;
+
+        // a user breakpoint on the return address stops us by itself, and
+        // writing ours over it would lose its original bytes
+        if state.bp_cont.get_breakpoint(addr).is_none() {
+            let bp_bytes = SW_BREAKPOINT_BYTES.to_vec();
+            let mut orig_bytes = vec![0u8; bp_bytes.len()];
+
+            let mut mut_addr = addr;
+            thread
+                .proc_mem
+                .read_bytes(&mut mut_addr, &mut orig_bytes, bp_bytes.len() as i32)
+                .or(Err(DebuggerError::MemoryAccessFailed))?;
+            mut_addr = addr;
+            thread
+                .proc_mem
+                .write_bytes(&mut mut_addr, &bp_bytes)
+                .or(Err(DebuggerError::MemoryNotWritable))?;
+
+            let bp = BreakpointEntry::new(addr, bp_bytes, orig_bytes);
+            state.step_over_bp = Some((thread_pid, bp, min_sp));
+        }
+
+        // this steps over a user breakpoint at pc first if there is one
+        let cont_result = self.cont_one_impl(state_guard, thread_idx);
+        if cont_result.is_err() {
+            let mut state = self.state.lock().unwrap();
+            Self::remove_step_over_bp(&mut state);
+        }
+        cont_result
+    }
+
+    // runs in: dbg thread
+    // puts the original bytes back under step_over's breakpoint, if there is one
+    fn remove_step_over_bp(state: &mut DebuggerLinuxState) -> Option<(i32, BreakpointEntry, u64)> {
+        let (thread_pid, bp, min_sp) = state.step_over_bp.take()?;
+        if let Some(thread) = state.threads.get_mut(&thread_pid) {
+            let mut mut_addr = bp.addr;
+            if thread.proc_mem.write_bytes(&mut mut_addr, &bp.orig_bytes).is_err() {
+                debug!("couldn't remove step over breakpoint at {:#x}", bp.addr);
+            }
+        }
+        Some((thread_pid, bp, min_sp))
+    }
+
+    // runs in: dbg thread
+    // called for every stop that gets reported while a step over is running.
+    // if the thread stopped on our breakpoint, the stop is turned into a step.
+    // returns true if the stop was from a deeper recursive call instead and the
+    // thread was sent on its way again, so there's nothing to report.
+    fn finish_step_over(
+        &self,
+        state: &mut DebuggerLinuxState,
+        thread_pid: i32,
+        result: &mut DebuggerEvent,
+    ) -> Result<bool, DebuggerError> {
+        let (bp_pid, bp, min_sp) = match Self::remove_step_over_bp(state) {
+            Some(v) => v,
+            None => return Ok(false),
+        };
+
+        if bp_pid != thread_pid || result.kind != DebuggerEventKind::BreakpointHit || result.bp_idx != u32::MAX {
+            return Ok(false);
+        }
+
+        let thread_idx = DebuggerThreadIndex::Specific(thread_pid as u32);
+        if self.read_register_pc(state, thread_idx)? != bp.addr {
+            return Ok(false);
+        }
+
+        let sp = self.read_register_sp(state, thread_idx)?;
+
+        // the real pc is still past the breakpoint, only the cache was adjusted
+        self.write_register_pc(state, thread_pid, bp.addr)?;
+
+        if sp < min_sp {
+            // step off it like a user breakpoint. once the step is done, the
+            // breakpoint bytes go back and the thread is continued.
+            let thread = state.threads.get_mut(&thread_pid).ok_or(DebuggerError::InvalidThread)?;
+            thread.pause_state = DebuggerLinuxPauseState::SteppingBpContOne;
+            trace!(
+                "thread {} pause state -> {} (step over, recursive)",
+                thread_pid, thread.pause_state
+            );
+            state.stepping_thread_pid = Some(thread_pid);
+            state.stepping_thread_bp = Some(bp.clone());
+            state.step_over_bp = Some((bp_pid, bp, min_sp));
+            superpt::singlestep(thread_pid);
+            return Ok(true);
+        }
+
+        let thread = state.threads.get_mut(&thread_pid).ok_or(DebuggerError::InvalidThread)?;
+        thread.pause_state = DebuggerLinuxPauseState::StepCompleted;
+        trace!(
+            "thread {} pause state -> {} (step over)",
+            thread_pid, thread.pause_state
+        );
+
+        *result = DebuggerEvent::new(DebuggerEventKind::StepComplete, result.code);
+        Ok(false)
+    }
+
+    // runs in: dbg thread
+    // sets pc on the thread itself rather than in the register cache
+    fn write_register_pc(&self, state: &mut DebuggerLinuxState, thread_pid: i32, pc: u64) -> Result<(), DebuggerError> {
+        if cfg!(target_arch = "x86_64") {
+            let entry = find_regmap_entry(RegCodeAmd64::Rip as i32).ok_or(DebuggerError::InternalError)?;
+            let mut reg_data = superpt::getregs(thread_pid);
+            reg_data[entry.native_off..entry.native_off + 8].copy_from_slice(&pc.to_le_bytes());
+            superpt::setregs(thread_pid, &reg_data);
+            state.mark_dirty();
+            Ok(())
+        } else {
+            Err(DebuggerError::InternalError)
+        }
+    }
+
+    // runs in: dbg thread
+    fn step_replace_bp_impl(
         &self,
         state: &mut DebuggerLinuxState,
         thread_idx: DebuggerThreadIndex,
     ) -> Result<(), DebuggerError> {
-        let thread_pid = Self::get_thread_pid_or_current(&state, thread_idx)?;
+        let thread_pid = Self::validate_thread_locked(&state, thread_idx)?;
         let thread = state.threads.get_mut(&thread_pid).ok_or(DebuggerError::InvalidThread)?;
 
         let bp = state.stepping_thread_bp.as_ref().ok_or(DebuggerError::InternalError)?;
@@ -453,7 +1644,9 @@ impl DebuggerLinux {
         mut state: MutexGuard<'_, DebuggerLinuxState>,
         thread_idx: DebuggerThreadIndex,
     ) -> Result<(), DebuggerError> {
-        let thread_pid = Self::get_thread_pid_or_current(&state, thread_idx)?;
+        let thread_pid = Self::validate_thread_locked(&state, thread_idx)?;
+        state.check_not_stepping()?;
+        let queued_signal: i32;
         {
             let thread = state.threads.get_mut(&thread_pid).ok_or(DebuggerError::InvalidThread)?;
             if thread.pause_state == DebuggerLinuxPauseState::SwBreakpointHit {
@@ -461,23 +1654,31 @@ impl DebuggerLinux {
                 return self.step_impl(state, thread_idx, DebuggerLinuxStepKind::StepBpContOne);
             }
 
-            state.reg_mem_dirty = true;
+            queued_signal = std::mem::take(&mut thread.queued_signal);
+            thread.pause_state = DebuggerLinuxPauseState::Running;
+            trace!("thread {} pause state -> {} (cont one)", thread_pid, thread.pause_state);
+            state.mark_dirty();
         }
         std::mem::drop(state); // unlock state
 
-        superpt::cont(thread_pid);
+        superpt::cont(thread_pid, queued_signal);
         Ok(())
     }
 
     // runs in: dbg thread
     fn cont_impl(&self, mut state: MutexGuard<'_, DebuggerLinuxState>) -> Result<(), DebuggerError> {
-        let mut thread_pids: Vec<i32>;
+        state.check_not_stepping()?;
+        let mut thread_pids: Vec<(i32, i32)>;
         {
-            state.reg_mem_dirty = true;
+            state.mark_dirty();
             thread_pids = Vec::with_capacity(state.threads.len());
             for (pid, thread) in &state.threads {
+                if thread.frozen {
+                    continue;
+                }
+
                 let pid_value = *pid;
-                thread_pids.push(pid_value);
+                thread_pids.push((pid_value, thread.queued_signal));
                 if thread.pause_state == DebuggerLinuxPauseState::SwBreakpointHit {
                     // stop building the list and pass this along to step_impl instead
                     // todo: optimize this to never build the list if stopped at bp
@@ -488,15 +1689,231 @@ impl DebuggerLinux {
                     );
                 }
             }
+
+            // everything not frozen gets continued, so those queued signals will be delivered
+            for thread in state.threads.values_mut() {
+                if !thread.frozen {
+                    thread.queued_signal = 0;
+                    thread.pause_state = DebuggerLinuxPauseState::Running;
+                    trace!("thread {} pause state -> {} (cont all)", thread.pid, thread.pause_state);
+                }
+            }
         }
         std::mem::drop(state); // unlock state
 
-        for thread_pid in thread_pids {
-            superpt::cont(thread_pid);
+        for (thread_pid, queued_signal) in thread_pids {
+            superpt::cont(thread_pid, queued_signal);
+        }
+        Ok(())
+    }
+
+    // runs in: dbg thread
+    fn stop_all_impl<'a>(&'a self, mut state: MutexGuard<'a, DebuggerLinuxState>) -> Result<(), DebuggerError> {
+        // a thread stepping over a breakpoint will stop on its own soon
+        state.check_not_stepping()?;
+
+        // a thread that still has a SIGSTOP on the way doesn't need another
+        for thread in state.threads.values_mut() {
+            if !thread.pause_state.is_stopped() && !thread.stop_all_pending {
+                superpt::tkill(thread.pid, libc::SIGSTOP);
+                thread.stop_all_pending = true;
+            }
+        }
+
+        // threads can stop for some other reason (or exit) before the SIGSTOP
+        // gets to them. those events go through the usual path and are kept
+        // for wait_next_event, only our own SIGSTOPs are swallowed.
+        state.stopping_all = true;
+        while state.threads.values().any(|t| !t.pause_state.is_stopped()) {
+            std::mem::drop(state); // unlock state
+
+            let raw_event = self.event_source.wait_event();
+            let debug_event_opt = raw_event.and_then(|v| self.handle_raw_event(v));
+
+            state = self.state.lock().unwrap();
+            match raw_event {
+                Some(_) => state.queued_events.extend(debug_event_opt),
+                None => break,
+            }
+        }
+        state.stopping_all = false;
+
+        state.mark_dirty();
+        Ok(())
+    }
+
+    // runs in: dbg thread
+    fn detach_impl(&self, state: MutexGuard<'_, DebuggerLinuxState>) -> Result<(), DebuggerError> {
+        if state.threads.is_empty() {
+            return Err(DebuggerError::NoThreads);
+        }
+
+        // ptrace can only let go of stopped threads
+        self.stop_all_impl(state)?;
+
+        // a thread that stopped for something else before our SIGSTOP got to
+        // it still has the SIGSTOP queued, which would stop it again as soon
+        // as we let go. let it take the SIGSTOP now instead.
+        let mut state_guard = self.state.lock().unwrap();
+        let mut leftover: Vec<(i32, DebuggerLinuxPauseState)> = Vec::new();
+        for thread in state_guard.threads.values_mut() {
+            if thread.stop_all_pending {
+                leftover.push((thread.pid, thread.pause_state.clone()));
+                thread.pause_state = DebuggerLinuxPauseState::Running;
+                superpt::cont(thread.pid, 0);
+            }
+        }
+
+        if !leftover.is_empty() {
+            self.stop_all_impl(state_guard)?;
+            state_guard = self.state.lock().unwrap();
+
+            // the SIGSTOP shows up before the thread runs anything, so it's
+            // still wherever it was before
+            for (pid, pause_state) in leftover {
+                let thread = match state_guard.threads.get_mut(&pid) {
+                    Some(v) => v,
+                    None => continue,
+                };
+                if thread.pause_state == DebuggerLinuxPauseState::StoppedUnknownReason {
+                    thread.pause_state = pause_state;
+                }
+            }
+        }
+
+        let state = state_guard.deref_mut();
+        Self::remove_step_over_bp(state);
+
+        let thread_pids: Vec<i32> = state.threads.keys().copied().collect();
+        for pid in &thread_pids {
+            // a thread sitting on a breakpoint is really one byte past it
+            let thread = state.threads.get(pid).ok_or(DebuggerError::InternalError)?;
+            if thread.pause_state == DebuggerLinuxPauseState::SwBreakpointHit {
+                let thread_idx = DebuggerThreadIndex::Specific(*pid as u32);
+                let pc = self.read_register_pc(state, thread_idx)?;
+                if state.bp_cont.get_breakpoint(pc).is_some() {
+                    self.write_register_pc(state, *pid, pc)?;
+                }
+            }
+        }
+
+        if let Some(thread) = thread_pids.first().and_then(|pid| state.threads.get_mut(pid)) {
+            for bp in state.bp_cont.iter() {
+                let mut mut_addr = bp.addr;
+                if thread.proc_mem.write_bytes(&mut mut_addr, &bp.orig_bytes).is_err() {
+                    debug!("couldn't remove breakpoint at {:#x} before detaching", bp.addr);
+                }
+            }
+        }
+
+        let no_watchpoints = [None; WATCHPOINT_SLOT_COUNT];
+        for pid in &thread_pids {
+            let thread = state.threads.get(pid).ok_or(DebuggerError::InternalError)?;
+            _ = write_watchpoints(*pid, &no_watchpoints);
+            superpt::detach(*pid, thread.queued_signal);
+        }
+
+        // the session itself sticks around until the next run or attach
+        // replaces it, since wait_next_event may still be using its fds
+        let mem_read_mode = state.mem_read_mode;
+        let mem_cache = state.mem_cache.take();
+        *state = DebuggerLinuxState::new();
+        state.mem_read_mode = mem_read_mode;
+        state.mem_cache = mem_cache;
+        state.mark_dirty();
+        debug!("detached from {} threads", thread_pids.len());
+        Ok(())
+    }
+
+    // runs in: dbg thread
+    fn set_flag_impl(
+        &self,
+        mut state: MutexGuard<'_, DebuggerLinuxState>,
+        thread_idx: DebuggerThreadIndex,
+        bit: u32,
+        value: bool,
+    ) -> Result<(), DebuggerError> {
+        let thread_pid = Self::validate_thread_locked(&state, thread_idx)?;
+        self.verify_stopped_by_thread_idx(&mut state, thread_idx)?;
+
+        // the flags register lives in the standard (getregs) set
+        let entry = find_regmap_entry(ARCH_FLAGS_REG).ok_or(DebuggerError::InvalidRegister)?;
+        let mut reg_data = superpt::getregs(thread_pid);
+        let flags_data = &mut reg_data[entry.native_off..entry.native_off + entry.size as usize];
+
+        let mut flags_bytes = [0u8; 8];
+        flags_bytes[..flags_data.len()].copy_from_slice(flags_data);
+        let mut flags = u64::from_le_bytes(flags_bytes);
+        if value {
+            flags |= 1 << bit;
+        } else {
+            flags &= !(1 << bit);
+        }
+        let flags_len = flags_data.len();
+        flags_data.copy_from_slice(&flags.to_le_bytes()[..flags_len]);
+
+        superpt::setregs(thread_pid, &reg_data);
+        state.mark_dirty();
+        Ok(())
+    }
+
+    // runs in: dbg thread
+    // writes `slots` to every thread's debug registers and keeps them on success.
+    // every thread has to be stopped since this goes through ptrace.
+    fn set_watchpoints_impl(
+        &self,
+        state: &mut DebuggerLinuxState,
+        slots: [Option<DebuggerLinuxWatchpoint>; WATCHPOINT_SLOT_COUNT],
+    ) -> Result<(), DebuggerError> {
+        for thread in state.threads.values() {
+            self.verify_stopped_by_thread(thread)?;
+        }
+
+        for pid in state.threads.keys() {
+            write_watchpoints(*pid, &slots)?;
         }
+        state.watchpoints = slots;
         Ok(())
     }
 
+    // runs in: dbg thread
+    fn add_watchpoint_impl(
+        &self,
+        mut state: MutexGuard<'_, DebuggerLinuxState>,
+        thread_idx: DebuggerThreadIndex,
+        wp: DebuggerLinuxWatchpoint,
+    ) -> Result<u32, DebuggerError> {
+        Self::validate_thread_locked(&state, thread_idx)?;
+
+        let slot = state
+            .watchpoints
+            .iter()
+            .position(|w| w.is_none())
+            .ok_or(DebuggerError::NoFreeWatchpoints)?;
+
+        let mut slots = state.watchpoints;
+        slots[slot] = Some(wp);
+        self.set_watchpoints_impl(&mut state, slots)?;
+        Ok(slot as u32)
+    }
+
+    // runs in: dbg thread
+    fn remove_watchpoint_impl(
+        &self,
+        mut state: MutexGuard<'_, DebuggerLinuxState>,
+        thread_idx: DebuggerThreadIndex,
+        slot: u32,
+    ) -> Result<(), DebuggerError> {
+        Self::validate_thread_locked(&state, thread_idx)?;
+
+        let mut slots = state.watchpoints;
+        match slots.get_mut(slot as usize) {
+            Some(v) if v.is_some() => *v = None,
+            _ => return Err(DebuggerError::InvalidBreakpoint),
+        }
+        self.set_watchpoints_impl(&mut state, slots)
+    }
+
     // runs in: dbg thread (or cmd thread assuming we checked /proc/mem)
     fn disassemble_one_impl(
         &self,
@@ -505,6 +1922,7 @@ impl DebuggerLinux {
     ) -> Result<DisasmDispInstruction, DebuggerError> {
         let disasm = &self.disasm;
         let state = state_guard.deref_mut();
+        state.update_mem_cache_bypass();
         let cur_thread_pid = state.cur_thread_pid.ok_or(DebuggerError::NoThreads)?;
         let thread = state
             .threads
@@ -518,14 +1936,35 @@ impl DebuggerLinux {
                 mem_view: &mut thread.proc_mem,
                 bp_cont: &state.bp_cont,
             };
-            display_ins = disasm
-                .disasm_display(&mem_bp_wrapped, addr)
-                .or(Err(DebuggerError::DisassemblyFailed))?;
+            display_ins = disasm.disasm_display(&mem_bp_wrapped, addr)?;
         }
 
         Ok(display_ins)
     }
 
+    // runs in: dbg thread (or cmd thread assuming we checked /proc/mem)
+    fn disassemble_range_impl(
+        &self,
+        mut state_guard: MutexGuard<'_, DebuggerLinuxState>,
+        start: u64,
+        count: usize,
+    ) -> Result<Vec<DisasmDispInstruction>, DebuggerError> {
+        let state = state_guard.deref_mut();
+        state.update_mem_cache_bypass();
+        let cur_thread_pid = state.cur_thread_pid.ok_or(DebuggerError::NoThreads)?;
+        let thread = state
+            .threads
+            .get_mut(&cur_thread_pid)
+            .ok_or(DebuggerError::InvalidThread)?;
+
+        // one wrapper for the whole range instead of one per instruction
+        let mem_bp_wrapped = BreakpointWrapMemView {
+            mem_view: &mut thread.proc_mem,
+            bp_cont: &state.bp_cont,
+        };
+        Ok(self.disasm.disasm_display_range(&mem_bp_wrapped, start, count)?)
+    }
+
     // runs in: dbg thread (or cmd thread assuming we checked /proc/mem)
     fn read_bytes_impl(
         &self,
@@ -535,6 +1974,7 @@ impl DebuggerLinux {
         out_data: &mut [u8],
     ) -> Result<u64, DebuggerError> {
         let state = state_guard.deref_mut();
+        state.update_mem_cache_bypass();
         let thread = state.threads.get_mut(&thread_pid).ok_or(DebuggerError::InvalidThread)?;
 
         let mut mut_addr = addr;
@@ -552,14 +1992,85 @@ impl DebuggerLinux {
         Ok(mut_addr)
     }
 
+    // runs in: dbg thread (or cmd thread assuming we checked /proc/mem)
+    fn scan_memory_impl(
+        &self,
+        mut state_guard: MutexGuard<'_, DebuggerLinuxState>,
+        thread_pid: i32,
+        start: u64,
+        end: u64,
+        needle: &[u8],
+        mask: Option<&[u8]>,
+    ) -> Result<Option<u64>, DebuggerError> {
+        let state = state_guard.deref_mut();
+        state.update_mem_cache_bypass();
+        let thread = state.threads.get_mut(&thread_pid).ok_or(DebuggerError::InvalidThread)?;
+
+        // only look where something is mapped. the address space is huge and
+        // reading the gaps a page at a time would take forever. mappings that
+        // touch come back as one region, so a match can span both.
+        let regions = read_mapped_regions(thread_pid)?;
+
+        // our own 0xcc bytes shouldn't match (or stop a match)
+        let mem_bp_wrapped = BreakpointWrapMemView {
+            mem_view: &mut thread.proc_mem,
+            bp_cont: &state.bp_cont,
+        };
+        for (region_start, region_end) in regions {
+            let scan_start = region_start.max(start);
+            let scan_end = region_end.min(end);
+            if scan_start >= scan_end {
+                continue;
+            }
+
+            if let Some(addr) = mem_bp_wrapped.find(scan_start, scan_end, needle, mask) {
+                return Ok(Some(addr));
+            }
+        }
+        Ok(None)
+    }
+
     fn get_register_read_range_by_idx(&self, reg_idx: i32, max_read_size: usize) -> Result<(u64, i32), DebuggerError> {
         let reg_info = self
             .nat_reg_info
             .get_host_info(reg_idx)
             .ok_or(DebuggerError::InvalidRegister)?;
 
+        self.get_register_read_range(reg_info, max_read_size)
+    }
+
+    fn get_register_read_range_by_name(&self, name: &str, max_read_size: usize) -> Result<(u64, i32), DebuggerError> {
+        let reg_info = self
+            .nat_reg_info
+            .get_reg_info(name, true)
+            .ok_or(DebuggerError::InvalidRegister)?;
+
+        self.get_register_read_range(reg_info, max_read_size)
+    }
+
+    // reg_info can be a host register or one inside it like eax or ah. either
+    // way it's read from its own spot in reg_mem, which only has the host
+    // registers' bytes in it, so it can't reach past the end of its host.
+    fn get_register_read_range(
+        &self,
+        reg_info: &RegisterInfo,
+        max_read_size: usize,
+    ) -> Result<(u64, i32), DebuggerError> {
+        let host_info = self
+            .nat_reg_info
+            .get_host_info(reg_info.mizl_idx)
+            .ok_or(DebuggerError::InvalidRegister)?;
+        if reg_info.addr == u32::MAX || host_info.addr == u32::MAX {
+            return Err(DebuggerError::InvalidRegister);
+        }
+
         let reg_start = reg_info.addr as u64;
         let reg_size = (reg_info.bit_len + 7) / 8 as i32;
+        let host_start = host_info.addr as u64;
+        let host_end = host_start + ((host_info.bit_len + 7) / 8) as u64;
+        if reg_start < host_start || reg_start + reg_size as u64 > host_end {
+            return Err(DebuggerError::InvalidRegister);
+        }
 
         // prevent reading more bytes than possible
         if max_read_size > i32::MAX as usize || max_read_size < (reg_size as usize) {
@@ -585,11 +2096,9 @@ impl DebuggerLinux {
         reg_idx: i32,
         out_data: &mut [u8],
     ) -> Result<(), DebuggerError> {
-        let reg_mem_dirty = state.reg_mem_dirty;
-
         let (reg_start, read_size) = self.get_register_read_range_by_idx(reg_idx, out_data.len())?;
-        let use_thread_pid = Self::get_thread_pid_or_current(&state, thread_idx)?;
-        if reg_mem_dirty {
+        let use_thread_pid = Self::validate_thread_locked(&state, thread_idx)?;
+        if state.is_reg_cache_dirty(use_thread_pid) {
             if self.is_debugger_thread() {
                 self.load_reg_cache(state, use_thread_pid)?;
                 Self::read_register_final(state, use_thread_pid, reg_start, out_data, read_size)?;
@@ -603,6 +2112,39 @@ impl DebuggerLinux {
         Ok(())
     }
 
+    // runs in: cmd thread, dbg thread
+    // returns the state lock with the register cache loaded. off the dbg thread
+    // the lock has to be let go while the dbg thread loads the cache, so dirty
+    // gets checked again once we have it back. if it went dirty in between,
+    // the thread was resumed and there's nothing left to read.
+    fn lock_loaded_reg_cache<'a>(
+        &'a self,
+        mut state: MutexGuard<'a, DebuggerLinuxState>,
+        thread_pid: i32,
+    ) -> Result<MutexGuard<'a, DebuggerLinuxState>, DebuggerError> {
+        if !state.is_reg_cache_dirty(thread_pid) {
+            return Ok(state);
+        }
+
+        if self.is_debugger_thread() {
+            self.load_reg_cache(&mut state, thread_pid)?;
+            return Ok(state);
+        }
+
+        std::mem::drop(state);
+        match self.send_cmd_req(DebuggerLinuxCmdReqOp::LoadRegCache(thread_pid)) {
+            DebuggerLinuxCmdRspOp::Success => (),
+            DebuggerLinuxCmdRspOp::Error(e) => return Err(e),
+            _ => return Err(DebuggerError::InternalError),
+        }
+
+        let state = self.state.lock().unwrap();
+        if state.is_reg_cache_dirty(thread_pid) {
+            return Err(DebuggerError::NotStopped);
+        }
+        Ok(state)
+    }
+
     // runs in: cmd thread, dbg thread
     fn read_register_final(
         state: &mut DebuggerLinuxState,
@@ -647,7 +2189,31 @@ impl DebuggerLinux {
         match req {
             DebuggerLinuxCmdReqOp::SingleStep(thread_idx) => {
                 let state = self.state.lock().unwrap();
-                let rsp = match self.step_impl(state, thread_idx, DebuggerLinuxStepKind::Step) {
+                let rsp = match self.step_impl(state, thread_idx, DebuggerLinuxStepKind::Step) {
+                    Ok(_) => DebuggerLinuxCmdRspOp::Success,
+                    Err(e) => DebuggerLinuxCmdRspOp::Error(e),
+                };
+                chan_cont.cmd_rsp_tx.send(rsp).unwrap();
+            }
+            DebuggerLinuxCmdReqOp::StepOver(thread_idx) => {
+                let state = self.state.lock().unwrap();
+                let rsp = match self.step_over_impl(state, thread_idx) {
+                    Ok(_) => DebuggerLinuxCmdRspOp::Success,
+                    Err(e) => DebuggerLinuxCmdRspOp::Error(e),
+                };
+                chan_cont.cmd_rsp_tx.send(rsp).unwrap();
+            }
+            DebuggerLinuxCmdReqOp::StepOut(thread_idx) => {
+                let state = self.state.lock().unwrap();
+                let rsp = match self.step_out_impl(state, thread_idx) {
+                    Ok(_) => DebuggerLinuxCmdRspOp::Success,
+                    Err(e) => DebuggerLinuxCmdRspOp::Error(e),
+                };
+                chan_cont.cmd_rsp_tx.send(rsp).unwrap();
+            }
+            DebuggerLinuxCmdReqOp::Detach => {
+                let state = self.state.lock().unwrap();
+                let rsp = match self.detach_impl(state) {
                     Ok(_) => DebuggerLinuxCmdRspOp::Success,
                     Err(e) => DebuggerLinuxCmdRspOp::Error(e),
                 };
@@ -669,6 +2235,14 @@ impl DebuggerLinux {
                 };
                 chan_cont.cmd_rsp_tx.send(rsp).unwrap();
             }
+            DebuggerLinuxCmdReqOp::StopAll => {
+                let state = self.state.lock().unwrap();
+                let rsp = match self.stop_all_impl(state) {
+                    Ok(_) => DebuggerLinuxCmdRspOp::Success,
+                    Err(e) => DebuggerLinuxCmdRspOp::Error(e),
+                };
+                chan_cont.cmd_rsp_tx.send(rsp).unwrap();
+            }
             DebuggerLinuxCmdReqOp::DisasmOne(addr) => {
                 let state = self.state.lock().unwrap();
                 let rsp = match self.disassemble_one_impl(state, addr) {
@@ -677,14 +2251,68 @@ impl DebuggerLinux {
                 };
                 chan_cont.cmd_rsp_tx.send(rsp).unwrap();
             }
+            DebuggerLinuxCmdReqOp::DisasmRange(start, count) => {
+                let state = self.state.lock().unwrap();
+                let rsp = match self.disassemble_range_impl(state, start, count) {
+                    Ok(insts) => DebuggerLinuxCmdRspOp::ResultDisasmRange(insts),
+                    Err(e) => DebuggerLinuxCmdRspOp::Error(e),
+                };
+                chan_cont.cmd_rsp_tx.send(rsp).unwrap();
+            }
             DebuggerLinuxCmdReqOp::LoadRegCache(thread_pid) => {
                 let mut state = self.state.lock().unwrap();
-                let rsp = match self.load_reg_cache(&mut state, thread_pid) {
+                // several readers can queue a load for the same stop. only the
+                // first one has to do anything, the rest find the cache clean.
+                let rsp = if !state.is_reg_cache_dirty(thread_pid) {
+                    DebuggerLinuxCmdRspOp::Success
+                } else {
+                    match self.load_reg_cache(&mut state, thread_pid) {
+                        Ok(_) => DebuggerLinuxCmdRspOp::Success,
+                        Err(e) => DebuggerLinuxCmdRspOp::Error(e),
+                    }
+                };
+                chan_cont.cmd_rsp_tx.send(rsp).unwrap();
+            }
+            DebuggerLinuxCmdReqOp::WriteRegister(thread_idx, name, data) => {
+                let mut state = self.state.lock().unwrap();
+                let rsp = match self.write_register_impl(&mut state, thread_idx, &name, &data) {
+                    Ok(_) => DebuggerLinuxCmdRspOp::Success,
+                    Err(e) => DebuggerLinuxCmdRspOp::Error(e),
+                };
+                chan_cont.cmd_rsp_tx.send(rsp).unwrap();
+            }
+            DebuggerLinuxCmdReqOp::SetFlag(thread_idx, bit, value) => {
+                let state = self.state.lock().unwrap();
+                let rsp = match self.set_flag_impl(state, thread_idx, bit, value) {
+                    Ok(_) => DebuggerLinuxCmdRspOp::Success,
+                    Err(e) => DebuggerLinuxCmdRspOp::Error(e),
+                };
+                chan_cont.cmd_rsp_tx.send(rsp).unwrap();
+            }
+            DebuggerLinuxCmdReqOp::AddWatchpoint(thread_idx, wp) => {
+                let state = self.state.lock().unwrap();
+                let rsp = match self.add_watchpoint_impl(state, thread_idx, wp) {
+                    Ok(slot) => DebuggerLinuxCmdRspOp::ResultAddWatchpoint(slot),
+                    Err(e) => DebuggerLinuxCmdRspOp::Error(e),
+                };
+                chan_cont.cmd_rsp_tx.send(rsp).unwrap();
+            }
+            DebuggerLinuxCmdReqOp::RemoveWatchpoint(thread_idx, slot) => {
+                let state = self.state.lock().unwrap();
+                let rsp = match self.remove_watchpoint_impl(state, thread_idx, slot) {
                     Ok(_) => DebuggerLinuxCmdRspOp::Success,
                     Err(e) => DebuggerLinuxCmdRspOp::Error(e),
                 };
                 chan_cont.cmd_rsp_tx.send(rsp).unwrap();
             }
+            DebuggerLinuxCmdReqOp::GetRawRegset(thread_idx, which) => {
+                let mut state = self.state.lock().unwrap();
+                let rsp = match self.get_raw_regset_impl(&mut state, thread_idx, which) {
+                    Ok(v) => DebuggerLinuxCmdRspOp::ResultRawRegset(v),
+                    Err(e) => DebuggerLinuxCmdRspOp::Error(e),
+                };
+                chan_cont.cmd_rsp_tx.send(rsp).unwrap();
+            }
             DebuggerLinuxCmdReqOp::ReadBytes(thread_idx, addr, buffer_mutex, count) => {
                 let state = self.state.lock().unwrap();
                 let mut buffer_guard = match buffer_mutex.lock() {
@@ -704,90 +2332,248 @@ impl DebuggerLinux {
                 };
                 chan_cont.cmd_rsp_tx.send(rsp).unwrap();
             }
+            DebuggerLinuxCmdReqOp::ScanMemory(thread_pid, start, end, needle, mask) => {
+                let state = self.state.lock().unwrap();
+                let rsp = match self.scan_memory_impl(state, thread_pid, start, end, &needle, mask.as_deref()) {
+                    Ok(v) => DebuggerLinuxCmdRspOp::ResultScanMemory(v),
+                    Err(e) => DebuggerLinuxCmdRspOp::Error(e),
+                };
+                chan_cont.cmd_rsp_tx.send(rsp).unwrap();
+            }
         }
     }
 
     // runs in: dbg thread
     fn handle_child_event(&self) -> Option<Result<DebuggerEvent, DebuggerError>> {
         loop {
-            // this is in a loop because we may not want to report
-            // every event we receive back. obviously, that's not
-            // the case right now but it's very likely to happen
-            // at some point.
-            let (status, pid) = superpt::waitpid_nohang(-1);
-            if pid <= 0 {
-                // escape if waitpid failed
-                // todo: why might waitpid fail?
+            // this is in a loop because we don't report every event we
+            // receive back (stops we caused ourselves, untracked threads)
+            let raw_event = self.event_source.next_event()?;
+            if let Some(result) = self.handle_raw_event(raw_event) {
+                return Some(result);
+            }
+        }
+    }
+
+    // runs in: dbg thread
+    // None if the event isn't something to report
+    fn handle_raw_event(&self, raw_event: DebuggerLinuxRawEvent) -> Option<Result<DebuggerEvent, DebuggerError>> {
+        let pid = raw_event.pid;
+        if libc::WIFSTOPPED(raw_event.status) && raw_event.status >> 16 == libc::PTRACE_EVENT_EXEC {
+            let mut state = self.state.lock().unwrap();
+            if !state.apply_exec(pid) {
                 return None;
-            } else if libc::WIFSTOPPED(status) {
-                // process just stopped thread (in pid)
-                let mut state = self.state.lock().unwrap();
+            }
+
+            self.disasm.invalidate(0, u64::MAX);
+            debug!("process {} called exec, breakpoints cleared", pid);
+            let mut result = DebuggerEvent::new_with_pid(DebuggerEventKind::Exec, raw_event.status as u32, pid as u32);
+            result.image_path = read_exe_path(pid).unwrap_or_default();
+            return Some(Ok(result));
+        }
+
+        if libc::WIFSTOPPED(raw_event.status) && raw_event.status >> 16 == libc::PTRACE_EVENT_CLONE {
+            let new_pid = superpt::geteventmsg(pid) as i32;
+            let mut state = self.state.lock().unwrap();
+            if !state.threads.contains_key(&pid) {
+                return None;
+            }
+
+            // the new thread starts with a SIGSTOP that we may or may not
+            // have seen already
+            if !state.pending_clone_pids.remove(&new_pid) {
+                _ = superpt::waitpid_thread(new_pid);
+            }
+
+            state.apply_clone(pid, new_pid);
+            // debug registers aren't copied to new threads
+            _ = write_watchpoints(new_pid, &state.watchpoints);
+
+            debug!("thread {} created thread {}", pid, new_pid);
+            return Some(Ok(DebuggerEvent::new_with_pid(
+                DebuggerEventKind::ThreadSpawned,
+                new_pid as u32,
+                pid as u32,
+            )));
+        }
 
-                let siginfo = superpt::getsiginfo(pid);
-                let thread_state = match state.threads.get_mut(&pid) {
-                    Some(t) => t,
-                    None => {
-                        // ignore this thread as we're not tracking it.
-                        // todo: we don't want to consume this in case
-                        // another debugger is running in this process.
-                        // maybe have another impl handle which thread
-                        // gets the waitpid event?
-                        continue;
+        if libc::WIFSTOPPED(raw_event.status) {
+            // process just stopped thread (in pid)
+            let mut state = self.state.lock().unwrap();
+
+            if state.apply_stop_all_sigstop(&raw_event) {
+                // a late one, the thread was resumed after something else stopped it
+                if !state.stopping_all {
+                    match state.threads[&pid].pause_state.is_stepping() {
+                        true => superpt::singlestep(pid),
+                        false => superpt::cont(pid, 0),
                     }
-                };
+                }
+                return None;
+            }
 
-                let prev_pause_state = thread_state.pause_state.clone();
-                let was_stepping_bp = prev_pause_state == DebuggerLinuxPauseState::SteppingBp
-                    || prev_pause_state == DebuggerLinuxPauseState::SteppingBpContOne
-                    || prev_pause_state == DebuggerLinuxPauseState::SteppingBpContAll;
-
-                let (pause_state, evt_kind) = convert_si_code(siginfo.si_code);
-                let result = DebuggerEvent::new(evt_kind, status as u32);
-                thread_state.pause_state = pause_state;
-                println!("[setting pause state to {} 1]", thread_state.pause_state);
-
-                // if we finished stepping over a breakpoint, put the breakpoint back
-                if was_stepping_bp && evt_kind == DebuggerEventKind::StepComplete {
-                    let thread_idx = DebuggerThreadIndex::Specific(pid as u32);
-                    match self.step_replace_bp_impl(&mut state, thread_idx) {
-                        Ok(_) => {}
-                        Err(e) => return Some(Err(e)),
+            let outcome = match state.apply_stop(&raw_event) {
+                Some(v) => v,
+                None => {
+                    // a thread we haven't heard about yet is one whose
+                    // clone event hasn't shown up. hold on to its first
+                    // stop for when it does.
+                    if libc::WSTOPSIG(raw_event.status) == libc::SIGSTOP {
+                        state.pending_clone_pids.insert(pid);
+                        return None;
                     }
 
-                    // discard all new events (otherwise ptrace acts up)
-                    loop {
-                        let (_, ignored_pid) = superpt::waitpid_nohang(-1);
-                        if ignored_pid <= 0 {
-                            break;
-                        }
+                    // ignore this thread as we're not tracking it.
+                    // todo: we don't want to consume this in case
+                    // another debugger is running in this process.
+                    // maybe have another impl handle which thread
+                    // gets the waitpid event?
+                    return None;
+                }
+            };
+            let mut result = outcome.event;
+
+            // pc is adjusted back to the breakpoint address now that we're
+            // in the SwBreakpointHit state, so we can look up which one it was
+            if result.kind == DebuggerEventKind::BreakpointHit {
+                let thread_idx = DebuggerThreadIndex::Specific(pid as u32);
+                if let Ok(pc) = self.read_register_pc(&mut state, thread_idx) {
+                    if let Some(bp) = state.bp_cont.get_breakpoint(pc) {
+                        result.bp_idx = bp.id;
+                        result.bp_addr = bp.addr;
+                    }
+                }
+            }
+
+            // the cpu only tells us which slot fired, so fill in the address ourselves
+            if result.kind == DebuggerEventKind::WatchpointHit {
+                if let Some(slot) = take_hit_watchpoint_slot(pid) {
+                    if let Some(wp) = state.watchpoints.get(slot as usize).copied().flatten() {
+                        result.bp_idx = slot;
+                        result.bp_addr = wp.addr;
                     }
+                }
+            }
+
+            // a step over a breakpoint that was really a cont gets continued
+            // below, and nobody hears about the step
+            let cont_after_bp_step = outcome.finished_bp_step
+                && result.kind == DebuggerEventKind::StepComplete
+                && (outcome.prev_pause_state == DebuggerLinuxPauseState::SteppingBpContOne
+                    || outcome.prev_pause_state == DebuggerLinuxPauseState::SteppingBpContAll);
+
+            if !cont_after_bp_step {
+                match self.finish_step_over(&mut state, pid, &mut result) {
+                    Ok(true) => return None,
+                    Ok(false) => {}
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+
+            // if we finished stepping over a breakpoint, put the breakpoint back
+            if outcome.finished_bp_step {
+                let thread_idx = DebuggerThreadIndex::Specific(pid as u32);
+                match self.step_replace_bp_impl(&mut state, thread_idx) {
+                    Ok(_) => {}
+                    Err(e) => return Some(Err(e)),
+                }
 
-                    // continue if needed (this will discard the current debugger event)
-                    println!("[about to continue after breakpoint step]");
-                    let cont_result = match prev_pause_state {
+                // the breakpoint is back in place, so the step is over
+                state.stepping_thread_pid = None;
+                state.stepping_thread_bp = None;
+
+                // discard all new events (otherwise ptrace acts up)
+                while self.event_source.next_event().is_some() {}
+
+                // continue if needed, discarding the current debugger event.
+                // a watchpoint hit during the step gets reported instead.
+                if cont_after_bp_step {
+                    debug!("continuing after stepping over breakpoint");
+                    let cont_result = match outcome.prev_pause_state {
                         DebuggerLinuxPauseState::SteppingBpContOne => self.cont_one_impl(state, thread_idx),
-                        DebuggerLinuxPauseState::SteppingBpContAll => self.cont_impl(state),
-                        _ => Ok(()),
+                        _ => self.cont_impl(state),
                     };
 
                     match cont_result {
-                        Ok(_) => {}
+                        Ok(_) => return None,
                         Err(e) => return Some(Err(e)),
                     };
-                } else {
-                    // we stopped normally, so unset the stepping thread pid
-                    if let Some(stepping_thread_pid) = state.stepping_thread_pid {
-                        if stepping_thread_pid == pid {
-                            state.stepping_thread_pid = None;
-                        }
+                }
+            }
+
+            return Some(Ok(result));
+        }
+
+        // the thread is gone, and so is anything step_over left for it
+        let mut state = self.state.lock().unwrap();
+        if state.step_over_bp.as_ref().map(|v| v.0) == Some(pid) {
+            Self::remove_step_over_bp(&mut state);
+        }
+
+        // a breakpoint it was stepping over has to go back in through
+        // another thread
+        let stepping_bp = match state.stepping_thread_pid == Some(pid) {
+            true => state.stepping_thread_bp.take(),
+            false => None,
+        };
+
+        if state.apply_thread_exit(pid) {
+            if let Some(bp) = stepping_bp {
+                if let Some(thread) = state.threads.values_mut().next() {
+                    let mut mut_addr = bp.addr;
+                    if thread.proc_mem.write_bytes(&mut mut_addr, &bp.bp_bytes).is_err() {
+                        debug!("couldn't put back breakpoint at {:#x}", bp.addr);
                     }
                 }
+            }
 
-                return Some(Ok(result));
-            } else {
-                return Some(Ok(DebuggerEvent::new(DebuggerEventKind::UnknownEvent, status as u32)));
+            // the last thread leaving is the whole process exiting
+            if !state.threads.is_empty() {
+                debug!("thread {} exited", pid);
+                return Some(Ok(DebuggerEvent::new_with_pid(
+                    DebuggerEventKind::ThreadKilled,
+                    raw_event.status as u32,
+                    pid as u32,
+                )));
             }
+
+            let code = if libc::WIFSIGNALED(raw_event.status) {
+                DebuggerEvent::PROCESS_KILLED_BY_SIGNAL | libc::WTERMSIG(raw_event.status) as u32
+            } else {
+                libc::WEXITSTATUS(raw_event.status) as u32
+            };
+
+            debug!("process {} exited with code {:#x}", pid, code);
+            return Some(Ok(DebuggerEvent::new_with_pid(
+                DebuggerEventKind::ProcessExited,
+                code,
+                pid as u32,
+            )));
+        }
+
+        Some(Ok(DebuggerEvent::new(
+            DebuggerEventKind::UnknownEvent,
+            raw_event.status as u32,
+        )))
+    }
+
+    // runs in: dbg thread
+    // nothing is left to debug after the process exits, so the session goes
+    // away and the next run starts from scratch
+    fn finish_child_event(
+        &self,
+        debug_event_res: Result<DebuggerEvent, DebuggerError>,
+        sstate_guard: RwLockReadGuard<'_, Option<DebuggerLinuxSessionState>>,
+    ) -> Result<DebuggerEvent, DebuggerError> {
+        if matches!(&debug_event_res, Ok(e) if e.kind == DebuggerEventKind::ProcessExited) {
+            let mut state = self.state.lock().unwrap();
+            state.pending_events.clear();
+            state.queued_events.clear();
+            drop(state);
+            drop(sstate_guard);
+            self.end_session();
         }
+        debug_event_res
     }
 
     fn verify_stopped_by_thread_idx(
@@ -795,7 +2581,7 @@ impl DebuggerLinux {
         state: &mut DebuggerLinuxState,
         thread_idx: DebuggerThreadIndex,
     ) -> Result<(), DebuggerError> {
-        let thread_pid = Self::get_thread_pid_or_current(&state, thread_idx)?;
+        let thread_pid = Self::validate_thread_locked(&state, thread_idx)?;
         let thread = state.threads.get(&thread_pid).ok_or(DebuggerError::InvalidThread)?;
         self.verify_stopped_by_thread(thread)
     }
@@ -810,15 +2596,21 @@ impl DebuggerLinux {
 
 impl Debugger for DebuggerLinux {
     fn is_big_endian(&self) -> bool {
-        false
+        self.disasm.sleigh.is_big_endian()
+    }
+
+    fn get_pointer_size(&self) -> u32 {
+        self.disasm.sleigh.default_pointer_size()
     }
 
     fn get_flags(&self) -> DebuggerFlags {
-        todo!();
+        *self.flags.read().unwrap()
     }
 
-    fn set_flags(&self, _flags: DebuggerFlags) -> Result<(), DebuggerError> {
-        todo!();
+    // ptrace options only change on the next run or attach
+    fn set_flags(&self, flags: DebuggerFlags) -> Result<(), DebuggerError> {
+        *self.flags.write().unwrap() = flags;
+        Ok(())
     }
 
     // runs in: dbg thread
@@ -875,75 +2667,9 @@ impl Debugger for DebuggerLinux {
             // todo: this is bad if we already have a waitpid running
             _ = superpt::waitpid(fork_id);
 
-            // set up events to notify wait_next_event
-            // todo: this is kinda nasty. we should have something to
-            // automatically close/unset whatever we drop the object.
-            // todo: check statuses
-            let epoll_fd: i32;
-            let action_fd: i32;
-            let sigchld_fd: i32;
-            unsafe {
-                // setup epoll
-                epoll_fd = libc::epoll_create1(0);
-                if epoll_fd < 0 {
-                    return Err(DebuggerError::InternalError);
-                }
-
-                // setup action eventfd
-                action_fd = libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK);
-                if action_fd < 0 {
-                    libc::close(epoll_fd);
-                    return Err(DebuggerError::InternalError);
-                }
-
-                // at first glance, signalfd should be perfect for epolling for either
-                // a user action or a SIGCHLD signal. we just have to epoll for it,
-                // plus we get a free signalfd_siginfo object that tells us about why
-                // we stopped, right? unfortunately, linux (or rather, unix systems).
-                // in order for signalfd to work correctly, ALL threads in the process
-                // must have SIGCHLD blocked. if even one thread has it unblocked,
-                // that thread will be assigned to take care of the signal (which
-                // probably means doing nothing but discarding signal.) even if we get
-                // the epoll event, signalfd most likely has been consumed and we'll
-                // either block on read or skip it if EFD_NONBLOCK is set. since we
-                // can't guarantee the consumer can even block the signal in every
-                // thread (i.e., any language with a runtime), this won't work.
-                // the alternative? global signal handlers, yay! now we will have to
-                // keep track of every signal handler that's been registered. and we
-                // better hope nobody else wants to handle SIGCHLD.
-                sigchld_fd = libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK);
-                if sigchld_fd < 0 {
-                    libc::close(action_fd);
-                    libc::close(epoll_fd);
-                    return Err(DebuggerError::InternalError);
-                }
-
-                // register handler now
-                sigchld_register(sigchld_fd);
-
-                // add both fds to epoll
-                let mut action_evt = libc::epoll_event {
-                    events: libc::EPOLLIN as u32,
-                    u64: action_fd as u64,
-                };
-                let mut sigchld_evt = libc::epoll_event {
-                    events: libc::EPOLLIN as u32,
-                    u64: sigchld_fd as u64,
-                };
-                libc::epoll_ctl(epoll_fd, libc::EPOLL_CTL_ADD, action_fd, &mut action_evt);
-                libc::epoll_ctl(epoll_fd, libc::EPOLL_CTL_ADD, sigchld_fd, &mut sigchld_evt);
-            }
-
-            let mut state = self.state.lock().unwrap();
-            state.threads.insert(fork_id, DebuggerLinuxThread::new(fork_id));
-            state.cur_thread_pid = Some(fork_id);
-            {
-                let mut sstate_opt = self.session_state.write().unwrap();
-                let chan_cont = DebuggerLinuxChannelContainer::new(epoll_fd, action_fd, sigchld_fd);
-                let sstate = DebuggerLinuxSessionState::new(thread::current().id(), chan_cont);
-                *sstate_opt = Some(sstate);
-            }
+            superpt::setoptions(fork_id, self.ptrace_options());
 
+            self.setup_session(&[fork_id])?;
             Ok(fork_id)
         }
     }
@@ -954,6 +2680,7 @@ impl Debugger for DebuggerLinux {
             ActionEvent(DebuggerLinuxCmdReqOp),
             UserIdEvent(i32),
             ChildEvent,
+            InterruptEvent,
         }
 
         // these should not change while the program is running. if the program stops
@@ -969,6 +2696,13 @@ impl Debugger for DebuggerLinux {
         let epoll_fd = chan_cont.epoll_fd;
         let action_fd = chan_cont.action_fd;
         let sigchld_fd = chan_cont.sigchld_fd;
+        let interrupt_fd = chan_cont.interrupt_fd;
+
+        // events stop_all held on to go out before anything new
+        let queued_event = self.state.lock().unwrap().queued_events.pop_front();
+        if let Some(debug_event_res) = queued_event {
+            return self.finish_child_event(debug_event_res, sstate_opt_guard);
+        }
 
         const MAX_EVENT_COUNT: usize = 32;
         let mut events: [libc::epoll_event; MAX_EVENT_COUNT] = unsafe { std::mem::zeroed() };
@@ -1030,6 +2764,14 @@ impl Debugger for DebuggerLinux {
                     }
 
                     res = SelectResult::ChildEvent;
+                } else if pid == interrupt_fd {
+                    // several interrupts before we get here only wake us once
+                    let mut data = [0u64; 1];
+                    unsafe {
+                        libc::read(interrupt_fd, &mut data as *mut u64 as *mut libc::c_void, 8);
+                    }
+
+                    res = SelectResult::InterruptEvent;
                 } else {
                     res = SelectResult::UserIdEvent(pid);
                 }
@@ -1037,7 +2779,25 @@ impl Debugger for DebuggerLinux {
                 match res {
                     SelectResult::ActionEvent(req) => {
                         // non-dbg thread asking us to perform action
+                        let is_detach = matches!(req, DebuggerLinuxCmdReqOp::Detach);
                         self.handle_action_event(req, chan_cont);
+
+                        // nothing will ever wake us up again
+                        if is_detach && self.state.lock().unwrap().threads.is_empty() {
+                            return Err(DebuggerError::NoThreads);
+                        }
+
+                        // a stop_all may have picked up events on the way
+                        let queued_event = self.state.lock().unwrap().queued_events.pop_front();
+                        if let Some(debug_event_res) = queued_event {
+                            let mut state = self.state.lock().unwrap();
+                            while cur_event_idx < event_count {
+                                state.pending_events.push(events[cur_event_idx]);
+                                cur_event_idx += 1;
+                            }
+                            drop(state);
+                            return self.finish_child_event(debug_event_res, sstate_opt_guard);
+                        }
                     }
                     SelectResult::ChildEvent => {
                         // sigchild event, handle waitpid
@@ -1059,7 +2819,7 @@ impl Debugger for DebuggerLinux {
                             }
                         }
 
-                        return debug_event_res;
+                        return self.finish_child_event(debug_event_res, sstate_opt_guard);
                     }
                     SelectResult::UserIdEvent(user_id) => {
                         // save old events and return now
@@ -1073,6 +2833,18 @@ impl Debugger for DebuggerLinux {
 
                         return Ok(DebuggerEvent::new(DebuggerEventKind::UserEvent, user_id as u32));
                     }
+                    SelectResult::InterruptEvent => {
+                        // save old events and return now
+                        if cur_event_idx < event_count {
+                            let mut state = self.state.lock().unwrap();
+                            while cur_event_idx < event_count {
+                                state.pending_events.push(events[cur_event_idx].clone());
+                                cur_event_idx += 1;
+                            }
+                        }
+
+                        return Ok(DebuggerEvent::new(DebuggerEventKind::Interrupted, 0));
+                    }
                 };
             }
 
@@ -1119,6 +2891,30 @@ impl Debugger for DebuggerLinux {
         Ok(())
     }
 
+    // runs in: cmd thread
+    fn interrupt(&self) -> Result<(), DebuggerError> {
+        let sstate_opt_guard = self.session_state.read().unwrap();
+        let sstate_opt = sstate_opt_guard.as_ref();
+        let sstate = match sstate_opt {
+            Some(sstate) => sstate,
+            None => return Err(DebuggerError::NoThreads),
+        };
+
+        let data = [1u64; 1];
+        let res = unsafe {
+            libc::write(
+                sstate.chan_cont.interrupt_fd,
+                &data as *const u64 as *const libc::c_void,
+                8,
+            )
+        };
+        if res < 0 {
+            return Err(DebuggerError::InternalError);
+        }
+
+        Ok(())
+    }
+
     // runs in: cmd thread, dbg thread
     // todo: should take thread idx
     fn disassemble_one(&self, addr: u64) -> Result<DisasmDispInstruction, DebuggerError> {
@@ -1144,6 +2940,30 @@ impl Debugger for DebuggerLinux {
         }
     }
 
+    // runs in: cmd thread, dbg thread
+    fn disassemble_range(&self, start: u64, count: i32) -> Result<Vec<DisasmDispInstruction>, DebuggerError> {
+        let count = usize::try_from(count).or(Err(DebuggerError::InvalidArguments))?;
+        let mut state_guard = self.state.lock().unwrap();
+        let state = state_guard.deref_mut();
+        let cur_thread_pid = state.cur_thread_pid.ok_or(DebuggerError::NoThreads)?;
+        let thread = state
+            .threads
+            .get_mut(&cur_thread_pid)
+            .ok_or(DebuggerError::InvalidThread)?;
+
+        // same as disassemble_one, but the whole range is one lock and one round trip
+        if thread.proc_mem.is_using_proc_mem() || self.is_debugger_thread() {
+            return self.disassemble_range_impl(state_guard, start, count);
+        } else {
+            std::mem::drop(state_guard); // unlock state
+            match self.send_cmd_req(DebuggerLinuxCmdReqOp::DisasmRange(start, count)) {
+                DebuggerLinuxCmdRspOp::ResultDisasmRange(insts) => return Ok(insts),
+                DebuggerLinuxCmdRspOp::Error(e) => return Err(e),
+                _ => return Err(DebuggerError::InternalError),
+            }
+        }
+    }
+
     // runs in: cmd thread, dbg thread
     fn get_register_infos(&self, _: DebuggerThreadIndex) -> Vec<&RegisterInfo> {
         self.nat_reg_info.get_all_infos()
@@ -1156,28 +2976,11 @@ impl Debugger for DebuggerLinux {
         reg_idx: i32,
         out_data: &mut [u8],
     ) -> Result<(), DebuggerError> {
-        let mut state = self.state.lock().unwrap();
-        let reg_mem_dirty = state.reg_mem_dirty;
-
+        let state = self.state.lock().unwrap();
         let (reg_start, read_size) = self.get_register_read_range_by_idx(reg_idx, out_data.len())?;
-        let use_thread_pid = Self::get_thread_pid_or_current(&state, thread_idx)?;
-        if reg_mem_dirty {
-            if self.is_debugger_thread() {
-                self.load_reg_cache(&mut state, use_thread_pid)?;
-                Self::read_register_final(&mut state, use_thread_pid, reg_start, out_data, read_size)?;
-            } else {
-                std::mem::drop(state);
-                match self.send_cmd_req(DebuggerLinuxCmdReqOp::LoadRegCache(use_thread_pid)) {
-                    DebuggerLinuxCmdRspOp::Success => (),
-                    DebuggerLinuxCmdRspOp::Error(e) => return Err(e),
-                    _ => return Err(DebuggerError::InternalError),
-                }
-                let mut state = self.state.lock().unwrap();
-                Self::read_register_final(&mut state, use_thread_pid, reg_start, out_data, read_size)?;
-            }
-        } else {
-            Self::read_register_final(&mut state, use_thread_pid, reg_start, out_data, read_size)?;
-        }
+        let use_thread_pid = Self::validate_thread_locked(&state, thread_idx)?;
+        let mut state = self.lock_loaded_reg_cache(state, use_thread_pid)?;
+        Self::read_register_final(&mut state, use_thread_pid, reg_start, out_data, read_size)?;
 
         Ok(())
     }
@@ -1190,44 +2993,52 @@ impl Debugger for DebuggerLinux {
         out_data: &mut [u8],
     ) -> Result<(), DebuggerError> {
         let mut state = self.state.lock().unwrap();
-        let reg_mem_dirty = state.reg_mem_dirty;
-
         self.verify_stopped_by_thread_idx(&mut state, thread_idx)?; // for testing, apply everywhere else as well
 
-        let reg_info = self
-            .nat_reg_info
-            .get_reg_info(name, true)
-            .ok_or(DebuggerError::InvalidRegister)?;
-
-        let reg_start = reg_info.addr as u64;
-        let reg_size = (reg_info.bit_len + 7) / 8 as i32;
+        let (reg_start, read_size) = self.get_register_read_range_by_name(name, out_data.len())?;
+        let use_thread_pid = Self::validate_thread_locked(&state, thread_idx)?;
+        let mut state = self.lock_loaded_reg_cache(state, use_thread_pid)?;
+        Self::read_register_final(&mut state, use_thread_pid, reg_start, out_data, read_size)?;
 
-        let size = out_data.len();
-        // prevent reading more bytes than possible
-        if size > (i32::MAX as usize) || size < (reg_size as usize) {
-            return Err(DebuggerError::InvalidRegister);
-        }
+        Ok(())
+    }
 
-        let read_size = (size as i32).min(reg_size);
-        let use_thread_pid = Self::get_thread_pid_or_current(&state, thread_idx)?;
-        if reg_mem_dirty {
-            if self.is_debugger_thread() {
-                self.load_reg_cache(&mut state, use_thread_pid)?;
-                Self::read_register_final(&mut state, use_thread_pid, reg_start, out_data, read_size)?;
-            } else {
-                std::mem::drop(state);
-                match self.send_cmd_req(DebuggerLinuxCmdReqOp::LoadRegCache(use_thread_pid)) {
-                    DebuggerLinuxCmdRspOp::Success => (),
-                    DebuggerLinuxCmdRspOp::Error(e) => return Err(e),
-                    _ => return Err(DebuggerError::InternalError),
-                }
-                let mut state = self.state.lock().unwrap();
-                Self::read_register_final(&mut state, use_thread_pid, reg_start, out_data, read_size)?;
-            }
+    // runs in: cmd thread, dbg thread
+    fn write_register_by_name_buf(
+        &self,
+        thread_idx: DebuggerThreadIndex,
+        name: &str,
+        data: &[u8],
+    ) -> Result<(), DebuggerError> {
+        if self.is_debugger_thread() {
+            let mut state = self.state.lock().unwrap();
+            return self.write_register_impl(&mut state, thread_idx, name, data);
         } else {
-            Self::read_register_final(&mut state, use_thread_pid, reg_start, out_data, read_size)?;
+            self.validate_thread(thread_idx)?;
+            let req = DebuggerLinuxCmdReqOp::WriteRegister(thread_idx, name.to_string(), data.to_vec());
+            match self.send_cmd_req(req) {
+                DebuggerLinuxCmdRspOp::Success => return Ok(()),
+                DebuggerLinuxCmdRspOp::Error(e) => return Err(e),
+                _ => return Err(DebuggerError::InternalError),
+            }
         }
+    }
+
+    // runs in: cmd thread, dbg thread
+    fn invalidate_register_cache(&self, thread_idx: DebuggerThreadIndex) -> Result<(), DebuggerError> {
+        let mut state = self.state.lock().unwrap();
+        Self::validate_thread_locked(&state, thread_idx)?;
+        state.mark_dirty();
+        Ok(())
+    }
 
+    // runs in: cmd thread, dbg thread
+    fn flush_register_cache(&self, thread_idx: DebuggerThreadIndex) -> Result<(), DebuggerError> {
+        let mut state = self.state.lock().unwrap();
+        self.verify_stopped_by_thread_idx(&mut state, thread_idx)?;
+        let use_thread_pid = Self::validate_thread_locked(&state, thread_idx)?;
+        state.mark_dirty();
+        let _state = self.lock_loaded_reg_cache(state, use_thread_pid)?;
         Ok(())
     }
 
@@ -1239,7 +3050,7 @@ impl Debugger for DebuggerLinux {
         out_data: &mut [u8],
     ) -> Result<u64, DebuggerError> {
         let state = self.state.lock().unwrap();
-        let use_thread_pid = Self::get_thread_pid_or_current(&state, thread_idx)?;
+        let use_thread_pid = Self::validate_thread_locked(&state, thread_idx)?;
         let thread = state.threads.get(&use_thread_pid).ok_or(DebuggerError::InvalidThread)?;
         let count = out_data.len();
 
@@ -1258,6 +3069,7 @@ impl Debugger for DebuggerLinux {
             // will allocate just 1024 bytes. this is what you get for
             // not supporting proc mem and I don't care to make it any
             // better right now.
+            std::mem::drop(state); // unlock state
 
             // allocate at most a 1024 sized buffer to share with dbg thread
             let tmp_buf: Arc<Mutex<Vec<u8>>> = if count < 1024 {
@@ -1273,7 +3085,7 @@ impl Debugger for DebuggerLinux {
                 let bytes_to_read = bytes_left.min(1024);
                 last_addr = match self.send_cmd_req(DebuggerLinuxCmdReqOp::ReadBytes(
                     use_thread_pid,
-                    addr,
+                    addr + bytes_addr as u64,
                     tmp_buf.clone(),
                     bytes_to_read,
                 )) {
@@ -1282,17 +3094,50 @@ impl Debugger for DebuggerLinux {
                     _ => return Err(DebuggerError::InternalError),
                 };
                 let tmp_buf_data = tmp_buf.lock().unwrap();
-                out_data[bytes_addr..(bytes_addr + bytes_to_read as usize)].copy_from_slice(&tmp_buf_data);
+                out_data[bytes_addr..(bytes_addr + bytes_to_read as usize)]
+                    .copy_from_slice(&tmp_buf_data[..bytes_to_read as usize]);
                 bytes_addr += bytes_to_read as usize;
                 bytes_left -= bytes_to_read;
             }
-            return Ok(last_addr);
+            return Ok(last_addr);
+        }
+    }
+
+    // runs in: cmd thread, dbg thread
+    fn scan_memory(
+        &self,
+        thread_idx: DebuggerThreadIndex,
+        start: u64,
+        end: u64,
+        needle: &[u8],
+        mask: Option<&[u8]>,
+    ) -> Result<Option<u64>, DebuggerError> {
+        let state = self.state.lock().unwrap();
+        let use_thread_pid = Self::validate_thread_locked(&state, thread_idx)?;
+        let thread = state.threads.get(&use_thread_pid).ok_or(DebuggerError::InvalidThread)?;
+
+        if thread.proc_mem.is_using_proc_mem() || self.is_debugger_thread() {
+            return self.scan_memory_impl(state, use_thread_pid, start, end, needle, mask);
+        } else {
+            std::mem::drop(state); // unlock state
+            let req = DebuggerLinuxCmdReqOp::ScanMemory(
+                use_thread_pid,
+                start,
+                end,
+                needle.to_vec(),
+                mask.map(|m| m.to_vec()),
+            );
+            match self.send_cmd_req(req) {
+                DebuggerLinuxCmdRspOp::ResultScanMemory(v) => return Ok(v),
+                DebuggerLinuxCmdRspOp::Error(e) => return Err(e),
+                _ => return Err(DebuggerError::InternalError),
+            }
         }
     }
 
     fn write_bytes(&self, thread_idx: DebuggerThreadIndex, addr: u64, data: &[u8]) -> Result<u64, DebuggerError> {
         let mut state = self.state.lock().unwrap();
-        let use_thread_pid = Self::get_thread_pid_or_current(&state, thread_idx)?;
+        let use_thread_pid = Self::validate_thread_locked(&state, thread_idx)?;
         let thread = state
             .threads
             .get_mut(&use_thread_pid)
@@ -1303,19 +3148,20 @@ impl Debugger for DebuggerLinux {
             .proc_mem
             .write_bytes(&mut mut_addr, data)
             .or(Err(DebuggerError::MemoryAccessFailed))?;
+        self.disasm.invalidate(addr, data.len() as u64);
 
         Ok(mut_addr)
     }
 
     fn add_breakpoint(&self, thread_idx: DebuggerThreadIndex, addr: u64) -> Result<u32, DebuggerError> {
         let mut state = self.state.lock().unwrap();
-        let use_thread_pid = Self::get_thread_pid_or_current(&state, thread_idx)?;
+        let use_thread_pid = Self::validate_thread_locked(&state, thread_idx)?;
         let thread = state
             .threads
             .get_mut(&use_thread_pid)
             .ok_or(DebuggerError::InvalidThread)?;
 
-        let bp_bytes: Vec<u8> = vec![0xcc];
+        let bp_bytes: Vec<u8> = SW_BREAKPOINT_BYTES.to_vec();
         let mut orig_bytes: Vec<u8> = vec![0; bp_bytes.len()];
 
         let mut mut_addr = addr;
@@ -1324,11 +3170,16 @@ impl Debugger for DebuggerLinux {
             .read_bytes(&mut mut_addr, &mut orig_bytes, bp_bytes.len() as i32)
             .or(Err(DebuggerError::MemoryAccessFailed))?;
 
+        // the read worked so the page is mapped. ptrace writes ignore page
+        // protections for private mappings, so this is usually a shared
+        // mapping (or sealed memory) that would need remapping to patch.
         mut_addr = addr;
         thread
             .proc_mem
             .write_bytes(&mut mut_addr, &bp_bytes)
-            .or(Err(DebuggerError::MemoryAccessFailed))?;
+            .or(Err(DebuggerError::MemoryNotWritable))?;
+        // disassembly hides breakpoints, but the cache shouldn't have to know that
+        self.disasm.invalidate(addr, bp_bytes.len() as u64);
 
         let bp = BreakpointEntry::new(addr, bp_bytes, orig_bytes);
         let bp_idx = state.bp_cont.add_breakpoint(bp);
@@ -1339,12 +3190,48 @@ impl Debugger for DebuggerLinux {
         todo!()
     }
 
+    // runs in: cmd thread, dbg thread
+    fn add_watchpoint(
+        &self,
+        thread_idx: DebuggerThreadIndex,
+        addr: u64,
+        len: u32,
+        kind: DebuggerWatchpointKind,
+    ) -> Result<u32, DebuggerError> {
+        let wp = DebuggerLinuxWatchpoint { addr, len, kind };
+        if self.is_debugger_thread() {
+            let state = self.state.lock().unwrap();
+            return self.add_watchpoint_impl(state, thread_idx, wp);
+        } else {
+            match self.send_cmd_req(DebuggerLinuxCmdReqOp::AddWatchpoint(thread_idx, wp)) {
+                DebuggerLinuxCmdRspOp::ResultAddWatchpoint(slot) => return Ok(slot),
+                DebuggerLinuxCmdRspOp::Error(e) => return Err(e),
+                _ => return Err(DebuggerError::InternalError),
+            }
+        }
+    }
+
+    // runs in: cmd thread, dbg thread
+    fn remove_watchpoint(&self, thread_idx: DebuggerThreadIndex, slot: u32) -> Result<(), DebuggerError> {
+        if self.is_debugger_thread() {
+            let state = self.state.lock().unwrap();
+            return self.remove_watchpoint_impl(state, thread_idx, slot);
+        } else {
+            match self.send_cmd_req(DebuggerLinuxCmdReqOp::RemoveWatchpoint(thread_idx, slot)) {
+                DebuggerLinuxCmdRspOp::Success => return Ok(()),
+                DebuggerLinuxCmdRspOp::Error(e) => return Err(e),
+                _ => return Err(DebuggerError::InternalError),
+            }
+        }
+    }
+
     // runs in: cmd thread
     fn step(&self, thread_idx: DebuggerThreadIndex) -> Result<(), DebuggerError> {
         if self.is_debugger_thread() {
             let state = self.state.lock().unwrap();
             return self.step_impl(state, thread_idx, DebuggerLinuxStepKind::Step);
         } else {
+            self.validate_thread(thread_idx)?;
             match self.send_cmd_req(DebuggerLinuxCmdReqOp::SingleStep(thread_idx)) {
                 DebuggerLinuxCmdRspOp::Success => return Ok(()),
                 DebuggerLinuxCmdRspOp::Error(e) => return Err(e),
@@ -1353,13 +3240,25 @@ impl Debugger for DebuggerLinux {
         }
     }
 
+    // runs in: cmd thread, dbg thread
+    fn step_out(&self, thread_idx: DebuggerThreadIndex) -> Result<(), DebuggerError> {
+        if self.is_debugger_thread() {
+            let state = self.state.lock().unwrap();
+            return self.step_out_impl(state, thread_idx);
+        } else {
+            self.validate_thread(thread_idx)?;
+            match self.send_cmd_req(DebuggerLinuxCmdReqOp::StepOut(thread_idx)) {
+                DebuggerLinuxCmdRspOp::Success => return Ok(()),
+                DebuggerLinuxCmdRspOp::Error(e) => return Err(e),
+                _ => return Err(DebuggerError::InternalError),
+            }
+        }
+    }
+
     // runs in: cmd thread
     fn cont_all(&self) -> Result<(), DebuggerError> {
         let state = self.state.lock().unwrap();
-        if let Some(stepping_thread_pid) = state.stepping_thread_pid {
-            std::mem::drop(state); // unlock state
-            return self.step(DebuggerThreadIndex::Specific(stepping_thread_pid as u32));
-        }
+        state.check_not_stepping()?;
 
         if self.is_debugger_thread() {
             return self.cont_impl(state);
@@ -1376,11 +3275,9 @@ impl Debugger for DebuggerLinux {
     // runs in: cmd thread
     fn cont_one(&self, thread_idx: DebuggerThreadIndex) -> Result<(), DebuggerError> {
         let state = self.state.lock().unwrap();
-        if let Some(stepping_thread_pid) = state.stepping_thread_pid {
-            std::mem::drop(state); // unlock state
-            return self.step(DebuggerThreadIndex::Specific(stepping_thread_pid as u32));
-        }
+        state.check_not_stepping()?;
 
+        Self::validate_thread_locked(&state, thread_idx)?;
         if self.is_debugger_thread() {
             return self.cont_one_impl(state, thread_idx);
         } else {
@@ -1393,3 +3290,1054 @@ impl Debugger for DebuggerLinux {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::debugger::host_debuggers::debugger_linux_builder::{live_test_lock, x86_64_builder};
+
+    #[test]
+    fn overlapping_step_is_busy() {
+        use crate::debugger::debugger::DebuggerHelper;
+        use std::io::{Read, Seek, SeekFrom};
+
+        let _live = live_test_lock();
+
+        let dbg = x86_64_builder().build().unwrap();
+
+        let pid = dbg.run("/bin/true", &[]).unwrap();
+        let thread_idx = DebuggerThreadIndex::Current;
+
+        let pc: u64 = dbg.read_register_by_name(thread_idx, "RIP").unwrap();
+        dbg.add_breakpoint(thread_idx, pc).unwrap();
+        assert_eq!(
+            dbg.step_n(thread_idx, 1).unwrap().kind,
+            DebuggerEventKind::BreakpointHit
+        );
+
+        // stepping off a breakpoint takes it out until the step finishes
+        dbg.step(thread_idx).unwrap();
+        assert!(dbg.state.lock().unwrap().stepping_thread_bp.is_some());
+        assert_eq!(dbg.state.lock().unwrap().stepping_thread_pid, Some(pid));
+
+        // nothing has picked up the step's stop yet, so a cmd thread trying
+        // to resume anything has to wait for it
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                assert!(matches!(dbg.cont_all(), Err(DebuggerError::Busy)));
+                assert!(matches!(dbg.cont_one(thread_idx), Err(DebuggerError::Busy)));
+            });
+        });
+        assert!(matches!(dbg.step(thread_idx), Err(DebuggerError::Busy)));
+
+        let event = dbg.wait_next_event(false).unwrap();
+        assert_eq!(event.kind, DebuggerEventKind::StepComplete);
+        assert_eq!(dbg.state.lock().unwrap().stepping_thread_pid, None);
+
+        // the breakpoint went back in, and stepping works again
+        let mut mem_file = std::fs::File::open(format!("/proc/{}/mem", pid)).unwrap();
+        mem_file.seek(SeekFrom::Start(pc)).unwrap();
+        let mut bp_bytes = vec![0u8; SW_BREAKPOINT_BYTES.len()];
+        mem_file.read_exact(&mut bp_bytes).unwrap();
+        assert_eq!(bp_bytes, SW_BREAKPOINT_BYTES);
+        assert_eq!(dbg.step_n(thread_idx, 1).unwrap().kind, DebuggerEventKind::StepComplete);
+
+        dbg.detach().unwrap();
+    }
+
+    #[test]
+    fn thread_clone_and_exit_update_state() {
+        let mut state = DebuggerLinuxState::new();
+        state
+            .threads
+            .insert(100, DebuggerLinuxThread::new(100, DebuggerMemReadMode::ProcMem));
+        state.cur_thread_pid = Some(100);
+
+        assert!(!state.apply_clone(999, 102));
+        assert!(!state.threads.contains_key(&102));
+
+        assert!(state.apply_clone(100, 102));
+        assert!(state.apply_clone(102, 101));
+        assert_eq!(
+            state.threads[&100].pause_state,
+            DebuggerLinuxPauseState::StoppedUnknownReason
+        );
+        assert_eq!(state.threads[&101].pause_state, DebuggerLinuxPauseState::FirstStop);
+        assert_eq!(state.threads.len(), 3);
+
+        // losing the current thread moves to the lowest one left
+        state.stepping_thread_pid = Some(100);
+        assert!(state.apply_thread_exit(100));
+        assert_eq!(state.cur_thread_pid, Some(101));
+        assert_eq!(state.stepping_thread_pid, None);
+
+        assert!(!state.apply_thread_exit(100));
+        assert!(state.apply_thread_exit(102));
+        assert_eq!(state.cur_thread_pid, Some(101));
+        assert!(state.apply_thread_exit(101));
+        assert_eq!(state.cur_thread_pid, None);
+    }
+
+    #[test]
+    fn replayed_stops_drive_pause_states() {
+        use super::super::debugger_linux_event_source::DebuggerLinuxReplayEventSource;
+        use crate::debugger::debugger::DebuggerHelper;
+
+        let _live = live_test_lock();
+
+        // a is a real stopped child, so putting its breakpoint back is a real write
+        let live_dbg = x86_64_builder().build().unwrap();
+        let pid_a = live_dbg.run("/bin/true", &[]).unwrap();
+        // well past pid_max, so nothing real can be hit
+        const PID_B: i32 = 0x7000_0002;
+        const PID_UNKNOWN: i32 = 0x7000_0003;
+
+        let code_addr: u64 = live_dbg
+            .read_register_by_name(DebuggerThreadIndex::Current, "RIP")
+            .unwrap();
+        let mut orig_byte = [0u8; 1];
+        live_dbg
+            .read_bytes(DebuggerThreadIndex::Current, code_addr, &mut orig_byte)
+            .unwrap();
+
+        let source = DebuggerLinuxReplayEventSource::new(vec![
+            // not our thread
+            DebuggerLinuxRawEvent::stopped(PID_UNKNOWN, libc::SIGTRAP, libc::TRAP_TRACE),
+            DebuggerLinuxRawEvent::stopped(PID_B, libc::SIGUSR1, libc::SI_USER),
+            DebuggerLinuxRawEvent::stopped(pid_a, libc::SIGTRAP, libc::TRAP_TRACE),
+        ]);
+        let dbg = x86_64_builder().event_source(Box::new(source)).build().unwrap();
+
+        {
+            let mut state = dbg.state.lock().unwrap();
+            let mut thread = DebuggerLinuxThread::new(pid_a, DebuggerMemReadMode::ProcMem);
+            thread.pause_state = DebuggerLinuxPauseState::SteppingBp;
+            state.threads.insert(pid_a, thread);
+            let mut thread = DebuggerLinuxThread::new(PID_B, DebuggerMemReadMode::ProcMem);
+            thread.pause_state = DebuggerLinuxPauseState::Running;
+            state.threads.insert(PID_B, thread);
+            state.cur_thread_pid = Some(pid_a);
+            state.stepping_thread_pid = Some(pid_a);
+            state.stepping_thread_bp = Some(BreakpointEntry::new(code_addr, vec![0xcc], orig_byte.to_vec()));
+        }
+
+        let event = dbg.handle_child_event().unwrap().unwrap();
+        assert_eq!(event.kind, DebuggerEventKind::MiscSignalReceived);
+        assert_eq!(
+            dbg.state.lock().unwrap().threads[&PID_B].pause_state,
+            DebuggerLinuxPauseState::StoppedUnknownReason
+        );
+
+        // finishing a step over a breakpoint puts the breakpoint back
+        let event = dbg.handle_child_event().unwrap().unwrap();
+        assert_eq!(event.kind, DebuggerEventKind::StepComplete);
+        let mut code_byte = [0u8; 1];
+        live_dbg
+            .read_bytes(DebuggerThreadIndex::Current, code_addr, &mut code_byte)
+            .unwrap();
+        assert_eq!(code_byte, [0xcc]);
+        {
+            let state = dbg.state.lock().unwrap();
+            assert_eq!(
+                state.threads[&pid_a].pause_state,
+                DebuggerLinuxPauseState::StepCompleted
+            );
+            assert_eq!(state.stepping_thread_pid, None);
+            assert!(state.stepping_thread_bp.is_none());
+        }
+
+        assert!(dbg.handle_child_event().is_none());
+
+        live_dbg
+            .write_bytes(DebuggerThreadIndex::Current, code_addr, &orig_byte)
+            .unwrap();
+        live_dbg.detach().unwrap();
+    }
+
+    #[test]
+    fn stop_all_only_swallows_its_own_sigstops() {
+        use super::super::debugger_linux_event_source::DebuggerLinuxReplayEventSource;
+
+        // well past pid_max, so the real tkills and conts can't hit anything
+        const PID_A: i32 = 0x7000_0001;
+        const PID_B: i32 = 0x7000_0002;
+        const PID_C: i32 = 0x7000_0003;
+
+        let source = DebuggerLinuxReplayEventSource::new(vec![
+            // a signal gets to a before our SIGSTOP does
+            DebuggerLinuxRawEvent::stopped(PID_A, libc::SIGUSR1, libc::SI_USER),
+            DebuggerLinuxRawEvent::stopped(PID_B, libc::SIGSTOP, libc::SI_TKILL),
+            // c exits instead
+            DebuggerLinuxRawEvent::new(0, PID_C, 0),
+            // a's SIGSTOP, once it has been resumed
+            DebuggerLinuxRawEvent::stopped(PID_A, libc::SIGSTOP, libc::SI_TKILL),
+        ]);
+
+        let dbg = x86_64_builder().event_source(Box::new(source)).build().unwrap();
+
+        {
+            let mut state = dbg.state.lock().unwrap();
+            for pid in [PID_A, PID_B, PID_C] {
+                let mut thread = DebuggerLinuxThread::new(pid, DebuggerMemReadMode::ProcMem);
+                thread.pause_state = DebuggerLinuxPauseState::Running;
+                state.threads.insert(pid, thread);
+            }
+            state.cur_thread_pid = Some(PID_A);
+        }
+
+        dbg.stop_all_impl(dbg.state.lock().unwrap()).unwrap();
+
+        {
+            let state = dbg.state.lock().unwrap();
+            assert!(!state.stopping_all);
+            assert_eq!(state.threads.len(), 2);
+            assert!(state.threads.values().all(|t| t.pause_state.is_stopped()));
+            assert!(state.threads[&PID_A].stop_all_pending);
+            assert!(!state.threads[&PID_B].stop_all_pending);
+
+            // everything but b's SIGSTOP is still reported
+            let kinds: Vec<_> = state
+                .queued_events
+                .iter()
+                .map(|e| {
+                    let e = e.as_ref().unwrap();
+                    (e.kind, e.pid)
+                })
+                .collect();
+            assert_eq!(kinds.len(), 2);
+            assert_eq!(kinds[0].0, DebuggerEventKind::MiscSignalReceived);
+            assert_eq!(kinds[1], (DebuggerEventKind::ThreadKilled, PID_C as u32));
+        }
+
+        // a's SIGSTOP finally shows up after it's continued, and it keeps running
+        dbg.state.lock().unwrap().threads.get_mut(&PID_A).unwrap().pause_state = DebuggerLinuxPauseState::Running;
+        assert!(dbg.handle_child_event().is_none());
+
+        let state = dbg.state.lock().unwrap();
+        assert_eq!(state.threads[&PID_A].pause_state, DebuggerLinuxPauseState::Running);
+        assert!(!state.threads[&PID_A].stop_all_pending);
+        assert_eq!(state.queued_events.len(), 2);
+    }
+
+    #[test]
+    fn syscall_stops_are_told_apart_by_signal() {
+        let stop = |sig, si_code| {
+            let status = DebuggerLinuxRawEvent::stopped(1, sig, si_code).status;
+            convert_stop(status, si_code, &DebuggerLinuxPauseState::Stepping)
+        };
+
+        assert_eq!(
+            stop(libc::SIGTRAP | 0x80, libc::SIGTRAP | 0x80),
+            (
+                DebuggerLinuxPauseState::SyscallHit,
+                DebuggerEventKind::StepCompleteSyscall
+            )
+        );
+        assert_eq!(stop(libc::SIGTRAP, libc::TRAP_TRACE).1, DebuggerEventKind::StepComplete);
+        // SEGV_MAPERR (1) is the same number as TRAP_BRKPT
+        assert_eq!(
+            stop(libc::SIGSEGV, 1),
+            (
+                DebuggerLinuxPauseState::StoppedUnknownReason,
+                DebuggerEventKind::MiscSignalReceived
+            )
+        );
+    }
+
+    #[test]
+    fn si_codes_depend_on_whether_we_stepped() {
+        use super::super::debugger_linux_event_source::DebuggerLinuxReplayEventSource;
+
+        // well past pid_max, so the register reads for the breakpoint hit fail
+        const PID_BASE: i32 = 0x7000_0000;
+
+        let source = DebuggerLinuxReplayEventSource::new(vec![
+            DebuggerLinuxRawEvent::stopped(PID_BASE, libc::SIGTRAP, libc::TRAP_TRACE),
+            // the program set the trap flag on its own
+            DebuggerLinuxRawEvent::stopped(PID_BASE + 1, libc::SIGTRAP, libc::TRAP_TRACE),
+            DebuggerLinuxRawEvent::stopped(PID_BASE + 2, libc::SIGTRAP, libc::TRAP_BRKPT),
+            DebuggerLinuxRawEvent::stopped(PID_BASE + 3, libc::SIGTRAP, libc::SI_KERNEL),
+            DebuggerLinuxRawEvent::stopped(PID_BASE + 4, libc::SIGTRAP, libc::TRAP_HWBKPT),
+        ]);
+        let dbg = x86_64_builder().event_source(Box::new(source)).build().unwrap();
+
+        {
+            let mut state = dbg.state.lock().unwrap();
+            let pause_states = [
+                DebuggerLinuxPauseState::Stepping,
+                DebuggerLinuxPauseState::Running,
+                DebuggerLinuxPauseState::Stepping,
+                DebuggerLinuxPauseState::Running,
+                DebuggerLinuxPauseState::Running,
+            ];
+            for (pid, pause_state) in (PID_BASE..).zip(pause_states) {
+                let mut thread = DebuggerLinuxThread::new(pid, DebuggerMemReadMode::ProcMem);
+                thread.pause_state = pause_state;
+                state.threads.insert(pid, thread);
+            }
+        }
+
+        let expected = [
+            (DebuggerLinuxPauseState::StepCompleted, DebuggerEventKind::StepComplete),
+            (
+                DebuggerLinuxPauseState::StoppedUnknownReason,
+                DebuggerEventKind::MiscSignalReceived,
+            ),
+            (
+                DebuggerLinuxPauseState::SyscallHitEnd,
+                DebuggerEventKind::StepCompleteSyscall,
+            ),
+            (
+                DebuggerLinuxPauseState::SwBreakpointHit,
+                DebuggerEventKind::BreakpointHit,
+            ),
+            (
+                DebuggerLinuxPauseState::HwBreakpointHit,
+                DebuggerEventKind::WatchpointHit,
+            ),
+        ];
+        for (pid, (pause_state, kind)) in (PID_BASE..).zip(expected) {
+            let event = dbg.handle_child_event().unwrap().unwrap();
+            assert_eq!(event.kind, kind);
+            assert_eq!(dbg.state.lock().unwrap().threads[&pid].pause_state, pause_state);
+        }
+        assert!(dbg.handle_child_event().is_none());
+    }
+
+    #[test]
+    fn exec_drops_old_image_state() {
+        let mut state = DebuggerLinuxState::new();
+        state
+            .threads
+            .insert(100, DebuggerLinuxThread::new(100, DebuggerMemReadMode::ProcMem));
+        state
+            .threads
+            .insert(101, DebuggerLinuxThread::new(101, DebuggerMemReadMode::ProcMem));
+        state.cur_thread_pid = Some(101);
+        state.stepping_thread_pid = Some(101);
+        state
+            .bp_cont
+            .add_breakpoint(BreakpointEntry::new(0x1000, vec![0xcc], vec![0x90]));
+        state.watchpoints[0] = Some(DebuggerLinuxWatchpoint {
+            addr: 0x2000,
+            len: 8,
+            kind: DebuggerWatchpointKind::Write,
+        });
+        for thread in state.threads.values_mut() {
+            thread.reg_mem_dirty = false;
+        }
+
+        assert!(!state.apply_exec(999));
+        assert_eq!(state.threads.len(), 2);
+
+        assert!(state.apply_exec(100));
+        assert_eq!(state.threads.len(), 1);
+        assert_eq!(state.threads[&100].pause_state, DebuggerLinuxPauseState::ExecStopped);
+        assert_eq!(state.cur_thread_pid, Some(100));
+        assert!(state.check_not_stepping().is_ok());
+        assert!(state.bp_cont.get_breakpoint(0x1000).is_none());
+        assert!(state.watchpoints.iter().all(|w| w.is_none()));
+        assert!(state.threads[&100].reg_mem_dirty);
+    }
+
+    #[test]
+    fn process_exit_ends_session() {
+        let _live = live_test_lock();
+
+        let dbg = x86_64_builder().build().unwrap();
+
+        // run already waited for the stop at exec
+        dbg.run("/bin/true", &[]).unwrap();
+        dbg.cont_all().unwrap();
+
+        let mut exit_events = Vec::new();
+        loop {
+            match dbg.wait_next_event(false) {
+                Ok(event) if event.kind == DebuggerEventKind::ProcessExited => exit_events.push(event),
+                Ok(_) => dbg.cont_all().unwrap(),
+                Err(e) => {
+                    assert!(matches!(e, DebuggerError::NoThreads));
+                    break;
+                }
+            }
+        }
+
+        assert_eq!(exit_events.len(), 1);
+        assert_eq!(exit_events[0].code, 0);
+        assert!(dbg.session_state.read().unwrap().is_none());
+        assert!(matches!(dbg.cont_all(), Err(DebuggerError::NoThreads)));
+    }
+
+    #[test]
+    fn sub_registers_read_from_host_register() {
+        let _live = live_test_lock();
+
+        let dbg = x86_64_builder().build().unwrap();
+
+        dbg.run("/bin/true", &[]).unwrap();
+        let thread_idx = DebuggerThreadIndex::Current;
+        dbg.write_register_by_name_buf(thread_idx, "RAX", &0x1122334455667788u64.to_le_bytes())
+            .unwrap();
+
+        let mut rax = [0u8; 8];
+        dbg.read_register_by_name_buf(thread_idx, "RAX", &mut rax).unwrap();
+        let mut eax = [0u8; 4];
+        dbg.read_register_by_name_buf(thread_idx, "EAX", &mut eax).unwrap();
+        assert_eq!(eax, rax[..4]);
+
+        let mut ax = [0u8; 2];
+        dbg.read_register_by_name_buf(thread_idx, "AX", &mut ax).unwrap();
+        assert_eq!(ax, rax[..2]);
+        let mut ah = [0u8; 1];
+        dbg.read_register_by_name_buf(thread_idx, "AH", &mut ah).unwrap();
+        assert_eq!(ah[0], 0x77);
+
+        dbg.detach().unwrap();
+    }
+
+    #[test]
+    fn sub_register_write_only_touches_its_bytes() {
+        use crate::debugger::debugger::DebuggerHelper;
+
+        let _live = live_test_lock();
+
+        let dbg = x86_64_builder().build().unwrap();
+
+        let pid = dbg.run("/bin/true", &[]).unwrap();
+        let thread_idx = DebuggerThreadIndex::Current;
+        dbg.write_register_by_name_buf(thread_idx, "RAX", &0x1122334455667788u64.to_le_bytes())
+            .unwrap();
+        dbg.write_register_by_name_buf(thread_idx, "AX", &0xbeefu16.to_le_bytes())
+            .unwrap();
+        dbg.write_register_by_name_buf(thread_idx, "AH", &[0x12]).unwrap();
+
+        let rax: u64 = dbg.read_register_by_name(thread_idx, "RAX").unwrap();
+        assert_eq!(rax, 0x11223344_556612ef);
+
+        // make sure the write reached the thread and not just the cache
+        dbg.flush_register_cache(thread_idx).unwrap();
+        let reg_data = superpt::getregs(pid);
+        assert_eq!(
+            u64::from_le_bytes(reg_data[0x50..0x58].try_into().unwrap()),
+            0x11223344_556612ef
+        );
+
+        dbg.detach().unwrap();
+    }
+
+    #[test]
+    fn watchpoint_hit_reports_slot_and_address() {
+        use crate::debugger::debugger::DebuggerHelper;
+
+        let _live = live_test_lock();
+
+        let dbg = x86_64_builder().build().unwrap();
+
+        let pid = dbg.run("/bin/true", &[]).unwrap();
+        let thread_idx = DebuggerThreadIndex::Current;
+        let debugreg = |idx: usize| {
+            let offset = std::mem::offset_of!(libc::user, u_debugreg) + idx * std::mem::size_of::<u64>();
+            superpt::peekuser(pid, offset).unwrap()
+        };
+
+        // the loader's first call pushes its return address right below rsp.
+        // put an unrelated watchpoint in slot 0 so the hit isn't just slot 0
+        let rsp: u64 = dbg.read_register_by_name(thread_idx, "RSP").unwrap();
+        let watch_addr = (rsp - 8) & !7;
+        let unused_slot = dbg
+            .add_watchpoint(thread_idx, 0x1000, 1, DebuggerWatchpointKind::Execute)
+            .unwrap();
+        let slot = dbg
+            .add_watchpoint(thread_idx, watch_addr, 8, DebuggerWatchpointKind::Write)
+            .unwrap();
+        assert_eq!((unused_slot, slot), (0, 1));
+        assert_eq!(debugreg(1), watch_addr);
+        assert_eq!(debugreg(7), 0x0090_0005);
+
+        assert!(matches!(
+            dbg.add_watchpoint(thread_idx, watch_addr + 1, 8, DebuggerWatchpointKind::Write),
+            Err(DebuggerError::InvalidArguments)
+        ));
+
+        dbg.cont_all().unwrap();
+        let event = dbg.wait_next_event(false).unwrap();
+        assert_eq!(event.kind, DebuggerEventKind::WatchpointHit);
+        assert_eq!(event.bp_idx, slot);
+        assert_eq!(event.bp_addr, watch_addr);
+        // cleared so the next hit isn't blamed on this slot too
+        assert_eq!(debugreg(6) & 0xf, 0);
+
+        // fill the last two slots, then there's no room left
+        for addr in [0x2000, 0x3000] {
+            dbg.add_watchpoint(thread_idx, addr, 4, DebuggerWatchpointKind::ReadWrite)
+                .unwrap();
+        }
+        assert!(matches!(
+            dbg.add_watchpoint(thread_idx, 0x4000, 4, DebuggerWatchpointKind::ReadWrite),
+            Err(DebuggerError::NoFreeWatchpoints)
+        ));
+
+        for slot in 0..4 {
+            dbg.remove_watchpoint(thread_idx, slot).unwrap();
+        }
+        assert!(matches!(
+            dbg.remove_watchpoint(thread_idx, 0),
+            Err(DebuggerError::InvalidBreakpoint)
+        ));
+        assert!(matches!(
+            dbg.remove_watchpoint(thread_idx, 4),
+            Err(DebuggerError::InvalidBreakpoint)
+        ));
+        assert_eq!(debugreg(7), 0);
+
+        dbg.detach().unwrap();
+    }
+
+    #[test]
+    fn disassemble_range_matches_single_decodes() {
+        use crate::debugger::debugger::DebuggerHelper;
+
+        let _live = live_test_lock();
+
+        let dbg = x86_64_builder().build().unwrap();
+
+        dbg.run("/bin/true", &[]).unwrap();
+        let pc: u64 = dbg.read_register_by_name(DebuggerThreadIndex::Current, "RIP").unwrap();
+
+        let insts = dbg.disassemble_range(pc, 8).unwrap();
+        assert_eq!(insts.len(), 8);
+        let mut addr = pc;
+        for inst in &insts {
+            assert_eq!(inst, &dbg.disassemble_one(addr).unwrap());
+            addr += inst.len;
+        }
+
+        dbg.detach().unwrap();
+    }
+
+    #[test]
+    fn invalidate_register_cache_picks_up_outside_writes() {
+        let _live = live_test_lock();
+
+        let dbg = x86_64_builder().build().unwrap();
+
+        let pid = dbg.run("/bin/true", &[]).unwrap();
+        let thread_idx = DebuggerThreadIndex::Current;
+        let mut rax = [0u8; 8];
+        dbg.read_register_by_name_buf(thread_idx, "RAX", &mut rax).unwrap();
+
+        // rax is at 0x50 in user_regs_struct
+        let mut reg_data = superpt::getregs(pid);
+        reg_data[0x50..0x58].copy_from_slice(&0x1234u64.to_le_bytes());
+        superpt::setregs(pid, &reg_data);
+
+        dbg.read_register_by_name_buf(thread_idx, "RAX", &mut rax).unwrap();
+        assert_ne!(u64::from_le_bytes(rax), 0x1234);
+
+        dbg.invalidate_register_cache(thread_idx).unwrap();
+        dbg.read_register_by_name_buf(thread_idx, "RAX", &mut rax).unwrap();
+        assert_eq!(u64::from_le_bytes(rax), 0x1234);
+
+        reg_data[0x50..0x58].copy_from_slice(&0x5678u64.to_le_bytes());
+        superpt::setregs(pid, &reg_data);
+        dbg.flush_register_cache(thread_idx).unwrap();
+        assert!(!dbg.state.lock().unwrap().is_reg_cache_dirty(pid));
+        dbg.read_register_by_name_buf(thread_idx, "RAX", &mut rax).unwrap();
+        assert_eq!(u64::from_le_bytes(rax), 0x5678);
+
+        dbg.detach().unwrap();
+    }
+
+    #[test]
+    fn register_cache_is_loaded_per_thread() {
+        let _live = live_test_lock();
+
+        let dbg = x86_64_builder().build().unwrap();
+
+        let pid = dbg.run("/bin/true", &[]).unwrap();
+        let real_idx = DebuggerThreadIndex::Specific(pid as u32);
+        let mut rax = [0u8; 8];
+        dbg.read_register_by_name_buf(real_idx, "RAX", &mut rax).unwrap();
+
+        // a second stopped thread. the pid is past pid_max, so loading its
+        // registers just reads zeros.
+        const OTHER_PID: i32 = 0x7000_0001;
+        let other_idx = DebuggerThreadIndex::Specific(OTHER_PID as u32);
+        {
+            let mut state = dbg.state.lock().unwrap();
+            let mut thread = state.new_thread(OTHER_PID);
+            thread.pause_state = DebuggerLinuxPauseState::StoppedUnknownReason;
+            state.threads.insert(OTHER_PID, thread);
+        }
+
+        // rax is at 0x50 in user_regs_struct
+        let mut reg_data = superpt::getregs(pid);
+        reg_data[0x50..0x58].copy_from_slice(&0x1234u64.to_le_bytes());
+        superpt::setregs(pid, &reg_data);
+        dbg.invalidate_register_cache(real_idx).unwrap();
+
+        // loading the other thread can't make the real one look loaded
+        let mut other_rax = [0u8; 8];
+        dbg.read_register_by_name_buf(other_idx, "RAX", &mut other_rax).unwrap();
+        {
+            let state = dbg.state.lock().unwrap();
+            assert!(!state.is_reg_cache_dirty(OTHER_PID));
+            assert!(state.is_reg_cache_dirty(pid));
+        }
+
+        dbg.read_register_by_name_buf(real_idx, "RAX", &mut rax).unwrap();
+        assert_eq!(u64::from_le_bytes(rax), 0x1234);
+
+        dbg.state.lock().unwrap().threads.remove(&OTHER_PID);
+        dbg.detach().unwrap();
+    }
+
+    #[test]
+    fn ptrace_reads_from_cmd_thread_match_dbg_thread() {
+        use crate::debugger::debugger::DebuggerHelper;
+
+        let _live = live_test_lock();
+
+        let dbg = x86_64_builder()
+            .mem_read_mode(DebuggerMemReadMode::Ptrace)
+            .build()
+            .unwrap();
+
+        dbg.run("/bin/true", &[]).unwrap();
+        let thread_idx = DebuggerThreadIndex::Current;
+        let pc: u64 = dbg.read_register_by_name(thread_idx, "RIP").unwrap();
+
+        // not a multiple of 1024 so the last chunk is a short one
+        let mut dbg_data = vec![0u8; 2600];
+        let dbg_end = dbg.read_bytes(thread_idx, pc, &mut dbg_data).unwrap();
+
+        // the cmd thread's read goes through the dbg thread 1024 bytes at a
+        // time, so the dbg thread has to sit in wait_next_event to serve it
+        let (cmd_data, cmd_end) = thread::scope(|s| {
+            let reader = s.spawn(|| {
+                let mut data = vec![0u8; 2600];
+                let end = dbg.read_bytes(thread_idx, pc, &mut data);
+                dbg.interrupt().unwrap();
+                (data, end)
+            });
+            loop {
+                let event = dbg.wait_next_event(false).unwrap();
+                if event.kind == DebuggerEventKind::Interrupted {
+                    break;
+                }
+            }
+            reader.join().unwrap()
+        });
+
+        assert_eq!(cmd_end.unwrap(), dbg_end);
+        assert_eq!(cmd_data, dbg_data);
+
+        dbg.detach().unwrap();
+    }
+
+    #[test]
+    fn step_out_returns_to_caller() {
+        use crate::debugger::debugger::DebuggerHelper;
+
+        let _live = live_test_lock();
+
+        let dbg = x86_64_builder().build().unwrap();
+
+        dbg.run("/bin/true", &[]).unwrap();
+        let thread_idx = DebuggerThreadIndex::Current;
+        let read_code = |pc: u64| {
+            let mut code = [0u8; RETURN_ADDR_CODE_LEN];
+            dbg.read_bytes(thread_idx, pc, &mut code).unwrap();
+            code
+        };
+
+        // the loader's entry point wasn't called by anything
+        let pc: u64 = dbg.read_register_by_name(thread_idx, "RIP").unwrap();
+        let sp: u64 = dbg.read_register_by_name(thread_idx, "RSP").unwrap();
+        if find_return_addr_slot(&read_code(pc), sp).is_none() {
+            assert!(matches!(dbg.step_out(thread_idx), Err(DebuggerError::NoFrame)));
+        }
+
+        // step until we've just gone through a call into a function with a
+        // prologue step_out understands
+        let mut call_site = None;
+        for _ in 0..10000 {
+            let pc: u64 = dbg.read_register_by_name(thread_idx, "RIP").unwrap();
+            let inst = dbg.disassemble_one(pc).unwrap();
+            let event = dbg.step_n(thread_idx, 1).unwrap();
+            assert_eq!(event.kind, DebuggerEventKind::StepComplete);
+
+            let new_pc: u64 = dbg.read_register_by_name(thread_idx, "RIP").unwrap();
+            let new_sp: u64 = dbg.read_register_by_name(thread_idx, "RSP").unwrap();
+            if inst.text.starts_with("CALL") && new_pc != pc + inst.len {
+                if find_return_addr_slot(&read_code(new_pc), new_sp).is_some() {
+                    call_site = Some((pc + inst.len, new_sp + 8));
+                    break;
+                }
+            }
+        }
+        let (ret_addr, caller_sp) = call_site.expect("never called a function with a prologue");
+
+        dbg.step_out(thread_idx).unwrap();
+        let event = dbg.wait_next_event(false).unwrap();
+        assert_eq!(event.kind, DebuggerEventKind::StepComplete);
+        let pc: u64 = dbg.read_register_by_name(thread_idx, "RIP").unwrap();
+        let sp: u64 = dbg.read_register_by_name(thread_idx, "RSP").unwrap();
+        assert_eq!(pc, ret_addr);
+        assert_eq!(sp, caller_sp);
+
+        dbg.detach().unwrap();
+    }
+
+    #[test]
+    fn step_over_plants_and_removes_temp_bp() {
+        use crate::debugger::debugger::DebuggerHelper;
+        use std::io::{Read, Seek, SeekFrom};
+
+        let _live = live_test_lock();
+
+        let dbg = x86_64_builder().build().unwrap();
+
+        dbg.run("/bin/true", &[]).unwrap();
+        let thread_idx = DebuggerThreadIndex::Current;
+
+        // the loader calls into itself almost right away
+        let mut call = None;
+        for _ in 0..10000 {
+            let pc: u64 = dbg.read_register_by_name(thread_idx, "RIP").unwrap();
+            let inst = dbg.disassemble_one(pc).unwrap();
+            if inst.text.starts_with("CALL") {
+                call = Some(pc + inst.len);
+                break;
+            }
+            assert_eq!(dbg.step_n(thread_idx, 1).unwrap().kind, DebuggerEventKind::StepComplete);
+        }
+        let ret_addr = call.expect("never reached a call");
+        let mut orig_bytes = vec![0u8; SW_BREAKPOINT_BYTES.len()];
+        dbg.read_bytes(thread_idx, ret_addr, &mut orig_bytes).unwrap();
+        assert_ne!(orig_bytes, SW_BREAKPOINT_BYTES);
+
+        dbg.step_over(thread_idx).unwrap();
+
+        // the thread is running now, so look at its memory directly
+        let pid = {
+            let state = dbg.state.lock().unwrap();
+            let (pid, bp, _) = state.step_over_bp.as_ref().expect("no temp breakpoint");
+            assert_eq!(bp.addr, ret_addr);
+            *pid
+        };
+        let mut mem_file = std::fs::File::open(format!("/proc/{}/mem", pid)).unwrap();
+        mem_file.seek(SeekFrom::Start(ret_addr)).unwrap();
+        let mut planted_bytes = vec![0u8; SW_BREAKPOINT_BYTES.len()];
+        mem_file.read_exact(&mut planted_bytes).unwrap();
+        assert_eq!(planted_bytes, SW_BREAKPOINT_BYTES);
+
+        let event = dbg.wait_next_event(false).unwrap();
+        assert_eq!(event.kind, DebuggerEventKind::StepComplete);
+        let pc: u64 = dbg.read_register_by_name(thread_idx, "RIP").unwrap();
+        assert_eq!(pc, ret_addr);
+
+        assert!(dbg.state.lock().unwrap().step_over_bp.is_none());
+        let mut after_bytes = vec![0u8; SW_BREAKPOINT_BYTES.len()];
+        dbg.read_bytes(thread_idx, ret_addr, &mut after_bytes).unwrap();
+        assert_eq!(after_bytes, orig_bytes);
+
+        dbg.detach().unwrap();
+    }
+
+    #[test]
+    fn attach_stops_a_running_child() {
+        use crate::debugger::debugger::DebuggerHelper;
+
+        let _live = live_test_lock();
+
+        let mut child = std::process::Command::new("sleep").arg("10").spawn().unwrap();
+        let pid = child.id() as i32;
+
+        let dbg = x86_64_builder().build().unwrap();
+        assert_eq!(dbg.attach(pid).unwrap(), pid);
+        {
+            let state = dbg.state.lock().unwrap();
+            assert_eq!(state.cur_thread_pid, Some(pid));
+            assert_eq!(state.threads[&pid].pause_state, DebuggerLinuxPauseState::FirstStop);
+            assert!(!state.threads[&pid].stop_all_pending);
+            // the attach SIGSTOP was ours, so it isn't reported
+            assert!(state.queued_events.is_empty());
+        }
+        let pc: u64 = dbg.read_register_by_name(DebuggerThreadIndex::Current, "RIP").unwrap();
+        assert_ne!(pc, 0);
+
+        // the attach SIGSTOP was swallowed, so the child goes back to sleeping
+        dbg.detach().unwrap();
+        assert!(child.try_wait().unwrap().is_none());
+
+        _ = child.kill();
+        _ = child.wait();
+    }
+
+    #[test]
+    fn interrupt_wakes_wait_next_event() {
+        let _live = live_test_lock();
+
+        let dbg = x86_64_builder().build().unwrap();
+        assert!(matches!(dbg.interrupt(), Err(DebuggerError::NoThreads)));
+        dbg.run("/bin/true", &[]).unwrap();
+
+        // the thread is stopped, so only the interrupt can wake this up
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                std::thread::sleep(std::time::Duration::from_millis(100));
+                dbg.interrupt().unwrap();
+            });
+            let event = dbg.wait_next_event(false).unwrap();
+            assert_eq!(event.kind, DebuggerEventKind::Interrupted);
+        });
+
+        // with nothing waiting, the next wait picks it up. several interrupts
+        // before then only wake it once.
+        dbg.interrupt().unwrap();
+        dbg.interrupt().unwrap();
+        assert_eq!(dbg.wait_next_event(false).unwrap().kind, DebuggerEventKind::Interrupted);
+        let interrupt_fd = dbg
+            .session_state
+            .read()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .chan_cont
+            .interrupt_fd;
+        let mut data = [0u64; 1];
+        let res = unsafe { libc::read(interrupt_fd, &mut data as *mut u64 as *mut libc::c_void, 8) };
+        assert_eq!(res, -1);
+
+        dbg.detach().unwrap();
+    }
+
+    #[test]
+    fn interrupt_inferior_stops_a_running_thread() {
+        let _live = live_test_lock();
+
+        // the state letter in /proc/pid/stat, t = ptrace stop, T = job control stop
+        fn proc_state(pid: i32) -> char {
+            let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).unwrap();
+            stat[stat.rfind(')').unwrap() + 2..].chars().next().unwrap()
+        }
+
+        let dbg = x86_64_builder().build().unwrap();
+        let pid = dbg.run("/bin/sleep", &["sleep", "10"]).unwrap();
+
+        // nothing is running yet
+        dbg.interrupt_inferior().unwrap();
+        assert_eq!(dbg.wait_next_event(true).unwrap().kind, DebuggerEventKind::NoEvent);
+
+        for _ in 0..2 {
+            dbg.cont_all().unwrap();
+            dbg.interrupt_inferior().unwrap();
+            let event = dbg.wait_next_event(false).unwrap();
+            assert_eq!(event.kind, DebuggerEventKind::MiscSignalReceived);
+            assert!(dbg.state.lock().unwrap().threads[&pid].pause_state.is_stopped());
+            assert_eq!(proc_state(pid), 't');
+        }
+
+        // the SIGSTOP isn't passed on, so sleep goes back to sleeping
+        dbg.detach().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert_eq!(proc_state(pid), 'S');
+
+        unsafe {
+            libc::kill(pid, libc::SIGKILL);
+        }
+        superpt::waitpid(pid);
+    }
+
+    #[test]
+    fn raw_regset_matches_ptrace_layout() {
+        let _live = live_test_lock();
+
+        let dbg = x86_64_builder().build().unwrap();
+
+        dbg.run("/bin/true", &[]).unwrap();
+        let thread_idx = DebuggerThreadIndex::Current;
+        let prstatus = dbg.get_raw_regset(thread_idx, DebuggerLinuxRegset::Prstatus).unwrap();
+        let fpregset = dbg.get_raw_regset(thread_idx, DebuggerLinuxRegset::Fpregset).unwrap();
+        assert_eq!(prstatus.len(), superpt::GETREGS_BYTESIZE);
+        assert_eq!(fpregset.len(), superpt::GETFPREGS_BYTESIZE);
+
+        // rip is at 0x80 in user_regs_struct
+        let mut rip = [0u8; 8];
+        dbg.read_register_by_name_buf(thread_idx, "RIP", &mut rip).unwrap();
+        assert_eq!(prstatus[0x80..0x88], rip);
+
+        // cached writes show up in the blob
+        dbg.write_register_by_name_buf(thread_idx, "RAX", &0x4142u64.to_le_bytes())
+            .unwrap();
+        let prstatus = dbg.get_raw_regset(thread_idx, DebuggerLinuxRegset::Prstatus).unwrap();
+        assert_eq!(prstatus[0x50..0x58], 0x4142u64.to_le_bytes());
+
+        dbg.detach().unwrap();
+    }
+
+    #[test]
+    fn flags_pick_ptrace_options() {
+        let dbg = x86_64_builder().build().unwrap();
+        assert_eq!(
+            dbg.ptrace_options(),
+            libc::PTRACE_O_TRACECLONE | libc::PTRACE_O_TRACEEXEC | libc::PTRACE_O_TRACESYSGOOD
+        );
+
+        let dbg = x86_64_builder().flags(DebuggerFlags::ExitKill).build().unwrap();
+        assert_eq!(
+            dbg.ptrace_options(),
+            libc::PTRACE_O_TRACECLONE
+                | libc::PTRACE_O_TRACEEXEC
+                | libc::PTRACE_O_TRACESYSGOOD
+                | libc::PTRACE_O_EXITKILL
+        );
+
+        let dbg = x86_64_builder()
+            .flags(DebuggerFlags::ExitKill)
+            .without_flags(DebuggerFlags::TraceClone | DebuggerFlags::TraceExec)
+            .build()
+            .unwrap();
+        assert_eq!(
+            dbg.ptrace_options(),
+            libc::PTRACE_O_EXITKILL | libc::PTRACE_O_TRACESYSGOOD
+        );
+
+        dbg.set_flags(DebuggerFlags::empty()).unwrap();
+        assert_eq!(dbg.ptrace_options(), 0);
+    }
+
+    #[test]
+    fn mem_read_cache_is_dropped_on_step() {
+        use crate::debugger::debugger::DebuggerHelper;
+
+        let _live = live_test_lock();
+
+        let dbg = x86_64_builder().mem_read_cache(true).build().unwrap();
+
+        let pid = dbg.run("/bin/true", &[]).unwrap();
+        let thread_idx = DebuggerThreadIndex::Current;
+        // well below anything the first instruction could touch
+        let rsp: u64 = dbg.read_register_by_name(thread_idx, "RSP").unwrap();
+        let addr = rsp - 0x1000;
+
+        let mut before = [0u8; 8];
+        dbg.read_bytes(thread_idx, addr, &mut before).unwrap();
+
+        // written behind the debugger's back, so only a fresh read sees it
+        let mut outside_view = DebuggerLinuxMemView::new(pid);
+        let new_bytes = (u64::from_le_bytes(before) ^ 0xffff).to_le_bytes();
+        let mut write_addr = addr;
+        outside_view.write_bytes(&mut write_addr, &new_bytes).unwrap();
+
+        let mut after = [0u8; 8];
+        dbg.read_bytes(thread_idx, addr, &mut after).unwrap();
+        assert_eq!(after, before);
+
+        dbg.step_n(thread_idx, 1).unwrap();
+        dbg.read_bytes(thread_idx, addr, &mut after).unwrap();
+        assert_eq!(after, new_bytes);
+
+        // writes through the debugger replace what's cached right away
+        dbg.write_bytes(thread_idx, addr, &before).unwrap();
+        dbg.read_bytes(thread_idx, addr, &mut after).unwrap();
+        assert_eq!(after, before);
+
+        dbg.detach().unwrap();
+    }
+
+    // cargo test --release mem_read_cache_benchmark -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn mem_read_cache_benchmark() {
+        use crate::debugger::debugger::DebuggerHelper;
+        use std::time::Instant;
+
+        const ROUNDS: u32 = 1000;
+        for mem_read_cache in [false, true] {
+            let dbg = x86_64_builder().mem_read_cache(mem_read_cache).build().unwrap();
+
+            dbg.run("/bin/true", &[]).unwrap();
+            let pc: u64 = dbg.read_register_by_name(DebuggerThreadIndex::Current, "RIP").unwrap();
+
+            // what the cli does after each step: everything is dropped and
+            // the window around pc is disassembled from scratch
+            let start = Instant::now();
+            for _ in 0..ROUNDS {
+                dbg.disasm.invalidate(pc, 0x1000);
+                dbg.state.lock().unwrap().mark_dirty();
+                dbg.disassemble_range(pc, 32).unwrap();
+            }
+            let elapsed = start.elapsed();
+            println!(
+                "mem_read_cache={}: {:?} per 32 instruction window",
+                mem_read_cache,
+                elapsed / ROUNDS
+            );
+
+            dbg.detach().unwrap();
+        }
+    }
+
+    #[test]
+    fn scan_memory_sees_through_breakpoints() {
+        use crate::debugger::debugger::DebuggerHelper;
+
+        let _live = live_test_lock();
+
+        let dbg = x86_64_builder().build().unwrap();
+
+        dbg.run("/bin/true", &[]).unwrap();
+        let thread_idx = DebuggerThreadIndex::Current;
+        let pc: u64 = dbg.read_register_by_name(thread_idx, "RIP").unwrap();
+        let mut code = [0u8; 8];
+        dbg.read_bytes(thread_idx, pc, &mut code).unwrap();
+
+        dbg.add_breakpoint(thread_idx, pc).unwrap();
+        assert_eq!(
+            dbg.scan_memory(thread_idx, pc, pc + 0x100, &code, None).unwrap(),
+            Some(pc)
+        );
+        let int3 = SW_BREAKPOINT_BYTES.to_vec();
+        let found = dbg.scan_memory(thread_idx, pc, pc + 1, &int3, None).unwrap();
+        assert_eq!(found, None);
+
+        // the whole address space, which only works if the gaps are skipped
+        let found = dbg.scan_memory(thread_idx, 0, u64::MAX, &code, None).unwrap();
+        assert!(found.is_some_and(|addr| addr <= pc));
+        let found = dbg
+            .scan_memory(thread_idx, 0, u64::MAX, b"not in /bin/true", None)
+            .unwrap();
+        assert_eq!(found, None);
+
+        dbg.detach().unwrap();
+    }
+
+    #[test]
+    fn scan_memory_matches_across_touching_mappings() {
+        use crate::debugger::debugger::DebuggerHelper;
+
+        let _live = live_test_lock();
+
+        let dbg = x86_64_builder().build().unwrap();
+
+        dbg.run("/bin/true", &[]).unwrap();
+        let thread_idx = DebuggerThreadIndex::Current;
+        let pc: u64 = dbg.read_register_by_name(thread_idx, "RIP").unwrap();
+
+        // the loader's code is mapped right up against its read-only data
+        let code_map = dbg.query_address(thread_idx, pc).unwrap().unwrap();
+        let next_map = dbg.query_address(thread_idx, code_map.end).unwrap().unwrap();
+        assert_eq!(next_map.start, code_map.end);
+
+        let mut needle = [0u8; 4];
+        dbg.read_bytes(thread_idx, code_map.end - 2, &mut needle).unwrap();
+        let found = dbg
+            .scan_memory(thread_idx, code_map.end - 2, code_map.end + 2, &needle, None)
+            .unwrap();
+        assert_eq!(found, Some(code_map.end - 2));
+
+        dbg.detach().unwrap();
+    }
+}
@@ -1,20 +1,166 @@
-use super::debugger_linux::DebuggerLinuxPauseState;
-use crate::debugger::debugger::DebuggerEventKind;
+use super::{
+    debugger_linux::{DebuggerLinuxPauseState, DebuggerLinuxWatchpoint},
+    debugger_linux_superpt as superpt,
+};
+use crate::debugger::debugger::{DebuggerError, DebuggerEventKind, DebuggerWatchpointKind};
 
-pub fn convert_si_code(si_code: i32) -> (DebuggerLinuxPauseState, DebuggerEventKind) {
+// int3
+pub const SW_BREAKPOINT_BYTES: &[u8] = &[0xcc];
+
+// what a plain SIGTRAP means, going by where x86 linux raises it. syscall
+// stops and other signals are sorted out by convert_stop first.
+//
+//   SI_KERNEL    int3 ran                               -> breakpoint hit
+//   TRAP_TRACE   the single step bit in dr6 was set     -> step complete *
+//   TRAP_BRKPT   a single step over `syscall` finished  -> syscall step complete *
+//   TRAP_HWBKPT  a debug register slot fired            -> watchpoint hit
+//
+// * only if we asked for a step. the program can set the trap flag itself,
+//   and that isn't a step anyone here is waiting on.
+pub fn convert_si_code(si_code: i32, prev: &DebuggerLinuxPauseState) -> (DebuggerLinuxPauseState, DebuggerEventKind) {
     match si_code {
         libc::SI_KERNEL => (
             DebuggerLinuxPauseState::SwBreakpointHit,
             DebuggerEventKind::BreakpointHit,
         ),
-        libc::TRAP_BRKPT => (
+        libc::TRAP_TRACE if prev.is_stepping() => {
+            (DebuggerLinuxPauseState::StepCompleted, DebuggerEventKind::StepComplete)
+        }
+        // the kernel reports the step when the syscall returns
+        libc::TRAP_BRKPT if prev.is_stepping() => (
             DebuggerLinuxPauseState::SyscallHitEnd,
             DebuggerEventKind::StepCompleteSyscall,
         ),
-        libc::TRAP_TRACE => (DebuggerLinuxPauseState::StepCompleted, DebuggerEventKind::StepComplete),
+        libc::TRAP_HWBKPT => (
+            DebuggerLinuxPauseState::HwBreakpointHit,
+            DebuggerEventKind::WatchpointHit,
+        ),
         _ => (
             DebuggerLinuxPauseState::StoppedUnknownReason,
             DebuggerEventKind::MiscSignalReceived,
         ),
     }
 }
+
+pub const WATCHPOINT_SLOT_COUNT: usize = 4;
+
+fn debugreg_offset(idx: usize) -> usize {
+    std::mem::offset_of!(libc::user, u_debugreg) + idx * std::mem::size_of::<u64>()
+}
+
+// the dr7 value that enables every used slot. only the local enable bits are
+// set, linux doesn't let us touch the global ones.
+pub fn encode_dr7(slots: &[Option<DebuggerLinuxWatchpoint>]) -> Result<u64, DebuggerError> {
+    let mut dr7 = 0u64;
+    for (i, slot) in slots.iter().enumerate() {
+        let wp = match slot {
+            Some(v) => v,
+            None => continue,
+        };
+
+        let rw_bits: u64 = match wp.kind {
+            DebuggerWatchpointKind::Execute => 0b00,
+            DebuggerWatchpointKind::Write => 0b01,
+            DebuggerWatchpointKind::ReadWrite => 0b11,
+        };
+        let len_bits: u64 = match (wp.kind, wp.len) {
+            (_, 1) => 0b00,
+            (DebuggerWatchpointKind::Execute, _) => return Err(DebuggerError::InvalidArguments),
+            (_, 2) => 0b01,
+            (_, 4) => 0b11,
+            (_, 8) => 0b10,
+            _ => return Err(DebuggerError::InvalidArguments),
+        };
+        if wp.addr % wp.len as u64 != 0 {
+            return Err(DebuggerError::InvalidArguments);
+        }
+
+        dr7 |= 1 << (i * 2);
+        dr7 |= rw_bits << (16 + i * 4);
+        dr7 |= len_bits << (18 + i * 4);
+    }
+    Ok(dr7)
+}
+
+pub fn write_watchpoints(pid: i32, slots: &[Option<DebuggerLinuxWatchpoint>]) -> Result<(), DebuggerError> {
+    let dr7 = encode_dr7(slots)?;
+
+    // linux checks dr7 against the address registers on every write,
+    // so turn everything off before moving addresses around
+    superpt::pokeuser(pid, debugreg_offset(7), 0).or(Err(DebuggerError::InternalError))?;
+    for (i, slot) in slots.iter().enumerate() {
+        if let Some(wp) = slot {
+            superpt::pokeuser(pid, debugreg_offset(i), wp.addr).or(Err(DebuggerError::InternalError))?;
+        }
+    }
+    superpt::pokeuser(pid, debugreg_offset(7), dr7).or(Err(DebuggerError::InternalError))?;
+    Ok(())
+}
+
+// which slot caused a TRAP_HWBKPT stop. dr6 is sticky, so this clears it too,
+// otherwise the next hit would look like it came from this slot as well.
+pub fn take_hit_watchpoint_slot(pid: i32) -> Option<u32> {
+    let dr6 = superpt::peekuser(pid, debugreg_offset(6)).ok()?;
+    _ = superpt::pokeuser(pid, debugreg_offset(6), 0);
+    (0..WATCHPOINT_SLOT_COUNT as u32).find(|i| dr6 & (1 << i) != 0)
+}
+
+// how many bytes at pc find_return_addr_slot looks at
+pub const RETURN_ADDR_CODE_LEN: usize = 4;
+
+// where on the stack the current function's return address is, given the code
+// at pc. this only works at the start or end of a function, where the code says
+// exactly what's on the stack. anywhere else rbp might not be a frame pointer
+// (leaf functions, -fomit-frame-pointer), and guessing would put the breakpoint
+// on some caller's return address, so that's None.
+pub fn find_return_addr_slot(code: &[u8; RETURN_ADDR_CODE_LEN], sp: u64) -> Option<u64> {
+    // endbr64, push rbp or ret: nothing is pushed, the return address is on top
+    if *code == [0xf3, 0x0f, 0x1e, 0xfa] || code[0] == 0x55 || code[0] == 0xc3 {
+        return Some(sp);
+    }
+
+    // mov rbp, rsp: only the old rbp is on top of it
+    if code[..3] == [0x48, 0x89, 0xe5] {
+        return sp.checked_add(8);
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_wp(addr: u64, len: u32, kind: DebuggerWatchpointKind) -> Option<DebuggerLinuxWatchpoint> {
+        Some(DebuggerLinuxWatchpoint { addr, len, kind })
+    }
+
+    #[test]
+    fn encodes_dr7_slots() {
+        let slots = [
+            make_wp(0x1000, 1, DebuggerWatchpointKind::Execute),
+            None,
+            make_wp(0x2000, 8, DebuggerWatchpointKind::Write),
+            make_wp(0x3004, 4, DebuggerWatchpointKind::ReadWrite),
+        ];
+        // L0, L2, L3, then rw/len for slot 2 (01/10) and slot 3 (11/11)
+        assert_eq!(encode_dr7(&slots).unwrap(), 0xf900_0051);
+
+        assert!(encode_dr7(&[make_wp(0x1000, 2, DebuggerWatchpointKind::Execute)]).is_err());
+        assert!(encode_dr7(&[make_wp(0x1002, 4, DebuggerWatchpointKind::Write)]).is_err());
+        assert!(encode_dr7(&[make_wp(0x1000, 3, DebuggerWatchpointKind::Write)]).is_err());
+    }
+
+    #[test]
+    fn finds_return_addr_through_prologue() {
+        let sp = 0x7ff0;
+        assert_eq!(find_return_addr_slot(&[0x55, 0x48, 0x89, 0xe5], sp), Some(sp));
+        assert_eq!(find_return_addr_slot(&[0xf3, 0x0f, 0x1e, 0xfa], sp), Some(sp));
+        assert_eq!(find_return_addr_slot(&[0xc3, 0x90, 0x90, 0x90], sp), Some(sp));
+        assert_eq!(find_return_addr_slot(&[0x48, 0x89, 0xe5, 0x90], sp), Some(sp + 8));
+
+        // the middle of a function, where rbp may or may not be a frame
+        assert_eq!(find_return_addr_slot(&[0x90, 0x90, 0x90, 0x90], sp), None);
+        assert_eq!(find_return_addr_slot(&[0x48, 0x89, 0xe7, 0x90], sp), None);
+    }
+}
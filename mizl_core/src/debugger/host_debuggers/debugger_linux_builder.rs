@@ -0,0 +1,270 @@
+use super::{
+    debugger_linux::DebuggerLinux,
+    debugger_linux_event_source::{DebuggerLinuxEventSource, DebuggerLinuxPtraceEventSource},
+};
+use crate::{
+    debugger::{
+        debugger::{DebuggerError, DebuggerFlags},
+        module_symbols::ModuleSymbolResolver,
+    },
+    sleigh::{disasm::Disasm, disasm_cache::DisasmCache, pspec_file::Pspec, sla_file::Sleigh},
+};
+use std::fs;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DebuggerArch {
+    Amd64,
+}
+
+impl DebuggerArch {
+    // the architecture this was built for, which is the only one ptrace can debug
+    pub fn host() -> Option<DebuggerArch> {
+        if cfg!(target_arch = "x86_64") {
+            Some(DebuggerArch::Amd64)
+        } else {
+            None
+        }
+    }
+
+    // (sla, pspec) looked up relative to the working directory
+    fn default_spec_paths(&self) -> (&'static str, &'static str) {
+        match self {
+            DebuggerArch::Amd64 => ("x86-64.sla", "x86-64.pspec"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DebuggerMemReadMode {
+    // /proc/[pid]/mem, falling back to ptrace if it can't be opened
+    ProcMem,
+    // always PTRACE_PEEKDATA/POKEDATA, one word at a time
+    Ptrace,
+}
+
+enum DebuggerSpecSource {
+    Default,
+    Path(String),
+    Bytes(Vec<u8>),
+}
+
+impl DebuggerSpecSource {
+    fn load(self, default_path: &str) -> Result<Vec<u8>, DebuggerError> {
+        match self {
+            DebuggerSpecSource::Default => fs::read(default_path).or(Err(DebuggerError::InvalidSpec)),
+            DebuggerSpecSource::Path(path) => fs::read(path).or(Err(DebuggerError::InvalidSpec)),
+            DebuggerSpecSource::Bytes(data) => Ok(data),
+        }
+    }
+}
+
+// sets up everything a DebuggerLinux needs before any process is started.
+// nothing is loaded until build, which reports problems instead of panicking.
+pub struct DebuggerBuilder {
+    arch: Option<DebuggerArch>,
+    sla: DebuggerSpecSource,
+    pspec: DebuggerSpecSource,
+    flags: DebuggerFlags,
+    symbols: Option<ModuleSymbolResolver>,
+    mem_read_mode: DebuggerMemReadMode,
+    mem_read_cache: bool,
+    disasm_cache: bool,
+    event_source: Option<Box<dyn DebuggerLinuxEventSource>>,
+}
+
+impl Default for DebuggerBuilder {
+    fn default() -> DebuggerBuilder {
+        DebuggerBuilder::new()
+    }
+}
+
+impl DebuggerBuilder {
+    pub fn new() -> DebuggerBuilder {
+        DebuggerBuilder {
+            arch: DebuggerArch::host(),
+            sla: DebuggerSpecSource::Default,
+            pspec: DebuggerSpecSource::Default,
+            flags: DebuggerFlags::TraceClone | DebuggerFlags::TraceExec | DebuggerFlags::TraceSysGood,
+            symbols: None,
+            mem_read_mode: DebuggerMemReadMode::ProcMem,
+            mem_read_cache: false,
+            disasm_cache: false,
+            event_source: None,
+        }
+    }
+
+    pub fn arch(mut self, arch: DebuggerArch) -> DebuggerBuilder {
+        self.arch = Some(arch);
+        self
+    }
+
+    pub fn sla_path(mut self, path: &str) -> DebuggerBuilder {
+        self.sla = DebuggerSpecSource::Path(path.to_string());
+        self
+    }
+
+    pub fn sla_bytes(mut self, data: Vec<u8>) -> DebuggerBuilder {
+        self.sla = DebuggerSpecSource::Bytes(data);
+        self
+    }
+
+    pub fn pspec_path(mut self, path: &str) -> DebuggerBuilder {
+        self.pspec = DebuggerSpecSource::Path(path.to_string());
+        self
+    }
+
+    pub fn pspec_bytes(mut self, data: Vec<u8>) -> DebuggerBuilder {
+        self.pspec = DebuggerSpecSource::Bytes(data);
+        self
+    }
+
+    // adds to the default flags (TraceClone | TraceExec | TraceSysGood)
+    pub fn flags(mut self, flags: DebuggerFlags) -> DebuggerBuilder {
+        self.flags |= flags;
+        self
+    }
+
+    // removes flags, defaults included. without the trace flags, new threads
+    // aren't followed and exec and syscall stops look like any other SIGTRAP.
+    pub fn without_flags(mut self, flags: DebuggerFlags) -> DebuggerBuilder {
+        self.flags -= flags;
+        self
+    }
+
+    pub fn symbols(mut self, symbols: ModuleSymbolResolver) -> DebuggerBuilder {
+        self.symbols = Some(symbols);
+        self
+    }
+
+    pub fn mem_read_mode(mut self, mode: DebuggerMemReadMode) -> DebuggerBuilder {
+        self.mem_read_mode = mode;
+        self
+    }
+
+    // keeps whole pages of process memory around while it's stopped, so the
+    // many small reads of a disassembly window only hit the process once per
+    // page. it's dropped every time anything runs. off by default.
+    pub fn mem_read_cache(mut self, enabled: bool) -> DebuggerBuilder {
+        self.mem_read_cache = enabled;
+        self
+    }
+
+    // remembers recently decoded instructions, which the cli shows again
+    // around pc after every step. each hit still reads the instruction's bytes
+    // to make sure they haven't changed. off by default.
+    pub fn disasm_cache(mut self, enabled: bool) -> DebuggerBuilder {
+        self.disasm_cache = enabled;
+        self
+    }
+
+    // events normally come from waitpid, see DebuggerLinuxEventSource
+    pub fn event_source(mut self, event_source: Box<dyn DebuggerLinuxEventSource>) -> DebuggerBuilder {
+        self.event_source = Some(event_source);
+        self
+    }
+
+    pub fn build(self) -> Result<DebuggerLinux, DebuggerError> {
+        // ptrace can only debug processes of our own architecture
+        let arch = match self.arch {
+            Some(v) if Some(v) == DebuggerArch::host() => v,
+            _ => return Err(DebuggerError::InvalidArguments),
+        };
+
+        let mut disasm = Self::load_disasm(arch, self.sla, self.pspec)?;
+        if self.disasm_cache {
+            disasm.set_cache_capacity(DisasmCache::DEFAULT_CAPACITY);
+        }
+        let event_source = match self.event_source {
+            Some(v) => v,
+            None => Box::new(DebuggerLinuxPtraceEventSource),
+        };
+
+        Ok(DebuggerLinux::new_from_parts(
+            disasm,
+            event_source,
+            self.flags,
+            self.symbols,
+            self.mem_read_mode,
+            self.mem_read_cache,
+        ))
+    }
+
+    fn load_disasm(
+        arch: DebuggerArch,
+        sla: DebuggerSpecSource,
+        pspec: DebuggerSpecSource,
+    ) -> Result<Disasm, DebuggerError> {
+        let (sla_path, pspec_path) = arch.default_spec_paths();
+        let sla_data = sla.load(sla_path)?;
+        let pspec_data = match String::from_utf8(pspec.load(pspec_path)?) {
+            Ok(v) => v,
+            Err(_) => return Err(DebuggerError::InvalidSpec),
+        };
+
+        let sleigh = Sleigh::try_new(&sla_data).or(Err(DebuggerError::InvalidSpec))?;
+        let pspec = Pspec::new(pspec_data).or(Err(DebuggerError::InvalidSpec))?;
+        let initial_ctx = pspec.get_initial_ctx(&sleigh).or(Err(DebuggerError::InvalidSpec))?;
+        Ok(Disasm::new(sleigh, initial_ctx))
+    }
+}
+
+// a builder using the x86-64 spec at the root of the repo, for tests
+#[cfg(test)]
+pub(crate) fn x86_64_builder() -> DebuggerBuilder {
+    let root = concat!(env!("CARGO_MANIFEST_DIR"), "/..");
+    DebuggerBuilder::new()
+        .sla_path(&format!("{}/x86-64.sla", root))
+        .pspec_path(&format!("{}/x86-64.pspec", root))
+}
+
+// the ptrace event source waits on any child (waitpid(-1)), so two debuggers
+// running children in the same process steal each other's events. tests that
+// start or attach to a real process hold this so they don't run in parallel.
+#[cfg(test)]
+pub(crate) fn live_test_lock() -> std::sync::MutexGuard<'static, ()> {
+    static LIVE_TEST_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    // a failed test poisons the lock, but the next test can still run fine
+    LIVE_TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::debugger::debugger::Debugger;
+
+    #[test]
+    fn reports_bad_specs() {
+        let root = concat!(env!("CARGO_MANIFEST_DIR"), "/..");
+        let sla_path = format!("{}/x86-64.sla", root);
+        let pspec_path = format!("{}/x86-64.pspec", root);
+
+        let result = DebuggerBuilder::new().sla_path("/nonexistent.sla").build();
+        assert!(matches!(result, Err(DebuggerError::InvalidSpec)));
+
+        let result = DebuggerBuilder::new()
+            .sla_bytes(vec![0u8; 16])
+            .pspec_path(&pspec_path)
+            .build();
+        assert!(matches!(result, Err(DebuggerError::InvalidSpec)));
+
+        let result = DebuggerBuilder::new()
+            .sla_path(&sla_path)
+            .pspec_bytes(b"<processor_spec>".to_vec())
+            .build();
+        assert!(matches!(result, Err(DebuggerError::InvalidSpec)));
+
+        let dbg = DebuggerBuilder::new()
+            .sla_path(&sla_path)
+            .pspec_path(&pspec_path)
+            .flags(DebuggerFlags::NonStop)
+            .mem_read_mode(DebuggerMemReadMode::Ptrace)
+            .symbols(ModuleSymbolResolver::new())
+            .build()
+            .unwrap();
+        assert!(
+            dbg.get_flags()
+                .contains(DebuggerFlags::NonStop | DebuggerFlags::TraceClone)
+        );
+        assert!(dbg.get_symbols().is_some());
+    }
+}
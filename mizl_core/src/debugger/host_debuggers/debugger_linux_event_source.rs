@@ -0,0 +1,96 @@
+use super::debugger_linux_superpt as superpt;
+use std::{collections::VecDeque, sync::Mutex};
+
+// one waitpid result. si_code comes from PTRACE_GETSIGINFO and is only
+// meaningful when the thread stopped (0 otherwise).
+#[derive(Clone, Copy, Debug)]
+pub struct DebuggerLinuxRawEvent {
+    pub status: i32,
+    pub pid: i32,
+    pub si_code: i32,
+}
+
+impl DebuggerLinuxRawEvent {
+    pub fn new(status: i32, pid: i32, si_code: i32) -> DebuggerLinuxRawEvent {
+        DebuggerLinuxRawEvent { status, pid, si_code }
+    }
+
+    // a waitpid status for a thread stopped by `sig`, for scripting events
+    pub fn stopped(pid: i32, sig: i32, si_code: i32) -> DebuggerLinuxRawEvent {
+        DebuggerLinuxRawEvent::new((sig << 8) | 0x7f, pid, si_code)
+    }
+}
+
+// where DebuggerLinux gets child events from. normally this is waitpid, but
+// tests can swap in a scripted list to drive the stop handling state machine
+// without a real process.
+pub trait DebuggerLinuxEventSource: Send + Sync {
+    // the next pending event without blocking, None if there isn't one
+    fn next_event(&self) -> Option<DebuggerLinuxRawEvent>;
+
+    // the next event, blocking until one shows up. None if nothing is left
+    // that could ever send one.
+    fn wait_event(&self) -> Option<DebuggerLinuxRawEvent> {
+        self.next_event()
+    }
+}
+
+pub struct DebuggerLinuxPtraceEventSource;
+
+impl DebuggerLinuxPtraceEventSource {
+    fn to_raw_event(status: i32, pid: i32) -> Option<DebuggerLinuxRawEvent> {
+        if pid <= 0 {
+            return None;
+        }
+
+        let si_code = if libc::WIFSTOPPED(status) {
+            superpt::getsiginfo(pid).si_code
+        } else {
+            0
+        };
+        Some(DebuggerLinuxRawEvent::new(status, pid, si_code))
+    }
+}
+
+impl DebuggerLinuxEventSource for DebuggerLinuxPtraceEventSource {
+    fn next_event(&self) -> Option<DebuggerLinuxRawEvent> {
+        let (status, pid) = superpt::waitpid_nohang(-1);
+        Self::to_raw_event(status, pid)
+    }
+
+    fn wait_event(&self) -> Option<DebuggerLinuxRawEvent> {
+        let (status, pid) = superpt::waitpid_thread(-1);
+        Self::to_raw_event(status, pid)
+    }
+}
+
+// hands back a fixed sequence of events in order
+pub struct DebuggerLinuxReplayEventSource {
+    events: Mutex<VecDeque<DebuggerLinuxRawEvent>>,
+}
+
+impl DebuggerLinuxReplayEventSource {
+    pub fn new(events: Vec<DebuggerLinuxRawEvent>) -> DebuggerLinuxReplayEventSource {
+        DebuggerLinuxReplayEventSource {
+            events: Mutex::new(events.into()),
+        }
+    }
+
+    pub fn push(&self, event: DebuggerLinuxRawEvent) {
+        self.events.lock().unwrap().push_back(event);
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.lock().unwrap().is_empty()
+    }
+}
+
+impl DebuggerLinuxEventSource for DebuggerLinuxReplayEventSource {
+    fn next_event(&self) -> Option<DebuggerLinuxRawEvent> {
+        self.events.lock().unwrap().pop_front()
+    }
+}
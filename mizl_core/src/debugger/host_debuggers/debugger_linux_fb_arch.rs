@@ -1,20 +1,42 @@
-use super::debugger_linux::DebuggerLinuxPauseState;
-use crate::debugger::debugger::DebuggerEventKind;
+use super::debugger_linux::{DebuggerLinuxPauseState, DebuggerLinuxWatchpoint};
+use crate::debugger::debugger::{DebuggerError, DebuggerEventKind};
 
-pub fn convert_si_code(si_code: i32) -> (DebuggerLinuxPauseState, DebuggerEventKind) {
+// brk #0 (aarch64, little endian). replace this when adding a new architecture.
+pub const SW_BREAKPOINT_BYTES: &[u8] = &[0x00, 0x00, 0x20, 0xd4];
+
+// the generic meaning of each si_code, see the amd64 version for the table.
+// arches that report things differently need their own.
+pub fn convert_si_code(si_code: i32, prev: &DebuggerLinuxPauseState) -> (DebuggerLinuxPauseState, DebuggerEventKind) {
     match si_code {
-        libc::SI_KERNEL => (DebuggerLinuxPauseState::StepCompleted, DebuggerEventKind::StepComplete),
         libc::TRAP_BRKPT => (
             DebuggerLinuxPauseState::SwBreakpointHit,
             DebuggerEventKind::BreakpointHit,
         ),
-        libc::TRAP_TRACE => (
-            DebuggerLinuxPauseState::StoppedUnknownReason,
-            DebuggerEventKind::MiscSignalReceived,
-        ),
+        libc::TRAP_TRACE if prev.is_stepping() => {
+            (DebuggerLinuxPauseState::StepCompleted, DebuggerEventKind::StepComplete)
+        }
         _ => (
             DebuggerLinuxPauseState::StoppedUnknownReason,
             DebuggerEventKind::MiscSignalReceived,
         ),
     }
 }
+
+// no hardware watchpoints here yet, so add_watchpoint always runs out of slots
+pub const WATCHPOINT_SLOT_COUNT: usize = 0;
+
+pub fn write_watchpoints(_pid: i32, _slots: &[Option<DebuggerLinuxWatchpoint>]) -> Result<(), DebuggerError> {
+    Ok(())
+}
+
+pub fn take_hit_watchpoint_slot(_pid: i32) -> Option<u32> {
+    None
+}
+
+// the return address starts out in a register here, not on the stack, so this
+// needs more than a look at the stack. step_out always fails with NoFrame.
+pub const RETURN_ADDR_CODE_LEN: usize = 4;
+
+pub fn find_return_addr_slot(_code: &[u8; RETURN_ADDR_CODE_LEN], _sp: u64) -> Option<u64> {
+    None
+}
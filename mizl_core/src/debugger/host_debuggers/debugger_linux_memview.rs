@@ -1,8 +1,9 @@
 use super::debugger_linux_superpt as superpt;
 use crate::memory::memview::{MemView, MemViewError};
 use libc::c_long;
-use smallvec::{smallvec, SmallVec};
+use smallvec::SmallVec;
 use std::{
+    collections::HashMap,
     fs::File,
     io::{ErrorKind, Read, Seek, SeekFrom, Write},
     mem,
@@ -10,10 +11,46 @@ use std::{
 };
 
 const WRDSZ: usize = mem::size_of::<usize>();
+pub const MEM_CACHE_PAGE_SIZE: usize = 0x1000;
+
+// whole pages read from the process, keyed by page address. every thread sees
+// the same address space, so one cache is shared by all of their views. it's
+// only right while the process is stopped and has to be invalidated whenever
+// it runs again.
+#[derive(Clone, Default)]
+pub struct DebuggerLinuxMemCache {
+    pages: Arc<Mutex<HashMap<u64, Box<[u8; MEM_CACHE_PAGE_SIZE]>>>>,
+}
+
+impl DebuggerLinuxMemCache {
+    pub fn new() -> Self {
+        DebuggerLinuxMemCache {
+            pages: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn invalidate(&self) {
+        self.pages.lock().unwrap().clear();
+    }
+
+    fn invalidate_range(&self, addr: u64, len: u64) {
+        let page_mask = !(MEM_CACHE_PAGE_SIZE as u64 - 1);
+        let first_page = addr & page_mask;
+        let last_page = addr.saturating_add(len.max(1) - 1) & page_mask;
+        self.pages
+            .lock()
+            .unwrap()
+            .retain(|page_addr, _| *page_addr < first_page || *page_addr > last_page);
+    }
+}
 
 pub struct DebuggerLinuxMemView {
     pid: i32,
     proc_mem: Option<Arc<Mutex<File>>>,
+    cache: Option<DebuggerLinuxMemCache>,
+    // read straight from the process even if there's a cache, for views of a
+    // process that is still running
+    bypass_cache: bool,
 }
 
 impl DebuggerLinuxMemView {
@@ -27,29 +64,83 @@ impl DebuggerLinuxMemView {
             Err(_) => None, // fallback to PEEKDATA
         };
 
-        DebuggerLinuxMemView { pid, proc_mem }
+        DebuggerLinuxMemView {
+            pid,
+            proc_mem,
+            cache: None,
+            bypass_cache: false,
+        }
+    }
+
+    // skips /proc/[pid]/mem and always goes through ptrace
+    pub fn new_ptrace_only(pid: i32) -> Self {
+        DebuggerLinuxMemView {
+            pid,
+            proc_mem: None,
+            cache: None,
+            bypass_cache: false,
+        }
     }
 
     pub fn is_using_proc_mem(&self) -> bool {
         self.proc_mem.is_some()
     }
 
-    // c_long should be the same size as usize (I think?)
-    fn from_bytes(bytes: &[u8; WRDSZ]) -> c_long {
-        c_long::from_ne_bytes(*bytes)
+    // reads are served from cache a page at a time when set. None turns it off.
+    pub fn set_cache(&mut self, cache: Option<DebuggerLinuxMemCache>) {
+        self.cache = cache;
     }
 
-    fn to_bytes(v: c_long) -> Vec<u8> {
-        v.to_ne_bytes().to_vec()
+    pub fn set_bypass_cache(&mut self, bypass: bool) {
+        self.bypass_cache = bypass;
     }
 
-    fn to_bytes_n(v: c_long, len: usize) -> Vec<u8> {
-        v.to_ne_bytes()[..len].to_vec()
+    // copies out of cached pages, reading in any page that isn't there yet.
+    // if a page can't be read whole (say the end of a mapping is in it), that
+    // part is read directly instead.
+    fn read_bytes_cached(
+        &self,
+        cache: &DebuggerLinuxMemCache,
+        addr: &mut u64,
+        out_data: &mut [u8],
+    ) -> Result<(), MemViewError> {
+        let page_size = MEM_CACHE_PAGE_SIZE as u64;
+        let mut pos = 0usize;
+        while pos < out_data.len() {
+            let cur_addr = *addr + pos as u64;
+            let page_addr = cur_addr & !(page_size - 1);
+            let page_off = (cur_addr - page_addr) as usize;
+            let chunk_len = (MEM_CACHE_PAGE_SIZE - page_off).min(out_data.len() - pos);
+            let out_chunk = &mut out_data[pos..pos + chunk_len];
+
+            let mut pages = cache.pages.lock().unwrap();
+            if !pages.contains_key(&page_addr) {
+                let mut page = Box::new([0u8; MEM_CACHE_PAGE_SIZE]);
+                let mut page_read_addr = page_addr;
+                if self
+                    .read_bytes_uncached(&mut page_read_addr, page.as_mut_slice(), MEM_CACHE_PAGE_SIZE as i32)
+                    .is_ok()
+                {
+                    pages.insert(page_addr, page);
+                }
+            }
+
+            match pages.get(&page_addr) {
+                Some(page) => out_chunk.copy_from_slice(&page[page_off..page_off + chunk_len]),
+                None => {
+                    std::mem::drop(pages); // unlock cache
+                    let mut chunk_addr = cur_addr;
+                    self.read_bytes_uncached(&mut chunk_addr, out_chunk, chunk_len as i32)?;
+                }
+            }
+            pos += chunk_len;
+        }
+
+        *addr += out_data.len() as u64;
+        Ok(())
     }
-}
 
-impl MemView for DebuggerLinuxMemView {
-    fn read_bytes(&self, addr: &mut u64, out_data: &mut [u8], count: i32) -> Result<(), MemViewError> {
+    pub fn read_bytes_uncached(&self, addr: &mut u64, out_data: &mut [u8], count: i32) -> Result<(), MemViewError> {
         if let Some(proc_mem_mtx) = &self.proc_mem {
             let mut file = proc_mem_mtx.lock().unwrap();
             match file.seek(SeekFrom::Start(*addr)) {
@@ -66,15 +157,17 @@ impl MemView for DebuggerLinuxMemView {
             let pid = self.pid;
 
             let mut bytes_left = count as usize;
-            let mut bytes: SmallVec<u8, 8> = smallvec![0; count as usize];
+            let mut word_addr = *addr;
+            let mut bytes: SmallVec<u8, 8> = SmallVec::with_capacity(count as usize);
             while bytes_left > 0 {
-                let v = match superpt::peekdata(pid, *addr) {
+                let v = match superpt::peekdata(pid, word_addr) {
                     Ok(v) => v,
                     Err(_) => return Err(MemViewError::ReadAccessDenied),
                 };
                 if bytes_left >= WRDSZ {
                     bytes.extend_from_slice(&Self::to_bytes(v));
                     bytes_left -= WRDSZ;
+                    word_addr += WRDSZ as u64;
                 } else if bytes_left < WRDSZ {
                     bytes.extend_from_slice(&Self::to_bytes_n(v, bytes_left));
                     break;
@@ -86,8 +179,33 @@ impl MemView for DebuggerLinuxMemView {
         }
     }
 
+    // c_long should be the same size as usize (I think?)
+    fn from_bytes(bytes: &[u8; WRDSZ]) -> c_long {
+        c_long::from_ne_bytes(*bytes)
+    }
+
+    fn to_bytes(v: c_long) -> Vec<u8> {
+        v.to_ne_bytes().to_vec()
+    }
+
+    fn to_bytes_n(v: c_long, len: usize) -> Vec<u8> {
+        v.to_ne_bytes()[..len].to_vec()
+    }
+}
+
+impl MemView for DebuggerLinuxMemView {
+    fn read_bytes(&self, addr: &mut u64, out_data: &mut [u8], count: i32) -> Result<(), MemViewError> {
+        match &self.cache {
+            Some(cache) if !self.bypass_cache => self.read_bytes_cached(cache, addr, &mut out_data[..count as usize]),
+            _ => self.read_bytes_uncached(addr, out_data, count),
+        }
+    }
+
     fn write_bytes(&mut self, addr: &mut u64, value: &[u8]) -> Result<(), MemViewError> {
         let count = value.len();
+        if let Some(cache) = &self.cache {
+            cache.invalidate_range(*addr, count as u64);
+        }
         if let Some(proc_mem_mtx) = &self.proc_mem {
             let mut file = proc_mem_mtx.lock().unwrap();
             match file.seek(SeekFrom::Start(*addr)) {
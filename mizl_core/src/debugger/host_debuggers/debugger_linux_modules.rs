@@ -0,0 +1,241 @@
+use crate::debugger::debugger::DebuggerError;
+
+// a file mapped into the process, like the main executable or a shared library.
+// base and size cover every segment mapped from the file, including any gaps
+// between them (usually there aren't any).
+pub struct LoadedModule {
+    pub path: String,
+    pub base: u64,
+    pub size: u64,
+}
+
+impl LoadedModule {
+    pub fn new(path: String, base: u64, size: u64) -> LoadedModule {
+        LoadedModule { path, base, size }
+    }
+}
+
+// the mapping an address falls in and what it's allowed to do
+pub struct AddressInfo {
+    pub start: u64,
+    pub end: u64,
+    pub readable: bool,
+    pub writable: bool,
+    pub executable: bool,
+    pub path: String, // empty for anonymous mappings, [heap] and such otherwise
+}
+
+// split off the next whitespace separated field, returning it and whatever is left
+fn next_field(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim_start();
+    if line.is_empty() {
+        return None;
+    }
+
+    match line.find(char::is_whitespace) {
+        Some(idx) => Some((&line[..idx], &line[idx..])),
+        None => Some((line, "")),
+    }
+}
+
+struct MapsEntry<'a> {
+    start: u64,
+    end: u64,
+    perms: &'a str,
+    inode: &'a str,
+    path: &'a str,
+}
+
+// one line of /proc/[pid]/maps looks like:
+// 7f0e1c000000-7f0e1c021000 r-xp 00000000 08:01 1234   /usr/lib/libc.so.6
+// the path can have spaces in it, so it's everything after the inode.
+fn parse_maps_entry(line: &str) -> Option<MapsEntry<'_>> {
+    let (range, rest) = next_field(line)?;
+    let (perms, rest) = next_field(rest)?;
+    let (_offset, rest) = next_field(rest)?;
+    let (_dev, rest) = next_field(rest)?;
+    let (inode, rest) = next_field(rest)?;
+
+    let (start_str, end_str) = range.split_once('-')?;
+    let start = u64::from_str_radix(start_str, 16).ok()?;
+    let end = u64::from_str_radix(end_str, 16).ok()?;
+    Some(MapsEntry {
+        start,
+        end,
+        perms,
+        inode,
+        path: rest.trim(),
+    })
+}
+
+// returns (start, end, path) for file backed mappings only
+fn parse_maps_line(line: &str) -> Option<(u64, u64, &str)> {
+    let entry = parse_maps_entry(line)?;
+
+    // anonymous mappings and things like [heap] and [vdso] have no inode
+    if entry.inode == "0" {
+        return None;
+    }
+
+    if !entry.path.starts_with('/') {
+        return None;
+    }
+
+    Some((entry.start, entry.end, entry.path))
+}
+
+fn find_address_info(maps_str: &str, addr: u64) -> Option<AddressInfo> {
+    for line in maps_str.lines() {
+        let entry = match parse_maps_entry(line) {
+            Some(v) => v,
+            None => continue,
+        };
+
+        if addr < entry.start || addr >= entry.end {
+            continue;
+        }
+
+        let perms = entry.perms.as_bytes();
+        return Some(AddressInfo {
+            start: entry.start,
+            end: entry.end,
+            readable: perms.first() == Some(&b'r'),
+            writable: perms.get(1) == Some(&b'w'),
+            executable: perms.get(2) == Some(&b'x'),
+            path: entry.path.to_string(),
+        });
+    }
+    None
+}
+
+// (start, end) of everything mapped, in address order. mappings that touch
+// are joined so a match can run from one into the next.
+fn parse_mapped_regions(maps_str: &str) -> Vec<(u64, u64)> {
+    let mut regions: Vec<(u64, u64)> = Vec::new();
+    for line in maps_str.lines() {
+        let entry = match parse_maps_entry(line) {
+            Some(v) => v,
+            None => continue,
+        };
+
+        match regions.last_mut() {
+            Some(last) if last.1 == entry.start => last.1 = entry.end,
+            _ => regions.push((entry.start, entry.end)),
+        }
+    }
+    regions
+}
+
+// coalesce every mapping of the same file into one module. modules are kept
+// in the order they first appear, which is address order.
+fn parse_maps(maps_str: &str) -> Vec<LoadedModule> {
+    let mut modules: Vec<LoadedModule> = Vec::new();
+    for line in maps_str.lines() {
+        let (start, end, path) = match parse_maps_line(line) {
+            Some(v) => v,
+            None => continue,
+        };
+
+        match modules.iter_mut().find(|m| m.path == path) {
+            Some(module) => {
+                let module_end = std::cmp::max(module.base + module.size, end);
+                module.base = std::cmp::min(module.base, start);
+                module.size = module_end - module.base;
+            }
+            None => modules.push(LoadedModule::new(path.to_string(), start, end - start)),
+        }
+    }
+    modules
+}
+
+// note: a file that was deleted after being mapped shows up with " (deleted)"
+// at the end of its path. we leave that in since it's the only hint the file
+// on disk isn't the one that's loaded anymore.
+pub fn read_loaded_modules(pid: i32) -> Result<Vec<LoadedModule>, DebuggerError> {
+    let maps_path = format!("/proc/{}/maps", pid);
+    let maps_str = std::fs::read_to_string(maps_path).or(Err(DebuggerError::InvalidThread))?;
+    Ok(parse_maps(&maps_str))
+}
+
+pub fn read_mapped_regions(pid: i32) -> Result<Vec<(u64, u64)>, DebuggerError> {
+    let maps_path = format!("/proc/{}/maps", pid);
+    let maps_str = std::fs::read_to_string(maps_path).or(Err(DebuggerError::InvalidThread))?;
+    Ok(parse_mapped_regions(&maps_str))
+}
+
+// None if nothing is mapped at addr
+pub fn query_address(pid: i32, addr: u64) -> Result<Option<AddressInfo>, DebuggerError> {
+    let maps_path = format!("/proc/{}/maps", pid);
+    let maps_str = std::fs::read_to_string(maps_path).or(Err(DebuggerError::InvalidThread))?;
+    Ok(find_address_info(&maps_str, addr))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coalesces_segments_by_path() {
+        let maps_str = "\
+555555554000-555555556000 r--p 00000000 08:01 100    /tmp/my app
+555555556000-555555557000 r-xp 00002000 08:01 100    /tmp/my app
+555555557000-555555578000 rw-p 00000000 00:00 0      [heap]
+7ffff7d80000-7ffff7da8000 r--p 00000000 08:01 200    /usr/lib/libc.so.6
+7ffff7da8000-7ffff7f3d000 r-xp 00028000 08:01 200    /usr/lib/libc.so.6
+7ffff7f3d000-7ffff7f40000 rw-p 00000000 00:00 0
+7ffff7fc1000-7ffff7fc3000 r-xp 00000000 00:00 0      [vdso]
+";
+        let modules = parse_maps(maps_str);
+        assert_eq!(modules.len(), 2);
+
+        assert_eq!(modules[0].path, "/tmp/my app");
+        assert_eq!(modules[0].base, 0x555555554000);
+        assert_eq!(modules[0].size, 0x3000);
+
+        assert_eq!(modules[1].path, "/usr/lib/libc.so.6");
+        assert_eq!(modules[1].base, 0x7ffff7d80000);
+        assert_eq!(modules[1].size, 0x1bd000);
+    }
+
+    #[test]
+    fn finds_address_permissions() {
+        let maps_str = "\
+555555556000-555555557000 r-xp 00002000 08:01 100    /tmp/my app
+555555557000-555555578000 rw-p 00000000 00:00 0      [heap]
+7ffff7f3d000-7ffff7f40000 rw-p 00000000 00:00 0
+";
+        let info = find_address_info(maps_str, 0x555555556abc).unwrap();
+        assert_eq!(info.start, 0x555555556000);
+        assert!(info.readable && !info.writable && info.executable);
+        assert_eq!(info.path, "/tmp/my app");
+
+        // end is exclusive
+        let info = find_address_info(maps_str, 0x555555557000).unwrap();
+        assert!(info.writable && !info.executable);
+        assert_eq!(info.path, "[heap]");
+
+        assert_eq!(find_address_info(maps_str, 0x7ffff7f3e000).unwrap().path, "");
+        assert!(find_address_info(maps_str, 0x1000).is_none());
+        assert!(find_address_info(maps_str, 0x7ffff7f40000).is_none());
+    }
+
+    #[test]
+    fn joins_touching_regions() {
+        let maps_str = "\
+555555554000-555555556000 r--p 00000000 08:01 100    /tmp/my app
+555555556000-555555557000 r-xp 00002000 08:01 100    /tmp/my app
+555555557000-555555578000 rw-p 00000000 00:00 0      [heap]
+7ffff7d80000-7ffff7da8000 r--p 00000000 08:01 200    /usr/lib/libc.so.6
+7ffff7fc1000-7ffff7fc3000 r-xp 00000000 00:00 0      [vdso]
+";
+        let regions = parse_mapped_regions(maps_str);
+        assert_eq!(
+            regions,
+            vec![
+                (0x555555554000, 0x555555578000),
+                (0x7ffff7d80000, 0x7ffff7da8000),
+                (0x7ffff7fc1000, 0x7ffff7fc3000),
+            ]
+        );
+    }
+}
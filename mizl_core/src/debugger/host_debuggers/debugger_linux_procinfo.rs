@@ -0,0 +1,62 @@
+use crate::debugger::debugger::DebuggerError;
+
+// note: these read what the kernel has for the process right now, which isn't
+// always what it was started with. a process can overwrite its argv strings in
+// place (to rename itself, for example) and /proc will show the new text. if it
+// moves argv somewhere else or calls setenv/putenv, /proc still shows the old
+// memory, so the result can be stale.
+
+// split a NUL-separated /proc file into its strings. the last string ends
+// with a NUL too, so there's no empty entry at the end.
+fn split_nul_separated(data: &[u8]) -> Vec<String> {
+    let data = data.strip_suffix(&[0u8]).unwrap_or(data);
+    if data.is_empty() {
+        return Vec::new();
+    }
+
+    data.split(|b| *b == 0)
+        .map(|s| String::from_utf8_lossy(s).into_owned())
+        .collect()
+}
+
+pub fn read_cmdline(pid: i32) -> Result<Vec<String>, DebuggerError> {
+    let cmdline_path = format!("/proc/{}/cmdline", pid);
+    let cmdline_data = std::fs::read(cmdline_path).or(Err(DebuggerError::InvalidThread))?;
+    Ok(split_nul_separated(&cmdline_data))
+}
+
+pub fn read_environ(pid: i32) -> Result<Vec<(String, String)>, DebuggerError> {
+    let environ_path = format!("/proc/{}/environ", pid);
+    let environ_data = std::fs::read(environ_path).or(Err(DebuggerError::InvalidThread))?;
+
+    let mut environ = Vec::new();
+    for entry in split_nul_separated(&environ_data) {
+        // entries without an = are unusual but legal, keep them with an empty value
+        match entry.split_once('=') {
+            Some((key, value)) => environ.push((key.to_string(), value.to_string())),
+            None => environ.push((entry, String::new())),
+        }
+    }
+    Ok(environ)
+}
+
+// the executable the process is running. unlike argv this always follows the
+// current image, so it's right after an exec.
+pub fn read_exe_path(pid: i32) -> Result<String, DebuggerError> {
+    let exe_path = format!("/proc/{}/exe", pid);
+    let target = std::fs::read_link(exe_path).or(Err(DebuggerError::InvalidThread))?;
+    Ok(target.to_string_lossy().into_owned())
+}
+
+// every thread in the process, sorted. threads can come and go at any time, so
+// this is only a snapshot.
+pub fn read_task_ids(pid: i32) -> Result<Vec<i32>, DebuggerError> {
+    let task_path = format!("/proc/{}/task", pid);
+    let entries = std::fs::read_dir(task_path).or(Err(DebuggerError::InvalidThread))?;
+
+    let mut task_ids: Vec<i32> = entries
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+        .collect();
+    task_ids.sort();
+    Ok(task_ids)
+}
@@ -1,33 +1,35 @@
-use arc_swap::ArcSwap;
+use std::sync::atomic::{AtomicI32, Ordering};
 use std::sync::{Arc, LazyLock, Mutex};
 
 // ah yes, the joy of multithreaded signal processing code.
 // in this file, we need to be able to - at any time - add
 // a new fd for a child but also be able to read the list
 // of fds at any time, including while we're writing to the
-// list which would prevent a RwLock from working. here, we
-// clone the entire list (we assume this list won't be very
-// large) and do an atomic pointer replace with ArcSwap.
+// list which would prevent a RwLock from working. and since
+// the handler can interrupt a thread that's in the middle of
+// malloc, it can't allocate or free anything either (not even
+// an Arc drop), or it deadlocks on the allocator's lock. so
+// the list is a fixed table of atomic fds, -1 meaning empty.
 
-static SIGCHLD_FDS: LazyLock<ArcSwap<Vec<i32>>> = LazyLock::new(|| ArcSwap::from_pointee(Vec::new()));
+const MAX_SIGCHLD_FDS: usize = 64;
+
+static SIGCHLD_FDS: [AtomicI32; MAX_SIGCHLD_FDS] = [const { AtomicI32::new(-1) }; MAX_SIGCHLD_FDS];
 static SIGCHLD_SETUP: LazyLock<Arc<Mutex<bool>>> = LazyLock::new(|| Arc::new(Mutex::new(false)));
 
 pub fn sigchld_register(fd: i32) -> bool {
-    let mut result = false;
-    // use rcu in case we register in two threads
-    // at the same time (but please don't do this)
-    SIGCHLD_FDS.rcu(|current| {
-        if current.contains(&fd) {
-            // why are we adding a pid we already added?
-            result = false;
-            Arc::clone(current)
-        } else {
-            let mut sigchld_fds_copy = current.to_vec();
-            sigchld_fds_copy.push(fd);
-            result = true;
-            Arc::new(sigchld_fds_copy)
-        }
-    });
+    if fd < 0 || SIGCHLD_FDS.iter().any(|v| v.load(Ordering::Acquire) == fd) {
+        // why are we adding a pid we already added?
+        return false;
+    }
+    // claim an empty slot with a cas in case we register in
+    // two threads at the same time (but please don't do this)
+    let result = SIGCHLD_FDS
+        .iter()
+        .any(|v| v.compare_exchange(-1, fd, Ordering::AcqRel, Ordering::Acquire).is_ok());
+    if !result {
+        // out of slots, this fd will never hear about SIGCHLD
+        return false;
+    }
     // we are guaranteed to have at least one item at this point.
     // let's setup the signal handler, but let's make sure only
     // one thread is doing that ;)
@@ -57,33 +59,45 @@ pub fn sigchld_register(fd: i32) -> bool {
 }
 
 pub fn sigchld_unregister(fd: i32) -> bool {
-    let mut result = false;
-    SIGCHLD_FDS.rcu(|current| {
-        if current.contains(&fd) {
-            result = true;
-            let new_list = current.iter().filter(|&&x| x != fd).copied().collect();
-            Arc::new(new_list)
-        } else {
-            // why are we removing a pid we haven't added?
-            result = false;
-            Arc::clone(current)
-        }
-    });
-    // todo: we don't check if we need to unregister the handler here
-    // this may be a little tricky since it's probably a bad idea to
-    // lock the mutex inside the rcu, but the rcu is the only place we
-    // can 100% know for sure whether or not the size is 0. so it's
-    // probably best for the user to clean up the SIGCHLD handler...
-    return result;
+    // why are we removing a pid we haven't added?
+    SIGCHLD_FDS
+        .iter()
+        .any(|v| v.compare_exchange(fd, -1, Ordering::AcqRel, Ordering::Acquire).is_ok())
+    // todo: we don't check if we need to unregister the handler here.
+    // it's probably best for the user to clean up the SIGCHLD handler...
 }
 
 extern "C" fn sigchld_handler(_sig: libc::c_int, _info: *mut libc::siginfo_t, _data: *mut libc::c_void) {
-    // I have no idea if this is thread safe (does it allocate?)
-    let sigchld_fds = SIGCHLD_FDS.load();
+    // only atomics and write(2) in here, both are async-signal-safe.
+    // write can clobber errno for whoever we interrupted, so put it back.
+    let saved_errno = unsafe { *libc::__errno_location() };
     let custom_data = [0x48646C6863676953u64; 1];
-    for &fd in sigchld_fds.iter() {
-        unsafe {
-            libc::write(fd, &custom_data as *const u64 as *const libc::c_void, 8);
+    for slot in SIGCHLD_FDS.iter() {
+        let fd = slot.load(Ordering::Acquire);
+        if fd >= 0 {
+            unsafe {
+                libc::write(fd, &custom_data as *const u64 as *const libc::c_void, 8);
+            }
         }
     }
+    unsafe {
+        *libc::__errno_location() = saved_errno;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fds_register_once() {
+        // never a real fd, so a SIGCHLD in the meantime just gets EBADF
+        let fd = 0x7fff_0000;
+        assert!(sigchld_register(fd));
+        assert!(!sigchld_register(fd));
+        assert!(SIGCHLD_FDS.iter().any(|v| v.load(Ordering::Acquire) == fd));
+        assert!(sigchld_unregister(fd));
+        assert!(!sigchld_unregister(fd));
+        assert!(!sigchld_register(-1));
+    }
 }
@@ -0,0 +1,97 @@
+use crate::debugger::debugger::DebuggerError;
+
+// signal masks as read from /proc/[pid]/status. bit n-1 is set for signal n.
+pub struct DebuggerLinuxSignalInfo {
+    pub blocked: u64,        // SigBlk
+    pub pending: u64,        // SigPnd (this thread only)
+    pub shared_pending: u64, // ShdPnd (whole thread group)
+}
+
+impl DebuggerLinuxSignalInfo {
+    pub fn new(blocked: u64, pending: u64, shared_pending: u64) -> DebuggerLinuxSignalInfo {
+        DebuggerLinuxSignalInfo {
+            blocked,
+            pending,
+            shared_pending,
+        }
+    }
+}
+
+pub fn signal_name(signal: u32) -> &'static str {
+    match signal {
+        1 => "SIGHUP",
+        2 => "SIGINT",
+        3 => "SIGQUIT",
+        4 => "SIGILL",
+        5 => "SIGTRAP",
+        6 => "SIGABRT",
+        7 => "SIGBUS",
+        8 => "SIGFPE",
+        9 => "SIGKILL",
+        10 => "SIGUSR1",
+        11 => "SIGSEGV",
+        12 => "SIGUSR2",
+        13 => "SIGPIPE",
+        14 => "SIGALRM",
+        15 => "SIGTERM",
+        16 => "SIGSTKFLT",
+        17 => "SIGCHLD",
+        18 => "SIGCONT",
+        19 => "SIGSTOP",
+        20 => "SIGTSTP",
+        21 => "SIGTTIN",
+        22 => "SIGTTOU",
+        23 => "SIGURG",
+        24 => "SIGXCPU",
+        25 => "SIGXFSZ",
+        26 => "SIGVTALRM",
+        27 => "SIGPROF",
+        28 => "SIGWINCH",
+        29 => "SIGIO",
+        30 => "SIGPWR",
+        31 => "SIGSYS",
+        34..=64 => "SIGRT",
+        _ => "UNKNOWN",
+    }
+}
+
+// list the names of every signal set in a mask
+pub fn signal_set_names(mask: u64) -> Vec<&'static str> {
+    let mut names: Vec<&'static str> = Vec::new();
+    for bit in 0..64 {
+        if (mask & (1u64 << bit)) != 0 {
+            names.push(signal_name(bit + 1));
+        }
+    }
+    names
+}
+
+pub fn read_signal_info(pid: i32) -> Result<DebuggerLinuxSignalInfo, DebuggerError> {
+    let status_path = format!("/proc/{}/status", pid);
+    let status_str = std::fs::read_to_string(status_path).or(Err(DebuggerError::InvalidThread))?;
+
+    let mut blocked: Option<u64> = None;
+    let mut pending: Option<u64> = None;
+    let mut shared_pending: Option<u64> = None;
+    for line in status_str.lines() {
+        let (key, value) = match line.split_once(':') {
+            Some(v) => v,
+            None => continue,
+        };
+
+        let target = match key {
+            "SigBlk" => &mut blocked,
+            "SigPnd" => &mut pending,
+            "ShdPnd" => &mut shared_pending,
+            _ => continue,
+        };
+        *target = Some(u64::from_str_radix(value.trim(), 16).or(Err(DebuggerError::InternalError))?);
+    }
+
+    match (blocked, pending, shared_pending) {
+        (Some(blocked), Some(pending), Some(shared_pending)) => {
+            Ok(DebuggerLinuxSignalInfo::new(blocked, pending, shared_pending))
+        }
+        _ => Err(DebuggerError::InternalError),
+    }
+}
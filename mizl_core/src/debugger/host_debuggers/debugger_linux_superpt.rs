@@ -34,15 +34,46 @@ pub fn traceme() {
     }
 }
 
+// PTRACE_O_* flags, replaces whatever options were set before
+pub fn setoptions(pid: i32, options: i32) {
+    unsafe {
+        libc::ptrace(
+            libc::PTRACE_SETOPTIONS,
+            libc::pid_t::from(pid),
+            NULLPTR,
+            options as usize,
+        );
+    }
+}
+
+// the thread gets a SIGSTOP, which still has to be waited for
+pub fn attach(pid: i32) -> Result<(), ()> {
+    unsafe {
+        if libc::ptrace(libc::PTRACE_ATTACH, libc::pid_t::from(pid), NULLPTR, NULLPTR) == -1 {
+            return Err(());
+        }
+    }
+
+    return Ok(());
+}
+
+// sig is delivered to the thread as it's let go, 0 for no signal
+pub fn detach(pid: i32, sig: i32) {
+    unsafe {
+        libc::ptrace(libc::PTRACE_DETACH, libc::pid_t::from(pid), NULLPTR, sig as usize);
+    }
+}
+
 pub fn singlestep(pid: i32) {
     unsafe {
         libc::ptrace(libc::PTRACE_SINGLESTEP, libc::pid_t::from(pid), NULLPTR, NULLPTR);
     }
 }
 
-pub fn cont(pid: i32) {
+// sig is delivered to the thread on resume, 0 for no signal
+pub fn cont(pid: i32, sig: i32) {
     unsafe {
-        libc::ptrace(libc::PTRACE_CONT, libc::pid_t::from(pid), NULLPTR, NULLPTR);
+        libc::ptrace(libc::PTRACE_CONT, libc::pid_t::from(pid), NULLPTR, sig as usize);
     }
 }
 
@@ -87,6 +118,13 @@ pub fn setregs(pid: i32, buffer: &[u8; GETREGS_BYTESIZE]) {
     }
 }
 
+pub fn setfpregs(pid: i32, buffer: &[u8; GETFPREGS_BYTESIZE]) {
+    // safety: please assure GETFPREGS_BYTESIZE is correct for the system.
+    unsafe {
+        libc::ptrace(libc::PTRACE_SETFPREGS, libc::pid_t::from(pid), NULLPTR, buffer.as_ptr());
+    }
+}
+
 pub fn waitpid(pid: i32) -> (i32, i32) {
     let mut status = 0;
     let ret_pid: i32;
@@ -105,6 +143,23 @@ pub fn waitpid_nohang(pid: i32) -> (i32, i32) {
     return (status, ret_pid);
 }
 
+// waits on a specific thread, including non-leader threads
+pub fn waitpid_thread(pid: i32) -> (i32, i32) {
+    let mut status = 0;
+    let ret_pid: i32;
+    unsafe {
+        ret_pid = libc::waitpid(pid, &mut status, libc::__WALL);
+    }
+    return (status, ret_pid);
+}
+
+// sends a signal to one thread rather than the whole thread group
+pub fn tkill(pid: i32, sig: i32) {
+    unsafe {
+        libc::syscall(libc::SYS_tkill, pid as c_long, sig as c_long);
+    }
+}
+
 pub fn getsiginfo(pid: i32) -> libc::siginfo_t {
     let mut siginfo: libc::siginfo_t = unsafe { std::mem::zeroed() };
     unsafe {
@@ -116,6 +171,16 @@ pub fn getsiginfo(pid: i32) -> libc::siginfo_t {
     return siginfo;
 }
 
+// extra info about the last ptrace event stop, like the new thread's id for clone
+pub fn geteventmsg(pid: i32) -> u64 {
+    let mut msg: libc::c_ulong = 0;
+    unsafe {
+        _ = libc::ptrace(libc::PTRACE_GETEVENTMSG, libc::pid_t::from(pid), NULLPTR, &mut msg);
+    }
+
+    return msg as u64;
+}
+
 pub fn peekdata(pid: i32, addr: u64) -> Result<i64, ()> {
     let ret_word;
     unsafe {
@@ -143,3 +208,28 @@ pub fn pokedata(pid: i32, addr: u64, value: i64) -> Result<i64, ()> {
 
     return Ok(ret_word);
 }
+
+// offset is into struct user, which is where the debug registers live
+pub fn peekuser(pid: i32, offset: usize) -> Result<u64, ()> {
+    let ret_word;
+    unsafe {
+        let errno_loc = libc::__errno_location();
+        *errno_loc = 0;
+        ret_word = libc::ptrace(libc::PTRACE_PEEKUSER, libc::pid_t::from(pid), offset, NULLPTR);
+        if *errno_loc != 0 {
+            return Err(());
+        }
+    }
+
+    return Ok(ret_word as u64);
+}
+
+pub fn pokeuser(pid: i32, offset: usize, value: u64) -> Result<(), ()> {
+    unsafe {
+        if libc::ptrace(libc::PTRACE_POKEUSER, libc::pid_t::from(pid), offset, value) == -1 {
+            return Err(());
+        }
+    }
+
+    return Ok(());
+}
@@ -0,0 +1,727 @@
+use super::{
+    debugger::{
+        Debugger, DebuggerError, DebuggerEvent, DebuggerEventKind, DebuggerFlags, DebuggerThreadIndex,
+        DebuggerWatchpointKind,
+    },
+    registers::registers::{RegisterInfo, RegisterRole},
+};
+use crate::{
+    memory::memview::{MemView, StaticMemView},
+    sleigh::disasm::{Disasm, DisasmDispInstruction},
+};
+use std::{collections::VecDeque, sync::Mutex};
+
+// the thread that runs code. DebuggerThreadIndex::Specific has to use this,
+// except for resuming threads added with add_thread.
+pub const MOCK_PID: u32 = 1;
+pub const MOCK_WATCHPOINT_SLOT_COUNT: usize = 4;
+// how many instructions cont runs before giving up and reporting Interrupted
+const MOCK_MAX_CONT_STEPS: u32 = 0x10000;
+
+pub enum MockStepMode {
+    // every instruction is this many bytes long
+    Fixed(u64),
+    // decode the instruction at pc to find the next one
+    Decoded(Disasm),
+}
+
+struct MockDebuggerState {
+    mem: StaticMemView,
+    reg_values: Vec<Vec<u8>>,
+    flags: DebuggerFlags,
+    running: bool,
+    event_ids: Vec<u32>,
+    // breakpoint id, address
+    breakpoints: Vec<(u32, u64)>,
+    next_bp_id: u32,
+    watchpoints: [Option<(u64, u32, DebuggerWatchpointKind)>; MOCK_WATCHPOINT_SLOT_COUNT],
+    // delivered by the next step or cont instead of running anything
+    scripted_events: VecDeque<DebuggerEvent>,
+    pending_events: VecDeque<DebuggerEvent>,
+    // extra threads from add_thread
+    idle_threads: Vec<u32>,
+}
+
+// an in-memory debugger for testing code written against the Debugger trait
+// without ptrace. one thread runs code and memory starts at address 0. nothing
+// executes: stepping only moves pc forward, so register and memory effects have
+// to be set up by the test. execute watchpoints fire, data watchpoints never do.
+pub struct MockDebugger {
+    reg_infos: Vec<RegisterInfo>,
+    pc_reg_pos: usize,
+    step_mode: MockStepMode,
+    state: Mutex<MockDebuggerState>,
+}
+
+impl MockDebugger {
+    // registers start zeroed. one of them has to have the ProgramCounter role.
+    pub fn new(mem: Vec<u8>, reg_infos: Vec<RegisterInfo>, step_mode: MockStepMode) -> MockDebugger {
+        let pc_reg_pos = reg_infos
+            .iter()
+            .position(|r| matches!(r.role, RegisterRole::ProgramCounter))
+            .expect("mock debugger needs a pc register");
+
+        let reg_values = reg_infos.iter().map(|r| vec![0u8; Self::reg_size(r)]).collect();
+        MockDebugger {
+            reg_infos,
+            pc_reg_pos,
+            step_mode,
+            state: Mutex::new(MockDebuggerState {
+                mem: StaticMemView::new(mem),
+                reg_values,
+                flags: DebuggerFlags::empty(),
+                running: false,
+                event_ids: Vec::new(),
+                breakpoints: Vec::new(),
+                next_bp_id: 0,
+                watchpoints: [None; MOCK_WATCHPOINT_SLOT_COUNT],
+                scripted_events: VecDeque::new(),
+                pending_events: VecDeque::new(),
+                idle_threads: Vec::new(),
+            }),
+        }
+    }
+
+    fn reg_size(reg_info: &RegisterInfo) -> usize {
+        ((reg_info.bit_len + 7) / 8) as usize
+    }
+
+    // value is little endian and zero extended to the register's size
+    pub fn set_register(&self, name: &str, value: u64) -> Result<(), DebuggerError> {
+        let reg_pos = self
+            .reg_infos
+            .iter()
+            .position(|r| r.name == name)
+            .ok_or(DebuggerError::InvalidRegister)?;
+
+        let mut state = self.state.lock().unwrap();
+        let reg_value = &mut state.reg_values[reg_pos];
+        let bytes = value.to_le_bytes();
+        for (i, b) in reg_value.iter_mut().enumerate() {
+            *b = if i < bytes.len() { bytes[i] } else { 0 };
+        }
+        Ok(())
+    }
+
+    pub fn get_pc(&self) -> u64 {
+        let state = self.state.lock().unwrap();
+        Self::read_reg_u64(&state, self.pc_reg_pos)
+    }
+
+    // the next step or cont reports this event instead of moving pc, like a
+    // signal arriving before the instruction runs
+    pub fn queue_event(&self, event: DebuggerEvent) {
+        let mut state = self.state.lock().unwrap();
+        state.scripted_events.push_back(event);
+    }
+
+    // adds a thread that doesn't run anything. it can only be resumed, and it
+    // stops again straight away with an Interrupted event carrying its pid.
+    // this is enough to tell cont_all and cont_one apart.
+    pub fn add_thread(&self, pid: u32) {
+        let mut state = self.state.lock().unwrap();
+        state.idle_threads.push(pid);
+    }
+
+    fn idle_stop_event(pid: u32) -> DebuggerEvent {
+        DebuggerEvent::new_with_pid(DebuggerEventKind::Interrupted, 0, pid)
+    }
+
+    fn read_reg_u64(state: &MockDebuggerState, reg_pos: usize) -> u64 {
+        let mut bytes = [0u8; 8];
+        for (i, b) in state.reg_values[reg_pos].iter().take(8).enumerate() {
+            bytes[i] = *b;
+        }
+        u64::from_le_bytes(bytes)
+    }
+
+    fn write_reg_u64(state: &mut MockDebuggerState, reg_pos: usize, value: u64) {
+        let bytes = value.to_le_bytes();
+        for (i, b) in state.reg_values[reg_pos].iter_mut().take(8).enumerate() {
+            *b = bytes[i];
+        }
+    }
+
+    fn validate_thread(thread_idx: DebuggerThreadIndex) -> Result<(), DebuggerError> {
+        match thread_idx {
+            DebuggerThreadIndex::Current => Ok(()),
+            DebuggerThreadIndex::Specific(pid) if pid == MOCK_PID => Ok(()),
+            DebuggerThreadIndex::Specific(_) => Err(DebuggerError::InvalidThread),
+        }
+    }
+
+    // where the instruction after pc starts, or None if it can't be decoded
+    // (which a real process would hit as a fault)
+    fn next_pc(&self, state: &MockDebuggerState, pc: u64) -> Option<u64> {
+        let next_pc = match &self.step_mode {
+            MockStepMode::Fixed(len) => pc.checked_add(*len)?,
+            MockStepMode::Decoded(disasm) => disasm.next_instruction_addr(&state.mem, pc).ok()?,
+        };
+
+        match state.mem.max_address() {
+            Ok(max_addr) if next_pc < max_addr => Some(next_pc),
+            _ => None,
+        }
+    }
+
+    fn fault_event() -> DebuggerEvent {
+        DebuggerEvent::new_with_pid(DebuggerEventKind::MiscSignalReceived, libc::SIGSEGV as u32, MOCK_PID)
+    }
+
+    fn run_until_stop(&self, state: &mut MockDebuggerState) -> DebuggerEvent {
+        let mut pc = Self::read_reg_u64(state, self.pc_reg_pos);
+        for _ in 0..MOCK_MAX_CONT_STEPS {
+            pc = match self.next_pc(state, pc) {
+                Some(v) => v,
+                None => return Self::fault_event(),
+            };
+            Self::write_reg_u64(state, self.pc_reg_pos, pc);
+
+            if let Some((bp_id, bp_addr)) = state.breakpoints.iter().find(|bp| bp.1 == pc) {
+                let mut event = DebuggerEvent::new_with_pid(DebuggerEventKind::BreakpointHit, 0, MOCK_PID);
+                event.bp_idx = *bp_id;
+                event.bp_addr = *bp_addr;
+                return event;
+            }
+
+            let wp_slot = state.watchpoints.iter().position(|wp| match wp {
+                Some((addr, _, DebuggerWatchpointKind::Execute)) => *addr == pc,
+                _ => false,
+            });
+            if let Some(slot) = wp_slot {
+                let mut event = DebuggerEvent::new_with_pid(DebuggerEventKind::WatchpointHit, 0, MOCK_PID);
+                event.bp_idx = slot as u32;
+                event.bp_addr = pc;
+                return event;
+            }
+        }
+
+        DebuggerEvent::new_with_pid(DebuggerEventKind::Interrupted, 0, MOCK_PID)
+    }
+}
+
+impl Debugger for MockDebugger {
+    fn is_big_endian(&self) -> bool {
+        false
+    }
+
+    fn get_pointer_size(&self) -> u32 {
+        8
+    }
+
+    fn get_flags(&self) -> DebuggerFlags {
+        self.state.lock().unwrap().flags
+    }
+
+    fn set_flags(&self, flags: DebuggerFlags) -> Result<(), DebuggerError> {
+        self.state.lock().unwrap().flags = flags;
+        Ok(())
+    }
+
+    fn run(&self, _path: &str, _args: &[&str]) -> Result<i32, DebuggerError> {
+        let mut state = self.state.lock().unwrap();
+        if state.running {
+            return Err(DebuggerError::AlreadyRunning);
+        }
+
+        state.running = true;
+        Ok(MOCK_PID as i32)
+    }
+
+    fn wait_next_event(&self, _no_block: bool) -> Result<DebuggerEvent, DebuggerError> {
+        // nothing runs in the background, so blocking would never end
+        let mut state = self.state.lock().unwrap();
+        match state.pending_events.pop_front() {
+            Some(event) => Ok(event),
+            None => Ok(DebuggerEvent::new(DebuggerEventKind::NoEvent, 0)),
+        }
+    }
+
+    fn add_event_id(&self, id: u32) -> Result<(), DebuggerError> {
+        let mut state = self.state.lock().unwrap();
+        if state.event_ids.contains(&id) {
+            return Err(DebuggerError::InvalidArguments);
+        }
+
+        state.event_ids.push(id);
+        Ok(())
+    }
+
+    fn remove_event_id(&self, id: u32) -> Result<(), DebuggerError> {
+        let mut state = self.state.lock().unwrap();
+        let id_pos = match state.event_ids.iter().position(|v| *v == id) {
+            Some(v) => v,
+            None => return Err(DebuggerError::InvalidArguments),
+        };
+
+        state.event_ids.remove(id_pos);
+        Ok(())
+    }
+
+    fn interrupt(&self) -> Result<(), DebuggerError> {
+        let mut state = self.state.lock().unwrap();
+        let event = DebuggerEvent::new_with_pid(DebuggerEventKind::Interrupted, 0, MOCK_PID);
+        state.pending_events.push_back(event);
+        Ok(())
+    }
+
+    fn disassemble_one(&self, addr: u64) -> Result<DisasmDispInstruction, DebuggerError> {
+        let state = self.state.lock().unwrap();
+        match &self.step_mode {
+            MockStepMode::Decoded(disasm) => Ok(disasm.disasm_display(&state.mem, addr)?),
+            MockStepMode::Fixed(_) => Err(DebuggerError::DisassemblyFailed),
+        }
+    }
+
+    fn disassemble_range(&self, start: u64, count: i32) -> Result<Vec<DisasmDispInstruction>, DebuggerError> {
+        let count = usize::try_from(count).or(Err(DebuggerError::InvalidArguments))?;
+        let state = self.state.lock().unwrap();
+        match &self.step_mode {
+            MockStepMode::Decoded(disasm) => Ok(disasm.disasm_display_range(&state.mem, start, count)?),
+            MockStepMode::Fixed(_) => Err(DebuggerError::DisassemblyFailed),
+        }
+    }
+
+    fn get_register_infos(&self, _: DebuggerThreadIndex) -> Vec<&RegisterInfo> {
+        self.reg_infos.iter().collect()
+    }
+
+    fn read_register_by_idx_buf(
+        &self,
+        thread_idx: DebuggerThreadIndex,
+        reg_idx: i32,
+        out_data: &mut [u8],
+    ) -> Result<(), DebuggerError> {
+        Self::validate_thread(thread_idx)?;
+        let reg_pos = self
+            .reg_infos
+            .iter()
+            .position(|r| r.mizl_idx == reg_idx)
+            .ok_or(DebuggerError::InvalidRegister)?;
+
+        let state = self.state.lock().unwrap();
+        let reg_value = &state.reg_values[reg_pos];
+        if out_data.len() < reg_value.len() {
+            return Err(DebuggerError::InvalidRegister);
+        }
+
+        out_data[..reg_value.len()].copy_from_slice(reg_value);
+        Ok(())
+    }
+
+    fn read_register_by_name_buf(
+        &self,
+        thread_idx: DebuggerThreadIndex,
+        name: &str,
+        out_data: &mut [u8],
+    ) -> Result<(), DebuggerError> {
+        let reg_info = self
+            .reg_infos
+            .iter()
+            .find(|r| r.name == name)
+            .ok_or(DebuggerError::InvalidRegister)?;
+
+        self.read_register_by_idx_buf(thread_idx, reg_info.mizl_idx, out_data)
+    }
+
+    fn write_register_by_name_buf(
+        &self,
+        thread_idx: DebuggerThreadIndex,
+        name: &str,
+        data: &[u8],
+    ) -> Result<(), DebuggerError> {
+        Self::validate_thread(thread_idx)?;
+        let reg_pos = self
+            .reg_infos
+            .iter()
+            .position(|r| r.name == name)
+            .ok_or(DebuggerError::InvalidRegister)?;
+
+        let mut state = self.state.lock().unwrap();
+        let reg_value = &mut state.reg_values[reg_pos];
+        if data.len() < reg_value.len() {
+            return Err(DebuggerError::InvalidRegister);
+        }
+
+        let reg_len = reg_value.len();
+        reg_value.copy_from_slice(&data[..reg_len]);
+        Ok(())
+    }
+
+    // there's no cache, registers are always read from state
+    fn invalidate_register_cache(&self, thread_idx: DebuggerThreadIndex) -> Result<(), DebuggerError> {
+        Self::validate_thread(thread_idx)
+    }
+
+    fn flush_register_cache(&self, thread_idx: DebuggerThreadIndex) -> Result<(), DebuggerError> {
+        Self::validate_thread(thread_idx)
+    }
+
+    fn read_bytes(
+        &self,
+        thread_idx: DebuggerThreadIndex,
+        addr: u64,
+        out_data: &mut [u8],
+    ) -> Result<u64, DebuggerError> {
+        Self::validate_thread(thread_idx)?;
+        let state = self.state.lock().unwrap();
+        let mut addr = addr;
+        match state.mem.read_bytes(&mut addr, out_data, out_data.len() as i32) {
+            Ok(_) => Ok(addr),
+            Err(_) => Err(DebuggerError::MemoryAccessFailed),
+        }
+    }
+
+    fn write_bytes(&self, thread_idx: DebuggerThreadIndex, addr: u64, data: &[u8]) -> Result<u64, DebuggerError> {
+        Self::validate_thread(thread_idx)?;
+        let mut state = self.state.lock().unwrap();
+        let mut addr = addr;
+        match state.mem.write_bytes(&mut addr, data) {
+            Ok(_) => Ok(addr),
+            Err(_) => Err(DebuggerError::MemoryAccessFailed),
+        }
+    }
+
+    // breakpoints never touch memory here, so there's nothing to hide
+    fn scan_memory(
+        &self,
+        thread_idx: DebuggerThreadIndex,
+        start: u64,
+        end: u64,
+        needle: &[u8],
+        mask: Option<&[u8]>,
+    ) -> Result<Option<u64>, DebuggerError> {
+        Self::validate_thread(thread_idx)?;
+        let state = self.state.lock().unwrap();
+        Ok(state.mem.find(start, end, needle, mask))
+    }
+
+    fn add_breakpoint(&self, thread_idx: DebuggerThreadIndex, addr: u64) -> Result<u32, DebuggerError> {
+        Self::validate_thread(thread_idx)?;
+        let mut state = self.state.lock().unwrap();
+        let bp_id = state.next_bp_id;
+        state.next_bp_id += 1;
+        state.breakpoints.push((bp_id, addr));
+        Ok(bp_id)
+    }
+
+    fn remove_breakpoint(&self, thread_idx: DebuggerThreadIndex, bp_idx: u32) -> Result<(), DebuggerError> {
+        Self::validate_thread(thread_idx)?;
+        let mut state = self.state.lock().unwrap();
+        let bp_pos = match state.breakpoints.iter().position(|bp| bp.0 == bp_idx) {
+            Some(v) => v,
+            None => return Err(DebuggerError::InvalidBreakpoint),
+        };
+
+        state.breakpoints.remove(bp_pos);
+        Ok(())
+    }
+
+    fn add_watchpoint(
+        &self,
+        thread_idx: DebuggerThreadIndex,
+        addr: u64,
+        len: u32,
+        kind: DebuggerWatchpointKind,
+    ) -> Result<u32, DebuggerError> {
+        Self::validate_thread(thread_idx)?;
+        // same rules as the hardware ones so callers get the same errors
+        let len_ok = match kind {
+            DebuggerWatchpointKind::Execute => len == 1,
+            _ => matches!(len, 1 | 2 | 4 | 8),
+        };
+        if !len_ok || addr % len as u64 != 0 {
+            return Err(DebuggerError::InvalidArguments);
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let slot = match state.watchpoints.iter().position(|wp| wp.is_none()) {
+            Some(v) => v,
+            None => return Err(DebuggerError::NoFreeWatchpoints),
+        };
+
+        state.watchpoints[slot] = Some((addr, len, kind));
+        Ok(slot as u32)
+    }
+
+    fn remove_watchpoint(&self, thread_idx: DebuggerThreadIndex, slot: u32) -> Result<(), DebuggerError> {
+        Self::validate_thread(thread_idx)?;
+        let mut state = self.state.lock().unwrap();
+        match state.watchpoints.get_mut(slot as usize) {
+            Some(wp) if wp.is_some() => {
+                *wp = None;
+                Ok(())
+            }
+            _ => Err(DebuggerError::InvalidBreakpoint),
+        }
+    }
+
+    fn step(&self, thread_idx: DebuggerThreadIndex) -> Result<(), DebuggerError> {
+        Self::validate_thread(thread_idx)?;
+        let mut state_guard = self.state.lock().unwrap();
+        let state = &mut *state_guard;
+        if let Some(event) = state.scripted_events.pop_front() {
+            state.pending_events.push_back(event);
+            return Ok(());
+        }
+
+        let pc = Self::read_reg_u64(state, self.pc_reg_pos);
+        let event = match self.next_pc(state, pc) {
+            Some(next_pc) => {
+                Self::write_reg_u64(state, self.pc_reg_pos, next_pc);
+                DebuggerEvent::new_with_pid(DebuggerEventKind::StepComplete, 0, MOCK_PID)
+            }
+            None => Self::fault_event(),
+        };
+        state.pending_events.push_back(event);
+        Ok(())
+    }
+
+    // there are no frames here, so this acts like a ret: pc comes off the top
+    // of the stack. breakpoints in between are never hit.
+    fn step_out(&self, thread_idx: DebuggerThreadIndex) -> Result<(), DebuggerError> {
+        Self::validate_thread(thread_idx)?;
+        let sp_reg_pos = self
+            .reg_infos
+            .iter()
+            .position(|r| matches!(r.role, RegisterRole::StackPointer))
+            .ok_or(DebuggerError::NoFrame)?;
+
+        let mut state_guard = self.state.lock().unwrap();
+        let state = &mut *state_guard;
+        if let Some(event) = state.scripted_events.pop_front() {
+            state.pending_events.push_back(event);
+            return Ok(());
+        }
+
+        let sp = Self::read_reg_u64(state, sp_reg_pos);
+        let mut ret_bytes = [0u8; 8];
+        let mut mut_addr = sp;
+        state
+            .mem
+            .read_bytes(&mut mut_addr, &mut ret_bytes, 8)
+            .or(Err(DebuggerError::MemoryAccessFailed))?;
+
+        Self::write_reg_u64(state, sp_reg_pos, sp + 8);
+        Self::write_reg_u64(state, self.pc_reg_pos, u64::from_le_bytes(ret_bytes));
+        let event = DebuggerEvent::new_with_pid(DebuggerEventKind::StepComplete, 0, MOCK_PID);
+        state.pending_events.push_back(event);
+        Ok(())
+    }
+
+    fn cont_all(&self) -> Result<(), DebuggerError> {
+        let mut state_guard = self.state.lock().unwrap();
+        let state = &mut *state_guard;
+        let event = match state.scripted_events.pop_front() {
+            Some(event) => event,
+            None => self.run_until_stop(state),
+        };
+        state.pending_events.push_back(event);
+        for pid in &state.idle_threads {
+            state.pending_events.push_back(Self::idle_stop_event(*pid));
+        }
+        Ok(())
+    }
+
+    fn cont_one(&self, thread_idx: DebuggerThreadIndex) -> Result<(), DebuggerError> {
+        let mut state_guard = self.state.lock().unwrap();
+        let state = &mut *state_guard;
+        match thread_idx {
+            DebuggerThreadIndex::Specific(pid) if state.idle_threads.contains(&pid) => {
+                state.pending_events.push_back(Self::idle_stop_event(pid));
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        Self::validate_thread(thread_idx)?;
+        let event = match state.scripted_events.pop_front() {
+            Some(event) => event,
+            None => self.run_until_stop(state),
+        };
+        state.pending_events.push_back(event);
+        Ok(())
+    }
+}
+
+// rax, rsp and rip at their x86-64 register offsets, for tests that need a
+// debugger without a live process
+#[cfg(test)]
+pub(crate) fn mock_x86_64(mem: Vec<u8>, step_mode: MockStepMode) -> MockDebugger {
+    use super::registers::registers::RegisterKind;
+
+    let reg_infos = vec![
+        RegisterInfo::new(
+            "RAX".to_string(),
+            RegisterKind::GeneralPurpose,
+            RegisterRole::None,
+            0x0,
+            0,
+            -1,
+            64,
+        ),
+        RegisterInfo::new(
+            "RSP".to_string(),
+            RegisterKind::GeneralPurpose,
+            RegisterRole::StackPointer,
+            0x20,
+            1,
+            -1,
+            64,
+        ),
+        RegisterInfo::new(
+            "RIP".to_string(),
+            RegisterKind::Control,
+            RegisterRole::ProgramCounter,
+            0x288,
+            2,
+            -1,
+            64,
+        ),
+    ];
+    MockDebugger::new(mem, reg_infos, step_mode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::debugger::{debugger::DebuggerHelper, trace_log::TraceLog};
+
+    #[test]
+    fn steps_and_stops_at_breakpoints() {
+        let dbg = mock_x86_64(vec![0u8; 0x100], MockStepMode::Fixed(4));
+        dbg.set_register("RIP", 0x10).unwrap();
+        dbg.set_register("RSP", 0xf0).unwrap();
+
+        let event = dbg.step_n(DebuggerThreadIndex::Current, 3).unwrap();
+        assert_eq!(event.kind, DebuggerEventKind::StepComplete);
+        assert_eq!(dbg.get_pc(), 0x1c);
+        assert_eq!(
+            dbg.read_register_by_name::<u64>(DebuggerThreadIndex::Current, "RSP")
+                .unwrap(),
+            0xf0
+        );
+
+        let bp_id = dbg.add_breakpoint(DebuggerThreadIndex::Current, 0x40).unwrap();
+        dbg.cont_all().unwrap();
+        let event = dbg.wait_next_event(false).unwrap();
+        assert_eq!(event.kind, DebuggerEventKind::BreakpointHit);
+        assert_eq!(event.bp_idx, bp_id);
+        assert_eq!(
+            dbg.read_register_by_idx::<u64>(DebuggerThreadIndex::Specific(MOCK_PID), 2)
+                .unwrap(),
+            0x40
+        );
+
+        // without the breakpoint it runs off the end of memory
+        dbg.remove_breakpoint(DebuggerThreadIndex::Current, bp_id).unwrap();
+        dbg.cont_one(DebuggerThreadIndex::Current).unwrap();
+        let event = dbg.wait_next_event(false).unwrap();
+        assert_eq!(event.kind, DebuggerEventKind::MiscSignalReceived);
+        assert_eq!(dbg.wait_next_event(true).unwrap().kind, DebuggerEventKind::NoEvent);
+    }
+
+    #[test]
+    fn scripted_events_preempt_execution() {
+        let dbg = mock_x86_64(vec![0u8; 0x100], MockStepMode::Fixed(4));
+        assert_eq!(dbg.run("mock", &[]).unwrap(), MOCK_PID as i32);
+        assert!(dbg.run("mock", &[]).is_err());
+
+        let slot = dbg
+            .add_watchpoint(DebuggerThreadIndex::Current, 0x20, 1, DebuggerWatchpointKind::Execute)
+            .unwrap();
+        dbg.queue_event(DebuggerEvent::new_with_pid(DebuggerEventKind::ThreadSpawned, 0, 2));
+
+        dbg.step(DebuggerThreadIndex::Current).unwrap();
+        assert_eq!(
+            dbg.wait_next_event(false).unwrap().kind,
+            DebuggerEventKind::ThreadSpawned
+        );
+        assert_eq!(dbg.get_pc(), 0);
+
+        dbg.cont_all().unwrap();
+        let event = dbg.wait_next_event(false).unwrap();
+        assert_eq!(event.kind, DebuggerEventKind::WatchpointHit);
+        assert_eq!(event.bp_idx, slot);
+        dbg.remove_watchpoint(DebuggerThreadIndex::Current, slot).unwrap();
+
+        dbg.interrupt().unwrap();
+        assert_eq!(dbg.wait_next_event(false).unwrap().kind, DebuggerEventKind::Interrupted);
+        assert!(
+            dbg.read_register_by_idx::<u64>(DebuggerThreadIndex::Specific(5), 0)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn trace_log_records_pc_changes() {
+        let dbg = mock_x86_64(vec![0u8; 0x100], MockStepMode::Fixed(4));
+        dbg.write_bytes(DebuggerThreadIndex::Current, 0x80, &[1, 2, 3, 4])
+            .unwrap();
+        dbg.set_register("RAX", 7).unwrap();
+
+        let mut log = TraceLog::new(4);
+        log.watch_memory(0x80, 4);
+        log.step_n(&dbg, DebuggerThreadIndex::Current, 2).unwrap();
+        assert_eq!(log.len(), 2);
+
+        // only rip moves, nothing executes
+        let step = &log.get_steps()[1];
+        assert_eq!(step.registers.len(), 1);
+        assert_eq!(step.registers[0].reg_idx, 2);
+        assert_eq!(step.registers[0].new_value, 8u64.to_le_bytes());
+        assert!(step.memory.is_empty());
+
+        let mut buf = [0u8; 4];
+        dbg.read_bytes(DebuggerThreadIndex::Current, 0x80, &mut buf).unwrap();
+        assert_eq!(buf, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn decoded_steps_follow_instruction_lengths() {
+        use crate::sleigh::disasm::x86_64_disasm;
+
+        // push rbp; mov rbp, rsp; nop
+        let mut mem = vec![0x55, 0x48, 0x89, 0xe5, 0x90];
+        mem.resize(0x20, 0);
+        let dbg = mock_x86_64(mem, MockStepMode::Decoded(x86_64_disasm()));
+
+        dbg.step_n(DebuggerThreadIndex::Current, 2).unwrap();
+        assert_eq!(dbg.get_pc(), 4);
+        assert!(dbg.disassemble_one(4).is_ok());
+
+        // the zero padding decodes too, so only count limits this
+        let insts = dbg.disassemble_range(0, 3).unwrap();
+        assert_eq!(
+            insts.iter().map(|i| (i.addr, i.len)).collect::<Vec<_>>(),
+            [(0, 1), (1, 3), (4, 1)]
+        );
+        assert!(dbg.disassemble_range(0, 0).unwrap().is_empty());
+        assert_eq!(dbg.disassemble_range(0, -1), Err(DebuggerError::InvalidArguments));
+    }
+
+    #[test]
+    fn step_out_pops_return_address() {
+        let dbg = mock_x86_64(vec![0u8; 0x100], MockStepMode::Fixed(4));
+        dbg.write_bytes(DebuggerThreadIndex::Current, 0xe8, &0x34u64.to_le_bytes())
+            .unwrap();
+        dbg.set_register("RIP", 0x80).unwrap();
+        dbg.set_register("RSP", 0xe8).unwrap();
+
+        dbg.step_out(DebuggerThreadIndex::Current).unwrap();
+        assert_eq!(
+            dbg.wait_next_event(false).unwrap().kind,
+            DebuggerEventKind::StepComplete
+        );
+        assert_eq!(dbg.get_pc(), 0x34);
+        assert_eq!(
+            dbg.read_register_by_name::<u64>(DebuggerThreadIndex::Current, "RSP")
+                .unwrap(),
+            0xf0
+        );
+
+        // the stack is past the end of memory
+        dbg.set_register("RSP", 0xfc).unwrap();
+        assert!(dbg.step_out(DebuggerThreadIndex::Current).is_err());
+    }
+}
@@ -0,0 +1,233 @@
+use crate::{
+    binary_formats::elf::{
+        file::ElfFile,
+        symbols::{STT_FUNC, STT_OBJECT, read_symbols},
+    },
+    debugger::host_debuggers::debugger_linux_modules::LoadedModule,
+    memory::memview::MemViewError,
+    shared::demangle::demangle,
+};
+
+struct SymbolMapEntry {
+    addr: u64,
+    size: u64,
+    name: String,
+}
+
+// symbols of a single file, sorted by address. addresses are the file's own
+// virtual addresses, not where it ended up at runtime.
+#[derive(Default)]
+pub struct SymbolMap {
+    entries: Vec<SymbolMapEntry>,
+}
+
+impl SymbolMap {
+    pub fn new() -> SymbolMap {
+        SymbolMap { entries: Vec::new() }
+    }
+
+    pub fn from_elf(elf: &ElfFile) -> Result<SymbolMap, MemViewError> {
+        let mut symbol_map = SymbolMap::new();
+        for symbol in read_symbols(elf)? {
+            let symbol_type = symbol.symbol_type();
+            if (symbol_type != STT_FUNC && symbol_type != STT_OBJECT) || symbol.name.is_empty() {
+                continue;
+            }
+            symbol_map.add(symbol.value, symbol.size, demangle(&symbol.name));
+        }
+        Ok(symbol_map)
+    }
+
+    pub fn add(&mut self, addr: u64, size: u64, name: String) {
+        // .symtab and .dynsym usually share most of their symbols
+        let insert_idx = self.entries.partition_point(|e| e.addr < addr);
+        let duplicate = self.entries[insert_idx..]
+            .iter()
+            .take_while(|e| e.addr == addr)
+            .any(|e| e.name == name);
+        if !duplicate {
+            self.entries.insert(insert_idx, SymbolMapEntry { addr, size, name });
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // the closest symbol at or before addr and how far into it addr is. symbols
+    // with a known size only match addresses inside them.
+    pub fn lookup(&self, addr: u64) -> Option<(&str, u64)> {
+        let idx = self.entries.partition_point(|e| e.addr <= addr);
+        if idx == 0 {
+            return None;
+        }
+
+        let entry = &self.entries[idx - 1];
+        let offset = addr - entry.addr;
+        if entry.size != 0 && offset >= entry.size {
+            return None;
+        }
+        Some((&entry.name, offset))
+    }
+
+    // the address of the first symbol called name
+    pub fn find(&self, name: &str) -> Option<u64> {
+        self.entries.iter().find(|e| e.name == name).map(|e| e.addr)
+    }
+}
+
+struct ModuleSymbols {
+    module: LoadedModule,
+    load_base: u64,
+    symbol_map: SymbolMap,
+}
+
+pub struct ResolvedAddress {
+    pub module_path: String,
+    pub module_offset: u64,            // from the module's runtime base
+    pub symbol: Option<(String, u64)>, // name and offset into the symbol
+}
+
+impl ResolvedAddress {
+    // just the file name, since full paths make for very long backtraces
+    pub fn module_name(&self) -> &str {
+        match self.module_path.rsplit_once('/') {
+            Some((_, name)) => name,
+            None => &self.module_path,
+        }
+    }
+}
+
+impl std::fmt::Display for ResolvedAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.symbol {
+            Some((name, 0)) => write!(f, "{}!{}", self.module_name(), name),
+            Some((name, offset)) => write!(f, "{}!{}+{:#x}", self.module_name(), name, offset),
+            None => write!(f, "{}+{:#x}", self.module_name(), self.module_offset),
+        }
+    }
+}
+
+// turns runtime addresses into module!symbol+offset using the symbols of each
+// loaded module's file on disk. the modules should come from the same process
+// the addresses do, and need to be rebuilt when it loads or unloads libraries.
+#[derive(Default)]
+pub struct ModuleSymbolResolver {
+    modules: Vec<ModuleSymbols>, // sorted by base, never overlapping
+}
+
+impl ModuleSymbolResolver {
+    pub fn new() -> ModuleSymbolResolver {
+        ModuleSymbolResolver { modules: Vec::new() }
+    }
+
+    // a module that can't be read (deleted file, not an elf, etc.) is still added
+    // with no symbols so addresses in it resolve to module+offset.
+    pub fn from_loaded_modules(modules: Vec<LoadedModule>) -> ModuleSymbolResolver {
+        let mut resolver = ModuleSymbolResolver::new();
+        for module in modules {
+            let (load_base, symbol_map) = match Self::load_module_file(&module.path) {
+                Ok(v) => v,
+                Err(_) => (0, SymbolMap::new()),
+            };
+            resolver.add_module(module, load_base, symbol_map);
+        }
+        resolver
+    }
+
+    fn load_module_file(path: &str) -> Result<(u64, SymbolMap), MemViewError> {
+        let data = match std::fs::read(path) {
+            Ok(v) => v,
+            Err(_) => return Err(MemViewError::NotLoaded),
+        };
+
+        let elf = ElfFile::new(data)?;
+        let symbol_map = SymbolMap::from_elf(&elf)?;
+        Ok((elf.load_base(), symbol_map))
+    }
+
+    // load_base is the lowest vaddr the module's file maps, see ElfFile::load_base
+    pub fn add_module(&mut self, module: LoadedModule, load_base: u64, symbol_map: SymbolMap) {
+        let insert_idx = self.modules.partition_point(|m| m.module.base < module.base);
+        self.modules.insert(
+            insert_idx,
+            ModuleSymbols {
+                module,
+                load_base,
+                symbol_map,
+            },
+        );
+    }
+
+    pub fn resolve(&self, addr: u64) -> Option<ResolvedAddress> {
+        let idx = self.modules.partition_point(|m| m.module.base <= addr);
+        if idx == 0 {
+            return None;
+        }
+
+        let module_symbols = &self.modules[idx - 1];
+        let module = &module_symbols.module;
+        let module_offset = addr - module.base;
+        if module_offset >= module.size {
+            return None; // between modules
+        }
+
+        let file_addr = module_symbols.load_base + module_offset;
+        let symbol = module_symbols
+            .symbol_map
+            .lookup(file_addr)
+            .map(|(name, offset)| (name.to_string(), offset));
+
+        Some(ResolvedAddress {
+            module_path: module.path.clone(),
+            module_offset,
+            symbol,
+        })
+    }
+
+    // the runtime address of a symbol, from the first module that has it
+    pub fn find_symbol(&self, name: &str) -> Option<u64> {
+        self.modules.iter().find_map(|m| {
+            let file_addr = m.symbol_map.find(name)?;
+            let module_offset = file_addr.checked_sub(m.load_base)?;
+            m.module.base.checked_add(module_offset)
+        })
+    }
+
+    // resolve() as text, falling back to the plain address when it isn't in a module
+    pub fn format_address(&self, addr: u64) -> String {
+        match self.resolve(addr) {
+            Some(v) => v.to_string(),
+            None => format!("{:#x}", addr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_module_and_symbol() {
+        let mut symbol_map = SymbolMap::new();
+        symbol_map.add(0x1000, 0x40, "malloc".to_string());
+        symbol_map.add(0x1040, 0, "free".to_string());
+        symbol_map.add(0x1000, 0x40, "malloc".to_string());
+        assert_eq!(symbol_map.len(), 2);
+
+        let mut resolver = ModuleSymbolResolver::new();
+        let module = LoadedModule::new("/usr/lib/libc.so.6".to_string(), 0x7f0000000000, 0x2000);
+        resolver.add_module(module, 0, symbol_map);
+
+        assert_eq!(resolver.format_address(0x7f0000001010), "libc.so.6!malloc+0x10");
+        assert_eq!(resolver.format_address(0x7f0000001000), "libc.so.6!malloc");
+        assert_eq!(resolver.format_address(0x7f0000001100), "libc.so.6!free+0xc0");
+        assert_eq!(resolver.format_address(0x7f0000000800), "libc.so.6+0x800");
+        assert_eq!(resolver.format_address(0x7f0000002000), "0x7f0000002000");
+        assert_eq!(resolver.format_address(0x1234), "0x1234");
+    }
+}
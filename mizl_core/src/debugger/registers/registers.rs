@@ -1,21 +1,30 @@
+use crate::ffi::core_framework::prelude::*;
+use mizl_pm::FfiSerialize;
+
+#[derive(FromPrimitive, ToPrimitive, Copy, Clone, Debug, PartialEq)]
 pub enum RegisterKind {
-    GeneralPurpose,
-    FloatingPoint,
-    Control,
-    Flag,
+    GeneralPurpose = 0,
+    FloatingPoint = 1,
+    Control = 2,
+    Flag = 3,
 }
 
+#[derive(FromPrimitive, ToPrimitive, Copy, Clone, Debug, PartialEq)]
 pub enum RegisterRole {
-    None,
-    Flag,
-    ProgramCounter,
-    StackPointer,
-    BasePointer,
+    None = 0,
+    Flag = 1,
+    ProgramCounter = 2,
+    StackPointer = 3,
+    BasePointer = 4,
 }
 
+#[derive(FfiSerialize, Clone, Debug, PartialEq)]
+#[ffi_serialize(deserialize)]
 pub struct RegisterInfo {
     pub name: String,
+    #[ffi_serialize_enum]
     pub kind: RegisterKind,
+    #[ffi_serialize_enum]
     pub role: RegisterRole,
     // the address in sleigh register address space
     pub addr: u32,
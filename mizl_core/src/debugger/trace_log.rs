@@ -0,0 +1,271 @@
+use crate::debugger::debugger::{Debugger, DebuggerError, DebuggerEvent, DebuggerEventKind, DebuggerThreadIndex};
+use std::collections::{HashSet, VecDeque};
+
+pub struct TraceRegisterDelta {
+    pub reg_idx: i32, // mizl register index
+    pub old_value: Vec<u8>,
+    pub new_value: Vec<u8>,
+}
+
+// only the bytes that changed, from the first changed byte to the last one
+pub struct TraceMemoryDelta {
+    pub addr: u64,
+    pub old_value: Vec<u8>,
+    pub new_value: Vec<u8>,
+}
+
+pub struct TraceStep {
+    pub registers: Vec<TraceRegisterDelta>,
+    pub memory: Vec<TraceMemoryDelta>,
+}
+
+// the registers and watched memory of a thread at one point in time. the log
+// only stores differences, so this is what gets rewound to see older state.
+#[derive(Default)]
+pub struct TraceSnapshot {
+    pub registers: Vec<(i32, Vec<u8>)>,
+    pub memory: Vec<(u64, Vec<u8>)>, // empty when the range couldn't be read
+}
+
+impl TraceSnapshot {
+    pub fn new() -> TraceSnapshot {
+        TraceSnapshot {
+            registers: Vec::new(),
+            memory: Vec::new(),
+        }
+    }
+
+    // reads every host register once (sub-registers like eax share a host register
+    // with rax) and each watched range. the thread needs to be stopped.
+    pub fn capture<D: Debugger + ?Sized>(
+        dbg: &D,
+        thread_idx: DebuggerThreadIndex,
+        watched_ranges: &[(u64, usize)],
+    ) -> Result<TraceSnapshot, DebuggerError> {
+        let mut snapshot = TraceSnapshot::new();
+
+        let mut seen_idxs = HashSet::new();
+        for info in dbg.get_register_infos(thread_idx) {
+            if !seen_idxs.insert(info.mizl_idx) {
+                continue;
+            }
+
+            let mut value = vec![0u8; (info.bit_len as usize).div_ceil(8)];
+            match dbg.read_register_by_idx_buf(thread_idx, info.mizl_idx, &mut value) {
+                Ok(_) => snapshot.registers.push((info.mizl_idx, value)),
+                Err(DebuggerError::InvalidRegister) => continue, // no host register for this one
+                Err(e) => return Err(e),
+            }
+        }
+
+        for (addr, len) in watched_ranges {
+            let mut value = vec![0u8; *len];
+            if dbg.read_bytes(thread_idx, *addr, &mut value).is_err() {
+                value.clear();
+            }
+            snapshot.memory.push((*addr, value));
+        }
+
+        Ok(snapshot)
+    }
+
+    pub fn get_register(&self, reg_idx: i32) -> Option<&[u8]> {
+        self.registers.iter().find(|r| r.0 == reg_idx).map(|r| r.1.as_slice())
+    }
+
+    // addr has to be the start of a watched range
+    pub fn get_memory(&self, addr: u64) -> Option<&[u8]> {
+        self.memory.iter().find(|m| m.0 == addr).map(|m| m.1.as_slice())
+    }
+
+    fn write_memory(&mut self, addr: u64, data: &[u8]) {
+        for (range_addr, range_data) in &mut self.memory {
+            if addr < *range_addr {
+                continue;
+            }
+
+            let start = (addr - *range_addr) as usize;
+            if start + data.len() <= range_data.len() {
+                range_data[start..start + data.len()].copy_from_slice(data);
+                return;
+            }
+        }
+    }
+}
+
+// a bounded log of what each single step changed. full reverse execution isn't
+// possible with this, but walking it backwards from the current state gives the
+// registers and watched memory as they were any number of steps ago. once the
+// log is full the oldest steps are dropped.
+pub struct TraceLog {
+    max_steps: usize,
+    watched_ranges: Vec<(u64, usize)>,
+    steps: VecDeque<TraceStep>,
+}
+
+impl TraceLog {
+    pub fn new(max_steps: usize) -> TraceLog {
+        TraceLog {
+            max_steps,
+            watched_ranges: Vec::new(),
+            steps: VecDeque::new(),
+        }
+    }
+
+    // memory writes are only noticed inside watched ranges, there's no way to
+    // know what an instruction wrote without decoding it
+    pub fn watch_memory(&mut self, addr: u64, len: usize) {
+        self.watched_ranges.push((addr, len));
+    }
+
+    pub fn get_watched_ranges(&self) -> &[(u64, usize)] {
+        &self.watched_ranges
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    // oldest first
+    pub fn get_steps(&self) -> &VecDeque<TraceStep> {
+        &self.steps
+    }
+
+    pub fn clear(&mut self) {
+        self.steps.clear();
+    }
+
+    pub fn record(&mut self, before: &TraceSnapshot, after: &TraceSnapshot) {
+        if self.max_steps == 0 {
+            return;
+        }
+
+        let mut step = TraceStep {
+            registers: Vec::new(),
+            memory: Vec::new(),
+        };
+
+        for (reg_idx, new_value) in &after.registers {
+            if let Some(old_value) = before.get_register(*reg_idx) {
+                if old_value != new_value.as_slice() {
+                    step.registers.push(TraceRegisterDelta {
+                        reg_idx: *reg_idx,
+                        old_value: old_value.to_vec(),
+                        new_value: new_value.clone(),
+                    });
+                }
+            }
+        }
+
+        for (addr, new_value) in &after.memory {
+            let old_value = match before.get_memory(*addr) {
+                Some(v) if v.len() == new_value.len() => v,
+                _ => continue, // unreadable before or after
+            };
+
+            let first = match old_value.iter().zip(new_value).position(|(a, b)| a != b) {
+                Some(v) => v,
+                None => continue,
+            };
+            let last = old_value.iter().zip(new_value).rposition(|(a, b)| a != b).unwrap();
+            step.memory.push(TraceMemoryDelta {
+                addr: addr + first as u64,
+                old_value: old_value[first..=last].to_vec(),
+                new_value: new_value[first..=last].to_vec(),
+            });
+        }
+
+        if self.steps.len() >= self.max_steps {
+            self.steps.pop_front();
+        }
+        self.steps.push_back(step);
+    }
+
+    // undo the newest `count` steps on a snapshot of the current state. returns
+    // how many steps were actually undone, which is less than count if the log
+    // doesn't go back that far.
+    pub fn rewind(&self, snapshot: &mut TraceSnapshot, count: usize) -> usize {
+        let count = std::cmp::min(count, self.steps.len());
+        for step in self.steps.iter().rev().take(count) {
+            for delta in &step.registers {
+                if let Some(reg) = snapshot.registers.iter_mut().find(|r| r.0 == delta.reg_idx) {
+                    reg.1 = delta.old_value.clone();
+                }
+            }
+            for delta in &step.memory {
+                snapshot.write_memory(delta.addr, &delta.old_value);
+            }
+        }
+        count
+    }
+
+    // step_n, but recording each step. stops early on anything other than a
+    // step completion and returns that event without recording it.
+    pub fn step_n<D: Debugger + ?Sized>(
+        &mut self,
+        dbg: &D,
+        thread_idx: DebuggerThreadIndex,
+        count: u32,
+    ) -> Result<DebuggerEvent, DebuggerError> {
+        let mut event = DebuggerEvent::new(DebuggerEventKind::NoEvent, 0);
+        let mut before = TraceSnapshot::capture(dbg, thread_idx, &self.watched_ranges)?;
+        for _ in 0..count {
+            dbg.step(thread_idx)?;
+            event = dbg.wait_next_event(false)?;
+            match event.kind {
+                DebuggerEventKind::StepComplete | DebuggerEventKind::StepCompleteSyscall => {}
+                _ => return Ok(event),
+            }
+
+            let after = TraceSnapshot::capture(dbg, thread_idx, &self.watched_ranges)?;
+            self.record(&before, &after);
+            before = after;
+        }
+        Ok(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_snapshot(rax: u8, rcx: u8, mem: &[u8]) -> TraceSnapshot {
+        TraceSnapshot {
+            registers: vec![(0, vec![rax]), (1, vec![rcx])],
+            memory: vec![(0x1000, mem.to_vec())],
+        }
+    }
+
+    #[test]
+    fn rewinds_recorded_deltas() {
+        let mut log = TraceLog::new(2);
+        let s0 = make_snapshot(1, 1, &[0, 0, 0, 0]);
+        let s1 = make_snapshot(2, 1, &[0, 5, 6, 0]);
+        let s2 = make_snapshot(2, 3, &[0, 5, 6, 0]);
+        let s3 = make_snapshot(4, 3, &[7, 5, 6, 0]);
+        log.record(&s0, &s1);
+        log.record(&s1, &s2);
+        log.record(&s2, &s3);
+        assert_eq!(log.len(), 2);
+
+        let step = &log.get_steps()[0];
+        assert_eq!(step.registers.len(), 1);
+        assert_eq!(step.registers[0].reg_idx, 1);
+        assert!(step.memory.is_empty());
+
+        let mut state = make_snapshot(4, 3, &[7, 5, 6, 0]);
+        assert_eq!(log.rewind(&mut state, 1), 1);
+        assert_eq!(state.get_register(0), Some(&[2u8][..]));
+        assert_eq!(state.get_memory(0x1000), Some(&[0u8, 5, 6, 0][..]));
+
+        // the first step fell out of the log
+        let mut state = make_snapshot(4, 3, &[7, 5, 6, 0]);
+        assert_eq!(log.rewind(&mut state, 5), 2);
+        assert_eq!(state.get_register(0), Some(&[2u8][..]));
+        assert_eq!(state.get_register(1), Some(&[1u8][..]));
+    }
+}
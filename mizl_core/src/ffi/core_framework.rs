@@ -160,3 +160,382 @@ pub extern "C" fn pheap_free(ptrd: *mut u8) {
         std::alloc::dealloc(ptr, layout);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::prelude::*;
+
+    #[derive(FfiSerialize, Debug, PartialEq)]
+    #[ffi_serialize(deserialize)]
+    pub struct ChildAllPrimitive {
+        a: u64,
+        b: u32,
+    }
+
+    #[derive(FfiSerialize, Debug, PartialEq)]
+    #[ffi_serialize(deserialize)]
+    pub struct ChildWithString {
+        id: u32,
+        name: String,
+    }
+
+    #[derive(FfiSerialize, Debug, PartialEq)]
+    #[ffi_serialize(deserialize)]
+    pub struct Parent {
+        tag: u8,
+        dynamic_children: Vec<ChildWithString>,
+        constant_children: Vec<ChildAllPrimitive>,
+        values: Vec<u16>,
+    }
+
+    #[derive(FfiSerialize, Debug, PartialEq)]
+    #[ffi_serialize(deserialize)]
+    pub enum TaggedValue {
+        Empty,
+        Small(u16),
+        Named { id: u32, name: String },
+        Children(Vec<ChildAllPrimitive>),
+    }
+
+    #[derive(FfiSerialize, Debug, PartialEq)]
+    #[ffi_serialize(deserialize)]
+    pub struct Flags {
+        enabled: bool,
+        letter: char,
+        bits: Vec<bool>,
+    }
+
+    #[derive(FfiSerialize, Debug, PartialEq)]
+    #[ffi_serialize(deserialize)]
+    pub struct InlinePrimitives {
+        name: String,
+        tag: u8,
+        #[ffi_inline_vec]
+        values: Vec<u32>,
+    }
+
+    #[derive(FfiSerialize, Debug, PartialEq)]
+    #[ffi_serialize(deserialize)]
+    pub struct InlineChildren {
+        id: u16,
+        #[ffi_inline_vec]
+        children: Vec<ChildWithString>,
+    }
+
+    #[derive(FfiSerialize, Debug, PartialEq)]
+    #[ffi_serialize(deserialize)]
+    pub struct Nested {
+        names: Vec<String>,
+        rows: Vec<Vec<u32>>,
+        groups: Vec<Vec<ChildWithString>>,
+    }
+
+    #[derive(FfiSerialize, Debug, PartialEq)]
+    #[ffi_serialize(deserialize)]
+    pub struct Buffers {
+        bytes: Vec<u8>,
+        signed: Vec<i8>,
+        wide: Vec<i64>,
+        reals: Vec<f64>,
+    }
+
+    const SENTINEL: u8 = 0xa5;
+
+    // serialize into an oversized buffer and return how many bytes were written.
+    // also checks nothing was written past what calculate_full_size promised.
+    fn serialize_and_measure<T: FfiSerializeTrait>(obj: &T) -> (usize, usize) {
+        let full_size = T::Ffi::calculate_full_size(obj);
+
+        // u64 backing so the buffer starts 8-byte aligned
+        let mut buffer = vec![0u64; (full_size + 64) / 8 + 1];
+        let start = buffer.as_mut_ptr() as *mut u8;
+        let buffer_len = buffer.len() * 8;
+        unsafe { std::ptr::write_bytes(start, SENTINEL, buffer_len) };
+
+        let end = unsafe { T::Ffi::serialize(start, obj) };
+        let written = end as usize - start as usize;
+
+        let bytes = unsafe { std::slice::from_raw_parts(start, buffer_len) };
+        assert!(
+            bytes[full_size..].iter().all(|b| *b == SENTINEL),
+            "serialize wrote past calculate_full_size"
+        );
+
+        (written, full_size)
+    }
+
+    fn make_parent(child_count: usize) -> Parent {
+        Parent {
+            tag: 7,
+            dynamic_children: (0..child_count)
+                .map(|i| ChildWithString {
+                    id: i as u32,
+                    name: "x".repeat(i * 3 + 1),
+                })
+                .collect(),
+            constant_children: (0..child_count)
+                .map(|i| ChildAllPrimitive {
+                    a: i as u64,
+                    b: i as u32 * 2,
+                })
+                .collect(),
+            values: (0..child_count as u16).collect(),
+        }
+    }
+
+    #[test]
+    fn vec_sizes_match_serialize() {
+        for child_count in [0, 1, 2, 5, 16] {
+            let parent = make_parent(child_count);
+            let (written, full_size) = serialize_and_measure(&parent);
+            assert_eq!(written, full_size, "mismatch with {} children", child_count);
+        }
+    }
+
+    #[test]
+    fn constant_child_has_no_dynamic_size() {
+        assert!(!ChildAllPrimitiveFfi::has_dynamic_size());
+        assert!(ChildWithStringFfi::has_dynamic_size());
+
+        let child = ChildAllPrimitive { a: 1, b: 2 };
+        let (written, full_size) = serialize_and_measure(&child);
+        assert_eq!(written, full_size);
+        assert_eq!(full_size, ChildAllPrimitiveFfi::calculate_base_size());
+    }
+
+    #[test]
+    fn enum_is_tag_then_union() {
+        // the payload is aligned for the pointer in Named/Children
+        assert_eq!(TaggedValueFfi::calculate_alignment(), WORD_SA);
+        assert_eq!(
+            TaggedValueFfi::calculate_base_size(),
+            WORD_SZ + align_usize_fast_const::<WORD_SA>(I32_SZ + WORD_SZ)
+        );
+        assert!(TaggedValueFfi::has_dynamic_size());
+
+        let values = [
+            TaggedValue::Empty,
+            TaggedValue::Small(0x1234),
+            TaggedValue::Named {
+                id: 9,
+                name: "hello".to_string(),
+            },
+            TaggedValue::Children((0..3).map(|i| ChildAllPrimitive { a: i, b: i as u32 }).collect()),
+        ];
+        for (tag, value) in values.iter().enumerate() {
+            let (written, full_size) = serialize_and_measure(value);
+            assert_eq!(written, full_size, "mismatch for tag {}", tag);
+        }
+
+        let mut buffer = vec![0u64; 8];
+        let start = buffer.as_mut_ptr() as *mut u8;
+        unsafe {
+            TaggedValueFfi::serialize(start, &values[1]);
+            assert_eq!(*(start as *const i32), 1);
+            assert_eq!(*(start.add(WORD_SZ) as *const u16), 0x1234);
+
+            TaggedValueFfi::serialize(start, &values[2]);
+            assert_eq!(*(start as *const i32), 2);
+            assert_eq!(*(start.add(WORD_SZ) as *const u32), 9);
+            let name_ptr = *(start.add(WORD_SZ * 2) as *const *const u8);
+            assert_eq!(*(name_ptr.sub(I32_SZ) as *const u32), 5);
+            assert_eq!(std::slice::from_raw_parts(name_ptr, 6), b"hello\0");
+        }
+    }
+
+    #[test]
+    fn bool_and_char_layout() {
+        // bool at 0, char at 4, vec pointer after that
+        assert_eq!(FlagsFfi::calculate_alignment(), WORD_SA);
+        assert_eq!(
+            FlagsFfi::calculate_base_size(),
+            align_usize_fast_const::<WORD_SA>(I32_SZ * 2) + WORD_SZ
+        );
+
+        let flags = Flags {
+            enabled: true,
+            letter: '\u{1f600}',
+            bits: vec![true, false, true],
+        };
+        let (written, full_size) = serialize_and_measure(&flags);
+        assert_eq!(written, full_size);
+
+        let mut buffer = vec![0xffu64; 8];
+        let start = buffer.as_mut_ptr() as *mut u8;
+        unsafe {
+            FlagsFfi::serialize(start, &flags);
+            assert_eq!(*start, 1);
+            assert_eq!(*(start.add(I32_SZ) as *const u32), 0x1f600);
+            let bits_ptr = *(start.add(align_usize_fast_const::<WORD_SA>(I32_SZ * 2)) as *const *const u8);
+            assert_eq!(*(bits_ptr.sub(I32_SZ) as *const u32), 3);
+            assert_eq!(std::slice::from_raw_parts(bits_ptr, 3), &[1, 0, 1]);
+        }
+    }
+
+    #[test]
+    fn inline_vec_follows_base() {
+        // no pointer for the inline vec, so the base ends after tag
+        assert_eq!(InlinePrimitivesFfi::calculate_base_size(), WORD_SZ + I8_SZ);
+        assert_eq!(InlineChildrenFfi::calculate_base_size(), I16_SZ);
+
+        let obj = InlinePrimitives {
+            name: "abc".to_string(),
+            tag: 3,
+            values: vec![10, 20, 30],
+        };
+        let (written, full_size) = serialize_and_measure(&obj);
+        assert_eq!(written, full_size);
+
+        let mut buffer = vec![0u64; 16];
+        let start = buffer.as_mut_ptr() as *mut u8;
+        unsafe {
+            InlinePrimitivesFfi::serialize(start, &obj);
+            // length then data right after the base, before the string's data
+            let len_off = align_usize_fast_const::<I32_SA>(WORD_SZ + I8_SZ);
+            assert_eq!(*(start.add(len_off) as *const u32), 3);
+            let values = std::slice::from_raw_parts(start.add(len_off + I32_SZ) as *const u32, 3);
+            assert_eq!(values, &[10, 20, 30]);
+
+            let name_ptr = *(start as *const *const u8);
+            assert_eq!(name_ptr as usize, start.add(len_off + I32_SZ * 5) as usize);
+            assert_eq!(std::slice::from_raw_parts(name_ptr, 4), b"abc\0");
+        }
+
+        for child_count in [0, 1, 4] {
+            let obj = InlineChildren {
+                id: 1,
+                children: (0..child_count)
+                    .map(|i| ChildWithString {
+                        id: i,
+                        name: "y".repeat(i as usize + 2),
+                    })
+                    .collect(),
+            };
+            let (written, full_size) = serialize_and_measure(&obj);
+            assert_eq!(written, full_size, "mismatch with {} children", child_count);
+        }
+    }
+
+    // serializes into a real pheap and reads it back
+    fn round_trip<T: FfiSerializeTrait>(obj: &T, deserialize: unsafe fn(*const u8) -> T) -> T {
+        let ptr = pheap_alloc(obj, None);
+        let result = unsafe { deserialize(ptr) };
+        super::pheap_free(ptr);
+        result
+    }
+
+    #[test]
+    fn deserialize_round_trips() {
+        for child_count in [0, 1, 3] {
+            let parent = make_parent(child_count);
+            assert_eq!(round_trip(&parent, ParentFfi::deserialize), parent);
+        }
+
+        let values = [
+            TaggedValue::Empty,
+            TaggedValue::Small(7),
+            TaggedValue::Named {
+                id: 2,
+                name: "named".to_string(),
+            },
+            TaggedValue::Children(vec![ChildAllPrimitive { a: 5, b: 6 }]),
+        ];
+        for value in &values {
+            assert_eq!(&round_trip(value, TaggedValueFfi::deserialize), value);
+        }
+
+        let flags = Flags {
+            enabled: false,
+            letter: 'z',
+            bits: vec![false, true],
+        };
+        assert_eq!(round_trip(&flags, FlagsFfi::deserialize), flags);
+
+        let inline_prims = InlinePrimitives {
+            name: String::new(),
+            tag: 1,
+            values: vec![u32::MAX, 0],
+        };
+        assert_eq!(
+            round_trip(&inline_prims, InlinePrimitivesFfi::deserialize),
+            inline_prims
+        );
+
+        let inline_children = InlineChildren {
+            id: 4,
+            children: vec![ChildWithString {
+                id: 8,
+                name: "child".to_string(),
+            }],
+        };
+        assert_eq!(
+            round_trip(&inline_children, InlineChildrenFfi::deserialize),
+            inline_children
+        );
+    }
+
+    #[test]
+    fn primitive_vecs_are_copied_whole() {
+        let bufs = Buffers {
+            bytes: (0..=255).collect(),
+            signed: vec![-1, 0, 1],
+            wide: vec![i64::MIN, -2, i64::MAX],
+            reals: vec![],
+        };
+        let (written, full_size) = serialize_and_measure(&bufs);
+        assert_eq!(written, full_size);
+
+        let ptr = pheap_alloc(&bufs, None);
+        unsafe {
+            let bytes_ptr = *(ptr as *const *const u8);
+            assert_eq!(*(bytes_ptr.sub(I32_SZ) as *const u32), 256);
+            assert_eq!(std::slice::from_raw_parts(bytes_ptr, 256), &bufs.bytes[..]);
+
+            // 3 signed bytes before this, so the data had to be realigned
+            let wide_ptr = *(ptr.add(WORD_SZ * 2) as *const *const i64);
+            assert_eq!(wide_ptr as usize % I64_SA, 0);
+            assert_eq!(std::slice::from_raw_parts(wide_ptr, 3), &bufs.wide[..]);
+
+            assert_eq!(BuffersFfi::deserialize(ptr), bufs);
+        }
+        super::pheap_free(ptr);
+    }
+
+    #[test]
+    fn nested_vecs_are_pointer_arrays() {
+        let nested = Nested {
+            names: vec!["a".to_string(), String::new(), "longer name".to_string()],
+            rows: vec![vec![1, 2, 3], vec![], vec![4]],
+            groups: vec![
+                vec![],
+                vec![ChildWithString {
+                    id: 1,
+                    name: "one".to_string(),
+                }],
+            ],
+        };
+        assert_eq!(NestedFfi::calculate_base_size(), WORD_SZ * 3);
+
+        let (written, full_size) = serialize_and_measure(&nested);
+        assert_eq!(written, full_size);
+
+        let ptr = pheap_alloc(&nested, None);
+        unsafe {
+            // names: length then pointers to length prefixed strings
+            let names_ptr = *(ptr as *const *const *const u8);
+            assert_eq!(*(names_ptr as *const u8).sub(I32_SZ).cast::<u32>(), 3);
+            let name_2 = *names_ptr.add(2);
+            assert_eq!(*(name_2.sub(I32_SZ) as *const u32), 11);
+            assert_eq!(std::slice::from_raw_parts(name_2, 12), b"longer name\0");
+
+            // rows: same, but each points to a length prefixed u32 array
+            let rows_ptr = *(ptr.add(WORD_SZ) as *const *const *const u32);
+            let row_0 = *rows_ptr;
+            assert_eq!(*(row_0 as *const u8).sub(I32_SZ).cast::<u32>(), 3);
+            assert_eq!(std::slice::from_raw_parts(row_0, 3), &[1, 2, 3]);
+
+            assert_eq!(NestedFfi::deserialize(ptr), nested);
+        }
+        super::pheap_free(ptr);
+    }
+}
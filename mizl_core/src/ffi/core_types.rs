@@ -195,8 +195,9 @@ impl<T: FfiSerializeTrait> FfiVecElement for T {
     fn element_is_inlined() -> bool {
         false
     }
+    // only strings and vecs have one, structs start right at their pointer
     fn element_has_var_length_field() -> bool {
-        true
+        T::Ffi::has_var_length_field()
     }
     unsafe fn serialize(ptrd: *mut u8, obj: &Self) -> *mut u8 {
         unsafe { T::Ffi::serialize(ptrd, obj) }
@@ -1,3 +1,4 @@
 pub mod database;
 pub mod debugger;
+pub mod disasm;
 pub mod memview;
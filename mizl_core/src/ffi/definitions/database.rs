@@ -1,14 +1,8 @@
 use crate::{
-    database::{
-        gbf::GbfFile, gbf_record::GbfFieldValue, gbf_table_schema::GbfTableSchema, gbf_table_view::GbfTableView,
-        gbf_tables::GbfTableDef,
-    },
+    database::{gbf::GbfFile, gbf_table_schema::GbfTableSchema, gbf_table_view::GbfTableView, gbf_tables::GbfTableDef},
     ffi::{
-        core_framework::{
-            FfiSerializeTrait, FfiSerializer, I32_SA, I32_SZ, WORD_SA, WORD_SZ, align_ptr_fast, align_usize_fast_const,
-            pheap_alloc,
-        },
-        core_types::{OpaqueMFFI, StringFFI, VecFFI},
+        core_framework::pheap_alloc,
+        core_types::OpaqueMFFI,
         definitions::memview::{MemViewVTable, mem_view_error_cpret, mem_view_error_dret, mem_view_error_pret},
     },
     memory::memview::MemViewError,
@@ -102,14 +96,49 @@ pub extern "C" fn database_get_table_defs(obj: *const c_void, err: *mut *const u
     pheap_alloc(&table_def_ptrs, None)
 }
 
+// the view borrows the database (and the table def's schema inside it), so
+// it must be freed before the database is. nothing on the c side checks this.
+/// # Safety
+/// table_name must be null or point to a nul-terminated string.
 #[unsafe(no_mangle)]
-pub extern "C" fn database_get_table_view_by_name(
-    _obj: *const c_void,
-    _schema: *const c_void,
-    _table_name: *const c_char,
-    _err: *mut *const u8,
+pub unsafe extern "C" fn database_get_table_view_by_name(
+    obj: *const c_void,
+    table_name: *const c_char,
+    err: *mut *const u8,
 ) -> *mut u8 {
-    todo!()
+    if obj.is_null() || table_name.is_null() {
+        return mem_view_error_pret(err, Some(&MemViewError::InvalidParameter));
+    }
+
+    let table_name_str = match unsafe { CStr::from_ptr(table_name) }.to_str() {
+        Ok(v) => v,
+        Err(_) => return mem_view_error_pret(err, Some(&MemViewError::InvalidParameter)),
+    };
+
+    let gbf = unsafe { &*(OpaqueMFFI::get_data_ptr(obj as *mut u8) as *const GbfFile) };
+
+    let table_def = match gbf.tables.table_defs.get(table_name_str) {
+        Some(v) => v,
+        None => {
+            let msg = format!("no table named `{}`", table_name_str);
+            return mem_view_error_pret(err, Some(&MemViewError::generic_dynamic(msg)));
+        }
+    };
+
+    let database_view = match GbfTableView::new(gbf, &table_def.schema, table_def.root_nid) {
+        Ok(v) => v,
+        Err(e) => return mem_view_error_pret(err, Some(&e)),
+    };
+    let database_view_box = Box::new(database_view);
+    let database_view_box_ptr = Box::into_raw(database_view_box);
+
+    let database_view_ptr = OpaqueMFFI::serialize(
+        database_view_box_ptr as *const c_void,
+        None,
+        OpaqueMFFI::free_fn::<GbfTableView>,
+    );
+
+    database_view_ptr
 }
 
 // #-class GbfTableDef
@@ -197,135 +226,6 @@ pub extern "C" fn database_table_schema_get_names(obj: *const c_void, err: *mut
     pheap_alloc(&gbf_ts.names, None)
 }
 
-// #-class GbfFieldValue
-pub struct GbfFieldValueFfi;
-impl GbfFieldValueFfi {
-    pub const fn calculate_alignment() -> usize {
-        WORD_SA
-    }
-
-    pub const fn calculate_base_size() -> usize {
-        let mut size = 0usize;
-
-        size = align_usize_fast_const::<I32_SA>(size);
-        size += I32_SZ;
-
-        size = align_usize_fast_const::<WORD_SA>(size);
-        size += WORD_SZ;
-
-        size
-    }
-
-    pub fn calculate_full_size(obj: &GbfFieldValue) -> usize {
-        let mut size = 0usize;
-
-        size += Self::calculate_base_size();
-
-        size = align_usize_fast_const::<WORD_SA>(size);
-        match obj {
-            GbfFieldValue::Boolean(_) => {}
-            GbfFieldValue::Byte(_) => {}
-            GbfFieldValue::Short(_) => {}
-            GbfFieldValue::Int(_) => {}
-            GbfFieldValue::Long(_) => {}
-            GbfFieldValue::String(v) => {
-                size = align_usize_fast_const::<WORD_SA>(size + I32_SZ) - I32_SZ;
-                size += StringFFI::calculate_full_size(v)
-            }
-            GbfFieldValue::Bytes(v) => {
-                size = align_usize_fast_const::<WORD_SA>(size + I32_SZ) - I32_SZ;
-                size += VecFFI::calculate_full_size(v)
-            }
-        };
-
-        size
-    }
-
-    pub const fn has_dynamic_size() -> bool {
-        true
-    }
-
-    pub const fn has_var_length_field() -> bool {
-        false
-    }
-
-    pub unsafe fn serialize(mut ptrd: *mut u8, obj: &GbfFieldValue) -> *mut u8 {
-        unsafe {
-            ptrd = align_ptr_fast::<WORD_SA>(ptrd);
-            let tag = match obj {
-                GbfFieldValue::Byte(_) => 0,
-                GbfFieldValue::Short(_) => 1,
-                GbfFieldValue::Int(_) => 2,
-                GbfFieldValue::Long(_) => 3,
-                GbfFieldValue::String(_) => 4,
-                GbfFieldValue::Bytes(_) => 5,
-                GbfFieldValue::Boolean(_) => 6,
-            };
-            *(ptrd as *mut i32) = tag;
-
-            ptrd = align_ptr_fast::<WORD_SA>(ptrd.add(I32_SZ));
-            match obj {
-                GbfFieldValue::Boolean(v) => {
-                    *(ptrd as *mut bool) = *v;
-                    ptrd = ptrd.add(WORD_SZ);
-                }
-                GbfFieldValue::Byte(v) => {
-                    *(ptrd as *mut i8) = *v;
-                    ptrd = ptrd.add(WORD_SZ);
-                }
-                GbfFieldValue::Short(v) => {
-                    *(ptrd as *mut i16) = *v;
-                    ptrd = ptrd.add(WORD_SZ);
-                }
-                GbfFieldValue::Int(v) => {
-                    *(ptrd as *mut i32) = *v;
-                    ptrd = ptrd.add(WORD_SZ);
-                }
-                GbfFieldValue::Long(v) => {
-                    *(ptrd as *mut i64) = *v;
-                    ptrd = ptrd.add(WORD_SZ);
-                }
-                GbfFieldValue::String(v) => {
-                    let str_start = align_ptr_fast::<WORD_SA>(ptrd.add(WORD_SZ).add(I32_SZ));
-                    *(ptrd as *mut *mut u8) = str_start;
-                    ptrd = StringFFI::serialize(str_start.sub(I32_SZ), v);
-                }
-                GbfFieldValue::Bytes(v) => {
-                    let vec_start = align_ptr_fast::<WORD_SA>(ptrd.add(WORD_SZ).add(I32_SZ));
-                    *(ptrd as *mut *mut u8) = vec_start;
-                    ptrd = VecFFI::serialize(vec_start.sub(I32_SZ), v);
-                }
-            };
-
-            ptrd
-        }
-    }
-}
-impl FfiSerializer for GbfFieldValueFfi {
-    type Target = GbfFieldValue;
-    fn calculate_alignment() -> usize {
-        GbfFieldValueFfi::calculate_alignment()
-    }
-    fn calculate_base_size() -> usize {
-        GbfFieldValueFfi::calculate_base_size()
-    }
-    fn calculate_full_size(obj: &GbfFieldValue) -> usize {
-        GbfFieldValueFfi::calculate_full_size(obj)
-    }
-    fn has_dynamic_size() -> bool {
-        GbfFieldValueFfi::has_dynamic_size()
-    }
-    fn has_var_length_field() -> bool {
-        GbfFieldValueFfi::has_var_length_field()
-    }
-    unsafe fn serialize(ptrd: *mut u8, obj: &GbfFieldValue) -> *mut u8 {
-        unsafe { GbfFieldValueFfi::serialize(ptrd, obj) }
-    }
-}
-impl FfiSerializeTrait for GbfFieldValue {
-    type Ffi = GbfFieldValueFfi;
-}
-
 // #-class GbfTableView
 
 #[unsafe(no_mangle)]
@@ -416,3 +316,80 @@ pub extern "C" fn database_view_get_record_at_after_long(obj: *const c_void, key
 }
 
 // /////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::{
+            gbf_record::{GbfFieldValue, GbfRecordFfi},
+            gbf_table_view::{make_gbf, make_schema},
+        },
+        ffi::core_framework::pheap_free,
+    };
+
+    // the key and first value of a record pheep, which is freed
+    fn take_record(ptr: *mut u8) -> (i64, i32) {
+        assert!(!ptr.is_null());
+        let record = unsafe { GbfRecordFfi::deserialize(ptr) };
+        pheap_free(ptr);
+        match (&record.key, record.get_int(0)) {
+            (GbfFieldValue::Long(key), Ok(value)) => (*key, value),
+            _ => panic!("not a long key with an int value"),
+        }
+    }
+
+    #[test]
+    fn table_view_by_name() {
+        let mut gbf = make_gbf();
+        gbf.tables
+            .table_defs
+            .insert("Test".to_string(), GbfTableDef::new(make_schema(), 4));
+        let gbf_ptr = Box::into_raw(Box::new(gbf));
+        let obj = OpaqueMFFI::serialize(gbf_ptr as *const c_void, None, OpaqueMFFI::free_fn::<GbfFile>);
+
+        let mut err: *const u8 = std::ptr::null();
+        let view = unsafe { database_get_table_view_by_name(std::ptr::null(), c"Test".as_ptr(), &mut err) };
+        assert!(view.is_null());
+        assert!(!err.is_null());
+        pheap_free(err as *mut u8);
+
+        err = std::ptr::null();
+        let view = unsafe { database_get_table_view_by_name(obj as *const c_void, std::ptr::null(), &mut err) };
+        assert!(view.is_null());
+        assert!(!err.is_null());
+        pheap_free(err as *mut u8);
+
+        err = std::ptr::null();
+        let view = unsafe { database_get_table_view_by_name(obj as *const c_void, c"Nope".as_ptr(), &mut err) };
+        assert!(view.is_null());
+        assert!(!err.is_null());
+        pheap_free(err as *mut u8);
+
+        err = std::ptr::null();
+        let view = unsafe { database_get_table_view_by_name(obj as *const c_void, c"Test".as_ptr(), &mut err) };
+        assert!(!view.is_null());
+        assert!(err.is_null());
+
+        // walk the whole table the way a c caller would
+        let view_obj = view as *const c_void;
+        let mut records = vec![take_record(database_view_get_record_at_after_long(
+            view_obj,
+            i64::MIN,
+            &mut err,
+        ))];
+        loop {
+            let next = database_view_get_record_after_long(view_obj, records.last().unwrap().0, &mut err);
+            assert!(err.is_null());
+            if next.is_null() {
+                break;
+            }
+            records.push(take_record(next));
+        }
+        assert_eq!(records, [(10, 100), (20, 200), (30, 300), (40, 400)]);
+
+        // the view borrows the database, so it goes first
+        pheap_free(view);
+        pheap_free(obj);
+    }
+}
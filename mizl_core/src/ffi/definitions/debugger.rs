@@ -3,7 +3,8 @@ use crate::ffi::core_framework::prelude::*;
 use crate::{
     debugger::{
         debugger::{Debugger, DebuggerError},
-        host_debuggers::debugger_linux::DebuggerLinux,
+        host_debuggers::debugger_linux_builder::DebuggerBuilder,
+        registers::registers::RegisterInfo,
     },
     ffi::core_types::{ErrorFfi, OpaqueMFFI},
 };
@@ -59,9 +60,21 @@ pub fn debugger_error_pret(err: *mut *const u8, error_opt: Option<&DebuggerError
 #[repr(C)]
 pub struct DebuggerVTable {
     pub is_big_endian: extern "C" fn(*const c_void) -> i32,
+    pub get_pointer_size: extern "C" fn(*const c_void) -> u32,
     pub run: extern "C" fn(*const c_void, path: *const c_char, args: *const *const c_char, err: *mut *const u8) -> i32,
     pub wait_next_event: extern "C" fn(*const c_void, no_block: bool, err: *mut *const u8) -> *mut u8,
+    pub interrupt: extern "C" fn(*const c_void, err: *mut *const u8),
     pub disassemble_one: extern "C" fn(*const c_void, addr: u64, err: *mut *const u8) -> *mut u8,
+    pub disassemble_range: extern "C" fn(*const c_void, start: u64, count: i32, err: *mut *const u8) -> *mut u8,
+    pub get_register_infos: extern "C" fn(*const c_void, thread_idx: i32) -> *mut u8,
+    pub read_register_by_idx_buf: extern "C" fn(
+        *const c_void,
+        thread_idx: i32,
+        reg_idx: i32,
+        out_data: *mut c_uchar,
+        out_data_len: usize,
+        err: *mut *const u8,
+    ),
     pub read_register_by_name_buf: extern "C" fn(
         *const c_void,
         thread_idx: i32,
@@ -70,64 +83,120 @@ pub struct DebuggerVTable {
         out_data_len: usize,
         err: *mut *const u8,
     ),
+    pub write_register_by_name_buf: extern "C" fn(
+        *const c_void,
+        thread_idx: i32,
+        name: *const c_char,
+        data: *const c_uchar,
+        data_len: usize,
+        err: *mut *const u8,
+    ),
+    pub read_bytes: extern "C" fn(
+        *const c_void,
+        thread_idx: i32,
+        addr: u64,
+        out_data: *mut c_uchar,
+        out_data_len: usize,
+        err: *mut *const u8,
+    ) -> u64,
+    pub write_bytes: extern "C" fn(
+        *const c_void,
+        thread_idx: i32,
+        addr: u64,
+        data: *const c_uchar,
+        data_len: usize,
+        err: *mut *const u8,
+    ) -> u64,
+    pub scan_memory: extern "C" fn(
+        *const c_void,
+        thread_idx: i32,
+        start: u64,
+        end: u64,
+        needle: *const c_uchar,
+        mask: *const c_uchar,
+        needle_len: usize,
+        err: *mut *const u8,
+    ) -> u64,
+    pub invalidate_register_cache: extern "C" fn(*const c_void, thread_idx: i32, err: *mut *const u8),
+    pub flush_register_cache: extern "C" fn(*const c_void, thread_idx: i32, err: *mut *const u8),
     pub add_breakpoint: extern "C" fn(*const c_void, thread_idx: i32, addr: u64, err: *mut *const u8) -> u32,
     pub step: extern "C" fn(*const c_void, thread_idx: i32, err: *mut *const u8),
+    pub step_out: extern "C" fn(*const c_void, thread_idx: i32, err: *mut *const u8),
     pub cont_all: extern "C" fn(*const c_void, err: *mut *const u8),
+    pub cont_one: extern "C" fn(*const c_void, thread_idx: i32, err: *mut *const u8),
 }
 
-// #-class DebuggerLinux
+// #-class Debugger
+
+/// Backend-agnostic wrapper so any `Debugger` can sit behind the same vtable.
+/// The data pointer of the opaque object points to one of these.
+pub struct DebuggerFfiWrap {
+    pub obj: Box<dyn Debugger>,
+}
 
-static DEBUGGER_LINUX_VTABLE: DebuggerVTable = DebuggerVTable {
-    is_big_endian: debugger_linux_is_big_endian,
-    run: debugger_linux_run,
-    wait_next_event: debugger_linux_wait_next_event,
-    disassemble_one: debugger_linux_disassemble_one,
-    read_register_by_name_buf: debugger_linux_read_register_by_name_buf,
-    add_breakpoint: debugger_linux_add_breakpoint,
-    step: debugger_linux_step,
-    cont_all: debugger_linux_cont_all,
+static DEBUGGER_DYN_VTABLE: DebuggerVTable = DebuggerVTable {
+    is_big_endian: debugger_dyn_is_big_endian,
+    get_pointer_size: debugger_dyn_get_pointer_size,
+    run: debugger_dyn_run,
+    wait_next_event: debugger_dyn_wait_next_event,
+    interrupt: debugger_dyn_interrupt,
+    disassemble_one: debugger_dyn_disassemble_one,
+    disassemble_range: debugger_dyn_disassemble_range,
+    get_register_infos: debugger_dyn_get_register_infos,
+    read_register_by_idx_buf: debugger_dyn_read_register_by_idx_buf,
+    read_register_by_name_buf: debugger_dyn_read_register_by_name_buf,
+    write_register_by_name_buf: debugger_dyn_write_register_by_name_buf,
+    read_bytes: debugger_dyn_read_bytes,
+    write_bytes: debugger_dyn_write_bytes,
+    scan_memory: debugger_dyn_scan_memory,
+    invalidate_register_cache: debugger_dyn_invalidate_register_cache,
+    flush_register_cache: debugger_dyn_flush_register_cache,
+    add_breakpoint: debugger_dyn_add_breakpoint,
+    step: debugger_dyn_step,
+    step_out: debugger_dyn_step_out,
+    cont_all: debugger_dyn_cont_all,
+    cont_one: debugger_dyn_cont_one,
 };
 
-#[unsafe(no_mangle)]
-pub extern "C" fn debugger_linux_new() -> *mut u8 {
-    let debugger_lin = DebuggerLinux::new();
-    let debugger_lin_box = Box::new(debugger_lin);
-    let debugger_lin_box_ptr = Box::into_raw(debugger_lin_box);
-
-    let debugger_lin_ptr = OpaqueMFFI::serialize(
-        debugger_lin_box_ptr as *const c_void,
-        Some(&DEBUGGER_LINUX_VTABLE as *const DebuggerVTable as *const c_void),
-        debugger_linux_free,
+/// Wrap a boxed debugger backend into an opaque ffi object.
+pub fn debugger_dyn_new(dbg: Box<dyn Debugger>) -> *mut u8 {
+    let debugger_wrap = DebuggerFfiWrap { obj: dbg };
+    let debugger_wrap_box = Box::new(debugger_wrap);
+    let debugger_wrap_box_ptr = Box::into_raw(debugger_wrap_box);
+
+    let debugger_ptr = OpaqueMFFI::serialize(
+        debugger_wrap_box_ptr as *const c_void,
+        Some(&DEBUGGER_DYN_VTABLE as *const DebuggerVTable as *const c_void),
+        OpaqueMFFI::free_fn::<DebuggerFfiWrap>,
     );
 
-    debugger_lin_ptr
+    debugger_ptr
 }
 
-extern "C" fn debugger_linux_free(ffi_obj: *mut u8) {
-    let obj = OpaqueMFFI::get_data_ptr(ffi_obj);
-    unsafe {
-        _ = Box::from_raw(obj as *mut DebuggerLinux);
-    }
+fn debugger_dyn_from_ptr<'a>(obj: *const c_void) -> &'a dyn Debugger {
+    let wrap = unsafe { &*(obj as *const DebuggerFfiWrap) };
+    wrap.obj.as_ref()
 }
 
-extern "C" fn debugger_linux_is_big_endian(ptr: *const c_void) -> i32 {
-    let dbg = unsafe { &*(ptr as *const DebuggerLinux) };
+extern "C" fn debugger_dyn_is_big_endian(ptr: *const c_void) -> i32 {
+    let dbg = debugger_dyn_from_ptr(ptr);
     if dbg.is_big_endian() { 1 } else { 0 }
 }
 
-extern "C" fn debugger_linux_run(
-    obj: *const c_void,
-    path: *const c_char,
-    args: *const *const c_char,
-    err: *mut *const u8,
-) -> i32 {
-    let dbg = unsafe { &*(obj as *const DebuggerLinux) };
+extern "C" fn debugger_dyn_get_pointer_size(ptr: *const c_void) -> u32 {
+    let dbg = debugger_dyn_from_ptr(ptr);
+    dbg.get_pointer_size()
+}
 
+// args is a null terminated array of c strings. returns None if any isn't utf-8.
+// safety: args must be null or point to a null terminated array of valid c
+// strings, and the array and strings must outlive 'a. nothing ties 'a to args,
+// so the caller has to pick a lifetime that doesn't outlast them.
+unsafe fn debugger_args_from_ffi<'a>(args: *const *const c_char) -> Option<Vec<&'a str>> {
     let mut args_strs: Vec<&str> = Vec::new();
-    let path_str = match unsafe { CStr::from_ptr(path) }.to_str() {
-        Ok(v) => v,
-        Err(_) => return debugger_error_dret(err, Some(&DebuggerError::InvalidArguments)),
-    };
+    if args.is_null() {
+        return Some(args_strs);
+    }
 
     let mut args_ptr = args;
     loop {
@@ -136,24 +205,42 @@ extern "C" fn debugger_linux_run(
             break;
         }
 
-        let this_arg_str = match unsafe { CStr::from_ptr(path) }.to_str() {
-            Ok(v) => v,
-            Err(_) => return debugger_error_dret(err, Some(&DebuggerError::InvalidArguments)),
-        };
-        args_strs.push(this_arg_str);
+        args_strs.push(unsafe { CStr::from_ptr(this_arg) }.to_str().ok()?);
         unsafe {
             args_ptr = args_ptr.add(1);
         }
     }
 
+    Some(args_strs)
+}
+
+extern "C" fn debugger_dyn_run(
+    obj: *const c_void,
+    path: *const c_char,
+    args: *const *const c_char,
+    err: *mut *const u8,
+) -> i32 {
+    let dbg = debugger_dyn_from_ptr(obj);
+
+    let path_str = match unsafe { CStr::from_ptr(path) }.to_str() {
+        Ok(v) => v,
+        Err(_) => return debugger_error_dret(err, Some(&DebuggerError::InvalidArguments)),
+    };
+
+    // safety: the strings are only borrowed until run returns
+    let args_strs = match unsafe { debugger_args_from_ffi(args) } {
+        Some(v) => v,
+        None => return debugger_error_dret(err, Some(&DebuggerError::InvalidArguments)),
+    };
+
     match dbg.run(path_str, &args_strs) {
         Ok(pid) => pid,
         Err(e) => debugger_error_dret(err, Some(&e)),
     }
 }
 
-extern "C" fn debugger_linux_wait_next_event(obj: *const c_void, no_block: bool, err: *mut *const u8) -> *mut u8 {
-    let dbg = unsafe { &*(obj as *const DebuggerLinux) };
+extern "C" fn debugger_dyn_wait_next_event(obj: *const c_void, no_block: bool, err: *mut *const u8) -> *mut u8 {
+    let dbg = debugger_dyn_from_ptr(obj);
 
     let result = dbg.wait_next_event(no_block);
     match result {
@@ -162,8 +249,18 @@ extern "C" fn debugger_linux_wait_next_event(obj: *const c_void, no_block: bool,
     }
 }
 
-extern "C" fn debugger_linux_disassemble_one(obj: *const c_void, addr: u64, err: *mut *const u8) -> *mut u8 {
-    let dbg = unsafe { &*(obj as *const DebuggerLinux) };
+extern "C" fn debugger_dyn_interrupt(obj: *const c_void, err: *mut *const u8) {
+    let dbg = debugger_dyn_from_ptr(obj);
+
+    let result = dbg.interrupt();
+    match result {
+        Ok(_) => {}
+        Err(e) => debugger_error_ret(err, Some(&e)),
+    }
+}
+
+extern "C" fn debugger_dyn_disassemble_one(obj: *const c_void, addr: u64, err: *mut *const u8) -> *mut u8 {
+    let dbg = debugger_dyn_from_ptr(obj);
 
     let result = dbg.disassemble_one(addr);
     match result {
@@ -172,7 +269,52 @@ extern "C" fn debugger_linux_disassemble_one(obj: *const c_void, addr: u64, err:
     }
 }
 
-extern "C" fn debugger_linux_read_register_by_name_buf(
+extern "C" fn debugger_dyn_disassemble_range(
+    obj: *const c_void,
+    start: u64,
+    count: i32,
+    err: *mut *const u8,
+) -> *mut u8 {
+    let dbg = debugger_dyn_from_ptr(obj);
+
+    let result = dbg.disassemble_range(start, count);
+    match result {
+        Ok(dis_insts) => pheap_alloc(&dis_insts, None),
+        Err(e) => debugger_error_pret(err, Some(&e)),
+    }
+}
+
+extern "C" fn debugger_dyn_get_register_infos(obj: *const c_void, thread_idx: i32) -> *mut u8 {
+    let dbg = debugger_dyn_from_ptr(obj);
+
+    let thread_idx_enum = DebuggerThreadIndex::from_ffi(thread_idx);
+
+    let reg_infos: Vec<RegisterInfo> = dbg.get_register_infos(thread_idx_enum).into_iter().cloned().collect();
+    pheap_alloc(&reg_infos, None)
+}
+
+extern "C" fn debugger_dyn_read_register_by_idx_buf(
+    obj: *const c_void,
+    thread_idx: i32,
+    reg_idx: i32,
+    out_data: *mut c_uchar,
+    out_data_len: usize,
+    err: *mut *const u8,
+) {
+    let dbg = debugger_dyn_from_ptr(obj);
+
+    let thread_idx_enum = DebuggerThreadIndex::from_ffi(thread_idx);
+
+    let out_data_slice = unsafe { std::slice::from_raw_parts_mut(out_data, out_data_len) };
+
+    let result = dbg.read_register_by_idx_buf(thread_idx_enum, reg_idx, out_data_slice);
+    match result {
+        Ok(_) => {}
+        Err(e) => debugger_error_ret(err, Some(&e)),
+    }
+}
+
+extern "C" fn debugger_dyn_read_register_by_name_buf(
     obj: *const c_void,
     thread_idx: i32,
     name: *const c_char,
@@ -180,13 +322,9 @@ extern "C" fn debugger_linux_read_register_by_name_buf(
     out_data_len: usize,
     err: *mut *const u8,
 ) {
-    let dbg = unsafe { &*(obj as *const DebuggerLinux) };
+    let dbg = debugger_dyn_from_ptr(obj);
 
-    let thread_idx_enum = if thread_idx < 0 {
-        DebuggerThreadIndex::Current
-    } else {
-        DebuggerThreadIndex::Specific(thread_idx as u32)
-    };
+    let thread_idx_enum = DebuggerThreadIndex::from_ffi(thread_idx);
 
     let name = match unsafe { CStr::from_ptr(name) }.to_str() {
         Ok(v) => v,
@@ -202,20 +340,144 @@ extern "C" fn debugger_linux_read_register_by_name_buf(
     }
 }
 
-extern "C" fn debugger_linux_add_breakpoint(
+extern "C" fn debugger_dyn_write_register_by_name_buf(
+    obj: *const c_void,
+    thread_idx: i32,
+    name: *const c_char,
+    data: *const c_uchar,
+    data_len: usize,
+    err: *mut *const u8,
+) {
+    let dbg = debugger_dyn_from_ptr(obj);
+
+    let thread_idx_enum = DebuggerThreadIndex::from_ffi(thread_idx);
+
+    let name = match unsafe { CStr::from_ptr(name) }.to_str() {
+        Ok(v) => v,
+        Err(_) => return debugger_error_ret(err, Some(&DebuggerError::InvalidRegister)),
+    };
+
+    let data_slice = unsafe { std::slice::from_raw_parts(data, data_len) };
+
+    let result = dbg.write_register_by_name_buf(thread_idx_enum, name, data_slice);
+    match result {
+        Ok(_) => {}
+        Err(e) => debugger_error_ret(err, Some(&e)),
+    }
+}
+
+extern "C" fn debugger_dyn_read_bytes(
     obj: *const c_void,
     thread_idx: i32,
     addr: u64,
+    out_data: *mut c_uchar,
+    out_data_len: usize,
     err: *mut *const u8,
-) -> u32 {
-    let dbg = unsafe { &*(obj as *const DebuggerLinux) };
+) -> u64 {
+    let dbg = debugger_dyn_from_ptr(obj);
+
+    let thread_idx_enum = DebuggerThreadIndex::from_ffi(thread_idx);
 
-    let thread_idx_enum = if thread_idx < 0 {
-        DebuggerThreadIndex::Current
+    let out_data_slice = unsafe { std::slice::from_raw_parts_mut(out_data, out_data_len) };
+
+    let result = dbg.read_bytes(thread_idx_enum, addr, out_data_slice);
+    match result {
+        Ok(v) => v,
+        Err(e) => debugger_error_dret(err, Some(&e)),
+    }
+}
+
+extern "C" fn debugger_dyn_write_bytes(
+    obj: *const c_void,
+    thread_idx: i32,
+    addr: u64,
+    data: *const c_uchar,
+    data_len: usize,
+    err: *mut *const u8,
+) -> u64 {
+    let dbg = debugger_dyn_from_ptr(obj);
+
+    let thread_idx_enum = DebuggerThreadIndex::from_ffi(thread_idx);
+
+    let data_slice = unsafe { std::slice::from_raw_parts(data, data_len) };
+
+    let result = dbg.write_bytes(thread_idx_enum, addr, data_slice);
+    match result {
+        Ok(v) => v,
+        Err(e) => debugger_error_dret(err, Some(&e)),
+    }
+}
+
+// scan_memory's return value when nothing matched
+pub const DEBUGGER_SCAN_NOT_FOUND: u64 = u64::MAX;
+
+extern "C" fn debugger_dyn_scan_memory(
+    obj: *const c_void,
+    thread_idx: i32,
+    start: u64,
+    end: u64,
+    needle: *const c_uchar,
+    mask: *const c_uchar,
+    needle_len: usize,
+    err: *mut *const u8,
+) -> u64 {
+    let dbg = debugger_dyn_from_ptr(obj);
+
+    let thread_idx_enum = DebuggerThreadIndex::from_ffi(thread_idx);
+
+    if needle.is_null() || needle_len == 0 {
+        return debugger_error_dret(err, Some(&DebuggerError::InvalidArguments));
+    }
+
+    let needle_slice = unsafe { std::slice::from_raw_parts(needle, needle_len) };
+    // mask is optional and the same length as needle
+    let mask_slice = if mask.is_null() {
+        None
     } else {
-        DebuggerThreadIndex::Specific(thread_idx as u32)
+        Some(unsafe { std::slice::from_raw_parts(mask, needle_len) })
     };
 
+    let result = dbg.scan_memory(thread_idx_enum, start, end, needle_slice, mask_slice);
+    match result {
+        Ok(v) => v.unwrap_or(DEBUGGER_SCAN_NOT_FOUND),
+        Err(e) => debugger_error_dret(err, Some(&e)),
+    }
+}
+
+extern "C" fn debugger_dyn_invalidate_register_cache(obj: *const c_void, thread_idx: i32, err: *mut *const u8) {
+    let dbg = debugger_dyn_from_ptr(obj);
+
+    let thread_idx_enum = DebuggerThreadIndex::from_ffi(thread_idx);
+
+    let result = dbg.invalidate_register_cache(thread_idx_enum);
+    match result {
+        Ok(_) => {}
+        Err(e) => debugger_error_ret(err, Some(&e)),
+    }
+}
+
+extern "C" fn debugger_dyn_flush_register_cache(obj: *const c_void, thread_idx: i32, err: *mut *const u8) {
+    let dbg = debugger_dyn_from_ptr(obj);
+
+    let thread_idx_enum = DebuggerThreadIndex::from_ffi(thread_idx);
+
+    let result = dbg.flush_register_cache(thread_idx_enum);
+    match result {
+        Ok(_) => {}
+        Err(e) => debugger_error_ret(err, Some(&e)),
+    }
+}
+
+extern "C" fn debugger_dyn_add_breakpoint(
+    obj: *const c_void,
+    thread_idx: i32,
+    addr: u64,
+    err: *mut *const u8,
+) -> u32 {
+    let dbg = debugger_dyn_from_ptr(obj);
+
+    let thread_idx_enum = DebuggerThreadIndex::from_ffi(thread_idx);
+
     let result = dbg.add_breakpoint(thread_idx_enum, addr);
     match result {
         Ok(v) => v,
@@ -223,14 +485,10 @@ extern "C" fn debugger_linux_add_breakpoint(
     }
 }
 
-extern "C" fn debugger_linux_step(obj: *const c_void, thread_idx: i32, err: *mut *const u8) {
-    let dbg = unsafe { &*(obj as *const DebuggerLinux) };
+extern "C" fn debugger_dyn_step(obj: *const c_void, thread_idx: i32, err: *mut *const u8) {
+    let dbg = debugger_dyn_from_ptr(obj);
 
-    let thread_idx_enum = if thread_idx < 0 {
-        DebuggerThreadIndex::Current
-    } else {
-        DebuggerThreadIndex::Specific(thread_idx as u32)
-    };
+    let thread_idx_enum = DebuggerThreadIndex::from_ffi(thread_idx);
 
     let result = dbg.step(thread_idx_enum);
     match result {
@@ -239,8 +497,20 @@ extern "C" fn debugger_linux_step(obj: *const c_void, thread_idx: i32, err: *mut
     }
 }
 
-extern "C" fn debugger_linux_cont_all(obj: *const c_void, err: *mut *const u8) {
-    let dbg = unsafe { &*(obj as *const DebuggerLinux) };
+extern "C" fn debugger_dyn_step_out(obj: *const c_void, thread_idx: i32, err: *mut *const u8) {
+    let dbg = debugger_dyn_from_ptr(obj);
+
+    let thread_idx_enum = DebuggerThreadIndex::from_ffi(thread_idx);
+
+    let result = dbg.step_out(thread_idx_enum);
+    match result {
+        Ok(_) => {}
+        Err(e) => debugger_error_ret(err, Some(&e)),
+    }
+}
+
+extern "C" fn debugger_dyn_cont_all(obj: *const c_void, err: *mut *const u8) {
+    let dbg = debugger_dyn_from_ptr(obj);
 
     let result = dbg.cont_all();
     match result {
@@ -249,6 +519,29 @@ extern "C" fn debugger_linux_cont_all(obj: *const c_void, err: *mut *const u8) {
     }
 }
 
+extern "C" fn debugger_dyn_cont_one(obj: *const c_void, thread_idx: i32, err: *mut *const u8) {
+    let dbg = debugger_dyn_from_ptr(obj);
+
+    let thread_idx_enum = DebuggerThreadIndex::from_ffi(thread_idx);
+
+    let result = dbg.cont_one(thread_idx_enum);
+    match result {
+        Ok(_) => {}
+        Err(e) => debugger_error_ret(err, Some(&e)),
+    }
+}
+
+// #-class DebuggerLinux
+
+#[unsafe(no_mangle)]
+pub extern "C" fn debugger_linux_new() -> *mut u8 {
+    // null if the spec files couldn't be loaded
+    match DebuggerBuilder::new().build() {
+        Ok(debugger_lin) => debugger_dyn_new(Box::new(debugger_lin)),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
 // /////
 
 #[unsafe(no_mangle)]
@@ -258,6 +551,13 @@ pub extern "C" fn debugger_get_big_endian(ffi_obj: *mut u8) -> i32 {
     unsafe { ((*vtable).is_big_endian)(obj) }
 }
 
+#[unsafe(no_mangle)]
+pub extern "C" fn debugger_get_pointer_size(ffi_obj: *mut u8) -> u32 {
+    let obj = OpaqueMFFI::get_data_ptr(ffi_obj);
+    let vtable = OpaqueMFFI::get_vtable_ptr(ffi_obj) as *const DebuggerVTable;
+    unsafe { ((*vtable).get_pointer_size)(obj) }
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn debugger_run(
     ffi_obj: *mut u8,
@@ -277,6 +577,13 @@ pub extern "C" fn debugger_wait_next_event(ffi_obj: *mut u8, no_block: i32, err:
     unsafe { ((*vtable).wait_next_event)(obj, no_block != 0, err) }
 }
 
+#[unsafe(no_mangle)]
+pub extern "C" fn debugger_interrupt(ffi_obj: *mut u8, err: *mut *const u8) {
+    let obj = OpaqueMFFI::get_data_ptr(ffi_obj);
+    let vtable = OpaqueMFFI::get_vtable_ptr(ffi_obj) as *const DebuggerVTable;
+    unsafe { ((*vtable).interrupt)(obj, err) }
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn debugger_disassemble_one(ffi_obj: *mut u8, addr: u64, err: *mut *const u8) -> *mut u8 {
     let obj = OpaqueMFFI::get_data_ptr(ffi_obj);
@@ -284,6 +591,34 @@ pub extern "C" fn debugger_disassemble_one(ffi_obj: *mut u8, addr: u64, err: *mu
     unsafe { ((*vtable).disassemble_one)(obj, addr, err) }
 }
 
+#[unsafe(no_mangle)]
+pub extern "C" fn debugger_disassemble_range(ffi_obj: *mut u8, start: u64, count: i32, err: *mut *const u8) -> *mut u8 {
+    let obj = OpaqueMFFI::get_data_ptr(ffi_obj);
+    let vtable = OpaqueMFFI::get_vtable_ptr(ffi_obj) as *const DebuggerVTable;
+    unsafe { ((*vtable).disassemble_range)(obj, start, count, err) }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn debugger_get_register_infos(ffi_obj: *mut u8, thread_idx: i32) -> *mut u8 {
+    let obj = OpaqueMFFI::get_data_ptr(ffi_obj);
+    let vtable = OpaqueMFFI::get_vtable_ptr(ffi_obj) as *const DebuggerVTable;
+    unsafe { ((*vtable).get_register_infos)(obj, thread_idx) }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn debugger_read_register_by_idx_buf(
+    ffi_obj: *mut u8,
+    thread_idx: i32,
+    reg_idx: i32,
+    out_data: *mut c_uchar,
+    out_data_len: usize,
+    err: *mut *const u8,
+) {
+    let obj = OpaqueMFFI::get_data_ptr(ffi_obj);
+    let vtable = OpaqueMFFI::get_vtable_ptr(ffi_obj) as *const DebuggerVTable;
+    unsafe { ((*vtable).read_register_by_idx_buf)(obj, thread_idx, reg_idx, out_data, out_data_len, err) }
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn debugger_read_register_by_name_buf(
     ffi_obj: *mut u8,
@@ -298,6 +633,78 @@ pub extern "C" fn debugger_read_register_by_name_buf(
     unsafe { ((*vtable).read_register_by_name_buf)(obj, thread_idx, name, out_data, out_data_len, err) }
 }
 
+#[unsafe(no_mangle)]
+pub extern "C" fn debugger_write_register_by_name_buf(
+    ffi_obj: *mut u8,
+    thread_idx: i32,
+    name: *const c_char,
+    data: *const c_uchar,
+    data_len: usize,
+    err: *mut *const u8,
+) {
+    let obj = OpaqueMFFI::get_data_ptr(ffi_obj);
+    let vtable = OpaqueMFFI::get_vtable_ptr(ffi_obj) as *const DebuggerVTable;
+    unsafe { ((*vtable).write_register_by_name_buf)(obj, thread_idx, name, data, data_len, err) }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn debugger_read_bytes(
+    ffi_obj: *mut u8,
+    thread_idx: i32,
+    addr: u64,
+    out_data: *mut c_uchar,
+    out_data_len: usize,
+    err: *mut *const u8,
+) -> u64 {
+    let obj = OpaqueMFFI::get_data_ptr(ffi_obj);
+    let vtable = OpaqueMFFI::get_vtable_ptr(ffi_obj) as *const DebuggerVTable;
+    unsafe { ((*vtable).read_bytes)(obj, thread_idx, addr, out_data, out_data_len, err) }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn debugger_write_bytes(
+    ffi_obj: *mut u8,
+    thread_idx: i32,
+    addr: u64,
+    data: *const c_uchar,
+    data_len: usize,
+    err: *mut *const u8,
+) -> u64 {
+    let obj = OpaqueMFFI::get_data_ptr(ffi_obj);
+    let vtable = OpaqueMFFI::get_vtable_ptr(ffi_obj) as *const DebuggerVTable;
+    unsafe { ((*vtable).write_bytes)(obj, thread_idx, addr, data, data_len, err) }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn debugger_scan_memory(
+    ffi_obj: *mut u8,
+    thread_idx: i32,
+    start: u64,
+    end: u64,
+    needle: *const c_uchar,
+    mask: *const c_uchar,
+    needle_len: usize,
+    err: *mut *const u8,
+) -> u64 {
+    let obj = OpaqueMFFI::get_data_ptr(ffi_obj);
+    let vtable = OpaqueMFFI::get_vtable_ptr(ffi_obj) as *const DebuggerVTable;
+    unsafe { ((*vtable).scan_memory)(obj, thread_idx, start, end, needle, mask, needle_len, err) }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn debugger_invalidate_register_cache(ffi_obj: *mut u8, thread_idx: i32, err: *mut *const u8) {
+    let obj = OpaqueMFFI::get_data_ptr(ffi_obj);
+    let vtable = OpaqueMFFI::get_vtable_ptr(ffi_obj) as *const DebuggerVTable;
+    unsafe { ((*vtable).invalidate_register_cache)(obj, thread_idx, err) }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn debugger_flush_register_cache(ffi_obj: *mut u8, thread_idx: i32, err: *mut *const u8) {
+    let obj = OpaqueMFFI::get_data_ptr(ffi_obj);
+    let vtable = OpaqueMFFI::get_vtable_ptr(ffi_obj) as *const DebuggerVTable;
+    unsafe { ((*vtable).flush_register_cache)(obj, thread_idx, err) }
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn debugger_add_breakpoint(ffi_obj: *mut u8, thread_idx: i32, addr: u64, err: *mut *const u8) -> u32 {
     let obj = OpaqueMFFI::get_data_ptr(ffi_obj);
@@ -312,9 +719,303 @@ pub extern "C" fn debugger_step(ffi_obj: *mut u8, thread_idx: i32, err: *mut *co
     unsafe { ((*vtable).step)(obj, thread_idx, err) }
 }
 
+#[unsafe(no_mangle)]
+pub extern "C" fn debugger_step_out(ffi_obj: *mut u8, thread_idx: i32, err: *mut *const u8) {
+    let obj = OpaqueMFFI::get_data_ptr(ffi_obj);
+    let vtable = OpaqueMFFI::get_vtable_ptr(ffi_obj) as *const DebuggerVTable;
+    unsafe { ((*vtable).step_out)(obj, thread_idx, err) }
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn debugger_cont_all(ffi_obj: *mut u8, err: *mut *const u8) {
     let obj = OpaqueMFFI::get_data_ptr(ffi_obj);
     let vtable = OpaqueMFFI::get_vtable_ptr(ffi_obj) as *const DebuggerVTable;
     unsafe { ((*vtable).cont_all)(obj, err) }
 }
+
+#[unsafe(no_mangle)]
+pub extern "C" fn debugger_cont_one(ffi_obj: *mut u8, thread_idx: i32, err: *mut *const u8) {
+    let obj = OpaqueMFFI::get_data_ptr(ffi_obj);
+    let vtable = OpaqueMFFI::get_vtable_ptr(ffi_obj) as *const DebuggerVTable;
+    unsafe { ((*vtable).cont_one)(obj, thread_idx, err) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::debugger::{
+        debugger::{DebuggerEventFfi, DebuggerEventKind},
+        host_debuggers::debugger_linux_builder::x86_64_builder,
+        mock_debugger::{MOCK_PID, MockDebugger, MockStepMode, mock_x86_64},
+        registers::registers::RegisterInfoFfi,
+    };
+    use crate::ffi::core_framework::pheap_free;
+
+    fn make_mock() -> MockDebugger {
+        mock_x86_64(vec![0u8; 0x10], MockStepMode::Fixed(1))
+    }
+
+    #[test]
+    fn thread_index_round_trips_through_ffi() {
+        assert!(matches!(DebuggerThreadIndex::from_ffi(-1), DebuggerThreadIndex::Current));
+        assert!(matches!(DebuggerThreadIndex::from_ffi(-5), DebuggerThreadIndex::Current));
+        assert!(matches!(DebuggerThreadIndex::from_ffi(7), DebuggerThreadIndex::Specific(7)));
+
+        assert_eq!(DebuggerThreadIndex::Current.to_ffi(), Some(-1));
+        assert_eq!(DebuggerThreadIndex::Specific(7).to_ffi(), Some(7));
+        let max = DebuggerThreadIndex::Specific(i32::MAX as u32).to_ffi().unwrap();
+        assert!(matches!(DebuggerThreadIndex::from_ffi(max), DebuggerThreadIndex::Specific(v) if v == i32::MAX as u32));
+        // would wrap to a negative number and come back as the current thread
+        assert_eq!(DebuggerThreadIndex::Specific(i32::MAX as u32 + 1).to_ffi(), None);
+        assert_eq!(DebuggerThreadIndex::Specific(u32::MAX).to_ffi(), None);
+    }
+
+    #[test]
+    fn arch_info_through_exports() {
+        let ffi_obj = debugger_dyn_new(Box::new(make_mock()));
+        assert_eq!(debugger_get_big_endian(ffi_obj), 0);
+        assert_eq!(debugger_get_pointer_size(ffi_obj), 8);
+        pheap_free(ffi_obj);
+    }
+
+    #[test]
+    fn read_register_by_idx_through_vtable() {
+        let dbg = make_mock();
+        dbg.set_register("RIP", 0x401000).unwrap();
+        let ffi_obj = debugger_dyn_new(Box::new(dbg));
+
+        let mut err: *const u8 = std::ptr::null();
+        let mut rip = [0u8; 8];
+        debugger_read_register_by_idx_buf(ffi_obj, -1, 2, rip.as_mut_ptr(), rip.len(), &mut err);
+        assert!(err.is_null());
+        assert_eq!(u64::from_le_bytes(rip), 0x401000);
+
+        debugger_read_register_by_idx_buf(ffi_obj, -1, 5, rip.as_mut_ptr(), rip.len(), &mut err);
+        assert!(!err.is_null());
+        pheap_free(err as *mut u8);
+
+        pheap_free(ffi_obj);
+    }
+
+    #[test]
+    fn read_and_write_bytes_through_vtable() {
+        let ffi_obj = debugger_dyn_new(Box::new(make_mock()));
+
+        let mut err: *const u8 = std::ptr::null();
+        let data = [0xde, 0xad, 0xbe, 0xef];
+        let next = debugger_write_bytes(ffi_obj, -1, 4, data.as_ptr(), data.len(), &mut err);
+        assert!(err.is_null());
+        assert_eq!(next, 8);
+
+        let mut read_back = [0u8; 4];
+        let next = debugger_read_bytes(ffi_obj, -1, 4, read_back.as_mut_ptr(), read_back.len(), &mut err);
+        assert!(err.is_null());
+        assert_eq!(next, 8);
+        assert_eq!(read_back, data);
+
+        // past the end of the mock's memory
+        debugger_read_bytes(ffi_obj, -1, 0x20, read_back.as_mut_ptr(), read_back.len(), &mut err);
+        assert!(!err.is_null());
+        pheap_free(err as *mut u8);
+
+        pheap_free(ffi_obj);
+    }
+
+    #[test]
+    fn resume_calls_take_thread_idx() {
+        let ffi_obj = debugger_dyn_new(Box::new(make_mock()));
+        let mut err: *const u8 = std::ptr::null();
+
+        // step and cont_one only resume the thread they're given
+        for thread_idx in [-1, MOCK_PID as i32] {
+            debugger_step(ffi_obj, thread_idx, &mut err);
+            assert!(err.is_null());
+            debugger_cont_one(ffi_obj, thread_idx, &mut err);
+            assert!(err.is_null());
+        }
+
+        // a specific thread that doesn't exist isn't swapped for the current one
+        debugger_step(ffi_obj, 5, &mut err);
+        assert!(!err.is_null());
+        pheap_free(err as *mut u8);
+        err = std::ptr::null();
+        debugger_cont_one(ffi_obj, 5, &mut err);
+        assert!(!err.is_null());
+        pheap_free(err as *mut u8);
+
+        pheap_free(ffi_obj);
+    }
+
+    // pids of every event waiting, in order
+    fn drain_event_pids(ffi_obj: *mut u8) -> Vec<u32> {
+        let mut pids = Vec::new();
+        loop {
+            let mut err: *const u8 = std::ptr::null();
+            let ptr = debugger_wait_next_event(ffi_obj, 1, &mut err);
+            assert!(err.is_null());
+            let event = unsafe { DebuggerEventFfi::deserialize(ptr) };
+            pheap_free(ptr);
+            if event.kind == DebuggerEventKind::NoEvent {
+                return pids;
+            }
+            pids.push(event.pid);
+        }
+    }
+
+    #[test]
+    fn cont_all_resumes_threads_cont_one_does_not() {
+        let dbg = make_mock();
+        dbg.add_thread(2);
+        dbg.add_thread(3);
+        let ffi_obj = debugger_dyn_new(Box::new(dbg));
+        let mut err: *const u8 = std::ptr::null();
+
+        debugger_cont_all(ffi_obj, &mut err);
+        assert!(err.is_null());
+        assert_eq!(drain_event_pids(ffi_obj), [MOCK_PID, 2, 3]);
+
+        debugger_cont_one(ffi_obj, 2, &mut err);
+        assert!(err.is_null());
+        assert_eq!(drain_event_pids(ffi_obj), [2]);
+
+        debugger_cont_one(ffi_obj, -1, &mut err);
+        assert!(err.is_null());
+        assert_eq!(drain_event_pids(ffi_obj), [MOCK_PID]);
+
+        pheap_free(ffi_obj);
+    }
+
+    #[test]
+    fn interrupt_through_export() {
+        let ffi_obj = debugger_dyn_new(Box::new(make_mock()));
+        let mut err: *const u8 = std::ptr::null();
+
+        assert!(drain_event_pids(ffi_obj).is_empty());
+        debugger_interrupt(ffi_obj, &mut err);
+        assert!(err.is_null());
+
+        let ptr = debugger_wait_next_event(ffi_obj, 1, &mut err);
+        assert!(err.is_null());
+        let event = unsafe { DebuggerEventFfi::deserialize(ptr) };
+        pheap_free(ptr);
+        assert_eq!(event.kind, DebuggerEventKind::Interrupted);
+        assert_eq!(event.pid, MOCK_PID);
+
+        pheap_free(ffi_obj);
+    }
+
+    #[test]
+    fn register_infos_round_trip() {
+        let dbg = x86_64_builder().build().unwrap();
+        let ffi_obj = debugger_dyn_new(Box::new(dbg));
+
+        // a vec pheep is a pointer per element with the count just before it
+        let ptr = debugger_get_register_infos(ffi_obj, -1);
+        let reg_infos: Vec<RegisterInfo> = unsafe {
+            let count = *(ptr.sub(4) as *const u32) as usize;
+            let elem_ptrs = std::slice::from_raw_parts(ptr as *const *const u8, count);
+            elem_ptrs.iter().map(|p| RegisterInfoFfi::deserialize(*p)).collect()
+        };
+        pheap_free(ptr);
+        pheap_free(ffi_obj);
+
+        let rip = reg_infos.iter().find(|r| r.name == "RIP").expect("no RIP");
+        assert_eq!(rip.bit_len, 64);
+    }
+
+    #[test]
+    fn disassemble_range_pheep_can_be_walked() {
+        use crate::memory::memview::StaticMemView;
+        use crate::sleigh::disasm::{DisasmDispInstruction, DisasmDispInstructionFfi, x86_64_disasm};
+
+        // push rbp; mov rbp, rsp; nop
+        let mut mem = vec![0x55, 0x48, 0x89, 0xe5, 0x90];
+        mem.resize(0x10, 0);
+        let expected = x86_64_disasm()
+            .disasm_display_range(&StaticMemView::new(mem.clone()), 0, 3)
+            .unwrap();
+        let dbg = mock_x86_64(mem, MockStepMode::Decoded(x86_64_disasm()));
+        let ffi_obj = debugger_dyn_new(Box::new(dbg));
+
+        let mut err: *const u8 = std::ptr::null();
+        let ptr = debugger_disassemble_range(ffi_obj, 0, 3, &mut err);
+        assert!(err.is_null());
+
+        // walk it the way a c caller would: count, then a pointer per instruction
+        let insts: Vec<DisasmDispInstruction> = unsafe {
+            let count = *(ptr.sub(4) as *const u32) as usize;
+            let elem_ptrs = std::slice::from_raw_parts(ptr as *const *const u8, count);
+            elem_ptrs
+                .iter()
+                .map(|p| {
+                    assert_eq!(*p as usize % std::mem::align_of::<u64>(), 0);
+                    DisasmDispInstructionFfi::deserialize(*p)
+                })
+                .collect()
+        };
+        pheap_free(ptr);
+        assert_eq!(insts, expected);
+        assert_eq!(
+            insts.iter().map(|i| (i.addr, i.len)).collect::<Vec<_>>(),
+            [(0, 1), (1, 3), (4, 1)]
+        );
+
+        let ptr = debugger_disassemble_range(ffi_obj, 0, -1, &mut err);
+        assert!(ptr.is_null());
+        assert!(!err.is_null());
+        pheap_free(err as *mut u8);
+
+        pheap_free(ffi_obj);
+    }
+
+    #[test]
+    fn run_args_are_decoded_individually() {
+        let ls = c"ls";
+        let la = c"-la";
+        let argv = [ls.as_ptr(), la.as_ptr(), std::ptr::null()];
+        assert_eq!(
+            unsafe { debugger_args_from_ffi(argv.as_ptr()) },
+            Some(vec!["ls", "-la"])
+        );
+
+        let ffi_obj = debugger_dyn_new(Box::new(make_mock()));
+        let mut err: *const u8 = std::ptr::null();
+        let pid = debugger_run(ffi_obj, ls.as_ptr(), argv.as_ptr(), &mut err);
+        assert!(err.is_null());
+        assert_eq!(pid, MOCK_PID as i32);
+
+        pheap_free(ffi_obj);
+    }
+
+    #[test]
+    fn scan_memory_through_vtable() {
+        let dbg = make_mock();
+        dbg.write_bytes(DebuggerThreadIndex::Current, 6, &[0xde, 0xad, 0xbe, 0xef])
+            .unwrap();
+        let ffi_obj = debugger_dyn_new(Box::new(dbg));
+
+        let mut err: *const u8 = std::ptr::null();
+        let needle = [0xde, 0x00, 0xbe];
+        let mask = [0xff, 0x00, 0xff];
+        let found = debugger_scan_memory(ffi_obj, -1, 0, 0x10, needle.as_ptr(), mask.as_ptr(), 3, &mut err);
+        assert!(err.is_null());
+        assert_eq!(found, 6);
+
+        let found = debugger_scan_memory(ffi_obj, -1, 0, 0x10, needle.as_ptr(), std::ptr::null(), 3, &mut err);
+        assert!(err.is_null());
+        assert_eq!(found, DEBUGGER_SCAN_NOT_FOUND);
+
+        // no needle is an error, not a match or a crash
+        let found = debugger_scan_memory(ffi_obj, -1, 0, 0x10, std::ptr::null(), std::ptr::null(), 3, &mut err);
+        assert!(!err.is_null());
+        assert_eq!(found, 0);
+        pheap_free(err as *mut u8);
+        err = std::ptr::null();
+        let found = debugger_scan_memory(ffi_obj, -1, 0, 0x10, needle.as_ptr(), std::ptr::null(), 0, &mut err);
+        assert!(!err.is_null());
+        assert_eq!(found, 0);
+        pheap_free(err as *mut u8);
+
+        pheap_free(ffi_obj);
+    }
+}
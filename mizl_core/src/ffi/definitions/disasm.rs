@@ -0,0 +1,152 @@
+use crate::ffi::core_framework::prelude::*;
+use crate::{
+    ffi::{
+        core_framework::pheap_free,
+        core_types::OpaqueMFFI,
+        definitions::memview::{mem_view_borrow, mem_view_error_pret, mem_view_error_ret},
+    },
+    memory::{
+        composite_memview::CompositeMemView,
+        memview::{MemViewError, StaticMemView},
+    },
+    sleigh::{
+        disasm::{Disasm, DisasmDispInstruction, DisasmDispInstructionRun, DisasmDispInstructionRunType, DisasmError},
+        pspec_file::Pspec,
+        sla_file::Sleigh,
+    },
+};
+use std::ffi::c_void;
+
+// #-class Disasm
+
+/// # Safety
+/// sla_data and pspec_data must point to at least sla_len and pspec_len readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn disasm_new(
+    sla_data: *const u8,
+    sla_len: u64,
+    pspec_data: *const u8,
+    pspec_len: u64,
+    err: *mut *const u8,
+) -> *mut u8 {
+    if sla_data.is_null() || pspec_data.is_null() {
+        return mem_view_error_pret(err, Some(&MemViewError::InvalidParameter));
+    }
+
+    let sla_slice = unsafe { std::slice::from_raw_parts(sla_data, sla_len as usize) };
+    let pspec_slice = unsafe { std::slice::from_raw_parts(pspec_data, pspec_len as usize) };
+
+    let pspec_str = match std::str::from_utf8(pspec_slice) {
+        Ok(v) => v.to_string(),
+        Err(_) => return mem_view_error_pret(err, Some(&MemViewError::generic_static("pspec was not utf-8"))),
+    };
+
+    let sleigh = match Sleigh::try_new(sla_slice) {
+        Ok(v) => v,
+        Err(e) => return mem_view_error_pret(err, Some(&MemViewError::generic_dynamic(e.to_string()))),
+    };
+    let pspec = match Pspec::new(pspec_str) {
+        Ok(v) => v,
+        Err(_) => return mem_view_error_pret(err, Some(&MemViewError::generic_static("error in pspec"))),
+    };
+    let initial_ctx = match pspec.get_initial_ctx(&sleigh) {
+        Ok(v) => v,
+        Err(_) => return mem_view_error_pret(err, Some(&MemViewError::generic_static("error in pspec"))),
+    };
+
+    let disasm = Disasm::new(sleigh, initial_ctx);
+    let disasm_box = Box::new(disasm);
+    let disasm_box_ptr = Box::into_raw(disasm_box);
+
+    let disasm_ptr = OpaqueMFFI::serialize(disasm_box_ptr as *const c_void, None, OpaqueMFFI::free_fn::<Disasm>);
+
+    disasm_ptr
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn disasm_drop(obj: *mut u8, err: *mut *const u8) {
+    if obj.is_null() {
+        return mem_view_error_ret(err, Some(&MemViewError::InvalidParameter));
+    }
+
+    pheap_free(obj);
+}
+
+/// Disassemble one instruction from a memview object. The memview is borrowed, not stolen.
+#[unsafe(no_mangle)]
+pub extern "C" fn disasm_one(obj: *const c_void, mv: *mut u8, addr: u64, err: *mut *const u8) -> *mut u8 {
+    if obj.is_null() || mv.is_null() {
+        return mem_view_error_pret(err, Some(&MemViewError::InvalidParameter));
+    }
+
+    let disasm = unsafe { &*(OpaqueMFFI::get_data_ptr(obj as *mut u8) as *const Disasm) };
+    let mem = match mem_view_borrow(mv) {
+        Ok(v) => v,
+        Err(e) => return mem_view_error_pret(err, Some(&e)),
+    };
+
+    match disasm.disasm_display(mem, addr) {
+        Ok(ins) => pheap_alloc(&ins, None),
+        Err(_) => mem_view_error_pret(err, Some(&MemViewError::generic_static("disassembly failed"))),
+    }
+}
+
+/// Disassemble every instruction in a buffer as if it were loaded at base_addr.
+/// Bytes that don't decode become a one byte "(bad)" instruction, like objdump.
+/// Decoding stops at a trailing instruction that doesn't fit in the buffer.
+///
+/// # Safety
+/// data must point to at least len readable bytes unless len is 0.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn disasm_bytes(
+    obj: *const c_void,
+    data: *const u8,
+    len: u64,
+    base_addr: u64,
+    err: *mut *const u8,
+) -> *mut u8 {
+    if obj.is_null() || (data.is_null() && len != 0) {
+        return mem_view_error_pret(err, Some(&MemViewError::InvalidParameter));
+    }
+
+    let disasm = unsafe { &*(OpaqueMFFI::get_data_ptr(obj as *mut u8) as *const Disasm) };
+    let data_vec = if len == 0 {
+        Vec::new()
+    } else {
+        unsafe { std::slice::from_raw_parts(data, len as usize) }.to_vec()
+    };
+
+    let mut mem = CompositeMemView::new();
+    if let Err(e) = mem.add_view(base_addr, len, Box::new(StaticMemView::new(data_vec))) {
+        return mem_view_error_pret(err, Some(&e));
+    }
+
+    let mut instructions: Vec<DisasmDispInstruction> = Vec::new();
+    let end_addr = base_addr + len;
+    let mut at = base_addr;
+    while at < end_addr {
+        let ins = match disasm.disasm_display(&mem, at) {
+            Ok(v) if v.len > 0 => v,
+            // ran off the end of the buffer, so there's nothing more to decode
+            Err(DisasmError::Truncated) => break,
+            _ => {
+                let text = "(bad)".to_string();
+                let runs = vec![DisasmDispInstructionRun::new(
+                    text.len() as u32,
+                    DisasmDispInstructionRunType::Normal,
+                )];
+                DisasmDispInstruction {
+                    addr: at,
+                    len: 1,
+                    text,
+                    runs,
+                    raw_bytes: Vec::new(),
+                }
+            }
+        };
+        at += ins.len;
+        instructions.push(ins);
+    }
+
+    pheap_alloc(&instructions, None)
+}
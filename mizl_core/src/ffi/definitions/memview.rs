@@ -14,6 +14,8 @@ pub fn mem_view_error_ffi(error_opt: Option<&MemViewError>) -> *mut u8 {
                 MemViewError::NotLoaded => 3,
                 MemViewError::InvalidParameter => 4,
                 MemViewError::Generic(_) => 5,
+                MemViewError::ReadOnly => 6,
+                MemViewError::Unmapped => 7,
             };
             let error_str: String = error.to_string();
             let error_mffi_ptr = ErrorFfi::make_error(error_code, Some(error_str));
@@ -66,17 +68,25 @@ pub struct MemViewFfiWrap {
     pub obj: Box<dyn MemView>,
 }
 
+/// Functions every opaque memview object carries so other ffi functions can use it
+/// without knowing the concrete type. All of them take the opaque object pointer.
+/// - steal: take ownership of the memview. the opaque object is left empty and
+///   freeing it afterwards does nothing. fails if it was already stolen.
+/// - as_mem_view: borrow the memview without taking ownership. fails if stolen.
+///
+/// Dropping is not part of the vtable. It's the free pointer every opaque object
+/// already has, so `pheap_free` works on any memview object.
 #[repr(C)]
 pub struct MemViewVTable {
-    // todo
     pub steal: fn(*const c_void) -> Result<Box<dyn MemView>, ()>,
+    pub as_mem_view: fn(*const c_void) -> Result<*const dyn MemView, ()>,
 }
 
 // #-class MemView
 
 static STATIC_MEM_VIEW_VTABLE: MemViewVTable = MemViewVTable {
-    // todo
     steal: static_mem_view_steal,
+    as_mem_view: static_mem_view_as_mem_view,
 };
 
 #[unsafe(no_mangle)]
@@ -122,7 +132,162 @@ pub extern "C" fn static_mem_view_from_data(data: *const u8, len: u64) -> *mut u
     static_mem_view_ptr
 }
 
+pub fn static_mem_view_as_mem_view(obj: *const c_void) -> Result<*const dyn MemView, ()> {
+    let data_ptr = OpaqueMFFI::get_data_ptr(obj as *mut u8);
+    if data_ptr.is_null() {
+        return Err(());
+    }
+    Ok(data_ptr as *const StaticMemView as *const dyn MemView)
+}
+
 pub fn static_mem_view_steal(obj: *const c_void) -> Result<Box<dyn MemView>, ()> {
     let stolen_obj = OpaqueMFFI::steal::<StaticMemView>(obj as *mut u8)?;
     Ok(stolen_obj as Box<dyn MemView>)
 }
+
+// #-class CallbackMemView
+
+/// Callbacks for a memview implemented on the C side.
+/// read_bytes and write_bytes return -1 on success or a MemViewError code on failure.
+/// write_bytes can be null for read-only memory. drop can be null if user_data
+/// doesn't need cleanup and is called once when the memview is freed.
+#[repr(C)]
+pub struct MemViewCallbacks {
+    pub user_data: *mut c_void,
+    pub read_bytes: extern "C" fn(user_data: *mut c_void, addr: u64, out_data: *mut u8, count: i32) -> i32,
+    pub write_bytes: Option<extern "C" fn(user_data: *mut c_void, addr: u64, data: *const u8, count: i32) -> i32>,
+    pub max_address: extern "C" fn(user_data: *mut c_void) -> u64,
+    pub drop: Option<extern "C" fn(user_data: *mut c_void)>,
+}
+
+pub struct CallbackMemView {
+    callbacks: MemViewCallbacks,
+}
+
+impl CallbackMemView {
+    pub fn new(callbacks: MemViewCallbacks) -> CallbackMemView {
+        CallbackMemView { callbacks }
+    }
+
+    fn error_from_code(code: i32) -> MemViewError {
+        match code {
+            0 => MemViewError::EndOfStream,
+            1 => MemViewError::ReadAccessDenied,
+            2 => MemViewError::WriteAccessDenied,
+            3 => MemViewError::NotLoaded,
+            4 => MemViewError::InvalidParameter,
+            6 => MemViewError::ReadOnly,
+            7 => MemViewError::Unmapped,
+            _ => MemViewError::generic_dynamic(format!("callback memview error {}", code)),
+        }
+    }
+}
+
+impl MemView for CallbackMemView {
+    fn read_bytes(&self, addr: &mut u64, out_data: &mut [u8], count: i32) -> Result<(), MemViewError> {
+        if count < 0 || count as usize > out_data.len() {
+            return Err(MemViewError::InvalidParameter);
+        }
+
+        let code = (self.callbacks.read_bytes)(self.callbacks.user_data, *addr, out_data.as_mut_ptr(), count);
+        if code != -1 {
+            return Err(CallbackMemView::error_from_code(code));
+        }
+
+        *addr += count as u64;
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, addr: &mut u64, value: &[u8]) -> Result<(), MemViewError> {
+        let write_bytes = match self.callbacks.write_bytes {
+            Some(v) => v,
+            None => return Err(MemViewError::ReadOnly),
+        };
+
+        let code = write_bytes(self.callbacks.user_data, *addr, value.as_ptr(), value.len() as i32);
+        if code != -1 {
+            return Err(CallbackMemView::error_from_code(code));
+        }
+
+        *addr += value.len() as u64;
+        Ok(())
+    }
+
+    fn max_address(&self) -> Result<u64, MemViewError> {
+        Ok((self.callbacks.max_address)(self.callbacks.user_data))
+    }
+
+    // we don't know what the callbacks are backed by, so play it safe
+    fn can_read_while_running(&self) -> bool {
+        false
+    }
+
+    fn can_write_while_running(&self) -> bool {
+        false
+    }
+}
+
+impl Drop for CallbackMemView {
+    fn drop(&mut self) {
+        if let Some(drop) = self.callbacks.drop {
+            drop(self.callbacks.user_data);
+        }
+    }
+}
+
+static CALLBACK_MEM_VIEW_VTABLE: MemViewVTable = MemViewVTable {
+    steal: callback_mem_view_steal,
+    as_mem_view: callback_mem_view_as_mem_view,
+};
+
+/// Create a memview backed by C callbacks. The callbacks struct is copied.
+///
+/// # Safety
+/// callbacks must be null or point to a valid MemViewCallbacks.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn callback_mem_view_new(callbacks: *const MemViewCallbacks, err: *mut *const u8) -> *mut u8 {
+    if callbacks.is_null() {
+        return mem_view_error_pret(err, Some(&MemViewError::InvalidParameter));
+    }
+
+    let callbacks_ref = unsafe { &*callbacks };
+    let callback_mem_view = CallbackMemView::new(MemViewCallbacks {
+        user_data: callbacks_ref.user_data,
+        read_bytes: callbacks_ref.read_bytes,
+        write_bytes: callbacks_ref.write_bytes,
+        max_address: callbacks_ref.max_address,
+        drop: callbacks_ref.drop,
+    });
+    let callback_mem_view_box = Box::new(callback_mem_view);
+    let callback_mem_view_box_ptr = Box::into_raw(callback_mem_view_box);
+
+    OpaqueMFFI::serialize(
+        callback_mem_view_box_ptr as *const c_void,
+        Some(&CALLBACK_MEM_VIEW_VTABLE as *const MemViewVTable as *const c_void),
+        OpaqueMFFI::free_fn::<CallbackMemView>,
+    )
+}
+
+pub fn callback_mem_view_as_mem_view(obj: *const c_void) -> Result<*const dyn MemView, ()> {
+    let data_ptr = OpaqueMFFI::get_data_ptr(obj as *mut u8);
+    if data_ptr.is_null() {
+        return Err(());
+    }
+    Ok(data_ptr as *const CallbackMemView as *const dyn MemView)
+}
+
+pub fn callback_mem_view_steal(obj: *const c_void) -> Result<Box<dyn MemView>, ()> {
+    let stolen_obj = OpaqueMFFI::steal::<CallbackMemView>(obj as *mut u8)?;
+    Ok(stolen_obj as Box<dyn MemView>)
+}
+
+// ///////
+
+/// Borrow the memview inside an opaque memview object without stealing it.
+pub fn mem_view_borrow<'a>(mv: *mut u8) -> Result<&'a dyn MemView, MemViewError> {
+    let mv_vtable = OpaqueMFFI::get_vtable_ptr(mv) as *const MemViewVTable;
+    match unsafe { ((*mv_vtable).as_mem_view)(mv as *const c_void) } {
+        Ok(v) => unsafe { Ok(&*v) },
+        Err(_) => Err(MemViewError::generic_static("`mv` was stolen")),
+    }
+}
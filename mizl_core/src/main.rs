@@ -16,12 +16,14 @@ use database::gbf_chained_buf_memview::GbfChainedBufMemView;
 use database::{gbf::GbfFile, gbf_table_view::GbfTableView};
 use database::{gbf_record::GbfFieldValue, gbf_table_view::GbfTableViewIterator};
 use debugger::{
+    address_expr::{AddressExprError, evaluate_address},
     debugger::{Debugger, DebuggerEvent, DebuggerEventKind, DebuggerHelper, DebuggerThreadIndex},
-    host_debuggers::debugger_linux::DebuggerLinux,
+    host_debuggers::{debugger_linux::DebuggerLinux, debugger_linux_signals::signal_name},
+    module_symbols::ModuleSymbolResolver,
     registers::registers::RegisterInfo,
 };
-use memory::memview::{MemView, StaticMemView};
-use sleigh::disasm::{DisasmDispInstructionRun, DisasmDispInstructionRunType};
+use memory::memview::{MemView, MemViewError, StaticMemView};
+use sleigh::disasm::{DisplayOptions, ListingOptions};
 use std::fs::File;
 use std::{
     io::{self, Write},
@@ -32,27 +34,44 @@ use std::{
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
-fn colorize_text(text: &str, runs: &Vec<DisasmDispInstructionRun>) -> String {
-    let mut color_text = String::new();
-    let mut text_idx = 0;
-    for run in runs {
-        color_text += match run.run_type {
-            DisasmDispInstructionRunType::Normal => "\x1b[0;37m",
-            DisasmDispInstructionRunType::Mnemonic => "\x1b[0;96m",
-            DisasmDispInstructionRunType::Register => "\x1b[0;93m",
-            DisasmDispInstructionRunType::Number => "\x1b[0;95m",
-        };
-        color_text += &text[(text_idx as usize)..((text_idx + run.length) as usize)];
-        text_idx += run.length;
+// reads the current thread's memory through the debugger, so breakpoints
+// show their original bytes
+struct DebuggerReadMemView<'a> {
+    debugger: &'a DebuggerLinux,
+}
+
+impl MemView for DebuggerReadMemView<'_> {
+    fn read_bytes(&self, addr: &mut u64, out_data: &mut [u8], count: i32) -> Result<(), MemViewError> {
+        if count < 0 || count as usize > out_data.len() {
+            return Err(MemViewError::InvalidParameter);
+        }
+
+        match self
+            .debugger
+            .read_bytes(DebuggerThreadIndex::Current, *addr, &mut out_data[..count as usize])
+        {
+            Ok(v) => {
+                *addr = v;
+                Ok(())
+            }
+            Err(_) => Err(MemViewError::ReadAccessDenied),
+        }
     }
 
-    return color_text + "\x1b[0;37m";
+    fn max_address(&self) -> Result<u64, MemViewError> {
+        Ok(u64::MAX)
+    }
+
+    fn can_read_while_running(&self) -> bool {
+        false
+    }
+
+    fn can_write_while_running(&self) -> bool {
+        false
+    }
 }
 
-fn disasm_at_pc<DBG>(debugger: &DBG, pc_reg: &RegisterInfo, len: i32) -> bool
-where
-    DBG: Debugger,
-{
+fn disasm_at_pc(debugger: &DebuggerLinux, pc_reg: &RegisterInfo, len: u64) -> bool {
     let pc_reg_val: u64 = match debugger.read_register_by_idx(DebuggerThreadIndex::Current, pc_reg.mizl_idx) {
         Ok(v) => v,
         Err(e) => {
@@ -61,43 +80,37 @@ where
         }
     };
 
-    let mut dis_addr = pc_reg_val;
-    for _ in 0..len {
-        let disp_ins = debugger.disassemble_one(dis_addr);
-        match disp_ins {
-            Ok(v) => {
-                let text_color = colorize_text(&v.text, &v.runs);
-                println!("\x1b[0;92m{:#10x}\x1b[0;37m: {}", dis_addr, text_color);
-                dis_addr += v.len;
-            }
-            Err(e) => {
-                println!("<disassembly failed> {}", e);
-                dis_addr += 1;
-            }
-        }
-    }
-
-    return true;
+    disasm_at_addr(debugger, pc_reg_val, len)
 }
 
-fn disasm_at_addr<DBG>(debugger: &DBG, mut dis_addr: u64, len: i32) -> bool
-where
-    DBG: Debugger,
-{
-    for _ in 0..len {
-        let disp_ins = debugger.disassemble_one(dis_addr);
-        match disp_ins {
-            Ok(v) => {
-                let text_color = colorize_text(&v.text, &v.runs);
-                println!("\x1b[0;92m{:#10x}\x1b[0;37m: {}", dis_addr, text_color);
-                dis_addr += v.len;
-            }
-            Err(e) => {
-                println!("<disassembly failed> {}", e);
-                dis_addr += 1;
-            }
+// len is in bytes. the last instruction may run a little past it
+fn disasm_at_addr(debugger: &DebuggerLinux, dis_addr: u64, len: u64) -> bool {
+    let mem = DebuggerReadMemView { debugger };
+
+    // label the start of each symbol, looking through whatever is loaded
+    // right now if no symbols were given up front
+    let loaded_symbols;
+    let symbols = match debugger.get_symbols() {
+        Some(v) => v,
+        None => {
+            let modules = debugger
+                .get_loaded_modules(DebuggerThreadIndex::Current)
+                .unwrap_or_default();
+            loaded_symbols = ModuleSymbolResolver::from_loaded_modules(modules);
+            &loaded_symbols
         }
-    }
+    };
+    let annotate = |addr: u64| match symbols.resolve(addr) {
+        Some(v) if matches!(v.symbol, Some((_, 0))) => Some(v.to_string()),
+        _ => None,
+    };
+
+    let opts = ListingOptions {
+        display: DisplayOptions::default(),
+        annotate: Some(&annotate),
+    };
+    let end = dis_addr.saturating_add(len);
+    print!("{}", debugger.get_disasm().listing(&mem, dis_addr, end, &opts));
 
     return true;
 }
@@ -210,6 +223,50 @@ fn main() {
 }
 
 fn main_real() {
+    // the library only logs through the log crate, so print everything here to
+    // keep the old debugging output in the cli
+    struct ConsoleLogger;
+
+    impl log::Log for ConsoleLogger {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            println!("[{}] {}", record.level(), record.args());
+        }
+
+        fn flush(&self) {}
+    }
+
+    static CONSOLE_LOGGER: ConsoleLogger = ConsoleLogger;
+
+    let _ = log::set_logger(&CONSOLE_LOGGER);
+    log::set_max_level(log::LevelFilter::Trace);
+
+    // prints the error itself so callers only have to bail out
+    fn parse_addr(debugger: &DebuggerLinux, text: &str) -> Option<u64> {
+        let result = match evaluate_address(debugger, debugger.get_symbols(), text) {
+            // no symbols were given up front, so look through whatever is loaded right now
+            Err(AddressExprError::UnknownName(_)) if debugger.get_symbols().is_none() => {
+                let modules = debugger
+                    .get_loaded_modules(DebuggerThreadIndex::Current)
+                    .unwrap_or_default();
+                let symbols = ModuleSymbolResolver::from_loaded_modules(modules);
+                evaluate_address(debugger, Some(&symbols), text)
+            }
+            v => v,
+        };
+
+        match result {
+            Ok(v) => Some(v),
+            Err(e) => {
+                println!("error: {}", e);
+                None
+            }
+        }
+    }
+
     let path = "/bin/ls";
     let args = vec!["ls", "-la"];
 
@@ -219,7 +276,7 @@ fn main_real() {
     let pc_reg = reg_infos.iter().find(|r| r.name == "RIP").unwrap();
     let mut cmd = "".to_owned();
     let mut last_cmd;
-    let mut last_disasm_len = 10;
+    let mut last_disasm_len = 0x20u64;
 
     let (dbg_tx, dbg_rx) = unbounded::<DebuggerEvent>();
     let (inp_tx, inp_rx) = unbounded::<String>();
@@ -299,6 +356,21 @@ fn main_real() {
                         Ok(_) => {}
                         Err(e) => println!("error: {}", e),
                     };
+                } else if cmd == "ni" {
+                    match debugger.step_over(DebuggerThreadIndex::Current) {
+                        Ok(_) => {}
+                        Err(e) => println!("error: {}", e),
+                    };
+                } else if cmd == "fin" {
+                    match debugger.step_out(DebuggerThreadIndex::Current) {
+                        Ok(_) => {}
+                        Err(e) => println!("error: {}", e),
+                    };
+                } else if cmd == "p" {
+                    match debugger.interrupt_inferior() {
+                        Ok(_) => {}
+                        Err(e) => println!("error: {}", e),
+                    };
                 } else if cmd == "c" {
                     match debugger.cont_all() {
                         Ok(_) => {}
@@ -308,16 +380,14 @@ fn main_real() {
                     if args.len() < 2 {
                         println!("incorrect arguments");
                     } else {
-                        let bp_addr_str = args[1];
-                        match u64::from_str_radix(bp_addr_str, 16) {
-                            Ok(bp_addr) => match debugger.add_breakpoint(DebuggerThreadIndex::Current, bp_addr) {
+                        if let Some(bp_addr) = parse_addr(&debugger, &args[1..].join(" ")) {
+                            match debugger.add_breakpoint(DebuggerThreadIndex::Current, bp_addr) {
                                 Ok(v) => {
                                     println!("created breakpoint {}", v);
                                 }
                                 Err(e) => println!("error: {}", e),
-                            },
-                            Err(_) => println!("incorrect arguments"),
-                        };
+                            };
+                        }
                     }
                 } else if cmd == "reg" {
                     if args.len() < 2 {
@@ -333,7 +403,7 @@ fn main_real() {
                     }
                 } else if cmd == "dis" {
                     let len = if args.len() > 1 {
-                        match i32::from_str_radix(args[1], 10) {
+                        match u64::from_str_radix(args[1], 10) {
                             Ok(v) => v,
                             Err(_) => last_disasm_len,
                         }
@@ -342,10 +412,9 @@ fn main_real() {
                     };
 
                     if args.len() > 2 {
-                        match u64::from_str_radix(args[2], 16) {
-                            Ok(v) => disasm_at_addr(&*debugger, v, len),
-                            Err(_) => disasm_at_pc(&*debugger, &pc_reg, len),
-                        };
+                        if let Some(v) = parse_addr(&debugger, &args[2..].join(" ")) {
+                            disasm_at_addr(&*debugger, v, len);
+                        }
                     } else {
                         disasm_at_pc(&*debugger, &pc_reg, len);
                     }
@@ -359,13 +428,8 @@ fn main_real() {
                             Ok(v) => v,
                             Err(_) => 10,
                         };
-                        let addr: Option<u64> = match u64::from_str_radix(args[2], 16) {
-                            Ok(v) => Some(v),
-                            Err(_) => None,
-                        };
-                        if addr.is_none() {
-                            println!("incorrect arguments");
-                        } else {
+                        let addr = parse_addr(&debugger, &args[2..].join(" "));
+                        if addr.is_some() {
                             let mut out_data = vec![0u8; byte_count as usize];
                             match debugger.read_bytes(DebuggerThreadIndex::Current, addr.unwrap(), &mut out_data) {
                                 Ok(_) => {
@@ -395,41 +459,7 @@ fn main_real() {
                     }
                     DebuggerEventKind::MiscSignalReceived => {
                         let signal = (e.code >> 8) & 0xff;
-                        let signal_name = match signal {
-                            1 => "SIGHUP",
-                            2 => "SIGINT",
-                            3 => "SIGQUIT",
-                            4 => "SIGILL",
-                            5 => "SIGTRAP",
-                            6 => "SIGABRT",
-                            7 => "SIGBUS",
-                            8 => "SIGFPE",
-                            9 => "SIGKILL",
-                            10 => "SIGUSR1",
-                            11 => "SIGSEGV",
-                            12 => "SIGUSR2",
-                            13 => "SIGPIPE",
-                            14 => "SIGALRM",
-                            15 => "SIGTERM",
-                            16 => "SIGSTKFLT",
-                            17 => "SIGCHLD",
-                            18 => "SIGCONT",
-                            19 => "SIGSTOP",
-                            20 => "SIGTSTP",
-                            21 => "SIGTTIN",
-                            22 => "SIGTTOU",
-                            23 => "SIGURG",
-                            24 => "SIGXCPU",
-                            25 => "SIGXFSZ",
-                            26 => "SIGVTALRM",
-                            27 => "SIGPROF",
-                            28 => "SIGWINCH",
-                            29 => "SIGIO",
-                            30 => "SIGPWR",
-                            31 => "SIGSYS",
-                            _ => "UNKNOWN",
-                        };
-                        println!("[received signal: {}]", signal_name);
+                        println!("[received signal: {}]", signal_name(signal));
                         if signal != 5 && signal != 19 {
                             match debugger.cont_all() {
                                 Ok(_) => {}
@@ -437,6 +467,13 @@ fn main_real() {
                             };
                         }
                     }
+                    DebuggerEventKind::ProcessExited => {
+                        if e.code & DebuggerEvent::PROCESS_KILLED_BY_SIGNAL != 0 {
+                            println!("[process killed by signal: {}]", signal_name(e.code & 0xff));
+                        } else {
+                            println!("[process exited with code {}]", e.code);
+                        }
+                    }
                     _ => {
                         println!("[received debugger event: {}]", e.code);
                     }
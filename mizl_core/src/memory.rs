@@ -1 +1,4 @@
+pub mod composite_memview;
 pub mod memview;
+pub mod memview_reader;
+pub mod overlay_memview;
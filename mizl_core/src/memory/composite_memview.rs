@@ -0,0 +1,200 @@
+use crate::memory::memview::{MemView, MemViewError};
+
+struct CompositeMemViewRegion {
+    base_addr: u64,
+    len: u64,
+    mem_view: Box<dyn MemView>,
+}
+
+// a memview made out of several sub-views placed at different base addresses.
+// addresses passed to a sub-view are relative to its base. reads and writes
+// can span neighboring regions but fail with Unmapped if they touch a gap
+// between them.
+#[derive(Default)]
+pub struct CompositeMemView {
+    regions: Vec<CompositeMemViewRegion>, // sorted by base_addr, never overlapping
+}
+
+impl CompositeMemView {
+    pub fn new() -> CompositeMemView {
+        CompositeMemView { regions: Vec::new() }
+    }
+
+    pub fn add_view(&mut self, base_addr: u64, len: u64, mem_view: Box<dyn MemView>) -> Result<(), MemViewError> {
+        let end_addr = match base_addr.checked_add(len) {
+            Some(v) => v,
+            None => return Err(MemViewError::InvalidParameter),
+        };
+
+        let insert_idx = self.regions.partition_point(|r| r.base_addr < base_addr);
+
+        // make sure we don't overlap our neighbors
+        if insert_idx > 0 {
+            let prev = &self.regions[insert_idx - 1];
+            if prev.base_addr + prev.len > base_addr {
+                return Err(MemViewError::generic_static("region overlaps previous region"));
+            }
+        }
+        if let Some(next) = self.regions.get(insert_idx) {
+            if end_addr > next.base_addr {
+                return Err(MemViewError::generic_static("region overlaps next region"));
+            }
+        }
+
+        self.regions.insert(
+            insert_idx,
+            CompositeMemViewRegion {
+                base_addr,
+                len,
+                mem_view,
+            },
+        );
+        Ok(())
+    }
+
+    fn find_region_idx(&self, addr: u64) -> Option<usize> {
+        let idx = self.regions.partition_point(|r| r.base_addr <= addr);
+        if idx == 0 {
+            return None;
+        }
+
+        let region = &self.regions[idx - 1];
+        if addr - region.base_addr < region.len {
+            Some(idx - 1)
+        } else {
+            None // in a gap
+        }
+    }
+}
+
+impl MemView for CompositeMemView {
+    fn read_bytes(&self, addr: &mut u64, out_data: &mut [u8], count: i32) -> Result<(), MemViewError> {
+        if count < 0 {
+            return Err(MemViewError::InvalidParameter);
+        }
+
+        let mut out_offset = 0usize;
+        let mut remaining = count as u64;
+        while remaining > 0 {
+            let region_idx = self.find_region_idx(*addr).ok_or(MemViewError::Unmapped)?;
+            let region = &self.regions[region_idx];
+
+            let region_offset = *addr - region.base_addr;
+            let read_len = std::cmp::min(remaining, region.len - region_offset);
+            let mut sub_addr = region_offset;
+            let out_end = out_offset + read_len as usize;
+            region
+                .mem_view
+                .read_bytes(&mut sub_addr, &mut out_data[out_offset..out_end], read_len as i32)?;
+
+            *addr += read_len;
+            out_offset = out_end;
+            remaining -= read_len;
+        }
+
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, addr: &mut u64, value: &[u8]) -> Result<(), MemViewError> {
+        let mut in_offset = 0usize;
+        while in_offset < value.len() {
+            let region_idx = self.find_region_idx(*addr).ok_or(MemViewError::Unmapped)?;
+            let region = &mut self.regions[region_idx];
+
+            let region_offset = *addr - region.base_addr;
+            let remaining = (value.len() - in_offset) as u64;
+            let write_len = std::cmp::min(remaining, region.len - region_offset);
+            let mut sub_addr = region_offset;
+            let in_end = in_offset + write_len as usize;
+            region.mem_view.write_bytes(&mut sub_addr, &value[in_offset..in_end])?;
+
+            *addr += write_len;
+            in_offset = in_end;
+        }
+
+        Ok(())
+    }
+
+    fn max_address(&self) -> Result<u64, MemViewError> {
+        match self.regions.last() {
+            Some(v) => Ok(v.base_addr + v.len),
+            None => Ok(0),
+        }
+    }
+
+    fn can_read_while_running(&self) -> bool {
+        self.regions.iter().all(|r| r.mem_view.can_read_while_running())
+    }
+
+    fn can_write_while_running(&self) -> bool {
+        self.regions.iter().all(|r| r.mem_view.can_write_while_running())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::memview::StaticMemView;
+
+    // [0x1000, 0x1004) and [0x1004, 0x1008) touch, then a gap until [0x2000, 0x2004)
+    fn make_mem_view() -> CompositeMemView {
+        let mut mv = CompositeMemView::new();
+        mv.add_view(0x2000, 4, Box::new(StaticMemView::new(vec![0x21, 0x22, 0x23, 0x24])))
+            .unwrap();
+        mv.add_view(0x1000, 4, Box::new(StaticMemView::new(vec![0x01, 0x02, 0x03, 0x04])))
+            .unwrap();
+        mv.add_view(0x1004, 4, Box::new(StaticMemView::new(vec![0x11, 0x12, 0x13, 0x14])))
+            .unwrap();
+        mv
+    }
+
+    #[test]
+    fn reads_span_neighbors_but_not_gaps() {
+        let mv = make_mem_view();
+        assert_eq!(mv.max_address().unwrap(), 0x2004);
+
+        let mut data = [0u8; 4];
+        let mut addr = 0x1002;
+        mv.read_bytes(&mut addr, &mut data, 4).unwrap();
+        assert_eq!(data, [0x03, 0x04, 0x11, 0x12]);
+        assert_eq!(addr, 0x1006);
+
+        addr = 0x2001;
+        mv.read_bytes(&mut addr, &mut data[..2], 2).unwrap();
+        assert_eq!(data[..2], [0x22, 0x23]);
+
+        addr = 0x1006;
+        assert_eq!(mv.read_bytes(&mut addr, &mut data, 4), Err(MemViewError::Unmapped));
+        addr = 0xfff;
+        assert_eq!(mv.read_bytes(&mut addr, &mut data, 1), Err(MemViewError::Unmapped));
+        addr = 0x2004;
+        assert_eq!(mv.read_bytes(&mut addr, &mut data, 1), Err(MemViewError::Unmapped));
+    }
+
+    #[test]
+    fn writes_land_in_the_right_sub_view() {
+        let mut mv = make_mem_view();
+        let mut addr = 0x1003;
+        mv.write_bytes(&mut addr, &[0xaa, 0xbb]).unwrap();
+        assert_eq!(addr, 0x1005);
+
+        let mut data = [0u8; 8];
+        addr = 0x1000;
+        mv.read_bytes(&mut addr, &mut data, 8).unwrap();
+        assert_eq!(data, [0x01, 0x02, 0x03, 0xaa, 0xbb, 0x12, 0x13, 0x14]);
+
+        addr = 0x1fff;
+        assert_eq!(mv.write_bytes(&mut addr, &[0]), Err(MemViewError::Unmapped));
+    }
+
+    #[test]
+    fn overlapping_regions_are_rejected() {
+        let mut mv = make_mem_view();
+        let empty = || Box::new(StaticMemView::new(vec![0; 4]));
+        assert!(mv.add_view(0x1006, 4, empty()).is_err());
+        assert!(mv.add_view(0x1ffe, 4, empty()).is_err());
+        assert!(mv.add_view(0x0ffe, 4, empty()).is_err());
+        assert_eq!(mv.add_view(u64::MAX, 4, empty()), Err(MemViewError::InvalidParameter));
+        mv.add_view(0x1008, 4, empty()).unwrap();
+    }
+}
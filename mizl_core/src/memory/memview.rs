@@ -1,7 +1,10 @@
 use crate::consts::arch::Endianness;
 use std::{borrow::Cow, fmt};
 
-#[derive(Debug)]
+// find reads this much at a time, aligned so a chunk never spans two pages
+const FIND_CHUNK_SIZE: u64 = 0x1000;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum MemViewError {
     EndOfStream,
     ReadAccessDenied,
@@ -9,6 +12,8 @@ pub enum MemViewError {
     NotLoaded,
     InvalidParameter,
     Generic(Cow<'static, str>),
+    ReadOnly,
+    Unmapped,
 }
 
 impl MemViewError {
@@ -30,6 +35,8 @@ impl fmt::Display for MemViewError {
             MemViewError::NotLoaded => write!(f, "memory is not yet loaded or was recently unloaded"),
             MemViewError::InvalidParameter => write!(f, "bad parameter"),
             MemViewError::Generic(s) => write!(f, "{}", s),
+            MemViewError::ReadOnly => write!(f, "memory view doesn't support writing"),
+            MemViewError::Unmapped => write!(f, "address isn't backed by any data"),
         }
     }
 }
@@ -40,7 +47,10 @@ impl fmt::Display for MemViewError {
 // run into many 128-bit addressed devices anytime soon...
 pub trait MemView {
     fn read_bytes(&self, addr: &mut u64, out_data: &mut [u8], count: i32) -> Result<(), MemViewError>;
-    fn write_bytes(&mut self, addr: &mut u64, value: &[u8]) -> Result<(), MemViewError>;
+    // views that can't be written to leave this as is
+    fn write_bytes(&mut self, _addr: &mut u64, _value: &[u8]) -> Result<(), MemViewError> {
+        Err(MemViewError::ReadOnly)
+    }
 
     // always returns one byte after the last byte.
     // ex: if last byte is at 0xf, this should be 0x10
@@ -125,7 +135,7 @@ pub trait MemView {
         }
     }
 
-    fn read_f32(&mut self, addr: &mut u64, endian: Endianness) -> Result<f32, MemViewError> {
+    fn read_f32(&self, addr: &mut u64, endian: Endianness) -> Result<f32, MemViewError> {
         let mut bytes = [0u8; 4];
         self.read_bytes(addr, &mut bytes, 4)?;
         if endian == Endianness::LittleEndian {
@@ -135,7 +145,7 @@ pub trait MemView {
         }
     }
 
-    fn read_f64(&mut self, addr: &mut u64, endian: Endianness) -> Result<f64, MemViewError> {
+    fn read_f64(&self, addr: &mut u64, endian: Endianness) -> Result<f64, MemViewError> {
         let mut bytes = [0u8; 8];
         self.read_bytes(addr, &mut bytes, 8)?;
         if endian == Endianness::LittleEndian {
@@ -145,6 +155,36 @@ pub trait MemView {
         }
     }
 
+    // first address in [start, end) where needle is, comparing only the bits
+    // set in mask (0xff = must match, 0x00 = anything). mask has to be as long
+    // as needle. bytes that can't be read are skipped, so gaps in a process's
+    // address space don't end the search.
+    fn find(&self, start: u64, end: u64, needle: &[u8], mask: Option<&[u8]>) -> Option<u64> {
+        if needle.is_empty() || mask.is_some_and(|m| m.len() != needle.len()) {
+            return None;
+        }
+        let end = end.min(self.max_address().unwrap_or(end));
+
+        let mut search = FindSearch {
+            needle,
+            mask,
+            window: Vec::new(),
+            window_addr: start,
+        };
+        let mut chunk_addr = start;
+        while chunk_addr < end {
+            let chunk_end = (chunk_addr & !(FIND_CHUNK_SIZE - 1))
+                .saturating_add(FIND_CHUNK_SIZE)
+                .min(end);
+            if let Some(addr) = search.scan(self, chunk_addr, chunk_end) {
+                return Some(addr);
+            }
+            chunk_addr = chunk_end;
+        }
+
+        None
+    }
+
     fn write_u8(&mut self, addr: &mut u64, value: u8) -> Result<(), MemViewError> {
         let v = [value];
         self.write_bytes(addr, &v)
@@ -152,27 +192,27 @@ pub trait MemView {
 
     fn write_u16(&mut self, addr: &mut u64, value: u16, endian: Endianness) -> Result<(), MemViewError> {
         let v = if endian == Endianness::LittleEndian {
-            u16::to_be_bytes(value)
-        } else {
             u16::to_le_bytes(value)
+        } else {
+            u16::to_be_bytes(value)
         };
         self.write_bytes(addr, &v)
     }
 
     fn write_u32(&mut self, addr: &mut u64, value: u32, endian: Endianness) -> Result<(), MemViewError> {
         let v = if endian == Endianness::LittleEndian {
-            u32::to_be_bytes(value)
-        } else {
             u32::to_le_bytes(value)
+        } else {
+            u32::to_be_bytes(value)
         };
         self.write_bytes(addr, &v)
     }
 
     fn write_u64(&mut self, addr: &mut u64, value: u64, endian: Endianness) -> Result<(), MemViewError> {
         let v = if endian == Endianness::LittleEndian {
-            u64::to_be_bytes(value)
-        } else {
             u64::to_le_bytes(value)
+        } else {
+            u64::to_be_bytes(value)
         };
         self.write_bytes(addr, &v)
     }
@@ -184,50 +224,138 @@ pub trait MemView {
 
     fn write_i16(&mut self, addr: &mut u64, value: i16, endian: Endianness) -> Result<(), MemViewError> {
         let v = if endian == Endianness::LittleEndian {
-            i16::to_be_bytes(value)
-        } else {
             i16::to_le_bytes(value)
+        } else {
+            i16::to_be_bytes(value)
         };
         self.write_bytes(addr, &v)
     }
 
     fn write_i32(&mut self, addr: &mut u64, value: i32, endian: Endianness) -> Result<(), MemViewError> {
         let v = if endian == Endianness::LittleEndian {
-            i32::to_be_bytes(value)
-        } else {
             i32::to_le_bytes(value)
+        } else {
+            i32::to_be_bytes(value)
         };
         self.write_bytes(addr, &v)
     }
 
     fn write_i64(&mut self, addr: &mut u64, value: i64, endian: Endianness) -> Result<(), MemViewError> {
         let v = if endian == Endianness::LittleEndian {
-            i64::to_be_bytes(value)
-        } else {
             i64::to_le_bytes(value)
+        } else {
+            i64::to_be_bytes(value)
         };
         self.write_bytes(addr, &v)
     }
 
     fn write_f32(&mut self, addr: &mut u64, value: f32, endian: Endianness) -> Result<(), MemViewError> {
         let v = if endian == Endianness::LittleEndian {
-            f32::to_be_bytes(value)
-        } else {
             f32::to_le_bytes(value)
+        } else {
+            f32::to_be_bytes(value)
         };
         self.write_bytes(addr, &v)
     }
 
     fn write_f64(&mut self, addr: &mut u64, value: f64, endian: Endianness) -> Result<(), MemViewError> {
         let v = if endian == Endianness::LittleEndian {
-            f64::to_be_bytes(value)
-        } else {
             f64::to_le_bytes(value)
+        } else {
+            f64::to_be_bytes(value)
         };
         self.write_bytes(addr, &v)
     }
 }
 
+// what find has read but not ruled out yet. a match can start in one chunk
+// and end in the next, so the end of each chunk is carried over.
+struct FindSearch<'a> {
+    needle: &'a [u8],
+    mask: Option<&'a [u8]>,
+    window: Vec<u8>,
+    window_addr: u64,
+}
+
+impl FindSearch<'_> {
+    fn scan<M: MemView + ?Sized>(&mut self, mem_view: &M, start: u64, end: u64) -> Option<u64> {
+        if let Some(data) = read_range(mem_view, start, end) {
+            return self.push(&data, end);
+        }
+
+        // part of the chunk might still be mapped, like when a mapping starts
+        // or ends inside it. whatever can be read at either end is searched,
+        // anything unreadable in the middle is skipped.
+        let len = end - start;
+        let head_len = readable_len(len - 1, |n| read_range(mem_view, start, start + n).is_some());
+        let tail_len = readable_len(len - head_len - 1, |n| read_range(mem_view, end - n, end).is_some());
+
+        let found = read_range(mem_view, start, start + head_len)
+            .filter(|d| !d.is_empty())
+            .and_then(|data| self.push(&data, start + head_len));
+        if found.is_some() {
+            return found;
+        }
+
+        self.window.clear();
+        self.window_addr = end - tail_len;
+        match read_range(mem_view, end - tail_len, end).filter(|d| !d.is_empty()) {
+            Some(data) => self.push(&data, end),
+            None => {
+                self.window_addr = end;
+                None
+            }
+        }
+    }
+
+    fn push(&mut self, data: &[u8], end: u64) -> Option<u64> {
+        self.window.extend_from_slice(data);
+        if let Some(pos) = find_masked(&self.window, self.needle, self.mask) {
+            return Some(self.window_addr + pos as u64);
+        }
+
+        let keep = (self.needle.len() - 1).min(self.window.len());
+        self.window.drain(..self.window.len() - keep);
+        self.window_addr = end - keep as u64;
+        None
+    }
+}
+
+fn read_range<M: MemView + ?Sized>(mem_view: &M, start: u64, end: u64) -> Option<Vec<u8>> {
+    let len = (end - start) as usize;
+    let mut data = vec![0u8; len];
+    let mut read_addr = start;
+    mem_view.read_bytes(&mut read_addr, &mut data, len as i32).ok()?;
+    Some(data)
+}
+
+// the most bytes up to max that can be read, if being able to read n bytes
+// means every smaller n can be read too
+fn readable_len(max: u64, can_read: impl Fn(u64) -> bool) -> u64 {
+    if max == 0 || !can_read(1) {
+        return 0;
+    }
+
+    let mut lo = 1;
+    let mut hi = max + 1;
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        if can_read(mid) {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
+
+fn find_masked(haystack: &[u8], needle: &[u8], mask: Option<&[u8]>) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| match mask {
+        Some(mask) => w.iter().zip(needle).zip(mask).all(|((h, n), m)| h & m == n & m),
+        None => w == needle,
+    })
+}
+
 pub struct StaticMemView {
     data: Vec<u8>,
 }
@@ -243,7 +371,7 @@ impl MemView for StaticMemView {
         let data_len = self.data.len();
         let addr_val = *addr as usize;
         let addr_end_val = addr_val + count as usize;
-        if addr_end_val >= data_len {
+        if addr_end_val > data_len {
             return Err(MemViewError::EndOfStream);
         }
 
@@ -257,7 +385,7 @@ impl MemView for StaticMemView {
         let count = value.len();
         let addr_val = *addr as usize;
         let addr_end_val = addr_val + count as usize;
-        if addr_end_val >= data_len {
+        if addr_end_val > data_len {
             return Err(MemViewError::EndOfStream);
         }
 
@@ -278,3 +406,177 @@ impl MemView for StaticMemView {
         true
     }
 }
+
+// len bytes that all read as zero, without storing any of them. for regions
+// like .bss that take up address space but have no data behind them.
+pub struct ZeroMemView {
+    len: u64,
+}
+
+impl ZeroMemView {
+    pub fn new(len: u64) -> ZeroMemView {
+        ZeroMemView { len }
+    }
+}
+
+impl MemView for ZeroMemView {
+    fn read_bytes(&self, addr: &mut u64, out_data: &mut [u8], count: i32) -> Result<(), MemViewError> {
+        if count < 0 {
+            return Err(MemViewError::InvalidParameter);
+        }
+        if addr.checked_add(count as u64).is_none_or(|end| end > self.len) {
+            return Err(MemViewError::EndOfStream);
+        }
+
+        *addr += count as u64;
+        out_data[..count as usize].fill(0);
+        Ok(())
+    }
+
+    fn max_address(&self) -> Result<u64, MemViewError> {
+        Ok(self.len)
+    }
+
+    fn can_read_while_running(&self) -> bool {
+        true
+    }
+
+    fn can_write_while_running(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_view_reads_zeros_up_to_its_length() {
+        let mv = ZeroMemView::new(1 << 40);
+        let mut data = [0xffu8; 4];
+        let mut addr = (1 << 40) - 4;
+        mv.read_bytes(&mut addr, &mut data, 4).unwrap();
+        assert_eq!(data, [0u8; 4]);
+        assert_eq!(addr, 1 << 40);
+        assert_eq!(mv.read_u8(&mut addr), Err(MemViewError::EndOfStream));
+        assert_eq!(
+            ZeroMemView::new(4).write_bytes(&mut 0, &[1]),
+            Err(MemViewError::ReadOnly)
+        );
+    }
+
+    #[test]
+    fn typed_reads_advance_and_respect_endianness() {
+        let mv = StaticMemView::new(vec![0x01, 0x02, 0xff, 0xfe, 0x00, 0x00, 0x80, 0x3f]);
+        let mut addr = 0u64;
+        assert_eq!(mv.read_u8(&mut addr).unwrap(), 0x01);
+        assert_eq!(mv.read_i8(&mut addr).unwrap(), 0x02);
+        assert_eq!(mv.read_i16(&mut addr, Endianness::LittleEndian).unwrap(), -257);
+        assert_eq!(mv.read_f32(&mut addr, Endianness::LittleEndian).unwrap(), 1.0);
+        assert_eq!(addr, 8);
+
+        addr = 0;
+        assert_eq!(mv.read_u16(&mut addr, Endianness::BigEndian).unwrap(), 0x0102);
+        assert_eq!(mv.read_u16(&mut addr, Endianness::LittleEndian).unwrap(), 0xfeff);
+
+        addr = 6;
+        assert_eq!(
+            mv.read_u32(&mut addr, Endianness::LittleEndian),
+            Err(MemViewError::EndOfStream)
+        );
+    }
+
+    #[test]
+    fn find_with_and_without_mask() {
+        // spread over a chunk boundary so the carried over tail gets used
+        let mut data = vec![0u8; 0x1800];
+        data[0xffe..0x1002].copy_from_slice(&[0x48, 0x8b, 0x05, 0x10]);
+        data[0x1400..0x1404].copy_from_slice(&[0x48, 0x8b, 0x0d, 0x10]);
+        let mv = StaticMemView::new(data);
+
+        assert_eq!(mv.find(0, 0x1800, &[0x48, 0x8b, 0x05], None), Some(0xffe));
+        assert_eq!(mv.find(0xfff, 0x1800, &[0x48, 0x8b, 0x05], None), None);
+        assert_eq!(
+            mv.find(
+                0xfff,
+                0x1800,
+                &[0x48, 0x8b, 0x00, 0x10],
+                Some(&[0xff, 0xff, 0x00, 0xff])
+            ),
+            Some(0x1400)
+        );
+        // has to fit before end
+        assert_eq!(mv.find(0x1000, 0x1403, &[0x48, 0x8b, 0x0d, 0x10], None), None);
+        // past the end of the view is just not found
+        assert_eq!(mv.find(0x1000, 0x3000, &[0x11], None), None);
+        assert_eq!(mv.find(0, 0x1800, &[0x48], Some(&[0xff, 0xff])), None);
+    }
+
+    // reads fail if they touch [gap_start, gap_end)
+    struct GappedMemView {
+        inner: StaticMemView,
+        gap_start: u64,
+        gap_end: u64,
+    }
+
+    impl MemView for GappedMemView {
+        fn read_bytes(&self, addr: &mut u64, out_data: &mut [u8], count: i32) -> Result<(), MemViewError> {
+            let end_addr = *addr + count as u64;
+            if *addr < self.gap_end && end_addr > self.gap_start {
+                return Err(MemViewError::NotLoaded);
+            }
+            self.inner.read_bytes(addr, out_data, count)
+        }
+
+        fn max_address(&self) -> Result<u64, MemViewError> {
+            self.inner.max_address()
+        }
+
+        fn can_read_while_running(&self) -> bool {
+            true
+        }
+
+        fn can_write_while_running(&self) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn find_searches_readable_parts_of_chunks() {
+        let mut data = vec![0u8; 0x4000];
+        // right before the gap, in a chunk that can't be read all at once
+        data[0x17fc..0x1800].copy_from_slice(&[0x11, 0x22, 0x33, 0x44]);
+        // right after the gap
+        data[0x2400..0x2404].copy_from_slice(&[0x55, 0x66, 0x77, 0x88]);
+        // from the readable end of that chunk into the next one
+        data[0x2ffe..0x3002].copy_from_slice(&[0x99, 0xaa, 0xbb, 0xcc]);
+        let mv = GappedMemView {
+            inner: StaticMemView::new(data),
+            gap_start: 0x1800,
+            gap_end: 0x2400,
+        };
+
+        assert_eq!(mv.find(0, 0x4000, &[0x11, 0x22, 0x33, 0x44], None), Some(0x17fc));
+        assert_eq!(mv.find(0, 0x4000, &[0x55, 0x66, 0x77, 0x88], None), Some(0x2400));
+        assert_eq!(mv.find(0, 0x4000, &[0x99, 0xaa, 0xbb, 0xcc], None), Some(0x2ffe));
+        // the gap in between doesn't get joined over
+        assert_eq!(mv.find(0, 0x4000, &[0x33, 0x44, 0x55, 0x66], None), None);
+    }
+
+    #[test]
+    fn typed_writes_round_trip() {
+        let mut mv = StaticMemView::new(vec![0u8; 16]);
+        let mut addr = 0u64;
+        mv.write_u32(&mut addr, 0x11223344, Endianness::LittleEndian).unwrap();
+        mv.write_i16(&mut addr, -2, Endianness::BigEndian).unwrap();
+        mv.write_f64(&mut addr, -0.5, Endianness::BigEndian).unwrap();
+        assert_eq!(addr, 14);
+
+        addr = 0;
+        let mut raw = [0u8; 4];
+        mv.read_bytes(&mut addr, &mut raw, 4).unwrap();
+        assert_eq!(raw, [0x44, 0x33, 0x22, 0x11]);
+        assert_eq!(mv.read_i16(&mut addr, Endianness::BigEndian).unwrap(), -2);
+        assert_eq!(mv.read_f64(&mut addr, Endianness::BigEndian).unwrap(), -0.5);
+    }
+}
@@ -0,0 +1,158 @@
+use crate::{
+    consts::arch::Endianness,
+    memory::memview::{MemView, MemViewError},
+};
+
+// a cursor over a memview so parsers don't need to pass `&mut at` around
+// everywhere. the position only moves forward on a successful read.
+pub struct MemViewReader<'a> {
+    mem_view: &'a dyn MemView,
+    pos: u64,
+    endian: Endianness,
+}
+
+impl<'a> MemViewReader<'a> {
+    pub fn new(mem_view: &'a dyn MemView, pos: u64, endian: Endianness) -> MemViewReader<'a> {
+        MemViewReader { mem_view, pos, endian }
+    }
+
+    pub fn tell(&self) -> u64 {
+        self.pos
+    }
+
+    pub fn seek(&mut self, pos: u64) {
+        self.pos = pos;
+    }
+
+    pub fn skip(&mut self, count: u64) -> Result<(), MemViewError> {
+        self.pos = self.pos.checked_add(count).ok_or(MemViewError::EndOfStream)?;
+        Ok(())
+    }
+
+    pub fn endian(&self) -> Endianness {
+        self.endian
+    }
+
+    pub fn set_endian(&mut self, endian: Endianness) {
+        self.endian = endian;
+    }
+
+    pub fn read_bytes(&mut self, count: usize) -> Result<Vec<u8>, MemViewError> {
+        if count > i32::MAX as usize {
+            return Err(MemViewError::InvalidParameter);
+        }
+
+        let mut data = vec![0u8; count];
+        self.read_into(&mut data)?;
+        Ok(data)
+    }
+
+    pub fn read_into(&mut self, out_data: &mut [u8]) -> Result<(), MemViewError> {
+        let mut at = self.pos;
+        self.mem_view.read_bytes(&mut at, out_data, out_data.len() as i32)?;
+        self.pos = at;
+        Ok(())
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, MemViewError> {
+        let mut at = self.pos;
+        let value = self.mem_view.read_u8(&mut at)?;
+        self.pos = at;
+        Ok(value)
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, MemViewError> {
+        let mut at = self.pos;
+        let value = self.mem_view.read_u16(&mut at, self.endian)?;
+        self.pos = at;
+        Ok(value)
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, MemViewError> {
+        let mut at = self.pos;
+        let value = self.mem_view.read_u32(&mut at, self.endian)?;
+        self.pos = at;
+        Ok(value)
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, MemViewError> {
+        let mut at = self.pos;
+        let value = self.mem_view.read_u64(&mut at, self.endian)?;
+        self.pos = at;
+        Ok(value)
+    }
+
+    pub fn read_i8(&mut self) -> Result<i8, MemViewError> {
+        Ok(self.read_u8()? as i8)
+    }
+
+    pub fn read_i16(&mut self) -> Result<i16, MemViewError> {
+        Ok(self.read_u16()? as i16)
+    }
+
+    pub fn read_i32(&mut self) -> Result<i32, MemViewError> {
+        Ok(self.read_u32()? as i32)
+    }
+
+    pub fn read_i64(&mut self) -> Result<i64, MemViewError> {
+        Ok(self.read_u64()? as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::memview::StaticMemView;
+
+    fn make_mem_view() -> StaticMemView {
+        StaticMemView::new(vec![0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a])
+    }
+
+    #[test]
+    fn reads_advance_position() {
+        let mv = make_mem_view();
+        let mut reader = MemViewReader::new(&mv, 0, Endianness::LittleEndian);
+
+        assert_eq!(reader.read_u8().unwrap(), 0x01);
+        assert_eq!(reader.tell(), 1);
+        assert_eq!(reader.read_u16().unwrap(), 0x0302);
+        assert_eq!(reader.tell(), 3);
+        assert_eq!(reader.read_u32().unwrap(), 0x07060504);
+        assert_eq!(reader.tell(), 7);
+        assert_eq!(reader.read_bytes(3).unwrap(), vec![0x08, 0x09, 0x0a]);
+        assert_eq!(reader.tell(), 10);
+    }
+
+    #[test]
+    fn endianness_is_stateful() {
+        let mv = make_mem_view();
+        let mut reader = MemViewReader::new(&mv, 0, Endianness::BigEndian);
+
+        assert_eq!(reader.read_u16().unwrap(), 0x0102);
+        reader.set_endian(Endianness::LittleEndian);
+        assert_eq!(reader.read_u16().unwrap(), 0x0403);
+        reader.seek(0);
+        assert_eq!(reader.read_u64().unwrap(), 0x0807060504030201);
+    }
+
+    #[test]
+    fn seek_and_skip() {
+        let mv = make_mem_view();
+        let mut reader = MemViewReader::new(&mv, 4, Endianness::LittleEndian);
+
+        reader.skip(2).unwrap();
+        assert_eq!(reader.read_u8().unwrap(), 0x07);
+        reader.seek(1);
+        assert_eq!(reader.read_i8().unwrap(), 0x02);
+    }
+
+    #[test]
+    fn failed_read_keeps_position() {
+        let mv = make_mem_view();
+        let mut reader = MemViewReader::new(&mv, 8, Endianness::LittleEndian);
+
+        assert!(reader.read_u32().is_err());
+        assert_eq!(reader.tell(), 8);
+        assert_eq!(reader.read_u16().unwrap(), 0x0a09);
+    }
+}
@@ -0,0 +1,203 @@
+use crate::{
+    debugger::debugger::{Debugger, DebuggerError, DebuggerThreadIndex},
+    memory::memview::{MemView, MemViewError},
+};
+use std::collections::BTreeMap;
+
+// a view with patched bytes on top of another view. writes only go into the
+// overlay, so the view underneath never changes until commit is called. good
+// for disassembling or emulating what code would do after a patch.
+pub struct OverlayMemView<'a> {
+    base: &'a dyn MemView,
+    patches: BTreeMap<u64, u8>,
+}
+
+impl<'a> OverlayMemView<'a> {
+    pub fn new(base: &'a dyn MemView) -> OverlayMemView<'a> {
+        OverlayMemView {
+            base,
+            patches: BTreeMap::new(),
+        }
+    }
+
+    pub fn is_patched(&self, addr: u64) -> bool {
+        self.patches.contains_key(&addr)
+    }
+
+    pub fn patch_count(&self) -> usize {
+        self.patches.len()
+    }
+
+    // drops every patch in [addr, addr + len)
+    pub fn revert(&mut self, addr: u64, len: u64) {
+        let end_addr = addr.saturating_add(len);
+        let reverted: Vec<u64> = self.patches.range(addr..end_addr).map(|(a, _)| *a).collect();
+        for patch_addr in reverted {
+            self.patches.remove(&patch_addr);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.patches.clear();
+    }
+
+    // patches as (address, bytes), merging ones that are next to each other
+    pub fn patch_runs(&self) -> Vec<(u64, Vec<u8>)> {
+        let mut runs: Vec<(u64, Vec<u8>)> = Vec::new();
+        for (addr, value) in &self.patches {
+            match runs.last_mut() {
+                Some((run_addr, run_bytes)) if *run_addr + run_bytes.len() as u64 == *addr => run_bytes.push(*value),
+                _ => runs.push((*addr, vec![*value])),
+            }
+        }
+        runs
+    }
+
+    // writes every patch to the real target. the overlay is left as is, so
+    // call clear afterwards if it shouldn't be applied again.
+    pub fn commit(&self, dbg: &dyn Debugger, thread_idx: DebuggerThreadIndex) -> Result<(), DebuggerError> {
+        for (addr, bytes) in self.patch_runs() {
+            dbg.write_bytes(thread_idx, addr, &bytes)?;
+        }
+        Ok(())
+    }
+}
+
+impl MemView for OverlayMemView<'_> {
+    fn read_bytes(&self, addr: &mut u64, out_data: &mut [u8], count: i32) -> Result<(), MemViewError> {
+        if count < 0 || count as usize > out_data.len() {
+            return Err(MemViewError::InvalidParameter);
+        }
+
+        let count = count as usize;
+        let start_addr = *addr;
+        let end_addr = start_addr
+            .checked_add(count as u64)
+            .ok_or(MemViewError::InvalidParameter)?;
+
+        let mut base_addr = start_addr;
+        if let Err(e) = self.base.read_bytes(&mut base_addr, out_data, count as i32) {
+            // fine as long as the patches cover everything
+            let patched_count = self.patches.range(start_addr..end_addr).count();
+            if patched_count != count {
+                return Err(e);
+            }
+        }
+
+        for (patch_addr, value) in self.patches.range(start_addr..end_addr) {
+            out_data[(patch_addr - start_addr) as usize] = *value;
+        }
+
+        *addr = end_addr;
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, addr: &mut u64, value: &[u8]) -> Result<(), MemViewError> {
+        let start_addr = *addr;
+        start_addr
+            .checked_add(value.len() as u64)
+            .ok_or(MemViewError::InvalidParameter)?;
+
+        for (i, byte) in value.iter().enumerate() {
+            self.patches.insert(start_addr + i as u64, *byte);
+        }
+
+        *addr += value.len() as u64;
+        Ok(())
+    }
+
+    fn max_address(&self) -> Result<u64, MemViewError> {
+        self.base.max_address()
+    }
+
+    fn can_read_while_running(&self) -> bool {
+        self.base.can_read_while_running()
+    }
+
+    // nothing underneath is touched
+    fn can_write_while_running(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        debugger::mock_debugger::{MockStepMode, mock_x86_64},
+        memory::memview::StaticMemView,
+        sleigh::disasm::x86_64_disasm,
+    };
+
+    #[test]
+    fn patched_byte_changes_disassembly() {
+        let disasm = x86_64_disasm();
+        // push rbp; ret
+        let base = StaticMemView::new(vec![0x55, 0xc3]);
+        let mut overlay = OverlayMemView::new(&base);
+
+        // push rbx instead
+        overlay.write_u8(&mut 0, 0x53).unwrap();
+        assert_eq!(disasm.disasm_display(&base, 0).unwrap().text, "PUSH RBP");
+        assert_eq!(disasm.disasm_display(&overlay, 0).unwrap().text, "PUSH RBX");
+        assert_eq!(disasm.disasm_display(&overlay, 1).unwrap().text, "RET");
+
+        let mut addr = 0;
+        let mut base_bytes = [0u8; 2];
+        base.read_bytes(&mut addr, &mut base_bytes, 2).unwrap();
+        assert_eq!(base_bytes, [0x55, 0xc3]);
+
+        overlay.revert(0, 1);
+        assert_eq!(disasm.disasm_display(&overlay, 0).unwrap().text, "PUSH RBP");
+    }
+
+    #[test]
+    fn reads_past_the_base_need_full_patches() {
+        let base = StaticMemView::new(vec![0x11, 0x22]);
+        let mut overlay = OverlayMemView::new(&base);
+        overlay.write_bytes(&mut 2, &[0x33]).unwrap();
+
+        let mut out = [0u8; 1];
+        overlay.read_bytes(&mut 2, &mut out, 1).unwrap();
+        assert_eq!(out, [0x33]);
+
+        let mut out = [0u8; 2];
+        assert_eq!(overlay.read_bytes(&mut 2, &mut out, 2), Err(MemViewError::EndOfStream));
+    }
+
+    #[test]
+    fn count_larger_than_buffer_is_rejected() {
+        let base = StaticMemView::new(vec![0x11, 0x22, 0x33, 0x44]);
+        let mut overlay = OverlayMemView::new(&base);
+        overlay.write_u8(&mut 1, 0xaa).unwrap();
+
+        let mut addr = 0;
+        let mut out = [0u8; 2];
+        assert_eq!(
+            overlay.read_bytes(&mut addr, &mut out, 4),
+            Err(MemViewError::InvalidParameter)
+        );
+        assert_eq!(
+            overlay.read_bytes(&mut addr, &mut out, -1),
+            Err(MemViewError::InvalidParameter)
+        );
+        assert_eq!(addr, 0);
+        assert_eq!(out, [0, 0]);
+    }
+
+    #[test]
+    fn commit_writes_runs_to_debugger() {
+        let dbg = mock_x86_64(vec![0u8; 8], MockStepMode::Fixed(1));
+
+        let base = StaticMemView::new(vec![0u8; 8]);
+        let mut overlay = OverlayMemView::new(&base);
+        overlay.write_bytes(&mut 1, &[0xaa, 0xbb]).unwrap();
+        overlay.write_u8(&mut 6, 0xcc).unwrap();
+        assert_eq!(overlay.patch_runs(), vec![(1, vec![0xaa, 0xbb]), (6, vec![0xcc])]);
+
+        overlay.commit(&dbg, DebuggerThreadIndex::Current).unwrap();
+        let mut mem = [0u8; 8];
+        dbg.read_bytes(DebuggerThreadIndex::Current, 0, &mut mem).unwrap();
+        assert_eq!(mem, [0x00, 0xaa, 0xbb, 0x00, 0x00, 0x00, 0xcc, 0x00]);
+    }
+}
@@ -3,6 +3,7 @@ use crate::{
     shared::fast_util::nibble_to_u8_fast,
 };
 use crossbeam::{channel::unbounded, select};
+use log::debug;
 use std::{
     io::{BufReader, Error, Read, Write},
     net::{TcpListener, TcpStream},
@@ -227,7 +228,7 @@ impl<DBG: Debugger + Send + Sync + 'static> GdbServer<DBG> {
                 recv(dbg_rx) -> msg => {
                     // todo: everything
                     let msg = msg.unwrap();
-                    println!("{}", msg.kind);
+                    debug!("debugger event: {}", msg.kind);
                     //self.send_or_print_err(&mut stream_write_copy, format!("{}", msg.kind));
                 }
                 recv(cli_rx) -> msg => {
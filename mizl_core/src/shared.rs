@@ -1 +1,2 @@
+pub mod demangle;
 pub mod fast_util;
@@ -0,0 +1,28 @@
+// turns mangled symbol names (itanium c++ and rust) into something readable.
+// anything we can't demangle is returned as-is, so this is always safe to call
+// on symbol names for display.
+
+#[cfg(feature = "demangle")]
+pub fn demangle(name: &str) -> String {
+    // rust first since legacy rust symbols are also valid itanium symbols,
+    // and the c++ demangler would leave the hash suffix in the output
+    if let Ok(v) = rustc_demangle::try_demangle(name) {
+        return format!("{:#}", v);
+    }
+
+    if name.starts_with("_Z") {
+        if let Ok(sym) = cpp_demangle::Symbol::new(name) {
+            if let Ok(v) = sym.demangle(&cpp_demangle::DemangleOptions::default()) {
+                return v;
+            }
+        }
+    }
+
+    name.to_string()
+}
+
+// without the demangle feature, names are left alone
+#[cfg(not(feature = "demangle"))]
+pub fn demangle(name: &str) -> String {
+    name.to_string()
+}
@@ -34,3 +34,38 @@ pub fn nibble_to_u8_fast(c: u8) -> Option<u8> {
         _ => None,
     }
 }
+
+// get_unchecked for hot paths that rely on an invariant set up somewhere else.
+// debug builds still check the index so a broken invariant (like a malformed
+// sla file) panics in tests instead of reading out of bounds.
+//
+// safety: idx must be less than slice.len()
+#[inline(always)]
+pub unsafe fn get_debug_checked<T>(slice: &[T], idx: usize) -> &T {
+    debug_assert!(
+        idx < slice.len(),
+        "index {} out of bounds for length {}",
+        idx,
+        slice.len()
+    );
+    unsafe { slice.get_unchecked(idx) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_checked_in_bounds() {
+        let values = [1, 2, 3];
+        assert_eq!(unsafe { *get_debug_checked(&values, 2) }, 3);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "out of bounds")]
+    fn debug_checked_out_of_bounds_panics() {
+        let values = [1, 2, 3];
+        unsafe { get_debug_checked(&values, 3) };
+    }
+}
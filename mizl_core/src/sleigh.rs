@@ -3,8 +3,12 @@ pub mod consts;
 pub mod cspec_file;
 pub mod decision;
 pub mod disasm;
+pub mod disasm_cache;
+pub mod disasm_trace;
 pub mod expression;
+pub mod instruction_model;
 pub mod memory;
+pub mod pcode;
 pub mod pspec_file;
 pub mod sla_file;
 pub mod sla_reader;
@@ -1,6 +1,8 @@
 use crate::sleigh::consts::{AttributeId, ElementId};
 use crate::sleigh::expression::Expression;
+use crate::sleigh::sla_file::SleighError;
 use crate::sleigh::sla_reader::{SlaBinReader, SlaElement, SpaceInfo};
+use num::FromPrimitive;
 
 pub enum ConstructorPrintElement {
     Operand(i32),
@@ -85,11 +87,14 @@ pub struct ConstTpl {
     pub const_type: ConstTplType,
     pub value_spaceid: Option<SpaceInfo>,
     pub value_real: u64,
+    // only used by handle consts: which operand and which part of it
+    pub handle_index: i16,
+    pub select: ConstTplHandleType,
 }
 
 impl ConstTpl {
-    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> ConstTpl {
-        reader.seek_elem_children_start(elem);
+    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Result<ConstTpl, SleighError> {
+        reader.seek_elem_children_start(elem)?;
 
         let const_type = match elem.id {
             ElementId::ConstReal => ConstTplType::Real,
@@ -105,7 +110,7 @@ impl ConstTpl {
             ElementId::ConstFlowrefSize => ConstTplType::JFlowrefSize,
             ElementId::ConstFlowdest => ConstTplType::JFlowdest,
             ElementId::ConstFlowdestSize => ConstTplType::JFlowdestSize,
-            _ => panic!("unsupported const template type"),
+            _ => return Err(SleighError::DecodeFailed),
         };
         let res: ConstTpl;
         if const_type == ConstTplType::Real || const_type == ConstTplType::JRelative {
@@ -114,12 +119,15 @@ impl ConstTpl {
                 const_type,
                 value_spaceid: None,
                 value_real: value,
+                handle_index: 0,
+                select: ConstTplHandleType::Space,
             };
         } else if const_type == ConstTplType::Handle {
-            _ = elem.as_int_or(AttributeId::Val, 0) as i16; // handle_index
-            let select = elem.as_int_or(AttributeId::S, 0) as i16;
+            let handle_index = elem.as_int_or(AttributeId::Val, 0) as i16;
+            let select =
+                ConstTplHandleType::from_i64(elem.as_int_or(AttributeId::S, 0)).ok_or(SleighError::DecodeFailed)?;
             let value: u64;
-            if select == ConstTplHandleType::OffsetPlus as i16 {
+            if select == ConstTplHandleType::OffsetPlus {
                 value = elem.as_uint_or(AttributeId::Plus, 0);
             } else {
                 value = 0;
@@ -128,6 +136,8 @@ impl ConstTpl {
                 const_type,
                 value_spaceid: None,
                 value_real: value,
+                handle_index,
+                select,
             };
         } else if const_type == ConstTplType::Spaceid {
             let value = elem.as_space(AttributeId::Space);
@@ -135,91 +145,98 @@ impl ConstTpl {
                 const_type,
                 value_spaceid: Some(value),
                 value_real: 0,
+                handle_index: 0,
+                select: ConstTplHandleType::Space,
             }
         } else {
             res = ConstTpl {
                 const_type,
                 value_spaceid: None,
                 value_real: 0,
+                handle_index: 0,
+                select: ConstTplHandleType::Space,
             }
         }
 
-        reader.read_elem_end(elem.id);
-        return res;
+        reader.read_elem_end(elem.id)?;
+        return Ok(res);
     }
 }
 
 impl VarNodeTpl {
-    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> VarNodeTpl {
-        reader.seek_elem_children_start(elem);
+    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Result<VarNodeTpl, SleighError> {
+        reader.seek_elem_children_start(elem)?;
 
-        let mut child_iter = reader.read_elem_children(elem.epos);
-        let space_elem = child_iter.next().expect("space template missing");
-        let space = ConstTpl::new(reader, &space_elem);
-        let offset_elem = child_iter.next().expect("offset template missing");
-        let offset = ConstTpl::new(reader, &offset_elem);
-        let size_elem = child_iter.next().expect("size template missing");
-        let size = ConstTpl::new(reader, &size_elem);
-        assert!(child_iter.next().is_none());
+        let mut child_iter = reader.read_elem_children(elem.epos)?;
+        let space_elem = SlaBinReader::next_child(&mut child_iter)?;
+        let space = ConstTpl::new(reader, &space_elem)?;
+        let offset_elem = SlaBinReader::next_child(&mut child_iter)?;
+        let offset = ConstTpl::new(reader, &offset_elem)?;
+        let size_elem = SlaBinReader::next_child(&mut child_iter)?;
+        let size = ConstTpl::new(reader, &size_elem)?;
+        if child_iter.next().is_some() {
+            return Err(SleighError::DecodeFailed);
+        }
 
-        reader.read_elem_end(elem.id);
-        VarNodeTpl { space, offset, size }
+        reader.read_elem_end(elem.id)?;
+        Ok(VarNodeTpl { space, offset, size })
     }
 }
 
 impl OpTpl {
-    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> OpTpl {
+    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Result<OpTpl, SleighError> {
         let code = elem.as_int_or(AttributeId::Code, -1) as i32;
-        reader.seek_elem_children_start(elem);
+        reader.seek_elem_children_start(elem)?;
 
         let mut input: Vec<VarNodeTpl> = Vec::new();
         let mut result: Option<VarNodeTpl> = None;
-        for (i, child) in reader.read_elem_children(elem.epos).enumerate() {
+        for (i, child) in reader.read_elem_children(elem.epos)?.enumerate() {
+            let child = child?;
             if i == 0 {
                 if child.id == ElementId::Null {
-                    reader.read_elem_end(child.id);
+                    reader.read_elem_end(child.id)?;
                 } else if child.id == ElementId::VarnodeTpl {
-                    result = Some(VarNodeTpl::new(reader, &child));
+                    result = Some(VarNodeTpl::new(reader, &child)?);
                 } else {
-                    panic!("can't handle this result type");
+                    return Err(SleighError::DecodeFailed);
                 }
             } else {
                 if child.id == ElementId::VarnodeTpl {
-                    input.push(VarNodeTpl::new(reader, &child));
+                    input.push(VarNodeTpl::new(reader, &child)?);
                 } else {
-                    panic!("can't handle this input type");
+                    return Err(SleighError::DecodeFailed);
                 }
             }
         }
         input.shrink_to_fit();
 
-        reader.read_elem_end(elem.id);
-        OpTpl { code, result, input }
+        reader.read_elem_end(elem.id)?;
+        Ok(OpTpl { code, result, input })
     }
 }
 
 impl HandleTpl {
-    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> HandleTpl {
-        reader.seek_elem_children_start(elem);
+    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Result<HandleTpl, SleighError> {
+        reader.seek_elem_children_start(elem)?;
 
-        let mut child_iter = reader.read_elem_children(elem.epos);
-        let space_elem = child_iter.next().expect("space missing");
-        let space = ConstTpl::new(reader, &space_elem);
-        let size_elem = child_iter.next().expect("size missing");
-        let size = ConstTpl::new(reader, &size_elem);
-        let ptrspace_elem = child_iter.next().expect("ptrspace missing");
-        let ptrspace = ConstTpl::new(reader, &ptrspace_elem);
-        let ptroffset_elem = child_iter.next().expect("ptroffset missing");
-        let ptroffset = ConstTpl::new(reader, &ptroffset_elem);
-        let ptrsize_elem = child_iter.next().expect("ptrsize missing");
-        let ptrsize = ConstTpl::new(reader, &ptrsize_elem);
-        let temp_space_elem = child_iter.next().expect("temp_space missing");
-        let temp_space = ConstTpl::new(reader, &temp_space_elem);
-        let temp_offset_elem = child_iter.next().expect("temp_offset missing");
-        let temp_offset = ConstTpl::new(reader, &temp_offset_elem);
+        let mut child_iter = reader.read_elem_children(elem.epos)?;
+        let space_elem = SlaBinReader::next_child(&mut child_iter)?;
+        let space = ConstTpl::new(reader, &space_elem)?;
+        let size_elem = SlaBinReader::next_child(&mut child_iter)?;
+        let size = ConstTpl::new(reader, &size_elem)?;
+        let ptrspace_elem = SlaBinReader::next_child(&mut child_iter)?;
+        let ptrspace = ConstTpl::new(reader, &ptrspace_elem)?;
+        let ptroffset_elem = SlaBinReader::next_child(&mut child_iter)?;
+        let ptroffset = ConstTpl::new(reader, &ptroffset_elem)?;
+        let ptrsize_elem = SlaBinReader::next_child(&mut child_iter)?;
+        let ptrsize = ConstTpl::new(reader, &ptrsize_elem)?;
+        let temp_space_elem = SlaBinReader::next_child(&mut child_iter)?;
+        let temp_space = ConstTpl::new(reader, &temp_space_elem)?;
+        let temp_offset_elem = SlaBinReader::next_child(&mut child_iter)?;
+        let temp_offset = ConstTpl::new(reader, &temp_offset_elem)?;
 
-        reader.read_elem_end(elem.id);
-        HandleTpl {
+        reader.read_elem_end(elem.id)?;
+        Ok(HandleTpl {
             space,
             size,
             ptrspace,
@@ -227,86 +244,88 @@ impl HandleTpl {
             ptrsize,
             temp_space,
             temp_offset,
-        }
+        })
     }
 }
 
 impl ConstructorTpl {
-    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> ConstructorTpl {
+    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Result<ConstructorTpl, SleighError> {
         let labels = elem.as_int_or(AttributeId::Labels, 0) as i32;
         let section = elem.as_int_or(AttributeId::Section, -1) as i32;
-        reader.seek_elem_children_start(elem);
+        reader.seek_elem_children_start(elem)?;
 
         let mut result: Option<HandleTpl> = None;
         let mut op_tpls: Vec<OpTpl> = Vec::new();
-        for (i, child) in reader.read_elem_children(elem.epos).enumerate() {
+        for (i, child) in reader.read_elem_children(elem.epos)?.enumerate() {
+            let child = child?;
             if i == 0 {
                 if child.id == ElementId::Null {
-                    reader.read_elem_end(child.id);
+                    reader.read_elem_end(child.id)?;
                 } else if child.id == ElementId::HandleTpl {
-                    result = Some(HandleTpl::new(reader, &child));
+                    result = Some(HandleTpl::new(reader, &child)?);
                 } else {
-                    panic!("can't handle this result type");
+                    return Err(SleighError::DecodeFailed);
                 }
             } else {
                 if child.id == ElementId::OpTpl {
-                    op_tpls.push(OpTpl::new(reader, &child));
+                    op_tpls.push(OpTpl::new(reader, &child)?);
                 } else {
-                    panic!("can't handle this input type");
+                    return Err(SleighError::DecodeFailed);
                 }
             }
         }
         op_tpls.shrink_to_fit();
 
-        reader.read_elem_end(elem.id);
-        ConstructorTpl {
+        reader.read_elem_end(elem.id)?;
+        Ok(ConstructorTpl {
             labels,
             section,
             result,
             op_tpls,
-        }
+        })
     }
 }
 
 impl Constructor {
-    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Constructor {
+    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Result<Constructor, SleighError> {
         let parent = elem.as_uint_or(AttributeId::Parent, 0) as u32;
         let first = elem.as_int_or(AttributeId::First, 0) as i32;
         let length = elem.as_int_or(AttributeId::Length, 0) as i32;
         let source = elem.as_int_or(AttributeId::Source, 0) as i32;
         let line = elem.as_int_or(AttributeId::Line, 0) as i32;
-        reader.seek_elem_children_start(elem);
+        reader.seek_elem_children_start(elem)?;
 
         let mut operand_ids: Vec<u32> = Vec::new();
         let mut print_elements: Vec<ConstructorPrintElement> = Vec::new();
         let mut context_ops: Vec<ContextOpTpl> = Vec::new();
         let mut template: Option<ConstructorTpl> = None;
-        for child in reader.read_elem_children(elem.epos) {
+        for child in reader.read_elem_children(elem.epos)? {
+            let child = child?;
             if child.id == ElementId::Oper {
                 let operand_id = child.as_uint_or(AttributeId::Id, 0) as u32;
                 operand_ids.push(operand_id);
-                reader.read_elem_end(child.id);
+                reader.read_elem_end(child.id)?;
             } else if child.id == ElementId::Print {
                 let str = child.as_str_or(AttributeId::Piece, "");
                 print_elements.push(ConstructorPrintElement::Literal(str));
-                reader.read_elem_end(child.id);
+                reader.read_elem_end(child.id)?;
             } else if child.id == ElementId::Opprint {
                 let oper_index = child.as_int_or(AttributeId::Id, -1) as i32;
                 print_elements.push(ConstructorPrintElement::Operand(oper_index));
-                reader.read_elem_end(child.id);
+                reader.read_elem_end(child.id)?;
             } else if child.id == ElementId::ConstructTpl {
-                template = Some(ConstructorTpl::new(reader, &child));
+                template = Some(ConstructorTpl::new(reader, &child)?);
             } else if child.id == ElementId::ContextOp {
                 // todo
                 let i = child.as_int_or(AttributeId::I, 0) as i32;
                 let shift = child.as_int_or(AttributeId::Shift, 0) as i32;
                 let mask = child.as_uint_or(AttributeId::Mask, 0) as u32;
-                reader.seek_elem_children_start(&child);
+                reader.seek_elem_children_start(&child)?;
 
-                let mut ctx_child_iter = reader.read_elem_children(child.epos);
-                let local_exp_ele = ctx_child_iter.next().expect("context op expression missing");
-                let local_exp = Expression::new(reader, &local_exp_ele);
-                reader.read_elem_end(child.id);
+                let mut ctx_child_iter = reader.read_elem_children(child.epos)?;
+                let local_exp_ele = SlaBinReader::next_child(&mut ctx_child_iter)?;
+                let local_exp = Expression::new(reader, &local_exp_ele)?;
+                reader.read_elem_end(child.id)?;
                 context_ops.push(ContextOpTpl {
                     word_start: i,
                     bit_shift: shift,
@@ -314,12 +333,12 @@ impl Constructor {
                     expression: local_exp,
                 });
             } else {
-                panic!("unexpected child type while reading constructor");
+                return Err(SleighError::DecodeFailed);
             }
         }
 
-        reader.read_elem_end(elem.id);
-        Constructor {
+        reader.read_elem_end(elem.id)?;
+        Ok(Constructor {
             parent,
             first,
             min_length: length,
@@ -329,6 +348,6 @@ impl Constructor {
             print_elements,
             context_ops,
             template,
-        }
+        })
     }
 }
@@ -1,7 +1,7 @@
 use super::disasm::DisasmState;
 use crate::sleigh::consts::{AttributeId, ElementId};
+use crate::sleigh::sla_file::SleighError;
 use crate::sleigh::sla_reader::{SlaBinReader, SlaElement};
-use num_traits::pow;
 
 pub struct PatBlock {
     pub offset: i32,
@@ -78,35 +78,36 @@ pub struct Decision {
 }
 
 impl PatBlock {
-    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> PatBlock {
+    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Result<PatBlock, SleighError> {
         let offset = elem.as_int_or(AttributeId::Off, -1) as i32;
         let non_zero = elem.as_int_or(AttributeId::Nonzero, -1) as i32;
-        reader.seek_elem_children_start(elem);
+        reader.seek_elem_children_start(elem)?;
 
         let mut mask_value_pairs: Vec<(u32, u32)> = Vec::new();
-        for child in reader.read_elem_children(elem.epos) {
+        for child in reader.read_elem_children(elem.epos)? {
+            let child = child?;
             if child.id != ElementId::MaskWord {
-                panic!("only expected mask word in patblock's children");
+                return Err(SleighError::DecodeFailed);
             }
 
             let mask = child.as_uint_or(AttributeId::Mask, u32::MAX as u64) as u32;
             let val = child.as_uint_or(AttributeId::Val, u32::MAX as u64) as u32;
             mask_value_pairs.push((mask, val));
-            reader.read_elem_end(child.id);
+            reader.read_elem_end(child.id)?;
         }
 
-        reader.read_elem_end(elem.id);
-        PatBlock {
+        reader.read_elem_end(elem.id)?;
+        Ok(PatBlock {
             offset,
             non_zero,
             mask_value_pairs,
-        }
+        })
     }
 }
 
 impl DisjointPattern {
-    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> DisjointPattern {
-        reader.seek_elem_children_start(elem);
+    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Result<DisjointPattern, SleighError> {
+        reader.seek_elem_children_start(elem)?;
 
         let pat_type;
         let mut pat_blocks;
@@ -114,82 +115,90 @@ impl DisjointPattern {
             pat_blocks = Vec::with_capacity(2);
             pat_type = DisjointPatternType::CombinePattern;
 
-            let context_elem = reader.read_elem();
-            reader.seek_elem_children_start(&context_elem);
-            let context_pat_block_elem = reader.read_elem();
-            let ctx_pat_block = PatBlock::new(reader, &context_pat_block_elem);
+            let context_elem = reader.read_elem()?;
+            reader.seek_elem_children_start(&context_elem)?;
+            let context_pat_block_elem = reader.read_elem()?;
+            let ctx_pat_block = PatBlock::new(reader, &context_pat_block_elem)?;
             pat_blocks.push(ctx_pat_block);
-            reader.read_elem_end(context_elem.id);
+            reader.read_elem_end(context_elem.id)?;
 
-            let instruct_elem = reader.read_elem();
-            reader.seek_elem_children_start(&instruct_elem);
-            let instruct_pat_block_elem = reader.read_elem();
-            let ins_pat_block = PatBlock::new(reader, &instruct_pat_block_elem);
+            let instruct_elem = reader.read_elem()?;
+            reader.seek_elem_children_start(&instruct_elem)?;
+            let instruct_pat_block_elem = reader.read_elem()?;
+            let ins_pat_block = PatBlock::new(reader, &instruct_pat_block_elem)?;
             pat_blocks.push(ins_pat_block);
-            reader.read_elem_end(instruct_elem.id);
+            reader.read_elem_end(instruct_elem.id)?;
         } else {
-            let pat_block_elem = reader.read_elem();
+            let pat_block_elem = reader.read_elem()?;
             if pat_block_elem.id == ElementId::PatBlock {
                 pat_blocks = Vec::with_capacity(1);
                 pat_type = match elem.id {
                     ElementId::InstructPat => DisjointPatternType::InstructionPattern,
                     ElementId::ContextPat => DisjointPatternType::ContextPattern,
-                    _ => panic!("unsupported pattern type"),
+                    _ => return Err(SleighError::DecodeFailed),
                 };
-                let pat_block = PatBlock::new(reader, &pat_block_elem);
+                let pat_block = PatBlock::new(reader, &pat_block_elem)?;
                 pat_blocks.push(pat_block);
             } else {
-                panic!("expected disjoint pattern child to be a pat block");
+                return Err(SleighError::DecodeFailed);
             }
         }
 
-        reader.read_elem_end(elem.id);
-        DisjointPattern { pat_type, pat_blocks }
+        reader.read_elem_end(elem.id)?;
+        Ok(DisjointPattern { pat_type, pat_blocks })
     }
 }
 
 impl DecisionPair {
-    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> DecisionPair {
+    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Result<DecisionPair, SleighError> {
         let ctor_id = elem.as_int_or(AttributeId::Id, -1) as i32;
-        reader.seek_elem_children_start(elem);
+        reader.seek_elem_children_start(elem)?;
 
-        let pattern_elem = reader.read_elem();
-        let pattern = DisjointPattern::new(reader, &pattern_elem);
+        let pattern_elem = reader.read_elem()?;
+        let pattern = DisjointPattern::new(reader, &pattern_elem)?;
 
-        reader.read_elem_end(elem.id);
-        DecisionPair { ctor_id, pattern }
+        reader.read_elem_end(elem.id)?;
+        Ok(DecisionPair { ctor_id, pattern })
     }
 }
 
 impl Decision {
-    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Decision {
+    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Result<Decision, SleighError> {
         let context = elem.as_bool_or(AttributeId::Context, false);
         let start = elem.as_int_or(AttributeId::Startbit, -1) as i32;
         let size = elem.as_int_or(AttributeId::Size, -1) as i32;
-        reader.seek_elem_children_start(elem);
+        reader.seek_elem_children_start(elem)?;
 
         let mut children: Vec<Decision> = Vec::new();
         let mut pairs: Vec<DecisionPair> = Vec::new();
 
-        for child in reader.read_elem_children(elem.epos) {
+        for child in reader.read_elem_children(elem.epos)? {
+            let child = child?;
             if child.id == ElementId::Decision {
-                assert_ne!(size, 0);
-                children.push(Decision::new(reader, &child));
+                if size == 0 {
+                    return Err(SleighError::DecodeFailed);
+                }
+                children.push(Decision::new(reader, &child)?);
             } else if child.id == ElementId::Pair {
-                assert_eq!(size, 0);
-                pairs.push(DecisionPair::new(reader, &child));
+                if size != 0 {
+                    return Err(SleighError::DecodeFailed);
+                }
+                pairs.push(DecisionPair::new(reader, &child)?);
             }
         }
 
-        assert!(size == 0 || pow(2, size as usize) == children.len());
+        // a node with size bits picks between 2^size children
+        if size != 0 && 1usize.checked_shl(size as u32) != Some(children.len()) {
+            return Err(SleighError::DecodeFailed);
+        }
 
-        reader.read_elem_end(elem.id);
-        Decision {
+        reader.read_elem_end(elem.id)?;
+        Ok(Decision {
             context,
             start,
             size,
             children,
             pairs,
-        }
+        })
     }
 }
@@ -1,6 +1,9 @@
 use super::constructor::{Constructor, ConstructorPrintElement, ContextOpTpl};
+use super::disasm_cache::{DisasmCache, DisasmCacheEntry};
+use super::disasm_trace::DisasmTrace;
 use super::expression::Expression;
 use super::memory::{read_ctx_u32_bits_at, read_mem_u32_bits_at, read_mem_u64_bits_at, write_ctx_u32_bits_at};
+use super::pcode::{PcodeBuilder, PcodeOp};
 use super::sla_file::{Sleigh, Symbol, SymbolInner};
 use super::sym_subtable::SubtableSym;
 use super::sym_value::ValueSym;
@@ -9,9 +12,53 @@ use super::sym_varlist::VarlistSym;
 use crate::consts::arch::Endianness;
 use crate::ffi::core_framework::prelude::*;
 use crate::memory::memview::{MemView, MemViewError};
-use crate::shared::fast_util::i64_to_str_fast;
+use crate::shared::fast_util::{get_debug_checked, i64_to_str_fast};
 use mizl_pm::FfiSerialize;
 use smallvec::SmallVec;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DisasmError {
+    // the instruction runs past the end of the memview
+    Truncated,
+    // no constructor's pattern matches the bytes, so they aren't an
+    // instruction this spec knows about
+    PatternNotFound,
+    // the memview failed a read, usually because the address isn't mapped
+    InvalidRead(MemViewError),
+    // an operand uses a kind of symbol we can't decode yet
+    UnsupportedSymbol,
+    // the spec read or wrote context past the end of the context
+    ContextOverflow,
+    // a context passed in isn't the size the spec's context is
+    ContextSizeMismatch,
+    // the instruction's semantics use a p-code op we can't emit yet
+    UnsupportedPcodeOp(i32),
+    // the spec decodes the instruction but doesn't say what it does
+    NoSemantics,
+    // a decision node looks at bits spanning more than two words, which
+    // the decision tree walk can't read yet
+    DecisionTooWide,
+}
+
+impl fmt::Display for DisasmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DisasmError::Truncated => write!(f, "the instruction runs past the end of memory"),
+            DisasmError::PatternNotFound => write!(f, "the bytes are not a valid instruction"),
+            DisasmError::InvalidRead(e) => write!(f, "couldn't read the instruction: {}", e),
+            DisasmError::UnsupportedSymbol => write!(f, "the instruction uses an unsupported symbol type"),
+            DisasmError::ContextOverflow => write!(f, "the spec accessed context past its end"),
+            DisasmError::ContextSizeMismatch => write!(f, "the context is not the size the spec expects"),
+            DisasmError::UnsupportedPcodeOp(v) => write!(f, "the instruction uses unsupported p-code op {}", v),
+            DisasmError::NoSemantics => write!(f, "the instruction has no semantics in the spec"),
+            DisasmError::DecisionTooWide => write!(f, "the spec decodes more than two words at once"),
+        }
+    }
+}
 
 pub enum DisasmProtoPart<'a> {
     Literal(&'a str),
@@ -27,6 +74,8 @@ pub enum DisasmInstructionPart {
 pub struct Disasm {
     pub sleigh: Sleigh,
     pub initial_ctx: Vec<u32>,
+    // off unless set_cache_capacity is called, see DisasmCache
+    cache: Option<Mutex<DisasmCache>>,
 }
 
 struct DisasmStackItem<'a> {
@@ -34,6 +83,8 @@ struct DisasmStackItem<'a> {
     pub print_elem_idx: usize,
     pub last_operand_idx: i32,
     pub op_offsets: Vec<u32>,
+    pub op_ranges: Vec<Option<DisasmOperandRange>>,
+    pub max_end: u64, // furthest byte this constructor or its operands read
     pub read_position: u64,
     pub subsym_id: u32,
     pub ctor_idx: u32,
@@ -49,6 +100,18 @@ pub struct DisasmOperandStackItem {
 pub struct DisasmPrototype<'a> {
     pub parts: SmallVec<DisasmProtoPart<'a>, 16>,
     pub length: u64,
+    // only filled in by disasm_operand_ranges
+    operand_ranges: Vec<Option<DisasmOperandRange>>,
+}
+
+// the bytes an operand was decoded from, relative to the start of the
+// instruction. end is exclusive. for operands that are subtables this covers
+// everything the subtable read, so it can overlap a later operand's bytes when
+// the spec reads them out of order.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DisasmOperandRange {
+    pub start: u32,
+    pub end: u32,
 }
 
 pub struct DisasmProtoExpression<'a> {
@@ -70,10 +133,13 @@ pub struct DisasmState<'a> {
     ctx: Vec<u32>,
     start_addr: u64,
     end_addr: u64,
-    _next2_addr: u64,
+    // inst_next2 is only decoded the first time something asks for it, and only
+    // if there's a context to decode the next instruction with
+    next2_addr: Cell<Option<u64>>,
+    next2_ctx: Option<&'a [u32]>,
 }
 
-#[derive(FromPrimitive, ToPrimitive, Copy, Clone)]
+#[derive(FromPrimitive, ToPrimitive, Copy, Clone, Debug, PartialEq)]
 pub enum DisasmDispInstructionRunType {
     Normal = 0,
     Mnemonic = 1,
@@ -81,19 +147,44 @@ pub enum DisasmDispInstructionRunType {
     Number = 3,
 }
 
-#[derive(FfiSerialize)]
+#[derive(FfiSerialize, Debug, PartialEq)]
+#[ffi_serialize(deserialize)]
 pub struct DisasmDispInstructionRun {
     pub length: u32,
     #[ffi_serialize_enum]
     pub run_type: DisasmDispInstructionRunType,
 }
 
-#[derive(FfiSerialize)]
+#[derive(FfiSerialize, Debug, PartialEq)]
+#[ffi_serialize(deserialize)]
 pub struct DisasmDispInstruction {
     pub addr: u64,
     pub len: u64,
     pub text: String,
     pub runs: Vec<DisasmDispInstructionRun>,
+    pub raw_bytes: Vec<u8>, // empty unless requested with disasm_display_with_bytes
+}
+
+// controls how disasm_display_opts lays out the instruction text.
+// the default matches disasm_display.
+#[derive(Clone, Default)]
+pub struct DisplayOptions {
+    // pad the mnemonic with spaces so operands start at this column. 0 = single space
+    pub mnemonic_width: usize,
+    // replaces the "," between operands, like ", ". None = keep the spec's text
+    pub operand_separator: Option<String>,
+    // also fill raw_bytes with the instruction's encoding
+    pub include_bytes: bool,
+}
+
+// controls Disasm::listing
+#[derive(Default)]
+pub struct ListingOptions<'a> {
+    // how each instruction's text is laid out. raw bytes are always included
+    pub display: DisplayOptions,
+    // called with each address in the listing. a name returned here is printed
+    // as a label line above the instruction, like a symbol starting there
+    pub annotate: Option<&'a dyn Fn(u64) -> Option<String>>,
 }
 
 impl DisasmDispInstructionRun {
@@ -102,14 +193,64 @@ impl DisasmDispInstructionRun {
     }
 }
 
-impl DisasmState<'_> {
-    pub fn new(mem: &dyn MemView, ctx: Vec<u32>, start_addr: u64) -> DisasmState {
+// the decoder reads whole words at a time, so an instruction shorter than a
+// word at the very end of memory would always fail to read. this pads reads
+// that run past max_addr with zeros instead. whether the instruction really
+// fit is checked once we know its length.
+struct DisasmEdgeMemView<'a> {
+    mem: &'a dyn MemView,
+    max_addr: u64,
+}
+
+impl MemView for DisasmEdgeMemView<'_> {
+    fn read_bytes(&self, addr: &mut u64, out_data: &mut [u8], count: i32) -> Result<(), MemViewError> {
+        let start_addr = *addr;
+        let end_addr = start_addr.saturating_add(count as u64);
+        if end_addr <= self.max_addr {
+            return self.mem.read_bytes(addr, out_data, count);
+        }
+
+        let avail_count = self.max_addr.saturating_sub(start_addr) as usize;
+        if avail_count > 0 {
+            let mut read_addr = start_addr;
+            self.mem
+                .read_bytes(&mut read_addr, &mut out_data[..avail_count], avail_count as i32)?;
+        }
+        out_data[avail_count..count as usize].fill(0);
+
+        *addr = end_addr;
+        Ok(())
+    }
+
+    fn max_address(&self) -> Result<u64, MemViewError> {
+        Ok(self.max_addr)
+    }
+
+    fn can_read_while_running(&self) -> bool {
+        self.mem.can_read_while_running()
+    }
+
+    fn can_write_while_running(&self) -> bool {
+        false
+    }
+}
+
+impl<'a> DisasmEdgeMemView<'a> {
+    fn new(mem: &'a dyn MemView) -> DisasmEdgeMemView<'a> {
+        let max_addr = mem.max_address().unwrap_or(u64::MAX);
+        DisasmEdgeMemView { mem, max_addr }
+    }
+}
+
+impl<'a> DisasmState<'a> {
+    pub fn new(mem: &'a dyn MemView, ctx: Vec<u32>, start_addr: u64) -> DisasmState<'a> {
         DisasmState {
             mem,
             ctx,
             start_addr,
             end_addr: start_addr,
-            _next2_addr: start_addr,
+            next2_addr: Cell::new(None),
+            next2_ctx: None,
         }
     }
 
@@ -171,19 +312,52 @@ impl DisasmState<'_> {
         self.end_addr as i64
     }
 
-    pub fn get_next2_ins(&self) -> i64 {
-        panic!("inst_next2 supported yet");
-        // self.next2_addr as i64
+    // the address of the instruction after the next one. this decodes the next
+    // instruction (once) to find its length. that decode can't look ahead again,
+    // and if it fails we fall back to inst_next.
+    pub fn get_next2_ins(&self, disasm: &Disasm) -> i64 {
+        if let Some(next2_addr) = self.next2_addr.get() {
+            return next2_addr as i64;
+        }
+
+        let next2_addr = match self.next2_ctx {
+            Some(ctx) => {
+                let mut trace = DisasmTrace::new();
+                disasm
+                    .disasm_proto_impl::<false, true, false>(self.mem, self.end_addr, ctx, None, &mut trace)
+                    .ok()
+                    .and_then(|p| self.end_addr.checked_add(p.length))
+                    .unwrap_or(self.end_addr)
+            }
+            None => self.end_addr,
+        };
+        self.next2_addr.set(Some(next2_addr));
+        next2_addr as i64
     }
 
     pub fn set_end_ins(&mut self, value: u64) {
         self.end_addr = value;
+        self.next2_addr.set(None);
+    }
+
+    // lets inst_next2 decode the next instruction with ctx
+    pub fn set_next2_ctx(&mut self, ctx: &'a [u32]) {
+        self.next2_ctx = Some(ctx);
+        self.next2_addr.set(None);
     }
 }
 
 impl DisasmPrototype<'_> {
-    fn new(parts: SmallVec<DisasmProtoPart, 16>, length: u64) -> DisasmPrototype {
-        DisasmPrototype { parts, length }
+    fn new(
+        parts: SmallVec<DisasmProtoPart, 16>,
+        length: u64,
+        operand_ranges: Vec<Option<DisasmOperandRange>>,
+    ) -> DisasmPrototype {
+        DisasmPrototype {
+            parts,
+            length,
+            operand_ranges,
+        }
     }
 }
 
@@ -232,20 +406,141 @@ impl DisasmOperandStackItem {
 
 impl Disasm {
     pub fn new(sleigh: Sleigh, initial_ctx: Vec<u32>) -> Disasm {
-        Disasm { sleigh, initial_ctx }
+        Disasm {
+            sleigh,
+            initial_ctx,
+            cache: None,
+        }
+    }
+
+    // remembers the last `capacity` decoded instructions so showing them again
+    // skips resolving constructors. a hit is checked against the bytes in memory
+    // first, so this costs a read of the instruction even when it's cached.
+    // 0 turns it off.
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.cache = if capacity > 0 {
+            Some(Mutex::new(DisasmCache::new(capacity)))
+        } else {
+            None
+        };
+    }
+
+    // forgets cached instructions with any bytes in [addr, addr + len). stale
+    // entries are never used anyway, this just frees their slots early.
+    pub fn invalidate(&self, addr: u64, len: u64) {
+        if let Some(cache) = &self.cache {
+            cache.lock().unwrap().invalidate(addr, len);
+        }
+    }
+
+    fn get_instruction_subtable(&self) -> (u32, &SubtableSym) {
+        let root_scope = &self.sleigh.symbol_table.scopes[0];
+        let instruction_subtable_idx = match root_scope.lookup.get("instruction") {
+            Some(v) => *v,
+            None => panic!("expected instruction in root scope"),
+        };
+
+        let subtable_sym_box = &self.sleigh.symbol_table.symbols[instruction_subtable_idx];
+        match &subtable_sym_box.inner {
+            SymbolInner::SubtableSym(v) => (subtable_sym_box.id, v),
+            _ => panic!("not a subtable symbol"),
+        }
+    }
+
+    // the fewest bytes any instruction can take, which is also how far to skip
+    // after a bad decode to stay aligned
+    pub fn min_instruction_length(&self) -> u32 {
+        let align = std::cmp::max(self.sleigh.align, 1) as u32;
+        let (_, subtable_sym) = self.get_instruction_subtable();
+        let min_length = subtable_sym
+            .ctors
+            .iter()
+            .map(|c| std::cmp::max(c.min_length, 1) as u32)
+            .min()
+            .unwrap_or(1);
+
+        min_length.div_ceil(align) * align
+    }
+
+    // the most bytes an instruction can take according to the spec, so reading
+    // this many bytes is always enough to decode one instruction. returns None if
+    // constructors can nest inside themselves (x86 does this for prefixes) since
+    // there's no upper bound then. callers need an architecture limit in that case.
+    // this walks every constructor, so don't call it per instruction.
+    pub fn max_instruction_length(&self) -> Option<u32> {
+        let (subtable_id, subtable_sym) = self.get_instruction_subtable();
+        let mut visited: HashMap<u32, Option<u32>> = HashMap::new();
+        self.get_subtable_max_length(subtable_id, subtable_sym, &mut visited)
+    }
+
+    // visited holds None while a subtable is still being walked, which is how a
+    // cycle gets noticed
+    fn get_subtable_max_length(
+        &self,
+        subtable_id: u32,
+        subtable_sym: &SubtableSym,
+        visited: &mut HashMap<u32, Option<u32>>,
+    ) -> Option<u32> {
+        if let Some(v) = visited.get(&subtable_id) {
+            return *v;
+        }
+        visited.insert(subtable_id, None);
+
+        let sleigh_symbols = &self.sleigh.symbol_table.symbols;
+        let mut max_length = 0u32;
+        for ctor in &subtable_sym.ctors {
+            let mut ctor_end = std::cmp::max(ctor.min_length, 0) as u32;
+            let mut operand_ends = vec![0u32; ctor.operand_ids.len()];
+            for (i, operand_id) in ctor.operand_ids.iter().enumerate() {
+                // a broken spec has no bound we can trust
+                let operand_sym = match &sleigh_symbols.get(*operand_id as usize)?.inner {
+                    SymbolInner::OperandSym(v) => v,
+                    _ => return None,
+                };
+
+                // same as disasm_proto, operands can start where another one ends
+                let operand_start = if operand_sym.offset_base == -1 {
+                    operand_sym.rel_offset as u32
+                } else {
+                    operand_ends.get(operand_sym.offset_base as usize).copied().unwrap_or(0)
+                        + operand_sym.rel_offset as u32
+                };
+
+                let mut operand_length = std::cmp::max(operand_sym.min_length, 0) as u32;
+                if operand_sym.subsym != u32::MAX {
+                    let operand_subsym_box = sleigh_symbols.get(operand_sym.subsym as usize)?;
+                    if let SymbolInner::SubtableSym(sub_subtable_sym) = &operand_subsym_box.inner {
+                        let sub_length =
+                            self.get_subtable_max_length(operand_subsym_box.id, sub_subtable_sym, visited)?;
+                        operand_length = std::cmp::max(operand_length, sub_length);
+                    }
+                }
+
+                operand_ends[i] = operand_start + operand_length;
+                ctor_end = std::cmp::max(ctor_end, operand_ends[i]);
+            }
+            max_length = std::cmp::max(max_length, ctor_end);
+        }
+
+        visited.insert(subtable_id, Some(max_length));
+        Some(max_length)
     }
 
-    // hot path
-    fn resolve_ctor(&self, state: &mut DisasmState, subtable_sym: &SubtableSym, at: u64) -> Result<i32, &str> {
+    // hot path. TRACE is a compile time flag so the recording disappears
+    // completely from the normal (non-traced) decode.
+    pub(super) fn resolve_ctor<const TRACE: bool>(
+        &self,
+        state: &mut DisasmState,
+        subtable_sym: &SubtableSym,
+        at: u64,
+        trace: &mut DisasmTrace,
+    ) -> Result<i32, DisasmError> {
         let mut decision = &subtable_sym.decision;
         let mut word_stack: SmallVec<u32, 3> = SmallVec::with_capacity(3);
         let mut word_stack_len = 1;
 
         // cache 32-bit words so we don't read multiple times for small bit segments
-        word_stack.push(match state.read_mem_u32_at(at, true) {
-            Ok(v) => v,
-            Err(_) => return Err("<invalid read>"),
-        });
+        word_stack.push(state.read_mem_u32_at(at, true).map_err(DisasmError::InvalidRead)?);
 
         loop {
             if decision.size != 0 {
@@ -253,6 +548,9 @@ impl Disasm {
                 let decision_start = decision.start;
                 let decision_size = decision.size;
                 if decision.context {
+                    if (decision_start / 32) as usize >= state.ctx.len() {
+                        return Err(DisasmError::ContextOverflow);
+                    }
                     check_bits = state.read_ctx_u32_bits_at(decision_start, decision_size);
                 } else {
                     let word_stack_idx = decision_start / 32;
@@ -263,30 +561,35 @@ impl Disasm {
                         word_stack_idx
                     };
                     while word_stack_end_idx >= word_stack_len {
-                        word_stack.push(match state.read_mem_u32_at((word_stack_len / 4) as u64, true) {
-                            Ok(v) => v,
-                            Err(_) => return Err("<invalid read>"),
-                        });
+                        word_stack.push(
+                            state
+                                .read_mem_u32_at((word_stack_len / 4) as u64, true)
+                                .map_err(DisasmError::InvalidRead)?,
+                        );
                         word_stack_len += 1;
                     }
 
                     let unused_bits = 32 - decision_size;
                     // safety: we just added enough items to word_stack above
-                    let mut tmp = unsafe { *word_stack.get_unchecked(word_stack_idx as usize) };
+                    let mut tmp = unsafe { *get_debug_checked(&word_stack, word_stack_idx as usize) };
                     tmp <<= decision_start - (word_stack_idx * 32);
                     tmp >>= unused_bits;
                     if word_stack_plus < 0 {
                         // safety: ditto
-                        let tmp2 = unsafe { *word_stack.get_unchecked((word_stack_idx + 1) as usize) } >> unused_bits;
+                        let tmp2 =
+                            unsafe { *get_debug_checked(&word_stack, (word_stack_idx + 1) as usize) } >> unused_bits;
                         tmp |= tmp2;
                         if word_stack_plus < -32 {
-                            todo!("oops, tried to read more than two words");
+                            return Err(DisasmError::DecisionTooWide);
                         }
                     }
                     check_bits = tmp;
                 }
+                if TRACE {
+                    trace.add_decision(decision.context, decision_start, decision_size, check_bits);
+                }
                 // safety: assertion exists in Decision constructor to guarantee this works
-                decision = unsafe { decision.children.get_unchecked(check_bits as usize) };
+                decision = unsafe { get_debug_checked(&decision.children, check_bits as usize) };
             } else {
                 break;
             }
@@ -295,11 +598,49 @@ impl Disasm {
         // find constructor
         for pair in &decision.pairs {
             let pattern = &pair.pattern;
-            if pattern.is_match(state, at) {
+            let is_match = pattern.is_match(state, at);
+            if TRACE {
+                self.trace_pattern(trace, subtable_sym, pair.ctor_id, is_match);
+            }
+            if is_match {
                 return Ok(pair.ctor_id);
             }
         }
-        return Err("<pattern not found>");
+        return Err(DisasmError::PatternNotFound);
+    }
+
+    // takes the constructor from replay if the cache had this instruction,
+    // otherwise resolves it. either way it's recorded in resolved for the cache.
+    fn next_ctor<const TRACE: bool>(
+        &self,
+        state: &mut DisasmState,
+        subtable_sym: &SubtableSym,
+        at: u64,
+        trace: &mut DisasmTrace,
+        replay: Option<&[u32]>,
+        resolved: &mut Vec<u32>,
+    ) -> Result<i32, DisasmError> {
+        let cached = replay
+            .and_then(|v| v.get(resolved.len()))
+            .filter(|v| (**v as usize) < subtable_sym.ctors.len());
+        let ctor_id = match cached {
+            Some(v) => *v as i32,
+            None => self.resolve_ctor::<TRACE>(state, subtable_sym, at, trace)?,
+        };
+        resolved.push(ctor_id as u32);
+        Ok(ctor_id)
+    }
+
+    #[cold]
+    fn trace_pattern(&self, trace: &mut DisasmTrace, subtable_sym: &SubtableSym, ctor_id: i32, matched: bool) {
+        let (source, line) = match subtable_sym.ctors.get(ctor_id as usize) {
+            Some(ctor) => {
+                let source = self.sleigh.source_files.iter().find(|s| s.index == ctor.source);
+                (source.map_or("?", |s| s.name.as_str()), ctor.line)
+            }
+            None => ("?", -1),
+        };
+        trace.add_pattern(ctor_id, source, line, matched);
     }
 
     fn get_value_sym_string(
@@ -330,11 +671,12 @@ impl Disasm {
         top_stack: &DisasmOperandStackItem,
         at: u64,
         sym: &Box<VarlistSym>,
-    ) -> Result<&str, ()> {
+    ) -> Result<&str, DisasmError> {
         let value = sym.patexp.evaluate(self, state, top_stack, at);
-        let var_idx = sym.var_ids[value as usize];
+        // unset entries in the list mean the encoding isn't valid
+        let var_idx = *sym.var_ids.get(value as usize).ok_or(DisasmError::PatternNotFound)?;
         if var_idx == u32::MAX {
-            return Err(());
+            return Err(DisasmError::PatternNotFound);
         }
 
         let varnode_sym_box = &self.sleigh.symbol_table.symbols[var_idx as usize];
@@ -353,25 +695,113 @@ impl Disasm {
         i64_to_str_fast(var_value)
     }
 
-    fn set_context(
+    pub(super) fn set_context(
         &self,
         state: &mut DisasmState,
         context_ops: &Vec<ContextOpTpl>,
         top_stack: &DisasmOperandStackItem,
         at: u64,
-    ) {
+    ) -> Result<(), DisasmError> {
         for context_op in context_ops {
+            if context_op.word_start as usize >= state.ctx.len() {
+                return Err(DisasmError::ContextOverflow);
+            }
             let exp_value = context_op.expression.evaluate(self, state, top_stack, at) as u32;
 
-            let old_ctx_val = state.read_ctx_u32_at((context_op.word_start * 32) as u64);
+            let old_ctx_val = state.read_ctx_u32_at((context_op.word_start * 4) as u64);
             let new_ctx_val = (old_ctx_val & (!context_op.mask)) | (exp_value << context_op.bit_shift);
             state.write_ctx_u32_bits_at(context_op.word_start * 32, 32, new_ctx_val);
         }
+        Ok(())
+    }
+
+    pub fn disasm_proto(&self, mem: &dyn MemView, at: u64) -> Result<DisasmPrototype, DisasmError> {
+        let mut trace = DisasmTrace::new();
+        self.disasm_proto_impl::<false, false, false>(mem, at, &self.initial_ctx, Some(&self.initial_ctx), &mut trace)
+    }
+
+    // same as disasm_proto but decodes with ctx instead of initial_ctx, for this
+    // call only. this is how something like "show as 16-bit" works without
+    // touching the disassembler everyone else is using.
+    pub fn disasm_proto_ctx(
+        &self,
+        mem: &dyn MemView,
+        at: u64,
+        ctx: &[u32],
+    ) -> Result<DisasmPrototype<'_>, DisasmError> {
+        self.check_ctx_size(ctx)?;
+        let mut trace = DisasmTrace::new();
+        self.disasm_proto_impl::<false, false, false>(mem, at, ctx, Some(ctx), &mut trace)
     }
 
-    // todo: error type
-    pub fn disasm_proto(&self, mem: &dyn MemView, at: u64) -> Result<DisasmPrototype, ()> {
-        let mut state = DisasmState::new(mem, self.initial_ctx.clone(), at);
+    // the bytes each operand of the main constructor (the first one that isn't a
+    // prefix like `^instruction`) was decoded from, in sleigh's operand order
+    // which isn't always display order. None for operands that aren't printed,
+    // since they're never decoded, and for ones that don't take any bytes.
+    pub fn disasm_operand_ranges(
+        &self,
+        mem: &dyn MemView,
+        at: u64,
+    ) -> Result<Vec<Option<DisasmOperandRange>>, DisasmError> {
+        let mut trace = DisasmTrace::new();
+        let prototype = self.disasm_proto_impl::<false, true, true>(
+            mem,
+            at,
+            &self.initial_ctx,
+            Some(&self.initial_ctx),
+            &mut trace,
+        )?;
+        Ok(prototype.operand_ranges)
+    }
+
+    fn check_ctx_size(&self, ctx: &[u32]) -> Result<(), DisasmError> {
+        if ctx.len() != self.initial_ctx.len() {
+            return Err(DisasmError::ContextSizeMismatch);
+        }
+        Ok(())
+    }
+
+    // same as disasm_proto but also records every decision tree step and pattern
+    // check along the way. slow, meant for figuring out why a spec won't decode
+    // something.
+    pub fn disasm_proto_traced(
+        &self,
+        mem: &dyn MemView,
+        at: u64,
+    ) -> (Result<DisasmPrototype<'_>, DisasmError>, DisasmTrace) {
+        let mut trace = DisasmTrace::new();
+        let result = self.disasm_proto_impl::<true, false, false>(
+            mem,
+            at,
+            &self.initial_ctx,
+            Some(&self.initial_ctx),
+            &mut trace,
+        );
+        (result, trace)
+    }
+
+    // with LEN_ONLY, only the length in the returned prototype is filled in.
+    // no parts are made, which skips copying the context for every operand.
+    // operand ranges are only tracked with RANGES.
+    // next2_ctx is what inst_next2 in a context op decodes the next instruction
+    // with. it's None when this is that decode, so it doesn't look ahead again.
+    fn disasm_proto_impl<const TRACE: bool, const LEN_ONLY: bool, const RANGES: bool>(
+        &self,
+        mem: &dyn MemView,
+        at: u64,
+        ctx: &[u32],
+        next2_ctx: Option<&[u32]>,
+        trace: &mut DisasmTrace,
+    ) -> Result<DisasmPrototype<'_>, DisasmError> {
+        let edge_mem = DisasmEdgeMemView::new(mem);
+        if at >= edge_mem.max_addr {
+            return Err(DisasmError::Truncated);
+        }
+
+        let mut state = DisasmState::new(&edge_mem, ctx.to_vec(), at);
+        if let Some(next2_ctx) = next2_ctx {
+            state.set_next2_ctx(next2_ctx);
+        }
 
         let root_scope = &self.sleigh.symbol_table.scopes[0];
         let instruction_subtable_idx = match root_scope.lookup.get("instruction") {
@@ -391,10 +821,22 @@ impl Disasm {
         let mut stack: SmallVec<DisasmStackItem, 16> = SmallVec::new();
         let mut proto_parts: SmallVec<DisasmProtoPart, 16> = SmallVec::new();
 
-        let base_ctor_idx = match self.resolve_ctor(&mut state, subtable_sym, at) {
-            Ok(c) => c,
-            Err(_) => return Err(()),
+        // a traced decode has to really look at everything, so it skips the cache
+        let use_cache = self.cache.is_some() && !TRACE;
+        let cached = match &self.cache {
+            Some(cache) if use_cache => cache.lock().unwrap().get(at, ctx),
+            _ => None,
         };
+        // the cache only knows the address, so the bytes have to still be the
+        // ones it decoded. anything else gets decoded again and replaces it.
+        let cached = cached.filter(|v| Self::read_ins_bytes(&edge_mem, at, v.length).as_ref() == Some(&v.bytes));
+        let replay = cached.as_ref().map(|v| v.ctor_ids.as_slice());
+        let mut resolved: Vec<u32> = Vec::new();
+
+        if TRACE {
+            trace.begin_table(&subtable_sym_box.name, at);
+        }
+        let base_ctor_idx = self.next_ctor::<TRACE>(&mut state, subtable_sym, at, trace, replay, &mut resolved)?;
         let base_ctor = &subtable_sym.ctors[base_ctor_idx as usize];
 
         // avoids recursion
@@ -403,12 +845,22 @@ impl Disasm {
             print_elem_idx: 0,
             last_operand_idx: -1,
             op_offsets: vec![u32::MAX; base_ctor.operand_ids.len()],
+            op_ranges: Self::new_op_ranges::<RANGES>(base_ctor),
+            max_end: at + base_ctor.min_length as u64,
             read_position: at,
             subsym_id: subtable_sym_box.id,
             ctor_idx: base_ctor_idx as u32,
         });
         let first_op_top_stack = DisasmOperandStackItem::from_stack_item(stack.last().unwrap());
-        self.set_context(&mut state, &base_ctor.context_ops, &first_op_top_stack, at);
+        // context ops run before the whole length is known, so inst_next is
+        // as far as we've decoded so far
+        state.set_end_ins(at + base_ctor.min_length as u64);
+        self.set_context(&mut state, &base_ctor.context_ops, &first_op_top_stack, at)?;
+
+        // stack depth of the constructor whose operands are the instruction's
+        // operands. starts at the root and moves down through prefix constructors.
+        let mut main_depth = 0;
+        let mut operand_ranges: Vec<Option<DisasmOperandRange>> = Vec::new();
 
         let mut end_pos = at + base_ctor.min_length as u64;
         while !stack.is_empty() {
@@ -416,11 +868,29 @@ impl Disasm {
 
             let top_stack = stack.last().expect("stack is empty");
             if top_stack.print_elem_idx >= top_stack.ctor.print_elements.len() {
+                if RANGES && stack.len() - 1 == main_depth {
+                    // an operand that took no bytes has nothing to point at
+                    operand_ranges = top_stack
+                        .op_ranges
+                        .iter()
+                        .map(|r| r.filter(|v| v.end > v.start))
+                        .collect();
+                }
+                let child_max_end = top_stack.max_end;
                 stack.pop();
                 // no reason to edit op_offsets if there's no more stack
                 if !stack.is_empty() {
                     let prev_top_stack = stack.last_mut().expect("stack is empty");
+                    prev_top_stack.max_end = std::cmp::max(prev_top_stack.max_end, child_max_end);
                     if prev_top_stack.last_operand_idx != -1 {
+                        // the subtable operand covers everything its constructor read
+                        if RANGES {
+                            let operand_range = &mut prev_top_stack.op_ranges[prev_top_stack.last_operand_idx as usize];
+                            if let Some(v) = operand_range {
+                                v.end = std::cmp::max(v.end, (child_max_end - at) as u32);
+                            }
+                        }
+
                         // todo: store end pos into stack item
                         // end_pos may not be trustworthy since
                         // operands could (theoretically) appear
@@ -435,7 +905,9 @@ impl Disasm {
             let print_elem = &top_stack.ctor.print_elements[top_stack.print_elem_idx];
             match print_elem {
                 ConstructorPrintElement::Literal(s) => {
-                    proto_parts.push(DisasmProtoPart::Literal(s));
+                    if !LEN_ONLY {
+                        proto_parts.push(DisasmProtoPart::Literal(s));
+                    }
                 }
                 ConstructorPrintElement::Operand(oper_idx) => {
                     last_oper_idx = *oper_idx;
@@ -469,17 +941,30 @@ impl Disasm {
                             | SymbolInner::VarlistSym(_)
                             | SymbolInner::ValuemapSym(_)
                             | SymbolInner::VarnodeSym(_) => {
-                                let op_top_stack = DisasmOperandStackItem::from_stack_item(top_stack);
-                                let saved_ctx = state.get_context().clone();
-                                let exp_info =
-                                    DisasmProtoSubsym::new(saved_ctx, op_top_stack, operand_subsym_box, operand_off);
-                                proto_parts.push(DisasmProtoPart::SymbolInfo(exp_info));
+                                if !LEN_ONLY {
+                                    let op_top_stack = DisasmOperandStackItem::from_stack_item(top_stack);
+                                    let saved_ctx = state.get_context().clone();
+                                    let exp_info = DisasmProtoSubsym::new(
+                                        saved_ctx,
+                                        op_top_stack,
+                                        operand_subsym_box,
+                                        operand_off,
+                                    );
+                                    proto_parts.push(DisasmProtoPart::SymbolInfo(exp_info));
+                                }
                             }
                             SymbolInner::SubtableSym(subtable_sym) => {
-                                let sub_ctor_idx = match self.resolve_ctor(&mut state, subtable_sym, operand_off) {
-                                    Ok(c) => c,
-                                    Err(_) => return Err(()),
-                                };
+                                if TRACE {
+                                    trace.begin_table(&operand_subsym_box.name, operand_off);
+                                }
+                                let sub_ctor_idx = self.next_ctor::<TRACE>(
+                                    &mut state,
+                                    subtable_sym,
+                                    operand_off,
+                                    trace,
+                                    replay,
+                                    &mut resolved,
+                                )?;
 
                                 let sub_ctor = &subtable_sym.ctors[sub_ctor_idx as usize];
                                 let sub_ctor_stack_item = DisasmStackItem {
@@ -487,6 +972,8 @@ impl Disasm {
                                     print_elem_idx: 0,
                                     last_operand_idx: -1,
                                     op_offsets: vec![u32::MAX; sub_ctor.operand_ids.len()],
+                                    op_ranges: Self::new_op_ranges::<RANGES>(sub_ctor),
+                                    max_end: operand_off + sub_ctor.min_length as u64,
                                     read_position: operand_off,
                                     subsym_id: operand_subsym_box.id,
                                     ctor_idx: sub_ctor_idx as u32,
@@ -496,12 +983,17 @@ impl Disasm {
                                     let elem_to_add_stack =
                                         DisasmOperandStackItem::from_stack_item(&sub_ctor_stack_item);
                                     //let op_top_stack = DisasmOperandStackItem::from_stack_item(top_stack);
+                                    state.set_end_ins(end_pos.max(operand_off + sub_ctor.min_length as u64));
                                     self.set_context(
                                         &mut state,
                                         &sub_ctor.context_ops,
                                         &elem_to_add_stack,
                                         operand_off,
-                                    );
+                                    )?;
+                                }
+
+                                if stack.len() - 1 == main_depth && Self::is_flowthru_ctor(top_stack.ctor) {
+                                    main_depth += 1;
                                 }
 
                                 elem_to_add = Some(sub_ctor_stack_item);
@@ -512,14 +1004,16 @@ impl Disasm {
                                     end_pos = ctor_end_pos;
                                 }
                             }
-                            _ => panic!("unsupported symbol type for operand"),
+                            _ => return Err(DisasmError::UnsupportedSymbol),
                         };
                     } else if let Some(def_exp) = &operand_sym.def_exp {
                         // static value
-                        let op_top_stack = DisasmOperandStackItem::from_stack_item(top_stack);
-                        let saved_ctx = state.get_context().clone();
-                        let exp_info = DisasmProtoExpression::new(saved_ctx, op_top_stack, def_exp, operand_off);
-                        proto_parts.push(DisasmProtoPart::ExpressionInfo(exp_info));
+                        if !LEN_ONLY {
+                            let op_top_stack = DisasmOperandStackItem::from_stack_item(top_stack);
+                            let saved_ctx = state.get_context().clone();
+                            let exp_info = DisasmProtoExpression::new(saved_ctx, op_top_stack, def_exp, operand_off);
+                            proto_parts.push(DisasmProtoPart::ExpressionInfo(exp_info));
+                        }
                     } else {
                         panic!("either operand subsymbol or defexp should've been set");
                     }
@@ -529,6 +1023,13 @@ impl Disasm {
                     // the _end_ of the operand
                     let top_stack_mut = stack.last_mut().expect("stack is empty");
                     top_stack_mut.op_offsets[*oper_idx as usize] = (operand_end_pos - at) as u32;
+                    if RANGES {
+                        top_stack_mut.op_ranges[*oper_idx as usize] = Some(DisasmOperandRange {
+                            start: (operand_off - at) as u32,
+                            end: (operand_end_pos - at) as u32,
+                        });
+                    }
+                    top_stack_mut.max_end = std::cmp::max(top_stack_mut.max_end, operand_end_pos);
                 }
             }
 
@@ -543,18 +1044,59 @@ impl Disasm {
             }
         }
 
+        // anything we decoded from the zero padding isn't real
+        if end_pos > edge_mem.max_addr {
+            return Err(DisasmError::Truncated);
+        }
+
         let length = end_pos - at;
-        let prototype = DisasmPrototype::new(proto_parts, length);
+        let new_entry = match (use_cache, &cached) {
+            (true, None) => Self::read_ins_bytes(&edge_mem, at, length).map(|bytes| DisasmCacheEntry {
+                length,
+                bytes,
+                ctx: ctx.to_vec(),
+                ctor_ids: resolved,
+            }),
+            _ => None,
+        };
+        if let (Some(cache), Some(entry)) = (&self.cache, new_entry) {
+            cache.lock().unwrap().insert(at, entry);
+        }
+
+        let prototype = DisasmPrototype::new(proto_parts, length, operand_ranges);
         return Ok(prototype);
     }
 
+    fn read_ins_bytes(mem: &dyn MemView, at: u64, length: u64) -> Option<Vec<u8>> {
+        let mut bytes = vec![0u8; length as usize];
+        mem.read_bytes(&mut at.clone(), &mut bytes, length as i32).ok()?;
+        Some(bytes)
+    }
+
+    // empty (so no allocation) unless operand ranges were asked for
+    fn new_op_ranges<const RANGES: bool>(ctor: &Constructor) -> Vec<Option<DisasmOperandRange>> {
+        if RANGES {
+            vec![None; ctor.operand_ids.len()]
+        } else {
+            Vec::new()
+        }
+    }
+
+    // a constructor that only prints a subtable, like x86's prefix constructors
+    // that display as `^instruction`. the operands belong to the subtable instead.
+    fn is_flowthru_ctor(ctor: &Constructor) -> bool {
+        ctor.print_elements.len() == 1 && matches!(ctor.print_elements[0], ConstructorPrintElement::Operand(_))
+    }
+
     fn get_proto_display(
         &self,
         mem: &dyn MemView,
         at: u64,
         end_pos: u64,
+        ctx: &[u32],
         prototype: &DisasmPrototype,
-    ) -> Result<(String, Vec<DisasmDispInstructionRun>), ()> {
+        opts: &DisplayOptions,
+    ) -> Result<(String, Vec<DisasmDispInstructionRun>), DisasmError> {
         let mut final_str = String::with_capacity(64);
         let mut runs: Vec<DisasmDispInstructionRun> = Vec::new();
         let mut is_mnemonic = true;
@@ -573,6 +1115,7 @@ impl Disasm {
         let ctx_size = self.initial_ctx.len();
         let mut state: DisasmState = DisasmState::new(mem, vec![0u32; ctx_size], at);
         state.set_end_ins(end_pos);
+        state.set_next2_ctx(ctx);
 
         for elem in &prototype.parts {
             match elem {
@@ -580,12 +1123,24 @@ impl Disasm {
                     // yes, the only thing marking the end of
                     // a mnemonic is a space character
                     // todo: is this guaranteed to be alone?
-                    if v.contains(" ") {
+                    if is_mnemonic && v.contains(" ") {
                         is_mnemonic = false;
+                        if opts.mnemonic_width > 0 {
+                            // swap the first run of spaces for enough padding to reach the column
+                            let space_idx = v.find(' ').unwrap_or(0);
+                            let (head, tail) = v.split_at(space_idx);
+                            let head_end = final_str.len() + head.len();
+                            let pad_len = opts.mnemonic_width.saturating_sub(head_end).max(1);
+                            let padded = format!("{}{}{}", head, " ".repeat(pad_len), tail.trim_start_matches(' '));
+                            let padded = self.apply_operand_separator(&padded, opts);
+                            add_run(&padded, DisasmDispInstructionRunType::Normal, &mut runs, &mut final_str);
+                            continue;
+                        }
                     }
                     if is_mnemonic {
                         add_run(&v, DisasmDispInstructionRunType::Mnemonic, &mut runs, &mut final_str);
                     } else {
+                        let v = self.apply_operand_separator(v, opts);
                         add_run(&v, DisasmDispInstructionRunType::Normal, &mut runs, &mut final_str);
                     }
                 }
@@ -615,7 +1170,7 @@ impl Disasm {
                             &self.get_valuemap_sym_string(&mut state, &op_top_stack, operand_off, valuemap_sym)
                         }
                         SymbolInner::VarnodeSym(_) => &info.symbol.name,
-                        _ => panic!("unsupported symbol type for operand"),
+                        _ => return Err(DisasmError::UnsupportedSymbol),
                     };
 
                     match inner {
@@ -631,7 +1186,7 @@ impl Disasm {
                         SymbolInner::VarnodeSym(_) => {
                             add_run(&v, DisasmDispInstructionRunType::Register, &mut runs, &mut final_str);
                         }
-                        _ => panic!("unsupported symbol type for operand"),
+                        _ => return Err(DisasmError::UnsupportedSymbol),
                     }
                 }
             };
@@ -640,17 +1195,670 @@ impl Disasm {
         Ok((final_str, runs))
     }
 
-    pub fn disasm_display(&self, mem: &dyn MemView, at: u64) -> Result<DisasmDispInstruction, ()> {
+    fn apply_operand_separator(&self, literal: &str, opts: &DisplayOptions) -> String {
+        match &opts.operand_separator {
+            Some(sep) => literal.replace(",", sep),
+            None => literal.to_string(),
+        }
+    }
+
+    // where the instruction after the one at `at` starts. stepping code should use
+    // this instead of adding lengths itself. it shares disasm_len's length-only
+    // walk, so it never builds operands or display text.
+    pub fn next_instruction_addr(&self, mem: &dyn MemView, at: u64) -> Result<u64, DisasmError> {
+        let length = self.disasm_len(mem, at)?;
+        at.checked_add(length).ok_or(DisasmError::Truncated)
+    }
+
+    // only the length of the instruction at `at`. constructors are still
+    // resolved and operand offsets followed, but nothing for display is kept.
+    pub fn disasm_len(&self, mem: &dyn MemView, at: u64) -> Result<u64, DisasmError> {
+        let mut trace = DisasmTrace::new();
+        let prototype = self.disasm_proto_impl::<false, true, false>(
+            mem,
+            at,
+            &self.initial_ctx,
+            Some(&self.initial_ctx),
+            &mut trace,
+        )?;
+        Ok(prototype.length)
+    }
+
+    // the p-code for the instruction at `at`, see PcodeBuilder
+    pub fn get_pcode(&self, mem: &dyn MemView, at: u64) -> Result<Vec<PcodeOp>, DisasmError> {
+        let length = self.disasm_len(mem, at)?;
+        let edge_mem = DisasmEdgeMemView::new(mem);
+        let mut state = DisasmState::new(&edge_mem, self.initial_ctx.clone(), at);
+        state.set_end_ins(at + length);
+        state.set_next2_ctx(&self.initial_ctx);
+
+        let (subsym_id, subtable_sym) = self.get_instruction_subtable();
+        PcodeBuilder::new(self, state).build(subsym_id, subtable_sym)
+    }
+
+    // returns only the mnemonic and the instruction length. the constructor tree
+    // still has to be walked to know the length, but no operand expressions are
+    // evaluated and no display runs are built, so this is much cheaper for indexing.
+    pub fn disasm_mnemonic(&self, mem: &dyn MemView, at: u64) -> Result<(String, u64), DisasmError> {
+        let prototype = self.disasm_proto(mem, at)?;
+
+        // same rule as get_proto_display: the mnemonic ends at the first space
+        let mut mnemonic = String::new();
+        for part in &prototype.parts {
+            match part {
+                DisasmProtoPart::Literal(v) => {
+                    if let Some(space_idx) = v.find(' ') {
+                        mnemonic += &v[..space_idx];
+                        break;
+                    }
+                    mnemonic += v;
+                }
+                _ => break,
+            }
+        }
+
+        Ok((mnemonic, prototype.length))
+    }
+
+    pub fn disasm_display(&self, mem: &dyn MemView, at: u64) -> Result<DisasmDispInstruction, DisasmError> {
+        self.disasm_display_opts(mem, at, &DisplayOptions::default())
+    }
+
+    // same as disasm_display but decodes with ctx instead of initial_ctx, see disasm_proto_ctx
+    pub fn disasm_display_ctx(
+        &self,
+        mem: &dyn MemView,
+        at: u64,
+        ctx: &[u32],
+    ) -> Result<DisasmDispInstruction, DisasmError> {
+        self.check_ctx_size(ctx)?;
+        self.disasm_display_impl(mem, at, ctx, &DisplayOptions::default())
+    }
+
+    // same as disasm_display, but also fills raw_bytes with the instruction's encoding
+    pub fn disasm_display_with_bytes(&self, mem: &dyn MemView, at: u64) -> Result<DisasmDispInstruction, DisasmError> {
+        let opts = DisplayOptions {
+            include_bytes: true,
+            ..DisplayOptions::default()
+        };
+        self.disasm_display_opts(mem, at, &opts)
+    }
+
+    // up to count instructions back to back from start. stops early at the
+    // first one that doesn't decode, which is only an error if it's the first.
+    pub fn disasm_display_range(
+        &self,
+        mem: &dyn MemView,
+        start: u64,
+        count: usize,
+    ) -> Result<Vec<DisasmDispInstruction>, DisasmError> {
+        let mut instructions = Vec::with_capacity(count);
+        let mut at = start;
+        while instructions.len() < count {
+            let ins = match self.disasm_display(mem, at) {
+                Ok(v) => v,
+                Err(e) if instructions.is_empty() => return Err(e),
+                Err(_) => break,
+            };
+            let next = at.checked_add(ins.len);
+            instructions.push(ins);
+            match next {
+                Some(v) => at = v,
+                None => break,
+            }
+        }
+        Ok(instructions)
+    }
+
+    // run lengths always match the formatted text, padding included
+    pub fn disasm_display_opts(
+        &self,
+        mem: &dyn MemView,
+        at: u64,
+        opts: &DisplayOptions,
+    ) -> Result<DisasmDispInstruction, DisasmError> {
+        self.disasm_display_impl(mem, at, &self.initial_ctx, opts)
+    }
+
+    fn disasm_display_impl(
+        &self,
+        mem: &dyn MemView,
+        at: u64,
+        ctx: &[u32],
+        opts: &DisplayOptions,
+    ) -> Result<DisasmDispInstruction, DisasmError> {
         let at_val = at;
-        let prototype = self.disasm_proto(mem, at_val)?;
-        let (text, runs) = self.get_proto_display(mem, at_val, at_val + prototype.length, &prototype)?;
+        let mut trace = DisasmTrace::new();
+        let prototype = self.disasm_proto_impl::<false, false, false>(mem, at_val, ctx, Some(ctx), &mut trace)?;
+        let edge_mem = DisasmEdgeMemView::new(mem);
+        let (text, runs) =
+            self.get_proto_display(&edge_mem, at_val, at_val + prototype.length, ctx, &prototype, opts)?;
+
+        let raw_bytes = if opts.include_bytes {
+            let mut raw_bytes = vec![0u8; prototype.length as usize];
+            let mut raw_at = at_val;
+            mem.read_bytes(&mut raw_at, &mut raw_bytes, prototype.length as i32)
+                .or(Err(DisasmError::Truncated))?;
+            raw_bytes
+        } else {
+            Vec::new()
+        };
 
         let display_ins = DisasmDispInstruction {
             addr: at_val,
             len: prototype.length,
             text,
             runs,
+            raw_bytes,
         };
         Ok(display_ins)
     }
+
+    // a text listing of every instruction from start up to end, one per line as
+    // `addr: bytes  text`. the columns are padded so they line up across the
+    // whole listing. bytes that don't decode are listed as `<invalid>` and
+    // skipped the same way a sweep would.
+    pub fn listing(&self, mem: &dyn MemView, start: u64, end: u64, opts: &ListingOptions) -> String {
+        let display_opts = DisplayOptions {
+            include_bytes: true,
+            ..opts.display.clone()
+        };
+        let skip_len = self.min_instruction_length() as u64;
+
+        // (addr, bytes, text), collected first so the columns can be sized
+        let mut lines: Vec<(u64, String, String)> = Vec::new();
+        let mut at = start;
+        while at < end {
+            let (len, bytes, text) = match self.disasm_display_opts(mem, at, &display_opts) {
+                Ok(ins) => (ins.len, ins.raw_bytes, ins.text),
+                Err(_) => {
+                    let mut bytes = vec![0u8; skip_len as usize];
+                    let mut read_at = at;
+                    match mem.read_bytes(&mut read_at, &mut bytes, skip_len as i32) {
+                        Ok(_) => (skip_len, bytes, "<invalid>".to_string()),
+                        Err(_) => (skip_len, Vec::new(), "<unreadable>".to_string()),
+                    }
+                }
+            };
+
+            let bytes_text = bytes.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ");
+            lines.push((at, bytes_text, text));
+            at = match at.checked_add(len) {
+                Some(v) => v,
+                None => break,
+            };
+        }
+
+        let addr_width = lines.iter().map(|l| format!("{:x}", l.0).len()).max().unwrap_or(0);
+        let bytes_width = lines.iter().map(|l| l.1.len()).max().unwrap_or(0);
+
+        let mut listing = String::new();
+        for (addr, bytes_text, text) in &lines {
+            if let Some(annotate) = opts.annotate {
+                if let Some(label) = annotate(*addr) {
+                    listing += &format!("{}:\n", label);
+                }
+            }
+            listing += &format!(
+                "{:0addr_width$x}: {:bytes_width$}  {}\n",
+                addr,
+                bytes_text,
+                text,
+                addr_width = addr_width,
+                bytes_width = bytes_width
+            );
+        }
+        listing
+    }
+}
+
+// the x86-64 spec at the root of the repo, shared by tests that need to decode
+#[cfg(test)]
+pub(crate) fn x86_64_disasm() -> Disasm {
+    use crate::sleigh::pspec_file::Pspec;
+
+    let root = concat!(env!("CARGO_MANIFEST_DIR"), "/..");
+    let sla_data = std::fs::read(format!("{}/x86-64.sla", root)).expect("can't read sla");
+    let pspec_data = std::fs::read_to_string(format!("{}/x86-64.pspec", root)).expect("can't read pspec");
+    let sleigh = Sleigh::new(&sla_data);
+    let initial_ctx = Pspec::new(pspec_data).unwrap().get_initial_ctx(&sleigh).unwrap();
+    Disasm::new(sleigh, initial_ctx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{composite_memview::CompositeMemView, memview::StaticMemView};
+    use crate::sleigh::memory::write_ctx_u32_bits_range;
+    use std::fs;
+
+    fn set_ctx_field(disasm: &Disasm, ctx: &mut [u32], name: &str, value: u32) {
+        let sym_idx = disasm.sleigh.symbol_table.scopes[0].lookup[name];
+        let SymbolInner::ContextSym(sym) = &disasm.sleigh.symbol_table.symbols[sym_idx].inner else {
+            panic!("{} isn't a context field", name);
+        };
+        write_ctx_u32_bits_range(ctx, sym.low, sym.high, value);
+    }
+
+    #[test]
+    fn ctx_override_is_per_call() {
+        let disasm = x86_64_disasm();
+        // mov rbx, rax in long mode, dec eax in 32-bit mode
+        let mem = StaticMemView::new(vec![0x48, 0x89, 0xc3]);
+
+        let mut ctx = disasm.initial_ctx.clone();
+        set_ctx_field(&disasm, &mut ctx, "longMode", 0);
+        set_ctx_field(&disasm, &mut ctx, "addrsize", 1);
+
+        let ins32 = disasm.disasm_display_ctx(&mem, 0, &ctx).unwrap();
+        assert_eq!(ins32.len, 1);
+        assert_eq!(disasm.disasm_proto_ctx(&mem, 0, &ctx).unwrap().length, 1);
+
+        // the disassembler's own context isn't touched
+        let ins64 = disasm.disasm_display(&mem, 0).unwrap();
+        assert_eq!(ins64.len, 3);
+
+        assert_eq!(
+            disasm.disasm_display_ctx(&mem, 0, &ctx[1..]).err(),
+            Some(DisasmError::ContextSizeMismatch)
+        );
+    }
+
+    #[test]
+    fn errors_say_what_failed() {
+        let disasm = x86_64_disasm();
+
+        // 0x06 (push es) doesn't exist in long mode
+        let mem = StaticMemView::new(vec![0x06, 0x00, 0x00, 0x00]);
+        assert_eq!(
+            disasm.disasm_display(&mem, 0).unwrap_err(),
+            DisasmError::PatternNotFound
+        );
+
+        let mut mem = CompositeMemView::new();
+        mem.add_view(0x1000, 4, Box::new(StaticMemView::new(vec![0x90; 4])))
+            .unwrap();
+        assert_eq!(
+            disasm.disasm_proto(&mem, 0x10).err(),
+            Some(DisasmError::InvalidRead(MemViewError::Unmapped))
+        );
+        assert_eq!(disasm.disasm_proto(&mem, 0x1004).err(), Some(DisasmError::Truncated));
+    }
+
+    #[test]
+    fn truncated_is_not_invalid_encoding() {
+        let disasm = x86_64_disasm();
+
+        // nop; ret. both are shorter than a word, so they only decode because
+        // the reads past the end are padded
+        let mem = StaticMemView::new(vec![0x90, 0xc3]);
+        assert_eq!(disasm.disasm_display(&mem, 0).unwrap().len, 1);
+        let ret = disasm.disasm_display_with_bytes(&mem, 1).unwrap();
+        assert_eq!(ret.len, 1);
+        assert_eq!(ret.raw_bytes, vec![0xc3]);
+        assert_eq!(disasm.disasm_display(&mem, 2).unwrap_err(), DisasmError::Truncated);
+
+        // mov rbx, rax with the last byte cut off
+        let mem = StaticMemView::new(vec![0x48, 0x89]);
+        assert_eq!(disasm.disasm_display(&mem, 0).unwrap_err(), DisasmError::Truncated);
+        assert_eq!(disasm.disasm_proto(&mem, 1).err(), Some(DisasmError::Truncated));
+
+        // a bad byte at the end is still bad, not truncated
+        let mem = StaticMemView::new(vec![0x90, 0x06]);
+        assert_eq!(
+            disasm.disasm_display(&mem, 1).unwrap_err(),
+            DisasmError::PatternNotFound
+        );
+    }
+
+    #[test]
+    fn cache_replays_until_bytes_change() {
+        let mut disasm = x86_64_disasm();
+        disasm.set_cache_capacity(16);
+
+        // mov rbx, rax
+        let mem = StaticMemView::new(vec![0x48, 0x89, 0xc3]);
+        let first = disasm.disasm_display(&mem, 0).unwrap();
+        assert_eq!(disasm.disasm_display(&mem, 0).unwrap(), first);
+
+        // same address, but add rbx, rax now. only constructors are cached, so
+        // replaying the old entry would show it as a mov.
+        let mem = StaticMemView::new(vec![0x48, 0x01, 0xc3]);
+        let fresh = disasm.disasm_display(&mem, 0).unwrap();
+        assert_ne!(fresh.text, first.text);
+        assert_eq!(fresh.len, 3);
+        assert_eq!(disasm.disasm_display(&mem, 0).unwrap(), fresh);
+
+        // and back again
+        let mem = StaticMemView::new(vec![0x48, 0x89, 0xc3]);
+        assert_eq!(disasm.disasm_display(&mem, 0).unwrap(), first);
+
+        disasm.invalidate(1, 1);
+        assert_eq!(disasm.disasm_display(&mem, 0).unwrap(), first);
+
+        // tracing never uses the cache
+        let (result, trace) = disasm.disasm_proto_traced(&mem, 0);
+        assert!(result.is_ok());
+        assert!(!trace.tables.is_empty());
+    }
+
+    #[test]
+    fn len_matches_display() {
+        let disasm = x86_64_disasm();
+        // push rbp, mov rbp rsp, mov eax [rbp - 8], call, ret, then half a jmp
+        let bytes = vec![
+            0x55, 0x48, 0x89, 0xe5, 0x8b, 0x45, 0xf8, 0xe8, 0x00, 0x00, 0x00, 0x00, 0xc3, 0xe9, 0x00,
+        ];
+        let mem = StaticMemView::new(bytes);
+
+        let mut at = 0;
+        while at < 13 {
+            let display = disasm.disasm_display(&mem, at).unwrap();
+            assert_eq!(disasm.disasm_len(&mem, at).unwrap(), display.len);
+            at += display.len;
+        }
+        assert_eq!(at, 13);
+        assert_eq!(disasm.disasm_len(&mem, 13), Err(DisasmError::Truncated));
+    }
+
+    #[test]
+    fn instruction_length_bounds() {
+        let disasm = x86_64_disasm();
+        // single byte instructions like nop and ret
+        assert_eq!(disasm.min_instruction_length(), 1);
+        // prefixes nest inside the instruction table, so there's no bound
+        assert_eq!(disasm.max_instruction_length(), None);
+
+        // subtables that don't nest still get one
+        let subtable_max = |name: &str| {
+            let id = disasm.sleigh.symbol_table.scopes[0].lookup[name];
+            let sym_box = &disasm.sleigh.symbol_table.symbols[id as usize];
+            match &sym_box.inner {
+                SymbolInner::SubtableSym(v) => disasm.get_subtable_max_length(sym_box.id, v, &mut HashMap::new()),
+                _ => panic!("{} isn't a subtable", name),
+            }
+        };
+        // a full 64-bit address
+        assert_eq!(subtable_max("moffs64"), Some(8));
+        // modrm, sib and a 32-bit displacement
+        assert_eq!(subtable_max("addr64"), Some(6));
+    }
+
+    #[test]
+    fn next_instruction_addr_follows_display() {
+        let mut disasm = x86_64_disasm();
+        // same code as len_matches_display
+        let bytes = vec![
+            0x55, 0x48, 0x89, 0xe5, 0x8b, 0x45, 0xf8, 0xe8, 0x00, 0x00, 0x00, 0x00, 0xc3, 0xe9, 0x00,
+        ];
+        let mem = StaticMemView::new(bytes);
+
+        // the second pass goes through the length-only walk with cached constructors
+        disasm.set_cache_capacity(16);
+        for _ in 0..2 {
+            let mut at = 0;
+            while at < 13 {
+                let next = disasm.next_instruction_addr(&mem, at).unwrap();
+                assert_eq!(next, at + disasm.disasm_display(&mem, at).unwrap().len);
+                at = next;
+            }
+            assert_eq!(at, 13);
+            assert_eq!(disasm.next_instruction_addr(&mem, 13), Err(DisasmError::Truncated));
+        }
+    }
+
+    #[test]
+    fn display_round_trips_through_ffi() {
+        let disasm = x86_64_disasm();
+        // push rbp; mov rbp, rsp; mov eax, dword ptr [rbp - 0x8]; call 0x...; ret
+        let mem = StaticMemView::new(vec![
+            0x55, 0x48, 0x89, 0xe5, 0x8b, 0x45, 0xf8, 0xe8, 0x10, 0x00, 0x00, 0x00, 0xc3,
+        ]);
+
+        let mut at = 0;
+        while at < 13 {
+            let ins = disasm.disasm_display_with_bytes(&mem, at).unwrap();
+            let ptr = pheap_alloc(&ins, None);
+            let read_back = unsafe { DisasmDispInstructionFfi::deserialize(ptr) };
+            crate::ffi::core_framework::pheap_free(ptr);
+
+            assert_eq!(read_back, ins);
+            at += ins.len;
+        }
+    }
+
+    #[test]
+    fn listing_lines_up_columns() {
+        let disasm = x86_64_disasm();
+        // push rbp; mov rbp,rsp; bad byte; ret
+        let mem = StaticMemView::new(vec![0x55, 0x48, 0x89, 0xe5, 0x06, 0xc3]);
+
+        let annotate = |addr: u64| if addr == 0 { Some("main".to_string()) } else { None };
+        let opts = ListingOptions {
+            annotate: Some(&annotate),
+            ..ListingOptions::default()
+        };
+        let listing = disasm.listing(&mem, 0, 6, &opts);
+        let lines = listing.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "main:");
+        assert_eq!(lines[1], "0: 55        PUSH RBP");
+        assert_eq!(lines[2], "1: 48 89 e5  MOV RBP,RSP");
+        assert_eq!(lines[3], "4: 06        <invalid>");
+        assert_eq!(lines[4], "5: c3        RET");
+    }
+
+    #[test]
+    fn display_opts_pad_mnemonic_and_separate_operands() {
+        let disasm = x86_64_disasm();
+        // mov rbx, rax; ret
+        let mem = StaticMemView::new(vec![0x48, 0x89, 0xc3, 0xc3]);
+
+        let default_ins = disasm.disasm_display_opts(&mem, 0, &DisplayOptions::default()).unwrap();
+        assert_eq!(default_ins, disasm.disasm_display(&mem, 0).unwrap());
+        assert_eq!(default_ins.text, "MOV RBX,RAX");
+
+        let opts = DisplayOptions {
+            mnemonic_width: 8,
+            operand_separator: Some(", ".to_string()),
+            include_bytes: false,
+        };
+        let ins = disasm.disasm_display_opts(&mem, 0, &opts).unwrap();
+        assert_eq!(ins.text, "MOV     RBX, RAX");
+        assert_eq!(ins.len, 3);
+
+        // the runs still cover the padded text exactly
+        let mut run_at = 0;
+        let mut run_texts = Vec::new();
+        for run in &ins.runs {
+            let run_end = run_at + run.length as usize;
+            run_texts.push((&ins.text[run_at..run_end], run.run_type));
+            run_at = run_end;
+        }
+        assert_eq!(run_at, ins.text.len());
+        assert_eq!(run_texts[0], ("MOV", DisasmDispInstructionRunType::Mnemonic));
+        assert!(run_texts.contains(&("RBX", DisasmDispInstructionRunType::Register)));
+        assert!(run_texts.contains(&("RAX", DisasmDispInstructionRunType::Register)));
+
+        // a mnemonic wider than the column still gets one space
+        let opts = DisplayOptions {
+            mnemonic_width: 2,
+            ..DisplayOptions::default()
+        };
+        assert_eq!(disasm.disasm_display_opts(&mem, 0, &opts).unwrap().text, "MOV RBX,RAX");
+
+        // no operands, nothing to pad
+        let opts = DisplayOptions {
+            mnemonic_width: 8,
+            ..DisplayOptions::default()
+        };
+        assert_eq!(disasm.disasm_display_opts(&mem, 3, &opts).unwrap().text, "RET");
+    }
+
+    #[test]
+    fn inst_next2_decodes_one_instruction_ahead() {
+        let disasm = x86_64_disasm();
+        // push rbp; mov rbp,rsp
+        let mem = StaticMemView::new(vec![0x55, 0x48, 0x89, 0xe5]);
+        let stack = DisasmOperandStackItem {
+            read_position: 0,
+            subsym_id: 0,
+            ctor_idx: 0,
+            operand_ids: Vec::new(),
+        };
+        let next2 = Expression::Next2InstructionValue;
+
+        let mut state = DisasmState::new(&mem, disasm.initial_ctx.clone(), 0);
+        state.set_end_ins(1);
+        // without a context to decode with, it's the same as inst_next
+        assert_eq!(next2.evaluate(&disasm, &state, &stack, 0), 1);
+
+        state.set_next2_ctx(&disasm.initial_ctx);
+        assert_eq!(next2.evaluate(&disasm, &state, &stack, 0), 4);
+
+        // nothing to decode past the end, so fall back to inst_next
+        state.set_end_ins(4);
+        assert_eq!(next2.evaluate(&disasm, &state, &stack, 0), 4);
+    }
+
+    #[test]
+    fn inst_next2_in_context_ops_decodes_ahead() {
+        let mut disasm = x86_64_disasm();
+        // a spare context word nothing in the spec reads, which every
+        // instruction constructor sets to inst_next2
+        let spare_word = disasm.initial_ctx.len();
+        disasm.initial_ctx.push(0);
+        let instruction_idx = disasm.sleigh.symbol_table.scopes[0].lookup["instruction"];
+        let SymbolInner::SubtableSym(subtable) = &mut disasm.sleigh.symbol_table.symbols[instruction_idx].inner else {
+            panic!("instruction isn't a subtable");
+        };
+        for ctor in &mut subtable.ctors {
+            ctor.context_ops.push(ContextOpTpl {
+                word_start: spare_word as i32,
+                bit_shift: 0,
+                mask: u32::MAX,
+                expression: Expression::Next2InstructionValue,
+            });
+        }
+
+        // jmp $+2; push rbp; mov rbp,rsp
+        let mem = StaticMemView::new(vec![0xeb, 0x00, 0x55, 0x48, 0x89, 0xe5]);
+        let proto = disasm.disasm_proto(&mem, 0).unwrap();
+        let saved_ctx = proto.parts.iter().rev().find_map(|part| match part {
+            DisasmProtoPart::SymbolInfo(v) => Some(&v.saved_ctx),
+            DisasmProtoPart::ExpressionInfo(v) => Some(&v.saved_ctx),
+            _ => None,
+        });
+        assert_eq!(saved_ctx.unwrap()[spare_word], 3);
+        assert_eq!(disasm.disasm_display(&mem, 0).unwrap().text, "JMP 0x2");
+    }
+
+    #[test]
+    fn operand_ranges_cover_their_bytes() {
+        let disasm = x86_64_disasm();
+        let ranges = |bytes: Vec<u8>| -> Vec<Option<(u32, u32)>> {
+            let mem = StaticMemView::new(bytes);
+            let ranges = disasm.disasm_operand_ranges(&mem, 0).unwrap();
+            ranges.iter().map(|r| r.map(|v| (v.start, v.end))).collect()
+        };
+
+        // mov eax,0x12345678: the register is in the opcode byte
+        assert_eq!(
+            ranges(vec![0xb8, 0x78, 0x56, 0x34, 0x12]),
+            vec![Some((0, 1)), Some((1, 5))]
+        );
+        // mov rax,qword ptr [rsp + 0x8]: the memory operand is modrm, sib and disp8
+        assert_eq!(
+            ranges(vec![0x48, 0x8b, 0x44, 0x24, 0x08]),
+            vec![Some((2, 5)), Some((2, 3))]
+        );
+        // add ax,0x1234: ax is implied by the opcode, so it has no bytes of its own
+        assert_eq!(ranges(vec![0x66, 0x05, 0x34, 0x12]), vec![None, Some((2, 4))]);
+        assert_eq!(ranges(vec![0xc3]), vec![]);
+
+        // only worked out when asked for
+        let mem = StaticMemView::new(vec![0xb8, 0x78, 0x56, 0x34, 0x12]);
+        assert!(disasm.disasm_proto(&mem, 0).unwrap().operand_ranges.is_empty());
+    }
+
+    #[test]
+    fn pcode_for_simple_instructions() {
+        use crate::sleigh::pcode::{PcodeOp, PcodeOpcode, Varnode};
+        let disasm = x86_64_disasm();
+        let pcode = |bytes: Vec<u8>| disasm.get_pcode(&StaticMemView::new(bytes), 0);
+        let (unique, ram, register) = (2, 3, 4);
+        let (rax, rbx) = (Varnode::new(register, 0x0, 8), Varnode::new(register, 0x18, 8));
+
+        // mov rax, rbx
+        let ops = pcode(vec![0x48, 0x89, 0xd8]).unwrap();
+        assert_eq!(
+            ops,
+            vec![PcodeOp {
+                opcode: PcodeOpcode::Copy,
+                output: Some(rax),
+                inputs: vec![rbx],
+            }]
+        );
+
+        // jmp +5 goes to inst_next + 5
+        let ops = pcode(vec![0xeb, 0x05]).unwrap();
+        assert_eq!(ops[0].opcode, PcodeOpcode::Branch);
+        assert_eq!(ops[0].inputs, vec![Varnode::new(ram, 7, 8)]);
+
+        // mov rax, [rbx] loads into a temporary first
+        let ops = pcode(vec![0x48, 0x8b, 0x03]).unwrap();
+        assert_eq!(ops.len(), 2);
+        assert_eq!(ops[0].opcode, PcodeOpcode::Load);
+        assert_eq!(ops[0].inputs, vec![Varnode::new(0, ram as u64, 8), rbx]);
+        assert_eq!(ops[0].output.unwrap().space, unique);
+        assert_eq!(ops[1].opcode, PcodeOpcode::Copy);
+        assert_eq!(ops[1].inputs, vec![ops[0].output.unwrap()]);
+
+        // mov [rax], rbx stores after
+        let ops = pcode(vec![0x48, 0x89, 0x18]).unwrap();
+        assert_eq!(ops[1].opcode, PcodeOpcode::Store);
+        assert_eq!(ops[1].inputs[1], rax);
+
+        // add sets flags with ops that aren't supported yet
+        assert_eq!(pcode(vec![0x48, 0x01, 0xd8]), Err(DisasmError::UnsupportedPcodeOp(21)));
+    }
+
+    #[test]
+    #[ignore]
+    fn disasm_len_benchmark() {
+        use crate::binary_formats::elf::file::ElfFile;
+        use std::time::Instant;
+
+        const COUNT: usize = 10000;
+        let disasm = x86_64_disasm();
+        let elf = ElfFile::new(fs::read("/bin/ls").expect("can't read /bin/ls")).unwrap();
+        let mem = elf.as_memview();
+
+        // decode straight through from the entry point, skipping a byte on
+        // anything that doesn't decode so both runs see the same addresses
+        let mut addrs = Vec::with_capacity(COUNT);
+        let mut at = elf.entry();
+        while addrs.len() < COUNT {
+            addrs.push(at);
+            at += disasm.disasm_len(&mem, at).unwrap_or(1);
+        }
+
+        let start = Instant::now();
+        for &addr in &addrs {
+            let _ = std::hint::black_box(disasm.disasm_len(&mem, addr));
+        }
+        let len_elapsed = start.elapsed();
+
+        let start = Instant::now();
+        for &addr in &addrs {
+            let _ = std::hint::black_box(disasm.disasm_display(&mem, addr));
+        }
+        let display_elapsed = start.elapsed();
+
+        println!(
+            "{} instructions: disasm_len {:?}, disasm_display {:?}",
+            COUNT, len_elapsed, display_elapsed
+        );
+    }
 }
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+// what one decode found: the constructor picked by each resolve_ctor call, in
+// the order they happened. the prototype borrows from the sleigh file so it
+// can't be kept around, but with these the walk can be replayed without going
+// through any decision trees or patterns. bytes are the instruction's bytes
+// at the time, an entry is only good while memory still has them.
+pub struct DisasmCacheEntry {
+    pub length: u64,
+    pub bytes: Vec<u8>,
+    pub ctx: Vec<u32>, // the context it was decoded with
+    pub ctor_ids: Vec<u32>,
+}
+
+struct DisasmCacheSlot {
+    entry: Arc<DisasmCacheEntry>,
+    last_used: u64,
+}
+
+// small lru of decoded instructions keyed on (address, context hash). the
+// context itself is compared too, so two contexts with the same hash never
+// share an entry. the same address can hold different code over time, so a
+// hit is only a candidate until the caller checks its bytes.
+pub struct DisasmCache {
+    capacity: usize,
+    tick: u64,
+    slots: HashMap<(u64, u64), DisasmCacheSlot>,
+}
+
+impl DisasmCache {
+    pub const DEFAULT_CAPACITY: usize = 256;
+
+    pub fn new(capacity: usize) -> DisasmCache {
+        DisasmCache {
+            capacity,
+            tick: 0,
+            slots: HashMap::new(),
+        }
+    }
+
+    fn key(at: u64, ctx: &[u32]) -> (u64, u64) {
+        let mut hasher = DefaultHasher::new();
+        ctx.hash(&mut hasher);
+        (at, hasher.finish())
+    }
+
+    // shared with the cache, so a hit doesn't copy the entry
+    pub fn get(&mut self, at: u64, ctx: &[u32]) -> Option<Arc<DisasmCacheEntry>> {
+        self.tick += 1;
+        let slot = self.slots.get_mut(&Self::key(at, ctx))?;
+        if slot.entry.ctx != ctx {
+            return None;
+        }
+        slot.last_used = self.tick;
+        Some(slot.entry.clone())
+    }
+
+    // replaces whatever was cached for the same address and context
+    pub fn insert(&mut self, at: u64, entry: DisasmCacheEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let key = Self::key(at, &entry.ctx);
+        if self.slots.len() >= self.capacity && !self.slots.contains_key(&key) {
+            // capacity is small, so a scan is cheaper than keeping a list in order
+            let oldest = self.slots.iter().min_by_key(|(_, v)| v.last_used).map(|(k, _)| *k);
+            if let Some(oldest) = oldest {
+                self.slots.remove(&oldest);
+            }
+        }
+
+        self.tick += 1;
+        let last_used = self.tick;
+        let entry = Arc::new(entry);
+        self.slots.insert(key, DisasmCacheSlot { entry, last_used });
+    }
+
+    // drops every instruction that has a byte in [addr, addr + len)
+    pub fn invalidate(&mut self, addr: u64, len: u64) {
+        let end = addr.saturating_add(len);
+        self.slots.retain(|(at, _), slot| {
+            let ins_end = at.saturating_add(slot.entry.length);
+            ins_end <= addr || *at >= end
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(length: u64, ctx: &[u32]) -> DisasmCacheEntry {
+        DisasmCacheEntry {
+            length,
+            bytes: vec![0x90; length as usize],
+            ctx: ctx.to_vec(),
+            ctor_ids: vec![0],
+        }
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut cache = DisasmCache::new(2);
+        cache.insert(0, entry(1, &[1]));
+        cache.insert(1, entry(1, &[1]));
+        assert!(cache.get(0, &[1]).is_some());
+        cache.insert(2, entry(1, &[1]));
+        assert!(cache.get(0, &[1]).is_some());
+        assert!(cache.get(1, &[1]).is_none());
+        assert!(cache.get(2, &[1]).is_some());
+
+        // same address, different context
+        assert!(cache.get(0, &[2]).is_none());
+    }
+
+    #[test]
+    fn hits_share_the_entry_and_check_context() {
+        let mut cache = DisasmCache::new(4);
+        cache.insert(0x10, entry(2, &[1, 2]));
+        let first = cache.get(0x10, &[1, 2]).unwrap();
+        let second = cache.get(0x10, &[1, 2]).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+
+        // an entry whose hash matches but context doesn't is a miss
+        let key = DisasmCache::key(0x20, &[3]);
+        let slot = DisasmCacheSlot {
+            entry: Arc::new(entry(1, &[4])),
+            last_used: 0,
+        };
+        cache.slots.insert(key, slot);
+        assert!(cache.get(0x20, &[3]).is_none());
+    }
+
+    #[test]
+    fn invalidate_drops_overlapping_instructions() {
+        let mut cache = DisasmCache::new(8);
+        cache.insert(0x10, entry(4, &[]));
+        cache.insert(0x14, entry(2, &[]));
+        cache.insert(0x16, entry(1, &[]));
+
+        // only touches the last byte of the first instruction
+        cache.invalidate(0x13, 1);
+        assert!(cache.get(0x10, &[]).is_none());
+        assert!(cache.get(0x14, &[]).is_some());
+        assert_eq!(cache.slots.len(), 2);
+
+        cache.invalidate(0, u64::MAX);
+        assert!(cache.slots.is_empty());
+    }
+}
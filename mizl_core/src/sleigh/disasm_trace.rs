@@ -0,0 +1,100 @@
+use std::fmt;
+
+// one step down a decision tree: which bits were read and what they were
+pub struct DisasmTraceDecision {
+    pub context: bool, // bits came from the context instead of instruction bytes
+    pub start: i32,
+    pub size: i32,
+    pub value: u32,
+}
+
+// a constructor pattern checked at the leaf the decision tree ended on
+pub struct DisasmTracePattern {
+    pub ctor_id: i32,
+    pub source: String, // sleigh source file the constructor came from
+    pub line: i32,
+    pub matched: bool,
+}
+
+// one constructor lookup in a subtable, like `instruction` or an operand table
+pub struct DisasmTraceTable {
+    pub name: String,
+    pub at: u64,
+    pub decisions: Vec<DisasmTraceDecision>,
+    pub patterns: Vec<DisasmTracePattern>,
+}
+
+// everything the decoder looked at for one instruction, in order. only filled
+// in by Disasm::disasm_proto_traced. the last table is the one that failed if
+// decoding failed.
+pub struct DisasmTrace {
+    pub tables: Vec<DisasmTraceTable>,
+}
+
+impl DisasmTrace {
+    pub fn new() -> DisasmTrace {
+        DisasmTrace { tables: Vec::new() }
+    }
+
+    pub(crate) fn begin_table(&mut self, name: &str, at: u64) {
+        self.tables.push(DisasmTraceTable {
+            name: name.to_string(),
+            at,
+            decisions: Vec::new(),
+            patterns: Vec::new(),
+        });
+    }
+
+    pub(crate) fn add_decision(&mut self, context: bool, start: i32, size: i32, value: u32) {
+        if let Some(table) = self.tables.last_mut() {
+            table.decisions.push(DisasmTraceDecision {
+                context,
+                start,
+                size,
+                value,
+            });
+        }
+    }
+
+    pub(crate) fn add_pattern(&mut self, ctor_id: i32, source: &str, line: i32, matched: bool) {
+        if let Some(table) = self.tables.last_mut() {
+            table.patterns.push(DisasmTracePattern {
+                ctor_id,
+                source: source.to_string(),
+                line,
+                matched,
+            });
+        }
+    }
+}
+
+impl fmt::Display for DisasmTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for table in &self.tables {
+            writeln!(f, "{} @ {:#x}", table.name, table.at)?;
+            for decision in &table.decisions {
+                let kind = if decision.context { "ctx" } else { "ins" };
+                writeln!(
+                    f,
+                    "  {} bits {}..{} = {:#x}",
+                    kind,
+                    decision.start,
+                    decision.start + decision.size,
+                    decision.value
+                )?;
+            }
+            for pattern in &table.patterns {
+                let result = if pattern.matched { "matched" } else { "no match" };
+                writeln!(
+                    f,
+                    "  ctor {} ({}:{}): {}",
+                    pattern.ctor_id, pattern.source, pattern.line, result
+                )?;
+            }
+            if !table.patterns.iter().any(|p| p.matched) {
+                writeln!(f, "  <pattern not found>")?;
+            }
+        }
+        Ok(())
+    }
+}
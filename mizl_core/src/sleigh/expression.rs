@@ -1,7 +1,8 @@
 use super::disasm::DisasmState;
 use crate::sleigh::consts::{AttributeId, ElementId};
 use crate::sleigh::disasm::{Disasm, DisasmOperandStackItem};
-use crate::sleigh::sla_file::SymbolInner;
+use crate::sleigh::memory::read_ctx_u32_bits_at;
+use crate::sleigh::sla_file::{SleighError, SymbolInner};
 use crate::sleigh::sla_reader::{SlaBinReader, SlaElement};
 
 pub struct TokenField {
@@ -51,7 +52,7 @@ pub enum Expression {
 }
 
 impl TokenField {
-    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> TokenField {
+    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Result<TokenField, SleighError> {
         let big_endian = elem.as_bool_or(AttributeId::Bigendian, false);
         let sign_bit = elem.as_bool_or(AttributeId::Signbit, false);
         let bit_start = elem.as_int_or(AttributeId::Startbit, 0) as i32;
@@ -59,10 +60,10 @@ impl TokenField {
         let byte_start = elem.as_int_or(AttributeId::Startbyte, 0) as i32;
         let byte_end = elem.as_int_or(AttributeId::Endbyte, 0) as i32;
         let shift = elem.as_int_or(AttributeId::Shift, 0) as i32;
-        reader.seek_elem_children_start(elem);
+        reader.seek_elem_children_start(elem)?;
 
-        reader.read_elem_end(elem.id);
-        TokenField {
+        reader.read_elem_end(elem.id)?;
+        Ok(TokenField {
             big_endian,
             sign_bit,
             bit_start,
@@ -70,7 +71,7 @@ impl TokenField {
             byte_start,
             byte_end,
             shift,
-        }
+        })
     }
 
     // todo: should return 0 for unread bytes, not the whole thing
@@ -87,65 +88,74 @@ impl TokenField {
         let mut value: i64 = (read_value >> self.shift) as i64;
 
         value &= (1 << bit_count) - 1;
-        if self.sign_bit && value >= 0 && (value & (1 << (bit_count - 1))) != 0 {
-            // manually sign extend
-            let mask = 1 << (bit_count - 1);
-            value = (value ^ mask) - mask;
+        if self.sign_bit {
+            value = sign_extend(value, bit_count);
         }
         value
     }
 }
 
 impl ContextField {
-    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> ContextField {
+    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Result<ContextField, SleighError> {
         let sign_bit = elem.as_bool_or(AttributeId::Signbit, false);
         let bit_start = elem.as_int_or(AttributeId::Startbit, 0) as i32;
         let bit_end = elem.as_int_or(AttributeId::Endbit, 0) as i32;
         let byte_start = elem.as_int_or(AttributeId::Startbyte, 0) as i32;
         let byte_end = elem.as_int_or(AttributeId::Endbyte, 0) as i32;
         let shift = elem.as_int_or(AttributeId::Shift, 0) as i32;
-        reader.seek_elem_children_start(elem);
+        reader.seek_elem_children_start(elem)?;
 
-        reader.read_elem_end(elem.id);
-        ContextField {
+        reader.read_elem_end(elem.id)?;
+        Ok(ContextField {
             sign_bit,
             bit_start,
             bit_end,
             byte_start,
             byte_end,
             shift,
-        }
+        })
     }
 
     pub fn evaluate(&self, state: &DisasmState) -> i64 {
-        // lazy tn, pls fix later
+        self.evaluate_ctx(state.get_context())
+    }
+
+    fn evaluate_ctx(&self, ctx: &[u32]) -> i64 {
+        // unlike token fields, the bits count from the start of the context,
+        // not from byte_start, so a field can span bytes
         let byte_count = self.byte_end - self.byte_start + 1;
-        let bit_count = (self.bit_end + self.byte_end * 8) - (self.bit_start + self.byte_start * 8) + 1;
+        let bit_count = self.bit_end - self.bit_start + 1;
 
-        let read_value = state.read_ctx_u32_bits_at(self.byte_start * 8, byte_count * 8);
+        let read_value = read_ctx_u32_bits_at(ctx, self.byte_start * 8, byte_count * 8);
         let mut value: i64 = (read_value >> self.shift) as i64;
 
         value &= (1 << bit_count) - 1;
-        if self.sign_bit && value >= 0 && (value & (1 << (bit_count - 1))) != 0 {
-            value = -value;
+        if self.sign_bit {
+            value = sign_extend(value, bit_count);
         }
         value
     }
 }
 
+// treats the low bit_count bits of value as a two's complement number
+fn sign_extend(value: i64, bit_count: i32) -> i64 {
+    let mask = 1 << (bit_count - 1);
+    (value ^ mask) - mask
+}
+
 impl OperandValue {
-    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> OperandValue {
+    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Result<OperandValue, SleighError> {
         let index = elem.as_int_or(AttributeId::Index, 0) as i32;
         let sym_id = elem.as_uint_or(AttributeId::Table, 0) as u32;
         let ctor_idx = elem.as_uint_or(AttributeId::Ct, 0) as u32;
-        reader.seek_elem_children_start(elem);
+        reader.seek_elem_children_start(elem)?;
 
-        reader.read_elem_end(elem.id);
-        OperandValue {
+        reader.read_elem_end(elem.id)?;
+        Ok(OperandValue {
             index,
             sym_id,
             ctor_idx,
-        }
+        })
     }
 
     pub fn evaluate(&self, disasm: &Disasm, state: &DisasmState, top_stack: &DisasmOperandStackItem) -> i64 {
@@ -192,76 +202,103 @@ impl OperandValue {
     }
 }
 
-fn parse_constant_value(reader: &SlaBinReader, elem: &SlaElement) -> i64 {
+// a spec (or the bytes an operand comes from) can make the divisor zero.
+// ghidra's pattern expressions do a plain java long division there and throw,
+// but we'd rather keep decoding, so x / 0 is 0. i64::MIN / -1 doesn't fit in
+// an i64, so it wraps back around to i64::MIN.
+fn div_exp_value(left: i64, right: i64) -> i64 {
+    if right == 0 {
+        return 0;
+    }
+    left.wrapping_div(right)
+}
+
+// shift amounts can also come from instruction bytes. anything outside 0..64
+// (including negative amounts) shifts every bit out. that leaves 0 for a left
+// shift, but a right shift is arithmetic, so it leaves copies of the sign bit.
+fn left_shift_exp_value(left: i64, right: i64) -> i64 {
+    if !(0..64).contains(&right) {
+        return 0;
+    }
+    left << right
+}
+
+fn right_shift_exp_value(left: i64, right: i64) -> i64 {
+    if !(0..64).contains(&right) {
+        return left >> 63;
+    }
+    left >> right
+}
+
+fn parse_constant_value(reader: &SlaBinReader, elem: &SlaElement) -> Result<i64, SleighError> {
     let value = elem.as_int_or(AttributeId::Val, 0);
-    reader.seek_elem_children_start(elem);
-    reader.read_elem_end(elem.id);
-    value
+    reader.seek_elem_children_start(elem)?;
+    reader.read_elem_end(elem.id)?;
+    Ok(value)
 }
 
-fn parse_empty(reader: &SlaBinReader, elem: &SlaElement) {
-    reader.seek_elem_children_start(elem);
-    reader.read_elem_end(elem.id);
+fn parse_empty(reader: &SlaBinReader, elem: &SlaElement) -> Result<(), SleighError> {
+    reader.seek_elem_children_start(elem)?;
+    reader.read_elem_end(elem.id)?;
+    Ok(())
 }
 
-fn parse_single_exp(reader: &SlaBinReader, elem: &SlaElement) -> Box<Expression> {
-    reader.seek_elem_children_start(elem);
-    let mut child_iter = reader.read_elem_children(elem.epos);
+fn parse_single_exp(reader: &SlaBinReader, elem: &SlaElement) -> Result<Box<Expression>, SleighError> {
+    reader.seek_elem_children_start(elem)?;
+    let mut child_iter = reader.read_elem_children(elem.epos)?;
 
-    let exp_ele = child_iter.next().expect("expression missing");
-    let ele = Expression::new(reader, &exp_ele);
+    let exp_ele = SlaBinReader::next_child(&mut child_iter)?;
+    let ele = Expression::new(reader, &exp_ele)?;
 
-    reader.read_elem_end(elem.id);
-    Box::new(ele)
+    reader.read_elem_end(elem.id)?;
+    Ok(Box::new(ele))
 }
 
-fn parse_tuple_exp(reader: &SlaBinReader, elem: &SlaElement) -> Box<(Expression, Expression)> {
-    reader.seek_elem_children_start(elem);
-    let mut child_iter = reader.read_elem_children(elem.epos);
+fn parse_tuple_exp(reader: &SlaBinReader, elem: &SlaElement) -> Result<Box<(Expression, Expression)>, SleighError> {
+    reader.seek_elem_children_start(elem)?;
+    let mut child_iter = reader.read_elem_children(elem.epos)?;
 
-    let left_ele = child_iter.next().expect("left expression missing");
-    let left = Expression::new(reader, &left_ele);
-    let right_ele = child_iter.next().expect("right expression missing");
-    let right = Expression::new(reader, &right_ele);
+    let left_ele = SlaBinReader::next_child(&mut child_iter)?;
+    let left = Expression::new(reader, &left_ele)?;
+    let right_ele = SlaBinReader::next_child(&mut child_iter)?;
+    let right = Expression::new(reader, &right_ele)?;
 
-    reader.read_elem_end(elem.id);
-    Box::new((left, right))
+    reader.read_elem_end(elem.id)?;
+    Ok(Box::new((left, right)))
 }
 
 impl Expression {
-    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Expression {
-        match elem.id {
-            ElementId::Tokenfield => Expression::TokenField(Box::new(TokenField::new(reader, elem))),
-            ElementId::Contextfield => Expression::ContextField(Box::new(ContextField::new(reader, elem))),
-            ElementId::Intb => Expression::ConstantValue(parse_constant_value(reader, elem)),
-            ElementId::OperandExp => Expression::OperandValue(Box::new(OperandValue::new(reader, elem))),
+    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Result<Expression, SleighError> {
+        Ok(match elem.id {
+            ElementId::Tokenfield => Expression::TokenField(Box::new(TokenField::new(reader, elem)?)),
+            ElementId::Contextfield => Expression::ContextField(Box::new(ContextField::new(reader, elem)?)),
+            ElementId::Intb => Expression::ConstantValue(parse_constant_value(reader, elem)?),
+            ElementId::OperandExp => Expression::OperandValue(Box::new(OperandValue::new(reader, elem)?)),
             ElementId::StartExp => {
-                parse_empty(reader, elem);
+                parse_empty(reader, elem)?;
                 Expression::StartInstructionValue
             }
             ElementId::EndExp => {
-                parse_empty(reader, elem);
+                parse_empty(reader, elem)?;
                 Expression::EndInstructionValue
             }
             ElementId::Next2Exp => {
-                parse_empty(reader, elem);
+                parse_empty(reader, elem)?;
                 Expression::Next2InstructionValue
             }
-            ElementId::PlusExp => Expression::AddExpression(parse_tuple_exp(reader, elem)),
-            ElementId::SubExp => Expression::SubExpression(parse_tuple_exp(reader, elem)),
-            ElementId::MultExp => Expression::MultExpression(parse_tuple_exp(reader, elem)),
-            ElementId::DivExp => Expression::DivExpression(parse_tuple_exp(reader, elem)),
-            ElementId::LshiftExp => Expression::LeftShiftExpression(parse_tuple_exp(reader, elem)),
-            ElementId::RshiftExp => Expression::RightShiftExpression(parse_tuple_exp(reader, elem)),
-            ElementId::AndExp => Expression::AndExpression(parse_tuple_exp(reader, elem)),
-            ElementId::OrExp => Expression::OrExpression(parse_tuple_exp(reader, elem)),
-            ElementId::XorExp => Expression::XorExpression(parse_tuple_exp(reader, elem)),
-            ElementId::MinusExp => Expression::NegExpression(parse_single_exp(reader, elem)),
-            ElementId::NotExp => Expression::NotExpression(parse_single_exp(reader, elem)),
-            _ => {
-                panic!("unsupported pattern expression type")
-            }
-        }
+            ElementId::PlusExp => Expression::AddExpression(parse_tuple_exp(reader, elem)?),
+            ElementId::SubExp => Expression::SubExpression(parse_tuple_exp(reader, elem)?),
+            ElementId::MultExp => Expression::MultExpression(parse_tuple_exp(reader, elem)?),
+            ElementId::DivExp => Expression::DivExpression(parse_tuple_exp(reader, elem)?),
+            ElementId::LshiftExp => Expression::LeftShiftExpression(parse_tuple_exp(reader, elem)?),
+            ElementId::RshiftExp => Expression::RightShiftExpression(parse_tuple_exp(reader, elem)?),
+            ElementId::AndExp => Expression::AndExpression(parse_tuple_exp(reader, elem)?),
+            ElementId::OrExp => Expression::OrExpression(parse_tuple_exp(reader, elem)?),
+            ElementId::XorExp => Expression::XorExpression(parse_tuple_exp(reader, elem)?),
+            ElementId::MinusExp => Expression::NegExpression(parse_single_exp(reader, elem)?),
+            ElementId::NotExp => Expression::NotExpression(parse_single_exp(reader, elem)?),
+            _ => return Err(SleighError::DecodeFailed),
+        })
     }
 
     pub fn evaluate(&self, disasm: &Disasm, state: &DisasmState, top_stack: &DisasmOperandStackItem, at: u64) -> i64 {
@@ -276,7 +313,7 @@ impl Expression {
             }
             Expression::StartInstructionValue => state.get_start_ins(),
             Expression::EndInstructionValue => state.get_end_ins(),
-            Expression::Next2InstructionValue => state.get_next2_ins(),
+            Expression::Next2InstructionValue => state.get_next2_ins(disasm),
             Expression::AddExpression(sub_exp) => {
                 let left = sub_exp.0.evaluate(disasm, state, top_stack, at);
                 let right = sub_exp.1.evaluate(disasm, state, top_stack, at);
@@ -295,17 +332,17 @@ impl Expression {
             Expression::DivExpression(div_exp) => {
                 let left = div_exp.0.evaluate(disasm, state, top_stack, at);
                 let right = div_exp.1.evaluate(disasm, state, top_stack, at);
-                left / right
+                div_exp_value(left, right)
             }
             Expression::LeftShiftExpression(ls_exp) => {
                 let left = ls_exp.0.evaluate(disasm, state, top_stack, at);
                 let right = ls_exp.1.evaluate(disasm, state, top_stack, at);
-                left << right
+                left_shift_exp_value(left, right)
             }
             Expression::RightShiftExpression(rs_exp) => {
                 let left = rs_exp.0.evaluate(disasm, state, top_stack, at);
                 let right = rs_exp.1.evaluate(disasm, state, top_stack, at);
-                left >> right
+                right_shift_exp_value(left, right)
             }
             Expression::AndExpression(and_exp) => {
                 let left = and_exp.0.evaluate(disasm, state, top_stack, at);
@@ -327,3 +364,75 @@ impl Expression {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn out_of_range_shifts_shift_everything_out() {
+        assert_eq!(left_shift_exp_value(1, 63), i64::MIN);
+        assert_eq!(left_shift_exp_value(1, 64), 0);
+        assert_eq!(left_shift_exp_value(1, -1), 0);
+        assert_eq!(right_shift_exp_value(-8, 1), -4);
+        assert_eq!(right_shift_exp_value(-8, 63), -1);
+        assert_eq!(right_shift_exp_value(-8, 64), -1);
+        assert_eq!(right_shift_exp_value(-8, -1), -1);
+        assert_eq!(right_shift_exp_value(8, 64), 0);
+        assert_eq!(right_shift_exp_value(8, -1), 0);
+    }
+
+    #[test]
+    fn division_by_zero_is_zero() {
+        use crate::memory::memview::StaticMemView;
+        use crate::sleigh::disasm::x86_64_disasm;
+
+        assert_eq!(div_exp_value(7, 2), 3);
+        assert_eq!(div_exp_value(-7, 2), -3);
+        assert_eq!(div_exp_value(7, 0), 0);
+        assert_eq!(div_exp_value(i64::MIN, 0), 0);
+        assert_eq!(div_exp_value(i64::MIN, -1), i64::MIN);
+
+        // and through a whole expression, like a spec dividing by an operand that decoded to 0
+        let disasm = x86_64_disasm();
+        let mem = StaticMemView::new(vec![0x90]);
+        let state = DisasmState::new(&mem, disasm.initial_ctx.clone(), 0);
+        let top_stack = DisasmOperandStackItem {
+            read_position: 0,
+            subsym_id: 0,
+            ctor_idx: 0,
+            operand_ids: Vec::new(),
+        };
+        let exp = Expression::DivExpression(Box::new((
+            Expression::ConstantValue(5),
+            Expression::SubExpression(Box::new((Expression::ConstantValue(3), Expression::ConstantValue(3)))),
+        )));
+        assert_eq!(exp.evaluate(&disasm, &state, &top_stack, 0), 0);
+    }
+
+    #[test]
+    fn context_fields_sign_extend() {
+        // bits 15-18 of the context, so it straddles bytes 1 and 2
+        let field = ContextField {
+            sign_bit: true,
+            bit_start: 15,
+            bit_end: 18,
+            byte_start: 1,
+            byte_end: 2,
+            shift: 5,
+        };
+
+        // 0b1110 with bits 14 and 19 set around it, which shouldn't leak in
+        let ctx = [0b1_1110_1u32 << 12];
+        assert_eq!(field.evaluate_ctx(&ctx), -2);
+        let ctx = [0b1_0111_1u32 << 12];
+        assert_eq!(field.evaluate_ctx(&ctx), 7);
+
+        let unsigned = ContextField {
+            sign_bit: false,
+            ..field
+        };
+        let ctx = [0b1_1110_1u32 << 12];
+        assert_eq!(unsigned.evaluate_ctx(&ctx), 14);
+    }
+}
@@ -0,0 +1,290 @@
+use super::disasm::{Disasm, DisasmDispInstruction, DisasmDispInstructionRunType, DisasmError};
+use crate::memory::composite_memview::CompositeMemView;
+use crate::memory::memview::{MemView, StaticMemView};
+use crate::shared::fast_util::i64_to_str_fast;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InstructionEditError {
+    // there's no number operand at that index
+    NoSuchOperand,
+    // no change to the instruction's bytes decodes to the requested value
+    NotInvertible,
+}
+
+impl fmt::Display for InstructionEditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InstructionEditError::NoSuchOperand => write!(f, "the instruction has no number operand at that index"),
+            InstructionEditError::NotInvertible => write!(f, "the value can't be encoded into this instruction"),
+        }
+    }
+}
+
+// an instruction that can be edited and turned back into bytes. only number
+// operands (immediates, displacements, branch targets) can be changed for now,
+// editing registers or the mnemonic needs a real assembler.
+//
+// there's no way to run sleigh's patterns backwards here, so an edit is found
+// by trying to write the value into each small window of the instruction's
+// bytes and keeping the first one that decodes back to the same instruction
+// with only that number changed. relative operands work as long as the value
+// shown is the raw field plus some constant.
+pub struct InstructionModel {
+    addr: u64,
+    bytes: Vec<u8>,
+    display: DisasmDispInstruction,
+}
+
+impl InstructionModel {
+    pub fn new(disasm: &Disasm, mem: &dyn MemView, addr: u64) -> Result<InstructionModel, DisasmError> {
+        let display = disasm.disasm_display_with_bytes(mem, addr)?;
+        let bytes = display.raw_bytes.clone();
+        Ok(InstructionModel { addr, bytes, display })
+    }
+
+    pub fn addr(&self) -> u64 {
+        self.addr
+    }
+
+    // the instruction's current encoding, edits included
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn display(&self) -> &DisasmDispInstruction {
+        &self.display
+    }
+
+    // number of runs in the display that are numbers, in display order
+    pub fn number_operand_count(&self) -> usize {
+        self.number_run_spans().len()
+    }
+
+    pub fn number_operand_value(&self, idx: usize) -> Option<i64> {
+        let (start, end) = *self.number_run_spans().get(idx)?;
+        parse_number_text(&self.display.text[start..end])
+    }
+
+    // rewrites the bytes so the idx-th number in the display becomes value.
+    // the instruction has to stay the same length.
+    pub fn set_number_operand(&mut self, disasm: &Disasm, idx: usize, value: i64) -> Result<(), InstructionEditError> {
+        let spans = self.number_run_spans();
+        let run_idx = self.number_run_indices();
+        let (start, end) = *spans.get(idx).ok_or(InstructionEditError::NoSuchOperand)?;
+        let cur_value = parse_number_text(&self.display.text[start..end]).ok_or(InstructionEditError::NoSuchOperand)?;
+        if cur_value == value {
+            return Ok(());
+        }
+
+        let big_endian = disasm.sleigh.is_big_endian();
+        let len = self.bytes.len();
+        for width in [1usize, 2, 4, 8] {
+            if width > len {
+                break;
+            }
+
+            // fields usually sit at the end of an instruction, so check there first
+            for pos in (0..=len - width).rev() {
+                let window = &self.bytes[pos..pos + width];
+                for signed in [true, false] {
+                    let raw = read_window(window, big_endian, signed);
+                    // whatever the spec adds to the field (like inst_next) stays the same
+                    let new_raw = value as i128 - (cur_value as i128 - raw);
+                    let Some(new_window) = write_window(new_raw, width, big_endian, signed) else {
+                        continue;
+                    };
+                    if new_window == window {
+                        continue;
+                    }
+
+                    let mut candidate = self.bytes.clone();
+                    candidate[pos..pos + width].copy_from_slice(&new_window);
+                    let Ok(new_display) = self.decode(disasm, &candidate) else {
+                        continue;
+                    };
+                    if self.only_run_changed(&new_display, run_idx[idx], value) {
+                        self.bytes = candidate;
+                        self.display = new_display;
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
+        Err(InstructionEditError::NotInvertible)
+    }
+
+    fn decode(&self, disasm: &Disasm, bytes: &[u8]) -> Result<DisasmDispInstruction, DisasmError> {
+        // decode at the real address so relative operands show the same values
+        let mut mem = CompositeMemView::new();
+        mem.add_view(
+            self.addr,
+            bytes.len() as u64,
+            Box::new(StaticMemView::new(bytes.to_vec())),
+        )
+        .or(Err(DisasmError::Truncated))?;
+        disasm.disasm_display_with_bytes(&mem, self.addr)
+    }
+
+    // same length and same text, except for the one run that now shows value
+    fn only_run_changed(&self, new_display: &DisasmDispInstruction, run_idx: usize, value: i64) -> bool {
+        if new_display.len != self.display.len || new_display.runs.len() != self.display.runs.len() {
+            return false;
+        }
+
+        let old_spans = run_spans(&self.display);
+        let new_spans = run_spans(new_display);
+        for i in 0..old_spans.len() {
+            let (old_start, old_end) = old_spans[i];
+            let (new_start, new_end) = new_spans[i];
+            let old_text = &self.display.text[old_start..old_end];
+            let new_text = &new_display.text[new_start..new_end];
+            if i == run_idx {
+                if new_text != i64_to_str_fast(value) {
+                    return false;
+                }
+            } else if old_text != new_text {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn number_run_indices(&self) -> Vec<usize> {
+        let runs = &self.display.runs;
+        (0..runs.len())
+            .filter(|i| matches!(runs[*i].run_type, DisasmDispInstructionRunType::Number))
+            .collect()
+    }
+
+    fn number_run_spans(&self) -> Vec<(usize, usize)> {
+        let spans = run_spans(&self.display);
+        self.number_run_indices().into_iter().map(|i| spans[i]).collect()
+    }
+}
+
+// byte span of each run in the display text
+fn run_spans(display: &DisasmDispInstruction) -> Vec<(usize, usize)> {
+    let mut spans = Vec::with_capacity(display.runs.len());
+    let mut pos = 0usize;
+    for run in &display.runs {
+        let end = pos + run.length as usize;
+        spans.push((pos, end));
+        pos = end;
+    }
+    spans
+}
+
+// numbers are printed by i64_to_str_fast, so always 0x or -0x
+fn parse_number_text(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(v) => (true, v),
+        None => (false, text),
+    };
+    let digits = digits.strip_prefix("0x")?;
+    let magnitude = u64::from_str_radix(digits, 16).ok()? as i128;
+    let value = if negative { -magnitude } else { magnitude };
+    i64::try_from(value).ok()
+}
+
+fn read_window(window: &[u8], big_endian: bool, signed: bool) -> i128 {
+    let mut value: u64 = 0;
+    for i in 0..window.len() {
+        let b = if big_endian {
+            window[i]
+        } else {
+            window[window.len() - 1 - i]
+        };
+        value = (value << 8) | b as u64;
+    }
+
+    let bits = window.len() * 8;
+    if signed && bits < 64 && (value >> (bits - 1)) & 1 == 1 {
+        value as i128 - (1i128 << bits)
+    } else if signed && bits == 64 {
+        value as i64 as i128
+    } else {
+        value as i128
+    }
+}
+
+fn write_window(value: i128, width: usize, big_endian: bool, signed: bool) -> Option<Vec<u8>> {
+    let bits = width as u32 * 8;
+    let (min, max) = if signed {
+        (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1)
+    } else {
+        (0, (1i128 << bits) - 1)
+    };
+    if value < min || value > max {
+        return None;
+    }
+
+    let raw = value as u64;
+    let mut window: Vec<u8> = (0..width).map(|i| (raw >> (i * 8)) as u8).collect();
+    if big_endian {
+        window.reverse();
+    }
+    Some(window)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sleigh::disasm::x86_64_disasm;
+
+    fn model_at(disasm: &Disasm, addr: u64, bytes: &[u8]) -> InstructionModel {
+        let mut mem = CompositeMemView::new();
+        mem.add_view(addr, bytes.len() as u64, Box::new(StaticMemView::new(bytes.to_vec())))
+            .unwrap();
+        InstructionModel::new(disasm, &mem, addr).unwrap()
+    }
+
+    #[test]
+    fn window_round_trip() {
+        let window = write_window(-2, 2, false, true).unwrap();
+        assert_eq!(window, vec![0xfe, 0xff]);
+        assert_eq!(read_window(&window, false, true), -2);
+        assert_eq!(read_window(&window, false, false), 0xfffe);
+        assert_eq!(write_window(0x100, 1, false, false), None);
+        assert_eq!(write_window(0x1234, 2, true, false).unwrap(), vec![0x12, 0x34]);
+        assert_eq!(parse_number_text("-0x10"), Some(-0x10));
+        assert_eq!(parse_number_text("eax"), None);
+    }
+
+    #[test]
+    fn edits_immediates_displacements_and_branches() {
+        let disasm = x86_64_disasm();
+
+        // mov eax, 0x5
+        let mut model = model_at(&disasm, 0x1000, &[0xb8, 0x05, 0x00, 0x00, 0x00]);
+        assert_eq!(model.number_operand_value(0), Some(5));
+        model.set_number_operand(&disasm, 0, 0x12345).unwrap();
+        assert_eq!(model.bytes(), &[0xb8, 0x45, 0x23, 0x01, 0x00]);
+
+        // mov eax, dword ptr [rbp + -0x8]
+        let mut model = model_at(&disasm, 0x1000, &[0x8b, 0x45, 0xf8]);
+        let disp_idx = (0..model.number_operand_count())
+            .find(|i| model.number_operand_value(*i) == Some(-8))
+            .unwrap();
+        model.set_number_operand(&disasm, disp_idx, -0x10).unwrap();
+        assert_eq!(model.bytes(), &[0x8b, 0x45, 0xf0]);
+
+        // a disp8 can't hold this without changing the encoding
+        assert_eq!(
+            model.set_number_operand(&disasm, disp_idx, 0x1000),
+            Err(InstructionEditError::NotInvertible)
+        );
+
+        // call 0x1005 (rel32 of 0)
+        let mut model = model_at(&disasm, 0x1000, &[0xe8, 0x00, 0x00, 0x00, 0x00]);
+        assert_eq!(model.number_operand_value(0), Some(0x1005));
+        model.set_number_operand(&disasm, 0, 0x2000).unwrap();
+        assert_eq!(model.bytes(), &[0xe8, 0xfb, 0x0f, 0x00, 0x00]);
+
+        assert_eq!(
+            model.set_number_operand(&disasm, 5, 0),
+            Err(InstructionEditError::NoSuchOperand)
+        );
+    }
+}
@@ -0,0 +1,545 @@
+use super::constructor::{ConstTpl, ConstTplHandleType, ConstTplType, Constructor, HandleTpl, OpTpl, VarNodeTpl};
+use super::disasm::{Disasm, DisasmError, DisasmOperandStackItem, DisasmState};
+use super::disasm_trace::DisasmTrace;
+use super::sla_file::{AddrSpaceType, SymbolInner};
+use super::sym_subtable::SubtableSym;
+use num::FromPrimitive;
+
+// the constant space isn't in the sla file's space list, it's always index 0.
+// a varnode in it is just a number, the offset is the value.
+pub const CONST_SPACE_INDEX: i32 = 0;
+
+// numbered the same as sleigh's opcodes. only the common ones are here so far,
+// an instruction that uses anything else fails with UnsupportedPcodeOp.
+#[derive(FromPrimitive, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PcodeOpcode {
+    Copy = 1,
+    Load = 2,
+    Store = 3,
+    Branch = 4,
+    Cbranch = 5,
+    Call = 7,
+    IntAdd = 19,
+}
+
+// templates borrow opcodes that can't show up in semantics to mark where an
+// operand's constructor gets built and where a label is
+const BUILD_OPCODE: i32 = 60;
+const LABEL_OPCODE: i32 = 65;
+
+// space is a space index from the sla file (or CONST_SPACE_INDEX)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Varnode {
+    pub space: i32,
+    pub offset: u64,
+    pub size: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PcodeOp {
+    pub opcode: PcodeOpcode,
+    pub output: Option<Varnode>,
+    pub inputs: Vec<Varnode>,
+}
+
+impl Varnode {
+    pub fn new(space: i32, offset: u64, size: u32) -> Varnode {
+        Varnode { space, offset, size }
+    }
+
+    pub fn is_const(&self) -> bool {
+        self.space == CONST_SPACE_INDEX
+    }
+}
+
+// what an operand resolved to. usually space:offset, but a subtable can export
+// a pointer instead (like x86's memory operands). then offset_space is set, the
+// value is whatever offset_space:offset_offset points to in space, and
+// temp_space:temp_offset is where it's loaded to.
+#[derive(Clone, Copy, Default)]
+struct FixedHandle {
+    space: i32,
+    size: u32,
+    offset_space: Option<i32>,
+    offset_offset: u64,
+    offset_size: u32,
+    temp_space: i32,
+    temp_offset: u64,
+}
+
+impl FixedHandle {
+    fn varnode(space: i32, offset: u64, size: u32) -> FixedHandle {
+        FixedHandle {
+            space,
+            size,
+            offset_offset: offset,
+            ..FixedHandle::default()
+        }
+    }
+
+    // expression values don't have a size, the template gives them one
+    fn constant(value: u64) -> FixedHandle {
+        FixedHandle::varnode(CONST_SPACE_INDEX, value, 0)
+    }
+}
+
+// one resolved constructor in the instruction
+struct PcodeNode<'a> {
+    ctor: &'a Constructor,
+    stack: DisasmOperandStackItem,
+    end: u64,
+    operand_offs: Vec<u64>,
+    // the node each subtable operand was resolved to
+    children: Vec<Option<usize>>,
+    handles: Vec<FixedHandle>,
+    export: Option<FixedHandle>,
+}
+
+// builds p-code the same way ghidra's SleighBuilder does. the constructor tree
+// is resolved first, then operands are turned into handles from the leaves up
+// since a constructor's export can depend on its operands, and last the
+// templates are walked from the root with each BUILD pulling in an operand's
+// constructor. context is read after the whole tree is resolved like ghidra.
+pub(super) struct PcodeBuilder<'a> {
+    disasm: &'a Disasm,
+    state: DisasmState<'a>,
+    cur_space: i32,
+    cur_space_size: u32,
+    unique_space: i32,
+    // or'd into unique offsets so temporaries of different instructions don't clash
+    unique_offset: u64,
+    nodes: Vec<PcodeNode<'a>>,
+    ops: Vec<PcodeOp>,
+    // op index of each label, every constructor's labels are numbered together
+    labels: Vec<Option<usize>>,
+    // ops whose first input is a label that still needs to become a relative offset
+    label_refs: Vec<usize>,
+}
+
+impl<'a> PcodeBuilder<'a> {
+    // state should already have the instruction's end set
+    pub(super) fn new(disasm: &'a Disasm, state: DisasmState<'a>) -> PcodeBuilder<'a> {
+        let sleigh = &disasm.sleigh;
+        let (cur_space, cur_space_size) = match sleigh.get_space_by_name(&sleigh.default_space) {
+            Some(v) => (v.index, v.size as u32),
+            None => (CONST_SPACE_INDEX, 0),
+        };
+        let unique_space = sleigh
+            .spaces
+            .iter()
+            .find(|s| matches!(s.space_type, AddrSpaceType::Unique))
+            .map_or(-1, |s| s.index);
+        let unique_offset = (state.get_start_ins() as u64 & sleigh.uniq_mask as u64) << 4;
+
+        PcodeBuilder {
+            disasm,
+            state,
+            cur_space,
+            cur_space_size,
+            unique_space,
+            unique_offset,
+            nodes: Vec::new(),
+            ops: Vec::new(),
+            labels: Vec::new(),
+            label_refs: Vec::new(),
+        }
+    }
+
+    pub(super) fn build(mut self, subsym_id: u32, subtable_sym: &'a SubtableSym) -> Result<Vec<PcodeOp>, DisasmError> {
+        let at = self.state.get_start_ins() as u64;
+        let root = self.parse(subsym_id, subtable_sym, at)?;
+        self.resolve_handles()?;
+        self.build_node(root)?;
+        self.resolve_labels()?;
+        Ok(self.ops)
+    }
+
+    // resolves the constructor at `at` and everything under it. operands are
+    // visited in order so context changes land the same as when decoding.
+    fn parse(&mut self, subsym_id: u32, subtable_sym: &'a SubtableSym, at: u64) -> Result<usize, DisasmError> {
+        let disasm = self.disasm;
+        let mut trace = DisasmTrace::new();
+        let ctor_idx = disasm.resolve_ctor::<false>(&mut self.state, subtable_sym, at, &mut trace)? as u32;
+        let ctor = &subtable_sym.ctors[ctor_idx as usize];
+        let stack = DisasmOperandStackItem {
+            read_position: at,
+            subsym_id,
+            ctor_idx,
+            operand_ids: ctor.operand_ids.clone(),
+        };
+        disasm.set_context(&mut self.state, &ctor.context_ops, &stack, at)?;
+
+        let operand_count = ctor.operand_ids.len();
+        let node_idx = self.nodes.len();
+        self.nodes.push(PcodeNode {
+            ctor,
+            stack,
+            end: at,
+            operand_offs: Vec::new(),
+            children: vec![None; operand_count],
+            handles: Vec::new(),
+            export: None,
+        });
+
+        let sleigh_symbols = &disasm.sleigh.symbol_table.symbols;
+        let mut end = at + std::cmp::max(ctor.min_length, 0) as u64;
+        let mut operand_offs = vec![at; operand_count];
+        let mut operand_ends = vec![at; operand_count];
+        for (i, operand_id) in ctor.operand_ids.iter().enumerate() {
+            let operand_sym = match &sleigh_symbols[*operand_id as usize].inner {
+                SymbolInner::OperandSym(v) => v,
+                _ => panic!("not an operand symbol"),
+            };
+
+            // same as disasm_proto, operands can start where another one ends
+            let operand_off = if operand_sym.offset_base == -1 {
+                at + operand_sym.rel_offset as u64
+            } else {
+                operand_ends
+                    .get(operand_sym.offset_base as usize)
+                    .copied()
+                    .unwrap_or(at)
+                    + operand_sym.rel_offset as u64
+            };
+
+            let mut operand_end = operand_off + std::cmp::max(operand_sym.min_length, 0) as u64;
+            if operand_sym.subsym != u32::MAX {
+                let operand_subsym_box = &sleigh_symbols[operand_sym.subsym as usize];
+                if let SymbolInner::SubtableSym(sub_subtable_sym) = &operand_subsym_box.inner {
+                    let child = self.parse(operand_subsym_box.id, sub_subtable_sym, operand_off)?;
+                    operand_end = std::cmp::max(operand_end, self.nodes[child].end);
+                    self.nodes[node_idx].children[i] = Some(child);
+                }
+            }
+
+            operand_offs[i] = operand_off;
+            operand_ends[i] = operand_end;
+            end = std::cmp::max(end, operand_end);
+        }
+
+        let node = &mut self.nodes[node_idx];
+        node.end = end;
+        node.operand_offs = operand_offs;
+        Ok(node_idx)
+    }
+
+    fn resolve_handles(&mut self) -> Result<(), DisasmError> {
+        // children always come after their parent, so going backwards means an
+        // operand's export is known before the constructor that uses it
+        for node_idx in (0..self.nodes.len()).rev() {
+            let node = &self.nodes[node_idx];
+            let handles = (0..node.ctor.operand_ids.len())
+                .map(|i| self.operand_handle(node, i))
+                .collect::<Result<Vec<_>, _>>()?;
+            let export = match node.ctor.template.as_ref().and_then(|t| t.result.as_ref()) {
+                Some(result) => Some(self.fix_handle(result, &handles)?),
+                None => None,
+            };
+
+            let node = &mut self.nodes[node_idx];
+            node.handles = handles;
+            node.export = export;
+        }
+        Ok(())
+    }
+
+    fn operand_handle(&self, node: &PcodeNode, operand_idx: usize) -> Result<FixedHandle, DisasmError> {
+        let disasm = self.disasm;
+        let state = &self.state;
+        let sleigh_symbols = &disasm.sleigh.symbol_table.symbols;
+        let operand_sym = match &sleigh_symbols[node.ctor.operand_ids[operand_idx] as usize].inner {
+            SymbolInner::OperandSym(v) => v,
+            _ => panic!("not an operand symbol"),
+        };
+        let at = node.operand_offs[operand_idx];
+
+        if operand_sym.subsym == u32::MAX {
+            let def_exp = match &operand_sym.def_exp {
+                Some(v) => v,
+                None => panic!("either operand subsymbol or defexp should've been set"),
+            };
+            return Ok(FixedHandle::constant(
+                def_exp.evaluate(disasm, state, &node.stack, at) as u64
+            ));
+        }
+
+        let handle = match &sleigh_symbols[operand_sym.subsym as usize].inner {
+            SymbolInner::VarnodeSym(v) => FixedHandle::varnode(v.space.index, v.offset as u64, v.size as u32),
+            SymbolInner::ValueSym(v) => FixedHandle::constant(v.patexp.evaluate(disasm, state, &node.stack, at) as u64),
+            SymbolInner::ContextSym(v) => {
+                FixedHandle::constant(v.patexp.evaluate(disasm, state, &node.stack, at) as u64)
+            }
+            SymbolInner::ValuemapSym(v) => {
+                let value = v.patexp.evaluate(disasm, state, &node.stack, at);
+                let mapped = *v.values.get(value as usize).ok_or(DisasmError::PatternNotFound)?;
+                FixedHandle::constant(mapped as u64)
+            }
+            SymbolInner::VarlistSym(v) => {
+                let value = v.patexp.evaluate(disasm, state, &node.stack, at);
+                // unset entries in the list mean the encoding isn't valid
+                let var_idx = *v.var_ids.get(value as usize).ok_or(DisasmError::PatternNotFound)?;
+                match sleigh_symbols.get(var_idx as usize).and_then(|s| s.as_varnode()) {
+                    Some(v) => FixedHandle::varnode(v.space.index, v.offset as u64, v.size as u32),
+                    None => return Err(DisasmError::PatternNotFound),
+                }
+            }
+            SymbolInner::StartSym => {
+                FixedHandle::varnode(self.cur_space, state.get_start_ins() as u64, self.cur_space_size)
+            }
+            SymbolInner::EndSym => {
+                FixedHandle::varnode(self.cur_space, state.get_end_ins() as u64, self.cur_space_size)
+            }
+            SymbolInner::Next2Sym => {
+                FixedHandle::varnode(self.cur_space, state.get_next2_ins(disasm) as u64, self.cur_space_size)
+            }
+            // a constructor without an export leaves a handle that's never used
+            SymbolInner::SubtableSym(_) => match node.children[operand_idx] {
+                Some(child) => self.nodes[child].export.unwrap_or_default(),
+                None => FixedHandle::default(),
+            },
+            _ => return Err(DisasmError::UnsupportedSymbol),
+        };
+        Ok(handle)
+    }
+
+    fn get_handle<'h>(handles: &'h [FixedHandle], ct: &ConstTpl) -> Result<&'h FixedHandle, DisasmError> {
+        handles
+            .get(ct.handle_index as usize)
+            .ok_or(DisasmError::UnsupportedSymbol)
+    }
+
+    fn fix_const(&self, ct: &ConstTpl, handles: &[FixedHandle]) -> Result<u64, DisasmError> {
+        let value = match ct.const_type {
+            ConstTplType::Real | ConstTplType::JRelative => ct.value_real,
+            ConstTplType::JStart => self.state.get_start_ins() as u64,
+            ConstTplType::JNext => self.state.get_end_ins() as u64,
+            ConstTplType::JNext2 => self.state.get_next2_ins(self.disasm) as u64,
+            ConstTplType::JCurspace => self.cur_space as u64,
+            ConstTplType::JCurspaceSize => self.cur_space_size as u64,
+            ConstTplType::Spaceid => ct.value_spaceid.as_ref().map_or(0, |s| s.index) as u64,
+            ConstTplType::Handle => {
+                let hand = Self::get_handle(handles, ct)?;
+                let (space, offset) = match hand.offset_space {
+                    Some(_) => (hand.temp_space, hand.temp_offset),
+                    None => (hand.space, hand.offset_offset),
+                };
+                match ct.select {
+                    ConstTplHandleType::Space => space as u64,
+                    ConstTplHandleType::Offset => offset,
+                    ConstTplHandleType::Size => hand.size as u64,
+                    // a truncated operand: bytes into a varnode, or a shift for a constant
+                    ConstTplHandleType::OffsetPlus if hand.space != CONST_SPACE_INDEX => {
+                        offset.wrapping_add(ct.value_real & 0xffff)
+                    }
+                    ConstTplHandleType::OffsetPlus => offset.checked_shr(8 * (ct.value_real >> 16) as u32).unwrap_or(0),
+                }
+            }
+            // only used by crossbuild and flow overrides
+            _ => return Err(DisasmError::UnsupportedSymbol),
+        };
+        Ok(value)
+    }
+
+    fn fix_space(&self, ct: &ConstTpl, handles: &[FixedHandle]) -> Result<i32, DisasmError> {
+        match ct.const_type {
+            ConstTplType::JCurspace => Ok(self.cur_space),
+            ConstTplType::Spaceid => Ok(ct.value_spaceid.as_ref().map_or(CONST_SPACE_INDEX, |s| s.index)),
+            ConstTplType::Handle if ct.select == ConstTplHandleType::Space => {
+                let hand = Self::get_handle(handles, ct)?;
+                Ok(hand.offset_space.map_or(hand.space, |_| hand.temp_space))
+            }
+            _ => Err(DisasmError::UnsupportedSymbol),
+        }
+    }
+
+    fn fix_handle(&self, tpl: &HandleTpl, handles: &[FixedHandle]) -> Result<FixedHandle, DisasmError> {
+        let mut hand = FixedHandle::default();
+        if tpl.ptrspace.const_type == ConstTplType::Real {
+            // not a pointer export, but it could be passing up an operand that is
+            hand.space = match tpl.space.const_type {
+                ConstTplType::Handle if tpl.space.select == ConstTplHandleType::Space => {
+                    Self::get_handle(handles, &tpl.space)?.space
+                }
+                ConstTplType::JCurspace | ConstTplType::Spaceid => self.fix_space(&tpl.space, handles)?,
+                _ => return Err(DisasmError::UnsupportedSymbol),
+            };
+            hand.size = self.fix_const(&tpl.size, handles)? as u32;
+            if tpl.ptroffset.const_type == ConstTplType::Handle {
+                let other = Self::get_handle(handles, &tpl.ptroffset)?;
+                hand.offset_space = other.offset_space;
+                hand.offset_offset = other.offset_offset;
+                hand.offset_size = other.offset_size;
+                hand.temp_space = other.temp_space;
+                hand.temp_offset = other.temp_offset;
+            } else {
+                hand.offset_offset = self.wrap_offset(hand.space, self.fix_const(&tpl.ptroffset, handles)?);
+            }
+        } else {
+            hand.space = self.fix_space(&tpl.space, handles)?;
+            hand.size = self.fix_const(&tpl.size, handles)? as u32;
+            hand.offset_offset = self.fix_const(&tpl.ptroffset, handles)?;
+            let offset_space = self.fix_space(&tpl.ptrspace, handles)?;
+            if offset_space == CONST_SPACE_INDEX {
+                // the pointer turned out to be constant, so it's a plain varnode
+                hand.offset_offset = self.wrap_offset(hand.space, hand.offset_offset);
+            } else {
+                hand.offset_space = Some(offset_space);
+                hand.offset_size = self.fix_const(&tpl.ptrsize, handles)? as u32;
+                hand.temp_space = self.fix_space(&tpl.temp_space, handles)?;
+                hand.temp_offset = self.fix_const(&tpl.temp_offset, handles)?;
+            }
+        }
+        Ok(hand)
+    }
+
+    fn wrap_offset(&self, space: i32, offset: u64) -> u64 {
+        match self.disasm.sleigh.spaces.iter().find(|s| s.index == space) {
+            Some(v) => offset & size_mask(v.size as u32),
+            None => offset,
+        }
+    }
+
+    fn fix_offset(&self, space: i32, offset: u64, size: u32) -> u64 {
+        if space == CONST_SPACE_INDEX {
+            offset & size_mask(size)
+        } else if space == self.unique_space {
+            offset | self.unique_offset
+        } else {
+            self.wrap_offset(space, offset)
+        }
+    }
+
+    fn location(&self, vn: &VarNodeTpl, handles: &[FixedHandle]) -> Result<Varnode, DisasmError> {
+        let space = self.fix_space(&vn.space, handles)?;
+        let size = self.fix_const(&vn.size, handles)? as u32;
+        let offset = self.fix_const(&vn.offset, handles)?;
+        Ok(Varnode::new(space, self.fix_offset(space, offset, size), size))
+    }
+
+    // for a varnode that's really in memory behind an exported pointer, the
+    // space id and pointer a LOAD or STORE needs. None for everything else.
+    fn dynamic_pointer(
+        &self,
+        vn: &VarNodeTpl,
+        handles: &[FixedHandle],
+    ) -> Result<Option<(Varnode, Varnode)>, DisasmError> {
+        if vn.offset.const_type != ConstTplType::Handle {
+            return Ok(None);
+        }
+        let hand = Self::get_handle(handles, &vn.offset)?;
+        let offset_space = match hand.offset_space {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        // would need an INT_ADD on the pointer first, not handled yet
+        if vn.offset.select == ConstTplHandleType::OffsetPlus && vn.offset.value_real & 0xffff != 0 {
+            return Err(DisasmError::UnsupportedSymbol);
+        }
+
+        let space_id = Varnode::new(CONST_SPACE_INDEX, hand.space as u64, 8);
+        let pointer = Varnode::new(
+            offset_space,
+            self.fix_offset(offset_space, hand.offset_offset, hand.offset_size),
+            hand.offset_size,
+        );
+        Ok(Some((space_id, pointer)))
+    }
+
+    fn build_node(&mut self, node_idx: usize) -> Result<(), DisasmError> {
+        let ctor = self.nodes[node_idx].ctor;
+        let template = ctor.template.as_ref().ok_or(DisasmError::NoSemantics)?;
+        let label_base = self.labels.len();
+        self.labels
+            .resize(label_base + std::cmp::max(template.labels, 0) as usize, None);
+
+        for op in &template.op_tpls {
+            let arg = op.input.first().map_or(0, |v| v.offset.value_real as usize);
+            match op.code {
+                BUILD_OPCODE => {
+                    // building an operand that isn't a subtable doesn't do anything
+                    if let Some(child) = self.nodes[node_idx].children.get(arg).copied().flatten() {
+                        self.build_node(child)?;
+                    }
+                }
+                LABEL_OPCODE => {
+                    if let Some(label) = self.labels.get_mut(label_base + arg) {
+                        *label = Some(self.ops.len());
+                    }
+                }
+                _ => self.dump(node_idx, op, label_base)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn dump(&mut self, node_idx: usize, op: &OpTpl, label_base: usize) -> Result<(), DisasmError> {
+        let opcode = PcodeOpcode::from_i32(op.code).ok_or(DisasmError::UnsupportedPcodeOp(op.code))?;
+        let handles = &self.nodes[node_idx].handles;
+
+        // operands behind a pointer are loaded into their temp first
+        let mut loads = Vec::new();
+        let mut inputs = Vec::with_capacity(op.input.len());
+        for vn in &op.input {
+            let input = self.location(vn, handles)?;
+            if let Some((space_id, pointer)) = self.dynamic_pointer(vn, handles)? {
+                loads.push(PcodeOp {
+                    opcode: PcodeOpcode::Load,
+                    output: Some(input),
+                    inputs: vec![space_id, pointer],
+                });
+            }
+            inputs.push(input);
+        }
+
+        let is_relative = op
+            .input
+            .first()
+            .is_some_and(|v| v.offset.const_type == ConstTplType::JRelative);
+        if is_relative {
+            inputs[0].offset += label_base as u64;
+        }
+
+        // and written back after if it's the output
+        let mut output = None;
+        let mut store = None;
+        if let Some(vn) = &op.result {
+            let result = self.location(vn, handles)?;
+            if let Some((space_id, pointer)) = self.dynamic_pointer(vn, handles)? {
+                store = Some(PcodeOp {
+                    opcode: PcodeOpcode::Store,
+                    output: None,
+                    inputs: vec![space_id, pointer, result],
+                });
+            }
+            output = Some(result);
+        }
+
+        self.ops.extend(loads);
+        if is_relative {
+            self.label_refs.push(self.ops.len());
+        }
+        self.ops.push(PcodeOp { opcode, output, inputs });
+        self.ops.extend(store);
+        Ok(())
+    }
+
+    // label inputs become the number of ops to skip from the op using them
+    fn resolve_labels(&mut self) -> Result<(), DisasmError> {
+        for &op_idx in &self.label_refs {
+            let input = &mut self.ops[op_idx].inputs[0];
+            let target = self.labels.get(input.offset as usize).copied().flatten();
+            let target = target.ok_or(DisasmError::UnsupportedSymbol)?;
+            input.offset = (target as u64).wrapping_sub(op_idx as u64) & size_mask(input.size);
+        }
+        Ok(())
+    }
+}
+
+// a size of 0 means the size was never known, so nothing is cut off
+fn size_mask(size: u32) -> u64 {
+    if size == 0 || size >= 8 {
+        u64::MAX
+    } else {
+        (1u64 << (size * 8)) - 1
+    }
+}
@@ -1,4 +1,5 @@
 use crate::sleigh::consts::{AttributeId, AttributeKind, ElementId};
+use crate::sleigh::sla_file::SleighError;
 use num::FromPrimitive;
 use std::cell::Cell;
 use std::collections::BTreeMap;
@@ -66,18 +67,18 @@ impl SlaBinReader {
         }
     }
 
-    pub fn read_elem(&self) -> SlaElement {
+    pub fn read_elem(&self) -> Result<SlaElement, SleighError> {
         let start_pos = self.get_pos();
 
-        let byte1 = self.read_u8();
+        let byte1 = self.read_u8()?;
         let elem_type = Self::get_element_type(byte1);
         if elem_type != 1 && elem_type != 2 {
-            panic!("not an element");
+            return Err(SleighError::DecodeFailed);
         }
 
         let elem_id: ElementId;
         if Self::is_extended_elem(byte1) {
-            let byte1e = self.read_u8();
+            let byte1e = self.read_u8()?;
             elem_id = FromPrimitive::from_u16(Self::get_element_id_ext(byte1, byte1e)).unwrap_or(ElementId::None);
         } else {
             elem_id = FromPrimitive::from_u16(Self::get_element_id(byte1)).unwrap_or(ElementId::None);
@@ -85,188 +86,216 @@ impl SlaBinReader {
 
         return if elem_type == 1 {
             let attr_pos = self.get_pos();
-            let child_iter = self.read_elem_attrs(attr_pos);
+            let child_iter = self.read_elem_attrs(attr_pos)?;
             let mut attrs: BTreeMap<AttributeId, SlaAttribute> = BTreeMap::new();
             for attr in child_iter {
+                let attr = attr?;
                 attrs.insert(attr.id, attr);
             }
             let end_pos = self.get_pos(); // get attribute end pos
 
-            SlaElement {
+            Ok(SlaElement {
                 start: true,
                 id: elem_id,
                 spos: start_pos,
                 apos: attr_pos,
                 epos: end_pos,
                 attrs,
-            }
+            })
         } else {
             let end_pos = self.get_pos();
-            SlaElement {
+            Ok(SlaElement {
                 start: false,
                 id: elem_id,
                 spos: start_pos,
                 apos: end_pos,
                 epos: end_pos,
                 attrs: BTreeMap::new(),
-            }
+            })
         };
     }
 
-    pub fn read_elem_start(&self, check_id: ElementId) -> SlaElement {
-        let elem = self.read_elem();
-        assert_eq!(elem.id, check_id);
-        assert!(elem.start);
-        return elem;
-    }
-
-    pub fn read_elem_end(&self, check_id: ElementId) -> SlaElement {
-        let elem = self.read_elem();
-        assert_eq!(elem.id, check_id);
-        assert!(!elem.start);
-        return elem;
+    pub fn read_elem_start(&self, check_id: ElementId) -> Result<SlaElement, SleighError> {
+        let elem = self.read_elem()?;
+        if elem.id != check_id || !elem.start {
+            return Err(SleighError::DecodeFailed);
+        }
+        return Ok(elem);
     }
 
-    pub fn read_elem_children(&self, epos: usize) -> impl Iterator<Item = SlaElement> + '_ {
-        self.seek(epos);
-        return std::iter::from_fn(move || {
-            let byte1 = self.peek_u8();
-            let elem_type = Self::get_element_type(byte1);
-            if elem_type == 1 {
-                Some(self.read_elem())
-            } else if elem_type == 2 {
-                None
-            } else {
-                panic!("not an element");
+    pub fn read_elem_end(&self, check_id: ElementId) -> Result<SlaElement, SleighError> {
+        let elem = self.read_elem()?;
+        if elem.id != check_id || elem.start {
+            return Err(SleighError::DecodeFailed);
+        }
+        return Ok(elem);
+    }
+
+    // stops after the first error, so callers can just ? each child
+    pub fn read_elem_children(
+        &self,
+        epos: usize,
+    ) -> Result<impl Iterator<Item = Result<SlaElement, SleighError>> + '_, SleighError> {
+        self.seek(epos)?;
+        let mut failed = false;
+        return Ok(std::iter::from_fn(move || {
+            if failed {
+                return None;
             }
-        });
-    }
 
-    pub fn read_elem_attrs(&self, apos: usize) -> impl Iterator<Item = SlaAttribute> + '_ {
-        self.seek(apos);
-        return std::iter::from_fn(move || {
-            let byte1 = self.peek_u8();
-            if Self::get_element_type(byte1) != 3 {
-                None
-            } else {
-                let attr = self.read_attr();
-                Some(attr)
+            let res = match self.peek_u8() {
+                Ok(byte1) => match Self::get_element_type(byte1) {
+                    1 => self.read_elem(),
+                    2 => return None,
+                    _ => Err(SleighError::DecodeFailed),
+                },
+                Err(e) => Err(e),
+            };
+            failed = res.is_err();
+            Some(res)
+        }));
+    }
+
+    // the next child from read_elem_children, failing if there isn't one
+    pub fn next_child(
+        child_iter: &mut impl Iterator<Item = Result<SlaElement, SleighError>>,
+    ) -> Result<SlaElement, SleighError> {
+        child_iter.next().unwrap_or(Err(SleighError::DecodeFailed))
+    }
+
+    pub fn read_elem_attrs(
+        &self,
+        apos: usize,
+    ) -> Result<impl Iterator<Item = Result<SlaAttribute, SleighError>> + '_, SleighError> {
+        self.seek(apos)?;
+        let mut failed = false;
+        return Ok(std::iter::from_fn(move || {
+            if failed {
+                return None;
             }
-        });
+
+            let res = match self.peek_u8() {
+                Ok(byte1) if Self::get_element_type(byte1) != 3 => return None,
+                Ok(_) => self.read_attr(),
+                Err(e) => Err(e),
+            };
+            failed = res.is_err();
+            Some(res)
+        }));
     }
 
-    pub fn read_attr(&self) -> SlaAttribute {
+    pub fn read_attr(&self) -> Result<SlaAttribute, SleighError> {
         let start_pos = self.get_pos();
 
-        let byte1 = self.read_u8();
+        let byte1 = self.read_u8()?;
         if Self::get_element_type(byte1) != 3 {
-            panic!("not an attribute");
+            return Err(SleighError::DecodeFailed);
         }
 
         let attr_id: AttributeId;
         if Self::is_extended_elem(byte1) {
-            let byte1e = self.read_u8();
+            let byte1e = self.read_u8()?;
             attr_id = FromPrimitive::from_u16(Self::get_element_id_ext(byte1, byte1e)).unwrap_or(AttributeId::None);
         } else {
             attr_id = FromPrimitive::from_u16(Self::get_element_id(byte1)).unwrap_or(AttributeId::None);
         }
 
-        let byte2 = self.read_u8();
+        let byte2 = self.read_u8()?;
         let attr_kind = FromPrimitive::from_u8(Self::get_attribute_type(byte2)).unwrap_or(AttributeKind::None);
 
         let value: SlaAttributeValue;
         if attr_kind == AttributeKind::Boolean {
-            let bool_value = self.read_attr_bool(byte2);
+            let bool_value = self.read_attr_bool(byte2)?;
             value = SlaAttributeValue::Bool(bool_value);
         } else if attr_kind == AttributeKind::PositiveSignedInteger {
-            let int_value = self.read_attr_int(byte2);
+            let int_value = self.read_attr_int(byte2)?;
             value = SlaAttributeValue::Int(int_value);
         } else if attr_kind == AttributeKind::NegativeSignedInteger {
-            let int_value = self.read_attr_int(byte2);
+            let int_value = self.read_attr_int(byte2)?;
             value = SlaAttributeValue::Int(int_value);
         } else if attr_kind == AttributeKind::UnsignedInteger {
-            let uint_value = self.read_attr_uint(byte2);
+            let uint_value = self.read_attr_uint(byte2)?;
             value = SlaAttributeValue::UInt(uint_value);
         } else if attr_kind == AttributeKind::String {
-            let str_value = self.read_attr_str(byte2);
+            let str_value = self.read_attr_str(byte2)?;
             value = SlaAttributeValue::String(str_value.to_string());
         } else if attr_kind == AttributeKind::BasicAddressSpace {
-            let space_value = self.read_attr_space(byte2);
+            let space_value = self.read_attr_space(byte2)?;
             value = SlaAttributeValue::Space(space_value);
         } else {
             value = SlaAttributeValue::Null;
         }
 
-        return SlaAttribute {
+        return Ok(SlaAttribute {
             id: attr_id,
             kind: attr_kind,
             spos: start_pos,
             value,
-        };
+        });
     }
 
-    fn read_attr_bool(&self, byte2: u8) -> bool {
+    fn read_attr_bool(&self, byte2: u8) -> Result<bool, SleighError> {
         let attr_type = Self::get_attribute_type(byte2);
         let size = Self::get_attribute_size(byte2);
         if attr_type != AttributeKind::Boolean as u8 {
-            panic!("not a uint attribute");
+            return Err(SleighError::DecodeFailed);
         }
 
-        return size != 0;
+        return Ok(size != 0);
     }
 
-    fn read_attr_int(&self, byte2: u8) -> i64 {
+    fn read_attr_int(&self, byte2: u8) -> Result<i64, SleighError> {
         let attr_type = Self::get_attribute_type(byte2);
         let size = Self::get_attribute_size(byte2);
         let val: i64;
         if attr_type == AttributeKind::PositiveSignedInteger as u8 {
-            val = self.read_sized_int(size);
+            val = self.read_sized_int(size)?;
         } else if attr_type == AttributeKind::NegativeSignedInteger as u8 {
-            val = -self.read_sized_int(size);
+            val = -self.read_sized_int(size)?;
         } else {
-            panic!("not an int attribute");
+            return Err(SleighError::DecodeFailed);
         }
 
-        return val;
+        return Ok(val);
     }
 
-    fn read_attr_uint(&self, byte2: u8) -> u64 {
+    fn read_attr_uint(&self, byte2: u8) -> Result<u64, SleighError> {
         let attr_type = Self::get_attribute_type(byte2);
         let size = Self::get_attribute_size(byte2);
         if attr_type != AttributeKind::UnsignedInteger as u8 {
-            panic!("not a uint attribute");
+            return Err(SleighError::DecodeFailed);
         }
 
-        return self.read_sized_int(size) as u64;
+        return Ok(self.read_sized_int(size)? as u64);
     }
 
-    fn read_attr_str(&self, byte2: u8) -> &str {
+    fn read_attr_str(&self, byte2: u8) -> Result<&str, SleighError> {
         let attr_type = Self::get_attribute_type(byte2);
         let size_size = Self::get_attribute_size(byte2);
         if attr_type != AttributeKind::String as u8 {
-            panic!("not a string attribute");
+            return Err(SleighError::DecodeFailed);
         }
 
-        let size = self.read_sized_int(size_size);
-        let strbuf = &self.buffer[self.get_pos()..self.get_pos() + (size as usize)];
-        self.seek(self.pos.get() + size as usize);
-        return match str::from_utf8(strbuf) {
-            Ok(v) => v,
-            Err(_) => panic!("failed to decode string"),
-        };
+        let size = self.read_sized_int(size_size)? as usize;
+        let strbuf = self
+            .buffer
+            .get(self.get_pos()..self.get_pos().saturating_add(size))
+            .ok_or(SleighError::DecodeFailed)?;
+        self.pos.set(self.get_pos() + size);
+        return str::from_utf8(strbuf).or(Err(SleighError::DecodeFailed));
     }
 
-    fn read_attr_space(&self, byte2: u8) -> SpaceInfo {
+    fn read_attr_space(&self, byte2: u8) -> Result<SpaceInfo, SleighError> {
         let attr_type = Self::get_attribute_type(byte2);
         let size = Self::get_attribute_size(byte2);
         let val: SpaceInfo;
         if attr_type == AttributeKind::BasicAddressSpace as u8 {
             val = SpaceInfo {
                 space_type: SpaceType::AddressSpace,
-                index: self.read_sized_int(size) as i32,
+                index: self.read_sized_int(size)? as i32,
             }
         } else if attr_type == AttributeKind::SpecialAddressSpace as u8 {
-            let code = self.read_sized_int(size) as i32;
+            let code = self.read_sized_int(size)? as i32;
             let space_type = match code {
                 0 => SpaceType::StackSpace,
                 1 => SpaceType::JoinSpace,
@@ -277,20 +306,20 @@ impl SlaBinReader {
             };
             val = SpaceInfo { space_type, index: 0 }
         } else {
-            panic!("not an int attribute");
+            return Err(SleighError::DecodeFailed);
         }
 
-        return val;
+        return Ok(val);
     }
 
-    fn read_sized_int(&self, size: u8) -> i64 {
+    fn read_sized_int(&self, size: u8) -> Result<i64, SleighError> {
         let mut res: i64 = 0;
         for _ in 0..size {
             res <<= 7;
-            let next_byte = self.read_u8();
+            let next_byte = self.read_u8()?;
             res |= (next_byte & 127) as i64;
         }
-        return res;
+        return Ok(res);
     }
 
     fn get_element_type(byte1: u8) -> u8 {
@@ -317,53 +346,57 @@ impl SlaBinReader {
         return byte2 & 15;
     }
 
-    pub fn seek_elem_children_start(&self, elem: &SlaElement) {
-        self.seek(elem.epos);
+    pub fn seek_elem_children_start(&self, elem: &SlaElement) -> Result<(), SleighError> {
+        self.seek(elem.epos)?;
+        Ok(())
     }
 
-    pub fn seek_elem_children_end(&self, elem: &SlaElement) {
+    pub fn seek_elem_children_end(&self, elem: &SlaElement) -> Result<(), SleighError> {
         let mut id_stack: Vec<ElementId> = Vec::new();
         id_stack.push(elem.id);
         loop {
-            let byte1 = self.peek_u8();
+            let byte1 = self.peek_u8()?;
             let elem_type = Self::get_element_type(byte1);
             if elem_type == 2 {
                 if id_stack.len() > 1 {
-                    self.read_elem_end(id_stack[id_stack.len() - 1]);
+                    self.read_elem_end(id_stack[id_stack.len() - 1])?;
                     id_stack.pop();
                 } else {
                     break;
                 }
             } else if elem_type == 1 {
-                let new_elem = self.read_elem();
+                let new_elem = self.read_elem()?;
                 id_stack.push(new_elem.id);
+            } else {
+                return Err(SleighError::DecodeFailed);
             }
         }
+        Ok(())
     }
 
-    fn seek(&self, pos: usize) -> usize {
+    fn seek(&self, pos: usize) -> Result<usize, SleighError> {
         if self.get_pos() < self.buffer.len() {
             self.pos.set(pos);
-            return pos;
+            return Ok(pos);
         }
-        panic!("outside bounds");
+        Err(SleighError::DecodeFailed)
     }
 
-    fn read_u8(&self) -> u8 {
+    fn read_u8(&self) -> Result<u8, SleighError> {
         if self.get_pos() < self.buffer.len() {
             let val = self.buffer[self.get_pos()];
             self.inc_pos();
-            return val;
+            return Ok(val);
         }
-        panic!("outside bounds");
+        Err(SleighError::DecodeFailed)
     }
 
-    fn peek_u8(&self) -> u8 {
+    fn peek_u8(&self) -> Result<u8, SleighError> {
         if self.get_pos() < self.buffer.len() {
             let val = self.buffer[self.get_pos()];
-            return val;
+            return Ok(val);
         }
-        panic!("outside bounds");
+        Err(SleighError::DecodeFailed)
     }
 
     pub fn get_pos(&self) -> usize {
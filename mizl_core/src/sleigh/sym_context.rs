@@ -1,4 +1,4 @@
-use super::sla_file::{Symbol, SymbolInner};
+use super::sla_file::{SleighError, Symbol, SymbolInner};
 use crate::sleigh::consts::AttributeId;
 use crate::sleigh::expression::Expression;
 use crate::sleigh::sla_reader::{SlaBinReader, SlaElement};
@@ -12,7 +12,7 @@ pub struct ContextSym {
 }
 
 impl ContextSym {
-    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Symbol {
+    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Result<Symbol, SleighError> {
         let name = elem.as_str_or(AttributeId::Name, "");
         let id = elem.as_uint_or(AttributeId::Id, 0) as u32;
         let scope = elem.as_uint_or(AttributeId::Scope, 0) as u32;
@@ -20,14 +20,14 @@ impl ContextSym {
         let low = elem.as_int_or(AttributeId::Low, 0) as i32;
         let high = elem.as_int_or(AttributeId::High, 0) as i32;
         let flow = elem.as_bool_or(AttributeId::Varnode, false);
-        reader.seek_elem_children_start(elem);
+        reader.seek_elem_children_start(elem)?;
 
-        let mut child_iter = reader.read_elem_children(elem.epos);
-        let patexp_elem = child_iter.next().expect("pattern expression missing");
-        let patexp = Expression::new(reader, &patexp_elem);
+        let mut child_iter = reader.read_elem_children(elem.epos)?;
+        let patexp_elem = SlaBinReader::next_child(&mut child_iter)?;
+        let patexp = Expression::new(reader, &patexp_elem)?;
 
-        reader.read_elem_end(elem.id);
-        Symbol {
+        reader.read_elem_end(elem.id)?;
+        Ok(Symbol {
             name,
             id,
             scope,
@@ -38,6 +38,6 @@ impl ContextSym {
                 flow,
                 patexp,
             })),
-        }
+        })
     }
 }
@@ -1,7 +1,7 @@
 use crate::sleigh::consts::{AttributeId};
 use crate::sleigh::expression::Expression;
 use crate::sleigh::sla_reader::{SlaBinReader, SlaElement};
-use super::sla_file::{SymbolInner, Symbol};
+use super::sla_file::{SleighError, SymbolInner, Symbol};
 
 pub struct OperandSym {
     pub hand: i32,
@@ -15,7 +15,7 @@ pub struct OperandSym {
 }
 
 impl OperandSym {
-    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Symbol {
+    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Result<Symbol, SleighError> {
         let name = elem.as_str_or(AttributeId::Name, "");
         let id = elem.as_uint_or(AttributeId::Id, 0) as u32;
         let scope = elem.as_uint_or(AttributeId::Scope, 0) as u32;
@@ -25,22 +25,22 @@ impl OperandSym {
         let min_length = elem.as_int_or(AttributeId::Minlen, 0) as i32;
         let subsym = elem.as_uint_or(AttributeId::Subsym, u32::MAX as u64) as u32;
         let code = elem.as_bool_or(AttributeId::Code, false);
-        reader.seek_elem_children_start(elem);
+        reader.seek_elem_children_start(elem)?;
 
-        let mut child_iter = reader.read_elem_children(elem.epos);
+        let mut child_iter = reader.read_elem_children(elem.epos)?;
 
-        let local_exp_ele = child_iter.next().expect("local operand expression missing");
-        let local_exp = Expression::new(reader, &local_exp_ele);
+        let local_exp_ele = SlaBinReader::next_child(&mut child_iter)?;
+        let local_exp = Expression::new(reader, &local_exp_ele)?;
 
         let mut def_exp = None;
         if subsym == u32::MAX {
-            let def_exp_ele = child_iter.next().expect("def operand expression missing");
-            def_exp = Some(Expression::new(reader, &def_exp_ele));
+            let def_exp_ele = SlaBinReader::next_child(&mut child_iter)?;
+            def_exp = Some(Expression::new(reader, &def_exp_ele)?);
         }
 
         //reader.seek_elem_children_end(elem);
-        reader.read_elem_end(elem.id);
-        Symbol {
+        reader.read_elem_end(elem.id)?;
+        Ok(Symbol {
             name,
             id,
             scope,
@@ -54,6 +54,6 @@ impl OperandSym {
                 local_exp,
                 def_exp,
             })),
-        }
+        })
     }
 }
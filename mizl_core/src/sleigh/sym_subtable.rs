@@ -1,7 +1,7 @@
 use crate::sleigh::constructor::Constructor;
 use crate::sleigh::consts::{AttributeId, ElementId};
 use crate::sleigh::decision::Decision;
-use crate::sleigh::sla_file::{Symbol, SymbolInner};
+use crate::sleigh::sla_file::{SleighError, Symbol, SymbolInner};
 use crate::sleigh::sla_reader::{SlaBinReader, SlaElement};
 
 pub struct SubtableSym {
@@ -10,50 +10,49 @@ pub struct SubtableSym {
 }
 
 impl SubtableSym {
-    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Symbol {
+    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Result<Symbol, SleighError> {
         let name = elem.as_str_or(AttributeId::Name, "");
         let id = elem.as_uint_or(AttributeId::Id, 0) as u32;
         let scope = elem.as_uint_or(AttributeId::Scope, 0) as u32;
         let numct = elem.as_int_or(AttributeId::Numct, 0) as i32;
-        reader.seek_elem_children_start(elem);
+        reader.seek_elem_children_start(elem)?;
 
         let mut ctors_left = numct;
         let mut decisions_left = 1;
 
         let mut ctors: Vec<Constructor> = Vec::new();
         let mut decision: Option<Decision> = None;
-        for child in reader.read_elem_children(elem.epos) {
+        for child in reader.read_elem_children(elem.epos)? {
+            let child = child?;
             if ctors_left > 0 {
                 ctors_left -= 1;
                 if child.id != ElementId::Constructor {
-                    panic!("expected constructor element");
+                    return Err(SleighError::DecodeFailed);
                 }
-                ctors.push(Constructor::new(reader, &child));
+                ctors.push(Constructor::new(reader, &child)?);
             } else if decisions_left > 0 {
                 // skip these
                 decisions_left -= 1;
                 if child.id != ElementId::Decision {
-                    panic!("expected decision element");
+                    return Err(SleighError::DecodeFailed);
                 }
-                decision = Some(Decision::new(reader, &child));
+                decision = Some(Decision::new(reader, &child)?);
             } else {
-                panic!("all scopes and symbols read but some elements still exist");
+                return Err(SleighError::DecodeFailed);
             }
         }
 
-        if decision.is_none() {
-            panic!("decision not found");
-        }
+        let decision = decision.ok_or(SleighError::DecodeFailed)?;
 
-        reader.read_elem_end(elem.id);
-        Symbol {
+        reader.read_elem_end(elem.id)?;
+        Ok(Symbol {
             name,
             id,
             scope,
             inner: SymbolInner::SubtableSym(Box::new(SubtableSym {
                 ctors,
-                decision: decision.unwrap(),
+                decision,
             })),
-        }
+        })
     }
 }
\ No newline at end of file
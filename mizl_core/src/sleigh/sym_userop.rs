@@ -1,27 +1,27 @@
 use crate::sleigh::consts::AttributeId;
 use crate::sleigh::sla_reader::{SlaBinReader, SlaElement};
-use super::sla_file::{SymbolInner, Symbol};
+use super::sla_file::{SleighError, SymbolInner, Symbol};
 
 pub struct UseropSym {
     pub index: i32,
 }
 
 impl UseropSym {
-    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Symbol {
+    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Result<Symbol, SleighError> {
         let name = elem.as_str_or(AttributeId::Name, "");
         let id = elem.as_uint_or(AttributeId::Id, 0) as u32;
         let scope = elem.as_uint_or(AttributeId::Scope, 0) as u32;
         let index = elem.as_int_or(AttributeId::Index, 0) as i32;
-        reader.seek_elem_children_start(elem);
+        reader.seek_elem_children_start(elem)?;
 
-        reader.read_elem_end(elem.id);
-        Symbol {
+        reader.read_elem_end(elem.id)?;
+        Ok(Symbol {
             name,
             id,
             scope,
             inner: SymbolInner::Userop(Box::new(UseropSym {
                 index,
             })),
-        }
+        })
     }
 }
\ No newline at end of file
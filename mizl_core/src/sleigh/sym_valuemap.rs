@@ -1,4 +1,4 @@
-use super::sla_file::{Symbol, SymbolInner};
+use super::sla_file::{SleighError, Symbol, SymbolInner};
 use crate::sleigh::consts::{AttributeId, ElementId};
 use crate::sleigh::expression::Expression;
 use crate::sleigh::sla_reader::{SlaBinReader, SlaElement};
@@ -9,39 +9,35 @@ pub struct ValuemapSym {
 }
 
 impl ValuemapSym {
-    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Symbol {
+    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Result<Symbol, SleighError> {
         let name = elem.as_str_or(AttributeId::Name, "");
         let id = elem.as_uint_or(AttributeId::Id, 0) as u32;
         let scope = elem.as_uint_or(AttributeId::Scope, 0) as u32;
-        reader.seek_elem_children_start(elem);
+        reader.seek_elem_children_start(elem)?;
 
         let mut patexp: Option<Expression> = None;
         let mut values: Vec<i64> = Vec::new();
-        for child in reader.read_elem_children(elem.epos) {
+        for child in reader.read_elem_children(elem.epos)? {
+            let child = child?;
             if patexp.is_none() {
-                patexp = Some(Expression::new(reader, &child));
+                patexp = Some(Expression::new(reader, &child)?);
                 continue;
             }
             if child.id == ElementId::Valuetab {
                 let val = child.as_int_or(AttributeId::Val, 0xBADBEEF);
                 values.push(val);
-                reader.read_elem_end(child.id);
+                reader.read_elem_end(child.id)?;
             }
         }
 
-        if patexp.is_none() {
-            panic!("pattern expression missing");
-        }
+        let patexp = patexp.ok_or(SleighError::DecodeFailed)?;
 
-        reader.read_elem_end(elem.id);
-        Symbol {
+        reader.read_elem_end(elem.id)?;
+        Ok(Symbol {
             name,
             id,
             scope,
-            inner: SymbolInner::ValuemapSym(Box::new(ValuemapSym {
-                patexp: patexp.unwrap(),
-                values,
-            })),
-        }
+            inner: SymbolInner::ValuemapSym(Box::new(ValuemapSym { patexp, values })),
+        })
     }
 }
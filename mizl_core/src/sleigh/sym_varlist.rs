@@ -1,7 +1,7 @@
 use crate::sleigh::consts::{AttributeId, ElementId};
 use crate::sleigh::expression::Expression;
 use crate::sleigh::sla_reader::{SlaBinReader, SlaElement};
-use super::sla_file::{SymbolInner, Symbol};
+use super::sla_file::{SleighError, SymbolInner, Symbol};
 
 pub struct VarlistSym {
     pub patexp: Expression,
@@ -9,42 +9,41 @@ pub struct VarlistSym {
 }
 
 impl VarlistSym {
-    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Symbol {
+    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Result<Symbol, SleighError> {
         let name = elem.as_str_or(AttributeId::Name, "");
         let id = elem.as_uint_or(AttributeId::Id, 0) as u32;
         let scope = elem.as_uint_or(AttributeId::Scope, 0) as u32;
-        reader.seek_elem_children_start(elem);
+        reader.seek_elem_children_start(elem)?;
 
         let mut patexp: Option<Expression> = None;
         let mut var_ids: Vec<u32> = Vec::new();
-        for child in reader.read_elem_children(elem.epos) {
+        for child in reader.read_elem_children(elem.epos)? {
+            let child = child?;
             if patexp.is_none() {
-                patexp = Some(Expression::new(reader, &child));
+                patexp = Some(Expression::new(reader, &child)?);
                 continue;
             }
             if child.id == ElementId::Var {
                 let id = child.as_uint_or(AttributeId::Id, u32::MAX as u64) as u32;
                 var_ids.push(id);
-                reader.read_elem_end(child.id);
+                reader.read_elem_end(child.id)?;
             } else if child.id == ElementId::Null {
                 var_ids.push(u32::MAX);
-                reader.read_elem_end(child.id);
+                reader.read_elem_end(child.id)?;
             }
         }
 
-        if patexp.is_none() {
-            panic!("pattern expression missing");
-        }
+        let patexp = patexp.ok_or(SleighError::DecodeFailed)?;
 
-        reader.read_elem_end(elem.id);
-        Symbol {
+        reader.read_elem_end(elem.id)?;
+        Ok(Symbol {
             name,
             id,
             scope,
             inner: SymbolInner::VarlistSym(Box::new(VarlistSym {
-                patexp: patexp.unwrap(),
+                patexp,
                 var_ids,
             })),
-        }
+        })
     }
 }
\ No newline at end of file
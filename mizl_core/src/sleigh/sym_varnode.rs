@@ -1,33 +1,53 @@
 use crate::sleigh::consts::AttributeId;
 use crate::sleigh::sla_reader::{SlaBinReader, SlaElement, SpaceInfo};
-use super::sla_file::{SymbolInner, Symbol};
+use super::sla_file::{SleighError, SymbolInner, Symbol};
 
 pub struct VarnodeSym {
+    pub name: String,
     pub space: SpaceInfo,
     pub offset: u32,
     pub size: i32,
 }
 
 impl VarnodeSym {
-    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Symbol {
+    pub fn new(reader: &SlaBinReader, elem: &SlaElement) -> Result<Symbol, SleighError> {
         let name = elem.as_str_or(AttributeId::Name, "");
         let id = elem.as_uint_or(AttributeId::Id, 0) as u32;
         let scope = elem.as_uint_or(AttributeId::Scope, 0) as u32;
         let space = elem.as_space(AttributeId::Space);
         let offset = elem.as_uint_or(AttributeId::Off, 0) as u32;
         let size = elem.as_int_or(AttributeId::Size, 0) as i32;
-        reader.seek_elem_children_start(elem);
+        reader.seek_elem_children_start(elem)?;
 
-        reader.read_elem_end(elem.id);
-        Symbol {
+        reader.read_elem_end(elem.id)?;
+        Ok(Symbol {
             name,
             id,
             scope,
             inner: SymbolInner::VarnodeSym(Box::new(VarnodeSym {
+                name: String::new(), // filled in with the symbol head's name
                 space,
                 offset,
                 size,
             })),
-        }
+        })
     }
-}
\ No newline at end of file
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn space(&self) -> &SpaceInfo {
+        &self.space
+    }
+
+    // offset into the space, which for registers is the sleigh register address
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    // in bytes
+    pub fn size(&self) -> i32 {
+        self.size
+    }
+}
@@ -1,11 +1,11 @@
 use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
 use quote::{format_ident, quote};
-use syn::{Data, DeriveInput, Field, Fields, Type, parse_macro_input};
+use syn::{Data, DataEnum, DeriveInput, Field, Fields, Ident, Type, parse_macro_input};
 
 const DEBUG: bool = false;
 
-#[proc_macro_derive(FfiSerialize, attributes(ffi_serialize_enum, ffi_inline_vec))]
+#[proc_macro_derive(FfiSerialize, attributes(ffi_serialize, ffi_serialize_enum, ffi_inline_vec))]
 pub fn ffi_serialize_derive(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
     match make_ffi_serialize(&ast) {
@@ -25,18 +25,18 @@ enum FieldKind {
         align_expr: TokenStream2,
         size_expr: TokenStream2,
         type_expr: TokenStream2,
+        needs_cast: bool,
     },
     Enum,
+    // the rest are pointers in the base (unless the vec is inline),
+    // see PointeeKind for what they point to
     String,
-    Vec(Box<Type>, bool),
-    // VecOfString(bool),
+    Vec(bool),
     VecOfPrimitive {
         align_expr: TokenStream2,
-        size_expr: TokenStream2,
-        type_expr: TokenStream2,
         inline: bool,
     },
-    ChildStruct(Box<Type>),
+    ChildStruct,
 }
 
 fn is_serializable_enum_field(field: &Field) -> bool {
@@ -46,13 +46,65 @@ fn is_serializable_enum_field(field: &Field) -> bool {
         .any(|attr| attr.path().is_ident("ffi_serialize_enum"))
 }
 
-fn get_primitive_field_info(ty: &Type) -> Option<(TokenStream2, TokenStream2, TokenStream2)> {
+// #[ffi_serialize(deserialize)] on the type also generates a deserialize fn.
+// it's opt in so types only sent one way don't pay for the extra code.
+fn wants_deserialize(ast: &DeriveInput) -> syn::Result<bool> {
+    let mut deserialize = false;
+    for attr in &ast.attrs {
+        if !attr.path().is_ident("ffi_serialize") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("deserialize") {
+                deserialize = true;
+                Ok(())
+            } else {
+                Err(meta.error("unknown ffi_serialize option"))
+            }
+        })?;
+    }
+    Ok(deserialize)
+}
+
+fn is_inline_vec(field: &Field) -> bool {
+    field.attrs.iter().any(|attr| attr.path().is_ident("ffi_inline_vec"))
+}
+
+// an inline vec has its length and data right after the base size instead of
+// behind a pointer. only the last field can be one, so the data always starts
+// at a spot the reader can find without a pointer.
+fn check_inline_vecs(fields: &[&Field], allow_last: bool) -> syn::Result<()> {
+    for (i, field) in fields.iter().enumerate() {
+        if !is_inline_vec(field) {
+            continue;
+        }
+        if vec_inner(&field.ty).is_none() {
+            return Err(syn::Error::new_spanned(
+                field,
+                "ffi_inline_vec can only be used on a Vec",
+            ));
+        }
+        if !allow_last || i != fields.len() - 1 {
+            return Err(syn::Error::new_spanned(
+                field,
+                "ffi_inline_vec can only be used on the last field of a struct",
+            ));
+        }
+    }
+    Ok(())
+}
+
+// returns (alignment, size, stored type, whether the value has to be cast to
+// the stored type). bool and char aren't stored as themselves so other
+// languages don't have to know rust's representation of them.
+fn get_primitive_field_info(ty: &Type) -> Option<(TokenStream2, TokenStream2, TokenStream2, bool)> {
     let name = if let Type::Path(tp) = ty {
         tp.path.get_ident()?.to_string()
     } else {
         return None;
     };
 
+    let mut needs_cast = false;
     let (align_expr, size_expr, type_expr) = match name.as_str() {
         "u8" => (quote!(I8_SA), quote!(I8_SZ), quote!(u8)),
         "i8" => (quote!(I8_SA), quote!(I8_SZ), quote!(i8)),
@@ -66,9 +118,27 @@ fn get_primitive_field_info(ty: &Type) -> Option<(TokenStream2, TokenStream2, To
         "f64" => (quote!(I64_SA), quote!(I64_SZ), quote!(f64)),
         "isize" => (quote!(WORD_SA), quote!(WORD_SZ), quote!(isize)),
         "usize" => (quote!(WORD_SA), quote!(WORD_SZ), quote!(usize)),
+        "bool" => {
+            // written as a u8 0 or 1
+            needs_cast = true;
+            (quote!(I8_SA), quote!(I8_SZ), quote!(u8))
+        }
+        "char" => {
+            // written as its unicode scalar value
+            needs_cast = true;
+            (quote!(I32_SA), quote!(I32_SZ), quote!(u32))
+        }
         _ => return None,
     };
-    Some((align_expr, size_expr, type_expr))
+    Some((align_expr, size_expr, type_expr, needs_cast))
+}
+
+fn make_primitive_value(value: TokenStream2, type_expr: &TokenStream2, needs_cast: bool) -> TokenStream2 {
+    if needs_cast {
+        quote!(#value as #type_expr)
+    } else {
+        value
+    }
 }
 
 fn path_ident_eq(ty: &Type, name: &str) -> bool {
@@ -101,25 +171,24 @@ fn get_field_ffi_type(field: &Field, can_be_inlined: bool) -> FieldKind {
             align_expr: prim_inf.0,
             size_expr: prim_inf.1,
             type_expr: prim_inf.2,
+            needs_cast: prim_inf.3,
         }
     } else if is_serializable_enum_field(&field) {
         FieldKind::Enum
     } else if path_ident_eq(field_type, "String") {
         FieldKind::String
     } else if let Some(inner) = vec_inner(field_type) {
-        // let inline_vec = can_be_inlined && is_inline_vec(&field);
+        let inline = can_be_inlined && is_inline_vec(field);
         if let Some(prim_inf) = get_primitive_field_info(&inner) {
             FieldKind::VecOfPrimitive {
                 align_expr: prim_inf.0,
-                size_expr: prim_inf.1,
-                type_expr: prim_inf.2,
-                inline: false,
+                inline,
             }
         } else {
-            FieldKind::Vec(Box::new(inner), false)
+            FieldKind::Vec(inline)
         }
     } else {
-        FieldKind::ChildStruct(Box::new(field_type.clone()))
+        FieldKind::ChildStruct
     }
 }
 
@@ -134,26 +203,309 @@ fn get_ffi_token_from_base(base_type: &Type) -> TokenStream2 {
     }
 }
 
+// what a pointer written by the serializer leads to. vec elements can be any
+// of these too, so a Vec<Vec<String>> is a pointer array of pointer arrays of
+// strings. everything but a child struct is length prefixed.
+enum PointeeKind {
+    String,
+    PrimitiveVec {
+        align_expr: TokenStream2,
+        size_expr: TokenStream2,
+        type_expr: TokenStream2,
+        needs_cast: bool,
+        ele_type: Type,
+    },
+    Vec(Type),
+    ChildStruct(Type),
+}
+
+fn get_pointee_kind(ty: &Type) -> PointeeKind {
+    if path_ident_eq(ty, "String") {
+        PointeeKind::String
+    } else if let Some(inner) = vec_inner(ty) {
+        if let Some(prim_inf) = get_primitive_field_info(&inner) {
+            PointeeKind::PrimitiveVec {
+                align_expr: prim_inf.0,
+                size_expr: prim_inf.1,
+                type_expr: prim_inf.2,
+                needs_cast: prim_inf.3,
+                ele_type: inner,
+            }
+        } else {
+            PointeeKind::Vec(inner)
+        }
+    } else {
+        PointeeKind::ChildStruct(ty.clone())
+    }
+}
+
+// adds the dynamic data of value (of type ty) to size. has to line up exactly
+// with make_pointee_write_stmts. depth keeps nested loop names apart.
+fn make_pointee_size_stmts(value: &TokenStream2, ty: &Type, depth: usize) -> TokenStream2 {
+    match get_pointee_kind(ty) {
+        PointeeKind::String => quote! {
+            size = align_usize_fast_const::<I32_SA>(size + I32_SZ);
+            size += #value.len() + 1;
+        },
+        PointeeKind::PrimitiveVec {
+            align_expr, size_expr, ..
+        } => quote! {
+            size = align_usize_fast_const::<{ max_const_usize(I32_SA, #align_expr) }>(size + I32_SZ);
+            size += #value.len() * #size_expr;
+        },
+        PointeeKind::Vec(ele_type) => {
+            let elem = format_ident!("elem_{}", depth);
+            let elem_size_stmts = make_pointee_size_stmts(&quote! { #elem }, &ele_type, depth + 1);
+            let elems_size = if let PointeeKind::ChildStruct(_) = get_pointee_kind(&ele_type) {
+                let ele_ffi = get_ffi_token_from_base(&ele_type);
+                quote! {
+                    if #ele_ffi::has_dynamic_size() {
+                        // slow length calculation if element size is dynamic
+                        for #elem in #value.iter() {
+                            #elem_size_stmts
+                        }
+                    } else if #value.len() > 0 {
+                        // optimized length calculation if element size is constant.
+                        // every element but the last is padded up to the next one's alignment.
+                        size = align_usize_fast_const::<{ #ele_ffi::calculate_alignment() }>(size);
+                        size += align_usize_fast_const::<{ #ele_ffi::calculate_alignment() }>(
+                            #ele_ffi::calculate_base_size()
+                        ) * (#value.len() - 1);
+                        size += #ele_ffi::calculate_base_size();
+                    }
+                }
+            } else {
+                quote! {
+                    for #elem in #value.iter() {
+                        #elem_size_stmts
+                    }
+                }
+            };
+
+            quote! {
+                // pointer array
+                size = align_usize_fast_const::<WORD_SA>(size + I32_SZ);
+                size += #value.len() * WORD_SZ;
+
+                // element data
+                #elems_size
+            }
+        }
+        PointeeKind::ChildStruct(child_type) => {
+            let child_ffi = get_ffi_token_from_base(&child_type);
+            quote! {
+                size = align_usize_fast_const::<{ #child_ffi::calculate_alignment() }>(size);
+                size += #child_ffi::calculate_full_size(&#value);
+            }
+        }
+    }
+}
+
+// writes the dynamic data of value (of type ty) at ptrd_dyn and binds data_ptr
+// to what a pointer to it should hold
+fn make_pointee_write_stmts(value: &TokenStream2, ty: &Type, data_ptr: &Ident, depth: usize) -> TokenStream2 {
+    match get_pointee_kind(ty) {
+        PointeeKind::String => quote! {
+            // align to start position, write length, and remember start position
+            ptrd_dyn = align_ptr_fast::<I32_SA>(ptrd_dyn.add(I32_SZ));
+            *(ptrd_dyn.sub(I32_SZ) as *mut u32) = #value.len() as u32;
+            let #data_ptr = ptrd_dyn;
+
+            // copy string data and add null term
+            std::ptr::copy_nonoverlapping(#value.as_ptr(), ptrd_dyn, #value.len());
+            *ptrd_dyn.add(#value.len()) = 0u8;
+
+            // seek forward string length + null term
+            ptrd_dyn = ptrd_dyn.add(#value.len() + 1);
+        },
+        PointeeKind::PrimitiveVec {
+            align_expr,
+            size_expr,
+            type_expr,
+            needs_cast,
+            ..
+        } => {
+            let copy_stmts = if needs_cast {
+                let elem = format_ident!("elem_{}", depth);
+                let elem_value = make_primitive_value(quote!(*#elem), &type_expr, needs_cast);
+                quote! {
+                    for #elem in #value.iter() {
+                        *(ptrd_dyn as *mut #type_expr) = #elem_value;
+                        ptrd_dyn = ptrd_dyn.add(#size_expr);
+                    }
+                }
+            } else {
+                // same layout on both sides, so copy it all at once like a string
+                quote! {
+                    std::ptr::copy_nonoverlapping(#value.as_ptr() as *const u8, ptrd_dyn, #value.len() * #size_expr);
+                    ptrd_dyn = ptrd_dyn.add(#value.len() * #size_expr);
+                }
+            };
+            quote! {
+                // align to start position, write length, and remember start position
+                ptrd_dyn = align_ptr_fast::<{ max_const_usize(I32_SA, #align_expr) }>(ptrd_dyn.add(I32_SZ));
+                *(ptrd_dyn.sub(I32_SZ) as *mut u32) = #value.len() as u32;
+                let #data_ptr = ptrd_dyn;
+
+                #copy_stmts
+            }
+        }
+        PointeeKind::Vec(ele_type) => {
+            let elem = format_ident!("elem_{}", depth);
+            let elem_ptr = format_ident!("elem_ptr_{}", depth);
+            let ptr_array = format_ident!("vec_array_{}", depth);
+            let elem_write_stmts = make_pointee_write_stmts(&quote! { #elem }, &ele_type, &elem_ptr, depth + 1);
+            quote! {
+                // align to start position, write length, and remember start position
+                ptrd_dyn = align_ptr_fast::<WORD_SA>(ptrd_dyn.add(I32_SZ));
+                *(ptrd_dyn.sub(I32_SZ) as *mut u32) = #value.len() as u32;
+                let #data_ptr = ptrd_dyn;
+
+                // also make a copy pointer for writing the pointer array and move to end
+                let mut #ptr_array = ptrd_dyn;
+                ptrd_dyn = ptrd_dyn.add(#value.len() * WORD_SZ);
+
+                // write each element and point to it
+                for #elem in #value.iter() {
+                    #elem_write_stmts
+                    *(#ptr_array as *mut *mut u8) = #elem_ptr;
+                    #ptr_array = #ptr_array.add(WORD_SZ);
+                }
+            }
+        }
+        PointeeKind::ChildStruct(child_type) => {
+            let child_ffi = get_ffi_token_from_base(&child_type);
+            quote! {
+                ptrd_dyn = align_ptr_fast::<{ #child_ffi::calculate_alignment() }>(ptrd_dyn);
+                let #data_ptr = ptrd_dyn;
+                ptrd_dyn = #child_ffi::serialize(ptrd_dyn, &#value);
+            }
+        }
+    }
+}
+
+// the reverse of make_pointee_write_stmts, given the pointer it bound
+fn make_pointee_read_expr(data_ptr: &TokenStream2, ty: &Type, depth: usize) -> TokenStream2 {
+    match get_pointee_kind(ty) {
+        PointeeKind::String => quote! {
+            {
+                let len = *(#data_ptr.sub(I32_SZ) as *const u32) as usize;
+                String::from_utf8(std::slice::from_raw_parts(#data_ptr, len).to_vec()).unwrap()
+            }
+        },
+        PointeeKind::PrimitiveVec {
+            type_expr,
+            needs_cast: false,
+            ..
+        } => quote! {
+            {
+                let len = *(#data_ptr.sub(I32_SZ) as *const u32) as usize;
+                std::slice::from_raw_parts(#data_ptr as *const #type_expr, len).to_vec()
+            }
+        },
+        PointeeKind::PrimitiveVec {
+            size_expr,
+            type_expr,
+            ele_type,
+            ..
+        } => {
+            let idx = format_ident!("i_{}", depth);
+            let value = make_primitive_read(
+                quote! { *(#data_ptr.add(#idx * #size_expr) as *const #type_expr) },
+                &ele_type,
+            );
+            quote! {
+                {
+                    let len = *(#data_ptr.sub(I32_SZ) as *const u32) as usize;
+                    (0..len).map(|#idx| #value).collect::<Vec<_>>()
+                }
+            }
+        }
+        PointeeKind::Vec(ele_type) => {
+            let idx = format_ident!("i_{}", depth);
+            let elem_ptr = format_ident!("elem_ptr_{}", depth);
+            let elem_value = make_pointee_read_expr(&quote! { #elem_ptr }, &ele_type, depth + 1);
+            quote! {
+                {
+                    let len = *(#data_ptr.sub(I32_SZ) as *const u32) as usize;
+                    (0..len)
+                        .map(|#idx| {
+                            let #elem_ptr = *(#data_ptr.add(#idx * WORD_SZ) as *const *mut u8);
+                            #elem_value
+                        })
+                        .collect::<Vec<_>>()
+                }
+            }
+        }
+        PointeeKind::ChildStruct(child_type) => {
+            let child_ffi = get_ffi_token_from_base(&child_type);
+            quote! { #child_ffi::deserialize(#data_ptr) }
+        }
+    }
+}
+
 // /////
 
 fn make_ffi_serialize(ast: &DeriveInput) -> syn::Result<TokenStream2> {
     let name = &ast.ident;
     let ffi_name = format_ident!("{name}Ffi");
 
-    let fields = match &ast.data {
-        Data::Struct(ds) => match &ds.fields {
-            Fields::Named(nf) => nf.named.iter().collect::<Vec<_>>(),
-            _ => return Err(syn::Error::new_spanned(ast, "field must be named")),
-        },
-        _ => return Err(syn::Error::new_spanned(ast, "field must be struct type")),
-    };
+    let (calc_align_body, calc_base_size_body, calc_full_size_body, has_dynamic_size_body, serialize_body) =
+        match &ast.data {
+            Data::Struct(ds) => {
+                let fields = match &ds.fields {
+                    Fields::Named(nf) => nf.named.iter().collect::<Vec<_>>(),
+                    _ => return Err(syn::Error::new_spanned(ast, "field must be named")),
+                };
+                check_inline_vecs(&fields, true)?;
+                let accessed_fields = fields
+                    .iter()
+                    .map(|f| {
+                        let name = f.ident.as_ref().unwrap();
+                        (quote! { obj.#name }, *f)
+                    })
+                    .collect::<Vec<_>>();
 
-    let calc_align_body = make_calc_align_body(&fields);
-    let calc_base_size_body = make_calc_base_size_body(&fields);
-    let calc_full_size_body = make_calc_full_size_body(&fields);
-    let has_dynamic_size_body = make_has_dynamic_size_body(&fields);
-    let has_var_length_field_body = make_has_var_length_field_body(&fields);
-    let serialize_body = make_serialize_body(&fields);
+                (
+                    make_calc_align_body(&fields),
+                    make_calc_base_size_body(&fields),
+                    make_calc_full_size_body(&accessed_fields),
+                    make_has_dynamic_size_body(&fields),
+                    make_serialize_body(&accessed_fields),
+                )
+            }
+            Data::Enum(de) => {
+                let variants = get_enum_variants(name, de);
+                for variant in &variants {
+                    // variants share the payload, so there's no end to put the data at
+                    check_inline_vecs(&variant.fields, false)?;
+                }
+                (
+                    make_enum_calc_align_body(&ffi_name),
+                    make_enum_calc_base_size_body(&ffi_name, &variants),
+                    make_enum_calc_full_size_body(&variants),
+                    make_enum_has_dynamic_size_body(&variants),
+                    make_enum_serialize_body(&ffi_name, &variants),
+                )
+            }
+            _ => return Err(syn::Error::new_spanned(ast, "field must be struct or enum type")),
+        };
+    let has_var_length_field_body = make_has_var_length_field_body();
+    let mut extra_fns = match &ast.data {
+        Data::Enum(de) => make_enum_extra_fns(&get_enum_variants(name, de)),
+        _ => quote! {},
+    };
+    if wants_deserialize(ast)? {
+        let deserialize_body = match &ast.data {
+            Data::Struct(ds) => make_deserialize_body(name, &ds.fields.iter().collect::<Vec<_>>()),
+            Data::Enum(de) => make_enum_deserialize_body(&ffi_name, &get_enum_variants(name, de)),
+            _ => unreachable!(),
+        };
+        extra_fns = quote! {
+            #extra_fns
+            pub unsafe fn deserialize(ptr: *const u8) -> #name { #deserialize_body }
+        };
+    }
 
     Ok(quote! {
         pub struct #ffi_name;
@@ -167,6 +519,7 @@ fn make_ffi_serialize(ast: &DeriveInput) -> syn::Result<TokenStream2> {
             pub const fn has_dynamic_size() -> bool { #has_dynamic_size_body }
             pub const fn has_var_length_field() -> bool { #has_var_length_field_body }
             pub unsafe fn serialize(ptrd: *mut u8, obj: &#name) -> *mut u8 { #serialize_body }
+            #extra_fns
         }
 
         impl FfiSerializer for #ffi_name {
@@ -186,20 +539,24 @@ fn make_ffi_serialize(ast: &DeriveInput) -> syn::Result<TokenStream2> {
     })
 }
 
-fn make_calc_align_body(fields: &[&Field]) -> TokenStream2 {
+fn make_align_exprs(fields: &[&Field]) -> Vec<TokenStream2> {
     // currently, we only have primitives (up to 64-bit) or pointers,
     // so we don't have to worry about anything above 8 byte right now.
     let mut align_exprs: Vec<TokenStream2> = Vec::new();
     for (i, field) in fields.iter().enumerate() {
-        align_exprs.push(match get_field_ffi_type(field, i == 0) {
-            FieldKind::String | FieldKind::Vec(_, _) | FieldKind::VecOfPrimitive { .. } | FieldKind::ChildStruct(_) => {
+        align_exprs.push(match get_field_ffi_type(field, i == fields.len() - 1) {
+            FieldKind::String | FieldKind::Vec(_) | FieldKind::VecOfPrimitive { .. } | FieldKind::ChildStruct => {
                 quote! { WORD_SA }
             }
             FieldKind::Primitive { align_expr, .. } => align_expr,
             FieldKind::Enum => quote! { I32_SA },
         });
     }
+    align_exprs
+}
 
+fn make_calc_align_body(fields: &[&Field]) -> TokenStream2 {
+    let align_exprs = make_align_exprs(fields);
     quote! {
         let mut align = 1usize;
         #( align = max_const_usize(align, #align_exprs); )*
@@ -208,23 +565,31 @@ fn make_calc_align_body(fields: &[&Field]) -> TokenStream2 {
 }
 
 fn make_calc_base_size_body(fields: &[&Field]) -> TokenStream2 {
+    let size_stmts = make_base_size_stmts(fields);
+    quote! {
+        let mut size = 0usize;
+        #( #size_stmts )*
+        size
+    }
+}
+
+fn make_base_size_stmts(fields: &[&Field]) -> Vec<TokenStream2> {
     // calculate the static fields of a given struct.
     let mut size_stmts: Vec<TokenStream2> = Vec::new();
 
     for (i, field) in fields.iter().enumerate() {
-        size_stmts.push(match get_field_ffi_type(field, i == 0) {
-            FieldKind::String | FieldKind::ChildStruct(_) => {
+        size_stmts.push(match get_field_ffi_type(field, i == fields.len() - 1) {
+            FieldKind::String | FieldKind::ChildStruct => {
                 quote! {
                     // pointer only
                     size = align_usize_fast_const::<WORD_SA>(size);
                     size += WORD_SZ;
                 }
             }
-            FieldKind::Vec(_, inline) | FieldKind::VecOfPrimitive { inline, .. } => {
+            FieldKind::Vec(inline) | FieldKind::VecOfPrimitive { inline, .. } => {
                 if inline {
                     quote! {
-                        // no size
-                        size += 0;
+                        // no pointer, the data starts right after the base size
                     }
                 } else {
                     // normal vec
@@ -252,114 +617,95 @@ fn make_calc_base_size_body(fields: &[&Field]) -> TokenStream2 {
         })
     }
 
+    size_stmts
+}
+
+fn make_calc_full_size_body(fields: &[(TokenStream2, &Field)]) -> TokenStream2 {
+    let size_stmts = make_full_size_stmts(fields);
     quote! {
-        let mut size = 0usize;
+        let mut size = Self::calculate_base_size();
         #( #size_stmts )*
         size
     }
 }
 
-fn make_calc_full_size_body(fields: &[&Field]) -> TokenStream2 {
+// fields are paired with the expression that reads them, like `obj.name` for
+// a struct or the binding of a matched enum variant.
+fn make_full_size_stmts(fields: &[(TokenStream2, &Field)]) -> Vec<TokenStream2> {
     // calculate the dynamic fields of a given struct.
     // the static field sizes will be summed up in the base size.
     let mut size_stmts: Vec<TokenStream2> = Vec::new();
 
-    for (i, field) in fields.iter().enumerate() {
-        let name = field.ident.as_ref().unwrap();
-
-        match get_field_ffi_type(field, i == 0) {
-            FieldKind::String => {
-                size_stmts.push(quote! {
-                    size = align_usize_fast_const::<I32_SZ>(size + I32_SZ);
-                    size += obj.#name.len() + 1;
-                });
-            }
-            FieldKind::Vec(ele_type, inline) => {
-                let ele_ffi = get_ffi_token_from_base(&ele_type);
-                if inline {
-                    size_stmts.push(quote! {
-                        // pointer array
-                        size = obj.#name.len() * WORD_SZ;
-                    });
-                } else {
-                    size_stmts.push(quote! {
-                        // pointer array
-                        size = align_usize_fast_const::<WORD_SA>(size + I32_SZ);
-                        size += obj.#name.len() * WORD_SZ;
-                    });
-                }
-                size_stmts.push(quote! {
-                    // element data
-                    if #ele_ffi::has_dynamic_size() {
-                        // slow length calculation if element size is dynamic
-                        for elem in &obj.#name {
-                            size = align_usize_fast_const::<{ #ele_ffi::calculate_alignment() }>(size);
-                            size += #ele_ffi::calculate_full_size(elem);
-                        }
-                    } else {
-                        // optimized length calculation if element size is constant
-                        size = align_usize_fast_const::<{ #ele_ffi::calculate_alignment() }>(size);
-                        size += #ele_ffi::calculate_base_size() * obj.#name.len();
-                    }
-                });
+    for (i, (name, field)) in fields.iter().enumerate() {
+        match get_field_ffi_type(field, i == fields.len() - 1) {
+            FieldKind::String | FieldKind::ChildStruct => {
+                size_stmts.push(make_pointee_size_stmts(name, &field.ty, 0));
             }
-            FieldKind::VecOfPrimitive {
-                align_expr,
-                size_expr,
-                inline,
-                ..
-            } => {
+            FieldKind::Vec(inline) | FieldKind::VecOfPrimitive { inline, .. } => {
+                let stmts = make_pointee_size_stmts(name, &field.ty, 0);
                 if inline {
-                    size_stmts.push(quote! {
-                        size = obj.#name.len() * #size_expr;
-                    });
+                    // the inline vec's data comes before everything else's dynamic data
+                    size_stmts.insert(0, stmts);
                 } else {
-                    size_stmts.push(quote! {
-                        size = align_usize_fast_const::<{ max_const_usize(I32_SA, #align_expr) }>(size + I32_SZ);
-                        size += obj.#name.len() * #size_expr;
-                    });
+                    size_stmts.push(stmts);
                 }
             }
-            FieldKind::ChildStruct(child_type) => {
-                let child_ffi = get_ffi_token_from_base(&child_type);
-                size_stmts.push(quote! {
-                    size = align_usize_fast_const::<{ #child_ffi::calculate_alignment() }>(size);
-                    size += #child_ffi::calculate_full_size(&obj.#name);
-                });
-            }
             FieldKind::Primitive { .. } | FieldKind::Enum => {
                 // do nothing, these have no dynamic size
             }
         };
     }
 
-    quote! {
-        let mut size = Self::calculate_base_size();
-        #( #size_stmts )*
-        size
-    }
+    size_stmts
 }
 
-fn make_has_dynamic_size_body(fields: &[&Field]) -> TokenStream2 {
-    // does object use dynamic size?
-    // only used for Vec's full size calculation optimization.
-    let any_dynamic = fields.iter().any(|f| {
+fn has_dynamic_field(fields: &[&Field]) -> bool {
+    fields.iter().any(|f| {
         matches!(
             get_field_ffi_type(f, false),
-            FieldKind::String | FieldKind::Vec(_, _) | FieldKind::ChildStruct(_)
+            FieldKind::String | FieldKind::Vec(_) | FieldKind::ChildStruct
         )
-    });
+    })
+}
+
+fn make_has_dynamic_size_body(fields: &[&Field]) -> TokenStream2 {
+    // does object use dynamic size?
+    // only used for Vec's full size calculation optimization.
+    let any_dynamic = has_dynamic_field(fields);
     quote! { #any_dynamic }
 }
 
-fn make_has_var_length_field_body(_fields: &[&Field]) -> TokenStream2 {
+fn make_has_var_length_field_body() -> TokenStream2 {
     // does field need four byte length prefix?
     // this is currently unused but may be used in the future.
     let any_length = quote! { false };
     quote! { #any_length }
 }
 
-fn make_serialize_body(fields: &[&Field]) -> TokenStream2 {
+fn make_serialize_body(fields: &[(TokenStream2, &Field)]) -> TokenStream2 {
+    let (fixed_stmts, dynamic_stmts) = make_serialize_stmts(fields);
+    quote! {
+        // move dynamic data pointer to base data pointer after base size
+        let ptrd_start = ptrd;
+        let mut ptrd = ptrd;
+        let mut ptrd_dyn: *mut u8 = ptrd.add(Self::calculate_base_size());
+        #( #dynamic_stmts )*
+        #( #fixed_stmts )*
+
+        // if these drift apart, we either wasted memory or wrote past the end of the buffer
+        debug_assert_eq!(
+            ptrd_dyn as usize - ptrd_start as usize,
+            Self::calculate_full_size(obj),
+            "serialized size doesn't match calculate_full_size"
+        );
+
+        // next static data starts at end of current dynamic data
+        ptrd_dyn
+    }
+}
+
+// returns (fixed_stmts, dynamic_stmts)
+fn make_serialize_stmts(fields: &[(TokenStream2, &Field)]) -> (Vec<TokenStream2>, Vec<TokenStream2>) {
     // serialize data given our allocated buffer is large enough
     // static data is put in the fixed_stmts vec, while
     // dynamic data is put in the dynamic_stmts vec.
@@ -367,88 +713,23 @@ fn make_serialize_body(fields: &[&Field]) -> TokenStream2 {
     let mut fixed_stmts: Vec<TokenStream2> = Vec::new();
     let mut dynamic_stmts: Vec<TokenStream2> = Vec::new();
 
-    let mut str_ptr_idx = 0;
-    let mut vec_ptr_idx = 0;
-    let mut chd_ptr_idx = 0;
-
-    for (i, field) in fields.iter().enumerate() {
-        let name = field.ident.as_ref().unwrap();
-        let ffi_type = get_field_ffi_type(field, i == 0);
+    for (i, (name, field)) in fields.iter().enumerate() {
+        let ffi_type = get_field_ffi_type(field, i == fields.len() - 1);
 
         match ffi_type {
-            FieldKind::String => {
-                let data_ptr = format_ident!("str_ptr_{}", str_ptr_idx.to_string());
-                str_ptr_idx += 1;
-
-                dynamic_stmts.push(quote! {
-                    // align to start position, write length, and remember start position
-                    ptrd_dyn = align_ptr_fast::<I32_SA>(ptrd_dyn.add(I32_SZ));
-                    *(ptrd_dyn.sub(I32_SZ) as *mut u32) = obj.#name.len() as u32;
-                    let #data_ptr = ptrd_dyn;
-
-                    // copy string data and add null term
-                    std::ptr::copy_nonoverlapping(obj.#name.as_ptr(), ptrd_dyn, obj.#name.len());
-                    *ptrd_dyn.add(obj.#name.len()) = 0u8;
-
-                    // seek forward string length + null term
-                    ptrd_dyn = ptrd_dyn.add(obj.#name.len() + 1);
-                });
-
-                fixed_stmts.push(quote! {
-                    ptrd = align_ptr_fast::<WORD_SA>(ptrd);
-                    *(ptrd as *mut *mut u8) = #data_ptr;
-                    ptrd = ptrd.add(WORD_SZ);
-                });
-            }
-            FieldKind::Vec(ele_type, inline) => {
+            FieldKind::String | FieldKind::Vec(_) | FieldKind::VecOfPrimitive { .. } | FieldKind::ChildStruct => {
+                let inline = matches!(
+                    ffi_type,
+                    FieldKind::Vec(true) | FieldKind::VecOfPrimitive { inline: true, .. }
+                );
                 if inline {
-                    // todo: DELETE INLINE, WE HANDLE IT NOW!!!!
-                    let ele_ffi = get_ffi_token_from_base(&ele_type);
-                    let ptr_array = format_ident!("vec_array_{}", vec_ptr_idx.to_string());
-                    vec_ptr_idx += 1;
-
-                    dynamic_stmts.push(quote! {
-                        // write length
-                        *(ptrd_dyn.sub(I32_SZ) as *mut u32) = obj.#name.len() as u32;
-
-                        // also make a copy pointer for writing the pointer array and move to end
-                        let mut #ptr_array = ptrd_dyn;
-                        ptrd_dyn = ptrd_dyn.add(obj.#name.len() * WORD_SZ);
-
-                        // align and write each element
-                        ptrd_dyn = align_ptr_fast::<{ #ele_ffi::calculate_alignment() }>(ptrd_dyn);
-                        for elem in &obj.#name {
-                            ptrd_dyn = align_ptr_fast::<{ #ele_ffi::calculate_alignment() }>(ptrd_dyn);
-                            *(#ptr_array as *mut *mut u8) = ptrd_dyn;
-                            #ptr_array = #ptr_array.add(WORD_SZ);
-                            ptrd_dyn = #ele_ffi::serialize(ptrd_dyn, elem);
-                        }
-                    });
+                    // inline vecs have no pointer in the base to fill in later, and
+                    // their data has to be the first thing after the base size
+                    let data_ptr = format_ident!("_dyn_ptr_{}", i);
+                    dynamic_stmts.insert(0, make_pointee_write_stmts(name, &field.ty, &data_ptr, 0));
                 } else {
-                    let ele_ffi = get_ffi_token_from_base(&ele_type);
-                    let data_ptr = format_ident!("vec_ptr_{}", vec_ptr_idx.to_string());
-                    let ptr_array = format_ident!("vec_array_{}", vec_ptr_idx.to_string());
-                    vec_ptr_idx += 1;
-
-                    dynamic_stmts.push(quote! {
-                        // align to start position, write length, and remember start position
-                        ptrd_dyn = align_ptr_fast::<WORD_SA>(ptrd_dyn.add(I32_SZ));
-                        *(ptrd_dyn.sub(I32_SZ) as *mut u32) = obj.#name.len() as u32;
-                        let #data_ptr = ptrd_dyn;
-
-                        // also make a copy pointer for writing the pointer array and move to end
-                        let mut #ptr_array = ptrd_dyn;
-                        ptrd_dyn = ptrd_dyn.add(obj.#name.len() * WORD_SZ);
-
-                        // align and write each element
-                        for elem in &obj.#name {
-                            ptrd_dyn = align_ptr_fast::<{ #ele_ffi::calculate_alignment() }>(ptrd_dyn);
-                            *(#ptr_array as *mut *mut u8) = ptrd_dyn;
-                            #ptr_array = #ptr_array.add(WORD_SZ);
-                            ptrd_dyn = #ele_ffi::serialize(ptrd_dyn, elem);
-                        }
-                    });
-
+                    let data_ptr = format_ident!("dyn_ptr_{}", i);
+                    dynamic_stmts.push(make_pointee_write_stmts(name, &field.ty, &data_ptr, 0));
                     fixed_stmts.push(quote! {
                         ptrd = align_ptr_fast::<WORD_SA>(ptrd);
                         *(ptrd as *mut *mut u8) = #data_ptr;
@@ -456,91 +737,318 @@ fn make_serialize_body(fields: &[&Field]) -> TokenStream2 {
                     });
                 }
             }
-            FieldKind::VecOfPrimitive {
+            FieldKind::Primitive {
                 align_expr,
                 size_expr,
                 type_expr,
-                inline,
+                needs_cast,
             } => {
-                if inline {
-                    dynamic_stmts.push(quote! {
-                        // write length
-                        *(ptrd_dyn.sub(I32_SZ) as *mut u32) = obj.#name.len() as u32;
+                let value = make_primitive_value(quote!(#name), &type_expr, needs_cast);
+                fixed_stmts.push(quote! {
+                    ptrd = align_ptr_fast::<#align_expr>(ptrd);
+                    *(ptrd as *mut #type_expr) = #value;
+                    ptrd = ptrd.add(#size_expr);
+                });
+            }
+            FieldKind::Enum => {
+                fixed_stmts.push(quote! {
+                    ptrd = align_ptr_fast::<I32_SA>(ptrd);
+                    *(ptrd as *mut u32) = { use num::ToPrimitive as _; #name.to_u32().unwrap() };
+                    ptrd = ptrd.add(I32_SZ);
+                });
+            }
+        }
+    }
 
-                        for elem in &obj.#name {
-                            *(ptrd_dyn as *mut #type_expr) = *elem;
-                            ptrd_dyn = ptrd_dyn.add(#size_expr);
-                        }
-                    });
-                } else {
-                    let data_ptr = format_ident!("pvec_ptr_{}", vec_ptr_idx.to_string());
-                    vec_ptr_idx += 1;
-
-                    dynamic_stmts.push(quote! {
-                        // align to start position, write length, and remember start position
-                        ptrd_dyn = align_ptr_fast::<{ max_const_usize(I32_SA, #align_expr) }>(ptrd_dyn.add(I32_SZ));
-                        *(ptrd_dyn.sub(I32_SZ) as *mut u32) = obj.#name.len() as u32;
-                        let #data_ptr = ptrd_dyn;
-
-                        for elem in &obj.#name {
-                            *(ptrd_dyn as *mut #type_expr) = *elem;
-                            ptrd_dyn = ptrd_dyn.add(#size_expr);
-                        }
-                    });
+    (fixed_stmts, dynamic_stmts)
+}
 
-                    fixed_stmts.push(quote! {
-                        ptrd = align_ptr_fast::<WORD_SA>(ptrd);
-                        *(ptrd as *mut *mut u8) = #data_ptr;
-                        ptrd = ptrd.add(WORD_SZ);
-                    });
-                }
+// /////
+
+// enums are written as an i32 tag (the variant's index) followed by a payload
+// area laid out like a c union: aligned to the strictest variant field and as
+// big as the largest variant. each variant's fields go in the payload the same
+// way a struct's would, so strings and vecs are pointers to data after the
+// base size.
+struct EnumVariant<'a> {
+    fields: Vec<&'a Field>,
+    // binds every field, like `Name::Int(f0)`
+    pattern: TokenStream2,
+    // same but ignores the fields, like `Name::Int(..)`
+    wild_pattern: TokenStream2,
+    // how each field is read once the pattern matched
+    accessors: Vec<TokenStream2>,
+}
+
+fn get_enum_variants<'a>(name: &Ident, de: &'a DataEnum) -> Vec<EnumVariant<'a>> {
+    let mut variants: Vec<EnumVariant> = Vec::new();
+    for variant in &de.variants {
+        let var_name = &variant.ident;
+        let fields = variant.fields.iter().collect::<Vec<_>>();
+        let bindings = (0..fields.len()).map(|i| format_ident!("f{}", i)).collect::<Vec<_>>();
+
+        let (pattern, wild_pattern) = match &variant.fields {
+            Fields::Named(_) => {
+                let field_names = fields.iter().map(|f| f.ident.as_ref().unwrap());
+                (
+                    quote! { #name::#var_name { #( #field_names: #bindings ),* } },
+                    quote! { #name::#var_name { .. } },
+                )
             }
-            FieldKind::ChildStruct(child_type) => {
-                let child_ffi = get_ffi_token_from_base(&child_type);
-                let data_ptr = format_ident!("chd_ptr_{}", chd_ptr_idx.to_string());
-                chd_ptr_idx += 1;
+            Fields::Unnamed(_) => (
+                quote! { #name::#var_name( #( #bindings ),* ) },
+                quote! { #name::#var_name(..) },
+            ),
+            Fields::Unit => (quote! { #name::#var_name }, quote! { #name::#var_name }),
+        };
 
-                dynamic_stmts.push(quote! {
-                    ptrd_dyn = align_ptr_fast::<{ #child_ffi::calculate_alignment() }>(ptrd_dyn);
-                    let #data_ptr = ptrd_dyn;
-                    ptrd_dyn = #child_ffi::serialize(ptrd_dyn, &obj.#name);
-                });
+        variants.push(EnumVariant {
+            fields,
+            pattern,
+            wild_pattern,
+            accessors: bindings.iter().map(|b| quote! { (*#b) }).collect(),
+        });
+    }
+    variants
+}
 
-                fixed_stmts.push(quote! {
-                    ptrd = align_ptr_fast::<WORD_SA>(ptrd);
-                    *(ptrd as *mut *mut u8) = #data_ptr;
-                    ptrd = ptrd.add(WORD_SZ);
-                });
+fn make_enum_extra_fns(variants: &[EnumVariant]) -> TokenStream2 {
+    let align_exprs = variants
+        .iter()
+        .flat_map(|v| make_align_exprs(&v.fields))
+        .collect::<Vec<_>>();
+
+    quote! {
+        pub const fn calculate_payload_alignment() -> usize {
+            let mut align = 1usize;
+            #( align = max_const_usize(align, #align_exprs); )*
+            align
+        }
+    }
+}
+
+fn make_enum_calc_align_body(ffi_name: &Ident) -> TokenStream2 {
+    quote! {
+        max_const_usize(I32_SA, #ffi_name::calculate_payload_alignment())
+    }
+}
+
+fn make_enum_calc_base_size_body(ffi_name: &Ident, variants: &[EnumVariant]) -> TokenStream2 {
+    let variant_size_blocks = variants.iter().map(|v| {
+        let size_stmts = make_base_size_stmts(&v.fields);
+        quote! {
+            {
+                let mut size = 0usize;
+                #( #size_stmts )*
+                payload_size = max_const_usize(payload_size, size);
+            }
+        }
+    });
+
+    quote! {
+        let mut payload_size = 0usize;
+        #( #variant_size_blocks )*
+
+        // tag, then the payload padded like a union would be
+        let size = align_usize_fast_const::<{ #ffi_name::calculate_payload_alignment() }>(I32_SZ);
+        size + align_usize_fast_const::<{ #ffi_name::calculate_payload_alignment() }>(payload_size)
+    }
+}
+
+fn make_enum_calc_full_size_body(variants: &[EnumVariant]) -> TokenStream2 {
+    let arms = variants.iter().map(|v| {
+        let accessed_fields = v
+            .accessors
+            .iter()
+            .cloned()
+            .zip(v.fields.iter().copied())
+            .collect::<Vec<_>>();
+        let size_stmts = make_full_size_stmts(&accessed_fields);
+        if size_stmts.is_empty() {
+            let wild_pattern = &v.wild_pattern;
+            quote! { #wild_pattern => {} }
+        } else {
+            let pattern = &v.pattern;
+            quote! { #pattern => { #( #size_stmts )* } }
+        }
+    });
+
+    quote! {
+        let mut size = Self::calculate_base_size();
+        match obj {
+            #( #arms )*
+        }
+        size
+    }
+}
+
+fn make_enum_has_dynamic_size_body(variants: &[EnumVariant]) -> TokenStream2 {
+    let any_dynamic = variants.iter().any(|v| has_dynamic_field(&v.fields));
+    quote! { #any_dynamic }
+}
+
+fn make_enum_serialize_body(ffi_name: &Ident, variants: &[EnumVariant]) -> TokenStream2 {
+    let arms = variants.iter().enumerate().map(|(tag, v)| {
+        let tag = tag as i32;
+        let accessed_fields = v
+            .accessors
+            .iter()
+            .cloned()
+            .zip(v.fields.iter().copied())
+            .collect::<Vec<_>>();
+        let (fixed_stmts, dynamic_stmts) = make_serialize_stmts(&accessed_fields);
+        let pattern = if v.fields.is_empty() {
+            &v.wild_pattern
+        } else {
+            &v.pattern
+        };
+        quote! {
+            #pattern => {
+                *(ptrd_tag as *mut i32) = #tag;
+                #( #dynamic_stmts )*
+                #( #fixed_stmts )*
             }
+        }
+    });
+
+    quote! {
+        let ptrd_start = ptrd;
+        let mut ptrd_dyn: *mut u8 = ptrd.add(Self::calculate_base_size());
+
+        // clear the payload so smaller variants don't leave garbage in the rest of it
+        let ptrd_tag = align_ptr_fast::<I32_SA>(ptrd);
+        let ptrd_payload = align_ptr_fast::<{ #ffi_name::calculate_payload_alignment() }>(ptrd_tag.add(I32_SZ));
+        std::ptr::write_bytes(ptrd_payload, 0, ptrd_dyn as usize - ptrd_payload as usize);
+
+        let mut ptrd = ptrd_payload;
+        match obj {
+            #( #arms )*
+        }
+
+        // if these drift apart, we either wasted memory or wrote past the end of the buffer
+        debug_assert_eq!(
+            ptrd_dyn as usize - ptrd_start as usize,
+            Self::calculate_full_size(obj),
+            "serialized size doesn't match calculate_full_size"
+        );
+
+        // next static data starts at end of current dynamic data
+        ptrd_dyn
+    }
+}
+
+// /////
+
+// the reverse of make_serialize_stmts. reads each field from ptrd (walking the
+// fixed part the same way serialize does) into f0, f1, ... and follows
+// pointers for anything dynamic. child types need deserialize too.
+fn make_deserialize_stmts(fields: &[&Field]) -> (Vec<TokenStream2>, Vec<Ident>) {
+    let mut stmts: Vec<TokenStream2> = Vec::new();
+    let mut bindings: Vec<Ident> = Vec::new();
+
+    for (i, field) in fields.iter().enumerate() {
+        let binding = format_ident!("f{}", i);
+        let ffi_type = get_field_ffi_type(field, i == fields.len() - 1);
+
+        stmts.push(match ffi_type {
             FieldKind::Primitive {
                 align_expr,
                 size_expr,
                 type_expr,
+                ..
             } => {
-                fixed_stmts.push(quote! {
+                let value = make_primitive_read(quote! { *(ptrd as *const #type_expr) }, &field.ty);
+                quote! {
                     ptrd = align_ptr_fast::<#align_expr>(ptrd);
-                    *(ptrd as *mut #type_expr) = obj.#name;
+                    let #binding = #value;
                     ptrd = ptrd.add(#size_expr);
-                });
+                }
             }
             FieldKind::Enum => {
-                fixed_stmts.push(quote! {
+                let ty = &field.ty;
+                quote! {
                     ptrd = align_ptr_fast::<I32_SA>(ptrd);
-                    *(ptrd as *mut u32) = { use num::ToPrimitive as _; obj.#name.to_u32().unwrap() };
+                    let #binding = <#ty as num::FromPrimitive>::from_u32(*(ptrd as *const u32)).unwrap();
                     ptrd = ptrd.add(I32_SZ);
-                });
+                }
             }
-        }
+            FieldKind::String | FieldKind::Vec(_) | FieldKind::VecOfPrimitive { .. } | FieldKind::ChildStruct => {
+                let value = make_pointee_read_expr(&quote! { data_ptr }, &field.ty, 0);
+                let find_data = match ffi_type {
+                    FieldKind::Vec(true) => quote! {
+                        let data_ptr = align_ptr_fast::<WORD_SA>(ptrd_start.add(Self::calculate_base_size() + I32_SZ));
+                    },
+                    FieldKind::VecOfPrimitive {
+                        align_expr,
+                        inline: true,
+                        ..
+                    } => quote! {
+                        let data_ptr = align_ptr_fast::<{ max_const_usize(I32_SA, #align_expr) }>(
+                            ptrd_start.add(Self::calculate_base_size() + I32_SZ)
+                        );
+                    },
+                    _ => quote! {
+                        ptrd = align_ptr_fast::<WORD_SA>(ptrd);
+                        let data_ptr = *(ptrd as *const *mut u8);
+                        ptrd = ptrd.add(WORD_SZ);
+                    },
+                };
+                quote! {
+                    let #binding = {
+                        #find_data
+                        #value
+                    };
+                }
+            }
+        });
+        bindings.push(binding);
+    }
+
+    (stmts, bindings)
+}
+
+// undoes make_primitive_value
+fn make_primitive_read(read: TokenStream2, ty: &Type) -> TokenStream2 {
+    if path_ident_eq(ty, "bool") {
+        quote! { (#read != 0) }
+    } else if path_ident_eq(ty, "char") {
+        quote! { char::from_u32(#read).unwrap() }
+    } else {
+        read
     }
+}
 
+fn make_deserialize_body(name: &Ident, fields: &[&Field]) -> TokenStream2 {
+    let (stmts, bindings) = make_deserialize_stmts(fields);
+    let field_names = fields.iter().map(|f| f.ident.as_ref().unwrap());
     quote! {
-        // move dynamic data pointer to base data pointer after base size
-        let mut ptrd = ptrd;
-        let mut ptrd_dyn: *mut u8 = ptrd.add(Self::calculate_base_size());
-        #( #dynamic_stmts )*
-        #( #fixed_stmts )*
+        let ptrd_start = ptr as *mut u8;
+        let mut ptrd = ptrd_start;
+        #( #stmts )*
+        #name { #( #field_names: #bindings ),* }
+    }
+}
 
-        // next static data starts at end of current dynamic data
-        ptrd_dyn
+fn make_enum_deserialize_body(ffi_name: &Ident, variants: &[EnumVariant]) -> TokenStream2 {
+    let arms = variants.iter().enumerate().map(|(tag, v)| {
+        let tag = tag as i32;
+        let (stmts, _) = make_deserialize_stmts(&v.fields);
+        // the binding pattern doubles as the constructor, the names line up
+        let pattern = &v.pattern;
+        quote! {
+            #tag => {
+                #( #stmts )*
+                #pattern
+            }
+        }
+    });
+
+    quote! {
+        let ptrd_tag = align_ptr_fast::<I32_SA>(ptr as *mut u8);
+        let mut ptrd = align_ptr_fast::<{ #ffi_name::calculate_payload_alignment() }>(ptrd_tag.add(I32_SZ));
+        match *(ptrd_tag as *const i32) {
+            #( #arms )*
+            tag => panic!("invalid tag {}", tag),
+        }
     }
 }